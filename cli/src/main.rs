@@ -0,0 +1,623 @@
+mod plot;
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+use ozeecubed_core::audio::calibration::rms_dbfs;
+use ozeecubed_core::audio::{input_configs, input_device_names, AudioCapture, InputSelection};
+use ozeecubed_core::display::{format_time, DisplayTheme};
+use ozeecubed_core::oscilloscope::trigger::TriggerEdge;
+use ozeecubed_core::oscilloscope::{
+    Measurements, SpectrumAnalyzer, SpectrumAverage, SpectrumMetadata, SpectrumOverlap,
+    SpectrumScale, TriggerSettings, WaveformData,
+};
+use ozeecubed_core::parallel;
+use ozeecubed_core::recording::Recording;
+use ozeecubed_core::source::network::{SampleEncoding, DEFAULT_NETWORK_PORT};
+use ozeecubed_core::source::{NetworkSender, SignalSource, SourceStatus};
+
+const USAGE: &str = "\
+Usage:
+  ozeecubed-cli measure <input> [--csv] [--time-div SECONDS] [--trigger VOLTS] [--falling]
+  ozeecubed-cli spectrum <input> [-o OUTPUT.csv|OUTPUT.png] [--scale SCALE] [--theme THEME]
+  ozeecubed-cli spectrogram <input> -o OUTPUT.png|OUTPUT.csv [--scale SCALE] [--theme THEME]
+                            [--overlap PERCENT]
+  ozeecubed-cli send <host[:port]> [--tcp] [--i16] [--device NAME] [--rate HZ]
+                     [--channels N] [--buffer FRAMES]
+  ozeecubed-cli inputs [--device NAME]
+
+Inputs are WAV files, or raw little-endian f32 (.f32, .raw) at 48 kHz.
+  measure      Measurements of the whole recording, or with --csv of each
+               screen at --time-div (1 ms by default), triggered with --trigger
+  spectrum     Average spectrum, as CSV (to stdout without -o) or a PNG plot
+  spectrogram  Spectrum over time, as a PNG or one CSV row per transform
+  send         Stream the audio input to a scope listening on another machine
+               (port 7355 by default), over UDP unless --tcp, as f32 samples
+               unless --i16 halves them; a small --buffer cuts the delay
+  inputs       The audio inputs and the channels, rates, sample formats and
+               buffer sizes each supports
+SCALE is linear (the default), constant-q or mel; THEME is a display theme
+such as green, amber or print. --overlap is how much each transform's window
+overlaps the last: 0, 50, 75 (the default) or 87.5 percent. Long recordings
+may overlap less, to fit the image.";
+
+/// How long `send` waits between reads of the input
+const SEND_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Most spectrogram columns; longer recordings hop further between
+/// transforms
+const MAX_COLUMNS: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Command {
+    Measure,
+    Spectrum,
+    Spectrogram,
+    Send,
+    Inputs,
+}
+
+#[derive(Debug, Clone)]
+struct Options {
+    command: Command,
+    input: PathBuf,
+    output: Option<PathBuf>,
+    /// Per-screen CSV rather than a summary, for `measure`
+    csv: bool,
+    time_per_division: f32,
+    trigger: TriggerSettings,
+    scale: SpectrumScale,
+    /// Of the spectrogram's transforms, at most
+    overlap: SpectrumOverlap,
+    theme: DisplayTheme,
+    /// Where `send` streams to, as `host:port`
+    destination: String,
+    /// TCP rather than UDP, for `send`
+    tcp: bool,
+    encoding: SampleEncoding,
+    selection: InputSelection,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match parse(&args).and_then(|options| run(&options)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse(args: &[String]) -> Result<Options, String> {
+    let mut args = args.iter();
+    let command = match args.next().map(String::as_str) {
+        Some("measure") => Command::Measure,
+        Some("spectrum") => Command::Spectrum,
+        Some("spectrogram") => Command::Spectrogram,
+        Some("send") => Command::Send,
+        Some("inputs") => Command::Inputs,
+        Some("-h" | "--help") | None => return Err(USAGE.to_string()),
+        Some(other) => return Err(format!("Unknown command: {other}\n\n{USAGE}")),
+    };
+
+    let mut input = None;
+    let mut options = Options {
+        command,
+        input: PathBuf::new(),
+        output: None,
+        csv: false,
+        time_per_division: 0.001,
+        // Free-running unless a level is given
+        trigger: TriggerSettings {
+            enabled: false,
+            ..TriggerSettings::default()
+        },
+        scale: SpectrumScale::default(),
+        overlap: SpectrumOverlap::default(),
+        theme: DisplayTheme::default(),
+        destination: String::new(),
+        tcp: false,
+        encoding: SampleEncoding::F32,
+        selection: InputSelection::default(),
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .map(String::as_str)
+                .ok_or_else(|| format!("{arg} needs a value"))
+        };
+        match arg.as_str() {
+            "-o" | "--output" => options.output = Some(PathBuf::from(value()?)),
+            "--csv" => options.csv = true,
+            "--time-div" => {
+                options.time_per_division = value()?
+                    .parse()
+                    .ok()
+                    .filter(|&seconds: &f32| seconds > 0.0)
+                    .ok_or_else(|| format!("{arg} needs a time in seconds"))?;
+            }
+            "--trigger" => {
+                let level = value()?
+                    .parse()
+                    .map_err(|_| format!("{arg} needs a level in volts"))?;
+                options.trigger.enabled = true;
+                options.trigger.set_level(level);
+            }
+            "--falling" => options.trigger.edge = TriggerEdge::Falling,
+            "--scale" => {
+                let label = value()?;
+                options.scale = SpectrumScale::from_label(label)
+                    .ok_or_else(|| format!("Unknown spectrum scale: {label}"))?;
+            }
+            "--overlap" => {
+                let label = value()?;
+                options.overlap = SpectrumOverlap::from_label(label)
+                    .ok_or_else(|| format!("Unsupported overlap: {label}"))?;
+            }
+            "--theme" => {
+                let label = value()?;
+                options.theme = DisplayTheme::from_label(label)
+                    .ok_or_else(|| format!("Unknown theme: {label}"))?;
+            }
+            "--tcp" => options.tcp = true,
+            "--i16" => options.encoding = SampleEncoding::I16,
+            "--device" => options.selection.device = Some(value()?.to_string()),
+            "--rate" => {
+                options.selection.sample_rate = Some(
+                    value()?
+                        .parse()
+                        .ok()
+                        .filter(|&rate| rate > 0)
+                        .ok_or_else(|| format!("{arg} needs a rate in Hz"))?,
+                );
+            }
+            "--channels" => {
+                options.selection.channels = Some(
+                    value()?
+                        .parse()
+                        .ok()
+                        .filter(|&channels| channels > 0)
+                        .ok_or_else(|| format!("{arg} needs a channel count"))?,
+                );
+            }
+            "--buffer" => {
+                options.selection.buffer_size = Some(
+                    value()?
+                        .parse()
+                        .ok()
+                        .filter(|&frames| frames > 0)
+                        .ok_or_else(|| format!("{arg} needs a size in frames"))?,
+                );
+            }
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if arg.starts_with('-') => return Err(format!("Unknown option: {arg}\n\n{USAGE}")),
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => return Err(format!("Unexpected argument: {arg}\n\n{USAGE}")),
+        }
+    }
+
+    if command == Command::Inputs {
+        return Ok(options);
+    }
+    if command == Command::Send {
+        let host = input.ok_or_else(|| format!("No host to send to\n\n{USAGE}"))?;
+        let host = host.to_string_lossy();
+        options.destination = if host.contains(':') {
+            host.into_owned()
+        } else {
+            format!("{host}:{DEFAULT_NETWORK_PORT}")
+        };
+        return Ok(options);
+    }
+    options.input = input.ok_or_else(|| format!("No input file given\n\n{USAGE}"))?;
+    if command == Command::Spectrogram && options.output.is_none() {
+        return Err("spectrogram needs an output file: -o OUTPUT.png".to_string());
+    }
+    Ok(options)
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    match options.command {
+        Command::Send => return send(options),
+        Command::Inputs => return list_inputs(options),
+        _ => {}
+    }
+    let recording = Recording::load(&options.input)?;
+    if recording.samples.is_empty() {
+        return Err(format!("{} has no samples", options.input.display()));
+    }
+    match options.command {
+        Command::Measure if options.csv => emit(options, &screen_measurements(&recording, options)),
+        Command::Measure => {
+            print!("{}", summary(&recording));
+            Ok(())
+        }
+        Command::Spectrum => {
+            let (magnitudes, metadata) =
+                average_spectrum(&recording, options.scale, options.overlap);
+            match options.output {
+                Some(ref path) if is_png(path) => plot::write_spectrum(
+                    path,
+                    &magnitudes,
+                    options.scale,
+                    recording.sample_rate,
+                    &options.theme.palette(),
+                ),
+                _ => emit(options, &metadata.to_csv(&magnitudes)),
+            }
+        }
+        Command::Spectrogram => {
+            let (hop, frames) = spectrogram(&recording, options.scale, options.overlap);
+            match options.output {
+                Some(ref path) if is_png(path) => plot::write_spectrogram(
+                    path,
+                    &frames,
+                    hop,
+                    options.scale,
+                    recording.sample_rate,
+                    &options.theme.palette(),
+                ),
+                _ => emit(
+                    options,
+                    &spectrogram_csv(&frames, hop, options.scale, recording.sample_rate),
+                ),
+            }
+        }
+        Command::Send | Command::Inputs => unreachable!("handled without a recording"),
+    }
+}
+
+/// Each input, or only `--device`, with the configurations it supports
+fn list_inputs(options: &Options) -> Result<(), String> {
+    let devices = match options.selection.device {
+        Some(ref device) => vec![device.clone()],
+        None => input_device_names(),
+    };
+    if devices.is_empty() {
+        return Err("No audio inputs found".to_string());
+    }
+    for device in devices {
+        println!("{device}");
+        match input_configs(Some(&device)) {
+            Ok(configs) => {
+                for config in configs {
+                    println!("  {config}");
+                }
+            }
+            Err(e) => println!("  {e}"),
+        }
+    }
+    Ok(())
+}
+
+/// Stream the input to `options.destination` until interrupted
+fn send(options: &Options) -> Result<(), String> {
+    let mut capture = AudioCapture::open(&options.selection).map_err(|e| e.to_string())?;
+    // The receiver shows no more than a stereo pair
+    let channels = capture.channels().min(2);
+    let rate = capture.sample_rate();
+    let open = if options.tcp {
+        NetworkSender::tcp
+    } else {
+        NetworkSender::udp
+    };
+    let mut sender = open(
+        options.destination.as_str(),
+        rate,
+        channels,
+        options.encoding,
+    )
+    .map_err(|e| e.to_string())?;
+    eprintln!(
+        "Sending {} at {rate} Hz, {channels} channel(s), to {}",
+        capture.name(),
+        options.destination
+    );
+
+    let mut interleaved = Vec::new();
+    while capture.status().is_running() {
+        let mono = capture.read_samples(usize::MAX);
+        let stereo = capture.read_stereo(usize::MAX);
+        let samples = if channels == 2 {
+            interleaved.clear();
+            interleaved.extend(stereo.iter().flat_map(|&(left, right)| [left, right]));
+            &interleaved
+        } else {
+            &mono
+        };
+        sender.send(samples).map_err(|e| e.to_string())?;
+        thread::sleep(SEND_INTERVAL);
+    }
+    match capture.status() {
+        SourceStatus::Failed(e) => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+fn is_png(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+}
+
+/// Write `text` to the output file, or stdout without one
+fn emit(options: &Options, text: &str) -> Result<(), String> {
+    match options.output {
+        Some(ref path) => {
+            fs::write(path, text).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+        }
+        None => {
+            print!("{text}");
+            Ok(())
+        }
+    }
+}
+
+fn format_value(value: Option<f32>, precision: usize) -> String {
+    value.map_or_else(|| "--".to_string(), |v| format!("{v:.precision$}"))
+}
+
+/// Measurements of the whole recording
+fn summary(recording: &Recording) -> String {
+    let mut waveform = WaveformData::new(recording.sample_rate);
+    waveform.copy_samples(&recording.samples);
+    let measurements = Measurements::of(&waveform);
+    let seconds = recording.samples.len() as f32 / recording.sample_rate as f32;
+
+    let mut text = String::new();
+    let _ = writeln!(text, "Duration: {}", format_time(seconds));
+    let _ = writeln!(text, "Sample rate: {} Hz", recording.sample_rate);
+    let _ = writeln!(
+        text,
+        "Frequency: {} Hz",
+        format_value(measurements.frequency, 2)
+    );
+    let _ = writeln!(
+        text,
+        "Vpp: {} V",
+        format_value(measurements.peak_to_peak, 4)
+    );
+    let _ = writeln!(text, "Vrms: {} V", format_value(measurements.rms, 4));
+    let _ = writeln!(text, "Duty: {} %", format_value(measurements.duty_cycle, 1));
+    let _ = writeln!(
+        text,
+        "Level: {} dBFS",
+        format_value(measurements.rms.map(rms_dbfs), 1)
+    );
+    text
+}
+
+/// One CSV row per screen at the chosen time/div: each screen is triggered
+/// as the scope would, with the scope's trigger search history after it, and
+/// measured over what would be on screen. Screens are measured across
+/// threads.
+fn screen_measurements(recording: &Recording, options: &Options) -> String {
+    let setup = || {
+        let mut waveform = WaveformData::new(recording.sample_rate);
+        waveform.time_per_division = options.time_per_division;
+        (waveform, WaveformData::new(recording.sample_rate))
+    };
+    let screen = setup().0.calculate_samples_per_screen().max(1);
+    let starts: Vec<usize> = (0..recording.samples.len()).step_by(screen).collect();
+    let rows = parallel::map_init(
+        &starts,
+        recording.samples.len(),
+        setup,
+        |(waveform, shown), &start| {
+            let end = (start + waveform.buffer_size()).min(recording.samples.len());
+            waveform.copy_samples(&recording.samples[start..end]);
+            let range = waveform.display_range(&options.trigger);
+            shown.copy_samples(&waveform.samples[range.clone()]);
+            let time = (start + range.start) as f32 / recording.sample_rate as f32;
+            (time, Measurements::of(shown))
+        },
+    );
+
+    let mut text = "time_s,frequency_hz,vpp_v,vrms_v,duty_percent,level_dbfs\n".to_string();
+    for (time, measurements) in rows {
+        let _ = writeln!(
+            text,
+            "{time:.6},{},{},{},{},{}",
+            csv_value(measurements.frequency, 2),
+            csv_value(measurements.peak_to_peak, 5),
+            csv_value(measurements.rms, 5),
+            csv_value(measurements.duty_cycle, 2),
+            csv_value(measurements.rms.map(rms_dbfs), 2),
+        );
+    }
+    text
+}
+
+/// Empty where there's nothing to measure
+fn csv_value(value: Option<f32>, precision: usize) -> String {
+    value.map_or_else(String::new, |v| format!("{v:.precision$}"))
+}
+
+/// The spectrum of each hop across the recording, as an analyzer streaming
+/// through it would see it on the live display. Returns the hop with the
+/// frames.
+fn spectrogram(
+    recording: &Recording,
+    scale: SpectrumScale,
+    overlap: SpectrumOverlap,
+) -> (usize, Vec<Vec<f32>>) {
+    let mut analyzer = SpectrumAnalyzer::new();
+    analyzer.set_scale(scale, recording.sample_rate);
+    let hop = (recording.samples.len() / MAX_COLUMNS).max(overlap.hop_size(analyzer.fft_size()));
+    analyzer.set_hop_size(hop);
+    (hop, analyzer.spectrogram(&recording.samples))
+}
+
+/// Power average of every frame of the spectrogram, in dB, with how it
+/// was taken
+fn average_spectrum(
+    recording: &Recording,
+    scale: SpectrumScale,
+    overlap: SpectrumOverlap,
+) -> (Vec<f32>, SpectrumMetadata) {
+    let mut analyzer = SpectrumAnalyzer::new();
+    analyzer.set_scale(scale, recording.sample_rate);
+    let metadata = SpectrumMetadata {
+        fft_size: Some(analyzer.fft_size()),
+        ..SpectrumMetadata::new(scale, recording.sample_rate)
+    };
+    let (_, frames) = spectrogram(recording, scale, overlap);
+    if frames.is_empty() {
+        // Shorter than one hop: the one transform of all of it
+        analyzer.update(&recording.samples);
+        return (analyzer.magnitudes().to_vec(), metadata);
+    }
+    let mut average = SpectrumAverage::default();
+    for frame in &frames {
+        average.add(frame);
+    }
+    let metadata = SpectrumMetadata {
+        averages: average.count(),
+        ..metadata
+    };
+    (average.magnitudes(), metadata)
+}
+
+/// A header of bin frequencies, then the time and levels of each frame
+fn spectrogram_csv(
+    frames: &[Vec<f32>],
+    hop: usize,
+    scale: SpectrumScale,
+    sample_rate: u32,
+) -> String {
+    let bins = frames.first().map_or(0, Vec::len);
+    let mut text = "time_s".to_string();
+    for bin in 0..bins {
+        let _ = write!(text, ",{:.2}", scale.bin_frequency(bin, bins, sample_rate));
+    }
+    text.push('\n');
+    for (index, frame) in frames.iter().enumerate() {
+        let time = ((index + 1) * hop) as f32 / sample_rate as f32;
+        let _ = write!(text, "{time:.6}");
+        for db in frame {
+            let _ = write!(text, ",{db:.2}");
+        }
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn sine(frequency: f32, seconds: f32) -> Recording {
+        let samples = (0..(seconds * 48000.0) as usize)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * frequency * i as f32 / 48000.0).sin())
+            .collect();
+        Recording::new(samples, 48000)
+    }
+
+    #[test]
+    fn test_parse() {
+        let options = parse(&args(
+            "spectrum in.wav -o spec.png --scale constant-q --theme amber",
+        ))
+        .unwrap();
+        assert_eq!(options.command, Command::Spectrum);
+        assert_eq!(options.input, PathBuf::from("in.wav"));
+        assert_eq!(options.output, Some(PathBuf::from("spec.png")));
+        assert_eq!(options.scale, SpectrumScale::ConstantQ);
+        assert_eq!(options.theme, DisplayTheme::Amber);
+
+        let options = parse(&args("measure in.wav --csv --trigger 0.2 --falling")).unwrap();
+        assert!(options.csv && options.trigger.enabled);
+        assert_eq!(options.trigger.edge, TriggerEdge::Falling);
+
+        assert!(parse(&args("measure")).is_err());
+        assert!(parse(&args("spectrogram in.wav")).is_err());
+        assert!(parse(&args("spectrum in.wav --scale bark")).is_err());
+        let options = parse(&args("spectrogram in.wav -o out.png --overlap 50")).unwrap();
+        assert_eq!(options.overlap, SpectrumOverlap::Half);
+        assert!(parse(&args("spectrogram in.wav -o out.png --overlap 60")).is_err());
+        assert!(parse(&args("measure in.wav --time-div")).is_err());
+
+        let options = parse(&args("send pi.local --tcp --i16 --rate 44100")).unwrap();
+        assert_eq!(options.command, Command::Send);
+        assert_eq!(options.destination, "pi.local:7355");
+        assert!(options.tcp);
+        assert_eq!(options.encoding, SampleEncoding::I16);
+        assert_eq!(options.selection.sample_rate, Some(44100));
+        let options = parse(&args("send 10.0.0.2:9000 --channels 1 --buffer 128")).unwrap();
+        assert_eq!(options.destination, "10.0.0.2:9000");
+        assert_eq!(options.selection.channels, Some(1));
+        assert_eq!(options.selection.buffer_size, Some(128));
+        assert!(parse(&args("send pi.local --buffer 0")).is_err());
+
+        let options = parse(&args("inputs --device USB")).unwrap();
+        assert_eq!(options.command, Command::Inputs);
+        assert_eq!(options.selection.device.as_deref(), Some("USB"));
+        assert!(parse(&args("send")).is_err());
+    }
+
+    #[test]
+    fn test_screen_measurements() {
+        let recording = sine(1000.0, 0.1);
+        let options = parse(&args("measure in.wav --csv --time-div 0.001")).unwrap();
+        let csv = screen_measurements(&recording, &options);
+        let rows: Vec<&str> = csv.lines().skip(1).collect();
+        // 10 ms screens of a 100 ms recording
+        assert_eq!(rows.len(), 10);
+        let fields: Vec<&str> = rows[0].split(',').collect();
+        let frequency: f32 = fields[1].parse().unwrap();
+        assert!((frequency - 1000.0).abs() < 5.0);
+        let vpp: f32 = fields[2].parse().unwrap();
+        assert!((vpp - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_average_spectrum_peaks_at_the_tone() {
+        let recording = sine(3000.0, 0.5);
+        let (magnitudes, metadata) = average_spectrum(
+            &recording,
+            SpectrumScale::Linear,
+            SpectrumOverlap::default(),
+        );
+        let peak = magnitudes
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(bin, _)| bin)
+            .unwrap();
+        let frequency = SpectrumScale::Linear.bin_frequency(peak, magnitudes.len(), 48000);
+        assert!((frequency - 3000.0).abs() < 20.0);
+
+        assert!(metadata.averages > 1);
+        let csv = metadata.to_csv(&magnitudes);
+        let rows = csv.lines().filter(|line| !line.starts_with('#'));
+        assert_eq!(rows.count(), magnitudes.len() + 1);
+    }
+
+    #[test]
+    fn test_spectrogram_columns() {
+        let recording = sine(440.0, 1.0);
+        let (hop, frames) = spectrogram(&recording, SpectrumScale::Mel, SpectrumOverlap::default());
+        assert_eq!(hop, 1024);
+        assert_eq!(frames.len(), 48000 / 1024);
+        assert!(frames.iter().all(|frame| frame.len() == 64));
+
+        let (hop, frames) = spectrogram(
+            &recording,
+            SpectrumScale::Mel,
+            SpectrumOverlap::SevenEighths,
+        );
+        assert_eq!(hop, 512);
+        assert_eq!(frames.len(), 48000 / 512);
+
+        let csv = spectrogram_csv(&frames, hop, SpectrumScale::Mel, 48000);
+        assert_eq!(csv.lines().count(), frames.len() + 1);
+    }
+}
@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use ozeecubed_core::display::plot::{level, spectrogram_image, PlotImage};
+use ozeecubed_core::display::Palette;
+use ozeecubed_core::oscilloscope::spectrum::{DB_MAX, DB_MIN};
+use ozeecubed_core::oscilloscope::SpectrumScale;
+
+/// Size of the spectrum plot, in pixels
+pub const PLOT_WIDTH: u32 = 1024;
+pub const PLOT_HEIGHT: u32 = 512;
+
+/// dB between horizontal grid lines
+const DB_STEP: f32 = 10.0;
+
+/// The spectrum as a trace over a grid: frequency lines where the display
+/// labels them and a line every 10 dB
+pub fn write_spectrum(
+    path: &Path,
+    magnitudes: &[f32],
+    scale: SpectrumScale,
+    sample_rate: u32,
+    palette: &Palette,
+) -> Result<(), String> {
+    let mut image = PlotImage::new(PLOT_WIDTH, PLOT_HEIGHT, palette.background);
+    let right = PLOT_WIDTH as i32 - 1;
+    let bottom = PLOT_HEIGHT as i32 - 1;
+
+    for (fraction, _) in scale.frequency_labels(sample_rate, magnitudes.len()) {
+        let x = (fraction * right as f32).round() as i32;
+        image.line((x, 0), (x, bottom), palette.grid);
+    }
+    let lines = ((DB_MAX - DB_MIN) / DB_STEP) as i32;
+    for i in 0..=lines {
+        let y = (i as f32 / lines as f32 * bottom as f32).round() as i32;
+        image.line((0, y), (right, y), palette.grid);
+    }
+
+    let bins = magnitudes.len().max(2) - 1;
+    let point = |(bin, &db): (usize, &f32)| {
+        let x = bin as f32 / bins as f32 * right as f32;
+        let y = (1.0 - level(db)) * bottom as f32;
+        (x.round() as i32, y.round() as i32)
+    };
+    let points: Vec<(i32, i32)> = magnitudes.iter().enumerate().map(point).collect();
+    for pair in points.windows(2) {
+        image.line(pair[0], pair[1], palette.trace);
+    }
+
+    image.save(path)
+}
+
+/// One column per spectrum of every hop, oldest on the left, lowest
+/// frequency at the bottom, with the axes labeled like the exported
+/// spectrogram history
+pub fn write_spectrogram(
+    path: &Path,
+    frames: &[Vec<f32>],
+    hop: usize,
+    scale: SpectrumScale,
+    sample_rate: u32,
+    palette: &Palette,
+) -> Result<(), String> {
+    spectrogram_image(
+        frames.iter().map(Vec::as_slice),
+        frames.first().map_or(0, Vec::len),
+        hop.max(1) as f32 / sample_rate.max(1) as f32,
+        scale,
+        sample_rate,
+        palette,
+    )
+    .save(path)
+}
@@ -0,0 +1,699 @@
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::audio::trigger_events::{TriggerEvent, TriggerSubscribers, TriggerTracker};
+use crate::audio::{AudioCapture, AudioError, InputSelection, MonitorFeed};
+use crate::dsp::{BandwidthFilter, BandwidthLimit, FilterBlock, FilterChain};
+use crate::oscilloscope::{
+    AutoSweep, AutoTrigger, MathExpression, Measurements, SpectrogramHistory, SpectrumAnalyzer,
+    SpectrumOverlap, SpectrumScale, TriggerSettings, WaveformData,
+};
+use crate::parallel;
+use crate::plugin::{self, PluginMeasurement};
+use crate::source::{DataLoss, SignalSource, SourceStatus};
+
+/// How long the thread sleeps between drains of the capture ring, well
+/// inside the ring's ~200 ms of headroom
+const POLL_INTERVAL: Duration = Duration::from_millis(4);
+
+/// Frames that can wait for the UI before the thread stops queueing more
+const FRAME_QUEUE: usize = 16;
+
+/// Most raw audio held back while the UI isn't collecting frames
+const MAX_UNSENT_SECONDS: u32 = 10;
+
+/// Woken by the acquisition thread after each frame it sends, for a
+/// consumer waiting on frames asynchronously
+type FrameWaker = Arc<Mutex<Option<Waker>>>;
+
+/// What the acquisition thread needs to know about the display
+#[derive(Debug, Clone)]
+pub struct AcquisitionSettings {
+    pub time_per_division: f32,
+    pub horizontal_divisions: usize,
+    pub trigger: TriggerSettings,
+    /// Run the spectrum analyzer
+    pub spectrum: bool,
+    pub spectrum_scale: SpectrumScale,
+    /// Of each spectrum's window with the last one's
+    pub spectrum_overlap: SpectrumOverlap,
+    /// Pass the stereo input on for the vectorscope
+    pub stereo: bool,
+    /// Bandwidth of channels A and B. A is the mono input the main trace
+    /// shows and the left input, B the right input.
+    pub bandwidth: [BandwidthLimit; 2],
+    /// Applied to the mono input, after the bandwidth limit, before
+    /// anything else sees it
+    pub filters: Vec<FilterBlock>,
+    /// Math channel to evaluate over each window, `A` and `B` being the
+    /// left and right inputs
+    pub math: Option<MathExpression>,
+}
+
+impl AcquisitionSettings {
+    pub fn new(waveform: &WaveformData, trigger: &TriggerSettings, spectrum: bool) -> Self {
+        Self {
+            time_per_division: waveform.time_per_division,
+            horizontal_divisions: waveform.horizontal_divisions,
+            trigger: trigger.clone(),
+            spectrum,
+            spectrum_scale: SpectrumScale::default(),
+            spectrum_overlap: SpectrumOverlap::default(),
+            stereo: false,
+            bandwidth: [BandwidthLimit::Full; 2],
+            filters: Vec::new(),
+            math: None,
+        }
+    }
+}
+
+/// A display-ready capture from the acquisition thread
+#[derive(Debug, Clone, Default)]
+pub struct AcquiredFrame {
+    /// Every sample read from the input since the previous frame, for
+    /// loggers and decoders that need the unbroken stream
+    pub new_samples: Vec<f32>,
+    /// The screen and the trigger search history before it, as held in
+    /// `WaveformData::samples`. Shared, as a held sweep goes out again.
+    pub window: Arc<[f32]>,
+    /// Part of `window` on screen after triggering
+    pub display_range: Range<usize>,
+    /// See `WaveformData::trigger_position`
+    pub trigger_position: Option<f32>,
+    pub measurements: Measurements,
+    /// Of every registered measurement plugin
+    pub plugin_measurements: Vec<PluginMeasurement>,
+    /// dB magnitudes, when the analyzer completed a hop since the last
+    /// frame
+    pub spectrum: Option<Vec<f32>>,
+    /// (left, right) pairs read since the previous frame, when
+    /// `AcquisitionSettings::stereo` is set
+    pub stereo: Vec<(f32, f32)>,
+    /// `AcquisitionSettings::math` evaluated sample for sample alongside
+    /// `window`
+    pub math: Option<Arc<[f32]>>,
+    /// When the newest input in the frame was read
+    pub read_at: Option<Instant>,
+    /// Input that had built up in the capture ring by then, for latency
+    /// estimates
+    pub buffered: Duration,
+    /// Input lost so far, by the source or as raw samples this thread
+    /// couldn't hold while the UI wasn't collecting frames
+    pub data_loss: DataLoss,
+}
+
+/// Audio capture, triggering and analysis on a background thread.
+///
+/// The thread drains the input as it arrives and publishes an
+/// `AcquiredFrame` for each batch, so heavy analysis never holds up
+/// rendering and a slow UI tick doesn't overflow the capture ring. Each
+/// new trigger edge also goes to whoever subscribed with `on_trigger` or
+/// `trigger_events`. With the trigger on, a capture without an edge repeats
+/// the last sweep until `AUTO_TIMEOUT` passes, then shows the newest
+/// samples once. Stops when dropped.
+pub struct Acquisition {
+    settings: Arc<Mutex<AcquisitionSettings>>,
+    frames: Receiver<AcquiredFrame>,
+    frame_waker: FrameWaker,
+    triggers: TriggerSubscribers,
+    status: Arc<Mutex<SourceStatus>>,
+    monitor: Arc<Mutex<Option<MonitorFeed>>>,
+    /// Every spectrum analyzed, kept here rather than by the UI so none
+    /// are lost when frames are merged
+    spectrogram: Arc<Mutex<SpectrogramHistory>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+    device_name: String,
+    sample_rate: u32,
+}
+
+impl Acquisition {
+    /// Open the default input on a new thread, analyzing it as
+    /// `sample_rate`. Fails, with no thread left running, if the input
+    /// can't be opened.
+    pub fn start(sample_rate: u32, settings: AcquisitionSettings) -> Result<Self, AudioError> {
+        Self::spawn(
+            || AudioCapture::open(&InputSelection::default()),
+            Some(sample_rate),
+            settings,
+        )
+    }
+
+    /// Open the selected input on a new thread, analyzing it at the rate
+    /// it runs at
+    pub fn start_on(
+        input: InputSelection,
+        settings: AcquisitionSettings,
+    ) -> Result<Self, AudioError> {
+        Self::spawn(move || AudioCapture::open(&input), None, settings)
+    }
+
+    /// Read the source `open` makes on a new thread, analyzing it at the
+    /// rate it runs at. It's made on that thread as some sources, such as
+    /// capture streams, have to live on the thread that reads them. The
+    /// thread stops once the source is no longer running.
+    pub fn start_source<S, F>(open: F, settings: AcquisitionSettings) -> Result<Self, AudioError>
+    where
+        S: SignalSource + 'static,
+        F: FnOnce() -> Result<S, AudioError> + Send + 'static,
+    {
+        Self::spawn(open, None, settings)
+    }
+
+    fn spawn<S, F>(
+        open: F,
+        sample_rate: Option<u32>,
+        settings: AcquisitionSettings,
+    ) -> Result<Self, AudioError>
+    where
+        S: SignalSource + 'static,
+        F: FnOnce() -> Result<S, AudioError> + Send + 'static,
+    {
+        let settings = Arc::new(Mutex::new(settings));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (frame_sender, frames) = mpsc::sync_channel(FRAME_QUEUE);
+        let (ready_sender, ready) = mpsc::channel();
+        let triggers = TriggerSubscribers::default();
+        let frame_waker = FrameWaker::default();
+        let status = Arc::new(Mutex::new(SourceStatus::Running));
+        let monitor = Arc::new(Mutex::new(None));
+        let spectrogram = Arc::new(Mutex::new(SpectrogramHistory::new()));
+
+        let thread_settings = Arc::clone(&settings);
+        let thread_stop = Arc::clone(&stop);
+        let thread_triggers = triggers.clone();
+        let thread_waker = Arc::clone(&frame_waker);
+        let thread_status = Arc::clone(&status);
+        let thread_monitor = Arc::clone(&monitor);
+        let thread_spectrogram = Arc::clone(&spectrogram);
+        let handle = thread::Builder::new()
+            .name("acquisition".into())
+            .spawn(move || {
+                let mut source = match open() {
+                    Ok(source) => {
+                        let _ = ready_sender.send(Ok((
+                            source.name().to_string(),
+                            sample_rate.unwrap_or(source.sample_rate()),
+                        )));
+                        source
+                    }
+                    Err(e) => {
+                        let _ = ready_sender.send(Err(e));
+                        return;
+                    }
+                };
+                let sample_rate = sample_rate.unwrap_or(source.sample_rate());
+                run(
+                    &mut source,
+                    sample_rate,
+                    &thread_settings,
+                    &thread_stop,
+                    &frame_sender,
+                    &thread_triggers,
+                    &thread_waker,
+                    &thread_status,
+                    &thread_monitor,
+                    &thread_spectrogram,
+                );
+                // So a waiting consumer sees the frames end
+                wake(&thread_waker);
+            })
+            .map_err(|e| AudioError::Backend(format!("Failed to start acquisition thread: {e}")))?;
+
+        match ready.recv() {
+            Ok(Ok((device_name, sample_rate))) => Ok(Self {
+                settings,
+                frames,
+                frame_waker,
+                triggers,
+                status,
+                monitor,
+                spectrogram,
+                stop,
+                handle: Some(handle),
+                device_name,
+                sample_rate,
+            }),
+            Ok(Err(e)) => {
+                let _ = handle.join();
+                Err(e)
+            }
+            Err(_) => {
+                let _ = handle.join();
+                Err(AudioError::Backend(
+                    "Acquisition thread exited during startup".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Name of the source being read
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    /// Rate the capture is analyzed at
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// `Running` until the source ends or fails, after which no more
+    /// frames come
+    pub fn status(&self) -> SourceStatus {
+        self.status
+            .lock()
+            .map_or(SourceStatus::Running, |status| status.clone())
+    }
+
+    /// Play the filtered input through `feed` from now on, or stop with
+    /// `None`
+    pub fn set_monitor(&self, feed: Option<MonitorFeed>) {
+        if let Ok(mut monitor) = self.monitor.lock() {
+            *monitor = feed;
+        }
+    }
+
+    /// Every spectrum analyzed so far, up to `MAX_SPECTROGRAM_COLUMNS`, for
+    /// exporting
+    pub fn spectrogram(&self) -> SpectrogramHistory {
+        self.spectrogram
+            .lock()
+            .map_or_else(|_| SpectrogramHistory::new(), |history| history.clone())
+    }
+
+    /// Used from the next frame on
+    pub fn set_settings(&self, settings: AcquisitionSettings) {
+        if let Ok(mut current) = self.settings.lock() {
+            *current = settings;
+        }
+    }
+
+    /// Call `callback` for each capture the scope triggers on. It runs on
+    /// the acquisition thread and holds up the capture while it does, so
+    /// slow work such as saving the record belongs on `trigger_events`.
+    pub fn on_trigger(&self, callback: impl FnMut(&TriggerEvent) + Send + 'static) {
+        self.triggers.add_callback(callback);
+    }
+
+    /// A channel getting each capture the scope triggers on from now on.
+    /// Dropping the receiver unsubscribes.
+    pub fn trigger_events(&self) -> Receiver<TriggerEvent> {
+        self.triggers.add_channel()
+    }
+
+    /// The newest frame since the last call, if any. Frames it replaces
+    /// still contribute their `new_samples`, so no audio is skipped.
+    pub fn latest(&self) -> Option<AcquiredFrame> {
+        newest(self.frames.try_iter())
+    }
+
+    /// The next frame, or `Pending` with `waker` registered to be woken
+    /// when there is one. `None` once the thread has stopped.
+    pub(crate) fn poll_frame(&self, waker: &Waker) -> Poll<Option<AcquiredFrame>> {
+        // Registered before looking, so a frame sent in between still wakes
+        if let Ok(mut current) = self.frame_waker.lock() {
+            *current = Some(waker.clone());
+        }
+        match self.frames.try_recv() {
+            Ok(frame) => Poll::Ready(Some(frame)),
+            Err(TryRecvError::Empty) => Poll::Pending,
+            Err(TryRecvError::Disconnected) => Poll::Ready(None),
+        }
+    }
+
+    /// A handle on frames that something else produces, with no thread or
+    /// input behind it
+    #[cfg(test)]
+    pub(crate) fn from_frames(frames: Receiver<AcquiredFrame>, sample_rate: u32) -> Self {
+        Self {
+            settings: Arc::new(Mutex::new(AcquisitionSettings::new(
+                &WaveformData::new(sample_rate),
+                &TriggerSettings::default(),
+                false,
+            ))),
+            frames,
+            frame_waker: FrameWaker::default(),
+            triggers: TriggerSubscribers::default(),
+            status: Arc::new(Mutex::new(SourceStatus::Running)),
+            monitor: Arc::new(Mutex::new(None)),
+            spectrogram: Arc::new(Mutex::new(SpectrogramHistory::new())),
+            stop: Arc::new(AtomicBool::new(false)),
+            handle: None,
+            device_name: String::new(),
+            sample_rate,
+        }
+    }
+}
+
+fn wake(waker: &FrameWaker) {
+    if let Some(waker) = waker.lock().ok().and_then(|mut waker| waker.take()) {
+        waker.wake();
+    }
+}
+
+/// The last of `frames`, carrying the `new_samples` and `stereo` pairs of
+/// all of them and the newest spectrum among them
+fn newest(frames: impl Iterator<Item = AcquiredFrame>) -> Option<AcquiredFrame> {
+    frames.reduce(|older, mut frame| {
+        let mut new_samples = older.new_samples;
+        new_samples.append(&mut frame.new_samples);
+        frame.new_samples = new_samples;
+        let mut stereo = older.stereo;
+        stereo.append(&mut frame.stereo);
+        frame.stereo = stereo;
+        frame.spectrum = frame.spectrum.or(older.spectrum);
+        frame
+    })
+}
+
+impl Drop for Acquisition {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// What a frame shows of the capture. Its samples are shared, so holding
+/// a sweep and sending it again copies none of them.
+#[derive(Clone)]
+struct Sweep {
+    window: Arc<[f32]>,
+    display_range: Range<usize>,
+    trigger_position: Option<f32>,
+    math: Option<Arc<[f32]>>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run(
+    source: &mut dyn SignalSource,
+    sample_rate: u32,
+    settings: &Mutex<AcquisitionSettings>,
+    stop: &AtomicBool,
+    frames: &SyncSender<AcquiredFrame>,
+    triggers: &TriggerSubscribers,
+    frame_waker: &FrameWaker,
+    status: &Mutex<SourceStatus>,
+    monitor: &Mutex<Option<MonitorFeed>>,
+    spectrogram: &Mutex<SpectrogramHistory>,
+) {
+    let mut waveform = WaveformData::new(sample_rate);
+    let mut analyzer = SpectrumAnalyzer::new();
+    let mut filters = FilterChain::new(sample_rate);
+    // Mono, left and right inputs
+    let mut bandwidth = [(); 3].map(|_| BandwidthFilter::new(sample_rate));
+    // Raw samples from frames the UI had no room for
+    let mut unsent: Vec<f32> = Vec::new();
+    let max_unsent = (MAX_UNSENT_SECONDS * sample_rate) as usize;
+    let mut discarded: u64 = 0;
+    // Input channels for the math trace, and how many samples each ring
+    // has given so far: the two rings are read one after the other, so
+    // the counts line the channels up with the window
+    let mut channels: Vec<(f32, f32)> = Vec::new();
+    let mut mono_read: u64 = 0;
+    let mut stereo_read: u64 = 0;
+    let mut tracker = TriggerTracker::default();
+    let mut auto = AutoTrigger::new(sample_rate);
+    // The last sweep sent, shown again while waiting for an edge
+    let mut shown: Option<Sweep> = None;
+
+    while !stop.load(Ordering::Relaxed) {
+        let mut new_samples = source.read_samples(usize::MAX);
+        let read_at = Instant::now();
+        let arrived = new_samples.len();
+        let buffered = Duration::from_secs_f64(new_samples.len() as f64 / sample_rate as f64);
+        // Drained even when unused so it stays in step with the mono ring
+        let mut stereo = source.read_stereo(usize::MAX);
+        if stereo.is_empty() && source.channels() < 2 {
+            stereo = new_samples.iter().map(|&sample| (sample, sample)).collect();
+        }
+        mono_read += new_samples.len() as u64;
+        stereo_read += stereo.len() as u64;
+        if new_samples.is_empty() {
+            let current = source.status();
+            if !current.is_running() {
+                if let Ok(mut status) = status.lock() {
+                    *status = current;
+                }
+                return;
+            }
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+
+        let settings = match settings.lock() {
+            Ok(settings) => settings.clone(),
+            Err(_) => return,
+        };
+        waveform.time_per_division = settings.time_per_division;
+        waveform.horizontal_divisions = settings.horizontal_divisions;
+
+        // Display, analysis, logging and decoding all see the filtered input
+        let [mono_limit, left_limit, right_limit] = &mut bandwidth;
+        mono_limit.set_limit(settings.bandwidth[0]);
+        left_limit.set_limit(settings.bandwidth[0]);
+        right_limit.set_limit(settings.bandwidth[1]);
+        mono_limit.process(&mut new_samples);
+        for (left, right) in &mut stereo {
+            *left = left_limit.process_sample(*left);
+            *right = right_limit.process_sample(*right);
+        }
+        filters.set_blocks(&settings.filters);
+        filters.process(&mut new_samples);
+        if let Some(feed) = monitor.lock().ok().as_deref().and_then(Option::as_ref) {
+            feed.push(&new_samples);
+        }
+        waveform.append_samples(&new_samples);
+        analyzer.set_scale(settings.spectrum_scale, sample_rate);
+        if analyzer.overlap() != Some(settings.spectrum_overlap) {
+            analyzer.set_overlap(settings.spectrum_overlap);
+        }
+
+        let math = match &settings.math {
+            Some(expression) => {
+                channels.extend_from_slice(&stereo);
+                // A little past the window, in case the stereo ring runs ahead
+                let keep = 2 * waveform.samples.len();
+                if channels.len() > keep {
+                    channels.drain(0..channels.len() - keep);
+                }
+                let (a, b) =
+                    aligned_channels(&channels, stereo_read, waveform.samples.len(), mono_read);
+                Some(expression.evaluate(&a, &b, sample_rate))
+            }
+            None => {
+                channels.clear();
+                None
+            }
+        };
+
+        // Only the input that's new since the last hop is analyzed
+        let spectrum = (settings.spectrum && analyzer.push(&new_samples))
+            .then(|| analyzer.magnitudes().to_vec());
+        if settings.spectrum {
+            if let Ok(mut history) = spectrogram.lock() {
+                history.push(
+                    new_samples.len(),
+                    spectrum.as_deref(),
+                    settings.spectrum_scale,
+                    sample_rate,
+                );
+            }
+        }
+
+        if unsent.is_empty() {
+            unsent = new_samples;
+        } else {
+            unsent.extend_from_slice(&new_samples);
+        }
+        if unsent.len() > max_unsent {
+            let to_remove = unsent.len() - max_unsent;
+            unsent.drain(0..to_remove);
+            discarded += to_remove as u64;
+        }
+
+        let display_range = waveform.display_range(&settings.trigger);
+        let trigger_index = waveform.trigger_index(&settings.trigger);
+        if let Some(index) = trigger_index {
+            // The window ends at the newest sample read
+            let sample = mono_read - (waveform.samples.len() - index) as u64;
+            if tracker.is_new(sample) && !triggers.is_empty() {
+                triggers.publish(&TriggerEvent {
+                    timestamp: SystemTime::now(),
+                    sample,
+                    sample_rate,
+                    record: waveform.samples[display_range.clone()].into(),
+                });
+            }
+        }
+
+        let sweep = if settings.trigger.enabled {
+            auto.sweep(settings.trigger.mode, trigger_index.is_some(), arrived)
+        } else {
+            AutoSweep::FreeRun
+        };
+        let sweep = match (sweep, shown.as_ref()) {
+            (AutoSweep::Waiting, Some(held)) => held.clone(),
+            _ => {
+                let current = Sweep {
+                    window: waveform.samples.as_slice().into(),
+                    display_range,
+                    trigger_position: waveform.trigger_position(&settings.trigger),
+                    math: math.map(Into::into),
+                };
+                // Only a triggering scope goes back to it
+                shown = settings.trigger.enabled.then(|| current.clone());
+                current
+            }
+        };
+
+        // Plugins measure alongside the built-in measurements of a long window
+        let (measurements, plugin_measurements) = parallel::join(
+            waveform.samples.len(),
+            || Measurements::of(&waveform),
+            || plugin::measure(&waveform.samples, sample_rate),
+        );
+        let frame = AcquiredFrame {
+            new_samples: std::mem::take(&mut unsent),
+            window: sweep.window,
+            display_range: sweep.display_range,
+            trigger_position: sweep.trigger_position,
+            measurements,
+            plugin_measurements,
+            spectrum,
+            // Display only: pairs the UI has no room for are dropped
+            stereo: if settings.stereo { stereo } else { Vec::new() },
+            math: sweep.math,
+            read_at: Some(read_at),
+            buffered,
+            data_loss: DataLoss {
+                dropped_samples: source.data_loss().dropped_samples + discarded,
+                ..source.data_loss()
+            },
+        };
+        match frames.try_send(frame) {
+            Ok(()) => wake(frame_waker),
+            // Carry the raw samples into the next frame instead
+            Err(TrySendError::Full(frame)) => unsent = frame.new_samples,
+            Err(TrySendError::Disconnected(_)) => return,
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Left and right for each of the `len` samples up to sample `end` of the
+/// input, from `channels`, which holds pairs up to pair `channels_end`.
+/// Samples the history doesn't cover are zero.
+fn aligned_channels(
+    channels: &[(f32, f32)],
+    channels_end: u64,
+    len: usize,
+    end: u64,
+) -> (Vec<f32>, Vec<f32>) {
+    let channels_start = channels_end as i64 - channels.len() as i64;
+    let start = end as i64 - len as i64;
+    (0..len as i64)
+        .map(|i| {
+            usize::try_from(start + i - channels_start)
+                .ok()
+                .and_then(|index| channels.get(index))
+                .copied()
+                .unwrap_or((0.0, 0.0))
+        })
+        .unzip()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_newest_frame_keeps_all_samples() {
+        let frames = (0..3).map(|i| AcquiredFrame {
+            new_samples: vec![i as f32; 2],
+            window: vec![i as f32].into(),
+            spectrum: (i == 1).then(|| vec![-6.0]),
+            stereo: vec![(i as f32, -(i as f32))],
+            ..Default::default()
+        });
+
+        let frame = newest(frames).unwrap();
+        assert_eq!(*frame.window, [2.0]);
+        assert_eq!(frame.new_samples, vec![0.0, 0.0, 1.0, 1.0, 2.0, 2.0]);
+        assert_eq!(frame.spectrum, Some(vec![-6.0]));
+        assert_eq!(frame.stereo, vec![(0.0, -0.0), (1.0, -1.0), (2.0, -2.0)]);
+        assert!(newest(std::iter::empty()).is_none());
+    }
+
+    #[test]
+    fn test_aligned_channels() {
+        let channels = [(1.0, -1.0), (2.0, -2.0), (3.0, -3.0)];
+        // Pairs 7..10 against a window of samples 6..9
+        assert_eq!(
+            aligned_channels(&channels, 10, 3, 9),
+            (vec![0.0, 1.0, 2.0], vec![0.0, -1.0, -2.0])
+        );
+        assert_eq!(
+            aligned_channels(&channels, 10, 2, 10),
+            (vec![2.0, 3.0], vec![-2.0, -3.0])
+        );
+    }
+
+    #[test]
+    fn test_runs_any_source_until_it_ends() {
+        use crate::recording::Recording;
+        use crate::source::FileSource;
+
+        // A tenth of a second at 10 kHz
+        let recording = Recording::new(vec![0.25; 1000], 10_000);
+        let waveform = WaveformData::new(10_000);
+        let settings = AcquisitionSettings::new(&waveform, &TriggerSettings::default(), false);
+        let acquisition = Acquisition::start_source(
+            move || Ok(FileSource::new(recording, "steady.wav")),
+            settings,
+        )
+        .unwrap();
+        assert_eq!(acquisition.device_name(), "steady.wav");
+        assert_eq!(acquisition.sample_rate(), 10_000);
+
+        // Every sample comes through before the frames end
+        let mut read = 0;
+        while let Ok(frame) = acquisition.frames.recv_timeout(Duration::from_secs(5)) {
+            assert!(frame.new_samples.iter().all(|&sample| sample == 0.25));
+            // A mono source gives its samples as equal pairs
+            assert!(frame.stereo.iter().all(|&(left, right)| left == right));
+            read += frame.new_samples.len();
+        }
+        assert_eq!(read, 1000);
+        assert_eq!(acquisition.status(), SourceStatus::Ended);
+    }
+
+    #[test]
+    fn test_spectrogram_keeps_every_spectrum() {
+        use crate::recording::Recording;
+        use crate::source::FileSource;
+
+        let recording = Recording::new(vec![0.25; 10_000], 10_000);
+        let waveform = WaveformData::new(10_000);
+        let settings = AcquisitionSettings::new(&waveform, &TriggerSettings::default(), true);
+        let acquisition = Acquisition::start_source(
+            move || Ok(FileSource::new(recording, "steady.wav")),
+            settings,
+        )
+        .unwrap();
+
+        let mut spectra = 0;
+        while let Ok(frame) = acquisition.frames.recv_timeout(Duration::from_secs(5)) {
+            spectra += frame.spectrum.is_some() as usize;
+        }
+        assert!(spectra > 0);
+        assert_eq!(acquisition.spectrogram().len(), spectra);
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::config_directory;
+
+/// Level of the usual acoustic calibrator, 1 kHz at 94 dB SPL (1 Pa)
+pub const DEFAULT_REFERENCE_SPL: f32 = 94.0;
+
+/// Lowest and highest reference level accepted, in dB SPL
+pub const REFERENCE_SPL_RANGE: (f32, f32) = (40.0, 140.0);
+
+/// Level floor in dBFS, so silence doesn't read as minus infinity
+const FLOOR_DBFS: f32 = -120.0;
+
+/// Calibration file inside `config_directory`
+const FILE_NAME: &str = "calibration.txt";
+
+/// Level of an RMS value in dBFS, a full-scale sine reading 0 dBFS like
+/// the spectrum does
+pub fn rms_dbfs(rms: f32) -> f32 {
+    (20.0 * (rms * std::f32::consts::SQRT_2).max(1e-6).log10()).max(FLOOR_DBFS)
+}
+
+/// Converts dBFS to absolute dB SPL for one input chain: microphone,
+/// preamp gain and converter. Only holds while the gain stays where it
+/// was when calibrated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    /// dB SPL that reads as 0 dBFS
+    pub offset: f32,
+}
+
+impl Calibration {
+    /// Calibration from a reference tone of `reference_spl` dB SPL that
+    /// measured `rms`. `None` when the input is silent.
+    pub fn from_reference(rms: f32, reference_spl: f32) -> Option<Self> {
+        let dbfs = rms_dbfs(rms);
+        (rms.is_finite() && dbfs > FLOOR_DBFS).then_some(Self {
+            offset: reference_spl - dbfs,
+        })
+    }
+
+    pub fn spl(&self, dbfs: f32) -> f32 {
+        dbfs + self.offset
+    }
+}
+
+/// Calibrations of each input device by name, kept in a text file with one
+/// `offset<TAB>device` line per device
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CalibrationStore {
+    devices: BTreeMap<String, Calibration>,
+}
+
+impl CalibrationStore {
+    /// `calibration.txt` in `config_directory`
+    pub fn default_path() -> Option<PathBuf> {
+        config_directory().map(|directory| directory.join(FILE_NAME))
+    }
+
+    /// Read a store saved by `save`. A missing file is an empty store.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read {}: {e}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)
+                .map_err(|e| format!("Failed to create {}: {e}", directory.display()))?;
+        }
+        fs::write(path, self.to_text())
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    pub fn get(&self, device: &str) -> Option<Calibration> {
+        self.devices.get(device).copied()
+    }
+
+    /// Store or, with `None`, forget the calibration of `device`
+    pub fn set(&mut self, device: &str, calibration: Option<Calibration>) {
+        match calibration {
+            Some(calibration) => {
+                self.devices.insert(device.to_string(), calibration);
+            }
+            None => {
+                self.devices.remove(device);
+            }
+        }
+    }
+
+    /// Lines that don't parse are skipped, so a hand-edited file loses only
+    /// its broken entries
+    fn parse(text: &str) -> Self {
+        let devices = text
+            .lines()
+            .filter_map(|line| {
+                let (offset, device) = line.split_once('\t')?;
+                let offset: f32 = offset.trim().parse().ok()?;
+                offset
+                    .is_finite()
+                    .then(|| (device.to_string(), Calibration { offset }))
+            })
+            .collect();
+        Self { devices }
+    }
+
+    fn to_text(&self) -> String {
+        self.devices
+            .iter()
+            .map(|(device, calibration)| format!("{:.2}\t{device}\n", calibration.offset))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_tone_reads_its_level() {
+        // A calibrator reading -20 dBFS: a sine of amplitude 0.1
+        let rms = 0.1 / std::f32::consts::SQRT_2;
+        assert!((rms_dbfs(rms) + 20.0).abs() < 0.01);
+
+        let calibration = Calibration::from_reference(rms, 94.0).unwrap();
+        assert!((calibration.offset - 114.0).abs() < 0.01);
+        assert!((calibration.spl(-20.0) - 94.0).abs() < 0.01);
+        assert!((calibration.spl(-40.0) - 74.0).abs() < 0.01);
+
+        assert!(Calibration::from_reference(0.0, 94.0).is_none());
+        assert!(Calibration::from_reference(f32::NAN, 94.0).is_none());
+    }
+
+    #[test]
+    fn test_store_round_trips_through_text() {
+        let mut store = CalibrationStore::default();
+        store.set("Built-in Microphone", Some(Calibration { offset: 120.5 }));
+        store.set("USB Audio\tCODEC", Some(Calibration { offset: 98.25 }));
+        store.set("Gone", Some(Calibration { offset: 1.0 }));
+        store.set("Gone", None);
+
+        let parsed = CalibrationStore::parse(&store.to_text());
+        assert_eq!(parsed, store);
+        assert_eq!(parsed.get("Built-in Microphone").unwrap().offset, 120.5);
+        assert!(parsed.get("Gone").is_none());
+
+        // Broken lines are dropped, the rest kept
+        let parsed = CalibrationStore::parse("oops\nnan\tA\n100\tB\n");
+        assert!(parsed.get("A").is_none());
+        assert_eq!(parsed.get("B"), Some(Calibration { offset: 100.0 }));
+    }
+
+    #[test]
+    fn test_missing_file_is_an_empty_store() {
+        let path = std::env::temp_dir().join("ozeecubed-no-such-calibration.txt");
+        assert_eq!(
+            CalibrationStore::load(&path),
+            Ok(CalibrationStore::default())
+        );
+    }
+}
@@ -1,56 +1,355 @@
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{Device, Stream, StreamConfig};
+use cpal::{
+    BufferSize, Device, FromSample, Sample, SampleFormat, SizedSample, Stream, StreamConfig,
+    SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
+};
 use ringbuf::{traits::*, HeapRb};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::AudioError;
+use crate::source::{DataLoss, SignalSource, SourceStatus};
 
 const BUFFER_SIZE: usize = 9600; // ~200ms at 48kHz - enough for 3 frames at 60fps with headroom
 
+/// How late a callback's input can be captured, as a share of the previous
+/// callback's length, before the input between them is taken as lost
+const OVERRUN_GAP: f64 = 0.5;
+
+/// Kept by the stream, for `data_loss`
+#[derive(Debug, Default)]
+struct LossCounters {
+    dropped_samples: AtomicU64,
+    overruns: AtomicU64,
+}
+
+/// What the stream callback writes to, shared with the `AudioCapture`
+struct StreamParts {
+    producer: ringbuf::HeapProd<f32>,
+    stereo_producer: ringbuf::HeapProd<(f32, f32)>,
+    channels: u16,
+    error: Arc<Mutex<Option<AudioError>>>,
+    losses: Arc<LossCounters>,
+}
+
+/// Whether input captured `elapsed` after the previous callback's, which
+/// held `frames`, leaves a gap: the device overran while no callback came
+fn missed_input(elapsed: Duration, frames: usize, sample_rate: u32) -> bool {
+    let expected = frames as f64 / sample_rate as f64;
+    elapsed.as_secs_f64() > expected * (1.0 + OVERRUN_GAP)
+}
+
+/// Which input to open and how, `None` taking the system's choice
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputSelection {
+    pub device: Option<String>,
+    pub sample_rate: Option<u32>,
+    /// Channels to capture, such as 1 for a mono microphone
+    pub channels: Option<u16>,
+    /// Frames the device hands over at a time. Smaller buffers cut latency
+    /// but give the system less time to keep up. Kept within what the
+    /// device takes.
+    pub buffer_size: Option<u32>,
+}
+
+/// Names of the inputs that can be opened, for a device picker
+pub fn input_device_names() -> Vec<String> {
+    cpal::default_host()
+        .input_devices()
+        .map(|devices| devices.filter_map(|device| device.name().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// A range of settings an input can capture with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputConfig {
+    pub channels: u16,
+    /// Lowest and highest rate, in Hz
+    pub sample_rates: (u32, u32),
+    /// Such as "i16" or "f32"
+    pub sample_format: String,
+    /// Smallest and largest buffer in frames, when the host says
+    pub buffer_sizes: Option<(u32, u32)>,
+}
+
+impl From<&SupportedStreamConfigRange> for InputConfig {
+    fn from(range: &SupportedStreamConfigRange) -> Self {
+        Self {
+            channels: range.channels(),
+            sample_rates: (range.min_sample_rate().0, range.max_sample_rate().0),
+            sample_format: range.sample_format().to_string(),
+            buffer_sizes: match *range.buffer_size() {
+                SupportedBufferSize::Range { min, max } => Some((min, max)),
+                SupportedBufferSize::Unknown => None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for InputConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (min_rate, max_rate) = self.sample_rates;
+        write!(f, "{} ch, ", self.channels)?;
+        if min_rate == max_rate {
+            write!(f, "{min_rate} Hz")?;
+        } else {
+            write!(f, "{min_rate}-{max_rate} Hz")?;
+        }
+        write!(f, ", {}", self.sample_format)?;
+        if let Some((min, max)) = self.buffer_sizes {
+            write!(f, ", {min}-{max} frames")?;
+        }
+        Ok(())
+    }
+}
+
+/// Every configuration `device` supports, the default input's if `None`
+pub fn input_configs(device: Option<&str>) -> Result<Vec<InputConfig>, AudioError> {
+    let device = find_input(device)?;
+    Ok(device
+        .supported_input_configs()?
+        .map(|range| InputConfig::from(&range))
+        .collect())
+}
+
+/// The input called `name`, or the default input
+fn find_input(name: Option<&str>) -> Result<Device, AudioError> {
+    let host = cpal::default_host();
+    match name {
+        Some(name) => host
+            .input_devices()?
+            .find(|device| device.name().is_ok_and(|n| n == name))
+            .ok_or_else(|| AudioError::DeviceNotFound(name.to_string())),
+        None => host.default_input_device().ok_or(AudioError::NoDevice),
+    }
+}
+
+/// The config among `ranges` to open for `selection`, `default` unless it
+/// asks for something else. A rate or channel count it leaves open is the
+/// default's, where a range allows; f32 is preferred, needing no
+/// converting.
+fn choose_config(
+    ranges: impl IntoIterator<Item = SupportedStreamConfigRange>,
+    default: SupportedStreamConfig,
+    selection: &InputSelection,
+) -> Result<SupportedStreamConfig, AudioError> {
+    if selection.sample_rate.is_none() && selection.channels.is_none() {
+        return Ok(default);
+    }
+    let rate = selection.sample_rate.unwrap_or(default.sample_rate().0);
+    ranges
+        .into_iter()
+        .filter(|range| {
+            (range.min_sample_rate().0..=range.max_sample_rate().0).contains(&rate)
+                && selection
+                    .channels
+                    .is_none_or(|channels| range.channels() == channels)
+        })
+        .max_by_key(|range| {
+            (
+                range.channels() == default.channels(),
+                range.sample_format() == SampleFormat::F32,
+            )
+        })
+        .map(|range| range.with_sample_rate(cpal::SampleRate(rate)))
+        .ok_or_else(|| {
+            AudioError::UnsupportedFormat(match selection.channels {
+                Some(channels) => format!("{channels} channels at {rate} Hz"),
+                None => format!("{rate} Hz"),
+            })
+        })
+}
+
+/// `requested` frames, within what the device takes. Hosts that don't say
+/// what they take keep their own size rather than refuse the stream.
+fn buffer_size(requested: Option<u32>, supported: &SupportedBufferSize) -> BufferSize {
+    match (requested, supported) {
+        (Some(frames), &SupportedBufferSize::Range { min, max }) => {
+            BufferSize::Fixed(frames.clamp(min, max))
+        }
+        _ => BufferSize::Default,
+    }
+}
+
 pub struct AudioCapture {
     _stream: Stream,
     consumer: Arc<Mutex<ringbuf::HeapCons<f32>>>,
+    /// (left, right) pairs alongside the mono mix, for the vectorscope
+    stereo_consumer: Arc<Mutex<ringbuf::HeapCons<(f32, f32)>>>,
+    device_name: String,
+    sample_rate: u32,
+    channels: u16,
+    /// Set by the stream when the device goes away
+    error: Arc<Mutex<Option<AudioError>>>,
+    losses: Arc<LossCounters>,
 }
 
 impl AudioCapture {
-    pub fn new() -> Result<Self, String> {
-        let host = cpal::default_host();
-
-        let device = host
-            .default_input_device()
-            .ok_or_else(|| "No input device available".to_string())?;
+    /// Open the default input at its default rate
+    pub fn new() -> Result<Self, AudioError> {
+        Self::open(&InputSelection::default())
+    }
 
-        let config = device
-            .default_input_config()
-            .map_err(|e| format!("Failed to get default input config: {e}"))?;
+    pub fn open(selection: &InputSelection) -> Result<Self, AudioError> {
+        let device = find_input(selection.device.as_deref())?;
+        let config = choose_config(
+            device.supported_input_configs()?,
+            device.default_input_config()?,
+            selection,
+        )?;
 
         let sample_rate = config.sample_rate().0;
+        let device_name = device.name().unwrap_or_else(|_| "Unknown".to_string());
 
-        println!(
-            "Using audio device: {}",
-            device.name().unwrap_or_else(|_| "Unknown".to_string())
-        );
+        println!("Using audio device: {device_name}");
         println!("Sample rate: {sample_rate} Hz");
         println!("Channels: {}", config.channels());
+        println!("Sample format: {}", config.sample_format());
+        let stream_config = StreamConfig {
+            buffer_size: buffer_size(selection.buffer_size, config.buffer_size()),
+            ..config.config()
+        };
+        if let BufferSize::Fixed(frames) = stream_config.buffer_size {
+            println!("Buffer: {frames} frames");
+        }
 
         let ring_buffer = HeapRb::<f32>::new(BUFFER_SIZE);
         let (producer, consumer) = ring_buffer.split();
-
-        let producer = Arc::new(Mutex::new(producer));
-        let producer_clone = Arc::clone(&producer);
         let consumer = Arc::new(Mutex::new(consumer));
 
+        let (stereo_producer, stereo_consumer) = HeapRb::<(f32, f32)>::new(BUFFER_SIZE).split();
+        let stereo_consumer = Arc::new(Mutex::new(stereo_consumer));
+
         let channels = config.channels();
-        let stream = Self::build_input_stream(&device, &config.into(), producer_clone, channels)?;
-        stream
-            .play()
-            .map_err(|e| format!("Failed to play stream: {e}"))?;
+        let error = Arc::new(Mutex::new(None));
+        let losses = Arc::new(LossCounters::default());
+        let parts = StreamParts {
+            producer,
+            stereo_producer,
+            channels,
+            error: Arc::clone(&error),
+            losses: Arc::clone(&losses),
+        };
+        // Built for the device's own format, as many deliver integers
+        let sample_format = config.sample_format();
+        let config = stream_config;
+        let stream = match sample_format {
+            SampleFormat::F32 => Self::build_input_stream::<f32>(&device, &config, parts),
+            SampleFormat::F64 => Self::build_input_stream::<f64>(&device, &config, parts),
+            SampleFormat::I8 => Self::build_input_stream::<i8>(&device, &config, parts),
+            SampleFormat::I16 => Self::build_input_stream::<i16>(&device, &config, parts),
+            SampleFormat::I32 => Self::build_input_stream::<i32>(&device, &config, parts),
+            SampleFormat::I64 => Self::build_input_stream::<i64>(&device, &config, parts),
+            SampleFormat::U8 => Self::build_input_stream::<u8>(&device, &config, parts),
+            SampleFormat::U16 => Self::build_input_stream::<u16>(&device, &config, parts),
+            SampleFormat::U32 => Self::build_input_stream::<u32>(&device, &config, parts),
+            SampleFormat::U64 => Self::build_input_stream::<u64>(&device, &config, parts),
+            format => Err(AudioError::UnsupportedFormat(format!("{format} samples"))),
+        }?;
+        stream.play()?;
 
         Ok(AudioCapture {
             _stream: stream,
             consumer,
+            stereo_consumer,
+            device_name,
+            sample_rate,
+            channels,
+            error,
+            losses,
         })
     }
 
-    pub fn read_samples(&self, max_samples: usize) -> Vec<f32> {
+    /// Open a stream delivering `T`, converted to f32 as it arrives
+    fn build_input_stream<T>(
+        device: &Device,
+        config: &StreamConfig,
+        parts: StreamParts,
+    ) -> Result<Stream, AudioError>
+    where
+        T: SizedSample,
+        f32: FromSample<T>,
+    {
+        let StreamParts {
+            mut producer,
+            mut stereo_producer,
+            channels,
+            error,
+            losses,
+        } = parts;
+        // Hosts report passing trouble such as overruns here too, and carry
+        // on; only a lost device stops the capture
+        let stream_losses = Arc::clone(&losses);
+        let err_fn = move |err: cpal::StreamError| {
+            eprintln!("Audio stream error: {err}");
+            if let cpal::StreamError::DeviceNotAvailable = err {
+                if let Ok(mut error) = error.lock() {
+                    *error = Some(AudioError::DeviceUnavailable);
+                }
+            } else {
+                stream_losses.overruns.fetch_add(1, Ordering::Relaxed);
+            }
+        };
+
+        let sample_rate = config.sample_rate.0;
+        // Capture time and length of the last callback's input
+        let mut previous: Option<(cpal::StreamInstant, usize)> = None;
+        // Reused by every callback, so converting doesn't allocate
+        let mut converted = Vec::new();
+        let stream = device.build_input_stream(
+            config,
+            move |data: &[T], info: &cpal::InputCallbackInfo| {
+                convert_samples(data, &mut converted);
+                let data = converted.as_slice();
+                let captured = info.timestamp().capture;
+                if let Some((last, frames)) = previous {
+                    if captured
+                        .duration_since(&last)
+                        .is_some_and(|elapsed| missed_input(elapsed, frames, sample_rate))
+                    {
+                        losses.overruns.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+                previous = Some((captured, data.len() / channels as usize));
+
+                let mut dropped = 0;
+                // Mix down to mono by averaging channels
+                for chunk in data.chunks(channels as usize) {
+                    let sample = chunk.iter().sum::<f32>() / chunk.len() as f32;
+                    // The reader fell behind and the ring is full
+                    if producer.try_push(sample).is_err() {
+                        dropped += 1;
+                    }
+
+                    let left = chunk[0];
+                    let right = chunk.get(1).copied().unwrap_or(left);
+                    let _ = stereo_producer.try_push((left, right));
+                }
+                losses.dropped_samples.fetch_add(dropped, Ordering::Relaxed);
+            },
+            err_fn,
+            None,
+        )?;
+
+        Ok(stream)
+    }
+}
+
+/// `data` as f32 in `converted`, replacing what it held. Integers are scaled
+/// so their full range spans -1 to 1, unsigned ones centered on zero.
+fn convert_samples<T>(data: &[T], converted: &mut Vec<f32>)
+where
+    T: Sample,
+    f32: FromSample<T>,
+{
+    converted.clear();
+    converted.extend(data.iter().map(|&sample| sample.to_sample::<f32>()));
+}
+
+impl SignalSource for AudioCapture {
+    fn read_samples(&mut self, max_samples: usize) -> Vec<f32> {
         if let Ok(mut consumer) = self.consumer.lock() {
             let available = consumer.occupied_len();
             let to_read = available.min(max_samples);
@@ -67,31 +366,131 @@ impl AudioCapture {
         }
     }
 
-    fn build_input_stream(
-        device: &Device,
-        config: &StreamConfig,
-        producer: Arc<Mutex<ringbuf::HeapProd<f32>>>,
-        channels: u16,
-    ) -> Result<Stream, String> {
-        let err_fn = |err| eprintln!("Audio stream error: {err}");
-
-        let stream = device
-            .build_input_stream(
-                config,
-                move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    if let Ok(mut prod) = producer.lock() {
-                        // Mix down to mono by averaging channels
-                        for chunk in data.chunks(channels as usize) {
-                            let sample = chunk.iter().sum::<f32>() / chunk.len() as f32;
-                            let _ = prod.try_push(sample);
-                        }
-                    }
-                },
-                err_fn,
-                None,
-            )
-            .map_err(|e| format!("Failed to build input stream: {e}"))?;
+    /// A mono input gives equal pairs
+    fn read_stereo(&mut self, max_pairs: usize) -> Vec<(f32, f32)> {
+        if let Ok(mut consumer) = self.stereo_consumer.lock() {
+            let to_read = consumer.occupied_len().min(max_pairs);
+            consumer.pop_iter().take(to_read).collect()
+        } else {
+            vec![]
+        }
+    }
 
-        Ok(stream)
+    /// Rate the input is actually running at
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn status(&self) -> SourceStatus {
+        match self.error.lock().ok().and_then(|error| error.clone()) {
+            Some(error) => SourceStatus::Failed(error),
+            None => SourceStatus::Running,
+        }
+    }
+
+    fn data_loss(&self) -> DataLoss {
+        DataLoss {
+            dropped_samples: self.losses.dropped_samples.load(Ordering::Relaxed),
+            overruns: self.losses.overruns.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Name of the input device
+    fn name(&self) -> &str {
+        &self.device_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(channels: u16, rates: (u32, u32), format: SampleFormat) -> SupportedStreamConfigRange {
+        SupportedStreamConfigRange::new(
+            channels,
+            cpal::SampleRate(rates.0),
+            cpal::SampleRate(rates.1),
+            SupportedBufferSize::Range { min: 64, max: 4096 },
+            format,
+        )
+    }
+
+    #[test]
+    fn test_config_chosen_for_selection() {
+        let ranges = [
+            range(1, (8000, 48000), SampleFormat::I16),
+            range(2, (8000, 96000), SampleFormat::I16),
+            range(2, (44100, 96000), SampleFormat::F32),
+        ];
+        let default = ranges[1].with_sample_rate(cpal::SampleRate(44100));
+        let choose = |selection: InputSelection| choose_config(ranges, default.clone(), &selection);
+
+        assert_eq!(choose(InputSelection::default()), Ok(default.clone()));
+        // Stereo like the default, and f32 when there's a choice
+        let config = choose(InputSelection {
+            sample_rate: Some(96000),
+            ..InputSelection::default()
+        })
+        .unwrap();
+        assert_eq!(config.sample_format(), SampleFormat::F32);
+        assert_eq!(config.sample_rate().0, 96000);
+        // At the default's rate
+        let config = choose(InputSelection {
+            channels: Some(1),
+            ..InputSelection::default()
+        })
+        .unwrap();
+        assert_eq!((config.channels(), config.sample_rate().0), (1, 44100));
+        assert!(matches!(
+            choose(InputSelection {
+                channels: Some(1),
+                sample_rate: Some(96000),
+                ..InputSelection::default()
+            }),
+            Err(AudioError::UnsupportedFormat(_))
+        ));
+
+        let listed = InputConfig::from(&ranges[0]);
+        assert_eq!(
+            listed.to_string(),
+            "1 ch, 8000-48000 Hz, i16, 64-4096 frames"
+        );
+    }
+
+    #[test]
+    fn test_buffer_size_kept_within_the_device() {
+        let supported = SupportedBufferSize::Range { min: 64, max: 4096 };
+        assert_eq!(buffer_size(None, &supported), BufferSize::Default);
+        assert_eq!(buffer_size(Some(16), &supported), BufferSize::Fixed(64));
+        assert_eq!(buffer_size(Some(256), &supported), BufferSize::Fixed(256));
+        assert_eq!(
+            buffer_size(Some(256), &SupportedBufferSize::Unknown),
+            BufferSize::Default
+        );
+    }
+
+    #[test]
+    fn test_integer_samples_convert_to_full_scale() {
+        let mut converted = vec![1.0; 8];
+        convert_samples(&[i16::MIN, 0, 16384], &mut converted);
+        assert_eq!(converted, [-1.0, 0.0, 0.5]);
+        // Unsigned samples sit around the middle of their range
+        convert_samples(&[0u16, 32768, 49152], &mut converted);
+        assert_eq!(converted, [-1.0, 0.0, 0.5]);
+        convert_samples(&[0.25f32], &mut converted);
+        assert_eq!(converted, [0.25]);
+    }
+
+    #[test]
+    fn test_late_callbacks_count_as_missed_input() {
+        // 480 frames at 48 kHz is 10 ms
+        assert!(!missed_input(Duration::from_millis(10), 480, 48000));
+        // Scheduling jitter isn't loss
+        assert!(!missed_input(Duration::from_millis(14), 480, 48000));
+        assert!(missed_input(Duration::from_millis(20), 480, 48000));
     }
 }
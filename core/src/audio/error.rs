@@ -0,0 +1,113 @@
+use cpal::{BuildStreamError, DefaultStreamConfigError, PlayStreamError};
+use cpal::{DevicesError, SupportedStreamConfigsError};
+
+/// Why an input couldn't be opened, by what a frontend can do about it:
+/// plug something in, pick another device, grant access or pick another
+/// format. Whatever the platform reports beyond that is `Backend`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AudioError {
+    #[error("No input device available")]
+    NoDevice,
+    /// A device asked for by name or id isn't connected
+    #[error("Input device not found: {0}")]
+    DeviceNotFound(String),
+    /// The device went away, such as being unplugged, while it was opened
+    #[error("Input device is no longer available")]
+    DeviceUnavailable,
+    /// The user or the platform refused access to the input. Browsers
+    /// report this; desktop hosts mostly fail as `Backend` or give silence.
+    #[error("Access to the input was denied: {0}")]
+    PermissionDenied(String),
+    /// The device can't capture at the rate or in the format asked for
+    #[error("Input does not support {0}")]
+    UnsupportedFormat(String),
+    #[error("{0}")]
+    Backend(String),
+}
+
+impl From<DevicesError> for AudioError {
+    fn from(e: DevicesError) -> Self {
+        AudioError::Backend(format!("Failed to list input devices: {e}"))
+    }
+}
+
+impl From<SupportedStreamConfigsError> for AudioError {
+    fn from(e: SupportedStreamConfigsError) -> Self {
+        match e {
+            SupportedStreamConfigsError::DeviceNotAvailable => AudioError::DeviceUnavailable,
+            e => AudioError::Backend(format!("Failed to get supported input configs: {e}")),
+        }
+    }
+}
+
+impl From<DefaultStreamConfigError> for AudioError {
+    fn from(e: DefaultStreamConfigError) -> Self {
+        match e {
+            DefaultStreamConfigError::DeviceNotAvailable => AudioError::DeviceUnavailable,
+            DefaultStreamConfigError::StreamTypeNotSupported => {
+                AudioError::UnsupportedFormat("capture".to_string())
+            }
+            e => AudioError::Backend(format!("Failed to get default input config: {e}")),
+        }
+    }
+}
+
+impl From<BuildStreamError> for AudioError {
+    fn from(e: BuildStreamError) -> Self {
+        match e {
+            BuildStreamError::DeviceNotAvailable => AudioError::DeviceUnavailable,
+            BuildStreamError::StreamConfigNotSupported => {
+                AudioError::UnsupportedFormat("the chosen stream config".to_string())
+            }
+            e => AudioError::Backend(format!("Failed to build input stream: {e}")),
+        }
+    }
+}
+
+impl From<PlayStreamError> for AudioError {
+    fn from(e: PlayStreamError) -> Self {
+        match e {
+            PlayStreamError::DeviceNotAvailable => AudioError::DeviceUnavailable,
+            e => AudioError::Backend(format!("Failed to play stream: {e}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpal_errors_keep_their_kind() {
+        assert_eq!(
+            AudioError::from(BuildStreamError::DeviceNotAvailable),
+            AudioError::DeviceUnavailable
+        );
+        assert!(matches!(
+            AudioError::from(BuildStreamError::StreamConfigNotSupported),
+            AudioError::UnsupportedFormat(_)
+        ));
+        assert!(matches!(
+            AudioError::from(DefaultStreamConfigError::StreamTypeNotSupported),
+            AudioError::UnsupportedFormat(_)
+        ));
+
+        let other = AudioError::from(BuildStreamError::StreamIdOverflow);
+        assert!(matches!(other, AudioError::Backend(_)));
+        assert!(other
+            .to_string()
+            .starts_with("Failed to build input stream"));
+    }
+
+    #[test]
+    fn test_messages_name_what_was_asked_for() {
+        assert_eq!(
+            AudioError::DeviceNotFound("USB Mic".into()).to_string(),
+            "Input device not found: USB Mic"
+        );
+        assert_eq!(
+            AudioError::UnsupportedFormat("96000 Hz".into()).to_string(),
+            "Input does not support 96000 Hz"
+        );
+    }
+}
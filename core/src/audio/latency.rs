@@ -0,0 +1,262 @@
+//! How far the display runs behind the input.
+//!
+//! `LatencyMeter` estimates it from each frame: input waiting in the capture
+//! ring, the acquisition thread's processing and the UI's wait to collect
+//! it, then a display frame to draw it. That leaves out the converters and
+//! the driver's buffers, which `LoopbackTest` measures by playing a click
+//! out of the output and timing its return on the input, through a cable
+//! or from the speakers to the microphone.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use cpal::Stream;
+
+use super::monitor::open_output;
+use super::{AcquiredFrame, AudioError};
+
+/// Weight of each new reading in the smoothed estimate
+const SMOOTHING: f64 = 0.1;
+
+/// Silence before the click, long enough to hear the input's noise floor
+const CLICK_DELAY: Duration = Duration::from_millis(300);
+
+/// Length of the click, a pulse that survives AC coupling as a spike
+const CLICK_DURATION: Duration = Duration::from_millis(1);
+
+const CLICK_LEVEL: f32 = 0.8;
+
+/// Quietest return taken for the click, however quiet the input is
+const MIN_RETURN_LEVEL: f32 = 0.02;
+
+/// How far the click has to stand above the noise floor
+const RETURN_OVER_NOISE: f32 = 4.0;
+
+/// How long after the click to listen before giving up
+const LOOPBACK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Where the time between a sample arriving and being drawn goes
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Latency {
+    /// Waiting in the capture ring to be read
+    pub buffer: Duration,
+    /// Triggering and analysis, and waiting for the UI to collect the frame
+    pub processing: Duration,
+    /// Until the frame is drawn, taken as one display frame
+    pub frame: Duration,
+}
+
+impl Latency {
+    pub fn total(&self) -> Duration {
+        self.buffer + self.processing + self.frame
+    }
+}
+
+/// A smoothed `Latency` estimate, steady enough to read
+#[derive(Debug, Clone, Default)]
+pub struct LatencyMeter {
+    smoothed: Option<Latency>,
+}
+
+impl LatencyMeter {
+    /// Take in `frame`, just collected, and `frame_time` between display
+    /// frames
+    pub fn update(&mut self, frame: &AcquiredFrame, frame_time: Duration) {
+        let Some(read_at) = frame.read_at else {
+            return;
+        };
+        let reading = Latency {
+            buffer: frame.buffered,
+            processing: read_at.elapsed(),
+            frame: frame_time,
+        };
+        self.add(reading);
+    }
+
+    fn add(&mut self, reading: Latency) {
+        let blend =
+            |old: Duration, new: Duration| old.mul_f64(1.0 - SMOOTHING) + new.mul_f64(SMOOTHING);
+        self.smoothed = Some(match self.smoothed {
+            Some(old) => Latency {
+                buffer: blend(old.buffer, reading.buffer),
+                processing: blend(old.processing, reading.processing),
+                frame: blend(old.frame, reading.frame),
+            },
+            None => reading,
+        });
+    }
+
+    /// `None` until the first frame
+    pub fn latency(&self) -> Option<Latency> {
+        self.smoothed
+    }
+}
+
+/// How a loopback test came out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopbackResult {
+    /// From the click leaving for the output to its return being read
+    RoundTrip(Duration),
+    /// Nothing came back above the noise in time
+    NotHeard,
+}
+
+/// Finds a click's return in the input, timing it by when each batch of
+/// samples was read
+#[derive(Debug, Clone)]
+struct ClickDetector {
+    sample_rate: u32,
+    /// Loudest input before the click was played
+    noise: f32,
+}
+
+impl ClickDetector {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            noise: 0.0,
+        }
+    }
+
+    /// When the click came back, if it's in `samples`, the newest of which
+    /// was read at `read_at`. Before the click goes out at `clicked`, the
+    /// samples set the noise floor.
+    fn scan(
+        &mut self,
+        samples: &[f32],
+        read_at: Instant,
+        clicked: Option<Instant>,
+    ) -> Option<Instant> {
+        let Some(clicked) = clicked else {
+            self.noise = samples
+                .iter()
+                .fold(self.noise, |noise, s| noise.max(s.abs()));
+            return None;
+        };
+        let threshold = (self.noise * RETURN_OVER_NOISE).max(MIN_RETURN_LEVEL);
+        samples.iter().enumerate().find_map(|(index, sample)| {
+            let age = (samples.len() - 1 - index) as f64 / self.sample_rate as f64;
+            let arrived = read_at.checked_sub(Duration::from_secs_f64(age))?;
+            // Input from before the click can't be its return
+            (sample.abs() >= threshold && arrived >= clicked).then_some(arrived)
+        })
+    }
+}
+
+/// Plays one click out of the default output and listens for it on the
+/// input, given through `feed`. Measures the round trip through both
+/// converters and their buffers, which `LatencyMeter` can't see.
+pub struct LoopbackTest {
+    _stream: Stream,
+    /// When the click was handed to the output
+    clicked: Arc<Mutex<Option<Instant>>>,
+    detector: ClickDetector,
+}
+
+impl LoopbackTest {
+    /// Start the test at the input's `sample_rate`
+    pub fn start(sample_rate: u32) -> Result<Self, AudioError> {
+        let clicked = Arc::new(Mutex::new(None));
+        let output_clicked = Arc::clone(&clicked);
+        let click_start = (CLICK_DELAY.as_secs_f64() * sample_rate as f64) as u64;
+        let click_end = click_start + (CLICK_DURATION.as_secs_f64() * sample_rate as f64) as u64;
+        let mut position = 0u64;
+        let (stream, _) = open_output(sample_rate, move |frames| {
+            let start = position;
+            for frame in frames.iter_mut() {
+                *frame = if (click_start..click_end).contains(&position) {
+                    CLICK_LEVEL
+                } else {
+                    0.0
+                };
+                position += 1;
+            }
+            if (start..position).contains(&click_start) {
+                let offset = (click_start - start) as f64 / sample_rate as f64;
+                if let Ok(mut clicked) = output_clicked.lock() {
+                    *clicked = Some(Instant::now() + Duration::from_secs_f64(offset));
+                }
+            }
+        })?;
+        Ok(Self {
+            _stream: stream,
+            clicked,
+            detector: ClickDetector::new(sample_rate),
+        })
+    }
+
+    /// Look for the click in the input just read, `AcquiredFrame::new_samples`
+    /// read at `AcquiredFrame::read_at`. `Some` once the test is over.
+    pub fn feed(&mut self, samples: &[f32], read_at: Instant) -> Option<LoopbackResult> {
+        let clicked = self.clicked.lock().ok().and_then(|clicked| *clicked);
+        if let Some(arrived) = self.detector.scan(samples, read_at, clicked) {
+            return clicked.map(|clicked| LoopbackResult::RoundTrip(arrived - clicked));
+        }
+        clicked
+            .filter(|clicked| clicked.elapsed() > LOOPBACK_TIMEOUT)
+            .map(|_| LoopbackResult::NotHeard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meter_smooths_readings() {
+        let mut meter = LatencyMeter::default();
+        assert_eq!(meter.latency(), None);
+        let ms = Duration::from_millis;
+        meter.add(Latency {
+            buffer: ms(10),
+            processing: ms(2),
+            frame: ms(16),
+        });
+        assert_eq!(meter.latency().unwrap().total(), ms(28));
+
+        meter.add(Latency {
+            buffer: ms(20),
+            processing: ms(2),
+            frame: ms(16),
+        });
+        let latency = meter.latency().unwrap();
+        assert!((latency.buffer.as_secs_f64() - 0.011).abs() < 1e-6);
+        assert!((latency.total().as_secs_f64() - 0.029).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_meter_times_frames_from_their_read() {
+        let mut meter = LatencyMeter::default();
+        meter.update(
+            &AcquiredFrame {
+                read_at: Some(Instant::now() - Duration::from_millis(5)),
+                buffered: Duration::from_millis(4),
+                ..AcquiredFrame::default()
+            },
+            Duration::from_millis(16),
+        );
+        let latency = meter.latency().unwrap();
+        assert!(latency.processing >= Duration::from_millis(5));
+        assert_eq!(latency.buffer, Duration::from_millis(4));
+    }
+
+    #[test]
+    fn test_click_found_above_the_noise() {
+        let mut detector = ClickDetector::new(1000);
+        let start = Instant::now();
+        // Noise up to 0.05 before the click, so 0.1 doesn't count
+        assert_eq!(detector.scan(&[0.05, -0.03], start, None), None);
+        let clicked = start + Duration::from_millis(10);
+        let mut samples = vec![0.1; 100];
+        samples[60] = 0.5;
+        // The newest sample read 100 ms after the click
+        let read_at = clicked + Duration::from_millis(100);
+        let arrived = detector.scan(&samples, read_at, Some(clicked)).unwrap();
+        // 39 samples before the newest, at 1 kHz
+        assert!(((read_at - arrived).as_secs_f64() - 0.039).abs() < 1e-6);
+
+        // A loud sample read before the click went out isn't its return
+        let early = vec![0.5; 10];
+        assert_eq!(detector.scan(&early, start, Some(clicked)), None);
+    }
+}
@@ -1,3 +1,17 @@
+pub mod acquisition;
+pub mod calibration;
 pub mod capture;
+pub mod error;
+pub mod latency;
+pub mod monitor;
+pub mod stream;
+pub mod trigger_events;
 
-pub use capture::AudioCapture;
+pub use acquisition::{AcquiredFrame, Acquisition, AcquisitionSettings};
+pub use calibration::{Calibration, CalibrationStore};
+pub use capture::{input_configs, input_device_names, AudioCapture, InputConfig, InputSelection};
+pub use error::AudioError;
+pub use latency::{Latency, LatencyMeter, LoopbackResult, LoopbackTest};
+pub use monitor::{Monitor, MonitorFeed};
+pub use stream::{FrameStream, ScopeFrame};
+pub use trigger_events::{TriggerEvent, TriggerSubscribers};
@@ -0,0 +1,424 @@
+//! Playing the input back through the speakers, so a signal being probed
+//! can be heard as well as seen.
+//!
+//! The output stream belongs to a `Monitor`, kept by the frontend; the
+//! acquisition thread feeds it through a `MonitorFeed`, after the filters,
+//! so what's heard is what's shown. A microphone near the speakers can
+//! howl, so monitoring starts quiet and a `FeedbackGuard` mutes it when the
+//! input or the output stays near full scale and goes quiet along with the
+//! monitor.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::Stream;
+use ringbuf::{traits::*, HeapRb};
+
+use super::AudioError;
+
+/// Lowest and highest monitor gain, in dB
+pub const MONITOR_GAIN_RANGE: (f32, f32) = (-40.0, 12.0);
+
+/// Gain monitoring starts at, well clear of feedback
+pub const DEFAULT_MONITOR_GAIN_DB: f32 = -12.0;
+
+/// Most audio queued for the output, which bounds the monitor's latency
+const MONITOR_BUFFER_SECONDS: f32 = 0.1;
+
+/// Input or output level taken as howling when held
+const FEEDBACK_LEVEL: f32 = 0.9;
+
+/// How long it has to stay that loud
+const FEEDBACK_SECONDS: f32 = 0.5;
+
+/// How long the monitor goes quiet to test for feedback
+const TEST_SECONDS: f32 = 0.25;
+
+/// How much of that passes before the input is judged, for the queued
+/// output and the room to die away
+const SETTLE_SECONDS: f32 = 0.15;
+
+/// What `FeedbackGuard::check` makes of the input so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedbackCheck {
+    Clear,
+    /// Held loud: the monitor stays quiet to see whether the input follows
+    Testing,
+    /// The input went quiet with the monitor, so it was the monitor's own
+    Feedback,
+}
+
+/// Spots feedback: input or output that stays near full scale, and drops
+/// away when the monitor goes quiet. A howl fills the microphone whatever
+/// the monitor's gain, so the input counts even when turned down too far
+/// for the output to reach the level; a loud source stays loud without the
+/// monitor, and is left playing.
+#[derive(Debug, Clone)]
+pub struct FeedbackGuard {
+    hold: usize,
+    test_length: usize,
+    settle: usize,
+    /// Samples since the level last dropped below `FEEDBACK_LEVEL`
+    loud: usize,
+    /// Samples into the current test, while testing
+    testing: Option<usize>,
+    /// Loudest input since the current test settled
+    test_peak: f32,
+    /// Shown by a test to be loud by itself; not tested again until it
+    /// quietens
+    source: bool,
+}
+
+impl FeedbackGuard {
+    pub fn new(sample_rate: u32) -> Self {
+        let samples = |seconds: f32| (seconds * sample_rate as f32) as usize;
+        Self {
+            hold: samples(FEEDBACK_SECONDS),
+            test_length: samples(TEST_SECONDS),
+            settle: samples(SETTLE_SECONDS),
+            loud: 0,
+            testing: None,
+            test_peak: 0.0,
+            source: false,
+        }
+    }
+
+    /// Whether `block`, the next input, heard at linear `gain`, is
+    /// feedback. Judged a block at a time, by the louder of its peak in and
+    /// out; while `Testing`, the caller keeps the monitor quiet.
+    pub fn check(&mut self, block: &[f32], gain: f32) -> FeedbackCheck {
+        let peak = block.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        if let Some(elapsed) = self.testing {
+            if elapsed >= self.settle {
+                self.test_peak = self.test_peak.max(peak);
+            }
+            let elapsed = elapsed + block.len();
+            if elapsed < self.test_length {
+                self.testing = Some(elapsed);
+                return FeedbackCheck::Testing;
+            }
+            self.testing = None;
+            self.loud = 0;
+            if self.test_peak < FEEDBACK_LEVEL / 2.0 {
+                return FeedbackCheck::Feedback;
+            }
+            self.source = true;
+            return FeedbackCheck::Clear;
+        }
+
+        if peak * gain.max(1.0) < FEEDBACK_LEVEL {
+            self.loud = 0;
+            self.source = false;
+        } else if !self.source {
+            self.loud += block.len();
+            if self.loud >= self.hold {
+                self.testing = Some(0);
+                self.test_peak = 0.0;
+                return FeedbackCheck::Testing;
+            }
+        }
+        FeedbackCheck::Clear
+    }
+
+    pub fn reset(&mut self) {
+        self.loud = 0;
+        self.testing = None;
+        self.source = false;
+    }
+}
+
+/// Shared between the frontend, the feed and nothing else
+#[derive(Debug)]
+struct Controls {
+    /// Linear gain, as f32 bits
+    gain: AtomicU32,
+    muted: AtomicBool,
+    /// Muted by the guard rather than the user
+    feedback: AtomicBool,
+}
+
+/// Where the acquisition thread hands the monitor its samples
+#[derive(Clone)]
+pub struct MonitorFeed {
+    producer: Arc<Mutex<ringbuf::HeapProd<f32>>>,
+    controls: Arc<Controls>,
+    guard: Arc<Mutex<FeedbackGuard>>,
+}
+
+impl MonitorFeed {
+    /// A feed and the end the output reads from
+    fn new(sample_rate: u32) -> (Self, ringbuf::HeapCons<f32>) {
+        let capacity = ((MONITOR_BUFFER_SECONDS * sample_rate as f32) as usize).max(1);
+        let (producer, consumer) = HeapRb::<f32>::new(capacity).split();
+        let feed = Self {
+            producer: Arc::new(Mutex::new(producer)),
+            controls: Arc::new(Controls {
+                gain: AtomicU32::new(db_to_gain(DEFAULT_MONITOR_GAIN_DB).to_bits()),
+                muted: AtomicBool::new(false),
+                feedback: AtomicBool::new(false),
+            }),
+            guard: Arc::new(Mutex::new(FeedbackGuard::new(sample_rate))),
+        };
+        (feed, consumer)
+    }
+
+    /// Queue `samples` to be heard, at the monitor's gain. Those the output
+    /// has no room for are dropped rather than adding latency.
+    pub fn push(&self, samples: &[f32]) {
+        if self.controls.muted.load(Ordering::Relaxed) {
+            return;
+        }
+        let gain = f32::from_bits(self.controls.gain.load(Ordering::Relaxed));
+        let check = self
+            .guard
+            .lock()
+            .map_or(FeedbackCheck::Clear, |mut guard| guard.check(samples, gain));
+        match check {
+            FeedbackCheck::Clear => {}
+            // The output runs dry, and plays silence
+            FeedbackCheck::Testing => return,
+            FeedbackCheck::Feedback => {
+                self.controls.feedback.store(true, Ordering::Relaxed);
+                self.controls.muted.store(true, Ordering::Relaxed);
+                return;
+            }
+        }
+        if let Ok(mut producer) = self.producer.lock() {
+            producer.push_iter(
+                samples
+                    .iter()
+                    .map(|&sample| (sample * gain).clamp(-1.0, 1.0)),
+            );
+        }
+    }
+}
+
+fn db_to_gain(db: f32) -> f32 {
+    10_f32.powf(db / 20.0)
+}
+
+/// Play the default output at `sample_rate`, `fill` giving each buffer's
+/// samples, one per frame, which every channel plays. Returns the stream,
+/// which plays until dropped, and the device's name.
+pub(crate) fn open_output(
+    sample_rate: u32,
+    mut fill: impl FnMut(&mut [f32]) + Send + 'static,
+) -> Result<(Stream, String), AudioError> {
+    let host = cpal::default_host();
+    let device = host.default_output_device().ok_or(AudioError::NoDevice)?;
+    let config = device
+        .supported_output_configs()?
+        .filter(|range| {
+            (range.min_sample_rate().0..=range.max_sample_rate().0).contains(&sample_rate)
+        })
+        // The stream is written as f32
+        .max_by_key(|range| range.sample_format() == cpal::SampleFormat::F32)
+        .map(|range| range.with_sample_rate(cpal::SampleRate(sample_rate)))
+        .ok_or_else(|| AudioError::UnsupportedFormat(format!("output at {sample_rate} Hz")))?;
+    let channels = config.channels() as usize;
+    let device_name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+
+    let mut frames = Vec::new();
+    let stream = device.build_output_stream(
+        &config.into(),
+        move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+            frames.resize(data.len() / channels, 0.0);
+            fill(&mut frames);
+            for (frame, &sample) in data.chunks_mut(channels).zip(&frames) {
+                frame.fill(sample);
+            }
+        },
+        |err| eprintln!("Output stream error: {err}"),
+        None,
+    )?;
+    stream.play()?;
+    Ok((stream, device_name))
+}
+
+/// The default output, playing what its feed is given
+pub struct Monitor {
+    _stream: Stream,
+    feed: MonitorFeed,
+    device_name: String,
+}
+
+impl Monitor {
+    /// Open the default output at `sample_rate`, the input's, so samples
+    /// pass straight through
+    pub fn open(sample_rate: u32) -> Result<Self, AudioError> {
+        let (feed, mut consumer) = MonitorFeed::new(sample_rate);
+        let (stream, device_name) = open_output(sample_rate, move |frames| {
+            // Silence once it runs dry
+            frames.fill_with(|| consumer.try_pop().unwrap_or(0.0));
+        })?;
+
+        Ok(Self {
+            _stream: stream,
+            feed,
+            device_name,
+        })
+    }
+
+    /// For `Acquisition::set_monitor`
+    pub fn feed(&self) -> MonitorFeed {
+        self.feed.clone()
+    }
+
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    pub fn gain_db(&self) -> f32 {
+        let gain = f32::from_bits(self.feed.controls.gain.load(Ordering::Relaxed));
+        20.0 * gain.log10()
+    }
+
+    /// Clamped to `MONITOR_GAIN_RANGE`
+    pub fn set_gain_db(&self, db: f32) {
+        let db = db.clamp(MONITOR_GAIN_RANGE.0, MONITOR_GAIN_RANGE.1);
+        let gain = db_to_gain(db);
+        self.feed
+            .controls
+            .gain
+            .store(gain.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.feed.controls.muted.load(Ordering::Relaxed)
+    }
+
+    /// Unmuting also clears a feedback mute, giving the guard a fresh start
+    pub fn set_muted(&self, muted: bool) {
+        let controls = &self.feed.controls;
+        if !muted {
+            controls.feedback.store(false, Ordering::Relaxed);
+            if let Ok(mut guard) = self.feed.guard.lock() {
+                guard.reset();
+            }
+        }
+        controls.muted.store(muted, Ordering::Relaxed);
+    }
+
+    /// Whether the guard muted it for feedback
+    pub fn feedback_detected(&self) -> bool {
+        self.feed.controls.feedback.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::f32::consts::TAU;
+
+    /// A microphone `room_gain` times as loud as the speaker it hears,
+    /// starting from a quiet 50 Hz hum. Returns how many 50 ms blocks it
+    /// took the feed to mute for feedback, if it did within `seconds`.
+    fn run_room(
+        feed: &MonitorFeed,
+        consumer: &mut ringbuf::HeapCons<f32>,
+        room_gain: f32,
+        seconds: usize,
+    ) -> Option<usize> {
+        let mut heard: Vec<f32> = (0..50)
+            .map(|i| 0.01 * (i as f32 * TAU / 20.0).sin())
+            .collect();
+        for block in 0..seconds * 20 {
+            feed.push(&heard);
+            if feed.controls.feedback.load(Ordering::Relaxed) {
+                return Some(block);
+            }
+            let played: Vec<f32> = consumer.pop_iter().collect();
+            heard = (0..50)
+                .map(|i| {
+                    played
+                        .get(i)
+                        .map_or(0.0, |s| (s * room_gain).clamp(-1.0, 1.0))
+                })
+                .collect();
+        }
+        None
+    }
+
+    #[test]
+    fn test_guard_tests_sustained_full_scale() {
+        let mut guard = FeedbackGuard::new(1000);
+        // Loud, but broken up, as music peaks are
+        for _ in 0..10 {
+            assert_eq!(guard.check(&[0.95; 100], 1.0), FeedbackCheck::Clear);
+            assert_eq!(guard.check(&[0.2; 10], 1.0), FeedbackCheck::Clear);
+        }
+        assert_eq!(guard.check(&[0.95; 400], 1.0), FeedbackCheck::Clear);
+        assert_eq!(guard.check(&[-0.95; 100], 1.0), FeedbackCheck::Testing);
+
+        // The input dies away with the monitor: feedback
+        assert_eq!(guard.check(&[0.95; 100], 1.0), FeedbackCheck::Testing);
+        assert_eq!(guard.check(&[0.0; 100], 1.0), FeedbackCheck::Testing);
+        assert_eq!(guard.check(&[0.0; 100], 1.0), FeedbackCheck::Feedback);
+
+        // Quiet in, but loud out, and still there without the monitor
+        guard.reset();
+        assert_eq!(guard.check(&[0.5; 400], 4.0), FeedbackCheck::Clear);
+        assert_eq!(guard.check(&[0.5; 100], 4.0), FeedbackCheck::Testing);
+        for _ in 0..2 {
+            assert_eq!(guard.check(&[0.5; 100], 4.0), FeedbackCheck::Testing);
+        }
+        assert_eq!(guard.check(&[0.5; 100], 4.0), FeedbackCheck::Clear);
+        // Not tested again until it quietens
+        assert_eq!(guard.check(&[0.5; 1000], 4.0), FeedbackCheck::Clear);
+        assert_eq!(guard.check(&[0.1; 10], 4.0), FeedbackCheck::Clear);
+        assert_eq!(guard.check(&[0.5; 500], 4.0), FeedbackCheck::Testing);
+    }
+
+    #[test]
+    fn test_feed_keeps_playing_a_loud_steady_tone() {
+        for gain in [db_to_gain(DEFAULT_MONITOR_GAIN_DB), 1.0] {
+            let (feed, mut consumer) = MonitorFeed::new(1000);
+            feed.controls.gain.store(gain.to_bits(), Ordering::Relaxed);
+            // A full-scale 50 Hz test tone, whatever the speaker plays
+            let tone: Vec<f32> = (0..50).map(|i| (i as f32 * TAU / 20.0).sin()).collect();
+            let mut played = 0;
+            for _ in 0..60 {
+                feed.push(&tone);
+                played += consumer.pop_iter().count();
+            }
+            assert!(!feed.controls.feedback.load(Ordering::Relaxed));
+            assert!(!feed.controls.muted.load(Ordering::Relaxed));
+            // Only the one test went unheard
+            assert_eq!(played, 3000 - 250);
+        }
+    }
+
+    #[test]
+    fn test_feed_mutes_a_howl_at_the_default_gain() {
+        let (feed, mut consumer) = MonitorFeed::new(1000);
+        // A room loud enough to howl at -12 dB, caught within 1.5 s
+        let block = run_room(&feed, &mut consumer, 6.0, 3);
+        assert!(block.is_some_and(|block| block < 30), "{block:?}");
+        assert!(feed.controls.muted.load(Ordering::Relaxed));
+
+        // One that can't stays playing
+        let (feed, mut consumer) = MonitorFeed::new(1000);
+        assert_eq!(run_room(&feed, &mut consumer, 2.0, 3), None);
+    }
+
+    #[test]
+    fn test_feed_applies_gain_and_mutes_on_feedback() {
+        let (feed, mut consumer) = MonitorFeed::new(1000);
+        feed.push(&[1.0, -0.5]);
+        let heard: Vec<f32> = consumer.pop_iter().collect();
+        let gain = db_to_gain(DEFAULT_MONITOR_GAIN_DB);
+        assert_eq!(heard, [gain, -0.5 * gain]);
+
+        // Howling at full gain in a quiet room
+        feed.controls.gain.store(
+            db_to_gain(MONITOR_GAIN_RANGE.1).to_bits(),
+            Ordering::Relaxed,
+        );
+        assert!(run_room(&feed, &mut consumer, 1.0, 3).is_some());
+        assert!(feed.controls.muted.load(Ordering::Relaxed));
+        feed.push(&[0.5; 50]);
+        assert_eq!(consumer.pop_iter().count(), 0);
+    }
+}
@@ -0,0 +1,115 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::audio::{AcquiredFrame, Acquisition};
+use crate::oscilloscope::Measurements;
+use crate::plugin::PluginMeasurement;
+
+/// One capture as the scope sees it: triggered, windowed and measured
+#[derive(Debug, Clone)]
+pub struct ScopeFrame {
+    pub sample_rate: u32,
+    /// The screenful on display, from the trigger on when triggered
+    pub record: Arc<[f32]>,
+    /// See `WaveformData::trigger_position`; `None` free-running or when no
+    /// edge was found
+    pub trigger_position: Option<f32>,
+    pub measurements: Measurements,
+    /// Of every registered measurement plugin
+    pub plugin_measurements: Vec<PluginMeasurement>,
+    /// dB magnitudes, when the analyzer completed a hop since the last
+    /// frame
+    pub spectrum: Option<Vec<f32>>,
+    /// Every sample read since the previous frame, for an unbroken stream
+    pub new_samples: Vec<f32>,
+}
+
+impl ScopeFrame {
+    pub fn new(frame: AcquiredFrame, sample_rate: u32) -> Self {
+        let range = frame.display_range.start.min(frame.window.len())
+            ..frame.display_range.end.min(frame.window.len());
+        Self {
+            sample_rate,
+            record: frame.window[range].into(),
+            trigger_position: frame.trigger_position,
+            measurements: frame.measurements,
+            plugin_measurements: frame.plugin_measurements,
+            spectrum: frame.spectrum,
+            new_samples: frame.new_samples,
+        }
+    }
+}
+
+/// An `Acquisition` as a `Stream` of frames, for async applications that
+/// embed the capture without a GUI. Works with any executor; the capture
+/// stops when the stream is dropped and the stream ends if the capture
+/// does.
+pub struct FrameStream {
+    acquisition: Acquisition,
+}
+
+impl FrameStream {
+    pub fn new(acquisition: Acquisition) -> Self {
+        Self { acquisition }
+    }
+
+    /// To change settings or subscribe to triggers while streaming
+    pub fn acquisition(&self) -> &Acquisition {
+        &self.acquisition
+    }
+}
+
+impl Stream for FrameStream {
+    type Item = ScopeFrame;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<ScopeFrame>> {
+        let sample_rate = self.acquisition.sample_rate();
+        self.acquisition
+            .poll_frame(cx.waker())
+            .map(|frame| frame.map(|frame| ScopeFrame::new(frame, sample_rate)))
+    }
+}
+
+impl Acquisition {
+    /// Every frame from here on as a `Stream`
+    pub fn into_stream(self) -> FrameStream {
+        FrameStream::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::task::Waker;
+
+    #[test]
+    fn test_stream_yields_frames_then_ends() {
+        let (sender, frames) = mpsc::sync_channel(4);
+        let mut stream = Acquisition::from_frames(frames, 48000).into_stream();
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut poll = || Pin::new(&mut stream).poll_next(&mut cx);
+        assert!(poll().is_pending());
+
+        sender
+            .send(AcquiredFrame {
+                window: vec![0.0, 1.0, 2.0, 3.0].into(),
+                display_range: 1..3,
+                trigger_position: Some(0.0),
+                ..Default::default()
+            })
+            .unwrap();
+        let Poll::Ready(Some(frame)) = poll() else {
+            panic!("expected a frame");
+        };
+        assert_eq!(&*frame.record, &[1.0, 2.0]);
+        assert_eq!(frame.sample_rate, 48000);
+        assert!(poll().is_pending());
+
+        drop(sender);
+        assert!(matches!(poll(), Poll::Ready(None)));
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// A capture the scope triggered on
+#[derive(Debug, Clone)]
+pub struct TriggerEvent {
+    /// When the acquisition thread found the edge
+    pub timestamp: SystemTime,
+    /// Position of the triggering sample in the input stream, counted from
+    /// the start of the capture
+    pub sample: u64,
+    pub sample_rate: u32,
+    /// The screenful from the trigger on, as displayed
+    pub record: Arc<[f32]>,
+}
+
+impl TriggerEvent {
+    /// How far into the capture the edge came, by the sample clock
+    pub fn stream_time(&self) -> Duration {
+        Duration::from_secs_f64(self.sample as f64 / self.sample_rate.max(1) as f64)
+    }
+}
+
+type Callback = Box<dyn FnMut(&TriggerEvent) + Send>;
+
+enum Subscriber {
+    Callback(Callback),
+    Channel(Sender<TriggerEvent>),
+}
+
+/// Everyone to tell about trigger events, shared between the acquisition
+/// handle and its thread
+#[derive(Clone, Default)]
+pub struct TriggerSubscribers {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+impl TriggerSubscribers {
+    pub fn add_callback(&self, callback: impl FnMut(&TriggerEvent) + Send + 'static) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(Subscriber::Callback(Box::new(callback)));
+        }
+    }
+
+    /// A channel that gets every event from now on
+    pub fn add_channel(&self) -> Receiver<TriggerEvent> {
+        let (sender, receiver) = mpsc::channel();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(Subscriber::Channel(sender));
+        }
+        receiver
+    }
+
+    /// Whether anyone is listening, so captures aren't copied for nobody
+    pub fn is_empty(&self) -> bool {
+        self.subscribers
+            .lock()
+            .map_or(true, |subscribers| subscribers.is_empty())
+    }
+
+    /// Call each callback and send on each channel, dropping channels
+    /// whose receiver has gone
+    pub fn publish(&self, event: &TriggerEvent) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain_mut(|subscriber| match subscriber {
+                Subscriber::Callback(callback) => {
+                    callback(event);
+                    true
+                }
+                Subscriber::Channel(sender) => sender.send(event.clone()).is_ok(),
+            });
+        }
+    }
+}
+
+/// Tells a new trigger edge from the same one found again as more input
+/// arrives
+#[derive(Debug, Default)]
+pub struct TriggerTracker {
+    last: Option<u64>,
+}
+
+impl TriggerTracker {
+    /// Whether the edge at stream position `sample` is past the last one
+    /// reported, remembering it if so
+    pub fn is_new(&mut self, sample: u64) -> bool {
+        if self.last.is_some_and(|last| sample <= last) {
+            return false;
+        }
+        self.last = Some(sample);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(sample: u64) -> TriggerEvent {
+        TriggerEvent {
+            timestamp: SystemTime::now(),
+            sample,
+            sample_rate: 48000,
+            record: Arc::from(vec![0.5; 4]),
+        }
+    }
+
+    #[test]
+    fn test_publish_to_callbacks_and_channels() {
+        let subscribers = TriggerSubscribers::default();
+        assert!(subscribers.is_empty());
+
+        let count = Arc::new(Mutex::new(0));
+        let counter = Arc::clone(&count);
+        subscribers.add_callback(move |_| *counter.lock().unwrap() += 1);
+        let events = subscribers.add_channel();
+        let dropped = subscribers.add_channel();
+        drop(dropped);
+
+        subscribers.publish(&event(96000));
+        subscribers.publish(&event(144000));
+        assert_eq!(*count.lock().unwrap(), 2);
+        let received: Vec<u64> = events.try_iter().map(|event| event.sample).collect();
+        assert_eq!(received, vec![96000, 144000]);
+        assert_eq!(event(96000).stream_time(), Duration::from_secs(2));
+
+        // The dropped receiver's channel went at the first publish
+        assert_eq!(subscribers.subscribers.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_tracker_reports_each_edge_once() {
+        let mut tracker = TriggerTracker::default();
+        assert!(tracker.is_new(100));
+        assert!(!tracker.is_new(100));
+        assert!(!tracker.is_new(90));
+        assert!(tracker.is_new(580));
+    }
+}
@@ -0,0 +1,507 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::display::{
+    color_from_hex, color_to_hex, DisplayTheme, PersistenceColormap, PersistenceDecay,
+    PersistenceMode, RefreshRate, TraceColors, MAX_PERSISTENCE_FRAMES,
+};
+use crate::dsp::FilterBlock;
+
+/// Preferences file inside `config_directory`
+const FILE_NAME: &str = "preferences.txt";
+
+/// Window settings file inside `config_directory`
+const WINDOW_FILE_NAME: &str = "window.txt";
+
+/// Persistence depth limits, as the scope's persistence control allows
+const PERSISTENCE_FRAMES_RANGE: (usize, usize) = (1, MAX_PERSISTENCE_FRAMES);
+
+/// Where OzeeCubed keeps its files: `ozeecubed` in `$XDG_CONFIG_HOME`,
+/// falling back to `~/.config` and then `%APPDATA%`
+pub fn config_directory() -> Option<PathBuf> {
+    let directory = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(directory.join("ozeecubed"))
+}
+
+/// Startup settings and key bindings from the preferences view, kept in a
+/// text file of `name = value` lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preferences {
+    /// Input to capture by name, the system default when `None`
+    pub input_device: Option<String>,
+    /// Capture rate to ask the input for, its own default when `None`
+    pub sample_rate: Option<u32>,
+    /// Channels to capture, the input's default when `None`
+    pub input_channels: Option<u16>,
+    /// Frames per input buffer, small for low latency; the host's choice
+    /// when `None`
+    pub buffer_size: Option<u32>,
+    pub theme: DisplayTheme,
+    /// How often the display redraws
+    pub refresh_rate: RefreshRate,
+    /// Trace colors picked over the theme's
+    pub trace_colors: TraceColors,
+    /// Name shown by the input channel's measurements, such as "Mic L"
+    pub input_label: Option<String>,
+    /// Name shown by the math trace's controls
+    pub math_label: Option<String>,
+    /// Time/div the scope starts at, in seconds
+    pub time_per_division: f32,
+    pub persistence_enabled: bool,
+    pub persistence_frames: usize,
+    pub persistence_mode: PersistenceMode,
+    pub persistence_decay: PersistenceDecay,
+    pub persistence_colormap: PersistenceColormap,
+    /// Filter chain on the input, in order
+    pub filters: Vec<FilterBlock>,
+    /// Keys moved from the frontend's defaults, by action name
+    key_bindings: BTreeMap<String, char>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            input_device: None,
+            sample_rate: None,
+            input_channels: None,
+            buffer_size: None,
+            theme: DisplayTheme::default(),
+            refresh_rate: RefreshRate::default(),
+            trace_colors: TraceColors::default(),
+            input_label: None,
+            math_label: None,
+            time_per_division: 0.001,
+            persistence_enabled: true,
+            persistence_frames: 10,
+            persistence_mode: PersistenceMode::default(),
+            persistence_decay: PersistenceDecay::default(),
+            persistence_colormap: PersistenceColormap::default(),
+            filters: Vec::new(),
+            key_bindings: BTreeMap::new(),
+        }
+    }
+}
+
+impl Preferences {
+    /// `preferences.txt` in `config_directory`
+    pub fn default_path() -> Option<PathBuf> {
+        config_directory().map(|directory| directory.join(FILE_NAME))
+    }
+
+    /// Read preferences saved by `save`. A missing file gives the defaults.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read {}: {e}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)
+                .map_err(|e| format!("Failed to create {}: {e}", directory.display()))?;
+        }
+        fs::write(path, self.to_text())
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    /// The key bound to `action`, `None` leaving the frontend's default
+    pub fn key(&self, action: &str) -> Option<char> {
+        self.key_bindings.get(action).copied()
+    }
+
+    /// Bind `action` to `key`, or with `None` go back to the default.
+    /// Keys are kept lowercase; shortcuts ignore case.
+    pub fn bind_key(&mut self, action: &str, key: Option<char>) {
+        match key {
+            Some(key) => {
+                self.key_bindings
+                    .insert(action.to_string(), key.to_ascii_lowercase());
+            }
+            None => {
+                self.key_bindings.remove(action);
+            }
+        }
+    }
+
+    /// Lines that don't parse are skipped, leaving that setting at its
+    /// default, so a hand-edited file loses only its broken entries
+    fn parse(text: &str) -> Self {
+        let mut preferences = Self::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (name, value) = (name.trim(), value.trim());
+            match name {
+                "input_device" if !value.is_empty() => {
+                    preferences.input_device = Some(value.to_string());
+                }
+                "sample_rate" => {
+                    if let Some(rate) = value.parse().ok().filter(|&rate: &u32| rate > 0) {
+                        preferences.sample_rate = Some(rate);
+                    }
+                }
+                "input_channels" => {
+                    if let Some(channels) = value.parse().ok().filter(|&c: &u16| c > 0) {
+                        preferences.input_channels = Some(channels);
+                    }
+                }
+                "buffer_size" => {
+                    if let Some(frames) = value.parse().ok().filter(|&f: &u32| f > 0) {
+                        preferences.buffer_size = Some(frames);
+                    }
+                }
+                "theme" => {
+                    if let Some(theme) = DisplayTheme::from_label(value) {
+                        preferences.theme = theme;
+                    }
+                }
+                "refresh_rate" => {
+                    if let Some(rate) = RefreshRate::from_label(value) {
+                        preferences.refresh_rate = rate;
+                    }
+                }
+                "input_label" if !value.is_empty() => {
+                    preferences.input_label = Some(value.to_string());
+                }
+                "math_label" if !value.is_empty() => {
+                    preferences.math_label = Some(value.to_string());
+                }
+                "trace_color" => {
+                    if let Some(color) = color_from_hex(value) {
+                        preferences.trace_colors.trace = Some(color);
+                    }
+                }
+                "math_color" => {
+                    if let Some(color) = color_from_hex(value) {
+                        preferences.trace_colors.math = Some(color);
+                    }
+                }
+                "time_per_division" => {
+                    if let Some(seconds) = value.parse().ok().filter(|&s: &f32| s > 0.0) {
+                        preferences.time_per_division = seconds;
+                    }
+                }
+                "persistence" => {
+                    if let Ok(enabled) = value.parse() {
+                        preferences.persistence_enabled = enabled;
+                    }
+                }
+                "persistence_frames" => {
+                    if let Ok(frames) = value.parse::<usize>() {
+                        preferences.persistence_frames =
+                            frames.clamp(PERSISTENCE_FRAMES_RANGE.0, PERSISTENCE_FRAMES_RANGE.1);
+                    }
+                }
+                "persistence_mode" => {
+                    if let Some(mode) = PersistenceMode::from_label(value) {
+                        preferences.persistence_mode = mode;
+                    }
+                }
+                "persistence_decay" => {
+                    if let Some(decay) = PersistenceDecay::from_label(value) {
+                        preferences.persistence_decay = decay;
+                    }
+                }
+                "persistence_colormap" => {
+                    if let Some(colormap) = PersistenceColormap::from_label(value) {
+                        preferences.persistence_colormap = colormap;
+                    }
+                }
+                // One line per block, in chain order
+                "filter" => {
+                    if let Some(block) = FilterBlock::parse(value) {
+                        preferences.filters.push(block);
+                    }
+                }
+                _ => {
+                    let mut key = value.chars();
+                    if let (Some(action), Some(key), None) =
+                        (name.strip_prefix("key."), key.next(), key.next())
+                    {
+                        preferences.bind_key(action, Some(key));
+                    }
+                }
+            }
+        }
+        preferences
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        if let Some(ref device) = self.input_device {
+            text.push_str(&format!("input_device = {device}\n"));
+        }
+        if let Some(rate) = self.sample_rate {
+            text.push_str(&format!("sample_rate = {rate}\n"));
+        }
+        if let Some(channels) = self.input_channels {
+            text.push_str(&format!("input_channels = {channels}\n"));
+        }
+        if let Some(frames) = self.buffer_size {
+            text.push_str(&format!("buffer_size = {frames}\n"));
+        }
+        text.push_str(&format!("theme = {}\n", self.theme.label()));
+        text.push_str(&format!("refresh_rate = {}\n", self.refresh_rate.label()));
+        if let Some(ref label) = self.input_label {
+            text.push_str(&format!("input_label = {label}\n"));
+        }
+        if let Some(ref label) = self.math_label {
+            text.push_str(&format!("math_label = {label}\n"));
+        }
+        if let Some(color) = self.trace_colors.trace {
+            text.push_str(&format!("trace_color = {}\n", color_to_hex(color)));
+        }
+        if let Some(color) = self.trace_colors.math {
+            text.push_str(&format!("math_color = {}\n", color_to_hex(color)));
+        }
+        text.push_str(&format!("time_per_division = {}\n", self.time_per_division));
+        text.push_str(&format!("persistence = {}\n", self.persistence_enabled));
+        text.push_str(&format!(
+            "persistence_frames = {}\n",
+            self.persistence_frames
+        ));
+        text.push_str(&format!(
+            "persistence_mode = {}\n",
+            self.persistence_mode.label()
+        ));
+        text.push_str(&format!(
+            "persistence_decay = {}\n",
+            self.persistence_decay.label()
+        ));
+        text.push_str(&format!(
+            "persistence_colormap = {}\n",
+            self.persistence_colormap.label()
+        ));
+        for block in &self.filters {
+            text.push_str(&format!("filter = {}\n", block.to_text()));
+        }
+        for (action, key) in &self.key_bindings {
+            text.push_str(&format!("key.{action} = {key}\n"));
+        }
+        text
+    }
+}
+
+/// How the wgpu app opens its scope window, kept in its own text file of
+/// `name = value` lines. The app writes the size, position and monitor back
+/// when the window closes, so it reopens where it was left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowSettings {
+    /// Title bar text, the app's own when `None`
+    pub title: Option<String>,
+    /// Inner size in physical pixels
+    pub size: (u32, u32),
+    /// Outer position in physical pixels, left to the system when `None`
+    pub position: Option<(i32, i32)>,
+    /// Monitor to open on, by name or by number from 0
+    pub monitor: Option<String>,
+    /// Title bar and borders
+    pub decorations: bool,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            title: None,
+            size: (1280, 720),
+            position: None,
+            monitor: None,
+            decorations: true,
+        }
+    }
+}
+
+impl WindowSettings {
+    /// `window.txt` in `config_directory`
+    pub fn default_path() -> Option<PathBuf> {
+        config_directory().map(|directory| directory.join(WINDOW_FILE_NAME))
+    }
+
+    /// Read settings saved by `save`. A missing file gives the defaults.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Failed to read {}: {e}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)
+                .map_err(|e| format!("Failed to create {}: {e}", directory.display()))?;
+        }
+        fs::write(path, self.to_text())
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    /// Lines that don't parse are skipped, as in `Preferences`
+    fn parse(text: &str) -> Self {
+        let mut settings = Self::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (name, value) = (name.trim(), value.trim());
+            match name {
+                "title" if !value.is_empty() => settings.title = Some(value.to_string()),
+                "size" => {
+                    if let Some(size) = parse_size(value) {
+                        settings.size = size;
+                    }
+                }
+                "position" => {
+                    if let Some(position) = parse_position(value) {
+                        settings.position = Some(position);
+                    }
+                }
+                "monitor" if !value.is_empty() => settings.monitor = Some(value.to_string()),
+                "decorations" => {
+                    if let Ok(decorations) = value.parse() {
+                        settings.decorations = decorations;
+                    }
+                }
+                _ => {}
+            }
+        }
+        settings
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        if let Some(ref title) = self.title {
+            text.push_str(&format!("title = {title}\n"));
+        }
+        text.push_str(&format!("size = {}x{}\n", self.size.0, self.size.1));
+        if let Some((x, y)) = self.position {
+            text.push_str(&format!("position = {x},{y}\n"));
+        }
+        if let Some(ref monitor) = self.monitor {
+            text.push_str(&format!("monitor = {monitor}\n"));
+        }
+        text.push_str(&format!("decorations = {}\n", self.decorations));
+        text
+    }
+}
+
+/// `WIDTHxHEIGHT`, as in `1280x720`; neither may be zero
+pub fn parse_size(text: &str) -> Option<(u32, u32)> {
+    let (width, height) = text.split_once('x')?;
+    let size = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+/// `X,Y`, as in `100,-20`; negative on monitors left of or above the first
+pub fn parse_position(text: &str) -> Option<(i32, i32)> {
+    let (x, y) = text.split_once(',')?;
+    Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::{FilterKind, Weighting};
+
+    #[test]
+    fn test_preferences_round_trip_through_text() {
+        let mut preferences = Preferences {
+            input_device: Some("USB Audio = CODEC".to_string()),
+            sample_rate: Some(96000),
+            input_channels: Some(1),
+            buffer_size: Some(128),
+            theme: DisplayTheme::Amber,
+            refresh_rate: RefreshRate::Uncapped,
+            trace_colors: TraceColors {
+                trace: None,
+                math: Some([1.0, 0.0, 0.2, 1.0]),
+            },
+            input_label: Some("DI box".to_string()),
+            time_per_division: 0.0005,
+            persistence_enabled: false,
+            persistence_frames: 25,
+            persistence_mode: PersistenceMode::Fade,
+            persistence_decay: PersistenceDecay::TwoSeconds,
+            persistence_colormap: PersistenceColormap::Heat,
+            filters: vec![
+                FilterBlock::new(FilterKind::HighPass {
+                    frequency: 80.0,
+                    q: 0.5,
+                }),
+                FilterBlock {
+                    kind: FilterKind::Weighting(Weighting::A),
+                    bypassed: true,
+                },
+            ],
+            ..Preferences::default()
+        };
+        preferences.bind_key("toggle_trigger", Some('X'));
+        preferences.bind_key("cycle_theme", Some('k'));
+        preferences.bind_key("cycle_theme", None);
+
+        let parsed = Preferences::parse(&preferences.to_text());
+        assert_eq!(parsed, preferences);
+        assert_eq!(parsed.key("toggle_trigger"), Some('x'));
+        assert_eq!(parsed.key("cycle_theme"), None);
+    }
+
+    #[test]
+    fn test_broken_lines_keep_defaults() {
+        let parsed = Preferences::parse(
+            "# comment\n\
+             theme = Mauve\n\
+             trace_color = #12345\n\
+             sample_rate = -1\n\
+             buffer_size = 0\n\
+             time_per_division = 0\n\
+             persistence_frames = 5000\n\
+             key.toggle_trigger = xy\n\
+             nonsense\n\
+             filter = shelf 100 1\n\
+             persistence_mode = fade\n\
+             refresh_rate = 45 Hz\n\
+             persistence_decay = 3 s\n",
+        );
+        assert_eq!(parsed.theme, DisplayTheme::default());
+        assert_eq!(parsed.trace_colors, TraceColors::default());
+        assert_eq!(parsed.sample_rate, None);
+        assert_eq!(parsed.buffer_size, None);
+        assert_eq!(parsed.time_per_division, 0.001);
+        assert_eq!(parsed.persistence_frames, MAX_PERSISTENCE_FRAMES);
+        assert_eq!(parsed.key("toggle_trigger"), None);
+        assert_eq!(parsed.persistence_mode, PersistenceMode::Fade);
+        assert_eq!(parsed.refresh_rate, RefreshRate::Hz60);
+        assert_eq!(parsed.persistence_decay, PersistenceDecay::Frames);
+        assert!(parsed.filters.is_empty());
+    }
+
+    #[test]
+    fn test_window_settings_round_trip_through_text() {
+        let settings = WindowSettings {
+            title: Some("Bench = scope".to_string()),
+            size: (1920, 1080),
+            position: Some((-1280, 40)),
+            monitor: Some("DELL U2720Q".to_string()),
+            decorations: false,
+        };
+        assert_eq!(WindowSettings::parse(&settings.to_text()), settings);
+
+        let parsed = WindowSettings::parse("size = 0x720\nposition = 10\ndecorations = maybe\n");
+        assert_eq!(parsed, WindowSettings::default());
+        assert_eq!(parse_size("800 x 600"), Some((800, 600)));
+    }
+}
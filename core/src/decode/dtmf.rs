@@ -0,0 +1,188 @@
+use super::goertzel::{goertzel_power, mean_square};
+use super::{DecodedEvent, Decoder};
+
+pub const ROW_FREQUENCIES: [f32; 4] = [697.0, 770.0, 852.0, 941.0];
+pub const COLUMN_FREQUENCIES: [f32; 4] = [1209.0, 1336.0, 1477.0, 1633.0];
+
+const KEYPAD: [[char; 4]; 4] = [
+    ['1', '2', '3', 'A'],
+    ['4', '5', '6', 'B'],
+    ['7', '8', '9', 'C'],
+    ['*', '0', '#', 'D'],
+];
+
+const BLOCK_SECONDS: f32 = 0.02; // 50 Hz bin spacing separates adjacent tones
+const MIN_LEVEL: f32 = 1e-5; // ignore blocks quieter than about -50 dBFS
+const MIN_TONE_FRACTION: f32 = 0.6; // the tone pair must carry most of the energy
+const MAX_TWIST: f32 = 6.3; // 8 dB allowed difference between row and column
+const MIN_PEAK_RATIO: f32 = 4.0; // winning tone must beat others in its group by 6 dB
+
+/// Dual-tone multi-frequency detector.
+///
+/// Splits the input into 20 ms blocks, runs a Goertzel filter for each of the
+/// eight keypad tones and reports a digit for every run of blocks carrying a
+/// valid row/column pair.
+#[derive(Default)]
+pub struct DtmfDecoder;
+
+impl DtmfDecoder {
+    pub fn new() -> Self {
+        DtmfDecoder
+    }
+
+    fn block_samples(sample_rate: u32) -> usize {
+        ((sample_rate as f32 * BLOCK_SECONDS) as usize).max(1)
+    }
+
+    /// Detect a digit in a single block
+    pub fn detect(block: &[f32], sample_rate: u32) -> Option<char> {
+        let energy = mean_square(block);
+        if energy < MIN_LEVEL {
+            return None;
+        }
+
+        let (row, row_power) = strongest(block, &ROW_FREQUENCIES, sample_rate)?;
+        let (col, col_power) = strongest(block, &COLUMN_FREQUENCIES, sample_rate)?;
+
+        let twist = row_power / col_power;
+        let valid = row_power + col_power >= MIN_TONE_FRACTION * energy
+            && (1.0 / MAX_TWIST..=MAX_TWIST).contains(&twist);
+
+        valid.then_some(KEYPAD[row][col])
+    }
+}
+
+/// Strongest tone in a group, if it clearly dominates the others
+fn strongest(block: &[f32], frequencies: &[f32; 4], sample_rate: u32) -> Option<(usize, f32)> {
+    let powers: Vec<f32> = frequencies
+        .iter()
+        .map(|&f| goertzel_power(block, f, sample_rate))
+        .collect();
+
+    let (best, &best_power) = powers
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))?;
+
+    let dominant = powers
+        .iter()
+        .enumerate()
+        .all(|(i, &p)| i == best || p * MIN_PEAK_RATIO <= best_power);
+
+    dominant.then_some((best, best_power))
+}
+
+impl Decoder for DtmfDecoder {
+    fn name(&self) -> &'static str {
+        "DTMF"
+    }
+
+    fn max_symbol_samples(&self, sample_rate: u32) -> usize {
+        // A digit is only final once a following block confirms the tone stopped
+        2 * Self::block_samples(sample_rate)
+    }
+
+    fn decode(&self, samples: &[f32], sample_rate: u32) -> Vec<DecodedEvent> {
+        let block_samples = Self::block_samples(sample_rate);
+        let mut events: Vec<DecodedEvent> = Vec::new();
+        let mut previous: Option<char> = None;
+
+        for (index, block) in samples.chunks_exact(block_samples).enumerate() {
+            let digit = Self::detect(block, sample_rate);
+            let start = index * block_samples;
+
+            match (digit, previous) {
+                // Tone continues: extend the current event
+                (Some(d), Some(p)) if d == p => {
+                    if let Some(event) = events.last_mut() {
+                        event.end = start + block_samples;
+                    }
+                }
+                (Some(d), _) => events.push(DecodedEvent {
+                    start,
+                    end: start + block_samples,
+                    value: d as u32,
+                    label: d.to_string(),
+                    error: false,
+                }),
+                (None, _) => {}
+            }
+            previous = digit;
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::StreamDecoder;
+    use std::f32::consts::PI;
+
+    fn tone_pair(digit: char, seconds: f32, sample_rate: u32) -> Vec<f32> {
+        let (row, col) = (0..16)
+            .map(|i| (i / 4, i % 4))
+            .find(|&(r, c)| KEYPAD[r][c] == digit)
+            .unwrap();
+        let (f1, f2) = (ROW_FREQUENCIES[row], COLUMN_FREQUENCIES[col]);
+
+        (0..(seconds * sample_rate as f32) as usize)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                0.3 * (2.0 * PI * f1 * t).sin() + 0.3 * (2.0 * PI * f2 * t).sin()
+            })
+            .collect()
+    }
+
+    fn sequence(digits: &str, sample_rate: u32) -> Vec<f32> {
+        let silence = vec![0.0; (0.05 * sample_rate as f32) as usize];
+        let mut signal = silence.clone();
+        for digit in digits.chars() {
+            signal.extend(tone_pair(digit, 0.08, sample_rate));
+            signal.extend(&silence);
+        }
+        signal
+    }
+
+    #[test]
+    fn test_detect_every_key() {
+        for row in KEYPAD {
+            for digit in row {
+                let block = tone_pair(digit, 0.02, 48000);
+                assert_eq!(DtmfDecoder::detect(&block, 48000), Some(digit));
+            }
+        }
+    }
+
+    #[test]
+    fn test_rejects_single_tone_and_silence() {
+        let single: Vec<f32> = (0..960)
+            .map(|i| (2.0 * PI * 697.0 * i as f32 / 48000.0).sin())
+            .collect();
+        assert_eq!(DtmfDecoder::detect(&single, 48000), None);
+        assert_eq!(DtmfDecoder::detect(&[0.0; 960], 48000), None);
+    }
+
+    #[test]
+    fn test_decode_sequence() {
+        let signal = sequence("5551234#", 48000);
+        let events = DtmfDecoder::new().decode(&signal, 48000);
+
+        let digits: String = events.iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(digits, "5551234#");
+    }
+
+    #[test]
+    fn test_stream_reports_each_digit_once() {
+        let signal = sequence("19*", 8000);
+        let mut stream = StreamDecoder::new(Box::new(DtmfDecoder::new()), 8000);
+
+        let digits: String = signal
+            .chunks(100)
+            .flat_map(|block| stream.push(block))
+            .map(|e| e.label)
+            .collect();
+        assert_eq!(digits, "19*");
+    }
+}
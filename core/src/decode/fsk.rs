@@ -0,0 +1,592 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+use super::uart::{UartConfig, UartDecoder};
+use super::{DecodedEvent, Decoder, IncrementalDecoder, StreamDecoder};
+
+const HDLC_FLAG: u8 = 0x7E;
+const MAX_FRAME_BYTES: usize = 330; // AX.25 maximum including FCS
+const MIN_FRAME_BYTES: usize = 3; // at least one byte plus the 16-bit FCS
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FskFraming {
+    /// Start/stop bits around each byte, as used by Bell 103 style modems
+    Async,
+    /// NRZI with HDLC flags and bit stuffing, as used by AX.25/APRS
+    Hdlc,
+}
+
+#[derive(Debug, Clone)]
+pub struct FskConfig {
+    pub mark_hz: f32,  // logic 1
+    pub space_hz: f32, // logic 0
+    pub baud_rate: u32,
+    pub framing: FskFraming,
+}
+
+impl FskConfig {
+    /// Bell 202 AFSK at 1200 baud (APRS / packet radio)
+    pub fn bell_202() -> Self {
+        FskConfig {
+            mark_hz: 1200.0,
+            space_hz: 2200.0,
+            baud_rate: 1200,
+            framing: FskFraming::Hdlc,
+        }
+    }
+
+    /// Bell 103 originate channel at 300 baud
+    pub fn bell_103() -> Self {
+        FskConfig {
+            mark_hz: 1270.0,
+            space_hz: 1070.0,
+            baud_rate: 300,
+            framing: FskFraming::Async,
+        }
+    }
+
+    fn bit_samples(&self, sample_rate: u32) -> f32 {
+        sample_rate as f32 / self.baud_rate as f32
+    }
+}
+
+impl Default for FskConfig {
+    fn default() -> Self {
+        Self::bell_202()
+    }
+}
+
+/// Non-coherent FSK demodulator with bit clock recovery.
+///
+/// Mark and space energy are measured over a sliding one-bit window; the sign
+/// of their difference is the recovered NRZ signal. Bits are sampled half a
+/// bit period after each transition and then every bit period until the next.
+pub struct FskDecoder {
+    pub config: FskConfig,
+}
+
+impl FskDecoder {
+    pub fn new(config: FskConfig) -> Self {
+        FskDecoder { config }
+    }
+
+    /// Discriminator output per sample: positive for mark, negative for space,
+    /// normalized to -1.0..=1.0
+    pub fn demodulate(&self, samples: &[f32], sample_rate: u32) -> Vec<f32> {
+        let mut output = Vec::with_capacity(samples.len());
+        Discriminator::new(&self.config, sample_rate).push(samples, &mut output);
+        // The last half window never has its centre reached
+        output.resize(samples.len(), 0.0);
+        output
+    }
+
+    /// Recovered bits as (sample position, value), mark = true
+    pub fn recover_bits(&self, samples: &[f32], sample_rate: u32) -> Vec<(usize, bool)> {
+        let mut clock = BitClock::new(self.config.bit_samples(sample_rate));
+        self.demodulate(samples, sample_rate)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(position, value)| Some((position, clock.next(value)?)))
+            .collect()
+    }
+
+    fn decode_hdlc(&self, samples: &[f32], sample_rate: u32) -> Vec<DecodedEvent> {
+        let mut deframer = HdlcDeframer::new(self.config.bit_samples(sample_rate));
+        let mut events = Vec::new();
+        for (position, level) in self.recover_bits(samples, sample_rate) {
+            deframer.push(position, level, &mut events);
+        }
+        events
+    }
+
+    /// The discriminator output is a clean NRZ signal with mark idle high
+    fn uart(&self) -> UartDecoder {
+        UartDecoder::new(UartConfig {
+            baud_rate: self.config.baud_rate,
+            ..Default::default()
+        })
+    }
+}
+
+/// The discriminator run a sample at a time, so a stream is demodulated once
+/// however much of it the decoder still holds. Each output is for the sample
+/// at the centre of the window, so it lags the input by half a bit.
+struct Discriminator {
+    window: usize,
+    /// Mark and space mixer steps, radians per sample
+    steps: (f32, f32),
+    phases: (f32, f32),
+    /// Mixer outputs over the window, oldest first: mark I and Q, then
+    /// space I and Q
+    products: VecDeque<[f32; 4]>,
+    sums: [f32; 4],
+    samples_in: usize,
+}
+
+impl Discriminator {
+    fn new(config: &FskConfig, sample_rate: u32) -> Self {
+        let window = (config.bit_samples(sample_rate).round() as usize).max(1);
+        let step = |frequency: f32| 2.0 * PI * frequency / sample_rate as f32;
+        Discriminator {
+            window,
+            steps: (step(config.mark_hz), step(config.space_hz)),
+            phases: (0.0, 0.0),
+            products: VecDeque::with_capacity(window + 1),
+            sums: [0.0; 4],
+            samples_in: 0,
+        }
+    }
+
+    /// Append the output for each sample whose window is now complete
+    fn push(&mut self, samples: &[f32], output: &mut Vec<f32>) {
+        let half = self.window / 2;
+        for &x in samples {
+            let (mark, space) = self.phases;
+            let product = [
+                x * mark.cos(),
+                -x * mark.sin(),
+                x * space.cos(),
+                -x * space.sin(),
+            ];
+            self.phases = (
+                (mark + self.steps.0) % (2.0 * PI),
+                (space + self.steps.1) % (2.0 * PI),
+            );
+
+            add(&mut self.sums, product, 1.0);
+            self.products.push_back(product);
+            if self.products.len() > self.window {
+                if let Some(oldest) = self.products.pop_front() {
+                    add(&mut self.sums, oldest, -1.0);
+                }
+            }
+            self.samples_in += 1;
+            // Summed afresh once a window, so rounding can't build up over
+            // a long stream
+            if self.samples_in.is_multiple_of(self.window) {
+                self.sums = [0.0; 4];
+                for &product in &self.products {
+                    add(&mut self.sums, product, 1.0);
+                }
+            }
+
+            if self.samples_in > half {
+                let m = self.sums[0].hypot(self.sums[1]);
+                let s = self.sums[2].hypot(self.sums[3]);
+                output.push((m - s) / (m + s + f32::EPSILON));
+            }
+        }
+    }
+}
+
+fn add(sums: &mut [f32; 4], values: [f32; 4], sign: f32) {
+    for (sum, value) in sums.iter_mut().zip(values) {
+        *sum += sign * value;
+    }
+}
+
+/// Takes bits from the discriminator output half a bit period after each
+/// transition, and then every bit period until the next
+struct BitClock {
+    period: f32,
+    level: Option<bool>,
+    /// Samples until the next bit is taken, once there's been a transition
+    until_bit: Option<f32>,
+}
+
+impl BitClock {
+    fn new(period: f32) -> Self {
+        BitClock {
+            period,
+            level: None,
+            until_bit: None,
+        }
+    }
+
+    /// The bit taken at the next discriminator output, if any, mark = true
+    fn next(&mut self, value: f32) -> Option<bool> {
+        let level = value > 0.0;
+        let previous = self.level.replace(level)?;
+
+        // Resynchronise the bit clock on every transition
+        if level != previous {
+            self.until_bit = Some(self.period / 2.0);
+        } else if let Some(until) = self.until_bit.as_mut() {
+            *until -= 1.0;
+        }
+
+        let until = self.until_bit.as_mut()?;
+        if *until > 0.0 {
+            return None;
+        }
+        *until += self.period;
+        Some(level)
+    }
+}
+
+/// Unstuffs NRZI bits into HDLC frames, a bit at a time
+struct HdlcDeframer {
+    half_bit: usize,
+    previous_level: bool,
+    shift: u8,
+    ones: u32,
+    in_frame: bool,
+    frame_bits: Vec<(usize, bool)>,
+}
+
+impl HdlcDeframer {
+    fn new(bit_samples: f32) -> Self {
+        HdlcDeframer {
+            half_bit: (bit_samples / 2.0) as usize,
+            previous_level: true,
+            shift: 0,
+            ones: 0,
+            in_frame: false,
+            frame_bits: Vec::new(),
+        }
+    }
+
+    /// Take the line level sampled at `position`, adding a frame's bytes to
+    /// `events` once its closing flag is in
+    fn push(&mut self, position: usize, level: bool, events: &mut Vec<DecodedEvent>) {
+        // NRZI: no transition is a 1, a transition is a 0
+        let bit = level == self.previous_level;
+        self.previous_level = level;
+
+        self.shift = (self.shift >> 1) | ((bit as u8) << 7);
+        if self.shift == HDLC_FLAG {
+            if self.in_frame {
+                // The flag's first seven bits were collected as data
+                let frame_len = self.frame_bits.len().saturating_sub(7);
+                self.frame_bits.truncate(frame_len);
+                events.extend(frame_events(&self.frame_bits, self.half_bit));
+            }
+            self.in_frame = true;
+            self.frame_bits.clear();
+            self.ones = 0;
+            return;
+        }
+
+        if !self.in_frame {
+            return;
+        }
+
+        if bit {
+            self.ones += 1;
+            if self.ones > 6 || self.frame_bits.len() > MAX_FRAME_BYTES * 8 {
+                // Abort sequence or runaway frame
+                self.in_frame = false;
+                self.frame_bits.clear();
+            }
+        } else {
+            let stuffed = self.ones == 5;
+            self.ones = 0;
+            if stuffed {
+                return;
+            }
+        }
+
+        if self.in_frame {
+            self.frame_bits.push((position, bit));
+        }
+    }
+}
+
+/// `FskDecoder` over a stream, each sample demodulated and framed once
+struct FskStream {
+    discriminator: Discriminator,
+    /// The discriminator's output for the latest block
+    output: Vec<f32>,
+    framing: StreamFraming,
+}
+
+enum StreamFraming {
+    /// Async bytes are short, so their NRZ signal is streamed to the UART
+    /// decoder like any other input
+    Async(StreamDecoder),
+    Hdlc {
+        clock: BitClock,
+        deframer: HdlcDeframer,
+        /// Position of the next discriminator output
+        position: usize,
+    },
+}
+
+impl IncrementalDecoder for FskStream {
+    fn push(&mut self, samples: &[f32]) -> Vec<DecodedEvent> {
+        self.output.clear();
+        self.discriminator.push(samples, &mut self.output);
+        match &mut self.framing {
+            StreamFraming::Async(uart) => uart.push(&self.output),
+            StreamFraming::Hdlc {
+                clock,
+                deframer,
+                position,
+            } => {
+                let mut events = Vec::new();
+                for &value in &self.output {
+                    if let Some(level) = clock.next(value) {
+                        deframer.push(*position, level, &mut events);
+                    }
+                    *position += 1;
+                }
+                events
+            }
+        }
+    }
+}
+
+/// Pack unstuffed frame bits into byte events, checking the trailing FCS
+fn frame_events(bits: &[(usize, bool)], half_bit: usize) -> Vec<DecodedEvent> {
+    let bytes: Vec<(usize, usize, u8)> = bits
+        .chunks_exact(8)
+        .map(|chunk| {
+            let value = chunk
+                .iter()
+                .enumerate()
+                .fold(0u8, |acc, (i, &(_, bit))| acc | ((bit as u8) << i));
+            (
+                chunk[0].0.saturating_sub(half_bit),
+                chunk[7].0 + half_bit,
+                value,
+            )
+        })
+        .collect();
+
+    if bytes.len() < MIN_FRAME_BYTES {
+        return Vec::new();
+    }
+
+    let values: Vec<u8> = bytes.iter().map(|&(_, _, v)| v).collect();
+    let (payload, fcs) = values.split_at(values.len() - 2);
+    let fcs_ok = crc16_x25(payload) == u16::from_le_bytes([fcs[0], fcs[1]]);
+
+    bytes
+        .into_iter()
+        .map(|(start, end, value)| DecodedEvent {
+            start,
+            end,
+            value: value as u32,
+            label: format!("{value:02X}"),
+            error: !fcs_ok,
+        })
+        .collect()
+}
+
+/// CRC-16/X.25 as used for the AX.25 frame check sequence
+pub fn crc16_x25(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0x8408
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+impl Decoder for FskDecoder {
+    fn name(&self) -> &'static str {
+        "FSK"
+    }
+
+    fn max_symbol_samples(&self, sample_rate: u32) -> usize {
+        let bits = match self.config.framing {
+            FskFraming::Async => 10,
+            // Largest frame with worst-case bit stuffing plus both flags
+            FskFraming::Hdlc => MAX_FRAME_BYTES * 8 * 6 / 5 + 16,
+        };
+        (bits as f32 * self.config.bit_samples(sample_rate)).ceil() as usize
+    }
+
+    fn decode(&self, samples: &[f32], sample_rate: u32) -> Vec<DecodedEvent> {
+        match self.config.framing {
+            FskFraming::Async => self
+                .uart()
+                .decode(&self.demodulate(samples, sample_rate), sample_rate),
+            FskFraming::Hdlc => self.decode_hdlc(samples, sample_rate),
+        }
+    }
+
+    /// HDLC frames run to tens of thousands of samples, too many to
+    /// demodulate again on every block
+    fn incremental(&self, sample_rate: u32) -> Option<Box<dyn IncrementalDecoder>> {
+        let framing = match self.config.framing {
+            FskFraming::Async => {
+                StreamFraming::Async(StreamDecoder::new(Box::new(self.uart()), sample_rate))
+            }
+            FskFraming::Hdlc => StreamFraming::Hdlc {
+                clock: BitClock::new(self.config.bit_samples(sample_rate)),
+                deframer: HdlcDeframer::new(self.config.bit_samples(sample_rate)),
+                position: 0,
+            },
+        };
+        Some(Box::new(FskStream {
+            discriminator: Discriminator::new(&self.config, sample_rate),
+            output: Vec::new(),
+            framing,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Continuous-phase FSK modulation of a bit sequence
+    fn modulate(bits: &[bool], config: &FskConfig, sample_rate: u32) -> Vec<f32> {
+        let bit_samples = config.bit_samples(sample_rate);
+        let total = (bits.len() as f32 * bit_samples) as usize;
+        let mut phase = 0.0_f32;
+
+        (0..total)
+            .map(|i| {
+                let bit = bits[((i as f32 / bit_samples) as usize).min(bits.len() - 1)];
+                let frequency = if bit { config.mark_hz } else { config.space_hz };
+                phase += 2.0 * PI * frequency / sample_rate as f32;
+                0.5 * phase.sin()
+            })
+            .collect()
+    }
+
+    /// HDLC-frame a payload (appending its FCS) into NRZI line levels
+    fn hdlc_levels(payload: &[u8]) -> Vec<bool> {
+        let mut frame = payload.to_vec();
+        frame.extend(crc16_x25(payload).to_le_bytes());
+
+        let flag: Vec<bool> = (0..8).map(|i| HDLC_FLAG & (1 << i) != 0).collect();
+        let mut data_bits = Vec::new();
+        for _ in 0..4 {
+            data_bits.extend(&flag);
+        }
+
+        let mut ones = 0;
+        for byte in frame {
+            for i in 0..8 {
+                let bit = byte & (1 << i) != 0;
+                data_bits.push(bit);
+                ones = if bit { ones + 1 } else { 0 };
+                if ones == 5 {
+                    data_bits.push(false);
+                    ones = 0;
+                }
+            }
+        }
+        for _ in 0..4 {
+            data_bits.extend(&flag);
+        }
+
+        // NRZI encode: a 0 toggles the line
+        let mut level = true;
+        data_bits
+            .iter()
+            .map(|&bit| {
+                if !bit {
+                    level = !level;
+                }
+                level
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_crc16_x25_check_value() {
+        assert_eq!(crc16_x25(b"123456789"), 0x906E);
+    }
+
+    #[test]
+    fn test_demodulate_mark_and_space() {
+        let config = FskConfig::bell_202();
+        let decoder = FskDecoder::new(config.clone());
+        let signal = modulate(&[true; 20], &config, 48000);
+        let discriminator = decoder.demodulate(&signal, 48000);
+        assert!(discriminator[200..600].iter().all(|&d| d > 0.5));
+
+        let signal = modulate(&[false; 20], &config, 48000);
+        let discriminator = decoder.demodulate(&signal, 48000);
+        assert!(discriminator[200..600].iter().all(|&d| d < -0.5));
+    }
+
+    #[test]
+    fn test_demodulate_in_blocks() {
+        let config = FskConfig::bell_202();
+        let signal = modulate(&hdlc_levels(b"blocks"), &config, 48000);
+        let whole = FskDecoder::new(config.clone()).demodulate(&signal, 48000);
+
+        let mut discriminator = Discriminator::new(&config, 48000);
+        let mut blocks = Vec::new();
+        for block in signal.chunks(333) {
+            discriminator.push(block, &mut blocks);
+        }
+        // Only the last half window is still to come
+        assert_eq!(blocks.len(), signal.len() - 20);
+        assert_eq!(blocks, whole[..blocks.len()]);
+    }
+
+    #[test]
+    fn test_decode_bell_202_frame() {
+        let config = FskConfig::bell_202();
+        let payload = b"OZ>APRS:hello, scope";
+        let signal = modulate(&hdlc_levels(payload), &config, 48000);
+
+        let events = FskDecoder::new(config).decode(&signal, 48000);
+        let bytes: Vec<u8> = events.iter().map(|e| e.value as u8).collect();
+
+        assert_eq!(&bytes[..payload.len()], payload);
+        assert_eq!(bytes.len(), payload.len() + 2);
+        assert!(events.iter().all(|e| !e.error));
+        assert!(events.windows(2).all(|w| w[0].start < w[1].start));
+    }
+
+    #[test]
+    fn test_decode_bell_103_async() {
+        let config = FskConfig::bell_103();
+        let mut bits = vec![true; 10];
+        for &byte in b"OK" {
+            bits.push(false);
+            bits.extend((0..8).map(|i| byte & (1 << i) != 0));
+            bits.extend([true; 3]);
+        }
+        bits.extend([true; 10]);
+
+        let signal = modulate(&bits, &config, 48000);
+        let events = FskDecoder::new(config.clone()).decode(&signal, 48000);
+        let text: String = events.iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(text, "OK");
+
+        // Streamed, the same bytes at the same positions
+        let mut stream = StreamDecoder::new(Box::new(FskDecoder::new(config)), 48000);
+        let mut streamed = Vec::new();
+        for block in signal.chunks(500) {
+            streamed.extend(stream.push(block));
+        }
+        assert_eq!(streamed, events);
+    }
+
+    #[test]
+    fn test_stream_decodes_frame_once() {
+        let config = FskConfig::bell_202();
+        let mut levels = hdlc_levels(b"first");
+        levels.extend(hdlc_levels(b"second"));
+        let signal = modulate(&levels, &config, 48000);
+
+        let mut stream = StreamDecoder::new(Box::new(FskDecoder::new(config)), 48000);
+        let mut bytes = Vec::new();
+        for block in signal.chunks(800) {
+            bytes.extend(stream.push(block).iter().map(|e| e.value as u8));
+        }
+        // Flush with idle flags so the last frame is confirmed
+        let idle = modulate(&[true; 4000], &FskConfig::bell_202(), 48000);
+        bytes.extend(stream.push(&idle).iter().map(|e| e.value as u8));
+
+        let mut expected = b"first".to_vec();
+        expected.extend(crc16_x25(b"first").to_le_bytes());
+        expected.extend(b"second");
+        expected.extend(crc16_x25(b"second").to_le_bytes());
+        assert_eq!(bytes, expected);
+    }
+}
@@ -0,0 +1,56 @@
+use std::f32::consts::PI;
+
+/// Power of a single frequency component using the Goertzel algorithm.
+///
+/// Normalized so a sine of amplitude `A` at exactly `frequency` yields `A² / 2`,
+/// the same scale as the mean square of the block.
+pub fn goertzel_power(samples: &[f32], frequency: f32, sample_rate: u32) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+
+    let coeff = 2.0 * (2.0 * PI * frequency / sample_rate as f32).cos();
+    let (mut s1, mut s2) = (0.0_f32, 0.0_f32);
+
+    for &sample in samples {
+        let s0 = sample + coeff * s1 - s2;
+        s2 = s1;
+        s1 = s0;
+    }
+
+    let power = s1 * s1 + s2 * s2 - coeff * s1 * s2;
+    let n = samples.len() as f32;
+    2.0 * power / (n * n)
+}
+
+/// Mean square of a block, for comparing against [`goertzel_power`]
+pub fn mean_square(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples.iter().map(|&x| x * x).sum::<f32>() / samples.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_goertzel_matches_sine_power() {
+        let samples: Vec<f32> = (0..960)
+            .map(|i| 0.5 * (2.0 * PI * 1000.0 * i as f32 / 48000.0).sin())
+            .collect();
+
+        let on = goertzel_power(&samples, 1000.0, 48000);
+        let off = goertzel_power(&samples, 1500.0, 48000);
+
+        assert!((on - 0.125).abs() < 0.005, "Expected ~0.125, got {on}");
+        assert!(off < 0.001);
+        assert!((mean_square(&samples) - 0.125).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_goertzel_empty() {
+        assert_eq!(goertzel_power(&[], 1000.0, 48000), 0.0);
+    }
+}
@@ -0,0 +1,47 @@
+pub mod dtmf;
+pub mod fsk;
+pub mod goertzel;
+pub mod stream;
+pub mod uart;
+
+pub use dtmf::DtmfDecoder;
+pub use fsk::{FskConfig, FskDecoder, FskFraming};
+pub use stream::StreamDecoder;
+pub use uart::{Parity, UartConfig, UartDecoder};
+
+/// A decoded symbol positioned on the sample timeline
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedEvent {
+    pub start: usize, // index of the first sample of the symbol
+    pub end: usize,   // index one past the last sample of the symbol
+    pub value: u32,
+    pub label: String,
+    pub error: bool, // framing/parity error or otherwise unreliable decode
+}
+
+/// Turns a block of samples into decoded symbols.
+///
+/// Decoders are stateless over a slice; [`StreamDecoder`] handles symbols that
+/// straddle block boundaries for live input.
+pub trait Decoder: Send {
+    fn name(&self) -> &'static str;
+
+    /// Longest symbol in samples, used to decide how much input to carry over
+    fn max_symbol_samples(&self, sample_rate: u32) -> usize;
+
+    fn decode(&self, samples: &[f32], sample_rate: u32) -> Vec<DecodedEvent>;
+
+    /// A decoder that keeps its state from block to block, for those too
+    /// costly to run over all the input [`StreamDecoder`] carries over. Given
+    /// one, the stream hands it each block once instead.
+    fn incremental(&self, _sample_rate: u32) -> Option<Box<dyn IncrementalDecoder>> {
+        None
+    }
+}
+
+/// Live input decoded a block at a time, see [`Decoder::incremental`]
+pub trait IncrementalDecoder: Send {
+    /// Decode the next block, returning the symbols it completes, positioned
+    /// in samples since the stream started
+    fn push(&mut self, samples: &[f32]) -> Vec<DecodedEvent>;
+}
@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+
+use super::{DecodedEvent, Decoder, IncrementalDecoder};
+
+const DEFAULT_HISTORY: usize = 256;
+
+/// Feeds a [`Decoder`] from a live stream.
+///
+/// Unfinished input is carried over between calls so symbols split across
+/// capture blocks are decoded exactly once; decoders with their own
+/// incremental state are handed each block instead. Event positions are
+/// absolute sample counts since the stream started.
+pub struct StreamDecoder {
+    decoder: Box<dyn Decoder>,
+    sample_rate: u32,
+    pending: Vec<f32>,
+    offset: usize,         // absolute position of pending[0]
+    reported_until: usize, // absolute end of the last reported symbol
+    history: VecDeque<DecodedEvent>,
+    history_len: usize,
+    /// Takes the place of `pending` when the decoder has one
+    incremental: Option<Box<dyn IncrementalDecoder>>,
+}
+
+impl StreamDecoder {
+    pub fn new(decoder: Box<dyn Decoder>, sample_rate: u32) -> Self {
+        StreamDecoder {
+            incremental: decoder.incremental(sample_rate),
+            decoder,
+            sample_rate,
+            pending: Vec::new(),
+            offset: 0,
+            reported_until: 0,
+            history: VecDeque::new(),
+            history_len: DEFAULT_HISTORY,
+        }
+    }
+
+    pub fn decoder(&self) -> &dyn Decoder {
+        self.decoder.as_ref()
+    }
+
+    /// Decode newly captured samples, returning the symbols completed by them
+    pub fn push(&mut self, samples: &[f32]) -> Vec<DecodedEvent> {
+        let events = match self.incremental.as_mut() {
+            Some(incremental) => incremental.push(samples),
+            None => self.decode_pending(samples),
+        };
+
+        for event in &events {
+            self.history.push_back(event.clone());
+        }
+        while self.history.len() > self.history_len {
+            self.history.pop_front();
+        }
+
+        events
+    }
+
+    /// Decode the carried-over input with `samples` added, reporting only
+    /// the symbols not reported before
+    fn decode_pending(&mut self, samples: &[f32]) -> Vec<DecodedEvent> {
+        self.pending.extend_from_slice(samples);
+
+        let max_symbol = self.decoder.max_symbol_samples(self.sample_rate);
+        let final_before = self.pending.len().saturating_sub(max_symbol);
+        let reported = self.reported_until.saturating_sub(self.offset);
+
+        // A symbol is final once a full symbol length of input follows its end;
+        // later ones may still be growing and are decoded again next time
+        let events: Vec<DecodedEvent> = self
+            .decoder
+            .decode(&self.pending, self.sample_rate)
+            .into_iter()
+            .filter(|event| event.start >= reported && event.end <= final_before)
+            .map(|mut event| {
+                event.start += self.offset;
+                event.end += self.offset;
+                event
+            })
+            .collect();
+
+        if let Some(last) = events.last() {
+            self.reported_until = last.end;
+        }
+
+        // Resume just before the last reported symbol ended so the next leading edge
+        // is seen again, but never hold more than two symbol lengths of input
+        let resume = self
+            .reported_until
+            .saturating_sub(self.offset)
+            .saturating_sub(1);
+        let consumed = resume
+            .max(self.pending.len().saturating_sub(2 * max_symbol))
+            .min(self.pending.len());
+
+        self.pending.drain(0..consumed);
+        self.offset += consumed;
+
+        events
+    }
+
+    /// Most recently decoded symbols, oldest first
+    pub fn history(&self) -> &VecDeque<DecodedEvent> {
+        &self.history
+    }
+
+    /// Start over as a new stream, positions counting from zero again
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.history.clear();
+        self.offset = 0;
+        self.reported_until = 0;
+        self.incremental = self.decoder.incremental(self.sample_rate);
+    }
+}
@@ -0,0 +1,352 @@
+use super::{DecodedEvent, Decoder};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Parity {
+    None,
+    Even,
+    Odd,
+}
+
+#[derive(Debug, Clone)]
+pub struct UartConfig {
+    pub baud_rate: u32,
+    pub data_bits: u8, // 5 to 8
+    pub parity: Parity,
+    pub stop_bits: u8,  // 1 or 2
+    pub threshold: f32, // logic threshold in volts
+    pub inverted: bool, // RS-232 polarity: idle low instead of idle high
+}
+
+impl Default for UartConfig {
+    fn default() -> Self {
+        UartConfig {
+            baud_rate: 9600,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: 1,
+            threshold: 0.0,
+            inverted: false,
+        }
+    }
+}
+
+/// Common baud rates for stepping through in the UI
+pub const STANDARD_BAUD_RATES: [u32; 9] =
+    [300, 1200, 2400, 4800, 9600, 19200, 38400, 57600, 115200];
+
+/// Fewest samples per bit a rate is offered at; below this, mid-bit
+/// sampling lands on the neighbouring bit too often to decode
+const MIN_BIT_SAMPLES: f32 = 2.0;
+
+/// The standard baud rates that can be decoded at `sample_rate`, slowest
+/// first. The slowest is always offered, however low the rate.
+pub fn decodable_baud_rates(sample_rate: u32) -> Vec<u32> {
+    let mut rates: Vec<u32> = STANDARD_BAUD_RATES
+        .iter()
+        .copied()
+        .filter(|&baud| sample_rate as f32 / baud as f32 >= MIN_BIT_SAMPLES)
+        .collect();
+    if rates.is_empty() {
+        rates.push(STANDARD_BAUD_RATES[0]);
+    }
+    rates
+}
+
+impl UartConfig {
+    fn frame_bits(&self) -> usize {
+        let parity_bits = if self.parity == Parity::None { 0 } else { 1 };
+        1 + self.data_bits as usize + parity_bits + self.stop_bits as usize
+    }
+
+    /// Step to the next/previous standard baud rate decodable at `sample_rate`
+    pub fn step_baud_rate(&mut self, up: bool, sample_rate: u32) {
+        let rates = decodable_baud_rates(sample_rate);
+        let index = rates
+            .iter()
+            .position(|&b| b >= self.baud_rate)
+            .unwrap_or(rates.len() - 1);
+
+        let index = if up {
+            (index + 1).min(rates.len() - 1)
+        } else {
+            index.saturating_sub(1)
+        };
+        self.baud_rate = rates[index];
+    }
+
+    /// Drop to the fastest standard rate decodable at `sample_rate` if the
+    /// current one is too fast for it
+    pub fn fit_baud_rate(&mut self, sample_rate: u32) {
+        let fastest = decodable_baud_rates(sample_rate)
+            .last()
+            .copied()
+            .unwrap_or(STANDARD_BAUD_RATES[0]);
+        self.baud_rate = self.baud_rate.min(fastest);
+    }
+}
+
+/// Asynchronous serial decoder.
+///
+/// Looks for the mark-to-space transition of a start bit, then samples each
+/// bit in the middle of its bit period (LSB first).
+pub struct UartDecoder {
+    pub config: UartConfig,
+}
+
+impl UartDecoder {
+    pub fn new(config: UartConfig) -> Self {
+        UartDecoder { config }
+    }
+
+    fn level(&self, sample: f32) -> bool {
+        (sample > self.config.threshold) != self.config.inverted
+    }
+}
+
+impl Decoder for UartDecoder {
+    fn name(&self) -> &'static str {
+        "UART"
+    }
+
+    fn max_symbol_samples(&self, sample_rate: u32) -> usize {
+        let bit_samples = sample_rate as f32 / self.config.baud_rate as f32;
+        (bit_samples * self.config.frame_bits() as f32).ceil() as usize
+    }
+
+    fn decode(&self, samples: &[f32], sample_rate: u32) -> Vec<DecodedEvent> {
+        let bit_samples = sample_rate as f32 / self.config.baud_rate as f32;
+        let frame_bits = self.config.frame_bits();
+        let frame_samples = (bit_samples * frame_bits as f32).ceil() as usize;
+        let mut events = Vec::new();
+
+        if bit_samples < 1.0 || samples.len() < frame_samples {
+            return events;
+        }
+
+        let bit_at = |start: usize, bit: usize| -> bool {
+            let index = start + ((bit as f32 + 0.5) * bit_samples) as usize;
+            self.level(samples[index.min(samples.len() - 1)])
+        };
+
+        let mut i = 1;
+        while i + frame_samples <= samples.len() {
+            // Start bit: mark (1) to space (0) transition
+            let is_start_edge = self.level(samples[i - 1]) && !self.level(samples[i]);
+            if !is_start_edge {
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+
+            // Glitch rejection: start bit must still be low at its midpoint
+            if bit_at(start, 0) {
+                i += 1;
+                continue;
+            }
+
+            let mut value = 0u32;
+            let mut ones = 0;
+            for bit in 0..self.config.data_bits as usize {
+                if bit_at(start, 1 + bit) {
+                    value |= 1 << bit;
+                    ones += 1;
+                }
+            }
+
+            let mut error = false;
+            let mut next_bit = 1 + self.config.data_bits as usize;
+            if self.config.parity != Parity::None {
+                let parity_bit = bit_at(start, next_bit) as u32;
+                let expected = match self.config.parity {
+                    Parity::Even => ones % 2,
+                    Parity::Odd => (ones + 1) % 2,
+                    Parity::None => unreachable!(),
+                };
+                error |= parity_bit != expected;
+                next_bit += 1;
+            }
+
+            for stop in 0..self.config.stop_bits as usize {
+                error |= !bit_at(start, next_bit + stop);
+            }
+
+            let end = start + frame_samples;
+            events.push(DecodedEvent {
+                start,
+                end,
+                value,
+                label: byte_label(value),
+                error,
+            });
+
+            // Resume the search from the middle of the last stop bit
+            i = start + ((frame_bits as f32 - 0.5) * bit_samples) as usize;
+        }
+
+        events
+    }
+}
+
+fn byte_label(value: u32) -> String {
+    match char::from_u32(value) {
+        Some(c) if c.is_ascii_graphic() || c == ' ' => c.to_string(),
+        _ => format!("{value:02X}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode bytes as an 8N1 UART signal at ±1V with idle padding
+    fn encode(bytes: &[u8], baud: u32, sample_rate: u32) -> Vec<f32> {
+        let bit_samples = sample_rate as f32 / baud as f32;
+        let mut bits = vec![true; 20];
+        for &byte in bytes {
+            bits.push(false);
+            for bit in 0..8 {
+                bits.push(byte & (1 << bit) != 0);
+            }
+            bits.push(true);
+            bits.extend([true; 3]);
+        }
+        bits.extend([true; 20]);
+
+        let total = (bits.len() as f32 * bit_samples) as usize;
+        (0..total)
+            .map(|i| {
+                if bits[(i as f32 / bit_samples) as usize] {
+                    1.0
+                } else {
+                    -1.0
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_ascii() {
+        let signal = encode(b"Hi!", 9600, 48000);
+        let decoder = UartDecoder::new(UartConfig::default());
+        let events = decoder.decode(&signal, 48000);
+
+        let labels: Vec<&str> = events.iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(labels, vec!["H", "i", "!"]);
+        assert!(events.iter().all(|e| !e.error));
+    }
+
+    #[test]
+    fn test_decode_non_printable_label() {
+        let signal = encode(&[0x00, 0xFF], 4800, 48000);
+        let decoder = UartDecoder::new(UartConfig {
+            baud_rate: 4800,
+            ..Default::default()
+        });
+        let events = decoder.decode(&signal, 48000);
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].label, "00");
+        assert_eq!(events[1].value, 0xFF);
+    }
+
+    #[test]
+    fn test_inverted_polarity() {
+        let signal: Vec<f32> = encode(b"A", 9600, 48000).iter().map(|s| -s).collect();
+        let decoder = UartDecoder::new(UartConfig {
+            inverted: true,
+            ..Default::default()
+        });
+        let events = decoder.decode(&signal, 48000);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].value, b'A' as u32);
+    }
+
+    #[test]
+    fn test_framing_error_on_wrong_baud() {
+        let signal = encode(b"U", 2400, 48000);
+        let decoder = UartDecoder::new(UartConfig::default());
+        let events = decoder.decode(&signal, 48000);
+
+        assert!(!events.is_empty());
+        assert!(events[0].error);
+    }
+
+    #[test]
+    fn test_step_baud_rate() {
+        let mut config = UartConfig::default();
+        config.step_baud_rate(true, 48000);
+        assert_eq!(config.baud_rate, 19200);
+
+        config.step_baud_rate(false, 48000);
+        config.step_baud_rate(false, 48000);
+        assert_eq!(config.baud_rate, 4800);
+
+        config.baud_rate = 115200;
+        config.step_baud_rate(true, 384000);
+        assert_eq!(config.baud_rate, 115200);
+    }
+
+    #[test]
+    fn test_only_decodable_rates_are_offered() {
+        // 38400 Bd is barely over a sample per bit at 48 kHz
+        assert_eq!(decodable_baud_rates(48000).last(), Some(&19200));
+        assert_eq!(decodable_baud_rates(192000).last(), Some(&57600));
+        assert_eq!(decodable_baud_rates(100), vec![300]);
+
+        let mut config = UartConfig {
+            baud_rate: 19200,
+            ..UartConfig::default()
+        };
+        config.step_baud_rate(true, 48000);
+        assert_eq!(config.baud_rate, 19200);
+
+        config.baud_rate = 115200;
+        config.fit_baud_rate(48000);
+        assert_eq!(config.baud_rate, 19200);
+        config.fit_baud_rate(192000);
+        assert_eq!(config.baud_rate, 19200);
+    }
+
+    #[test]
+    fn test_stream_decoder_across_blocks() {
+        use crate::decode::StreamDecoder;
+
+        let signal = encode(b"stream", 9600, 48000);
+        let mut stream =
+            StreamDecoder::new(Box::new(UartDecoder::new(UartConfig::default())), 48000);
+
+        let mut decoded = Vec::new();
+        for block in signal.chunks(37) {
+            decoded.extend(stream.push(block));
+        }
+
+        let text: String = decoded.iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(text, "stream");
+        assert!(decoded.windows(2).all(|w| w[0].end <= w[1].start));
+        assert_eq!(stream.history().len(), 6);
+    }
+
+    #[test]
+    fn test_stream_decoder_after_clear() {
+        use crate::decode::StreamDecoder;
+
+        let signal = encode(b"again", 9600, 48000);
+        let mut stream =
+            StreamDecoder::new(Box::new(UartDecoder::new(UartConfig::default())), 48000);
+        let first: Vec<_> = signal.chunks(64).flat_map(|b| stream.push(b)).collect();
+
+        stream.clear();
+        assert!(stream.history().is_empty());
+        let second: Vec<_> = signal.chunks(64).flat_map(|b| stream.push(b)).collect();
+
+        // The same symbols at the same places, counted from the clear
+        let text: String = second.iter().map(|e| e.label.as_str()).collect();
+        assert_eq!(text, "again");
+        assert!(first
+            .iter()
+            .zip(&second)
+            .all(|(a, b)| a.start == b.start && a.end == b.end));
+    }
+}
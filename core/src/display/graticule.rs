@@ -0,0 +1,274 @@
+/// Division layouts offered by the UI, as (horizontal, vertical)
+pub const DIVISION_PRESETS: [(usize, usize); 4] = [(10, 8), (10, 10), (12, 8), (8, 6)];
+
+/// Minor subdivisions per division used for ticks and dot spacing
+const DEFAULT_MINOR_TICKS: usize = 5;
+
+/// Half the length of a minor tick, in normalized screen units
+const TICK_HALF_LENGTH: f32 = 0.008;
+
+/// Fraction of each dot period that is drawn in the dotted style
+const DOT_DUTY: f32 = 0.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LineStyle {
+    #[default]
+    Solid,
+    Dotted,
+}
+
+impl LineStyle {
+    pub fn next(&self) -> Self {
+        match self {
+            LineStyle::Solid => LineStyle::Dotted,
+            LineStyle::Dotted => LineStyle::Solid,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LineStyle::Solid => "Solid",
+            LineStyle::Dotted => "Dotted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridLineKind {
+    /// Division boundary
+    Major,
+    /// The horizontal/vertical axis through the middle of the screen
+    Center,
+    /// Minor tick mark along a center axis
+    Tick,
+}
+
+/// A grid segment in normalized screen space: (0, 0) is the top-left corner
+/// and (1, 1) the bottom-right
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridLine {
+    pub kind: GridLineKind,
+    pub from: (f32, f32),
+    pub to: (f32, f32),
+}
+
+/// Layout and style of the division grid, shared by every renderer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Graticule {
+    pub enabled: bool,
+    pub horizontal_divisions: usize,
+    pub vertical_divisions: usize,
+    pub minor_ticks: usize, // subdivisions per division along the center axes (0 = none)
+    pub style: LineStyle,
+}
+
+impl Default for Graticule {
+    fn default() -> Self {
+        Graticule {
+            enabled: true,
+            horizontal_divisions: 10,
+            vertical_divisions: 8,
+            minor_ticks: DEFAULT_MINOR_TICKS,
+            style: LineStyle::Solid,
+        }
+    }
+}
+
+impl Graticule {
+    /// Step to the next entry in `DIVISION_PRESETS`
+    pub fn cycle_divisions(&mut self) {
+        let current = (self.horizontal_divisions, self.vertical_divisions);
+        let index = DIVISION_PRESETS
+            .iter()
+            .position(|&preset| preset == current)
+            .map_or(0, |i| (i + 1) % DIVISION_PRESETS.len());
+        (self.horizontal_divisions, self.vertical_divisions) = DIVISION_PRESETS[index];
+    }
+
+    pub fn toggle_minor_ticks(&mut self) {
+        self.minor_ticks = if self.minor_ticks == 0 {
+            DEFAULT_MINOR_TICKS
+        } else {
+            0
+        };
+    }
+
+    pub fn divisions_label(&self) -> String {
+        format!("{}×{}", self.horizontal_divisions, self.vertical_divisions)
+    }
+
+    /// Every segment to draw, already split into dots for the dotted style.
+    /// Empty when the grid is disabled.
+    pub fn lines(&self) -> Vec<GridLine> {
+        let mut lines = Vec::new();
+        if !self.enabled {
+            return lines;
+        }
+
+        let columns = self.horizontal_divisions.max(1);
+        let rows = self.vertical_divisions.max(1);
+
+        // Center lines only exist when the division count is even
+        let line_kind = |i: usize, count: usize| {
+            if count.is_multiple_of(2) && i == count / 2 {
+                GridLineKind::Center
+            } else {
+                GridLineKind::Major
+            }
+        };
+
+        for i in 0..=columns {
+            let x = i as f32 / columns as f32;
+            let line = GridLine {
+                kind: line_kind(i, columns),
+                from: (x, 0.0),
+                to: (x, 1.0),
+            };
+            self.push_styled(&mut lines, line, rows);
+        }
+
+        for i in 0..=rows {
+            let y = i as f32 / rows as f32;
+            let line = GridLine {
+                kind: line_kind(i, rows),
+                from: (0.0, y),
+                to: (1.0, y),
+            };
+            self.push_styled(&mut lines, line, columns);
+        }
+
+        if self.minor_ticks > 0 {
+            let ticks_x = columns * self.minor_ticks;
+            for i in 1..ticks_x {
+                let x = i as f32 / ticks_x as f32;
+                lines.push(GridLine {
+                    kind: GridLineKind::Tick,
+                    from: (x, 0.5 - TICK_HALF_LENGTH),
+                    to: (x, 0.5 + TICK_HALF_LENGTH),
+                });
+            }
+
+            let ticks_y = rows * self.minor_ticks;
+            for i in 1..ticks_y {
+                let y = i as f32 / ticks_y as f32;
+                lines.push(GridLine {
+                    kind: GridLineKind::Tick,
+                    from: (0.5 - TICK_HALF_LENGTH, y),
+                    to: (0.5 + TICK_HALF_LENGTH, y),
+                });
+            }
+        }
+
+        lines
+    }
+
+    /// Push a full-length line, splitting it into dots if the style asks for it.
+    /// `crossing_divisions` is the number of divisions the line runs across.
+    fn push_styled(&self, lines: &mut Vec<GridLine>, line: GridLine, crossing_divisions: usize) {
+        if self.style == LineStyle::Solid {
+            lines.push(line);
+            return;
+        }
+
+        // One dot per minor subdivision, centered on it
+        let dots = crossing_divisions * DEFAULT_MINOR_TICKS;
+        let (dx, dy) = (line.to.0 - line.from.0, line.to.1 - line.from.1);
+        for i in 0..dots {
+            let center = (i as f32 + 0.5) / dots as f32;
+            let half = DOT_DUTY * 0.5 / dots as f32;
+            let (start, end) = (center - half, center + half);
+            lines.push(GridLine {
+                kind: line.kind,
+                from: (line.from.0 + dx * start, line.from.1 + dy * start),
+                to: (line.from.0 + dx * end, line.from.1 + dy * end),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(lines: &[GridLine], kind: GridLineKind) -> usize {
+        lines.iter().filter(|line| line.kind == kind).count()
+    }
+
+    #[test]
+    fn test_default_grid() {
+        let graticule = Graticule {
+            minor_ticks: 0,
+            ..Default::default()
+        };
+        let lines = graticule.lines();
+
+        // 11 vertical + 9 horizontal lines, two of them center lines
+        assert_eq!(lines.len(), 20);
+        assert_eq!(count(&lines, GridLineKind::Center), 2);
+        assert!(lines
+            .iter()
+            .filter(|l| l.kind == GridLineKind::Center)
+            .all(|l| l.from.0 == 0.5 || l.from.1 == 0.5));
+    }
+
+    #[test]
+    fn test_minor_ticks() {
+        let lines = Graticule::default().lines();
+        // 10×5 - 1 ticks across, 8×5 - 1 ticks down
+        assert_eq!(count(&lines, GridLineKind::Tick), 49 + 39);
+    }
+
+    #[test]
+    fn test_odd_divisions_have_no_center_line() {
+        let graticule = Graticule {
+            horizontal_divisions: 5,
+            vertical_divisions: 3,
+            minor_ticks: 0,
+            ..Default::default()
+        };
+        assert_eq!(count(&graticule.lines(), GridLineKind::Center), 0);
+    }
+
+    #[test]
+    fn test_disabled_grid_is_empty() {
+        let graticule = Graticule {
+            enabled: false,
+            ..Default::default()
+        };
+        assert!(graticule.lines().is_empty());
+    }
+
+    #[test]
+    fn test_dotted_lines_stay_on_their_line() {
+        let graticule = Graticule {
+            style: LineStyle::Dotted,
+            minor_ticks: 0,
+            ..Default::default()
+        };
+        let lines = graticule.lines();
+
+        // Each of the 11 vertical lines crosses 8 divisions of 5 dots
+        let vertical: Vec<_> = lines.iter().filter(|l| l.from.0 == l.to.0).collect();
+        assert_eq!(vertical.len(), 11 * 8 * 5);
+        assert!(vertical
+            .iter()
+            .all(|l| l.to.1 > l.from.1 && l.to.1 - l.from.1 < 0.01));
+    }
+
+    #[test]
+    fn test_cycle_divisions() {
+        let mut graticule = Graticule::default();
+        graticule.cycle_divisions();
+        assert_eq!(graticule.divisions_label(), "10×10");
+
+        for _ in 1..DIVISION_PRESETS.len() {
+            graticule.cycle_divisions();
+        }
+        assert_eq!(
+            (graticule.horizontal_divisions, graticule.vertical_divisions),
+            (10, 8)
+        );
+    }
+}
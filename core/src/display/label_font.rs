@@ -0,0 +1,52 @@
+//! A 5×7 pixel font with just the characters axis labels use, so plots
+//! can be annotated without loading a font
+
+/// Pixels across and down a character, before the one-pixel gap after it
+pub const GLYPH_WIDTH: i32 = 5;
+pub const GLYPH_HEIGHT: i32 = 7;
+pub const ADVANCE: i32 = GLYPH_WIDTH + 1;
+
+/// Rows of `c` from the top, the leftmost pixel in bit 4. Characters
+/// without a glyph are left blank.
+fn glyph(c: char) -> [u8; 7] {
+    match c {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'k' => [0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12],
+        'm' => [0x00, 0x00, 0x1A, 0x15, 0x15, 0x11, 0x11],
+        'n' => [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11],
+        's' => [0x00, 0x00, 0x0E, 0x10, 0x0E, 0x01, 0x1E],
+        'z' => [0x00, 0x00, 0x1F, 0x02, 0x04, 0x08, 0x1F],
+        'µ' => [0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x1D],
+        _ => [0; 7],
+    }
+}
+
+/// Width of `text` in pixels, without the gap after its last character
+pub fn text_width(text: &str) -> i32 {
+    (text.chars().count() as i32 * ADVANCE - 1).max(0)
+}
+
+/// The pixels of `text` set, relative to its top left corner
+pub fn pixels(text: &str) -> impl Iterator<Item = (i32, i32)> + '_ {
+    text.chars().enumerate().flat_map(|(index, c)| {
+        let left = index as i32 * ADVANCE;
+        glyph(c).into_iter().enumerate().flat_map(move |(y, row)| {
+            (0..GLYPH_WIDTH)
+                .filter(move |x| row & (0x10 >> x) != 0)
+                .map(move |x| (left + x, y as i32))
+        })
+    })
+}
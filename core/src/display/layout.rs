@@ -0,0 +1,39 @@
+/// How the scope and the second pane share a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LayoutMode {
+    /// The scope alone
+    ScopeOnly,
+    /// The spectrum alone, or whatever the second pane shows
+    SpectrumOnly,
+    SideBySide,
+    Stacked,
+}
+
+impl LayoutMode {
+    pub fn next(self) -> Self {
+        match self {
+            LayoutMode::ScopeOnly => LayoutMode::SpectrumOnly,
+            LayoutMode::SpectrumOnly => LayoutMode::SideBySide,
+            LayoutMode::SideBySide => LayoutMode::Stacked,
+            LayoutMode::Stacked => LayoutMode::ScopeOnly,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LayoutMode::ScopeOnly => "Scope only",
+            LayoutMode::SpectrumOnly => "Spectrum only",
+            LayoutMode::SideBySide => "Side by side",
+            LayoutMode::Stacked => "Stacked",
+        }
+    }
+
+    pub fn shows_scope(self) -> bool {
+        self != LayoutMode::SpectrumOnly
+    }
+
+    pub fn shows_spectrum(self) -> bool {
+        self != LayoutMode::ScopeOnly
+    }
+}
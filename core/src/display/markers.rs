@@ -0,0 +1,248 @@
+use crate::oscilloscope::band_power::BandCursors;
+use crate::oscilloscope::harmonics::HarmonicAnalysis;
+use crate::oscilloscope::spectrum::{DB_MAX, DB_MIN};
+use crate::oscilloscope::time_gate::TimeGate;
+use crate::oscilloscope::trigger::{TriggerEdge, TriggerSettings};
+
+/// A line segment in normalized screen space: (0, 0) top-left, (1, 1) bottom-right
+pub type Segment = ((f32, f32), (f32, f32));
+
+/// Dash period and drawn fraction of the trigger level line
+const DASHES: usize = 40;
+const DASH_DUTY: f32 = 0.5;
+
+/// Edge arrow size and position, in normalized units
+const ARROW_X: f32 = 0.012;
+const ARROW_HALF_HEIGHT: f32 = 0.03;
+const ARROW_HEAD: f32 = 0.008;
+
+/// Dashed horizontal line at the trigger level plus an arrow at the left edge
+/// pointing up for a rising edge or down for a falling edge.
+///
+/// When the level is off screen the marker is pinned to the nearest edge so
+/// the arrow still shows which way the trigger is set. Empty when the trigger
+/// is disabled.
+pub fn trigger_level_marker(
+    trigger: &TriggerSettings,
+    volts_per_division: f32,
+    vertical_divisions: usize,
+) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    if !trigger.enabled {
+        return segments;
+    }
+
+    let divisions = vertical_divisions.max(1) as f32;
+    let y = (0.5 - trigger.level / volts_per_division / divisions).clamp(0.0, 1.0);
+
+    // Dashed level line, starting clear of the arrow
+    let start = ARROW_X * 2.0;
+    let period = (1.0 - start) / DASHES as f32;
+    for i in 0..DASHES {
+        let x = start + i as f32 * period;
+        segments.push(((x, y), (x + period * DASH_DUTY, y)));
+    }
+
+    // Keep the arrow on screen when pinned to an edge
+    let center = y.clamp(ARROW_HALF_HEIGHT, 1.0 - ARROW_HALF_HEIGHT);
+    let (tip, tail) = match trigger.edge {
+        TriggerEdge::Rising => (center - ARROW_HALF_HEIGHT, center + ARROW_HALF_HEIGHT),
+        TriggerEdge::Falling => (center + ARROW_HALF_HEIGHT, center - ARROW_HALF_HEIGHT),
+    };
+    let back = if tip < tail { ARROW_HEAD } else { -ARROW_HEAD };
+    segments.push(((ARROW_X, tail), (ARROW_X, tip)));
+    segments.push(((ARROW_X, tip), (ARROW_X - ARROW_HEAD, tip + back)));
+    segments.push(((ARROW_X, tip), (ARROW_X + ARROW_HEAD, tip + back)));
+
+    segments
+}
+
+/// Size of the trigger point flag, in normalized units
+const FLAG_HALF_WIDTH: f32 = 0.008;
+const FLAG_HEIGHT: f32 = 0.03;
+const FLAG_TOP: f32 = 0.004;
+
+/// A small "T" flag hanging from the top edge at the trigger's horizontal
+/// position, with a notch pointing down at the trigger instant
+pub fn trigger_point_marker(x: f32) -> Vec<Segment> {
+    let x = x.clamp(0.0, 1.0);
+    let bottom = FLAG_TOP + FLAG_HEIGHT;
+    vec![
+        // Crossbar and stem of the T
+        (
+            (x - FLAG_HALF_WIDTH, FLAG_TOP),
+            (x + FLAG_HALF_WIDTH, FLAG_TOP),
+        ),
+        ((x, FLAG_TOP), (x, bottom)),
+        // Downward notch
+        (
+            (x - FLAG_HALF_WIDTH * 0.75, bottom - FLAG_HALF_WIDTH),
+            (x, bottom),
+        ),
+        (
+            (x + FLAG_HALF_WIDTH * 0.75, bottom - FLAG_HALF_WIDTH),
+            (x, bottom),
+        ),
+    ]
+}
+
+/// A bar for each harmonic on the spectrum display (linear frequency up to
+/// Nyquist, `DB_MIN` to `DB_MAX`), rising from the bottom edge to its level
+pub fn harmonic_markers(analysis: &HarmonicAnalysis, sample_rate: u32) -> Vec<Segment> {
+    let nyquist = sample_rate as f32 / 2.0;
+    analysis
+        .harmonics
+        .iter()
+        .map(|harmonic| {
+            let x = (harmonic.frequency / nyquist).clamp(0.0, 1.0);
+            let db = analysis.fundamental_level + harmonic.level;
+            let y = ((DB_MAX - db) / (DB_MAX - DB_MIN)).clamp(0.0, 1.0);
+            ((x, 1.0), (x, y))
+        })
+        .collect()
+}
+
+/// Full-height lines at the two band cursors on the spectrum display
+/// (linear frequency up to Nyquist), pinned to the edges when beyond them
+pub fn band_cursor_markers(cursors: &BandCursors, sample_rate: u32) -> Vec<Segment> {
+    let nyquist = sample_rate as f32 / 2.0;
+    [cursors.low, cursors.high]
+        .into_iter()
+        .map(|frequency| {
+            let x = (frequency / nyquist).clamp(0.0, 1.0);
+            ((x, 0.0), (x, 1.0))
+        })
+        .collect()
+}
+
+/// Full-height lines at the two time gate cursors on the waveform display,
+/// moved `x_offset` screen widths by any horizontal pan
+pub fn time_gate_markers(gate: &TimeGate, x_offset: f32) -> Vec<Segment> {
+    [gate.start, gate.end]
+        .into_iter()
+        .map(|x| {
+            let x = (x + x_offset).clamp(0.0, 1.0);
+            ((x, 0.0), (x, 1.0))
+        })
+        .collect()
+}
+
+/// A vectorscope point, -1 to 1 across and up, in normalized screen space
+/// within the vectorscope's square
+pub fn vectorscope_position((x, y): (f32, f32)) -> (f32, f32) {
+    ((x + 1.0) / 2.0, (1.0 - y) / 2.0)
+}
+
+/// Guide lines of the vectorscope square: the mono axis up the middle, the
+/// out-of-phase axis across it and the left and right channel diagonals
+pub fn vectorscope_axes() -> Vec<Segment> {
+    vec![
+        ((0.5, 0.0), (0.5, 1.0)),
+        ((0.0, 0.5), (1.0, 0.5)),
+        ((0.0, 0.0), (1.0, 1.0)),
+        ((1.0, 0.0), (0.0, 1.0)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level_y(segments: &[Segment]) -> f32 {
+        segments[0].0 .1
+    }
+
+    fn arrow_tip_y(segments: &[Segment]) -> f32 {
+        segments[segments.len() - 3].1 .1
+    }
+
+    #[test]
+    fn test_vectorscope_position() {
+        assert_eq!(vectorscope_position((0.0, 0.0)), (0.5, 0.5));
+        // Full-scale left channel alone ends on the top-left corner
+        let (x, y) = crate::oscilloscope::vectorscope::rotate(1.0, 0.0);
+        let (x, y) = vectorscope_position((x * 2_f32.sqrt(), y * 2_f32.sqrt()));
+        assert!(x.abs() < 1e-6 && y.abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_disabled_trigger_has_no_marker() {
+        let trigger = TriggerSettings {
+            enabled: false,
+            ..Default::default()
+        };
+        assert!(trigger_level_marker(&trigger, 0.5, 8).is_empty());
+    }
+
+    #[test]
+    fn test_level_position() {
+        let mut trigger = TriggerSettings::default();
+        assert_eq!(level_y(&trigger_level_marker(&trigger, 0.5, 8)), 0.5);
+
+        // +1 V at 0.5 V/div is two divisions above center: 2/8 of the screen
+        trigger.level = 1.0;
+        assert!((level_y(&trigger_level_marker(&trigger, 0.5, 8)) - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_arrow_direction_follows_edge() {
+        let mut trigger = TriggerSettings::default();
+        let rising = trigger_level_marker(&trigger, 0.5, 8);
+        assert!(arrow_tip_y(&rising) < 0.5); // points up
+
+        trigger.toggle_edge();
+        let falling = trigger_level_marker(&trigger, 0.5, 8);
+        assert!(arrow_tip_y(&falling) > 0.5); // points down
+    }
+
+    #[test]
+    fn test_trigger_point_marker_at_position() {
+        let segments = trigger_point_marker(0.25);
+        // The stem ends at the trigger's horizontal position near the top edge
+        let (top, bottom) = segments[1];
+        assert_eq!(top.0, 0.25);
+        assert_eq!(bottom.0, 0.25);
+        assert!(bottom.1 < 0.1);
+    }
+
+    #[test]
+    fn test_harmonic_markers() {
+        use crate::oscilloscope::harmonics::Harmonic;
+
+        let analysis = HarmonicAnalysis {
+            fundamental: 6000.0,
+            fundamental_level: -20.0,
+            harmonics: vec![
+                Harmonic {
+                    number: 1,
+                    frequency: 6000.0,
+                    level: 0.0,
+                },
+                Harmonic {
+                    number: 2,
+                    frequency: 12000.0,
+                    level: -40.0,
+                },
+            ],
+            thd: 1.0,
+        };
+        let segments = harmonic_markers(&analysis, 48000);
+        assert_eq!(
+            segments,
+            vec![((0.25, 1.0), (0.25, 0.25)), ((0.5, 1.0), (0.5, 0.75))]
+        );
+    }
+
+    #[test]
+    fn test_off_screen_level_is_pinned() {
+        let trigger = TriggerSettings {
+            level: 10.0,
+            ..Default::default()
+        };
+        let segments = trigger_level_marker(&trigger, 0.1, 8);
+        assert_eq!(level_y(&segments), 0.0);
+        assert!(segments
+            .iter()
+            .all(|&((x0, y0), (x1, y1))| [x0, y0, x1, y1].iter().all(|v| (0.0..=1.0).contains(v))));
+    }
+}
@@ -0,0 +1,34 @@
+pub mod graticule;
+mod label_font;
+pub mod layout;
+pub mod markers;
+pub mod persistence;
+pub mod plot;
+pub mod readout;
+pub mod refresh;
+pub mod theme;
+pub mod trace_colors;
+pub mod units;
+
+pub use graticule::{Graticule, GridLine, GridLineKind, LineStyle};
+pub use layout::LayoutMode;
+pub use markers::{
+    band_cursor_markers, harmonic_markers, time_gate_markers, trigger_level_marker,
+    trigger_point_marker, vectorscope_axes, vectorscope_position, Segment,
+};
+pub use persistence::{
+    heat_color, IntensityMap, PersistenceColormap, PersistenceDecay, PersistenceMode,
+    MAX_PERSISTENCE_FRAMES, MAX_TRACE_HISTORY,
+};
+pub use plot::{spectrogram_image, PlotImage};
+pub use readout::{
+    axis_labels, band_power_label, frequency_track_labels, harmonic_table, measurement_readout,
+    phase_labels, time_gate_label, vectorscope_labels, AxisLabel, LabelAnchor,
+};
+pub use refresh::RefreshRate;
+pub use theme::{DisplayTheme, Palette, Rgba};
+pub use trace_colors::{
+    color_from_hex, color_to_hex, next_trace_color, trace_color_label, TraceColors,
+    TRACE_COLOR_CHOICES,
+};
+pub use units::{format_frequency, format_time, format_volts};
@@ -0,0 +1,636 @@
+use std::time::Duration;
+
+use super::theme::{Palette, Rgba};
+
+/// Grid resolution used by the renderers; fine enough to look continuous when
+/// stretched over a typical window, small enough to clone every frame
+pub const DEFAULT_COLUMNS: usize = 320;
+pub const DEFAULT_ROWS: usize = 200;
+
+/// Fraction of its initial brightness a hit keeps after the persistence length
+const RESIDUAL_BRIGHTNESS: f32 = 0.05;
+
+/// Cells dimmer than this are not drawn
+const MIN_VISIBLE_INTENSITY: f32 = 0.01;
+
+/// Longest persistence in frames. Past the trace history it's drawn from
+/// the intensity map, which costs the same however long it lasts.
+pub const MAX_PERSISTENCE_FRAMES: usize = 500;
+
+/// Most traces kept as points to be redrawn line by line
+pub const MAX_TRACE_HISTORY: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PersistenceMode {
+    /// The last N traces redrawn with fading alpha
+    Fade,
+    /// Analog-style intensity grading: frequently traversed paths glow brighter
+    #[default]
+    Graded,
+}
+
+impl PersistenceMode {
+    pub const ALL: [PersistenceMode; 2] = [PersistenceMode::Fade, PersistenceMode::Graded];
+
+    /// Look up a mode by its label (case-insensitive)
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.label().eq_ignore_ascii_case(label))
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            PersistenceMode::Fade => PersistenceMode::Graded,
+            PersistenceMode::Graded => PersistenceMode::Fade,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PersistenceMode::Fade => "Fade",
+            PersistenceMode::Graded => "Graded",
+        }
+    }
+}
+
+/// Colors of the heat map from cold to hot: blue, green, yellow, red
+const HEAT_STOPS: [[f32; 3]; 4] = [
+    [0.0, 0.2, 1.0],
+    [0.0, 0.9, 0.2],
+    [1.0, 0.9, 0.0],
+    [1.0, 0.1, 0.0],
+];
+
+/// Opacity of the coldest heat map color; rare paths stay plainly visible,
+/// which is the point of coloring them
+const HEAT_MIN_ALPHA: f32 = 0.5;
+
+/// How persisted traces are colored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PersistenceColormap {
+    /// The trace color, fainter for rarer paths or older traces
+    #[default]
+    Trace,
+    /// Blue through green and yellow to red from rare or old to busy or
+    /// new, so excursions stand apart from the steady waveform
+    Heat,
+}
+
+impl PersistenceColormap {
+    pub const ALL: [PersistenceColormap; 2] =
+        [PersistenceColormap::Trace, PersistenceColormap::Heat];
+
+    /// Look up a colormap by its label (case-insensitive)
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|colormap| colormap.label().eq_ignore_ascii_case(label))
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            PersistenceColormap::Trace => PersistenceColormap::Heat,
+            PersistenceColormap::Heat => PersistenceColormap::Trace,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PersistenceColormap::Trace => "Trace",
+            PersistenceColormap::Heat => "Heat",
+        }
+    }
+
+    /// Color of an intensity-graded cell or segment (0.0 to 1.0)
+    pub fn graded(&self, palette: &Palette, intensity: f32) -> Rgba {
+        match self {
+            PersistenceColormap::Trace => palette.graded(intensity),
+            PersistenceColormap::Heat => heat_color(intensity),
+        }
+    }
+
+    /// Color of a faded trace, `age_alpha` from the oldest (near 0.0) to
+    /// the newest (1.0)
+    pub fn faded(&self, palette: &Palette, age_alpha: f32) -> Rgba {
+        match self {
+            PersistenceColormap::Trace => Palette::faded(palette.trace, age_alpha),
+            PersistenceColormap::Heat => heat_color(age_alpha),
+        }
+    }
+
+    /// Color of an intensity map cell at `level`, as read for `mode`
+    pub fn cell(&self, palette: &Palette, mode: PersistenceMode, level: f32) -> Rgba {
+        match mode {
+            PersistenceMode::Graded => self.graded(palette, level),
+            PersistenceMode::Fade => self.faded(palette, level),
+        }
+    }
+}
+
+/// The heat map color at `level`, 0.0 (blue) to 1.0 (red)
+pub fn heat_color(level: f32) -> Rgba {
+    let position = level.clamp(0.0, 1.0) * (HEAT_STOPS.len() - 1) as f32;
+    let index = (position as usize).min(HEAT_STOPS.len() - 2);
+    let t = position - index as f32;
+    let (from, to) = (HEAT_STOPS[index], HEAT_STOPS[index + 1]);
+    [
+        from[0] + (to[0] - from[0]) * t,
+        from[1] + (to[1] - from[1]) * t,
+        from[2] + (to[2] - from[2]) * t,
+        HEAT_MIN_ALPHA + (1.0 - HEAT_MIN_ALPHA) * level.clamp(0.0, 1.0),
+    ]
+}
+
+/// How long persisted traces take to fade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PersistenceDecay {
+    /// Over the configured number of frames, however long those take at
+    /// the frame rate
+    #[default]
+    Frames,
+    HalfSecond,
+    TwoSeconds,
+    /// Traces stay until persistence is cleared
+    Infinite,
+}
+
+impl PersistenceDecay {
+    pub const ALL: [PersistenceDecay; 4] = [
+        PersistenceDecay::Frames,
+        PersistenceDecay::HalfSecond,
+        PersistenceDecay::TwoSeconds,
+        PersistenceDecay::Infinite,
+    ];
+
+    /// Look up a decay by its label (case-insensitive), with or without
+    /// the space before the unit
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label.replace(' ', "");
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|decay| decay.label().replace(' ', "").eq_ignore_ascii_case(&label))
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            PersistenceDecay::Frames => PersistenceDecay::HalfSecond,
+            PersistenceDecay::HalfSecond => PersistenceDecay::TwoSeconds,
+            PersistenceDecay::TwoSeconds => PersistenceDecay::Infinite,
+            PersistenceDecay::Infinite => PersistenceDecay::Frames,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PersistenceDecay::Frames => "Frames",
+            PersistenceDecay::HalfSecond => "0.5 s",
+            PersistenceDecay::TwoSeconds => "2 s",
+            PersistenceDecay::Infinite => "Infinite",
+        }
+    }
+
+    /// Whether traces fade by their age rather than their place in the
+    /// history
+    pub fn is_timed(&self) -> bool {
+        *self != PersistenceDecay::Frames
+    }
+
+    /// Time for a trace to fade out, if it ever does
+    pub fn fade_time(&self) -> Option<Duration> {
+        match self {
+            PersistenceDecay::HalfSecond => Some(Duration::from_millis(500)),
+            PersistenceDecay::TwoSeconds => Some(Duration::from_secs(2)),
+            PersistenceDecay::Frames | PersistenceDecay::Infinite => None,
+        }
+    }
+
+    /// Opacity of a trace `age` old relative to a new one, falling
+    /// linearly to nothing at the fade time. Counting frames, a trace's
+    /// place in the history sets its fade instead, so this is 1.0.
+    pub fn alpha(&self, age: Duration) -> f32 {
+        match self.fade_time() {
+            Some(fade) => (1.0 - age.as_secs_f32() / fade.as_secs_f32()).clamp(0.0, 1.0),
+            None => 1.0,
+        }
+    }
+
+    /// Whether a trace `age` old can be dropped
+    pub fn has_faded(&self, age: Duration) -> bool {
+        self.fade_time().is_some_and(|fade| age >= fade)
+    }
+
+    /// Whether traces persist longer than the trace history holds, counting
+    /// `frames` frames, so they're drawn from the intensity map's cells
+    pub fn outlasts_history(&self, frames: usize) -> bool {
+        match self {
+            PersistenceDecay::Frames => frames > MAX_TRACE_HISTORY,
+            // The trace history covers half a second at 60 frames a second
+            PersistenceDecay::HalfSecond => false,
+            PersistenceDecay::TwoSeconds | PersistenceDecay::Infinite => true,
+        }
+    }
+
+    /// Fraction of a hit's brightness kept over `elapsed`, falling to the
+    /// same residual over the fade time that frame counting leaves after
+    /// its frames. `None` when counting frames.
+    pub fn retention(&self, elapsed: Duration) -> Option<f32> {
+        match self {
+            PersistenceDecay::Frames => None,
+            PersistenceDecay::Infinite => Some(1.0),
+            PersistenceDecay::HalfSecond | PersistenceDecay::TwoSeconds => {
+                let fade = self.fade_time()?.as_secs_f32();
+                Some(RESIDUAL_BRIGHTNESS.powf(elapsed.as_secs_f32() / fade))
+            }
+        }
+    }
+}
+
+/// Decaying hit-count map of where recent traces have been drawn.
+///
+/// Each frame every cell is multiplied by the decay factor, then the cells
+/// crossed by the new trace are incremented. Brightness is the log of the hit
+/// count relative to the busiest cell, so a steady waveform saturates while
+/// rare excursions remain faintly visible.
+///
+/// Alongside the hits each cell keeps its recency, reset to 1.0 whenever a
+/// trace crosses it and decayed the same way, which fades cells by the age
+/// of their newest trace however many traces that spans.
+#[derive(Debug, Clone)]
+pub struct IntensityMap {
+    columns: usize,
+    rows: usize,
+    hits: Vec<f32>,
+    recency: Vec<f32>,
+    decay: f32,
+    peak: f32,
+    vertical_divisions: f32, // screen height in the divisions used by the normalized y
+}
+
+impl Default for IntensityMap {
+    fn default() -> Self {
+        Self::new(DEFAULT_COLUMNS, DEFAULT_ROWS)
+    }
+}
+
+impl IntensityMap {
+    pub fn new(columns: usize, rows: usize) -> Self {
+        let columns = columns.max(1);
+        let rows = rows.max(1);
+        let mut map = IntensityMap {
+            columns,
+            rows,
+            hits: vec![0.0; columns * rows],
+            recency: vec![0.0; columns * rows],
+            decay: 0.0,
+            peak: 0.0,
+            vertical_divisions: 8.0,
+        };
+        map.set_decay_frames(10);
+        map
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn decay(&self) -> f32 {
+        self.decay
+    }
+
+    /// Choose the per-frame decay so a hit fades out over roughly `frames` frames
+    pub fn set_decay_frames(&mut self, frames: usize) {
+        self.decay = RESIDUAL_BRIGHTNESS.powf(1.0 / frames.max(1) as f32);
+    }
+
+    /// Match the graticule's vertical divisions; clears the map since every
+    /// cell now corresponds to a different voltage
+    pub fn set_vertical_divisions(&mut self, divisions: usize) {
+        let divisions = divisions.max(1) as f32;
+        if divisions != self.vertical_divisions {
+            self.vertical_divisions = divisions;
+            self.clear();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.hits.fill(0.0);
+        self.recency.fill(0.0);
+        self.peak = 0.0;
+    }
+
+    /// Decay the existing hits and add one trace of normalized display points
+    pub fn accumulate(&mut self, points: &[(f32, f32)]) {
+        self.accumulate_with(points, self.decay);
+    }
+
+    /// Like `accumulate`, decaying the hits by as much as `decay` fades
+    /// them over `elapsed`, the time since the last trace. Counting frames
+    /// decays them by the per-frame amount as usual.
+    pub fn accumulate_after(
+        &mut self,
+        points: &[(f32, f32)],
+        decay: PersistenceDecay,
+        elapsed: Duration,
+    ) {
+        let retention = decay.retention(elapsed).unwrap_or(self.decay);
+        self.accumulate_with(points, retention);
+    }
+
+    fn accumulate_with(&mut self, points: &[(f32, f32)], retention: f32) {
+        if retention < 1.0 {
+            for hit in self.hits.iter_mut().chain(&mut self.recency) {
+                *hit *= retention;
+            }
+        }
+
+        match points {
+            [] => {}
+            [point] => self.plot_segment(*point, *point, true),
+            _ => {
+                for (i, segment) in points.windows(2).enumerate() {
+                    // Shared endpoints are only counted once
+                    self.plot_segment(segment[0], segment[1], i == 0);
+                }
+            }
+        }
+
+        self.peak = self.hits.iter().copied().fold(0.0, f32::max);
+    }
+
+    /// Brightness of a cell, 0.0 (never hit) to 1.0 (the busiest cell)
+    pub fn intensity(&self, column: usize, row: usize) -> f32 {
+        if self.peak <= 0.0 || column >= self.columns || row >= self.rows {
+            return 0.0;
+        }
+        let hits = self.hits[row * self.columns + column];
+        (hits.ln_1p() / self.peak.ln_1p()).clamp(0.0, 1.0)
+    }
+
+    /// Brightness at a normalized display point
+    pub fn intensity_at(&self, x: f32, y: f32) -> f32 {
+        let (column, row) = self.to_cell(x, y);
+        if column < 0.0 || row < 0.0 {
+            return 0.0;
+        }
+        self.intensity(column as usize, row as usize)
+    }
+
+    /// How recently a trace crossed a cell, 1.0 for the newest trace
+    /// fading toward 0.0 as it ages
+    pub fn recency(&self, column: usize, row: usize) -> f32 {
+        if column >= self.columns || row >= self.rows {
+            return 0.0;
+        }
+        self.recency[row * self.columns + column]
+    }
+
+    /// Cells bright enough to draw, as (column, row, intensity); row 0 is the top
+    pub fn lit_cells(&self) -> impl Iterator<Item = (usize, usize, f32)> + '_ {
+        self.cells(PersistenceMode::Graded)
+    }
+
+    /// Cells bright enough to draw as `mode` reads them: by intensity when
+    /// graded, by recency when fading
+    pub fn cells(&self, mode: PersistenceMode) -> impl Iterator<Item = (usize, usize, f32)> + '_ {
+        (0..self.rows).flat_map(move |row| {
+            (0..self.columns).filter_map(move |column| {
+                let level = match mode {
+                    PersistenceMode::Graded => self.intensity(column, row),
+                    PersistenceMode::Fade => self.recency(column, row),
+                };
+                (level >= MIN_VISIBLE_INTENSITY).then_some((column, row, level))
+            })
+        })
+    }
+
+    /// Top-left and bottom-right corners of a cell in normalized display
+    /// points, x from 0.0 to 1.0 and y in divisions up from the center line
+    pub fn cell_bounds(&self, column: usize, row: usize) -> ((f32, f32), (f32, f32)) {
+        let x = |column: usize| column as f32 / self.columns as f32;
+        let y = |row: usize| (0.5 - row as f32 / self.rows as f32) * self.vertical_divisions;
+        ((x(column), y(row)), (x(column + 1), y(row + 1)))
+    }
+
+    /// Fractional cell coordinates, clamped to one cell beyond each edge so
+    /// wildly off-screen points don't produce huge segments
+    fn to_cell(&self, x: f32, y: f32) -> (f32, f32) {
+        let column = (x * self.columns as f32).clamp(-1.0, self.columns as f32);
+        let row =
+            ((0.5 - y / self.vertical_divisions) * self.rows as f32).clamp(-1.0, self.rows as f32);
+        (column, row)
+    }
+
+    fn plot_segment(&mut self, from: (f32, f32), to: (f32, f32), include_start: bool) {
+        let (x0, y0) = self.to_cell(from.0, from.1);
+        let (x1, y1) = self.to_cell(to.0, to.1);
+        // Half-cell steps so rounding never skips a cell; repeats are filtered below
+        let steps = ((x1 - x0).abs().max((y1 - y0).abs()) * 2.0).ceil().max(1.0) as usize;
+
+        let mut last = None;
+        let first_step = if include_start { 0 } else { 1 };
+        for step in first_step..=steps {
+            let t = step as f32 / steps as f32;
+            let column = (x0 + (x1 - x0) * t).floor();
+            let row = (y0 + (y1 - y0) * t).floor();
+            if column < 0.0
+                || row < 0.0
+                || column >= self.columns as f32
+                || row >= self.rows as f32
+                || last == Some((column, row))
+            {
+                continue;
+            }
+            last = Some((column, row));
+            let cell = row as usize * self.columns + column as usize;
+            self.hits[cell] += 1.0;
+            self.recency[cell] = 1.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_from_label() {
+        assert_eq!(
+            PersistenceMode::from_label("fade"),
+            Some(PersistenceMode::Fade)
+        );
+        assert_eq!(
+            PersistenceMode::from_label("Graded"),
+            Some(PersistenceMode::Graded)
+        );
+        assert_eq!(PersistenceMode::from_label("smear"), None);
+    }
+
+    #[test]
+    fn test_repeated_path_is_brightest() {
+        let mut map = IntensityMap::new(100, 80);
+        map.set_decay_frames(1000);
+
+        // A steady flat line with one excursion
+        for _ in 0..20 {
+            map.accumulate(&[(0.0, 0.0), (0.99, 0.0)]);
+        }
+        map.accumulate(&[(0.0, 2.0), (0.99, 2.0)]);
+
+        let steady = map.intensity_at(0.5, 0.0);
+        let rare = map.intensity_at(0.5, 2.0);
+        assert!((steady - 1.0).abs() < 1e-6);
+        assert!(rare > 0.0 && rare < 0.5);
+        assert_eq!(map.intensity_at(0.5, -2.0), 0.0);
+    }
+
+    #[test]
+    fn test_decay_fades_old_traces() {
+        let mut map = IntensityMap::new(10, 8);
+        map.set_decay_frames(2);
+        map.accumulate(&[(0.0, 1.0), (0.95, 1.0)]);
+        for _ in 0..4 {
+            map.accumulate(&[(0.0, -1.0), (0.95, -1.0)]);
+        }
+
+        assert!(map.intensity_at(0.5, 1.0) < map.intensity_at(0.5, -1.0) * 0.2);
+    }
+
+    #[test]
+    fn test_vertical_segment_hits_every_row_once() {
+        let mut map = IntensityMap::new(10, 8);
+        // Full-screen vertical edge in one column
+        map.accumulate(&[(0.55, 4.0), (0.55, -4.0)]);
+
+        let lit: Vec<_> = map.lit_cells().collect();
+        assert_eq!(lit.len(), 8);
+        assert!(lit
+            .iter()
+            .all(|&(column, _, intensity)| column == 5 && intensity == 1.0));
+    }
+
+    #[test]
+    fn test_off_screen_points_are_clipped() {
+        let mut map = IntensityMap::new(10, 8);
+        map.accumulate(&[(0.0, 1000.0), (1.0, 1000.0), (2.0, -1000.0)]);
+        assert!(map.lit_cells().all(|(c, r, _)| c < 10 && r < 8));
+
+        map.clear();
+        assert_eq!(map.lit_cells().count(), 0);
+    }
+
+    #[test]
+    fn test_heat_map_runs_blue_to_red() {
+        let close = |a: Rgba, b: Rgba| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-6);
+        assert!(close(heat_color(0.0), [0.0, 0.2, 1.0, HEAT_MIN_ALPHA]));
+        assert!(close(heat_color(1.0), [1.0, 0.1, 0.0, 1.0]));
+        assert_eq!(heat_color(2.0), heat_color(1.0));
+        // Green a third of the way, yellow two thirds
+        assert!(heat_color(1.0 / 3.0)[1] > 0.89);
+        assert!(heat_color(2.0 / 3.0)[0] > 0.99);
+
+        let palette = Palette::default();
+        let trace = PersistenceColormap::Trace;
+        assert_eq!(trace.graded(&palette, 0.5), palette.graded(0.5));
+        assert_eq!(
+            trace.faded(&palette, 0.5),
+            Palette::faded(palette.trace, 0.5)
+        );
+        assert_eq!(
+            PersistenceColormap::Heat.faded(&palette, 0.25),
+            heat_color(0.25)
+        );
+        assert_eq!(
+            PersistenceColormap::from_label("heat"),
+            Some(PersistenceColormap::Heat)
+        );
+    }
+
+    #[test]
+    fn test_timed_decay() {
+        let decay = PersistenceDecay::HalfSecond;
+        assert_eq!(decay.alpha(Duration::ZERO), 1.0);
+        assert!((decay.alpha(Duration::from_millis(125)) - 0.75).abs() < 1e-6);
+        assert_eq!(decay.alpha(Duration::from_secs(1)), 0.0);
+        assert!(decay.has_faded(Duration::from_millis(500)));
+        assert!(!PersistenceDecay::Infinite.has_faded(Duration::from_secs(3600)));
+        assert_eq!(PersistenceDecay::Frames.retention(Duration::ZERO), None);
+
+        // The same fade however the time is split into frames
+        let mut slow = IntensityMap::new(10, 8);
+        let mut fast = IntensityMap::new(10, 8);
+        let trace = [(0.0, 1.0), (0.95, 1.0)];
+        slow.accumulate_after(&trace, decay, Duration::ZERO);
+        fast.accumulate_after(&trace, decay, Duration::ZERO);
+        slow.accumulate_after(&[], decay, Duration::from_millis(250));
+        for _ in 0..15 {
+            fast.accumulate_after(&[], decay, Duration::from_secs_f32(0.25 / 15.0));
+        }
+        let slow_hits = slow.hits[slow.columns * 3 + 5];
+        let fast_hits = fast.hits[fast.columns * 3 + 5];
+        assert!((slow_hits - RESIDUAL_BRIGHTNESS.sqrt()).abs() < 1e-4);
+        assert!((slow_hits - fast_hits).abs() < 1e-4);
+
+        assert_eq!(
+            PersistenceDecay::from_label("2 S"),
+            Some(PersistenceDecay::TwoSeconds)
+        );
+        assert_eq!(
+            PersistenceDecay::from_label("0.5s"),
+            Some(PersistenceDecay::HalfSecond)
+        );
+    }
+
+    #[test]
+    fn test_recency_fades_by_age_not_count() {
+        let mut map = IntensityMap::new(10, 8);
+        map.set_decay_frames(MAX_PERSISTENCE_FRAMES);
+        // One excursion, then hundreds of frames of a steady line
+        map.accumulate(&[(0.0, 2.0), (0.95, 2.0)]);
+        for _ in 0..MAX_PERSISTENCE_FRAMES / 2 {
+            map.accumulate(&[(0.0, -1.0), (0.95, -1.0)]);
+        }
+
+        let rare = map
+            .cells(PersistenceMode::Fade)
+            .find(|&(_, row, _)| row == 2);
+        assert!(rare.is_some_and(|(_, _, level)| level > 0.1 && level < 0.5));
+        assert_eq!(map.recency(5, 5), 1.0);
+        // Graded, the excursion is still there but far dimmer than the line
+        assert!(map.intensity(5, 2) < map.intensity(5, 5) * 0.3);
+
+        let ((left, top), (right, bottom)) = map.cell_bounds(5, 2);
+        assert!((left - 0.5).abs() < 1e-6 && (right - 0.6).abs() < 1e-6);
+        assert!((top - 2.0).abs() < 1e-6 && (bottom - 1.0).abs() < 1e-6);
+
+        map.accumulate_after(&[], PersistenceDecay::Infinite, Duration::from_secs(60));
+        assert_eq!(map.recency(5, 5), 1.0);
+        map.clear();
+        assert_eq!(map.cells(PersistenceMode::Fade).count(), 0);
+    }
+
+    #[test]
+    fn test_outlasting_the_trace_history() {
+        assert!(!PersistenceDecay::Frames.outlasts_history(MAX_TRACE_HISTORY));
+        assert!(PersistenceDecay::Frames.outlasts_history(MAX_TRACE_HISTORY + 1));
+        assert!(!PersistenceDecay::HalfSecond.outlasts_history(1));
+        assert!(PersistenceDecay::Infinite.outlasts_history(1));
+    }
+
+    #[test]
+    fn test_decay_frames() {
+        let mut map = IntensityMap::default();
+        map.set_decay_frames(10);
+        assert!((map.decay().powi(10) - RESIDUAL_BRIGHTNESS).abs() < 1e-4);
+    }
+}
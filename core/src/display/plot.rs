@@ -0,0 +1,274 @@
+//! Plots drawn straight into pixels, for saving as PNG without a GPU or a
+//! window: the CLI's spectrum and spectrogram, and the spectrogram history
+//! the frontends export.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::label_font as font;
+use super::{format_time, Palette, Rgba};
+use crate::oscilloscope::spectrum::{DB_MAX, DB_MIN};
+use crate::oscilloscope::waveform::step_1_2_5;
+use crate::oscilloscope::SpectrumScale;
+
+/// Height of the spectrogram itself, between its axes, in pixels
+pub const SPECTROGRAM_HEIGHT: u32 = 512;
+
+/// Room around the spectrogram for its axes: frequency labels on the left,
+/// times below, and half a label's overhang above and to the right
+const LEFT_MARGIN: i32 = 32;
+const BOTTOM_MARGIN: i32 = 14;
+const TOP_MARGIN: i32 = 4;
+const RIGHT_MARGIN: i32 = 24;
+/// Length of the axis ticks
+const TICK: i32 = 3;
+/// Time labels along the spectrogram, at most
+const TIME_LABELS: f32 = 8.0;
+
+/// An RGBA image drawn in display palette colors
+pub struct PlotImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl PlotImage {
+    pub fn new(width: u32, height: u32, background: Rgba) -> Self {
+        let pixel = to_bytes(background);
+        Self {
+            width,
+            height,
+            pixels: pixel.repeat((width * height) as usize),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Four bytes a pixel, row by row from the top left
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Blend `color` over the pixel at (x, y), if it's inside the image
+    pub fn blend(&mut self, x: i32, y: i32, color: Rgba) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+        let index = ((y as u32 * self.width + x as u32) * 4) as usize;
+        let alpha = color[3].clamp(0.0, 1.0);
+        for (channel, &value) in color[..3].iter().enumerate() {
+            let old = self.pixels[index + channel] as f32 / 255.0;
+            let new = old + (value - old) * alpha;
+            self.pixels[index + channel] = (new.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+
+    /// One-pixel line from (x0, y0) to (x1, y1)
+    pub fn line(&mut self, (x0, y0): (i32, i32), (x1, y1): (i32, i32), color: Rgba) {
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).max(1);
+        for step in 0..=steps {
+            let t = step as f32 / steps as f32;
+            let x = x0 as f32 + (x1 - x0) as f32 * t;
+            let y = y0 as f32 + (y1 - y0) as f32 * t;
+            self.blend(x.round() as i32, y.round() as i32, color);
+        }
+    }
+
+    /// `text` in the label font with its top left corner at (x, y)
+    pub fn text(&mut self, x: i32, y: i32, text: &str, color: Rgba) {
+        for (dx, dy) in font::pixels(text) {
+            self.blend(x + dx, y + dy, color);
+        }
+    }
+
+    /// The image as a PNG file's bytes
+    pub fn to_png(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        self.write_png(&mut bytes)
+            .map_err(|e| format!("Failed to encode PNG: {e}"))?;
+        Ok(bytes)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let file =
+            File::create(path).map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+        self.write_png(BufWriter::new(file))
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    fn write_png(&self, out: impl Write) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(out, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.pixels))
+    }
+}
+
+fn to_bytes(color: Rgba) -> [u8; 4] {
+    color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+/// Fraction of the dB range from the floor, 0.0 to 1.0
+pub fn level(db: f32) -> f32 {
+    ((db - DB_MIN) / (DB_MAX - DB_MIN)).clamp(0.0, 1.0)
+}
+
+/// One column per spectrum in `frames`, oldest on the left, lowest
+/// frequency at the bottom, shaded like graded persistence. `bins` is how
+/// many the spectra had when analyzed, for the frequency labels, and
+/// `column_seconds` the time between them; frequencies are labeled as the
+/// display labels them and times every 1-2-5 step.
+pub fn spectrogram_image<'a>(
+    frames: impl ExactSizeIterator<Item = &'a [f32]>,
+    bins: usize,
+    column_seconds: f32,
+    scale: SpectrumScale,
+    sample_rate: u32,
+    palette: &Palette,
+) -> PlotImage {
+    let columns = frames.len().max(1) as i32;
+    let height = SPECTROGRAM_HEIGHT as i32;
+    let mut image = PlotImage::new(
+        (LEFT_MARGIN + columns + RIGHT_MARGIN) as u32,
+        (TOP_MARGIN + height + BOTTOM_MARGIN) as u32,
+        palette.background,
+    );
+    for (x, frame) in frames.enumerate() {
+        if frame.is_empty() {
+            continue;
+        }
+        for y in 0..height {
+            let fraction = 1.0 - (y as f32 + 0.5) / height as f32;
+            let bin = ((fraction * frame.len() as f32) as usize).min(frame.len() - 1);
+            image.blend(
+                LEFT_MARGIN + x as i32,
+                TOP_MARGIN + y,
+                palette.graded(level(frame[bin])),
+            );
+        }
+    }
+
+    let bottom = TOP_MARGIN + height;
+    image.line(
+        (LEFT_MARGIN - 1, TOP_MARGIN),
+        (LEFT_MARGIN - 1, bottom),
+        palette.grid,
+    );
+    image.line(
+        (LEFT_MARGIN - 1, bottom),
+        (LEFT_MARGIN + columns - 1, bottom),
+        palette.grid,
+    );
+
+    for (fraction, label) in scale.frequency_labels(sample_rate, bins) {
+        let y = TOP_MARGIN + ((1.0 - fraction) * (height - 1) as f32).round() as i32;
+        image.line(
+            (LEFT_MARGIN - 1 - TICK, y),
+            (LEFT_MARGIN - 2, y),
+            palette.grid,
+        );
+        let x = LEFT_MARGIN - 2 - TICK - 1 - font::text_width(&label);
+        image.text(x, y - font::GLYPH_HEIGHT / 2, &label, palette.text);
+    }
+
+    // Column x is the spectrum of the window ending (x + 1) columns in
+    let column_seconds = column_seconds.max(f32::MIN_POSITIVE);
+    let duration = columns as f32 * column_seconds;
+    let mut step = 1e-3;
+    while duration / step > TIME_LABELS {
+        step = step_1_2_5(step, true);
+    }
+    // Labels that would run into the last are left out on narrow plots
+    let mut free_from = i32::MIN;
+    for index in 0.. {
+        let seconds = index as f32 * step;
+        if seconds > duration {
+            break;
+        }
+        let x = LEFT_MARGIN + (seconds / column_seconds).round() as i32 - 1;
+        let label = format_time(seconds);
+        let left = x - font::text_width(&label) / 2;
+        if left < free_from {
+            continue;
+        }
+        image.line((x, bottom + 1), (x, bottom + TICK), palette.grid);
+        image.text(left, bottom + TICK + 2, &label, palette.text);
+        free_from = left + font::text_width(&label) + font::ADVANCE;
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blending() {
+        let mut image = PlotImage::new(2, 1, [0.0, 0.0, 0.0, 1.0]);
+        image.blend(0, 0, [1.0, 0.5, 0.0, 0.5]);
+        image.blend(5, 5, [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(&image.pixels[..4], &[128, 64, 0, 255]);
+        assert_eq!(&image.pixels[4..], &[0, 0, 0, 255]);
+
+        image.line((0, 0), (1, 0), [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(&image.pixels[4..], &[255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_label_font() {
+        assert_eq!(font::text_width("10k"), 17);
+        assert_eq!(font::text_width(""), 0);
+        // The vertical of "1", its foot and its flag
+        assert_eq!(font::pixels("1").count(), 10);
+        assert!(font::pixels("1 s").all(|(x, y)| (0..17).contains(&x) && (0..7).contains(&y)));
+    }
+
+    #[test]
+    fn test_spectrogram_has_axes() {
+        let palette = Palette::default();
+        let frames = vec![vec![DB_MIN; 8]; 100];
+        let image = spectrogram_image(
+            frames.iter().map(Vec::as_slice),
+            8,
+            0.01,
+            SpectrumScale::Linear,
+            48000,
+            &palette,
+        );
+        assert_eq!(image.width as i32, LEFT_MARGIN + 100 + RIGHT_MARGIN);
+        assert_eq!(
+            image.height as i32,
+            TOP_MARGIN + SPECTROGRAM_HEIGHT as i32 + BOTTOM_MARGIN
+        );
+
+        let background = to_bytes(palette.background);
+        let labeled = |xs: std::ops::Range<i32>, ys: std::ops::Range<i32>| {
+            ys.flat_map(|y| xs.clone().map(move |x| (x, y)))
+                .any(|(x, y)| {
+                    let index = ((y as u32 * image.width + x as u32) * 4) as usize;
+                    image.pixels[index..index + 4] != background
+                })
+        };
+        // Frequencies left of the ticks, times below them
+        assert!(labeled(0..LEFT_MARGIN - 1 - TICK, 0..image.height as i32));
+        let below = TOP_MARGIN + SPECTROGRAM_HEIGHT as i32 + TICK + 1;
+        assert!(labeled(0..image.width as i32, below..image.height as i32));
+    }
+
+    #[test]
+    fn test_png_has_the_image_size() {
+        let png = PlotImage::new(3, 2, [0.0, 0.0, 0.0, 1.0]).to_png().unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        // Width and height lead the IHDR chunk
+        assert_eq!(&png[16..24], &[0, 0, 0, 3, 0, 0, 0, 2]);
+    }
+}
@@ -0,0 +1,465 @@
+use super::graticule::Graticule;
+use super::units::{format_frequency, format_time, format_volts};
+use crate::oscilloscope::band_power::BandPower;
+use crate::oscilloscope::frequency_track::FrequencyTrack;
+use crate::oscilloscope::harmonics::HarmonicAnalysis;
+use crate::oscilloscope::measurements::Measurements;
+use crate::oscilloscope::phase::PhaseResponse;
+use crate::oscilloscope::time_gate::TimeGate;
+use crate::oscilloscope::vectorscope::StereoBalance;
+
+/// Gap between a label and the edge or line it annotates, in normalized units
+const LABEL_MARGIN: f32 = 0.006;
+
+/// Left edge of the band power readout, clear of the spectrum's dB labels
+const BAND_READOUT_X: f32 = 0.12;
+
+/// Which point of the text `position` refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelAnchor {
+    /// Position is the bottom-left corner of the text
+    BottomLeft,
+    /// Position is the bottom-right corner of the text
+    BottomRight,
+    /// Position is the top-right corner of the text
+    TopRight,
+    /// Position is the top-left corner of the text
+    TopLeft,
+}
+
+/// A text label in normalized screen space: (0, 0) top-left, (1, 1) bottom-right
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxisLabel {
+    pub text: String,
+    pub position: (f32, f32),
+    pub anchor: LabelAnchor,
+}
+
+/// Per-division value labels for the waveform display.
+///
+/// Voltages sit just above each inner horizontal line along the left edge, time
+/// offsets from the left edge sit along the bottom at each inner vertical line,
+/// and the time/voltage scales are shown in the top-right corner.
+pub fn axis_labels(
+    graticule: &Graticule,
+    time_per_division: f32,
+    volts_per_division: f32,
+) -> Vec<AxisLabel> {
+    let columns = graticule.horizontal_divisions.max(1);
+    let rows = graticule.vertical_divisions.max(1);
+    let mut labels = Vec::new();
+
+    // Edge lines are skipped so labels never hang off the display
+    for row in 1..rows {
+        let divisions_above_center = rows as f32 / 2.0 - row as f32;
+        labels.push(AxisLabel {
+            text: format_volts(divisions_above_center * volts_per_division),
+            position: (LABEL_MARGIN, row as f32 / rows as f32 - LABEL_MARGIN),
+            anchor: LabelAnchor::BottomLeft,
+        });
+    }
+
+    for column in 1..columns {
+        labels.push(AxisLabel {
+            text: format_time(column as f32 * time_per_division),
+            position: (
+                column as f32 / columns as f32 - LABEL_MARGIN,
+                1.0 - LABEL_MARGIN,
+            ),
+            anchor: LabelAnchor::BottomRight,
+        });
+    }
+
+    labels.push(AxisLabel {
+        text: format!(
+            "{}/div  {}/div",
+            format_time(time_per_division),
+            format_volts(volts_per_division)
+        ),
+        position: (1.0 - LABEL_MARGIN, LABEL_MARGIN),
+        anchor: LabelAnchor::TopRight,
+    });
+
+    labels
+}
+
+/// The harmonics table for the top-right corner of the spectrum display:
+/// each harmonic's frequency and level, the fundamental's in dB and the rest
+/// in dBc, then the THD
+pub fn harmonic_table(analysis: &HarmonicAnalysis) -> AxisLabel {
+    let mut rows: Vec<String> = analysis
+        .harmonics
+        .iter()
+        .map(|harmonic| {
+            let (level, unit) = if harmonic.number == 1 {
+                (analysis.fundamental_level, "dB ")
+            } else {
+                (harmonic.level, "dBc")
+            };
+            format!(
+                "H{:<2} {:>10} {level:>6.1} {unit}",
+                harmonic.number,
+                format_frequency(harmonic.frequency)
+            )
+        })
+        .collect();
+    rows.push(format!("THD {:.3} %", analysis.thd));
+
+    AxisLabel {
+        text: rows.join("\n"),
+        position: (1.0 - LABEL_MARGIN, LABEL_MARGIN),
+        anchor: LabelAnchor::TopRight,
+    }
+}
+
+/// Labels for the frequency track plot: the frequency of each quarter line
+/// down the left edge, and the mean frequency and deviation in the
+/// top-right corner
+pub fn frequency_track_labels(track: &FrequencyTrack) -> Vec<AxisLabel> {
+    let mut labels: Vec<AxisLabel> = (0..4)
+        .map(|row| {
+            let fraction = row as f32 / 4.0;
+            AxisLabel {
+                text: format_frequency(track.high - fraction * (track.high - track.low)),
+                position: (LABEL_MARGIN, fraction + LABEL_MARGIN),
+                anchor: LabelAnchor::TopLeft,
+            }
+        })
+        .collect();
+
+    labels.push(AxisLabel {
+        text: format!(
+            "{}  ±{}",
+            format_frequency(track.mean()),
+            format_frequency(track.deviation())
+        ),
+        position: (1.0 - LABEL_MARGIN, LABEL_MARGIN),
+        anchor: LabelAnchor::TopRight,
+    });
+
+    labels
+}
+
+/// One line of the scope's measurements for an on-screen readout, "--"
+/// for any the capture doesn't give
+pub fn measurement_readout(measurements: &Measurements) -> String {
+    let or_dashes = |value: Option<String>| value.unwrap_or_else(|| "--".to_string());
+    format!(
+        "Freq {}  Vpp {}  Vrms {}  Duty {}",
+        or_dashes(measurements.frequency.map(format_frequency)),
+        or_dashes(measurements.peak_to_peak.map(format_volts)),
+        or_dashes(measurements.rms.map(format_volts)),
+        or_dashes(measurements.duty_cycle.map(|duty| format!("{duty:.1} %")))
+    )
+}
+
+/// The band power readout for the spectrum display, beside the top dB
+/// label: the band, its power and the noise floor density. In dB SPL when
+/// given a calibration offset, otherwise in dBFS.
+pub fn band_power_label(band: &BandPower, level_offset: Option<f32>) -> AxisLabel {
+    let (offset, unit) = match level_offset {
+        Some(offset) => (offset, "dB SPL"),
+        None => (0.0, "dBFS"),
+    };
+    AxisLabel {
+        text: format!(
+            "{} – {}\nBand {:.1} {unit}\nFloor {:.1} {unit}/√Hz",
+            format_frequency(band.low),
+            format_frequency(band.high),
+            band.power + offset,
+            band.noise_density + offset
+        ),
+        position: (BAND_READOUT_X, LABEL_MARGIN),
+        anchor: LabelAnchor::TopLeft,
+    }
+}
+
+/// Labels for the vectorscope square: the channel diagonals in the top
+/// corners, and the balance and gain in the bottom-left corner
+pub fn vectorscope_labels(balance: &StereoBalance, gain: f32) -> Vec<AxisLabel> {
+    let side_to_mid = match balance.side_to_mid() {
+        Some(db) => format!("{db:.1} dB"),
+        None => "--".to_string(),
+    };
+    vec![
+        AxisLabel {
+            text: "L".to_string(),
+            position: (LABEL_MARGIN, LABEL_MARGIN),
+            anchor: LabelAnchor::TopLeft,
+        },
+        AxisLabel {
+            text: "R".to_string(),
+            position: (1.0 - LABEL_MARGIN, LABEL_MARGIN),
+            anchor: LabelAnchor::TopRight,
+        },
+        AxisLabel {
+            text: format!(
+                "Corr {:+.2}\nS/M {side_to_mid}\nGain ×{gain:.1}",
+                balance.correlation
+            ),
+            position: (LABEL_MARGIN, 1.0 - LABEL_MARGIN),
+            anchor: LabelAnchor::BottomLeft,
+        },
+    ]
+}
+
+/// Length of the time gate, just inside its start cursor along the top of
+/// the waveform display
+pub fn time_gate_label(
+    gate: &TimeGate,
+    x_offset: f32,
+    samples_per_screen: usize,
+    sample_rate: u32,
+) -> AxisLabel {
+    AxisLabel {
+        text: format!(
+            "Gate {}",
+            format_time(gate.duration(samples_per_screen, sample_rate))
+        ),
+        position: (
+            (gate.start + x_offset).clamp(0.0, 1.0) + LABEL_MARGIN,
+            LABEL_MARGIN,
+        ),
+        anchor: LabelAnchor::TopLeft,
+    }
+}
+
+/// Labels for the phase plot: degrees down the left edge, decades of
+/// frequency along the bottom, and the delay and polarity between the
+/// channels in the top-right corner
+pub fn phase_labels(response: &PhaseResponse) -> Vec<AxisLabel> {
+    let mut labels: Vec<AxisLabel> = [180, 90, 0, -90]
+        .into_iter()
+        .map(|degrees| AxisLabel {
+            text: if degrees == 0 {
+                "0°".to_string()
+            } else {
+                format!("{degrees:+}°")
+            },
+            position: (
+                LABEL_MARGIN,
+                1.0 - PhaseResponse::level(degrees as f32) + LABEL_MARGIN,
+            ),
+            anchor: LabelAnchor::TopLeft,
+        })
+        .collect();
+
+    let nyquist = response.sample_rate as f32 / 2.0;
+    labels.extend(
+        [100.0, 1000.0, 10000.0]
+            .into_iter()
+            .filter(|&hertz| hertz < nyquist)
+            .map(|hertz| AxisLabel {
+                text: if hertz >= 1000.0 {
+                    format!("{:.0}k", hertz / 1000.0)
+                } else {
+                    format!("{hertz:.0}")
+                },
+                position: (response.position(hertz) - LABEL_MARGIN, 1.0 - LABEL_MARGIN),
+                anchor: LabelAnchor::BottomRight,
+            }),
+    );
+
+    labels.push(AxisLabel {
+        text: format!(
+            "Delay {}\n{}",
+            format_time(response.delay),
+            if response.inverted {
+                "Polarity inverted"
+            } else {
+                "Polarity normal"
+            }
+        ),
+        position: (1.0 - LABEL_MARGIN, LABEL_MARGIN),
+        anchor: LabelAnchor::TopRight,
+    });
+
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_voltage_labels_are_symmetric() {
+        let labels = axis_labels(&Graticule::default(), 0.001, 0.5);
+        let volts: Vec<&str> = labels
+            .iter()
+            .filter(|l| l.anchor == LabelAnchor::BottomLeft)
+            .map(|l| l.text.as_str())
+            .collect();
+
+        // 8 divisions: 7 inner lines from +1.5 V down to -1.5 V
+        assert_eq!(
+            volts,
+            vec!["1.50 V", "1.00 V", "500 mV", "0 V", "-500 mV", "-1.00 V", "-1.50 V"]
+        );
+    }
+
+    #[test]
+    fn test_time_labels_follow_divisions() {
+        let graticule = Graticule {
+            horizontal_divisions: 12,
+            ..Default::default()
+        };
+        let labels = axis_labels(&graticule, 0.002, 1.0);
+        let times: Vec<&AxisLabel> = labels
+            .iter()
+            .filter(|l| l.anchor == LabelAnchor::BottomRight)
+            .collect();
+
+        assert_eq!(times.len(), 11);
+        assert_eq!(times[0].text, "2.00 ms");
+        assert_eq!(times[10].text, "22.0 ms");
+    }
+
+    #[test]
+    fn test_scale_summary() {
+        let labels = axis_labels(&Graticule::default(), 0.001, 0.5);
+        let summary = labels.last().unwrap();
+        assert_eq!(summary.text, "1.00 ms/div  500 mV/div");
+        assert_eq!(summary.anchor, LabelAnchor::TopRight);
+    }
+
+    #[test]
+    fn test_harmonic_table() {
+        use crate::oscilloscope::harmonics::Harmonic;
+
+        let analysis = HarmonicAnalysis {
+            fundamental: 440.0,
+            fundamental_level: -6.02,
+            harmonics: vec![
+                Harmonic {
+                    number: 1,
+                    frequency: 440.0,
+                    level: 0.0,
+                },
+                Harmonic {
+                    number: 2,
+                    frequency: 880.0,
+                    level: -40.0,
+                },
+                Harmonic {
+                    number: 3,
+                    frequency: 1320.0,
+                    level: -52.5,
+                },
+            ],
+            thd: 1.05,
+        };
+        let table = harmonic_table(&analysis);
+        let rows: Vec<&str> = table.text.lines().collect();
+        assert_eq!(
+            rows,
+            vec![
+                "H1    440.0 Hz   -6.0 dB ",
+                "H2    880.0 Hz  -40.0 dBc",
+                "H3   1.320 kHz  -52.5 dBc",
+                "THD 1.050 %",
+            ]
+        );
+        assert_eq!(table.anchor, LabelAnchor::TopRight);
+    }
+
+    #[test]
+    fn test_frequency_track_labels() {
+        let track = FrequencyTrack {
+            points: vec![(0.0, 430.0), (0.5, 450.0)],
+            low: 400.0,
+            high: 480.0,
+        };
+
+        let labels = frequency_track_labels(&track);
+        let text: Vec<&str> = labels.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(
+            text,
+            [
+                "480.0 Hz",
+                "460.0 Hz",
+                "440.0 Hz",
+                "420.0 Hz",
+                "440.0 Hz  ±10.0 Hz"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_labels_stay_on_screen() {
+        let labels = axis_labels(&Graticule::default(), 0.001, 0.5);
+        assert!(labels.iter().all(|l| {
+            (0.0..=1.0).contains(&l.position.0) && (0.0..=1.0).contains(&l.position.1)
+        }));
+    }
+
+    #[test]
+    fn test_band_power_label() {
+        let band = BandPower {
+            low: 20.0,
+            high: 20000.0,
+            power: -12.34,
+            noise_density: -110.0,
+        };
+        let rows: Vec<String> = band_power_label(&band, None)
+            .text
+            .lines()
+            .map(String::from)
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                "20.0 Hz – 20.000 kHz",
+                "Band -12.3 dBFS",
+                "Floor -110.0 dBFS/√Hz"
+            ]
+        );
+
+        let calibrated = band_power_label(&band, Some(120.0));
+        assert!(calibrated
+            .text
+            .ends_with("Band 107.7 dB SPL\nFloor 10.0 dB SPL/√Hz"));
+    }
+
+    #[test]
+    fn test_measurement_readout() {
+        let measurements = Measurements {
+            frequency: Some(1000.0),
+            peak_to_peak: Some(2.0),
+            rms: Some(0.35),
+            duty_cycle: None,
+        };
+        assert_eq!(
+            measurement_readout(&measurements),
+            "Freq 1.000 kHz  Vpp 2.00 V  Vrms 350 mV  Duty --"
+        );
+    }
+
+    #[test]
+    fn test_phase_labels() {
+        let response = PhaseResponse {
+            points: Vec::new(),
+            delay: 0.00025,
+            inverted: true,
+            sample_rate: 48000,
+        };
+        let labels = phase_labels(&response);
+        let degrees: Vec<&str> = labels
+            .iter()
+            .filter(|l| l.anchor == LabelAnchor::TopLeft)
+            .map(|l| l.text.as_str())
+            .collect();
+        assert_eq!(degrees, vec!["+180°", "+90°", "0°", "-90°"]);
+
+        let frequencies: Vec<&str> = labels
+            .iter()
+            .filter(|l| l.anchor == LabelAnchor::BottomRight)
+            .map(|l| l.text.as_str())
+            .collect();
+        assert_eq!(frequencies, vec!["100", "1k", "10k"]);
+
+        assert_eq!(
+            labels.last().unwrap().text,
+            "Delay 250 µs\nPolarity inverted"
+        );
+    }
+}
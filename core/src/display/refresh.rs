@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+/// How often the display redraws. Lower rates save CPU and GPU time, which
+/// matters most on battery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RefreshRate {
+    Hz15,
+    Hz30,
+    #[default]
+    Hz60,
+    /// As fast as the display takes frames
+    Uncapped,
+}
+
+impl RefreshRate {
+    pub const ALL: [RefreshRate; 4] = [
+        RefreshRate::Hz15,
+        RefreshRate::Hz30,
+        RefreshRate::Hz60,
+        RefreshRate::Uncapped,
+    ];
+
+    /// Look up a rate by its label (case-insensitive)
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|rate| rate.label().eq_ignore_ascii_case(label))
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            RefreshRate::Hz15 => RefreshRate::Hz30,
+            RefreshRate::Hz30 => RefreshRate::Hz60,
+            RefreshRate::Hz60 => RefreshRate::Uncapped,
+            RefreshRate::Uncapped => RefreshRate::Hz15,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RefreshRate::Hz15 => "15 Hz",
+            RefreshRate::Hz30 => "30 Hz",
+            RefreshRate::Hz60 => "60 Hz",
+            RefreshRate::Uncapped => "Uncapped",
+        }
+    }
+
+    /// Time between redraws, `None` when uncapped
+    pub fn interval(&self) -> Option<Duration> {
+        let hz = match self {
+            RefreshRate::Hz15 => 15,
+            RefreshRate::Hz30 => 30,
+            RefreshRate::Hz60 => 60,
+            RefreshRate::Uncapped => return None,
+        };
+        Some(Duration::from_secs(1) / hz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rates_by_label_and_interval() {
+        assert_eq!(RefreshRate::from_label("30 hz"), Some(RefreshRate::Hz30));
+        assert_eq!(
+            RefreshRate::from_label("uncapped"),
+            Some(RefreshRate::Uncapped)
+        );
+        assert_eq!(RefreshRate::from_label("120 Hz"), None);
+        assert_eq!(
+            RefreshRate::Hz15.interval(),
+            Some(Duration::from_nanos(66_666_666))
+        );
+        assert_eq!(RefreshRate::Uncapped.interval(), None);
+    }
+}
@@ -0,0 +1,221 @@
+/// Linear RGBA color, each component 0.0 to 1.0
+pub type Rgba = [f32; 4];
+
+/// Named color schemes for the scope display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DisplayTheme {
+    /// Green phosphor on black
+    #[default]
+    ClassicGreen,
+    /// Amber phosphor on black
+    Amber,
+    /// White trace on black
+    WhiteOnBlack,
+    /// Dark trace on white, for printing and bright rooms
+    Print,
+    /// Okabe-Ito colors that stay distinct under common color blindness
+    ColorblindSafe,
+}
+
+impl DisplayTheme {
+    pub const ALL: [DisplayTheme; 5] = [
+        DisplayTheme::ClassicGreen,
+        DisplayTheme::Amber,
+        DisplayTheme::WhiteOnBlack,
+        DisplayTheme::Print,
+        DisplayTheme::ColorblindSafe,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DisplayTheme::ClassicGreen => "Green",
+            DisplayTheme::Amber => "Amber",
+            DisplayTheme::WhiteOnBlack => "White",
+            DisplayTheme::Print => "Print",
+            DisplayTheme::ColorblindSafe => "Colorblind",
+        }
+    }
+
+    /// Look up a theme by its label (case-insensitive)
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|theme| theme.label().eq_ignore_ascii_case(label))
+    }
+
+    /// The next theme in the cycle, wrapping around
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|t| t == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Whether the background is light, for picking a matching UI chrome
+    pub fn is_light(&self) -> bool {
+        *self == DisplayTheme::Print
+    }
+
+    pub fn palette(&self) -> Palette {
+        match self {
+            DisplayTheme::ClassicGreen => Palette {
+                background: [0.0, 0.0, 0.0, 1.0],
+                grid: [0.0, 1.0, 0.16, 0.3],
+                grid_center: [0.0, 1.0, 0.16, 0.5],
+                trace: [0.0, 1.0, 0.16, 1.0],
+                math: [0.2, 0.85, 1.0, 1.0],
+                text: [0.0, 1.0, 0.25, 0.7],
+                accent: [1.0, 0.75, 0.0, 1.0],
+                error: [1.0, 0.25, 0.25, 1.0],
+            },
+            DisplayTheme::Amber => Palette {
+                background: [0.0, 0.0, 0.0, 1.0],
+                grid: [1.0, 0.6, 0.0, 0.25],
+                grid_center: [1.0, 0.6, 0.0, 0.45],
+                trace: [1.0, 0.7, 0.0, 1.0],
+                math: [1.0, 0.4, 0.2, 1.0],
+                text: [1.0, 0.7, 0.0, 0.7],
+                accent: [1.0, 0.95, 0.6, 1.0],
+                error: [1.0, 0.25, 0.25, 1.0],
+            },
+            DisplayTheme::WhiteOnBlack => Palette {
+                background: [0.0, 0.0, 0.0, 1.0],
+                grid: [1.0, 1.0, 1.0, 0.2],
+                grid_center: [1.0, 1.0, 1.0, 0.4],
+                trace: [1.0, 1.0, 1.0, 1.0],
+                math: [1.0, 0.92, 0.2, 1.0],
+                text: [1.0, 1.0, 1.0, 0.7],
+                accent: [0.4, 0.8, 1.0, 1.0],
+                error: [1.0, 0.3, 0.3, 1.0],
+            },
+            DisplayTheme::Print => Palette {
+                background: [1.0, 1.0, 1.0, 1.0],
+                grid: [0.0, 0.0, 0.0, 0.15],
+                grid_center: [0.0, 0.0, 0.0, 0.35],
+                trace: [0.0, 0.0, 0.0, 1.0],
+                math: [0.7, 0.0, 0.5, 1.0],
+                text: [0.0, 0.0, 0.0, 0.8],
+                accent: [0.0, 0.3, 0.8, 1.0],
+                error: [0.8, 0.0, 0.0, 1.0],
+            },
+            DisplayTheme::ColorblindSafe => Palette {
+                background: [0.0, 0.0, 0.0, 1.0],
+                grid: [0.34, 0.71, 0.91, 0.25], // sky blue
+                grid_center: [0.34, 0.71, 0.91, 0.45],
+                trace: [0.9, 0.62, 0.0, 1.0], // orange
+                math: [0.0, 0.62, 0.45, 1.0], // bluish green
+                text: [0.34, 0.71, 0.91, 0.8],
+                accent: [0.94, 0.89, 0.26, 1.0], // yellow
+                error: [0.8, 0.47, 0.65, 1.0],   // reddish purple
+            },
+        }
+    }
+}
+
+/// Colors used by every scope renderer (iced canvas, wgpu, WebGL)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub background: Rgba,
+    pub grid: Rgba,
+    pub grid_center: Rgba,
+    pub trace: Rgba,
+    /// The math channel's trace
+    pub math: Rgba,
+    pub text: Rgba,   // axis labels and readouts
+    pub accent: Rgba, // overlays such as protocol decode
+    pub error: Rgba,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        DisplayTheme::default().palette()
+    }
+}
+
+impl Palette {
+    /// The same color with its alpha scaled, for persistence fading
+    pub fn faded(color: Rgba, alpha: f32) -> Rgba {
+        [
+            color[0],
+            color[1],
+            color[2],
+            color[3] * alpha.clamp(0.0, 1.0),
+        ]
+    }
+
+    /// Trace color for an intensity-graded persistence cell (0.0 to 1.0).
+    ///
+    /// Dim paths are faint; busy paths become opaque and, on dark
+    /// backgrounds, bloom toward white like an overdriven phosphor.
+    pub fn graded(&self, intensity: f32) -> Rgba {
+        let intensity = intensity.clamp(0.0, 1.0);
+        let [r, g, b, _] = self.trace;
+        let background = self.background;
+        let light_background =
+            0.2126 * background[0] + 0.7152 * background[1] + 0.0722 * background[2] > 0.5;
+
+        let bloom = if light_background {
+            0.0
+        } else {
+            intensity * intensity * 0.6
+        };
+        [
+            r + (1.0 - r) * bloom,
+            g + (1.0 - g) * bloom,
+            b + (1.0 - b) * bloom,
+            0.1 + 0.9 * intensity,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_cycles_through_all() {
+        let mut theme = DisplayTheme::default();
+        for _ in 0..DisplayTheme::ALL.len() {
+            theme = theme.next();
+        }
+        assert_eq!(theme, DisplayTheme::default());
+        assert_eq!(DisplayTheme::ClassicGreen.next(), DisplayTheme::Amber);
+    }
+
+    #[test]
+    fn test_from_label() {
+        assert_eq!(DisplayTheme::from_label("amber"), Some(DisplayTheme::Amber));
+        assert_eq!(DisplayTheme::from_label("PRINT"), Some(DisplayTheme::Print));
+        assert_eq!(DisplayTheme::from_label("plaid"), None);
+    }
+
+    #[test]
+    fn test_trace_contrasts_with_background() {
+        let luminance = |c: Rgba| 0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2];
+        for theme in DisplayTheme::ALL {
+            let palette = theme.palette();
+            let contrast = (luminance(palette.trace) - luminance(palette.background)).abs();
+            assert!(contrast > 0.4, "{} trace is too dim", theme.label());
+            assert_eq!(palette.background[3], 1.0);
+        }
+    }
+
+    #[test]
+    fn test_graded_brightens_with_intensity() {
+        let palette = DisplayTheme::ClassicGreen.palette();
+        let dim = palette.graded(0.1);
+        let bright = palette.graded(1.0);
+        assert!(bright[3] > dim[3]);
+        assert!(bright[0] > dim[0]); // blooms toward white
+
+        // No bloom on a light background; the trace stays dark
+        let print = DisplayTheme::Print.palette();
+        assert_eq!(&print.graded(1.0)[..3], &[0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_faded() {
+        let color = Palette::faded([0.0, 1.0, 0.0, 0.5], 0.5);
+        assert_eq!(color, [0.0, 1.0, 0.0, 0.25]);
+    }
+}
@@ -0,0 +1,133 @@
+use super::theme::{Palette, Rgba};
+
+/// Colors offered for a trace, after the theme's own
+pub const TRACE_COLOR_CHOICES: [(&str, Rgba); 8] = [
+    ("Green", [0.0, 1.0, 0.16, 1.0]),
+    ("Amber", [1.0, 0.7, 0.0, 1.0]),
+    ("Yellow", [1.0, 0.92, 0.2, 1.0]),
+    ("Cyan", [0.2, 0.85, 1.0, 1.0]),
+    ("Blue", [0.3, 0.45, 1.0, 1.0]),
+    ("Magenta", [1.0, 0.3, 0.85, 1.0]),
+    ("Red", [1.0, 0.3, 0.3, 1.0]),
+    ("White", [1.0, 1.0, 1.0, 1.0]),
+];
+
+/// Trace colors chosen in place of the theme's, `None` keeping the theme's
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraceColors {
+    /// The input channel's trace
+    pub trace: Option<Rgba>,
+    pub math: Option<Rgba>,
+}
+
+impl TraceColors {
+    /// The theme's palette with these colors in it
+    pub fn apply(&self, palette: Palette) -> Palette {
+        Palette {
+            trace: self.trace.unwrap_or(palette.trace),
+            math: self.math.unwrap_or(palette.math),
+            ..palette
+        }
+    }
+}
+
+/// The choice after `color`: the theme's color, then each of
+/// `TRACE_COLOR_CHOICES`, then the theme's again. A color that isn't one of
+/// the choices goes to the first.
+pub fn next_trace_color(color: Option<Rgba>) -> Option<Rgba> {
+    let Some(color) = color else {
+        return Some(TRACE_COLOR_CHOICES[0].1);
+    };
+    TRACE_COLOR_CHOICES
+        .iter()
+        .position(|&(_, choice)| same_color(choice, color))
+        .map_or(Some(TRACE_COLOR_CHOICES[0].1), |index| {
+            TRACE_COLOR_CHOICES.get(index + 1).map(|&(_, next)| next)
+        })
+}
+
+/// Name of a trace color: "Theme", a choice's name, or its hex code
+pub fn trace_color_label(color: Option<Rgba>) -> String {
+    match color {
+        None => "Theme".to_string(),
+        Some(color) => TRACE_COLOR_CHOICES
+            .iter()
+            .find(|&&(_, choice)| same_color(choice, color))
+            .map_or_else(|| color_to_hex(color), |(name, _)| name.to_string()),
+    }
+}
+
+/// Equal once saved, as a color read back from the preferences is
+fn same_color(a: Rgba, b: Rgba) -> bool {
+    color_to_hex(a) == color_to_hex(b)
+}
+
+/// `#rrggbb`, opaque
+pub fn color_to_hex(color: Rgba) -> String {
+    let [r, g, b] =
+        [color[0], color[1], color[2]].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Read `#rrggbb`, as written by `color_to_hex`
+pub fn color_from_hex(text: &str) -> Option<Rgba> {
+    let digits = text.strip_prefix('#')?;
+    if digits.len() != 6 || !digits.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| {
+        u8::from_str_radix(&digits[i..i + 2], 16)
+            .ok()
+            .map(|c| c as f32 / 255.0)
+    };
+    Some([channel(0)?, channel(2)?, channel(4)?, 1.0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::DisplayTheme;
+
+    #[test]
+    fn test_cycle_and_labels() {
+        let mut color = None;
+        let mut labels = Vec::new();
+        for _ in 0..=TRACE_COLOR_CHOICES.len() {
+            color = next_trace_color(color);
+            labels.push(trace_color_label(color));
+        }
+        assert_eq!(labels.first().map(String::as_str), Some("Green"));
+        assert_eq!(labels.last().map(String::as_str), Some("Theme"));
+
+        let custom = Some([0.5, 0.25, 0.0, 1.0]);
+        assert_eq!(trace_color_label(custom), "#804000");
+        assert_eq!(next_trace_color(custom), Some(TRACE_COLOR_CHOICES[0].1));
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        for (name, color) in TRACE_COLOR_CHOICES {
+            // A saved choice is still recognized when read back
+            let parsed = color_from_hex(&color_to_hex(color));
+            assert_eq!(trace_color_label(parsed), name);
+        }
+        assert_eq!(color_from_hex("#ff0000"), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(color_from_hex("ff0000"), None);
+        assert_eq!(color_from_hex("#ff00"), None);
+        assert_eq!(color_from_hex("#gg0000"), None);
+    }
+
+    #[test]
+    fn test_apply_keeps_the_rest_of_the_theme() {
+        let palette = DisplayTheme::Amber.palette();
+        let colors = TraceColors {
+            math: Some([1.0, 0.0, 0.0, 1.0]),
+            ..TraceColors::default()
+        };
+        let applied = colors.apply(palette);
+        assert_eq!(applied.trace, palette.trace);
+        assert_eq!(applied.math, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(applied.background, palette.background);
+    }
+}
@@ -0,0 +1,66 @@
+/// SI prefixes a readout can take, smallest first
+const PREFIXES: [(f32, &str); 3] = [(1e-6, "µ"), (1e-3, "m"), (1.0, "")];
+
+/// A time in µs, ms or s, whichever keeps three significant figures in
+/// front of the point: "10.0 µs", "2.50 ms", "1.20 s"
+pub fn format_time(seconds: f32) -> String {
+    format_si(seconds, "s")
+}
+
+/// A voltage in µV, mV or V, like `format_time`
+pub fn format_volts(volts: f32) -> String {
+    format_si(volts, "V")
+}
+
+pub fn format_frequency(hertz: f32) -> String {
+    if hertz >= 1000.0 {
+        format!("{:.3} kHz", hertz / 1000.0)
+    } else {
+        format!("{hertz:.1} Hz")
+    }
+}
+
+fn format_si(value: f32, unit: &str) -> String {
+    // Avoid printing "-0 V" on the center line
+    if value.abs() < 1e-9 || !value.is_finite() {
+        return format!("0 {unit}");
+    }
+
+    // Rounded first, so 999.6 mV reads "1.00 V" rather than "1000 mV"
+    let magnitude = value.abs().log10().floor() as i32;
+    let quantum = 10_f64.powi(magnitude - 2);
+    let rounded = ((value as f64 / quantum).round() * quantum) as f32;
+
+    let (factor, prefix) = PREFIXES
+        .iter()
+        .rev()
+        .find(|(factor, _)| rounded.abs() >= factor * 0.9999)
+        .unwrap_or(&PREFIXES[0]);
+    let scaled = rounded / factor;
+    let decimals = match scaled.abs() {
+        size if size < 10.0 => 2,
+        size if size < 100.0 => 1,
+        _ => 0,
+    };
+    format!("{scaled:.decimals$} {prefix}{unit}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_picks_the_prefix() {
+        assert_eq!(format_time(0.00001), "10.0 µs");
+        assert_eq!(format_time(0.0025), "2.50 ms");
+        assert_eq!(format_time(0.5), "500 ms");
+        assert_eq!(format_time(1.2), "1.20 s");
+        assert_eq!(format_volts(-0.05), "-50.0 mV");
+        assert_eq!(format_volts(2.0), "2.00 V");
+        assert_eq!(format_volts(0.0), "0 V");
+        assert_eq!(format_volts(-1e-12), "0 V");
+        // Rounding carries into the next prefix
+        assert_eq!(format_volts(0.9996), "1.00 V");
+        assert_eq!(format_time(0.0009999), "1.00 ms");
+    }
+}
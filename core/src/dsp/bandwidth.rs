@@ -0,0 +1,132 @@
+use super::biquad::{Biquad, BiquadFilter};
+use super::chain::BUTTERWORTH_Q;
+
+/// Bandwidth of an input channel, like the BW-limit button on a hardware
+/// scope: a low-pass that takes the hiss off low-frequency content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BandwidthLimit {
+    #[default]
+    Full,
+    Limit20k,
+    Limit5k,
+    Limit1k,
+}
+
+impl BandwidthLimit {
+    pub fn next(self) -> Self {
+        match self {
+            BandwidthLimit::Full => BandwidthLimit::Limit20k,
+            BandwidthLimit::Limit20k => BandwidthLimit::Limit5k,
+            BandwidthLimit::Limit5k => BandwidthLimit::Limit1k,
+            BandwidthLimit::Limit1k => BandwidthLimit::Full,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BandwidthLimit::Full => "Full",
+            BandwidthLimit::Limit20k => "20 kHz",
+            BandwidthLimit::Limit5k => "5 kHz",
+            BandwidthLimit::Limit1k => "1 kHz",
+        }
+    }
+
+    /// -3 dB point of the low-pass, if there is one
+    pub fn cutoff(self) -> Option<f32> {
+        match self {
+            BandwidthLimit::Full => None,
+            BandwidthLimit::Limit20k => Some(20000.0),
+            BandwidthLimit::Limit5k => Some(5000.0),
+            BandwidthLimit::Limit1k => Some(1000.0),
+        }
+    }
+}
+
+/// One channel's bandwidth limit as a second-order Butterworth low-pass.
+/// A cutoff the sample rate can't place below Nyquist leaves the channel
+/// at full bandwidth.
+#[derive(Debug, Clone)]
+pub struct BandwidthFilter {
+    limit: BandwidthLimit,
+    sample_rate: u32,
+    filter: Option<BiquadFilter>,
+}
+
+impl BandwidthFilter {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            limit: BandwidthLimit::Full,
+            sample_rate,
+            filter: None,
+        }
+    }
+
+    pub fn limit(&self) -> BandwidthLimit {
+        self.limit
+    }
+
+    /// Rebuilds the low-pass only when the limit changes, so its state
+    /// carries across calls
+    pub fn set_limit(&mut self, limit: BandwidthLimit) {
+        if limit == self.limit {
+            return;
+        }
+        self.limit = limit;
+        let nyquist = self.sample_rate as f32 / 2.0;
+        self.filter = limit
+            .cutoff()
+            .filter(|&cutoff| cutoff < 0.9 * nyquist)
+            .map(|cutoff| {
+                BiquadFilter::new(Biquad::low_pass(cutoff, BUTTERWORTH_Q, self.sample_rate))
+            });
+    }
+
+    pub fn process_sample(&mut self, x: f32) -> f32 {
+        match self.filter {
+            Some(ref mut filter) => filter.process(x),
+            None => x,
+        }
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        if let Some(ref mut filter) = self.filter {
+            for sample in samples {
+                *sample = filter.process(*sample);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    /// Peak out over peak in, past the filter's settling
+    fn tone_level(filter: &mut BandwidthFilter, frequency: f32) -> f32 {
+        let input: Vec<f32> = (0..48000)
+            .map(|i| (2.0 * PI * frequency * i as f32 / 48000.0).sin())
+            .collect();
+        let mut output = input.clone();
+        filter.process(&mut output);
+        let peak = |samples: &[f32]| samples[24000..].iter().fold(0.0_f32, |p, s| p.max(s.abs()));
+        peak(&output) / peak(&input)
+    }
+
+    #[test]
+    fn test_limit_attenuates_above_cutoff() {
+        let mut filter = BandwidthFilter::new(48000);
+        assert!((tone_level(&mut filter, 8000.0) - 1.0).abs() < 1e-3);
+
+        filter.set_limit(BandwidthLimit::Limit1k);
+        assert!((tone_level(&mut filter, 100.0) - 1.0).abs() < 0.01);
+        // Second order: 12 dB per octave, three octaves up
+        assert!(tone_level(&mut filter, 8000.0) < 0.02);
+
+        // 20 kHz is past what 32 kHz sampling can place
+        let mut filter = BandwidthFilter::new(32000);
+        filter.set_limit(BandwidthLimit::Limit20k);
+        assert_eq!(filter.process_sample(0.5), 0.5);
+    }
+}
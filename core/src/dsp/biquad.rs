@@ -0,0 +1,220 @@
+use std::f64::consts::PI;
+
+/// Highest corner a design will place, as a fraction of the sample rate,
+/// keeping it clear of Nyquist
+const MAX_CORNER: f64 = 0.49;
+
+/// Lowest corner a design will place, in Hz
+const MIN_CORNER: f64 = 1.0;
+
+/// Second-order IIR section, normalized so `a0` is 1:
+///
+/// `y[n] = b0 x[n] + b1 x[n-1] + b2 x[n-2] - a1 y[n-1] - a2 y[n-2]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Biquad {
+    pub b0: f64,
+    pub b1: f64,
+    pub b2: f64,
+    pub a1: f64,
+    pub a2: f64,
+}
+
+impl Biquad {
+    /// Passes everything unchanged
+    pub const IDENTITY: Biquad = Biquad {
+        b0: 1.0,
+        b1: 0.0,
+        b2: 0.0,
+        a1: 0.0,
+        a2: 0.0,
+    };
+
+    /// Scales by `db`
+    pub fn gain(db: f32) -> Self {
+        Biquad {
+            b0: 10_f64.powf(db as f64 / 20.0),
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Second-order low-pass, -3 dB at `frequency` when `q` is 0.707
+    pub fn low_pass(frequency: f32, q: f32, sample_rate: u32) -> Self {
+        let (cos, alpha) = corner(frequency, q, sample_rate);
+        Self::normalized(
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// Second-order high-pass, -3 dB at `frequency` when `q` is 0.707
+    pub fn high_pass(frequency: f32, q: f32, sample_rate: u32) -> Self {
+        let (cos, alpha) = corner(frequency, q, sample_rate);
+        Self::normalized(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// Removes `frequency`; higher `q` narrows the notch
+    pub fn notch(frequency: f32, q: f32, sample_rate: u32) -> Self {
+        let (cos, alpha) = corner(frequency, q, sample_rate);
+        Self::normalized(
+            [1.0, -2.0 * cos, 1.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// Digital equivalent of the analog section
+    /// `(n[0] s² + n[1] s + n[2]) / (d[0] s² + d[1] s + d[2])`, by the
+    /// bilinear transform. Pole and zero frequencies should be prewarped
+    /// with `prewarp`.
+    pub fn bilinear(n: [f64; 3], d: [f64; 3], sample_rate: u32) -> Self {
+        let k = 2.0 * sample_rate as f64;
+        let transform = |c: [f64; 3]| {
+            [
+                c[0] * k * k + c[1] * k + c[2],
+                2.0 * (c[2] - c[0] * k * k),
+                c[0] * k * k - c[1] * k + c[2],
+            ]
+        };
+        Self::normalized(transform(n), transform(d))
+    }
+
+    /// Digital equivalent of the first-order analog section
+    /// `(n[0] s + n[1]) / (d[0] s + d[1])`, by the bilinear transform
+    pub fn bilinear_first_order(n: [f64; 2], d: [f64; 2], sample_rate: u32) -> Self {
+        let k = 2.0 * sample_rate as f64;
+        let transform = |c: [f64; 2]| [c[0] * k + c[1], c[1] - c[0] * k, 0.0];
+        Self::normalized(transform(n), transform(d))
+    }
+
+    fn normalized(b: [f64; 3], a: [f64; 3]) -> Self {
+        Biquad {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+        }
+    }
+
+    /// Linear gain at `frequency`
+    pub fn magnitude(&self, frequency: f32, sample_rate: u32) -> f64 {
+        let w = 2.0 * PI * frequency as f64 / sample_rate as f64;
+        // Evaluate each polynomial in z⁻¹ = e^(-jw)
+        let evaluate = |c0: f64, c1: f64, c2: f64| {
+            let re = c0 + c1 * w.cos() + c2 * (2.0 * w).cos();
+            let im = -(c1 * w.sin() + c2 * (2.0 * w).sin());
+            re.hypot(im)
+        };
+        evaluate(self.b0, self.b1, self.b2) / evaluate(1.0, self.a1, self.a2)
+    }
+
+    /// Whether the poles are inside the unit circle, so the output can't
+    /// run away
+    pub fn is_stable(&self) -> bool {
+        self.a2.abs() < 1.0 && self.a1.abs() < 1.0 + self.a2
+    }
+}
+
+/// Angular frequency to put in an analog prototype so the bilinear
+/// transform lands its corner on `frequency`
+pub fn prewarp(frequency: f64, sample_rate: u32) -> f64 {
+    let frequency = frequency.min(MAX_CORNER * sample_rate as f64);
+    2.0 * sample_rate as f64 * (PI * frequency / sample_rate as f64).tan()
+}
+
+/// cos(w0) and alpha of the cookbook designs
+fn corner(frequency: f32, q: f32, sample_rate: u32) -> (f64, f64) {
+    let frequency = (frequency as f64).clamp(MIN_CORNER, MAX_CORNER * sample_rate as f64);
+    let w0 = 2.0 * PI * frequency / sample_rate as f64;
+    let q = (q as f64).max(0.1);
+    (w0.cos(), w0.sin() / (2.0 * q))
+}
+
+/// A biquad with its delay line, in transposed direct form II
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiquadFilter {
+    pub coefficients: Biquad,
+    z1: f64,
+    z2: f64,
+}
+
+impl BiquadFilter {
+    pub fn new(coefficients: Biquad) -> Self {
+        Self {
+            coefficients,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, x: f32) -> f32 {
+        let c = &self.coefficients;
+        let x = x as f64;
+        let y = c.b0 * x + self.z1;
+        self.z1 = c.b1 * x - c.a1 * y + self.z2;
+        self.z2 = c.b2 * x - c.a2 * y;
+        y as f32
+    }
+
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db(gain: f64) -> f64 {
+        20.0 * gain.log10()
+    }
+
+    #[test]
+    fn test_cookbook_responses() {
+        let rate = 48000;
+        let low_pass = Biquad::low_pass(1000.0, 0.707, rate);
+        assert!(db(low_pass.magnitude(10.0, rate)).abs() < 0.01);
+        assert!((db(low_pass.magnitude(1000.0, rate)) + 3.0).abs() < 0.1);
+        assert!(db(low_pass.magnitude(10000.0, rate)) < -35.0);
+
+        let high_pass = Biquad::high_pass(1000.0, 0.707, rate);
+        assert!(db(high_pass.magnitude(100.0, rate)) < -35.0);
+        assert!((db(high_pass.magnitude(1000.0, rate)) + 3.0).abs() < 0.1);
+        assert!(db(high_pass.magnitude(20000.0, rate)).abs() < 0.1);
+
+        let notch = Biquad::notch(50.0, 10.0, rate);
+        assert!(db(notch.magnitude(50.0, rate)) < -60.0);
+        assert!(db(notch.magnitude(500.0, rate)).abs() < 0.1);
+
+        assert!((db(Biquad::gain(-6.0).magnitude(1234.0, rate)) + 6.0).abs() < 1e-9);
+        for biquad in [low_pass, high_pass, notch] {
+            assert!(biquad.is_stable());
+        }
+        assert!(!Biquad {
+            a2: 1.5,
+            ..Biquad::IDENTITY
+        }
+        .is_stable());
+    }
+
+    #[test]
+    fn test_filter_settles_to_design() {
+        // A tone well inside the stop band comes out attenuated as designed
+        let rate = 48000;
+        let biquad = Biquad::low_pass(500.0, 0.707, rate);
+        let mut filter = BiquadFilter::new(biquad);
+        let output: Vec<f32> = (0..9600)
+            .map(|i| filter.process((2.0 * PI * 5000.0 * i as f64 / rate as f64).sin() as f32))
+            .collect();
+        let peak = output[4800..]
+            .iter()
+            .fold(0.0_f32, |peak, &y| peak.max(y.abs()));
+        assert!((peak as f64 - biquad.magnitude(5000.0, rate)).abs() < 0.005);
+
+        filter.reset();
+        assert_eq!(filter.process(0.0), 0.0);
+    }
+}
@@ -0,0 +1,539 @@
+use super::biquad::{prewarp, Biquad, BiquadFilter};
+use super::design::{FilterDesign, FilterFamily, FilterResponse};
+use super::fir::FirFilter;
+
+/// Q of a maximally flat second-order section
+pub const BUTTERWORTH_Q: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Pole frequencies of the IEC 61672 weighting curves, in Hz
+const WEIGHTING_POLES: [f64; 4] = [20.598997, 107.65265, 737.86223, 12194.217];
+
+/// Weighting curves are normalized to 0 dB here
+const WEIGHTING_REFERENCE: f32 = 1000.0;
+
+/// IEC 61672 frequency weighting, to read levels the way sound level
+/// meters do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weighting {
+    /// Follows the ear at low levels; the usual one for noise
+    A,
+    /// Nearly flat across the audio band, for peaks and loud sounds
+    C,
+}
+
+impl Weighting {
+    pub fn next(self) -> Self {
+        match self {
+            Weighting::A => Weighting::C,
+            Weighting::C => Weighting::A,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Weighting::A => "A",
+            Weighting::C => "C",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        [Weighting::A, Weighting::C]
+            .into_iter()
+            .find(|weighting| weighting.label().eq_ignore_ascii_case(label))
+    }
+
+    fn sections(self, sample_rate: u32) -> Vec<Biquad> {
+        let [w1, w2, w3, w4] = WEIGHTING_POLES.map(|pole| prewarp(pole, sample_rate));
+        let mut sections = vec![Biquad::bilinear(
+            [1.0, 0.0, 0.0],
+            [1.0, 2.0 * w1, w1 * w1],
+            sample_rate,
+        )];
+        if self == Weighting::A {
+            sections.push(Biquad::bilinear(
+                [1.0, 0.0, 0.0],
+                [1.0, w2 + w3, w2 * w3],
+                sample_rate,
+            ));
+        }
+        sections.push(Biquad::bilinear(
+            [0.0, 0.0, w4 * w4],
+            [1.0, 2.0 * w4, w4 * w4],
+            sample_rate,
+        ));
+
+        let gain: f64 = sections
+            .iter()
+            .map(|section| section.magnitude(WEIGHTING_REFERENCE, sample_rate))
+            .product();
+        let first = &mut sections[0];
+        first.b0 /= gain;
+        first.b1 /= gain;
+        first.b2 /= gain;
+        sections
+    }
+}
+
+/// What one block of a filter chain does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterKind {
+    Gain {
+        db: f32,
+    },
+    HighPass {
+        frequency: f32,
+        q: f32,
+    },
+    LowPass {
+        frequency: f32,
+        q: f32,
+    },
+    Notch {
+        frequency: f32,
+        q: f32,
+    },
+    Weighting(Weighting),
+    /// Coefficients entered by hand
+    Biquad(Biquad),
+    /// From the filter designer
+    Designed(FilterDesign),
+}
+
+impl FilterKind {
+    /// One of each kind, as blocks are first added. Designed blocks come
+    /// from the designer instead.
+    pub const DEFAULTS: [FilterKind; 6] = [
+        FilterKind::Gain { db: 0.0 },
+        FilterKind::HighPass {
+            frequency: 20.0,
+            q: BUTTERWORTH_Q,
+        },
+        FilterKind::LowPass {
+            frequency: 20000.0,
+            q: BUTTERWORTH_Q,
+        },
+        FilterKind::Notch {
+            frequency: 50.0,
+            q: 10.0,
+        },
+        FilterKind::Weighting(Weighting::A),
+        FilterKind::Biquad(Biquad::IDENTITY),
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FilterKind::Gain { .. } => "Gain",
+            FilterKind::HighPass { .. } => "High-pass",
+            FilterKind::LowPass { .. } => "Low-pass",
+            FilterKind::Notch { .. } => "Notch",
+            FilterKind::Weighting(_) => "Weighting",
+            FilterKind::Biquad(_) => "Biquad",
+            FilterKind::Designed(design) => design.response.label(),
+        }
+    }
+
+    /// Cascaded sections that make up this block at `sample_rate`; none
+    /// for an FIR design, which is realized from its taps
+    pub fn sections(&self, sample_rate: u32) -> Vec<Biquad> {
+        match *self {
+            FilterKind::Gain { db } => vec![Biquad::gain(db)],
+            FilterKind::HighPass { frequency, q } => {
+                vec![Biquad::high_pass(frequency, q, sample_rate)]
+            }
+            FilterKind::LowPass { frequency, q } => {
+                vec![Biquad::low_pass(frequency, q, sample_rate)]
+            }
+            FilterKind::Notch { frequency, q } => vec![Biquad::notch(frequency, q, sample_rate)],
+            FilterKind::Weighting(weighting) => weighting.sections(sample_rate),
+            FilterKind::Biquad(biquad) => vec![biquad],
+            FilterKind::Designed(design) => design.sections(sample_rate),
+        }
+    }
+}
+
+/// A block in a filter chain, as it's edited and saved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilterBlock {
+    pub kind: FilterKind,
+    /// Left in the chain but passing the signal through untouched
+    pub bypassed: bool,
+}
+
+impl FilterBlock {
+    pub fn new(kind: FilterKind) -> Self {
+        Self {
+            kind,
+            bypassed: false,
+        }
+    }
+
+    /// A block read back from `to_text`
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut words: Vec<&str> = text.split_whitespace().collect();
+        let bypassed = words.last() == Some(&"bypassed");
+        if bypassed {
+            words.pop();
+        }
+        let (&name, args) = words.split_first()?;
+        if name == "weighting" {
+            let &[label] = args else {
+                return None;
+            };
+            let kind = FilterKind::Weighting(Weighting::from_label(label)?);
+            return Some(Self { kind, bypassed });
+        }
+        if name == "design" {
+            let &[family, response, order, cutoff, upper_cutoff] = args else {
+                return None;
+            };
+            let frequency =
+                |arg: &str| arg.parse().ok().filter(|x: &f32| x.is_finite() && *x > 0.0);
+            let design = FilterDesign {
+                family: FilterFamily::from_label(family)?,
+                response: FilterResponse::from_label(response)?,
+                order: order.parse().ok()?,
+                cutoff: frequency(cutoff)?,
+                upper_cutoff: frequency(upper_cutoff)?,
+            };
+            let kind = FilterKind::Designed(design.with_family(design.family));
+            return Some(Self { kind, bypassed });
+        }
+        let numbers: Vec<f64> = args
+            .iter()
+            .map(|arg| arg.parse().ok().filter(|x: &f64| x.is_finite()))
+            .collect::<Option<_>>()?;
+        let positive = |x: f64| (x > 0.0).then_some(x as f32);
+
+        let kind = match (name, numbers.as_slice()) {
+            ("gain", &[db]) => FilterKind::Gain { db: db as f32 },
+            ("highpass", &[frequency, q]) => FilterKind::HighPass {
+                frequency: positive(frequency)?,
+                q: positive(q)?,
+            },
+            ("lowpass", &[frequency, q]) => FilterKind::LowPass {
+                frequency: positive(frequency)?,
+                q: positive(q)?,
+            },
+            ("notch", &[frequency, q]) => FilterKind::Notch {
+                frequency: positive(frequency)?,
+                q: positive(q)?,
+            },
+            ("biquad", &[b0, b1, b2, a1, a2]) => FilterKind::Biquad(Biquad { b0, b1, b2, a1, a2 }),
+            _ => return None,
+        };
+        Some(Self { kind, bypassed })
+    }
+
+    /// One line, e.g. `highpass 20 0.7071`, `weighting A bypassed` or
+    /// `design Butterworth Low-pass 4 1000 4000`
+    pub fn to_text(&self) -> String {
+        let kind = match self.kind {
+            FilterKind::Gain { db } => format!("gain {db}"),
+            FilterKind::HighPass { frequency, q } => format!("highpass {frequency} {q}"),
+            FilterKind::LowPass { frequency, q } => format!("lowpass {frequency} {q}"),
+            FilterKind::Notch { frequency, q } => format!("notch {frequency} {q}"),
+            FilterKind::Weighting(weighting) => format!("weighting {}", weighting.label()),
+            FilterKind::Biquad(Biquad { b0, b1, b2, a1, a2 }) => {
+                format!("biquad {b0} {b1} {b2} {a1} {a2}")
+            }
+            FilterKind::Designed(design) => format!(
+                "design {} {} {} {} {}",
+                design.family.label(),
+                design.response.label(),
+                design.order,
+                design.cutoff,
+                design.upper_cutoff
+            ),
+        };
+        if self.bypassed {
+            format!("{kind} bypassed")
+        } else {
+            kind
+        }
+    }
+}
+
+/// One filter in the signal path
+#[derive(Debug, Clone)]
+enum Stage {
+    Biquad(BiquadFilter),
+    Fir(FirFilter),
+}
+
+impl Stage {
+    fn process(&mut self, samples: &mut [f32]) {
+        match self {
+            Stage::Biquad(filter) => {
+                for sample in samples.iter_mut() {
+                    *sample = filter.process(*sample);
+                }
+            }
+            Stage::Fir(filter) => {
+                for sample in samples.iter_mut() {
+                    *sample = filter.process(*sample);
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            Stage::Biquad(filter) => filter.reset(),
+            Stage::Fir(filter) => filter.reset(),
+        }
+    }
+
+    fn magnitude(&self, frequency: f32, sample_rate: u32) -> f64 {
+        match self {
+            Stage::Biquad(filter) => filter.coefficients.magnitude(frequency, sample_rate),
+            Stage::Fir(filter) => filter.magnitude(frequency, sample_rate),
+        }
+    }
+}
+
+/// Processing between capture and display: the blocks in order, each a
+/// cascade of biquads or, for an FIR design, a set of taps.
+///
+/// Bypassed blocks and hand-entered biquads that would be unstable are
+/// left out of the signal path.
+#[derive(Debug, Clone)]
+pub struct FilterChain {
+    blocks: Vec<FilterBlock>,
+    sample_rate: u32,
+    /// Every section of the active blocks, in order
+    filters: Vec<Stage>,
+}
+
+impl FilterChain {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            blocks: Vec::new(),
+            sample_rate,
+            filters: Vec::new(),
+        }
+    }
+
+    pub fn blocks(&self) -> &[FilterBlock] {
+        &self.blocks
+    }
+
+    /// Replace the chain. The filters keep their state while the blocks
+    /// stay the same, so this can be called with every batch of samples.
+    pub fn set_blocks(&mut self, blocks: &[FilterBlock]) {
+        if self.blocks != blocks {
+            self.blocks = blocks.to_vec();
+            self.design();
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            self.design();
+        }
+    }
+
+    /// Whether any block touches the signal
+    pub fn is_active(&self) -> bool {
+        !self.filters.is_empty()
+    }
+
+    /// Filter `samples` in place, continuing from the previous batch
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for filter in &mut self.filters {
+            filter.process(samples);
+        }
+    }
+
+    /// Forget the previous samples, e.g. after a gap in the input
+    pub fn reset(&mut self) {
+        for filter in &mut self.filters {
+            filter.reset();
+        }
+    }
+
+    /// Linear gain of the whole chain at `frequency`
+    pub fn magnitude(&self, frequency: f32) -> f64 {
+        self.filters
+            .iter()
+            .map(|filter| filter.magnitude(frequency, self.sample_rate))
+            .product()
+    }
+
+    fn design(&mut self) {
+        self.filters = self
+            .blocks
+            .iter()
+            .filter(|block| !block.bypassed)
+            .flat_map(|block| match block.kind {
+                FilterKind::Designed(design) if design.family == FilterFamily::WindowedSinc => {
+                    vec![Stage::Fir(FirFilter::new(design.taps(self.sample_rate)))]
+                }
+                kind => kind
+                    .sections(self.sample_rate)
+                    .into_iter()
+                    .filter(Biquad::is_stable)
+                    .map(|section| Stage::Biquad(BiquadFilter::new(section)))
+                    .collect(),
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db(gain: f64) -> f64 {
+        20.0 * gain.log10()
+    }
+
+    fn chain(blocks: &[FilterBlock]) -> FilterChain {
+        let mut chain = FilterChain::new(48000);
+        chain.set_blocks(blocks);
+        chain
+    }
+
+    #[test]
+    fn test_weighting_curves() {
+        // IEC 61672 table values, with the bilinear transform's squeeze
+        // toward Nyquist allowed for at 10 kHz, still well inside class 1
+        let a = chain(&[FilterBlock::new(FilterKind::Weighting(Weighting::A))]);
+        for (frequency, expected, tolerance) in [
+            (31.5, -39.4, 0.3),
+            (100.0, -19.1, 0.3),
+            (1000.0, 0.0, 0.3),
+            (4000.0, 1.0, 0.3),
+            (10000.0, -2.5, 1.0),
+        ] {
+            let actual = db(a.magnitude(frequency));
+            assert!(
+                (actual - expected).abs() < tolerance,
+                "A at {frequency} Hz: {actual}"
+            );
+        }
+
+        let c = chain(&[FilterBlock::new(FilterKind::Weighting(Weighting::C))]);
+        for (frequency, expected) in [(31.5, -3.0), (1000.0, 0.0), (4000.0, -0.8)] {
+            let actual = db(c.magnitude(frequency));
+            assert!(
+                (actual - expected).abs() < 0.3,
+                "C at {frequency} Hz: {actual}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_bypass_and_order() {
+        let mut blocks = vec![
+            FilterBlock::new(FilterKind::Gain { db: 6.0 }),
+            FilterBlock::new(FilterKind::Notch {
+                frequency: 50.0,
+                q: 10.0,
+            }),
+        ];
+        let mut chain = chain(&blocks);
+        assert!(chain.is_active());
+        assert!((db(chain.magnitude(1000.0)) - 6.0).abs() < 0.1);
+
+        blocks[0].bypassed = true;
+        blocks[1].bypassed = true;
+        chain.set_blocks(&blocks);
+        assert!(!chain.is_active());
+        let mut samples = [0.5, -0.25];
+        chain.process(&mut samples);
+        assert_eq!(samples, [0.5, -0.25]);
+
+        // An unstable biquad is left out rather than blowing up the trace
+        chain.set_blocks(&[FilterBlock::new(FilterKind::Biquad(Biquad {
+            a1: -2.5,
+            a2: 1.5,
+            ..Biquad::IDENTITY
+        }))]);
+        assert!(!chain.is_active());
+    }
+
+    #[test]
+    fn test_state_carries_across_batches() {
+        let blocks = [FilterBlock::new(FilterKind::LowPass {
+            frequency: 1000.0,
+            q: BUTTERWORTH_Q,
+        })];
+        let input: Vec<f32> = (0..256)
+            .map(|i| ((i * 7) % 13) as f32 / 13.0 - 0.5)
+            .collect();
+
+        let mut whole = input.clone();
+        chain(&blocks).process(&mut whole);
+
+        let mut split = chain(&blocks);
+        let mut batches = input.clone();
+        let (first, second) = batches.split_at_mut(100);
+        split.process(first);
+        // Setting the same blocks again mustn't reset the filters
+        split.set_blocks(&blocks);
+        split.process(second);
+        assert_eq!(batches, whole);
+    }
+
+    #[test]
+    fn test_designed_blocks() {
+        let fir = FilterDesign {
+            family: FilterFamily::WindowedSinc,
+            response: FilterResponse::HighPass,
+            order: 64,
+            ..FilterDesign::default()
+        };
+        let butterworth = FilterDesign::default();
+        let chain = chain(&[
+            FilterBlock::new(FilterKind::Designed(fir)),
+            FilterBlock::new(FilterKind::Designed(butterworth)),
+        ]);
+        let expected = fir.magnitude(1500.0, 48000) * butterworth.magnitude(1500.0, 48000);
+        assert!((chain.magnitude(1500.0) - expected).abs() < 1e-12);
+
+        // The FIR's impulse response is its taps
+        let mut chain = self::chain(&[FilterBlock::new(FilterKind::Designed(fir))]);
+        let mut impulse = vec![0.0; 65];
+        impulse[0] = 1.0;
+        chain.process(&mut impulse);
+        let taps = fir.taps(48000);
+        assert!(impulse
+            .iter()
+            .zip(&taps)
+            .all(|(&y, &tap)| (y as f64 - tap).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_blocks_round_trip_through_text() {
+        let designed = FilterKind::Designed(FilterDesign {
+            family: FilterFamily::WindowedSinc,
+            response: FilterResponse::BandPass,
+            order: 32,
+            cutoff: 300.0,
+            upper_cutoff: 3400.0,
+        });
+        for kind in FilterKind::DEFAULTS.into_iter().chain([designed]) {
+            for bypassed in [false, true] {
+                let block = FilterBlock { kind, bypassed };
+                assert_eq!(FilterBlock::parse(&block.to_text()), Some(block));
+            }
+        }
+        assert_eq!(
+            FilterBlock::parse("weighting c"),
+            Some(FilterBlock::new(FilterKind::Weighting(Weighting::C)))
+        );
+        for broken in [
+            "",
+            "gain",
+            "lowpass -5 0.7",
+            "notch 50 nan",
+            "shelf 100 1",
+            "weighting Z",
+            "design Chebyshev Low-pass 4 1000 4000",
+            "design FIR Low-pass 4 0 4000",
+        ] {
+            assert_eq!(FilterBlock::parse(broken), None, "{broken}");
+        }
+    }
+}
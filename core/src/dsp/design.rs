@@ -0,0 +1,369 @@
+use std::f64::consts::PI;
+
+use super::biquad::{prewarp, Biquad};
+use super::fir;
+
+/// How a designed filter is built
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterFamily {
+    /// IIR: maximally flat pass band, steeper with each order, but with
+    /// phase shift that grows toward the corner
+    Butterworth,
+    /// FIR: a Hamming-windowed sinc with linear phase, delaying every
+    /// frequency by half its order in samples
+    WindowedSinc,
+}
+
+impl FilterFamily {
+    pub fn next(self) -> Self {
+        match self {
+            FilterFamily::Butterworth => FilterFamily::WindowedSinc,
+            FilterFamily::WindowedSinc => FilterFamily::Butterworth,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterFamily::Butterworth => "Butterworth",
+            FilterFamily::WindowedSinc => "FIR",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        [FilterFamily::Butterworth, FilterFamily::WindowedSinc]
+            .into_iter()
+            .find(|family| family.label().eq_ignore_ascii_case(label))
+    }
+
+    /// Orders a design can take
+    pub fn orders(self) -> (u32, u32) {
+        match self {
+            FilterFamily::Butterworth => (1, 8),
+            FilterFamily::WindowedSinc => (4, 256),
+        }
+    }
+}
+
+/// Which frequencies a designed filter passes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterResponse {
+    LowPass,
+    HighPass,
+    /// Between the cutoff and the upper cutoff
+    BandPass,
+}
+
+impl FilterResponse {
+    pub fn next(self) -> Self {
+        match self {
+            FilterResponse::LowPass => FilterResponse::HighPass,
+            FilterResponse::HighPass => FilterResponse::BandPass,
+            FilterResponse::BandPass => FilterResponse::LowPass,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterResponse::LowPass => "Low-pass",
+            FilterResponse::HighPass => "High-pass",
+            FilterResponse::BandPass => "Band-pass",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        [
+            FilterResponse::LowPass,
+            FilterResponse::HighPass,
+            FilterResponse::BandPass,
+        ]
+        .into_iter()
+        .find(|response| response.label().eq_ignore_ascii_case(label))
+    }
+}
+
+/// A filter given by its type, order and cutoff, as the designer builds
+/// it, rather than by coefficients. Stays editable in the chain, and is
+/// redesigned for whatever rate the input runs at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilterDesign {
+    pub family: FilterFamily,
+    pub response: FilterResponse,
+    /// Poles for Butterworth, taps less one for FIR
+    pub order: u32,
+    /// Corner in Hz; the lower edge of a band-pass
+    pub cutoff: f32,
+    /// Upper edge of a band-pass in Hz; unused otherwise
+    pub upper_cutoff: f32,
+}
+
+impl Default for FilterDesign {
+    fn default() -> Self {
+        Self {
+            family: FilterFamily::Butterworth,
+            response: FilterResponse::LowPass,
+            order: 4,
+            cutoff: 1000.0,
+            upper_cutoff: 4000.0,
+        }
+    }
+}
+
+impl FilterDesign {
+    /// The same design in another family, its order brought into range
+    pub fn with_family(self, family: FilterFamily) -> Self {
+        let (min, max) = family.orders();
+        Self {
+            family,
+            order: self.order.clamp(min, max),
+            ..self
+        }
+    }
+
+    /// Raise or lower the order a step: one pole at a time for
+    /// Butterworth, doubling or halving the taps for FIR
+    pub fn step_order(&mut self, up: bool) {
+        let order = match (self.family, up) {
+            (FilterFamily::Butterworth, true) => self.order + 1,
+            (FilterFamily::Butterworth, false) => self.order.saturating_sub(1),
+            (FilterFamily::WindowedSinc, true) => self.order * 2,
+            (FilterFamily::WindowedSinc, false) => self.order / 2,
+        };
+        let (min, max) = self.family.orders();
+        self.order = order.clamp(min, max);
+    }
+
+    /// Lower and upper edge, in order, whichever way round they were set
+    fn band(&self) -> (f64, f64) {
+        let (low, high) = (self.cutoff as f64, self.upper_cutoff as f64);
+        (low.min(high), low.max(high))
+    }
+
+    /// Cascaded biquads of a Butterworth design at `sample_rate`; none for
+    /// an FIR design. A band-pass is a high-pass at the lower edge
+    /// followed by a low-pass at the upper, each of the full order.
+    pub fn sections(&self, sample_rate: u32) -> Vec<Biquad> {
+        if self.family != FilterFamily::Butterworth {
+            return Vec::new();
+        }
+        let order = self.order.clamp(1, self.family.orders().1);
+        match self.response {
+            FilterResponse::LowPass => butterworth(false, self.cutoff, order, sample_rate),
+            FilterResponse::HighPass => butterworth(true, self.cutoff, order, sample_rate),
+            FilterResponse::BandPass => {
+                let (low, high) = self.band();
+                let mut sections = butterworth(true, low as f32, order, sample_rate);
+                sections.extend(butterworth(false, high as f32, order, sample_rate));
+                sections
+            }
+        }
+    }
+
+    /// Taps of an FIR design at `sample_rate`; none for Butterworth. The
+    /// order is rounded up to even, so the taps are symmetric about a
+    /// whole sample.
+    pub fn taps(&self, sample_rate: u32) -> Vec<f64> {
+        if self.family != FilterFamily::WindowedSinc {
+            return Vec::new();
+        }
+        let (min, max) = self.family.orders();
+        let order = (self.order.clamp(min, max) + 1) & !1;
+        let rate = sample_rate as f64;
+        match self.response {
+            FilterResponse::LowPass => windowed_sinc(self.cutoff as f64 / rate, order),
+            FilterResponse::HighPass => invert(windowed_sinc(self.cutoff as f64 / rate, order)),
+            FilterResponse::BandPass => {
+                let (low, high) = self.band();
+                let upper = windowed_sinc(high / rate, order);
+                let lower = windowed_sinc(low / rate, order);
+                upper.iter().zip(&lower).map(|(u, l)| u - l).collect()
+            }
+        }
+    }
+
+    /// Linear gain at `frequency`
+    pub fn magnitude(&self, frequency: f32, sample_rate: u32) -> f64 {
+        match self.family {
+            FilterFamily::Butterworth => self
+                .sections(sample_rate)
+                .iter()
+                .map(|section| section.magnitude(frequency, sample_rate))
+                .product(),
+            FilterFamily::WindowedSinc => {
+                fir::magnitude(&self.taps(sample_rate), frequency, sample_rate)
+            }
+        }
+    }
+
+    /// e.g. "Butterworth low-pass, order 4, 1000 Hz"
+    pub fn summary(&self) -> String {
+        let corner = match self.response {
+            FilterResponse::BandPass => {
+                let (low, high) = self.band();
+                format!("{low:.0}-{high:.0} Hz")
+            }
+            _ => format!("{:.0} Hz", self.cutoff),
+        };
+        format!(
+            "{} {}, order {}, {corner}",
+            self.family.label(),
+            self.response.label().to_lowercase(),
+            self.order
+        )
+    }
+}
+
+/// Butterworth low- or high-pass of `order` poles: a biquad for each
+/// conjugate pair, plus a first-order section when the order is odd
+fn butterworth(high_pass: bool, cutoff: f32, order: u32, sample_rate: u32) -> Vec<Biquad> {
+    let mut sections: Vec<Biquad> = (0..order / 2)
+        .map(|k| {
+            // Q of the pair at angle (2k + 1)π / 2n from the real axis
+            let angle = PI * (2 * k + 1) as f64 / (2 * order) as f64;
+            let q = (1.0 / (2.0 * angle.sin())) as f32;
+            if high_pass {
+                Biquad::high_pass(cutoff, q, sample_rate)
+            } else {
+                Biquad::low_pass(cutoff, q, sample_rate)
+            }
+        })
+        .collect();
+    if order % 2 == 1 {
+        let w = prewarp(cutoff.max(1.0) as f64, sample_rate);
+        let numerator = if high_pass { [1.0, 0.0] } else { [0.0, w] };
+        sections.push(Biquad::bilinear_first_order(
+            numerator,
+            [1.0, w],
+            sample_rate,
+        ));
+    }
+    sections
+}
+
+/// Low-pass taps for `order` + 1 taps, `cutoff` a fraction of the sample
+/// rate, with unity gain at DC
+fn windowed_sinc(cutoff: f64, order: u32) -> Vec<f64> {
+    let cutoff = cutoff.clamp(0.0, 0.5);
+    let middle = order as f64 / 2.0;
+    let taps: Vec<f64> = (0..=order)
+        .map(|n| {
+            let x = n as f64 - middle;
+            let sinc = if x == 0.0 {
+                2.0 * cutoff
+            } else {
+                (2.0 * PI * cutoff * x).sin() / (PI * x)
+            };
+            let hamming = 0.54 - 0.46 * (2.0 * PI * n as f64 / order as f64).cos();
+            sinc * hamming
+        })
+        .collect();
+    let sum: f64 = taps.iter().sum();
+    if sum.abs() < f64::EPSILON {
+        return taps;
+    }
+    taps.into_iter().map(|tap| tap / sum).collect()
+}
+
+/// Spectral inversion: everything the low-pass taps pass, stopped
+fn invert(mut taps: Vec<f64>) -> Vec<f64> {
+    taps.iter_mut().for_each(|tap| *tap = -*tap);
+    let middle = taps.len() / 2;
+    taps[middle] += 1.0;
+    taps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db(gain: f64) -> f64 {
+        20.0 * gain.log10()
+    }
+
+    #[test]
+    fn test_butterworth_rolls_off_by_order() {
+        let rate = 48000;
+        for order in 1..=8 {
+            let design = FilterDesign {
+                order,
+                ..FilterDesign::default()
+            };
+            assert_eq!(design.sections(rate).len() as u32, order.div_ceil(2));
+            assert!(design.sections(rate).iter().all(Biquad::is_stable));
+            // -3 dB at the corner, flat below and 6 dB per octave per pole
+            // above
+            assert!((db(design.magnitude(1000.0, rate)) + 3.01).abs() < 0.05);
+            assert!(db(design.magnitude(50.0, rate)).abs() < 0.02);
+            let octave_up = db(design.magnitude(4000.0, rate));
+            assert!(
+                (octave_up + 12.0 * order as f64).abs() < 0.7 * order as f64,
+                "order {order}: {octave_up} dB"
+            );
+        }
+
+        let high_pass = FilterDesign {
+            response: FilterResponse::HighPass,
+            order: 3,
+            ..FilterDesign::default()
+        };
+        assert!((db(high_pass.magnitude(1000.0, rate)) + 3.01).abs() < 0.05);
+        assert!(db(high_pass.magnitude(20000.0, rate)).abs() < 0.05);
+        assert!(db(high_pass.magnitude(250.0, rate)) < -35.0);
+    }
+
+    #[test]
+    fn test_windowed_sinc_responses() {
+        let rate = 48000;
+        let low_pass = FilterDesign {
+            family: FilterFamily::WindowedSinc,
+            order: 128,
+            ..FilterDesign::default()
+        };
+        assert_eq!(low_pass.taps(rate).len(), 129);
+        assert!(db(low_pass.magnitude(0.0, rate)).abs() < 1e-9);
+        assert!((db(low_pass.magnitude(1000.0, rate)) + 6.0).abs() < 0.5);
+        assert!(db(low_pass.magnitude(3000.0, rate)) < -45.0);
+
+        let high_pass = FilterDesign {
+            response: FilterResponse::HighPass,
+            ..low_pass
+        };
+        assert!(db(high_pass.magnitude(100.0, rate)) < -45.0);
+        assert!(db(high_pass.magnitude(10000.0, rate)).abs() < 0.1);
+
+        let band_pass = FilterDesign {
+            response: FilterResponse::BandPass,
+            cutoff: 4000.0,
+            upper_cutoff: 1000.0,
+            ..low_pass
+        };
+        assert!(db(band_pass.magnitude(2500.0, rate)).abs() < 0.1);
+        assert!(db(band_pass.magnitude(100.0, rate)) < -45.0);
+        assert!(db(band_pass.magnitude(10000.0, rate)) < -45.0);
+        assert!(band_pass.sections(rate).is_empty());
+    }
+
+    #[test]
+    fn test_order_steps_stay_in_range() {
+        let mut design = FilterDesign {
+            order: 8,
+            ..FilterDesign::default()
+        };
+        design.step_order(true);
+        assert_eq!(design.order, 8);
+
+        let mut fir = design.with_family(FilterFamily::WindowedSinc);
+        assert_eq!(fir.order, 8);
+        fir.step_order(true);
+        assert_eq!(fir.order, 16);
+        fir.order = 4;
+        fir.step_order(false);
+        assert_eq!(fir.order, 4);
+        assert_eq!(
+            fir.with_family(FilterFamily::Butterworth).order,
+            4,
+            "{}",
+            fir.summary()
+        );
+        assert_eq!(fir.summary(), "FIR low-pass, order 4, 1000 Hz");
+    }
+}
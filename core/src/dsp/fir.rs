@@ -0,0 +1,87 @@
+use std::f64::consts::PI;
+
+/// Finite impulse response filter: each output is the taps weighted over
+/// the most recent inputs
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirFilter {
+    taps: Vec<f64>,
+    /// Recent inputs, as a ring starting at `position`
+    history: Vec<f64>,
+    position: usize,
+}
+
+impl FirFilter {
+    pub fn new(taps: Vec<f64>) -> Self {
+        let history = vec![0.0; taps.len()];
+        Self {
+            taps,
+            history,
+            position: 0,
+        }
+    }
+
+    pub fn taps(&self) -> &[f64] {
+        &self.taps
+    }
+
+    pub fn process(&mut self, x: f32) -> f32 {
+        let len = self.taps.len();
+        if len == 0 {
+            return x;
+        }
+        self.position = (self.position + len - 1) % len;
+        self.history[self.position] = x as f64;
+        // history[position] is x[n], the next x[n-1] and so on around the ring
+        let (newest, oldest) = self.history.split_at(self.position);
+        let y: f64 = oldest
+            .iter()
+            .chain(newest)
+            .zip(&self.taps)
+            .map(|(x, tap)| x * tap)
+            .sum();
+        y as f32
+    }
+
+    pub fn reset(&mut self) {
+        self.history.fill(0.0);
+    }
+
+    /// Linear gain at `frequency`
+    pub fn magnitude(&self, frequency: f32, sample_rate: u32) -> f64 {
+        magnitude(&self.taps, frequency, sample_rate)
+    }
+}
+
+/// Linear gain of `taps` at `frequency`
+pub fn magnitude(taps: &[f64], frequency: f32, sample_rate: u32) -> f64 {
+    let w = 2.0 * PI * frequency as f64 / sample_rate as f64;
+    let (re, im) = taps
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(re, im), (n, tap)| {
+            let phase = w * n as f64;
+            (re + tap * phase.cos(), im - tap * phase.sin())
+        });
+    f64::hypot(re, im)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_taps_weight_recent_inputs() {
+        let mut filter = FirFilter::new(vec![0.5, 0.25, 0.125]);
+        let output: Vec<f32> = [1.0, 0.0, 0.0, 0.0, 2.0]
+            .into_iter()
+            .map(|x| filter.process(x))
+            .collect();
+        // The impulse response is the taps
+        assert_eq!(output, vec![0.5, 0.25, 0.125, 0.0, 1.0]);
+        assert!((filter.magnitude(0.0, 48000) - 0.875).abs() < 1e-12);
+
+        filter.reset();
+        assert_eq!(filter.process(0.0), 0.0);
+        assert_eq!(FirFilter::new(Vec::new()).process(0.5), 0.5);
+    }
+}
@@ -0,0 +1,11 @@
+pub mod bandwidth;
+pub mod biquad;
+pub mod chain;
+pub mod design;
+pub mod fir;
+
+pub use bandwidth::{BandwidthFilter, BandwidthLimit};
+pub use biquad::{Biquad, BiquadFilter};
+pub use chain::{FilterBlock, FilterChain, FilterKind, Weighting, BUTTERWORTH_Q};
+pub use design::{FilterDesign, FilterFamily, FilterResponse};
+pub use fir::FirFilter;
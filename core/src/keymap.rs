@@ -0,0 +1,221 @@
+use crate::config::Preferences;
+
+/// A keyboard shortcut's control, for each frontend to carry out its own way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    IncreaseTimeScale,
+    DecreaseTimeScale,
+    IncreaseVoltageScale,
+    DecreaseVoltageScale,
+    IncreaseTriggerLevel,
+    DecreaseTriggerLevel,
+    OpenPreferences,
+    CycleDecimation,
+    CyclePlot,
+    CycleLayout,
+    ToggleTrigger,
+    ToggleTriggerEdge,
+    TogglePersistence,
+    CyclePersistenceMode,
+    IncreasePersistence,
+    DecreasePersistence,
+    ToggleLogging,
+    CycleTheme,
+    ToggleAxisLabels,
+    ToggleHarmonics,
+    CycleSpectrumScale,
+    ToggleBandPower,
+    ToggleTimeGate,
+}
+
+impl Action {
+    /// Whether Shift makes this a fine step, as for the scales and trigger
+    /// level
+    pub fn has_fine_step(self) -> bool {
+        matches!(
+            self,
+            Action::IncreaseTimeScale
+                | Action::DecreaseTimeScale
+                | Action::IncreaseVoltageScale
+                | Action::DecreaseVoltageScale
+                | Action::IncreaseTriggerLevel
+                | Action::DecreaseTriggerLevel
+        )
+    }
+}
+
+/// A key press as the keymap sees it, whatever the toolkit calls the key.
+/// Characters are as typed without Shift.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Character(char),
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+}
+
+/// A shortcut that can be moved to another key. Arrows, `+`/`-` and `,`
+/// stay where they are.
+pub struct KeyAction {
+    /// Saved in the preferences file as `key.<name>`
+    pub name: &'static str,
+    pub label: &'static str,
+    pub default_key: char,
+    pub action: Action,
+}
+
+pub const KEY_ACTIONS: [KeyAction; 16] = [
+    KeyAction {
+        name: "cycle_decimation",
+        label: "Draw style",
+        default_key: 'd',
+        action: Action::CycleDecimation,
+    },
+    KeyAction {
+        name: "cycle_plot",
+        label: "Second pane",
+        default_key: 'f',
+        action: Action::CyclePlot,
+    },
+    KeyAction {
+        name: "cycle_layout",
+        label: "Layout",
+        default_key: 'y',
+        action: Action::CycleLayout,
+    },
+    KeyAction {
+        name: "toggle_trigger",
+        label: "Trigger on/off",
+        default_key: 't',
+        action: Action::ToggleTrigger,
+    },
+    KeyAction {
+        name: "toggle_trigger_edge",
+        label: "Trigger edge",
+        default_key: 'e',
+        action: Action::ToggleTriggerEdge,
+    },
+    KeyAction {
+        name: "toggle_persistence",
+        label: "Persistence on/off",
+        default_key: 'p',
+        action: Action::TogglePersistence,
+    },
+    KeyAction {
+        name: "cycle_persistence_mode",
+        label: "Persistence mode",
+        default_key: 'g',
+        action: Action::CyclePersistenceMode,
+    },
+    KeyAction {
+        name: "increase_persistence",
+        label: "More persistence",
+        default_key: ']',
+        action: Action::IncreasePersistence,
+    },
+    KeyAction {
+        name: "decrease_persistence",
+        label: "Less persistence",
+        default_key: '[',
+        action: Action::DecreasePersistence,
+    },
+    KeyAction {
+        name: "toggle_logging",
+        label: "Logging",
+        default_key: 'l',
+        action: Action::ToggleLogging,
+    },
+    KeyAction {
+        name: "cycle_theme",
+        label: "Theme",
+        default_key: 'c',
+        action: Action::CycleTheme,
+    },
+    KeyAction {
+        name: "toggle_axis_labels",
+        label: "Axis labels",
+        default_key: 'a',
+        action: Action::ToggleAxisLabels,
+    },
+    KeyAction {
+        name: "toggle_harmonics",
+        label: "Harmonics",
+        default_key: 'h',
+        action: Action::ToggleHarmonics,
+    },
+    KeyAction {
+        name: "cycle_spectrum_scale",
+        label: "Spectrum scale",
+        default_key: 'q',
+        action: Action::CycleSpectrumScale,
+    },
+    KeyAction {
+        name: "toggle_band_power",
+        label: "Band power",
+        default_key: 'b',
+        action: Action::ToggleBandPower,
+    },
+    KeyAction {
+        name: "toggle_time_gate",
+        label: "Time gate",
+        default_key: 'w',
+        action: Action::ToggleTimeGate,
+    },
+];
+
+/// The key `action` is on, its default unless rebound
+pub fn key_for(preferences: &Preferences, action: &KeyAction) -> char {
+    preferences.key(action.name).unwrap_or(action.default_key)
+}
+
+/// The shortcut on `key`: the fixed keys first, then those the preferences
+/// can move, ignoring case
+pub fn action_for_key(preferences: &Preferences, key: Key) -> Option<Action> {
+    match key {
+        Key::Character('+' | '=') => Some(Action::IncreaseTimeScale),
+        Key::Character('-') => Some(Action::DecreaseTimeScale),
+        Key::Character(',') => Some(Action::OpenPreferences),
+        Key::ArrowUp => Some(Action::IncreaseVoltageScale),
+        Key::ArrowDown => Some(Action::DecreaseVoltageScale),
+        Key::ArrowRight => Some(Action::IncreaseTriggerLevel),
+        Key::ArrowLeft => Some(Action::DecreaseTriggerLevel),
+        Key::Character(key) => {
+            let key = key.to_ascii_lowercase();
+            KEY_ACTIONS
+                .iter()
+                .find(|action| key_for(preferences, action) == key)
+                .map(|action| action.action)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_and_moved_keys() {
+        let mut preferences = Preferences::default();
+        assert_eq!(
+            action_for_key(&preferences, Key::Character('=')),
+            Some(Action::IncreaseTimeScale)
+        );
+        assert_eq!(
+            action_for_key(&preferences, Key::Character('T')),
+            Some(Action::ToggleTrigger)
+        );
+
+        preferences.bind_key("toggle_trigger", Some('x'));
+        assert_eq!(action_for_key(&preferences, Key::Character('t')), None);
+        assert_eq!(
+            action_for_key(&preferences, Key::Character('x')),
+            Some(Action::ToggleTrigger)
+        );
+        assert_eq!(
+            action_for_key(&preferences, Key::ArrowLeft),
+            Some(Action::DecreaseTriggerLevel)
+        );
+        assert_eq!(action_for_key(&preferences, Key::Character('z')), None);
+    }
+}
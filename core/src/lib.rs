@@ -1,2 +1,13 @@
 pub mod audio;
+pub mod config;
+pub mod decode;
+pub mod display;
+pub mod dsp;
+pub mod keymap;
 pub mod oscilloscope;
+pub mod parallel;
+pub mod plugin;
+pub mod recording;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+pub mod source;
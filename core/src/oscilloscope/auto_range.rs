@@ -0,0 +1,105 @@
+/// Share of the screen height the trace is kept within
+pub const AUTO_RANGE_BAND: (f32, f32) = (0.6, 0.8);
+
+/// Once adjusting, the share the trace is brought back to before the scale
+/// is left alone again. Narrower than the band, so a level sitting on one
+/// of its edges doesn't flip the scale back and forth.
+const SETTLED_BAND: (f32, f32) = (0.67, 0.73);
+
+/// Fraction of the way to the ideal scale moved each frame
+const ADAPT_RATE: f32 = 0.15;
+
+/// How close to the ideal scale a step lands on it outright
+const SNAP: f32 = 0.01;
+
+/// Volts/div the auto range stays within, matching the manual control
+pub const VOLTS_PER_DIVISION_RANGE: (f32, f32) = (0.01, 10.0);
+
+/// Slowly adapts volts/div so the trace fills roughly 60-80% of the screen
+/// height. The screen is centered on zero, so the height the trace takes is
+/// twice its largest excursion either way.
+#[derive(Debug, Clone, Default)]
+pub struct AutoRange {
+    adjusting: bool,
+}
+
+impl AutoRange {
+    /// Volts/div for the next frame, given the samples on screen now
+    pub fn update(
+        &mut self,
+        samples: &[f32],
+        volts_per_division: f32,
+        vertical_divisions: usize,
+    ) -> f32 {
+        let peak = samples.iter().fold(0.0_f32, |peak, s| peak.max(s.abs()));
+        let height = volts_per_division * vertical_divisions as f32;
+        let share = 2.0 * peak / height;
+
+        let band = if self.adjusting {
+            SETTLED_BAND
+        } else {
+            AUTO_RANGE_BAND
+        };
+        self.adjusting = share < band.0 || share > band.1;
+        if !self.adjusting {
+            return volts_per_division;
+        }
+
+        // Moving geometrically, a step is the same size on screen at any scale
+        let target = (SETTLED_BAND.0 + SETTLED_BAND.1) / 2.0;
+        let ideal = 2.0 * peak / (target * vertical_divisions as f32);
+        let ideal = ideal.clamp(VOLTS_PER_DIVISION_RANGE.0, VOLTS_PER_DIVISION_RANGE.1);
+        let mut scale = volts_per_division * (ideal / volts_per_division).powf(ADAPT_RATE);
+        if (scale / ideal - 1.0).abs() < SNAP {
+            scale = ideal;
+        }
+        if scale == volts_per_division {
+            // Pinned at a limit, as for silence
+            self.adjusting = false;
+        }
+        scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settle(auto_range: &mut AutoRange, samples: &[f32], mut scale: f32) -> f32 {
+        for _ in 0..200 {
+            scale = auto_range.update(samples, scale, 8);
+        }
+        scale
+    }
+
+    #[test]
+    fn test_converges_to_the_band() {
+        let samples = [0.0, 1.5, -2.0, 0.5];
+        let mut auto_range = AutoRange::default();
+
+        // The first frame only moves part of the way
+        let first = auto_range.update(&samples, 0.1, 8);
+        assert!(first > 0.1 && first < 0.5);
+
+        let scale = settle(&mut auto_range, &samples, first);
+        let share = 2.0 * 2.0 / (scale * 8.0);
+        assert!((0.67..=0.73).contains(&share), "share {share}");
+    }
+
+    #[test]
+    fn test_hysteresis() {
+        let mut auto_range = AutoRange::default();
+        // 75% of the screen is inside the band, so nothing changes
+        assert_eq!(auto_range.update(&[3.0], 1.0, 8), 1.0);
+
+        // Pushed past the band, it settles well inside it rather than on
+        // the edge it crossed
+        let scale = settle(&mut auto_range, &[3.5], 1.0);
+        let share = 7.0 / (scale * 8.0);
+        assert!(share < 0.73, "share {share}");
+
+        // Silence can't go below the finest scale
+        let scale = settle(&mut auto_range, &[0.0], 1.0);
+        assert_eq!(scale, VOLTS_PER_DIVISION_RANGE.0);
+    }
+}
@@ -0,0 +1,207 @@
+/// Sum of the squared Hann window over its length, per sample: converts
+/// the windowed bin powers back to the power of the signal
+const HANN_POWER: f32 = 3.0 / 8.0;
+
+/// Mean power of a noise bin over its median: FFT bins of Gaussian noise
+/// follow an exponential distribution, whose median is ln 2 of its mean
+const MEDIAN_TO_MEAN: f32 = std::f32::consts::LN_2;
+
+/// Mean-square level of a full-scale sine, which reads 0 dBFS
+const FULL_SCALE_POWER: f32 = 0.5;
+
+/// Lowest power in dBFS reported, so an empty band isn't minus infinity
+const FLOOR_DB: f32 = -200.0;
+
+/// One of the two frequency cursors bounding the measured band
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BandCursor {
+    Low,
+    High,
+}
+
+/// Two frequency cursors on the spectrum, in Hz, bounding the band whose
+/// power is measured
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BandCursors {
+    pub low: f32,
+    pub high: f32,
+}
+
+impl Default for BandCursors {
+    /// The audio band
+    fn default() -> Self {
+        Self {
+            low: 20.0,
+            high: 20000.0,
+        }
+    }
+}
+
+impl BandCursors {
+    /// The cursor closer to `frequency`, the one a click there picks up
+    pub fn nearest(&self, frequency: f32) -> BandCursor {
+        if (frequency - self.low).abs() <= (frequency - self.high).abs() {
+            BandCursor::Low
+        } else {
+            BandCursor::High
+        }
+    }
+
+    /// Move `cursor` to `frequency`, not below 0 Hz. Dragging one cursor
+    /// past the other pushes the other along with it.
+    pub fn set(&mut self, cursor: BandCursor, frequency: f32) {
+        let frequency = frequency.max(0.0);
+        match cursor {
+            BandCursor::Low => {
+                self.low = frequency;
+                self.high = self.high.max(frequency);
+            }
+            BandCursor::High => {
+                self.high = frequency;
+                self.low = self.low.min(frequency);
+            }
+        }
+    }
+
+    /// Measure the band between the cursors, clamped to the spectrum
+    pub fn measure(&self, magnitudes: &[f32], sample_rate: u32) -> Option<BandPower> {
+        BandPower::of(magnitudes, sample_rate, self.low, self.high)
+    }
+}
+
+/// Power in a frequency band of the spectrum and the noise floor under it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BandPower {
+    /// Edges of the bins measured, in Hz
+    pub low: f32,
+    pub high: f32,
+    /// Total power in the band in dBFS, a full-scale sine reading 0 dBFS
+    pub power: f32,
+    /// Noise floor density in dBFS/√Hz, from the median bin so tones in
+    /// the band don't raise it
+    pub noise_density: f32,
+}
+
+impl BandPower {
+    /// Integrate the linear-scale spectrum `magnitudes` (dB per FFT bin, as
+    /// produced by `SpectrumAnalyzer`) from `low` to `high` Hz. `None` when
+    /// the band misses the spectrum.
+    pub fn of(magnitudes: &[f32], sample_rate: u32, low: f32, high: f32) -> Option<Self> {
+        let bins = magnitudes.len();
+        if bins == 0 || sample_rate == 0 {
+            return None;
+        }
+        let fft_size = 2 * bins;
+        let bin_width = sample_rate as f32 / fft_size as f32;
+        let first = (low.min(high) / bin_width).round().max(0.0) as usize;
+        let last = ((low.max(high) / bin_width).round() as usize).min(bins - 1);
+        if first > last {
+            return None;
+        }
+
+        // Bin powers as the analyzer scaled them, |X|² / N, turned into the
+        // mean-square power each bin holds of the one-sided spectrum
+        let to_mean_square = 2.0 / (fft_size as f32 * HANN_POWER);
+        let mut powers: Vec<f32> = magnitudes[first..=last]
+            .iter()
+            .map(|&db| 10_f32.powf(db / 10.0) * to_mean_square)
+            .collect();
+        let power: f32 = powers.iter().sum();
+
+        powers.sort_by(f32::total_cmp);
+        let median = powers[powers.len() / 2] / MEDIAN_TO_MEAN;
+
+        let db = |mean_square: f32| {
+            let level = 10.0
+                * (mean_square / FULL_SCALE_POWER)
+                    .max(f32::MIN_POSITIVE)
+                    .log10();
+            level.max(FLOOR_DB)
+        };
+        Some(Self {
+            low: first as f32 * bin_width,
+            high: last as f32 * bin_width,
+            power: db(power),
+            noise_density: db(median / bin_width),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oscilloscope::SpectrumAnalyzer;
+
+    fn spectrum_of(samples: &[f32]) -> Vec<f32> {
+        let mut analyzer = SpectrumAnalyzer::new();
+        analyzer.update(samples);
+        analyzer.magnitudes().to_vec()
+    }
+
+    /// Uniform noise from -1 to 1, a mean-square power of 1/3
+    fn noise(length: usize) -> Vec<f32> {
+        let mut state: u32 = 0x1234_5678;
+        (0..length)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 8) as f32 / (1 << 23) as f32 - 1.0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sine_power() {
+        // Half scale: -6 dBFS, all of it around 1 kHz
+        let samples: Vec<f32> = (0..4096)
+            .map(|i| 0.5 * (std::f32::consts::TAU * 1000.0 * i as f32 / 48000.0).sin())
+            .collect();
+        let magnitudes = spectrum_of(&samples);
+
+        let band = BandPower::of(&magnitudes, 48000, 500.0, 2000.0).unwrap();
+        assert!((band.power + 6.02).abs() < 0.2, "{}", band.power);
+        // The tone doesn't count towards the floor
+        assert!(band.noise_density < -60.0, "{}", band.noise_density);
+
+        let elsewhere = BandPower::of(&magnitudes, 48000, 5000.0, 10000.0).unwrap();
+        assert!(elsewhere.power < -40.0, "{}", elsewhere.power);
+    }
+
+    #[test]
+    fn test_white_noise_density() {
+        let magnitudes = spectrum_of(&noise(4096));
+        let band = BandPower::of(&magnitudes, 48000, 0.0, 24000.0).unwrap();
+
+        // 1/3 spread evenly over 24 kHz
+        let total = 10.0 * (1.0 / 3.0 / FULL_SCALE_POWER).log10();
+        let density = 10.0 * (1.0 / 3.0 / FULL_SCALE_POWER / 24000.0).log10();
+        assert!((band.power - total).abs() < 0.5, "{}", band.power);
+        assert!(
+            (band.noise_density - density).abs() < 1.0,
+            "{} against {density}",
+            band.noise_density
+        );
+
+        // A quarter of the band holds a quarter of the power
+        let quarter = BandPower::of(&magnitudes, 48000, 6000.0, 12000.0).unwrap();
+        assert!(
+            (quarter.power - (total - 6.02)).abs() < 0.6,
+            "{}",
+            quarter.power
+        );
+    }
+
+    #[test]
+    fn test_cursors() {
+        let mut cursors = BandCursors::default();
+        assert_eq!(cursors.nearest(100.0), BandCursor::Low);
+        assert_eq!(cursors.nearest(15000.0), BandCursor::High);
+
+        cursors.set(BandCursor::Low, 25000.0);
+        assert_eq!((cursors.low, cursors.high), (25000.0, 25000.0));
+        cursors.set(BandCursor::High, -5.0);
+        assert_eq!((cursors.low, cursors.high), (0.0, 0.0));
+
+        assert!(cursors.measure(&[], 48000).is_none());
+        assert!(BandPower::of(&[0.0; 16], 48000, 30000.0, 40000.0).is_none());
+    }
+}
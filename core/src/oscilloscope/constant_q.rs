@@ -0,0 +1,162 @@
+use rustfft::num_complex::Complex;
+
+/// Centre of the lowest bin: C1
+pub const LOWEST_FREQUENCY: f32 = 32.703_196;
+
+/// One bin per equal-tempered semitone, centred on the notes
+pub const BINS_PER_OCTAVE: usize = 12;
+
+/// Highest bin centre as a fraction of the Nyquist frequency
+const MAX_NYQUIST_FRACTION: f32 = 0.9;
+
+/// Magnitude floor in dB, below `DB_MIN` like the FFT spectrum's
+const FLOOR_DB: f32 = -100.0;
+
+/// Constant-Q transform: bins spaced evenly in pitch, each as wide as a
+/// fixed fraction of its centre frequency.
+///
+/// Every bin correlates the newest samples with its own Hann-windowed
+/// complex tone, long enough to resolve one semitone, so the bass gets the
+/// frequency resolution an FFT only gives the treble and the treble keeps
+/// its time resolution. The lowest bins need the most history: about half
+/// a second for C1.
+pub struct ConstantQ {
+    sample_rate: u32,
+    /// Windowed tone for each bin, oldest sample first
+    kernels: Vec<Vec<Complex<f32>>>,
+    /// Sum of each kernel's window, to scale a full-scale sine to 0 dB
+    window_sums: Vec<f32>,
+}
+
+impl ConstantQ {
+    pub fn new(sample_rate: u32) -> Self {
+        let sample_rate = sample_rate.max(1);
+        let quality = 1.0 / (2_f32.powf(1.0 / BINS_PER_OCTAVE as f32) - 1.0);
+
+        let kernels: Vec<Vec<Complex<f32>>> = (0..Self::bins(sample_rate))
+            .map(|bin| {
+                let frequency = Self::frequency(bin);
+                let length = (quality * sample_rate as f32 / frequency).ceil() as usize;
+                let step = -std::f32::consts::TAU * frequency / sample_rate as f32;
+                (0..length)
+                    .map(|n| {
+                        let window =
+                            0.5 - 0.5 * (std::f32::consts::TAU * n as f32 / length as f32).cos();
+                        Complex::from_polar(window, step * n as f32)
+                    })
+                    .collect()
+            })
+            .collect();
+        let window_sums = kernels
+            .iter()
+            .map(|kernel| kernel.iter().map(|value| value.norm()).sum())
+            .collect();
+
+        Self {
+            sample_rate,
+            kernels,
+            window_sums,
+        }
+    }
+
+    /// Bins from C1 up to just below the Nyquist frequency
+    pub fn bins(sample_rate: u32) -> usize {
+        let highest = sample_rate as f32 / 2.0 * MAX_NYQUIST_FRACTION;
+        let octaves = (highest / LOWEST_FREQUENCY).log2();
+        (octaves * BINS_PER_OCTAVE as f32).floor().max(0.0) as usize + 1
+    }
+
+    /// Centre frequency of `bin` in Hz
+    pub fn frequency(bin: usize) -> f32 {
+        LOWEST_FREQUENCY * 2_f32.powf(bin as f32 / BINS_PER_OCTAVE as f32)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Samples the lowest bin looks back over
+    pub fn longest(&self) -> usize {
+        self.kernels.first().map_or(0, Vec::len)
+    }
+
+    /// dB magnitude of each bin, a full-scale sine reading 0 dB, with every
+    /// kernel ending at the newest of `samples`. A bin whose kernel reaches
+    /// back past the first sample uses the part that overlaps, trading its
+    /// resolution for still reading the right level.
+    pub fn transform(&self, samples: &[f32], magnitudes: &mut Vec<f32>) {
+        magnitudes.clear();
+        magnitudes.extend(self.kernels.iter().zip(&self.window_sums).map(
+            |(kernel, &window_sum)| {
+                let overlap = kernel.len().min(samples.len());
+                let partial = overlap < kernel.len();
+                let kernel = &kernel[kernel.len() - overlap..];
+                let samples = &samples[samples.len() - overlap..];
+                let sum: Complex<f32> = kernel
+                    .iter()
+                    .zip(samples)
+                    .map(|(value, &sample)| value * sample)
+                    .sum();
+                let window_sum = if partial {
+                    kernel.iter().map(|value| value.norm()).sum()
+                } else {
+                    window_sum
+                };
+                let amplitude = 2.0 * sum.norm() / window_sum.max(f32::MIN_POSITIVE);
+                (20.0 * amplitude.max(1e-5).log10()).max(FLOOR_DB)
+            },
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(frequency: f32, length: usize) -> Vec<f32> {
+        (0..length)
+            .map(|i| (std::f32::consts::TAU * frequency * i as f32 / 48000.0).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_bins_are_semitones() {
+        // C1 to just below 21.6 kHz: nine octaves and a few notes
+        assert_eq!(ConstantQ::bins(48000), 113);
+        assert!((ConstantQ::frequency(12) - 2.0 * LOWEST_FREQUENCY).abs() < 0.01);
+        // A4 is 45 semitones above C1
+        assert!((ConstantQ::frequency(45) - 440.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_tone_peaks_in_its_bin_at_full_scale() {
+        let transform = ConstantQ::new(48000);
+        let mut magnitudes = Vec::new();
+        transform.transform(&tone(440.0, transform.longest()), &mut magnitudes);
+
+        assert_eq!(magnitudes.len(), ConstantQ::bins(48000));
+        let peak = magnitudes
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(bin, _)| bin);
+        assert_eq!(peak, Some(45));
+        assert!(magnitudes[45].abs() < 0.1, "{}", magnitudes[45]);
+        // Neighbouring notes are down, and the octave below is far down
+        assert!(magnitudes[44] < -3.0 && magnitudes[46] < -3.0);
+        assert!(magnitudes[43] < -25.0 && magnitudes[47] < -25.0);
+        assert!(magnitudes[33] < -40.0, "{}", magnitudes[33]);
+    }
+
+    #[test]
+    fn test_short_input_keeps_the_level() {
+        let transform = ConstantQ::new(48000);
+        let mut magnitudes = Vec::new();
+        // Far shorter than the 440 Hz kernel
+        transform.transform(&tone(440.0, 480), &mut magnitudes);
+        assert!(magnitudes[45].abs() < 1.0, "{}", magnitudes[45]);
+
+        transform.transform(&[], &mut magnitudes);
+        assert!(magnitudes.iter().all(|&db| db == FLOOR_DB));
+    }
+}
@@ -0,0 +1,144 @@
+/// How a trace is drawn at every zoom: between its samples when a screen
+/// column spans a couple or fewer, and reduced to the display when it spans
+/// more
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Decimation {
+    /// Straight lines between samples, and the minimum and maximum of each
+    /// column zoomed out, so narrow glitches stay visible at full height
+    #[default]
+    PeakDetect,
+    /// Straight lines between samples, and zoomed out low-pass filtered to
+    /// the column rate before taking one point per column, so content above
+    /// what the display can resolve doesn't alias into false slow waveforms
+    Filtered,
+    /// Each sample held until the next, as a converter outputs them, and
+    /// the peaks of each column zoomed out
+    Step,
+    /// The band-limited waveform the samples stand for, interpolated
+    /// between them, and the peaks of each column zoomed out
+    Smooth,
+}
+
+impl Decimation {
+    pub const ALL: [Decimation; 4] = [
+        Decimation::PeakDetect,
+        Decimation::Filtered,
+        Decimation::Step,
+        Decimation::Smooth,
+    ];
+
+    /// Look up a mode by its label (case-insensitive)
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.label().eq_ignore_ascii_case(label))
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Decimation::PeakDetect => Decimation::Filtered,
+            Decimation::Filtered => Decimation::Step,
+            Decimation::Step => Decimation::Smooth,
+            Decimation::Smooth => Decimation::PeakDetect,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Decimation::PeakDetect => "Peak",
+            Decimation::Filtered => "Filtered",
+            Decimation::Step => "Step",
+            Decimation::Smooth => "Smooth",
+        }
+    }
+}
+
+/// Decimate `samples` by `factor` behind a triangular low-pass filter.
+///
+/// Each output is the weighted mean of the samples within `factor` of its
+/// centre, i.e. two cascaded moving averages of one output period, which
+/// puts the filter's first null at the output rate. Returns one
+/// `(position, value)` pair per output, with the position in samples.
+pub fn low_pass(samples: &[f32], factor: f32) -> Vec<(f32, f32)> {
+    let mut points = Vec::new();
+    low_pass_into(samples, factor, &mut points);
+    points
+}
+
+/// `low_pass` written into `points`, replacing what was there
+pub fn low_pass_into(samples: &[f32], factor: f32, points: &mut Vec<(f32, f32)>) {
+    let factor = factor.max(1.0);
+    points.clear();
+    points.reserve((samples.len() as f32 / factor) as usize + 1);
+
+    let mut output = 0;
+    loop {
+        let centre = (output as f32 + 0.5) * factor;
+        if centre >= samples.len() as f32 {
+            break;
+        }
+
+        let first = (centre - factor).ceil().max(0.0) as usize;
+        let last = ((centre + factor).floor() as usize).min(samples.len() - 1);
+        let mut sum = 0.0;
+        let mut weights = 0.0;
+        for (i, &sample) in samples.iter().enumerate().take(last + 1).skip(first) {
+            let weight = 1.0 - (i as f32 - centre).abs() / factor;
+            sum += weight * sample;
+            weights += weight;
+        }
+        if weights > 0.0 {
+            points.push((centre, sum / weights));
+        }
+        output += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_from_label() {
+        assert_eq!(Decimation::from_label("peak"), Some(Decimation::PeakDetect));
+        assert_eq!(
+            Decimation::from_label("Filtered"),
+            Some(Decimation::Filtered)
+        );
+        assert_eq!(Decimation::from_label("smooth"), Some(Decimation::Smooth));
+        assert_eq!(Decimation::from_label("boxcar"), None);
+    }
+
+    #[test]
+    fn test_low_pass_keeps_slow_signals() {
+        let samples: Vec<f32> = (0..4800)
+            .map(|i| (i as f32 / 4800.0 * std::f32::consts::TAU).sin())
+            .collect();
+
+        let points = low_pass(&samples, 10.0);
+        assert_eq!(points.len(), 480);
+        for &(position, value) in &points {
+            let expected = (position / 4800.0 * std::f32::consts::TAU).sin();
+            assert!((value - expected).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_low_pass_rejects_content_above_the_output_rate() {
+        // A tone just off the output rate would alias to a slow, full-height
+        // wave if only every tenth sample were kept
+        let samples: Vec<f32> = (0..4800)
+            .map(|i| (i as f32 * 0.101 * std::f32::consts::TAU).sin())
+            .collect();
+
+        let points = low_pass(&samples, 10.0);
+        // The first and last outputs only see half the filter
+        let peak = points[1..points.len() - 1]
+            .iter()
+            .map(|&(_, value)| value.abs())
+            .fold(0.0, f32::max);
+        assert!(peak < 0.05, "aliased peak {peak}");
+    }
+}
@@ -0,0 +1,174 @@
+/// How the envelope trace is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EnvelopeMode {
+    #[default]
+    Off,
+    /// Over the raw waveform
+    Overlay,
+    /// In place of the raw waveform
+    Only,
+}
+
+impl EnvelopeMode {
+    pub const ALL: [EnvelopeMode; 3] =
+        [EnvelopeMode::Off, EnvelopeMode::Overlay, EnvelopeMode::Only];
+
+    /// Look up a mode by its label (case-insensitive)
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.label().eq_ignore_ascii_case(label))
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            EnvelopeMode::Off => EnvelopeMode::Overlay,
+            EnvelopeMode::Overlay => EnvelopeMode::Only,
+            EnvelopeMode::Only => EnvelopeMode::Off,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EnvelopeMode::Off => "Off",
+            EnvelopeMode::Overlay => "Overlay",
+            EnvelopeMode::Only => "Only",
+        }
+    }
+}
+
+/// Attack time range in seconds
+pub const ATTACK_RANGE: (f32, f32) = (0.0001, 1.0);
+/// Release time range in seconds
+pub const RELEASE_RANGE: (f32, f32) = (0.001, 5.0);
+
+/// Amplitude envelope of a signal: full-wave rectified, then smoothed by a
+/// one-pole low-pass that rises with the attack time constant and falls
+/// with the release one, like a compressor's level detector. Makes
+/// amplitude modulation, tremolo and gain changes readable at slow
+/// timebases.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnvelopeFollower {
+    pub mode: EnvelopeMode,
+    /// Seconds to rise about 63% of the way to a louder level
+    attack: f32,
+    /// Seconds to fall about 63% of the way to a quieter level
+    release: f32,
+}
+
+impl Default for EnvelopeFollower {
+    fn default() -> Self {
+        Self {
+            mode: EnvelopeMode::default(),
+            attack: 0.005,
+            release: 0.05,
+        }
+    }
+}
+
+impl EnvelopeFollower {
+    pub fn attack(&self) -> f32 {
+        self.attack
+    }
+
+    /// Clamped to `ATTACK_RANGE`
+    pub fn set_attack(&mut self, seconds: f32) {
+        self.attack = seconds.clamp(ATTACK_RANGE.0, ATTACK_RANGE.1);
+    }
+
+    pub fn release(&self) -> f32 {
+        self.release
+    }
+
+    /// Clamped to `RELEASE_RANGE`
+    pub fn set_release(&mut self, seconds: f32) {
+        self.release = seconds.clamp(RELEASE_RANGE.0, RELEASE_RANGE.1);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.mode != EnvelopeMode::Off
+    }
+
+    /// Whether the raw waveform is still drawn
+    pub fn shows_raw(&self) -> bool {
+        self.mode != EnvelopeMode::Only
+    }
+
+    /// The envelope of `samples`, one value per sample. Starts from the
+    /// peak of the first release time, near where a settled follower would
+    /// sit, so even a short buffer reads sensibly from its start.
+    pub fn follow(&self, samples: &[f32], sample_rate: u32) -> Vec<f32> {
+        self.levels(samples, sample_rate).collect()
+    }
+
+    /// `follow` a sample at a time, without collecting the envelope
+    pub fn levels<'a>(
+        &self,
+        samples: &'a [f32],
+        sample_rate: u32,
+    ) -> impl Iterator<Item = f32> + 'a {
+        let sample_rate = sample_rate.max(1) as f32;
+        let coefficient = |seconds: f32| (-1.0 / (seconds * sample_rate)).exp();
+        let attack = coefficient(self.attack);
+        let release = coefficient(self.release);
+
+        let settle = (self.release * sample_rate).ceil() as usize;
+        let mut level = samples
+            .iter()
+            .take(settle)
+            .fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+        samples.iter().map(move |sample| {
+            let rectified = sample.abs();
+            let coefficient = if rectified > level { attack } else { release };
+            level = rectified + coefficient * (level - rectified);
+            level
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_from_label() {
+        assert_eq!(
+            EnvelopeMode::from_label("overlay"),
+            Some(EnvelopeMode::Overlay)
+        );
+        assert_eq!(EnvelopeMode::from_label("Only"), Some(EnvelopeMode::Only));
+        assert_eq!(EnvelopeMode::from_label("rms"), None);
+    }
+
+    #[test]
+    fn test_follows_amplitude_of_a_tone() {
+        let follower = EnvelopeFollower::default();
+        // 1 kHz at 0.8 for 50 ms, then at 0.2
+        let samples: Vec<f32> = (0..9600)
+            .map(|i| {
+                let amplitude = if i < 2400 { 0.8 } else { 0.2 };
+                amplitude * (i as f32 / 48.0 * std::f32::consts::TAU).sin()
+            })
+            .collect();
+
+        let envelope = follower.follow(&samples, 48000);
+        assert_eq!(envelope.len(), samples.len());
+        // Settles near the rectified peak, with little ripple
+        assert!((0.6..=0.8).contains(&envelope[2399]), "{}", envelope[2399]);
+        // A slower release: still falling 5 ms after the drop, settled after 100 ms
+        assert!(envelope[2640] > 0.4, "{}", envelope[2640]);
+        assert!(envelope[9599] < 0.25, "{}", envelope[9599]);
+    }
+
+    #[test]
+    fn test_attack_and_release_are_clamped() {
+        let mut follower = EnvelopeFollower::default();
+        follower.set_attack(0.0);
+        follower.set_release(60.0);
+        assert_eq!(follower.attack(), ATTACK_RANGE.0);
+        assert_eq!(follower.release(), RELEASE_RANGE.1);
+    }
+}
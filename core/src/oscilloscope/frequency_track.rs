@@ -0,0 +1,190 @@
+use std::ops::Range;
+
+use crate::oscilloscope::hilbert::{analytic_signal, instantaneous_frequency};
+use crate::oscilloscope::WaveformData;
+
+/// Columns quieter than this fraction of the loudest are left out; their
+/// phase is mostly noise
+const MIN_RELATIVE_AMPLITUDE: f32 = 0.05;
+
+/// Narrowest frequency span shown, in Hz, so a steady tone reads as a flat
+/// line rather than its noise scaled up to fill the screen
+const MIN_SPAN: f32 = 10.0;
+
+/// Samples faded in and out at each end of the buffer before the transform
+const TAPER: usize = 200;
+
+/// Room left above and below the track, as a fraction of its span
+const SPAN_PADDING: f32 = 0.1;
+
+/// Instantaneous frequency against time across the screen, from the
+/// analytic signal of the capture. Shows vibrato, FM and wow/flutter as a
+/// wandering line where the waveform only shows a smear.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrequencyTrack {
+    /// (x as a fraction of the screen width, frequency in Hz), one per
+    /// display column with signal in it
+    pub points: Vec<(f32, f32)>,
+    /// Frequency at the bottom of the plot
+    pub low: f32,
+    /// Frequency at the top of the plot
+    pub high: f32,
+}
+
+impl FrequencyTrack {
+    /// Track the part of `waveform` on screen, averaged down to `columns`
+    /// points. The whole buffer is transformed so the trigger margin keeps
+    /// the transform's edge error off the left of the screen. `None` when
+    /// the screen is silent.
+    pub fn of(waveform: &WaveformData, range: Range<usize>, columns: usize) -> Option<Self> {
+        let range = range.start.min(waveform.samples.len())..range.end.min(waveform.samples.len());
+        // Fading the ends in and out stops the transform seeing a step where
+        // the buffer wraps around, which would ripple the whole track
+        let mut samples = waveform.samples[..range.end].to_vec();
+        let taper = TAPER.min(samples.len() / 4);
+        for i in 0..taper {
+            let gain = 0.5 - 0.5 * (std::f32::consts::PI * (i as f32 + 0.5) / taper as f32).cos();
+            let last = samples.len() - 1 - i;
+            samples[i] *= gain;
+            samples[last] *= gain;
+        }
+        let analytic = analytic_signal(&samples);
+        let frequencies = instantaneous_frequency(&analytic, waveform.sample_rate);
+
+        let samples_per_screen = waveform.calculate_samples_per_screen().max(1);
+        let columns = columns.clamp(1, samples_per_screen);
+        let column_start = |column: usize| range.start + column * samples_per_screen / columns;
+
+        // Each column's frequency is weighted by power, so the zero
+        // crossings of a quiet stretch count for less
+        let start = range.start;
+        let columns: Vec<(f32, f32, f32)> = (0..columns)
+            .map(|column| (column_start(column), column_start(column + 1)))
+            .take_while(|&(first, _)| first < range.end)
+            .map(|(first, last)| {
+                let last = last.min(range.end);
+                let (mut power, mut weighted) = (0.0, 0.0);
+                for i in first..last {
+                    let weight = analytic[i].norm_sqr();
+                    power += weight;
+                    weighted += weight * frequencies[i];
+                }
+                let centre = (first + last) as f32 / 2.0 - start as f32;
+                let count = (last - first) as f32;
+                (
+                    centre / samples_per_screen as f32,
+                    weighted / power.max(f32::MIN_POSITIVE),
+                    power / count,
+                )
+            })
+            .collect();
+
+        let loudest = columns
+            .iter()
+            .map(|&(_, _, power)| power)
+            .fold(0.0, f32::max);
+        if loudest <= 0.0 {
+            return None;
+        }
+        let threshold = loudest * MIN_RELATIVE_AMPLITUDE * MIN_RELATIVE_AMPLITUDE;
+        let points: Vec<(f32, f32)> = columns
+            .into_iter()
+            .filter(|&(_, _, power)| power >= threshold)
+            .map(|(x, frequency, _)| (x, frequency))
+            .collect();
+
+        let (lowest, highest) = points
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(low, high), &(_, frequency)| {
+                (low.min(frequency), high.max(frequency))
+            });
+        let centre = (lowest + highest) / 2.0;
+        let half_span = (highest - lowest).max(MIN_SPAN) * (0.5 + SPAN_PADDING);
+
+        Some(Self {
+            points,
+            low: (centre - half_span).max(0.0),
+            high: centre + half_span,
+        })
+    }
+
+    /// Height of `frequency` on the plot, 0 at the bottom and 1 at the top
+    pub fn level(&self, frequency: f32) -> f32 {
+        (frequency - self.low) / (self.high - self.low)
+    }
+
+    /// Mean frequency over the screen
+    pub fn mean(&self) -> f32 {
+        let sum: f32 = self.points.iter().map(|&(_, frequency)| frequency).sum();
+        sum / self.points.len().max(1) as f32
+    }
+
+    /// Largest departure from the mean, e.g. the depth of a vibrato
+    pub fn deviation(&self) -> f32 {
+        let mean = self.mean();
+        self.points
+            .iter()
+            .map(|&(_, frequency)| (frequency - mean).abs())
+            .fold(0.0, f32::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oscilloscope::TriggerSettings;
+
+    fn waveform_of(signal: impl Fn(f32) -> f32) -> WaveformData {
+        let mut waveform = WaveformData::new(48000);
+        // 10 ms/div: 100 ms on screen
+        waveform.time_per_division = 0.01;
+        let length = waveform.calculate_samples_per_screen() + 200;
+        let samples: Vec<f32> = (0..length).map(|i| signal(i as f32 / 48000.0)).collect();
+        waveform.update_samples(samples);
+        waveform
+    }
+
+    #[test]
+    fn test_tracks_vibrato() {
+        let tau = std::f32::consts::TAU;
+        // 1 kHz swinging ±20 Hz twenty times a second
+        let waveform = waveform_of(|t| {
+            let phase = 1000.0 * t - (tau * 20.0 * t).cos() / tau;
+            0.5 * (tau * phase).sin()
+        });
+        let range = waveform.display_range(&TriggerSettings {
+            enabled: false,
+            ..Default::default()
+        });
+
+        let track = FrequencyTrack::of(&waveform, range, 480).unwrap();
+        // Bar the last few columns, quiet where the buffer is faded out
+        assert!(track.points.len() > 470, "{}", track.points.len());
+        assert!((track.mean() - 1000.0).abs() < 2.0, "{}", track.mean());
+        assert!(
+            (track.deviation() - 20.0).abs() < 4.0,
+            "{}",
+            track.deviation()
+        );
+        assert!(track.low < 980.0 && track.high > 1020.0);
+        assert!((track.level(1000.0) - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_steady_tone_keeps_a_minimum_span() {
+        let tau = std::f32::consts::TAU;
+        let waveform = waveform_of(|t| 0.5 * (tau * 440.0 * t).sin());
+        let range = waveform.display_range(&TriggerSettings::default());
+
+        let track = FrequencyTrack::of(&waveform, range, 100).unwrap();
+        assert!(track.high - track.low >= MIN_SPAN);
+        assert!((track.mean() - 440.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_silence_has_no_track() {
+        let waveform = waveform_of(|_| 0.0);
+        let range = waveform.display_range(&TriggerSettings::default());
+        assert!(FrequencyTrack::of(&waveform, range, 100).is_none());
+    }
+}
@@ -0,0 +1,152 @@
+/// Harmonics listed, the fundamental included
+pub const HARMONIC_COUNT: usize = 10;
+
+/// Weakest fundamental locked onto, in dB; anything quieter is noise
+const MIN_FUNDAMENTAL_DB: f32 = -60.0;
+
+/// Lowest bins skipped when looking for the fundamental, where the window
+/// leaks DC
+const DC_BINS: usize = 2;
+
+/// Bins either side of a harmonic's expected position searched for its peak
+const SEARCH_BINS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Harmonic {
+    /// 1 for the fundamental
+    pub number: usize,
+    pub frequency: f32,
+    /// Relative to the fundamental (dBc)
+    pub level: f32,
+}
+
+/// The fundamental of a magnitude spectrum and its harmonics, for amplifier
+/// and converter distortion tests
+#[derive(Debug, Clone, PartialEq)]
+pub struct HarmonicAnalysis {
+    pub fundamental: f32,
+    /// Absolute level of the fundamental in dB
+    pub fundamental_level: f32,
+    /// Up to `HARMONIC_COUNT`, stopping at the Nyquist frequency
+    pub harmonics: Vec<Harmonic>,
+    /// Total harmonic distortion in percent, from the listed harmonics.
+    /// Noise under a harmonic's bin counts towards it, so a clean tone
+    /// reads down to the noise floor rather than zero.
+    pub thd: f32,
+}
+
+impl HarmonicAnalysis {
+    /// Lock onto the strongest tone in `magnitudes` (dB, as produced by
+    /// `SpectrumAnalyzer`) and measure its harmonics. `None` without a tone
+    /// clear of the noise.
+    pub fn of(magnitudes: &[f32], sample_rate: u32) -> Option<Self> {
+        if magnitudes.len() <= DC_BINS + 1 {
+            return None;
+        }
+        let bin_width = sample_rate as f32 / (2 * magnitudes.len()) as f32;
+
+        let peak_bin =
+            (DC_BINS..magnitudes.len()).max_by(|&a, &b| magnitudes[a].total_cmp(&magnitudes[b]))?;
+        let (offset, fundamental_level) = interpolate_peak(magnitudes, peak_bin);
+        if fundamental_level < MIN_FUNDAMENTAL_DB {
+            return None;
+        }
+        let fundamental = (peak_bin as f32 + offset) * bin_width;
+
+        let nyquist = sample_rate as f32 / 2.0;
+        let harmonics: Vec<Harmonic> = (1..=HARMONIC_COUNT)
+            .map(|number| (number, fundamental * number as f32))
+            .take_while(|&(_, frequency)| frequency < nyquist)
+            .map(|(number, frequency)| {
+                let expected = (frequency / bin_width).round() as usize;
+                let first = expected.saturating_sub(SEARCH_BINS);
+                let last = (expected + SEARCH_BINS).min(magnitudes.len() - 1);
+                let bin = (first..=last)
+                    .max_by(|&a, &b| magnitudes[a].total_cmp(&magnitudes[b]))
+                    .unwrap_or(expected);
+                let (_, level) = interpolate_peak(magnitudes, bin);
+                Harmonic {
+                    number,
+                    frequency,
+                    level: level - fundamental_level,
+                }
+            })
+            .collect();
+
+        let distortion: f32 = harmonics
+            .iter()
+            .skip(1)
+            .map(|harmonic| 10_f32.powf(harmonic.level / 10.0))
+            .sum();
+
+        Some(Self {
+            fundamental,
+            fundamental_level,
+            harmonics,
+            thd: distortion.sqrt() * 100.0,
+        })
+    }
+}
+
+/// Fractional bin offset and level of the peak at `bin`, from a parabola
+/// through it and its neighbours
+fn interpolate_peak(magnitudes: &[f32], bin: usize) -> (f32, f32) {
+    if bin == 0 || bin + 1 >= magnitudes.len() {
+        return (0.0, magnitudes[bin]);
+    }
+    let (left, centre, right) = (magnitudes[bin - 1], magnitudes[bin], magnitudes[bin + 1]);
+    let curvature = left - 2.0 * centre + right;
+    if curvature >= 0.0 {
+        return (0.0, centre);
+    }
+    let offset = 0.5 * (left - right) / curvature;
+    (offset, centre - 0.25 * (left - right) * offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oscilloscope::SpectrumAnalyzer;
+
+    fn spectrum_of(signal: impl Fn(f32) -> f32) -> Vec<f32> {
+        let samples: Vec<f32> = (0..4096).map(|i| signal(i as f32 / 48000.0)).collect();
+        let mut analyzer = SpectrumAnalyzer::new();
+        analyzer.update(&samples);
+        analyzer.magnitudes().to_vec()
+    }
+
+    #[test]
+    fn test_locks_onto_fundamental() {
+        let tau = std::f32::consts::TAU;
+        let magnitudes =
+            spectrum_of(|t| 0.5 * (tau * 1000.0 * t).sin() + 0.05 * (tau * 3000.0 * t).sin());
+
+        let analysis = HarmonicAnalysis::of(&magnitudes, 48000).unwrap();
+        assert!((analysis.fundamental - 1000.0).abs() < 5.0);
+        assert_eq!(analysis.harmonics.len(), HARMONIC_COUNT);
+        assert_eq!(analysis.harmonics[0].level, 0.0);
+
+        // The third harmonic is 20 dB down and dominates the distortion
+        let third = analysis.harmonics[2];
+        assert_eq!(third.number, 3);
+        assert!((third.frequency - 3000.0).abs() < 15.0);
+        assert!((third.level + 20.0).abs() < 1.0, "{}", third.level);
+        assert!((analysis.thd - 10.0).abs() < 1.0, "{}", analysis.thd);
+    }
+
+    #[test]
+    fn test_harmonics_stop_at_nyquist() {
+        let tau = std::f32::consts::TAU;
+        let magnitudes = spectrum_of(|t| 0.5 * (tau * 5000.0 * t).sin());
+
+        let analysis = HarmonicAnalysis::of(&magnitudes, 48000).unwrap();
+        // 5 kHz to 20 kHz, below 24 kHz
+        assert_eq!(analysis.harmonics.len(), 4);
+    }
+
+    #[test]
+    fn test_no_fundamental_in_silence() {
+        assert!(HarmonicAnalysis::of(&spectrum_of(|_| 0.0), 48000).is_none());
+        assert!(HarmonicAnalysis::of(&[], 48000).is_none());
+    }
+}
@@ -0,0 +1,108 @@
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+/// The analytic signal of `samples`: the samples themselves as the real
+/// part and their Hilbert transform as the imaginary part.
+///
+/// Computed in the frequency domain by dropping the negative frequencies
+/// and doubling the positive ones. The transform treats the buffer as one
+/// period of a repeating signal, so the first and last few cycles pick up
+/// some error where the ends don't meet.
+pub fn analytic_signal(samples: &[f32]) -> Vec<Complex<f32>> {
+    let n = samples.len();
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let mut planner = FftPlanner::new();
+    let mut buffer: Vec<Complex<f32>> = samples
+        .iter()
+        .map(|&sample| Complex::new(sample, 0.0))
+        .collect();
+    planner.plan_fft_forward(n).process(&mut buffer);
+
+    // DC and, for even lengths, the Nyquist bin have no negative twin
+    let positive_end = n.div_ceil(2);
+    for (bin, value) in buffer.iter_mut().enumerate() {
+        let gain = if bin == 0 || 2 * bin == n {
+            1.0
+        } else if bin < positive_end {
+            2.0
+        } else {
+            0.0
+        };
+        *value *= gain / n as f32;
+    }
+
+    planner.plan_fft_inverse(n).process(&mut buffer);
+    buffer
+}
+
+/// Instantaneous frequency in Hz at each point of an analytic signal, from
+/// the phase advance since the previous point. The first point repeats the
+/// second, so the result lines up with the input.
+pub fn instantaneous_frequency(analytic: &[Complex<f32>], sample_rate: u32) -> Vec<f32> {
+    if analytic.is_empty() {
+        return Vec::new();
+    }
+
+    let hertz_per_radian = sample_rate as f32 / std::f32::consts::TAU;
+    let mut frequencies: Vec<f32> = analytic
+        .windows(2)
+        .map(|pair| (pair[1] * pair[0].conj()).arg() * hertz_per_radian)
+        .collect();
+    frequencies.insert(0, frequencies.first().copied().unwrap_or(0.0));
+    frequencies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analytic_signal_of_a_cosine() {
+        let tau = std::f32::consts::TAU;
+        let samples: Vec<f32> = (0..1000).map(|i| (tau * i as f32 / 50.0).cos()).collect();
+
+        let analytic = analytic_signal(&samples);
+        assert_eq!(analytic.len(), samples.len());
+        // A whole number of cycles: the imaginary part is the matching sine
+        for (i, value) in analytic.iter().enumerate() {
+            assert!((value.re - samples[i]).abs() < 1e-3);
+            assert!((value.im - (tau * i as f32 / 50.0).sin()).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_instantaneous_frequency_follows_a_sweep() {
+        // 1 kHz rising linearly to 2 kHz over the buffer
+        let sample_rate = 48000;
+        let duration = 0.1;
+        let samples: Vec<f32> = (0..4800)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                let phase = 1000.0 * t + 5000.0 * t * t;
+                (std::f32::consts::TAU * phase).sin()
+            })
+            .collect();
+
+        let frequencies = instantaneous_frequency(&analytic_signal(&samples), sample_rate);
+        assert_eq!(frequencies.len(), samples.len());
+        // Away from the ends, where the wrap-around discontinuity is felt
+        for i in (480..4320).step_by(240) {
+            let t = i as f32 / sample_rate as f32;
+            let expected = 1000.0 + 1000.0 * t / duration;
+            assert!(
+                (frequencies[i] - expected).abs() < 20.0,
+                "{} Hz at {i}, expected {expected}",
+                frequencies[i]
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert!(analytic_signal(&[]).is_empty());
+        assert!(instantaneous_frequency(&[], 48000).is_empty());
+    }
+}
@@ -0,0 +1,484 @@
+/// A math trace: an expression over the record's channels, compiled once
+/// and then run over every record.
+///
+/// `A` and `B` are the first two input channels, the same signal on a
+/// mono input, and `t` is seconds from the start of the record. Numbers,
+/// `+ - * / ^` and parentheses work as usual. Per-sample functions are
+/// `abs`, `sqrt`, `sin`, `cos`, `tan`, `exp`, `ln`, `log10` and `sign`.
+/// Record functions see the whole record: `avg`, `rms`, `min` and `max`
+/// give one number for it, `derivative` the rate of change per second and
+/// `integral` the running integral from the start. So `A - avg(A)` takes
+/// out the DC offset and `A*B` is the instantaneous product of the two.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MathExpression {
+    source: String,
+    /// Postfix, so evaluation is one pass over a stack
+    program: Vec<Op>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Constant(f32),
+    A,
+    B,
+    Time,
+    Negate,
+    Function(Function),
+    Binary(Binary),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Binary {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Abs,
+    Sqrt,
+    Sin,
+    Cos,
+    Tan,
+    Exp,
+    Ln,
+    Log10,
+    Sign,
+    Average,
+    Rms,
+    Min,
+    Max,
+    Derivative,
+    Integral,
+}
+
+impl Function {
+    const NAMES: [(&'static str, Function); 15] = [
+        ("abs", Function::Abs),
+        ("sqrt", Function::Sqrt),
+        ("sin", Function::Sin),
+        ("cos", Function::Cos),
+        ("tan", Function::Tan),
+        ("exp", Function::Exp),
+        ("ln", Function::Ln),
+        ("log10", Function::Log10),
+        ("sign", Function::Sign),
+        ("avg", Function::Average),
+        ("rms", Function::Rms),
+        ("min", Function::Min),
+        ("max", Function::Max),
+        ("derivative", Function::Derivative),
+        ("integral", Function::Integral),
+    ];
+
+    fn per_sample(self) -> Option<fn(f32) -> f32> {
+        Some(match self {
+            Function::Abs => f32::abs,
+            Function::Sqrt => f32::sqrt,
+            Function::Sin => f32::sin,
+            Function::Cos => f32::cos,
+            Function::Tan => f32::tan,
+            Function::Exp => f32::exp,
+            Function::Ln => f32::ln,
+            Function::Log10 => f32::log10,
+            Function::Sign => |x: f32| if x == 0.0 { 0.0 } else { x.signum() },
+            _ => return None,
+        })
+    }
+}
+
+/// A scalar stays a scalar until it meets a signal, so constants and
+/// record statistics cost nothing per sample
+enum Value {
+    Scalar(f32),
+    Signal(Vec<f32>),
+}
+
+impl Value {
+    fn map(self, f: impl Fn(f32) -> f32) -> Value {
+        match self {
+            Value::Scalar(x) => Value::Scalar(f(x)),
+            Value::Signal(mut signal) => {
+                signal.iter_mut().for_each(|x| *x = f(*x));
+                Value::Signal(signal)
+            }
+        }
+    }
+
+    /// A record statistic: one number for the whole signal
+    fn reduce(self, f: impl Fn(&[f32]) -> f32) -> Value {
+        match self {
+            Value::Scalar(x) => Value::Scalar(x),
+            Value::Signal(signal) if signal.is_empty() => Value::Scalar(0.0),
+            Value::Signal(signal) => Value::Scalar(f(&signal)),
+        }
+    }
+}
+
+impl MathExpression {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+            program: Vec::new(),
+        };
+        parser.expression()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("Unexpected {token}"));
+        }
+        Ok(Self {
+            source: source.trim().to_string(),
+            program: parser.program,
+        })
+    }
+
+    /// The expression as entered
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Run over one record of channels `a` and `b`. The result is as long
+    /// as the shorter channel.
+    pub fn evaluate(&self, a: &[f32], b: &[f32], sample_rate: u32) -> Vec<f32> {
+        let len = a.len().min(b.len());
+        let period = 1.0 / sample_rate.max(1) as f32;
+        let mut stack: Vec<Value> = Vec::with_capacity(self.program.len());
+
+        for &op in &self.program {
+            let value = match op {
+                Op::Constant(x) => Value::Scalar(x),
+                Op::A => Value::Signal(a[..len].to_vec()),
+                Op::B => Value::Signal(b[..len].to_vec()),
+                Op::Time => Value::Signal((0..len).map(|i| i as f32 * period).collect()),
+                Op::Negate => pop(&mut stack).map(|x| -x),
+                Op::Function(function) => {
+                    let value = pop(&mut stack);
+                    match function.per_sample() {
+                        Some(f) => value.map(f),
+                        None => record_function(function, value, len, sample_rate),
+                    }
+                }
+                Op::Binary(binary) => {
+                    let right = pop(&mut stack);
+                    let left = pop(&mut stack);
+                    apply(binary, left, right)
+                }
+            };
+            stack.push(value);
+        }
+
+        match stack.pop() {
+            Some(Value::Signal(signal)) => signal,
+            Some(Value::Scalar(x)) => vec![x; len],
+            None => vec![0.0; len],
+        }
+    }
+}
+
+/// The parser only emits programs that keep the stack fed
+fn pop(stack: &mut Vec<Value>) -> Value {
+    stack.pop().unwrap_or(Value::Scalar(0.0))
+}
+
+fn record_function(function: Function, value: Value, len: usize, sample_rate: u32) -> Value {
+    let rate = sample_rate.max(1) as f32;
+    match function {
+        Function::Average => value.reduce(|x| x.iter().sum::<f32>() / x.len() as f32),
+        Function::Rms => value
+            .reduce(|x| (x.iter().map(|x| x * x).sum::<f32>() / x.len() as f32).sqrt())
+            .map(f32::abs),
+        Function::Min => value.reduce(|x| x.iter().copied().fold(f32::INFINITY, f32::min)),
+        Function::Max => value.reduce(|x| x.iter().copied().fold(f32::NEG_INFINITY, f32::max)),
+        Function::Derivative => match value {
+            Value::Scalar(_) => Value::Scalar(0.0),
+            Value::Signal(x) => {
+                // Backward differences, the first sample taking the second's
+                let mut slope: Vec<f32> = x.windows(2).map(|w| (w[1] - w[0]) * rate).collect();
+                if let Some(&first) = slope.first() {
+                    slope.insert(0, first);
+                } else {
+                    slope = vec![0.0; x.len()];
+                }
+                Value::Signal(slope)
+            }
+        },
+        Function::Integral => {
+            let signal = match value {
+                Value::Scalar(x) => vec![x; len],
+                Value::Signal(x) => x,
+            };
+            let mut sum = 0.0;
+            Value::Signal(
+                signal
+                    .iter()
+                    .map(|&x| {
+                        sum += x / rate;
+                        sum
+                    })
+                    .collect(),
+            )
+        }
+        _ => value,
+    }
+}
+
+fn apply(binary: Binary, left: Value, right: Value) -> Value {
+    let f = match binary {
+        Binary::Add => |x: f32, y: f32| x + y,
+        Binary::Subtract => |x: f32, y: f32| x - y,
+        Binary::Multiply => |x: f32, y: f32| x * y,
+        Binary::Divide => |x: f32, y: f32| x / y,
+        Binary::Power => f32::powf,
+    };
+    match (left, right) {
+        (Value::Scalar(x), Value::Scalar(y)) => Value::Scalar(f(x, y)),
+        (Value::Signal(x), Value::Scalar(y)) => Value::Signal(x).map(|x| f(x, y)),
+        (Value::Scalar(x), Value::Signal(y)) => Value::Signal(y).map(|y| f(x, y)),
+        (Value::Signal(mut x), Value::Signal(y)) => {
+            x.iter_mut().zip(&y).for_each(|(x, &y)| *x = f(*x, y));
+            Value::Signal(x)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Name(String),
+    Symbol(char),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(x) => write!(f, "number {x}"),
+            Token::Name(name) => write!(f, "'{name}'"),
+            Token::Symbol(c) => write!(f, "'{c}'"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                let exponent_sign = (c == '+' || c == '-') && source[..i].ends_with(['e', 'E']);
+                if !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign) {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let text = &source[start..end];
+            let number = text
+                .parse()
+                .map_err(|_| format!("'{text}' isn't a number"))?;
+            tokens.push(Token::Number(number));
+        } else if c.is_alphabetic() {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Name(source[start..end].to_string()));
+        } else if "+-*/^()".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else {
+            return Err(format!("Unexpected '{c}'"));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent, lowest precedence first, emitting postfix
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    program: Vec<Op>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn accept(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        if self.accept(symbol) {
+            Ok(())
+        } else {
+            match self.peek() {
+                Some(token) => Err(format!("Expected '{symbol}' but found {token}")),
+                None => Err(format!("Expected '{symbol}'")),
+            }
+        }
+    }
+
+    /// term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<(), String> {
+        self.term()?;
+        loop {
+            let op = if self.accept('+') {
+                Binary::Add
+            } else if self.accept('-') {
+                Binary::Subtract
+            } else {
+                return Ok(());
+            };
+            self.term()?;
+            self.program.push(Op::Binary(op));
+        }
+    }
+
+    /// unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<(), String> {
+        self.unary()?;
+        loop {
+            let op = if self.accept('*') {
+                Binary::Multiply
+            } else if self.accept('/') {
+                Binary::Divide
+            } else {
+                return Ok(());
+            };
+            self.unary()?;
+            self.program.push(Op::Binary(op));
+        }
+    }
+
+    /// '-' unary | power
+    fn unary(&mut self) -> Result<(), String> {
+        if self.accept('-') {
+            self.unary()?;
+            self.program.push(Op::Negate);
+            Ok(())
+        } else if self.accept('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+
+    /// primary ('^' unary)?, so `-A^2` is `-(A^2)` and `2^-1` works
+    fn power(&mut self) -> Result<(), String> {
+        self.primary()?;
+        if self.accept('^') {
+            self.unary()?;
+            self.program.push(Op::Binary(Binary::Power));
+        }
+        Ok(())
+    }
+
+    fn primary(&mut self) -> Result<(), String> {
+        match self.next().cloned() {
+            Some(Token::Number(x)) => self.program.push(Op::Constant(x)),
+            Some(Token::Symbol('(')) => {
+                self.expression()?;
+                self.expect(')')?;
+            }
+            Some(Token::Name(name)) => match name.as_str() {
+                "A" | "a" => self.program.push(Op::A),
+                "B" | "b" => self.program.push(Op::B),
+                "t" => self.program.push(Op::Time),
+                "pi" => self.program.push(Op::Constant(std::f32::consts::PI)),
+                _ => {
+                    let function = Function::NAMES
+                        .iter()
+                        .find(|(function, _)| *function == name)
+                        .map(|&(_, function)| function)
+                        .ok_or_else(|| format!("Unknown name '{name}'"))?;
+                    self.expect('(')?;
+                    self.expression()?;
+                    self.expect(')')?;
+                    self.program.push(Op::Function(function));
+                }
+            },
+            Some(token) => return Err(format!("Unexpected {token}")),
+            None => return Err("Expression ends too soon".to_string()),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(source: &str, a: &[f32], b: &[f32]) -> Vec<f32> {
+        MathExpression::parse(source).unwrap().evaluate(a, b, 10)
+    }
+
+    #[test]
+    fn test_arithmetic_and_precedence() {
+        let a = [1.0, -2.0, 3.0];
+        let b = [2.0, 2.0, 2.0];
+        assert_eq!(evaluate("A*B", &a, &b), vec![2.0, -4.0, 6.0]);
+        assert_eq!(evaluate("1 + 2 * 3", &a, &b), vec![7.0; 3]);
+        assert_eq!(evaluate("(1 + 2) * 3", &a, &b), vec![9.0; 3]);
+        assert_eq!(evaluate("-B^2", &a, &b), vec![-4.0; 3]);
+        assert_eq!(evaluate("2^-1", &a, &b), vec![0.5; 3]);
+        assert_eq!(evaluate("abs(A) - B / 2", &a, &b), vec![0.0, 1.0, 2.0]);
+        assert_eq!(evaluate("1.5e1", &a, &b), vec![15.0; 3]);
+        // t counts seconds at the 10 Hz test rate
+        assert_eq!(evaluate("t * 10", &a, &b), vec![0.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_record_functions() {
+        let a = [1.0, 2.0, 3.0, 6.0];
+        assert_eq!(evaluate("A - avg(A)", &a, &a), vec![-2.0, -1.0, 0.0, 3.0]);
+        assert_eq!(evaluate("max(A) - min(A)", &a, &a), vec![5.0; 4]);
+        assert_eq!(evaluate("rms(2)", &a, &a), vec![2.0; 4]);
+        // Slopes per second at 10 Hz
+        assert_eq!(
+            evaluate("derivative(A)", &a, &a),
+            vec![10.0, 10.0, 10.0, 30.0]
+        );
+        assert_eq!(evaluate("integral(10)", &a, &a), vec![1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(evaluate("avg(A)", &[], &[]), Vec::<f32>::new());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (source, error) in [
+            ("", "Expression ends too soon"),
+            ("A +", "Expression ends too soon"),
+            ("(A", "Expected ')'"),
+            ("A B", "Unexpected 'B'"),
+            ("foo(A)", "Unknown name 'foo'"),
+            ("abs A", "Expected '(' but found 'A'"),
+            ("A % 2", "Unexpected '%'"),
+            ("1.2.3", "'1.2.3' isn't a number"),
+        ] {
+            assert_eq!(MathExpression::parse(source), Err(error.to_string()));
+        }
+        assert_eq!(MathExpression::parse(" A*B ").unwrap().source(), "A*B");
+    }
+}
@@ -0,0 +1,23 @@
+use crate::oscilloscope::WaveformData;
+
+/// The automatic measurements of a capture, each `None` when the signal
+/// doesn't have enough of it to measure
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Measurements {
+    pub frequency: Option<f32>,
+    pub peak_to_peak: Option<f32>,
+    pub rms: Option<f32>,
+    /// Percent of the period spent above zero
+    pub duty_cycle: Option<f32>,
+}
+
+impl Measurements {
+    pub fn of(waveform: &WaveformData) -> Self {
+        Self {
+            frequency: waveform.calculate_frequency(),
+            peak_to_peak: waveform.calculate_peak_to_peak(),
+            rms: waveform.calculate_rms(),
+            duty_cycle: waveform.calculate_duty_cycle(),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+/// Mel bands used unless configured otherwise
+pub const DEFAULT_MEL_BANDS: usize = 64;
+
+/// Fewest and most mel bands
+pub const MEL_BANDS_RANGE: (usize, usize) = (8, 256);
+
+/// Energy floor in dB, so silent bands don't read as minus infinity
+const FLOOR_DB: f32 = -100.0;
+
+/// Mel pitch of `hertz` (the HTK formula)
+pub fn hz_to_mel(hertz: f32) -> f32 {
+    2595.0 * (1.0 + hertz / 700.0).log10()
+}
+
+/// Frequency in Hz of `mel`, the inverse of `hz_to_mel`
+pub fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10_f32.powf(mel / 2595.0) - 1.0)
+}
+
+/// Triangular filters spaced evenly in mel from 0 Hz to the Nyquist
+/// frequency, each rising from its lower neighbour's centre to its own and
+/// falling to its upper neighbour's, summing FFT power into mel bands.
+///
+/// With few FFT bins and many bands, the narrowest low bands can fall
+/// between bins and read as the floor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MelFilterbank {
+    fft_size: usize,
+    sample_rate: u32,
+    /// First FFT bin and the weights from there, one entry per band
+    filters: Vec<(usize, Vec<f32>)>,
+}
+
+impl MelFilterbank {
+    pub fn new(bands: usize, fft_size: usize, sample_rate: u32) -> Self {
+        let bands = bands.clamp(MEL_BANDS_RANGE.0, MEL_BANDS_RANGE.1);
+        let bin_width = sample_rate as f32 / fft_size.max(1) as f32;
+        let top = hz_to_mel(sample_rate as f32 / 2.0);
+        // Centres of the bands with an edge either side
+        let edges: Vec<f32> = (0..bands + 2)
+            .map(|i| mel_to_hz(top * i as f32 / (bands + 1) as f32))
+            .collect();
+
+        let filters = edges
+            .windows(3)
+            .map(|edge| {
+                let (low, centre, high) = (edge[0], edge[1], edge[2]);
+                let first = (low / bin_width).ceil() as usize;
+                let last =
+                    ((high / bin_width).floor() as usize).min((fft_size / 2).saturating_sub(1));
+                let weights = (first..=last)
+                    .map(|bin| {
+                        let frequency = bin as f32 * bin_width;
+                        if frequency <= centre {
+                            (frequency - low) / (centre - low)
+                        } else {
+                            (high - frequency) / (high - centre)
+                        }
+                    })
+                    .collect();
+                (first, weights)
+            })
+            .collect();
+
+        Self {
+            fft_size,
+            sample_rate,
+            filters,
+        }
+    }
+
+    pub fn bands(&self) -> usize {
+        self.filters.len()
+    }
+
+    /// Whether this bank was built for these settings
+    pub fn matches(&self, bands: usize, fft_size: usize, sample_rate: u32) -> bool {
+        self.bands() == bands.clamp(MEL_BANDS_RANGE.0, MEL_BANDS_RANGE.1)
+            && self.fft_size == fft_size
+            && self.sample_rate == sample_rate
+    }
+
+    /// Energy in dB of each band, from the power of the positive-frequency
+    /// FFT bins
+    pub fn apply(&self, power: &[f32], bands: &mut Vec<f32>) {
+        bands.clear();
+        bands.extend(self.filters.iter().map(|(first, weights)| {
+            let energy: f32 = weights
+                .iter()
+                .zip(power.iter().skip(*first))
+                .map(|(weight, power)| weight * power)
+                .sum();
+            (10.0 * energy.max(1e-10).log10()).max(FLOOR_DB)
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mel_scale_round_trips() {
+        // 1000 Hz is close to 1000 mel by construction
+        assert!((hz_to_mel(1000.0) - 1000.0).abs() < 0.5);
+        for hertz in [0.0, 100.0, 440.0, 8000.0, 24000.0] {
+            assert!((mel_to_hz(hz_to_mel(hertz)) - hertz).abs() < hertz * 1e-4 + 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_tone_lands_in_one_band() {
+        let bank = MelFilterbank::new(40, 2048, 48000);
+        assert_eq!(bank.bands(), 40);
+        assert!(bank.matches(40, 2048, 48000));
+        assert!(!bank.matches(64, 2048, 48000));
+
+        // All the power in the bin nearest 1 kHz
+        let mut power = vec![0.0; 1024];
+        power[(1000.0_f32 / 48000.0 * 2048.0).round() as usize] = 1.0;
+        let mut bands = Vec::new();
+        bank.apply(&power, &mut bands);
+
+        let loud: Vec<usize> = (0..bands.len()).filter(|&i| bands[i] > -20.0).collect();
+        assert!(!loud.is_empty() && loud.len() <= 2, "{loud:?}");
+        let band = loud[0];
+        let top = hz_to_mel(24000.0);
+        let centre = mel_to_hz(top * (band + 1) as f32 / 41.0);
+        assert!(
+            (centre - 1000.0).abs() < 200.0,
+            "band {band} at {centre} Hz"
+        );
+    }
+
+    #[test]
+    fn test_band_count_is_clamped() {
+        assert_eq!(
+            MelFilterbank::new(1, 1024, 48000).bands(),
+            MEL_BANDS_RANGE.0
+        );
+        assert_eq!(
+            MelFilterbank::new(1000, 1024, 48000).bands(),
+            MEL_BANDS_RANGE.1
+        );
+    }
+}
@@ -1,5 +1,42 @@
+pub mod auto_range;
+pub mod band_power;
+pub mod constant_q;
+pub mod decimation;
+pub mod envelope;
+pub mod frequency_track;
+pub mod harmonics;
+pub mod hilbert;
+pub mod math;
+pub mod measurements;
+pub mod mel;
+pub mod octave_smoothing;
+pub mod phase;
+pub mod search;
+pub mod spectrogram;
+pub mod spectrum;
+pub mod spectrum_export;
+pub mod time_gate;
 pub mod trigger;
+pub mod vectorscope;
 pub mod waveform;
+pub mod zoom;
 
-pub use trigger::TriggerSettings;
+pub use auto_range::AutoRange;
+pub use band_power::{BandCursor, BandCursors, BandPower};
+pub use decimation::Decimation;
+pub use envelope::{EnvelopeFollower, EnvelopeMode};
+pub use frequency_track::FrequencyTrack;
+pub use harmonics::HarmonicAnalysis;
+pub use math::MathExpression;
+pub use measurements::Measurements;
+pub use octave_smoothing::OctaveSmoothing;
+pub use phase::{PhaseAnalyzer, PhasePoint, PhaseResponse};
+pub use search::{EventSearch, SearchKind};
+pub use spectrogram::SpectrogramHistory;
+pub use spectrum::{SpectrumAnalyzer, SpectrumAverage, SpectrumOverlap, SpectrumScale};
+pub use spectrum_export::SpectrumMetadata;
+pub use time_gate::{GateCursor, TimeGate};
+pub use trigger::{AutoSweep, AutoTrigger, TriggerMode, TriggerSettings};
+pub use vectorscope::{StereoBalance, Vectorscope};
 pub use waveform::WaveformData;
+pub use zoom::{ZoomHandle, ZoomWindow};
@@ -0,0 +1,156 @@
+use crate::oscilloscope::spectrum::{SpectrumScale, DB_MIN};
+
+/// Fractional-octave smoothing of the spectrum curve, as acoustics software
+/// shows it: each bin becomes the mean power of a band a fixed fraction of
+/// an octave wide around it, so the bands widen with frequency as hearing's
+/// do and the fine comb of a room response reads as its trend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OctaveSmoothing {
+    #[default]
+    Off,
+    Third,
+    Sixth,
+    Twelfth,
+}
+
+impl OctaveSmoothing {
+    pub const ALL: [OctaveSmoothing; 4] = [
+        OctaveSmoothing::Off,
+        OctaveSmoothing::Third,
+        OctaveSmoothing::Sixth,
+        OctaveSmoothing::Twelfth,
+    ];
+
+    /// Look up a smoothing by its label (case-insensitive)
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|smoothing| smoothing.label().eq_ignore_ascii_case(label))
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            OctaveSmoothing::Off => OctaveSmoothing::Third,
+            OctaveSmoothing::Third => OctaveSmoothing::Sixth,
+            OctaveSmoothing::Sixth => OctaveSmoothing::Twelfth,
+            OctaveSmoothing::Twelfth => OctaveSmoothing::Off,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OctaveSmoothing::Off => "Unsmoothed",
+            OctaveSmoothing::Third => "1/3 octave",
+            OctaveSmoothing::Sixth => "1/6 octave",
+            OctaveSmoothing::Twelfth => "1/12 octave",
+        }
+    }
+
+    /// Width of each band in octaves, `None` when off
+    pub fn octaves(&self) -> Option<f32> {
+        match self {
+            OctaveSmoothing::Off => None,
+            OctaveSmoothing::Third => Some(1.0 / 3.0),
+            OctaveSmoothing::Sixth => Some(1.0 / 6.0),
+            OctaveSmoothing::Twelfth => Some(1.0 / 12.0),
+        }
+    }
+
+    /// `magnitudes`, dB levels of the bins of a spectrum on `scale`,
+    /// smoothed into `smoothed`. Bins are averaged as power, so a narrow
+    /// peak spreads out lower rather than staying at its height.
+    pub fn smooth_into(
+        &self,
+        magnitudes: &[f32],
+        scale: SpectrumScale,
+        sample_rate: u32,
+        smoothed: &mut Vec<f32>,
+    ) {
+        smoothed.clear();
+        let Some(octaves) = self.octaves() else {
+            smoothed.extend_from_slice(magnitudes);
+            return;
+        };
+
+        let bins = magnitudes.len();
+        let frequencies: Vec<f32> = (0..bins)
+            .map(|bin| scale.bin_frequency(bin, bins, sample_rate))
+            .collect();
+        // Running sums of power, so each band's mean is one subtraction
+        let mut sums = Vec::with_capacity(bins + 1);
+        sums.push(0.0_f64);
+        for &db in magnitudes {
+            let total = sums[sums.len() - 1] + 10_f64.powf(db as f64 / 10.0);
+            sums.push(total);
+        }
+
+        let edge = 2_f32.powf(octaves / 2.0);
+        smoothed.extend(frequencies.iter().enumerate().map(|(bin, &frequency)| {
+            let start = frequencies
+                .partition_point(|&other| other < frequency / edge)
+                .min(bin);
+            let end = frequencies
+                .partition_point(|&other| other <= frequency * edge)
+                .max(bin + 1);
+            let mean = (sums[end] - sums[start]) / (end - start) as f64;
+            ((10.0 * mean.log10()) as f32).max(DB_MIN)
+        }));
+    }
+
+    /// `smooth_into` a new buffer
+    pub fn smooth(&self, magnitudes: &[f32], scale: SpectrumScale, sample_rate: u32) -> Vec<f32> {
+        let mut smoothed = Vec::new();
+        self.smooth_into(magnitudes, scale, sample_rate, &mut smoothed);
+        smoothed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_off_leaves_the_spectrum_alone() {
+        let magnitudes = [-10.0, -40.0, -20.0];
+        assert_eq!(
+            OctaveSmoothing::Off.smooth(&magnitudes, SpectrumScale::Linear, 48000),
+            magnitudes
+        );
+        assert_eq!(
+            OctaveSmoothing::from_label("1/6 OCTAVE"),
+            Some(OctaveSmoothing::Sixth)
+        );
+    }
+
+    #[test]
+    fn test_bands_widen_with_frequency() {
+        // 1024 bins of 23.4 Hz: a 1/3-octave band is about 23% of its
+        // centre, so one bin wide near 100 Hz and many near 10 kHz
+        let mut magnitudes = vec![DB_MIN; 1024];
+        magnitudes[4] = 0.0;
+        magnitudes[427] = 0.0;
+        let smoothed = OctaveSmoothing::Third.smooth(&magnitudes, SpectrumScale::Linear, 48000);
+        assert_eq!(smoothed.len(), 1024);
+        assert!(smoothed[4] > -1.0);
+        assert!(smoothed[427] < -15.0);
+        // Spread to its neighbours, as power
+        assert!(smoothed[420] > -25.0);
+
+        // Narrower bands keep more of the peak
+        let finer = OctaveSmoothing::Twelfth.smooth(&magnitudes, SpectrumScale::Linear, 48000);
+        assert!(finer[427] > smoothed[427]);
+    }
+
+    #[test]
+    fn test_flat_spectrum_stays_flat() {
+        let magnitudes = vec![-30.0; 64];
+        for smoothing in OctaveSmoothing::ALL {
+            for scale in SpectrumScale::ALL {
+                let smoothed = smoothing.smooth(&magnitudes, scale, 48000);
+                assert!(smoothed.iter().all(|&db| (db + 30.0).abs() < 1e-3));
+            }
+        }
+    }
+}
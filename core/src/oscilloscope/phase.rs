@@ -0,0 +1,375 @@
+use std::sync::Arc;
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+
+/// Pairs in each transform
+const FFT_SIZE: usize = 4096;
+
+/// Pairs between transforms: 50% overlap
+const HOP_SIZE: usize = FFT_SIZE / 2;
+
+/// Weight of each new transform in the running average of the spectra
+const AVERAGING: f32 = 0.25;
+
+/// Bins less coherent than this are left off the plot; their phase is
+/// mostly noise
+pub const MIN_COHERENCE: f32 = 0.5;
+
+/// Bins this far below the loudest in power are left off too
+const MIN_RELATIVE_POWER: f32 = 1e-6;
+
+/// Lowest frequency on the plot, in Hz
+pub const LOW_FREQUENCY: f32 = 20.0;
+
+/// Across the phase plot, 0 at `LOW_FREQUENCY` and 1 at the Nyquist
+/// frequency, on a log scale
+pub fn frequency_position(frequency: f32, sample_rate: u32) -> f32 {
+    let nyquist = sample_rate as f32 / 2.0;
+    (frequency / LOW_FREQUENCY).ln() / (nyquist / LOW_FREQUENCY).ln()
+}
+
+/// Phase of the right channel relative to the left at one frequency
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhasePoint {
+    pub frequency: f32,
+    /// Degrees, -180 to 180: negative where the right channel lags
+    pub phase: f32,
+    /// Magnitude-squared coherence, 0 (unrelated) to 1 (fully related)
+    pub coherence: f32,
+}
+
+/// Inter-channel phase against frequency, from the averaged
+/// cross-spectrum. A polarity flip shows as a flat line at ±180°, and a
+/// time offset as phase falling steadily with frequency and wrapping.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseResponse {
+    /// Coherent bins from `LOW_FREQUENCY` up, lowest first
+    pub points: Vec<PhasePoint>,
+    /// How far the right channel lags the left, in seconds, from the peak
+    /// of the phase-transform cross-correlation
+    pub delay: f32,
+    /// The correlation peak is negative: one channel's polarity is flipped
+    pub inverted: bool,
+    pub sample_rate: u32,
+}
+
+impl PhaseResponse {
+    /// See `frequency_position`
+    pub fn position(&self, frequency: f32) -> f32 {
+        frequency_position(frequency, self.sample_rate)
+    }
+
+    /// Height of `phase` on the plot, 0 at -180° and 1 at 180°
+    pub fn level(phase: f32) -> f32 {
+        (phase + 180.0) / 360.0
+    }
+
+    /// Runs of (position, level) to draw as lines, broken where bins are
+    /// left out and where the phase wraps between ±180°
+    pub fn traces(&self) -> Vec<Vec<(f32, f32)>> {
+        let bin_width = self.sample_rate as f32 / FFT_SIZE as f32;
+        let mut traces: Vec<Vec<(f32, f32)>> = Vec::new();
+        let mut previous: Option<PhasePoint> = None;
+        for &point in &self.points {
+            let joined = previous.is_some_and(|previous| {
+                point.frequency - previous.frequency < 1.5 * bin_width
+                    && (point.phase - previous.phase).abs() < 180.0
+            });
+            let position = (self.position(point.frequency), Self::level(point.phase));
+            match traces.last_mut() {
+                Some(trace) if joined => trace.push(position),
+                _ => traces.push(vec![position]),
+            }
+            previous = Some(point);
+        }
+        traces
+    }
+}
+
+/// Streams (left, right) pairs into overlapping transforms and keeps a
+/// running average of the channels' spectra and their cross-spectrum,
+/// for the phase between them
+pub struct PhaseAnalyzer {
+    fft: Arc<dyn Fft<f32>>,
+    inverse: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    /// The newest `FFT_SIZE` pairs pushed
+    history: Vec<(f32, f32)>,
+    /// Pairs pushed since the last transform
+    since_hop: usize,
+    /// Averages of conj(L)·R, |L|² and |R|² over the positive-frequency bins
+    cross: Vec<Complex<f32>>,
+    left_power: Vec<f32>,
+    right_power: Vec<f32>,
+    transforms: usize,
+    sample_rate: u32,
+}
+
+impl PhaseAnalyzer {
+    pub fn new(sample_rate: u32) -> Self {
+        let mut planner = FftPlanner::new();
+        let window = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+        let bins = FFT_SIZE / 2 + 1;
+        Self {
+            fft: planner.plan_fft_forward(FFT_SIZE),
+            inverse: planner.plan_fft_inverse(FFT_SIZE),
+            window,
+            history: Vec::with_capacity(FFT_SIZE),
+            since_hop: 0,
+            cross: vec![Complex::new(0.0, 0.0); bins],
+            left_power: vec![0.0; bins],
+            right_power: vec![0.0; bins],
+            transforms: 0,
+            sample_rate,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Add the newest (left, right) pairs, transforming every `HOP_SIZE`
+    /// of them once `FFT_SIZE` have arrived. True when the average moved.
+    ///
+    /// Unlike the spectrum, hops that arrive together are each transformed:
+    /// coherence only means something averaged over several.
+    pub fn push(&mut self, pairs: &[(f32, f32)]) -> bool {
+        let mut transformed = false;
+        let mut rest = pairs;
+        while !rest.is_empty() {
+            let take = (HOP_SIZE - self.since_hop).min(rest.len());
+            self.history.extend_from_slice(&rest[..take]);
+            rest = &rest[take..];
+            if self.history.len() > FFT_SIZE {
+                let to_remove = self.history.len() - FFT_SIZE;
+                self.history.drain(0..to_remove);
+            }
+
+            self.since_hop += take;
+            if self.since_hop == HOP_SIZE {
+                self.since_hop = 0;
+                if self.history.len() == FFT_SIZE {
+                    self.transform();
+                    transformed = true;
+                }
+            }
+        }
+        transformed
+    }
+
+    fn transform(&mut self) {
+        let mut left: Vec<Complex<f32>> = self
+            .history
+            .iter()
+            .zip(&self.window)
+            .map(|(&(sample, _), &weight)| Complex::new(sample * weight, 0.0))
+            .collect();
+        let mut right: Vec<Complex<f32>> = self
+            .history
+            .iter()
+            .zip(&self.window)
+            .map(|(&(_, sample), &weight)| Complex::new(sample * weight, 0.0))
+            .collect();
+        self.fft.process(&mut left);
+        self.fft.process(&mut right);
+
+        // The first transform stands alone rather than being averaged
+        // in with silence
+        let rate = if self.transforms == 0 { 1.0 } else { AVERAGING };
+        for bin in 0..self.cross.len() {
+            let (l, r) = (left[bin], right[bin]);
+            let cross = self.cross[bin];
+            self.cross[bin] = cross + (l.conj() * r - cross) * rate;
+            self.left_power[bin] += (l.norm_sqr() - self.left_power[bin]) * rate;
+            self.right_power[bin] += (r.norm_sqr() - self.right_power[bin]) * rate;
+        }
+        self.transforms += 1;
+    }
+
+    /// Coherence of one bin; 0 where either channel is silent
+    fn coherence(&self, bin: usize) -> f32 {
+        let power = self.left_power[bin] * self.right_power[bin];
+        if power > 0.0 {
+            (self.cross[bin].norm_sqr() / power).min(1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Phase of the coherent bins and the delay between the channels.
+    /// `None` until a transform has run, or when nothing is coherent.
+    pub fn response(&self) -> Option<PhaseResponse> {
+        if self.transforms == 0 {
+            return None;
+        }
+        let loudest = self
+            .left_power
+            .iter()
+            .zip(&self.right_power)
+            .map(|(&left, &right)| left.min(right))
+            .fold(0.0, f32::max);
+        let threshold = loudest * MIN_RELATIVE_POWER;
+        let bin_width = self.sample_rate as f32 / FFT_SIZE as f32;
+
+        let coherent: Vec<(usize, f32)> = (1..self.cross.len())
+            .map(|bin| (bin, self.coherence(bin)))
+            .filter(|&(bin, coherence)| {
+                coherence >= MIN_COHERENCE
+                    && self.left_power[bin].min(self.right_power[bin]) > threshold
+            })
+            .collect();
+        if coherent.is_empty() {
+            return None;
+        }
+
+        let points = coherent
+            .iter()
+            .map(|&(bin, coherence)| PhasePoint {
+                frequency: bin as f32 * bin_width,
+                phase: self.cross[bin].arg().to_degrees(),
+                coherence,
+            })
+            .filter(|point| point.frequency >= LOW_FREQUENCY)
+            .collect();
+
+        // Generalized cross-correlation with phase transform: each coherent
+        // bin contributes its phase alone, so the peak is sharp whatever
+        // the spectrum of the signal
+        let mut correlation = vec![Complex::new(0.0, 0.0); FFT_SIZE];
+        for &(bin, coherence) in &coherent {
+            let cross = self.cross[bin];
+            let weighted = cross / cross.norm() * coherence;
+            correlation[bin] = weighted;
+            if bin < FFT_SIZE / 2 {
+                correlation[FFT_SIZE - bin] = weighted.conj();
+            }
+        }
+        self.inverse.process(&mut correlation);
+        let (lag, peak) = correlation
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.re.abs().total_cmp(&b.re.abs()))
+            .map(|(lag, value)| (lag, value.re))
+            .unwrap_or((0, 0.0));
+        let lag = if lag > FFT_SIZE / 2 {
+            lag as f32 - FFT_SIZE as f32
+        } else {
+            lag as f32
+        };
+
+        Some(PhaseResponse {
+            points,
+            delay: lag / self.sample_rate as f32,
+            inverted: peak < 0.0,
+            sample_rate: self.sample_rate,
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.since_hop = 0;
+        self.cross.fill(Complex::new(0.0, 0.0));
+        self.left_power.fill(0.0);
+        self.right_power.fill(0.0);
+        self.transforms = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic white noise, -0.5 to 0.5
+    fn noise(length: usize) -> Vec<f32> {
+        let mut state: u32 = 12345;
+        (0..length)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 8) as f32 / (1 << 24) as f32 - 0.5
+            })
+            .collect()
+    }
+
+    fn analyze(pairs: &[(f32, f32)]) -> Option<PhaseResponse> {
+        let mut analyzer = PhaseAnalyzer::new(48000);
+        analyzer.push(pairs);
+        analyzer.response()
+    }
+
+    #[test]
+    fn test_identical_channels_are_in_phase() {
+        let pairs: Vec<(f32, f32)> = noise(FFT_SIZE * 4).into_iter().map(|s| (s, s)).collect();
+        let response = analyze(&pairs).unwrap();
+        assert!(response.points.len() > FFT_SIZE / 3);
+        assert!(response.points.iter().all(|point| point.phase.abs() < 1.0));
+        assert_eq!(response.delay, 0.0);
+        assert!(!response.inverted);
+        assert_eq!(response.traces().len(), 1);
+    }
+
+    #[test]
+    fn test_polarity_flip() {
+        let pairs: Vec<(f32, f32)> = noise(FFT_SIZE * 4).into_iter().map(|s| (s, -s)).collect();
+        let response = analyze(&pairs).unwrap();
+        assert!(response
+            .points
+            .iter()
+            .all(|point| point.phase.abs() > 179.0));
+        assert_eq!(response.delay, 0.0);
+        assert!(response.inverted);
+    }
+
+    #[test]
+    fn test_delay_is_found() {
+        let delay = 12;
+        let samples = noise(FFT_SIZE * 4 + delay);
+        let pairs: Vec<(f32, f32)> = (delay..samples.len())
+            .map(|i| (samples[i], samples[i - delay]))
+            .collect();
+        let response = analyze(&pairs).unwrap();
+        assert!((response.delay - delay as f32 / 48000.0).abs() < 1e-7);
+        assert!(!response.inverted);
+
+        // Phase falls by 360° per 4 kHz and so wraps at every multiple
+        let near_1k = response
+            .points
+            .iter()
+            .find(|point| point.frequency >= 1000.0)
+            .unwrap();
+        let expected = -360.0 * near_1k.frequency * delay as f32 / 48000.0;
+        assert!((near_1k.phase - expected).abs() < 5.0, "{}", near_1k.phase);
+        assert!(response.traces().len() >= 5);
+    }
+
+    #[test]
+    fn test_unrelated_channels_and_silence_have_no_response() {
+        let left = noise(FFT_SIZE * 16);
+        let right = noise(FFT_SIZE * 17);
+        let pairs: Vec<(f32, f32)> = left
+            .iter()
+            .zip(&right[FFT_SIZE..])
+            .map(|(&l, &r)| (l, r))
+            .collect();
+        let response = analyze(&pairs);
+        assert!(response.is_none_or(|response| response.points.len() < FFT_SIZE / 20));
+
+        assert!(analyze(&[(0.0, 0.0); FFT_SIZE * 2]).is_none());
+        assert!(analyze(&[(0.5, 0.5); 16]).is_none());
+    }
+
+    #[test]
+    fn test_log_frequency_axis() {
+        let response = PhaseResponse {
+            points: Vec::new(),
+            delay: 0.0,
+            inverted: false,
+            sample_rate: 48000,
+        };
+        assert_eq!(response.position(LOW_FREQUENCY), 0.0);
+        assert!((response.position(24000.0) - 1.0).abs() < 1e-6);
+        assert_eq!(PhaseResponse::level(0.0), 0.5);
+    }
+}
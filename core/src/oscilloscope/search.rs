@@ -0,0 +1,188 @@
+use std::ops::Range;
+
+/// Narrowest and widest pulse width a search can ask for, in seconds
+pub const PULSE_WIDTH_RANGE: (f32, f32) = (0.00001, 1.0);
+
+/// What to look for in a capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchKind {
+    RisingEdge,
+    FallingEdge,
+    /// Positive pulses above the level that are narrower than the width
+    NarrowPulse,
+    /// Runs of samples whose magnitude is above the level
+    AboveLevel,
+}
+
+impl SearchKind {
+    pub fn next(self) -> Self {
+        match self {
+            SearchKind::RisingEdge => SearchKind::FallingEdge,
+            SearchKind::FallingEdge => SearchKind::NarrowPulse,
+            SearchKind::NarrowPulse => SearchKind::AboveLevel,
+            SearchKind::AboveLevel => SearchKind::RisingEdge,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchKind::RisingEdge => "Rising edge",
+            SearchKind::FallingEdge => "Falling edge",
+            SearchKind::NarrowPulse => "Pulse <",
+            SearchKind::AboveLevel => "|Level| >",
+        }
+    }
+
+    /// Whether the pulse width matters to the search
+    pub fn uses_width(self) -> bool {
+        self == SearchKind::NarrowPulse
+    }
+}
+
+/// A search over a whole capture, such as a loaded recording, for every
+/// event of one kind
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EventSearch {
+    pub kind: SearchKind,
+    /// Threshold the signal crosses or exceeds
+    pub level: f32,
+    /// Widest pulse a `NarrowPulse` search reports, in seconds
+    pub max_width: f32,
+}
+
+impl Default for EventSearch {
+    fn default() -> Self {
+        Self {
+            kind: SearchKind::RisingEdge,
+            level: 0.0,
+            max_width: 0.001,
+        }
+    }
+}
+
+impl EventSearch {
+    /// Every hit in `samples`, in order, as sample ranges. An edge is the
+    /// one sample range where the signal crosses the level.
+    pub fn find(&self, samples: &[f32], sample_rate: u32) -> Vec<Range<usize>> {
+        match self.kind {
+            SearchKind::RisingEdge => self.crossings(samples, true),
+            SearchKind::FallingEdge => self.crossings(samples, false),
+            SearchKind::NarrowPulse => {
+                let max_samples = self.max_width * sample_rate as f32;
+                runs(samples, |sample| sample > self.level)
+                    // A pulse still high at either end of the capture has
+                    // no known width
+                    .filter(|run| run.start > 0 && run.end < samples.len())
+                    .filter(|run| (run.len() as f32) < max_samples)
+                    .collect()
+            }
+            SearchKind::AboveLevel => runs(samples, |sample| sample.abs() > self.level).collect(),
+        }
+    }
+
+    fn crossings(&self, samples: &[f32], rising: bool) -> Vec<Range<usize>> {
+        samples
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| {
+                let (before, after) = (pair[0] - self.level, pair[1] - self.level);
+                if rising {
+                    before < 0.0 && after >= 0.0
+                } else {
+                    before >= 0.0 && after < 0.0
+                }
+            })
+            .map(|(index, _)| index + 1..index + 2)
+            .collect()
+    }
+}
+
+/// Ranges of consecutive samples for which `inside` holds
+fn runs<'a>(
+    samples: &'a [f32],
+    inside: impl Fn(f32) -> bool + 'a,
+) -> impl Iterator<Item = Range<usize>> + 'a {
+    let mut index = 0;
+    std::iter::from_fn(move || {
+        let start = index + samples[index..].iter().position(|&s| inside(s))?;
+        let end = samples[start..]
+            .iter()
+            .position(|&s| !inside(s))
+            .map_or(samples.len(), |length| start + length);
+        index = end;
+        Some(start..end)
+    })
+}
+
+/// Index of the first hit starting after `position`, wrapping round to the
+/// first hit
+pub fn next_hit(hits: &[Range<usize>], position: usize) -> Option<usize> {
+    if hits.is_empty() {
+        return None;
+    }
+    Some(
+        hits.iter()
+            .position(|hit| hit.start > position)
+            .unwrap_or(0),
+    )
+}
+
+/// Index of the last hit starting before `position`, wrapping round to the
+/// last hit
+pub fn previous_hit(hits: &[Range<usize>], position: usize) -> Option<usize> {
+    if hits.is_empty() {
+        return None;
+    }
+    Some(
+        hits.iter()
+            .rposition(|hit| hit.start < position)
+            .unwrap_or(hits.len() - 1),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edges_and_levels() {
+        let samples = [0.0, 1.0, 1.0, 0.0, -1.0, 0.0, 1.0];
+        let mut search = EventSearch {
+            level: 0.5,
+            ..EventSearch::default()
+        };
+        assert_eq!(search.find(&samples, 1000), vec![1..2, 6..7]);
+
+        search.kind = SearchKind::FallingEdge;
+        assert_eq!(search.find(&samples, 1000), vec![3..4]);
+
+        // Negative peaks count as well as positive ones
+        search.kind = SearchKind::AboveLevel;
+        assert_eq!(search.find(&samples, 1000), vec![1..3, 4..5, 6..7]);
+    }
+
+    #[test]
+    fn test_narrow_pulses() {
+        // Pulses of 2, 5 and 1 samples, the last still high at the end
+        let mut samples = vec![0.0; 20];
+        samples[2..4].fill(1.0);
+        samples[8..13].fill(1.0);
+        samples[19] = 1.0;
+        let search = EventSearch {
+            kind: SearchKind::NarrowPulse,
+            level: 0.5,
+            max_width: 0.003,
+        };
+        assert_eq!(search.find(&samples, 1000), vec![2..4]);
+    }
+
+    #[test]
+    fn test_stepping_between_hits() {
+        let hits = [10..11, 20..21, 30..31];
+        assert_eq!(next_hit(&hits, 10), Some(1));
+        assert_eq!(next_hit(&hits, 30), Some(0));
+        assert_eq!(previous_hit(&hits, 20), Some(0));
+        assert_eq!(previous_hit(&hits, 5), Some(2));
+        assert_eq!(next_hit(&[], 0), None);
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+
+use crate::display::plot::{spectrogram_image, PlotImage, SPECTROGRAM_HEIGHT};
+use crate::display::Palette;
+use crate::oscilloscope::SpectrumScale;
+
+/// Most columns kept, the oldest dropped first. Shrunk to
+/// `SPECTROGRAM_HEIGHT` rows, that's 4 MB at most.
+pub const MAX_SPECTROGRAM_COLUMNS: usize = 2048;
+
+/// The spectra taken so far, oldest first, for exporting the whole history
+/// rather than what's on screen. Each column is kept at a row per pixel of
+/// the exported image; a change of scale, rate or transform size starts it
+/// afresh, as the old columns no longer line up.
+#[derive(Debug, Clone, Default)]
+pub struct SpectrogramHistory {
+    /// Samples in since the previous column, and the column, lowest
+    /// frequency first
+    columns: VecDeque<(usize, Vec<f32>)>,
+    /// Samples in since the newest column
+    since_column: usize,
+    /// Scale, rate and bins the columns were analyzed with
+    format: Option<(SpectrumScale, u32, usize)>,
+}
+
+impl SpectrogramHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count `new_samples` more of the input, and add `spectrum` if they
+    /// completed one
+    pub fn push(
+        &mut self,
+        new_samples: usize,
+        spectrum: Option<&[f32]>,
+        scale: SpectrumScale,
+        sample_rate: u32,
+    ) {
+        self.since_column += new_samples;
+        let Some(spectrum) = spectrum.filter(|spectrum| !spectrum.is_empty()) else {
+            return;
+        };
+        let format = Some((scale, sample_rate, spectrum.len()));
+        if self.format != format {
+            self.columns.clear();
+            self.format = format;
+        }
+        if self.columns.len() == MAX_SPECTROGRAM_COLUMNS {
+            self.columns.pop_front();
+        }
+        self.columns.push_back((
+            self.since_column,
+            shrink(spectrum, SPECTROGRAM_HEIGHT as usize),
+        ));
+        self.since_column = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.columns.clear();
+        self.since_column = 0;
+        self.format = None;
+    }
+
+    /// Average time between columns. The first column's wait is left out,
+    /// as it includes however long the input ran before the history began.
+    pub fn column_seconds(&self) -> f32 {
+        let Some((_, sample_rate, _)) = self.format else {
+            return 0.0;
+        };
+        let samples: usize = if self.columns.len() > 1 {
+            let later = self.columns.iter().skip(1).map(|&(samples, _)| samples);
+            later.sum::<usize>() / (self.columns.len() - 1)
+        } else {
+            self.columns.front().map_or(0, |&(samples, _)| samples)
+        };
+        samples as f32 / sample_rate.max(1) as f32
+    }
+
+    /// The whole history with its frequency and time axes labeled
+    pub fn image(&self, palette: &Palette) -> Result<PlotImage, String> {
+        let Some((scale, sample_rate, bins)) = self.format.filter(|_| !self.is_empty()) else {
+            return Err("No spectrogram to export yet".to_string());
+        };
+        Ok(spectrogram_image(
+            self.columns.iter().map(|(_, column)| column.as_slice()),
+            bins,
+            self.column_seconds(),
+            scale,
+            sample_rate,
+            palette,
+        ))
+    }
+}
+
+/// `spectrum` as `rows` bands, each the loudest of the bins it covers, or
+/// the nearest bin when there are fewer bins than rows
+fn shrink(spectrum: &[f32], rows: usize) -> Vec<f32> {
+    let bins = spectrum.len();
+    (0..rows)
+        .map(|row| {
+            let (start, end) = (row * bins / rows, (row + 1) * bins / rows);
+            if start < end {
+                spectrum[start..end]
+                    .iter()
+                    .copied()
+                    .fold(f32::NEG_INFINITY, f32::max)
+            } else {
+                let center = ((row as f32 + 0.5) / rows as f32 * bins as f32) as usize;
+                spectrum[center.min(bins - 1)]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shrink_keeps_peaks() {
+        let mut spectrum = vec![-100.0; 1024];
+        spectrum[3] = -6.0;
+        let rows = shrink(&spectrum, 512);
+        assert_eq!(rows.len(), 512);
+        assert_eq!(rows[1], -6.0);
+        assert_eq!(rows[0], -100.0);
+
+        // Fewer bins than rows repeat
+        assert_eq!(shrink(&[1.0, 2.0], 4), [1.0, 1.0, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn test_history_times_its_columns() {
+        let mut history = SpectrogramHistory::new();
+        assert!(history.image(&Palette::default()).is_err());
+
+        // A long wait before the first column doesn't count
+        history.push(48000, Some(&[-20.0; 64]), SpectrumScale::Mel, 48000);
+        for _ in 0..9 {
+            history.push(240, None, SpectrumScale::Mel, 48000);
+            history.push(240, Some(&[-20.0; 64]), SpectrumScale::Mel, 48000);
+        }
+        assert_eq!(history.len(), 10);
+        assert_eq!(history.column_seconds(), 0.01);
+        let image = history.image(&Palette::default()).unwrap();
+        assert!(image.width() > 10);
+
+        // Another scale starts over
+        history.push(480, Some(&[-20.0; 64]), SpectrumScale::Linear, 48000);
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    fn test_history_drops_its_oldest() {
+        let mut history = SpectrogramHistory::new();
+        for _ in 0..MAX_SPECTROGRAM_COLUMNS + 5 {
+            history.push(100, Some(&[0.0; 8]), SpectrumScale::Linear, 1000);
+        }
+        assert_eq!(history.len(), MAX_SPECTROGRAM_COLUMNS);
+        assert_eq!(history.column_seconds(), 0.1);
+    }
+}
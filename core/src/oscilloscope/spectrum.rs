@@ -0,0 +1,799 @@
+use std::sync::Arc;
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+
+use crate::oscilloscope::constant_q::{ConstantQ, BINS_PER_OCTAVE};
+use crate::oscilloscope::mel::{
+    hz_to_mel, mel_to_hz, MelFilterbank, DEFAULT_MEL_BANDS, MEL_BANDS_RANGE,
+};
+use crate::parallel;
+
+/// Largest FFT taken from the capture buffer
+const MAX_FFT_SIZE: usize = 4096;
+
+/// Shortest FFT; short gated regions are zero-padded up to it
+const MIN_FFT_SIZE: usize = 64;
+
+/// Floor of the magnitude spectrum in dB
+pub const DB_MIN: f32 = -80.0;
+pub const DB_MAX: f32 = 0.0;
+
+/// How the spectrum's frequency axis is divided
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpectrumScale {
+    /// FFT bins, evenly spaced from 0 Hz to the Nyquist frequency
+    #[default]
+    Linear,
+    /// Constant-Q bins, one per semitone from C1, evenly spaced in pitch
+    ConstantQ,
+    /// FFT power summed into mel bands, as used for audio ML features
+    Mel,
+}
+
+impl SpectrumScale {
+    pub const ALL: [SpectrumScale; 3] = [
+        SpectrumScale::Linear,
+        SpectrumScale::ConstantQ,
+        SpectrumScale::Mel,
+    ];
+
+    /// Look up a scale by its label (case-insensitive)
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|scale| scale.label().eq_ignore_ascii_case(label))
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            SpectrumScale::Linear => SpectrumScale::ConstantQ,
+            SpectrumScale::ConstantQ => SpectrumScale::Mel,
+            SpectrumScale::Mel => SpectrumScale::Linear,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SpectrumScale::Linear => "Linear",
+            SpectrumScale::ConstantQ => "Constant-Q",
+            SpectrumScale::Mel => "Mel",
+        }
+    }
+
+    /// Centre frequency in Hz of `bin` of a spectrum of `bins` magnitudes
+    pub fn bin_frequency(&self, bin: usize, bins: usize, sample_rate: u32) -> f32 {
+        let nyquist = sample_rate as f32 / 2.0;
+        match self {
+            SpectrumScale::Linear => bin as f32 * nyquist / bins.max(1) as f32,
+            SpectrumScale::ConstantQ => ConstantQ::frequency(bin),
+            SpectrumScale::Mel => {
+                mel_to_hz(hz_to_mel(nyquist) * (bin + 1) as f32 / (bins + 1) as f32)
+            }
+        }
+    }
+
+    /// Frequency axis labels for a spectrum of `bins` magnitudes, as
+    /// (fraction of the width, text): every fifth of the Nyquist frequency,
+    /// every C on the constant-Q scale, or round frequencies on the mel
+    /// scale
+    pub fn frequency_labels(&self, sample_rate: u32, bins: usize) -> Vec<(f32, String)> {
+        match self {
+            SpectrumScale::Linear => {
+                let nyquist_khz = sample_rate as f32 / 2000.0;
+                (0..=5)
+                    .map(|i| {
+                        let fraction = i as f32 / 5.0;
+                        let text = if i == 0 {
+                            "0".to_string()
+                        } else {
+                            format!("{:.0}k", fraction * nyquist_khz)
+                        };
+                        (fraction, text)
+                    })
+                    .collect()
+            }
+            SpectrumScale::ConstantQ => (0..bins.max(1))
+                .step_by(BINS_PER_OCTAVE)
+                .map(|bin| {
+                    let octave = bin / BINS_PER_OCTAVE + 1;
+                    (bin as f32 / bins as f32, format!("C{octave}"))
+                })
+                .collect(),
+            SpectrumScale::Mel => {
+                let nyquist = sample_rate as f32 / 2.0;
+                [0.0, 100.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0, 20000.0]
+                    .into_iter()
+                    .take_while(|&hertz| hertz < nyquist)
+                    .map(|hertz| {
+                        let text = if hertz >= 1000.0 {
+                            format!("{:.0}k", hertz / 1000.0)
+                        } else {
+                            format!("{hertz:.0}")
+                        };
+                        (hz_to_mel(hertz) / hz_to_mel(nyquist), text)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// How much each streaming transform's window overlaps the one before.
+/// More overlap gives the spectrum finer steps in time without shortening
+/// the window, at the cost of more transforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpectrumOverlap {
+    /// Each window starts where the last ended
+    None,
+    Half,
+    #[default]
+    ThreeQuarters,
+    SevenEighths,
+}
+
+impl SpectrumOverlap {
+    pub const ALL: [SpectrumOverlap; 4] = [
+        SpectrumOverlap::None,
+        SpectrumOverlap::Half,
+        SpectrumOverlap::ThreeQuarters,
+        SpectrumOverlap::SevenEighths,
+    ];
+
+    /// Look up an overlap by its label, with or without the `%`
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label.trim_end_matches('%');
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|overlap| overlap.label().trim_end_matches('%') == label)
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            SpectrumOverlap::None => SpectrumOverlap::Half,
+            SpectrumOverlap::Half => SpectrumOverlap::ThreeQuarters,
+            SpectrumOverlap::ThreeQuarters => SpectrumOverlap::SevenEighths,
+            SpectrumOverlap::SevenEighths => SpectrumOverlap::None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SpectrumOverlap::None => "0%",
+            SpectrumOverlap::Half => "50%",
+            SpectrumOverlap::ThreeQuarters => "75%",
+            SpectrumOverlap::SevenEighths => "87.5%",
+        }
+    }
+
+    /// New samples between transforms of `fft_size`
+    pub fn hop_size(&self, fft_size: usize) -> usize {
+        let divisor = match self {
+            SpectrumOverlap::None => 1,
+            SpectrumOverlap::Half => 2,
+            SpectrumOverlap::ThreeQuarters => 4,
+            SpectrumOverlap::SevenEighths => 8,
+        };
+        (fft_size / divisor).max(1)
+    }
+}
+
+/// Magnitude spectrum in dB of a Hann-windowed block of samples, or its
+/// constant-Q transform or mel bands.
+///
+/// Either analyzes whole blocks with `update`, or runs as a short-time
+/// transform over a stream with `push`: the newest `fft_size` samples are
+/// transformed once every `hop_size` new samples, so overlapping input
+/// isn't re-analyzed on every call.
+pub struct SpectrumAnalyzer {
+    planner: FftPlanner<f32>,
+    fft: Option<Arc<dyn Fft<f32>>>,
+    buffer: Vec<Complex<f32>>,
+    magnitudes: Vec<f32>,
+    fft_size: usize,
+    hop_size: usize,
+    /// Keeps `hop_size` in proportion when `fft_size` changes, until a hop
+    /// is set in samples
+    overlap: Option<SpectrumOverlap>,
+    /// The newest `fft_size` samples pushed
+    history: Vec<f32>,
+    /// Samples pushed since the last streaming transform
+    since_hop: usize,
+    scale: SpectrumScale,
+    /// What the constant-Q and mel scales are built for
+    sample_rate: u32,
+    /// Kernels for the constant-Q scale, built when it's first chosen
+    constant_q: Option<ConstantQ>,
+    mel_bands: usize,
+    /// Filters for the mel scale, rebuilt when its settings change
+    mel: Option<MelFilterbank>,
+}
+
+impl Default for SpectrumAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpectrumAnalyzer {
+    pub fn new() -> Self {
+        Self {
+            planner: FftPlanner::new(),
+            fft: None,
+            buffer: Vec::new(),
+            magnitudes: Vec::new(),
+            fft_size: MAX_FFT_SIZE,
+            hop_size: SpectrumOverlap::default().hop_size(MAX_FFT_SIZE),
+            overlap: Some(SpectrumOverlap::default()),
+            history: Vec::new(),
+            since_hop: 0,
+            scale: SpectrumScale::default(),
+            sample_rate: 48000,
+            constant_q: None,
+            mel_bands: DEFAULT_MEL_BANDS,
+            mel: None,
+        }
+    }
+
+    pub fn scale(&self) -> SpectrumScale {
+        self.scale
+    }
+
+    /// Switch the frequency axis; `sample_rate` is what the constant-Q
+    /// kernels and mel filters are built for. Takes effect from the next
+    /// transform.
+    pub fn set_scale(&mut self, scale: SpectrumScale, sample_rate: u32) {
+        self.scale = scale;
+        self.sample_rate = sample_rate;
+        let stale = self
+            .constant_q
+            .as_ref()
+            .is_none_or(|transform| transform.sample_rate() != sample_rate);
+        if scale == SpectrumScale::ConstantQ && stale {
+            self.constant_q = Some(ConstantQ::new(sample_rate));
+        }
+    }
+
+    /// dB magnitudes from the last transform: of the positive-frequency
+    /// bins, of each semitone on the constant-Q scale, or of each band on
+    /// the mel scale
+    pub fn magnitudes(&self) -> &[f32] {
+        &self.magnitudes
+    }
+
+    /// The newest mel spectrogram frame: the energy in dB of each mel
+    /// band, from the last transform. `None` unless the scale is mel.
+    ///
+    /// Streaming with `push`, a new frame is ready each time it returns
+    /// true; the window is `fft_size` and the hop `hop_size`.
+    pub fn get_mel_frame(&self) -> Option<&[f32]> {
+        (self.scale == SpectrumScale::Mel && !self.magnitudes.is_empty())
+            .then_some(self.magnitudes.as_slice())
+    }
+
+    pub fn mel_bands(&self) -> usize {
+        self.mel_bands
+    }
+
+    /// Clamped to `MEL_BANDS_RANGE`
+    pub fn set_mel_bands(&mut self, bands: usize) {
+        self.mel_bands = bands.clamp(MEL_BANDS_RANGE.0, MEL_BANDS_RANGE.1);
+    }
+
+    /// Streaming transform length
+    pub fn fft_size(&self) -> usize {
+        self.fft_size
+    }
+
+    /// Rounded up to a power of two, from 64 to 4096 samples. The hop
+    /// keeps its overlap, if it was set as one.
+    pub fn set_fft_size(&mut self, size: usize) {
+        self.fft_size = size.clamp(MIN_FFT_SIZE, MAX_FFT_SIZE).next_power_of_two();
+        self.hop_size = match self.overlap {
+            Some(overlap) => overlap.hop_size(self.fft_size),
+            None => self.hop_size.min(self.fft_size),
+        };
+    }
+
+    /// New samples between streaming transforms
+    pub fn hop_size(&self) -> usize {
+        self.hop_size
+    }
+
+    /// At least one sample and at most `fft_size`
+    pub fn set_hop_size(&mut self, hop: usize) {
+        self.hop_size = hop.clamp(1, self.fft_size);
+        self.overlap = None;
+    }
+
+    /// `None` when the hop was set in samples
+    pub fn overlap(&self) -> Option<SpectrumOverlap> {
+        self.overlap
+    }
+
+    /// Set the hop as a share of the window, kept as the window changes
+    pub fn set_overlap(&mut self, overlap: SpectrumOverlap) {
+        self.hop_size = overlap.hop_size(self.fft_size);
+        self.overlap = Some(overlap);
+    }
+
+    /// Analyze a whole block, taking up to 4096 samples from its start, or
+    /// on the constant-Q scale as much as the bass needs from its end
+    pub fn update(&mut self, samples: &[f32]) {
+        self.magnitudes.clear();
+        if samples.is_empty() {
+            return;
+        }
+        let constant_q = self.constant_q.as_ref();
+        if let Some(transform) = constant_q.filter(|_| self.scale == SpectrumScale::ConstantQ) {
+            transform.transform(samples, &mut self.magnitudes);
+            return;
+        }
+
+        let fft_size = samples.len().next_power_of_two().min(MAX_FFT_SIZE);
+        self.transform(samples, fft_size, fft_size);
+    }
+
+    /// Analyze `samples` alone, such as the part of a capture between
+    /// time gate cursors: the Hann window spans exactly them, zero-padded
+    /// to a power of two, so a short transient isn't smeared by a window
+    /// cut for a longer block. Regions over 4096 samples are cut to their
+    /// start.
+    pub fn update_gated(&mut self, samples: &[f32]) {
+        if self.scale == SpectrumScale::ConstantQ {
+            self.update(samples);
+            return;
+        }
+        self.magnitudes.clear();
+        if samples.is_empty() {
+            return;
+        }
+        let fft_size = samples
+            .len()
+            .next_power_of_two()
+            .clamp(MIN_FFT_SIZE, MAX_FFT_SIZE);
+        self.transform(samples, fft_size, samples.len().min(fft_size));
+    }
+
+    /// Add streaming input. The spectrum is recomputed, from the newest
+    /// `fft_size` samples (or the constant-Q transform's longest kernel),
+    /// only once a hop's worth has arrived; returns whether it was.
+    pub fn push(&mut self, samples: &[f32]) -> bool {
+        let history_size = self.constant_q().map_or(self.fft_size, ConstantQ::longest);
+        self.history.extend_from_slice(samples);
+        if self.history.len() > history_size {
+            let to_remove = self.history.len() - history_size;
+            self.history.drain(0..to_remove);
+        }
+
+        self.since_hop += samples.len();
+        if self.since_hop < self.hop_size {
+            return false;
+        }
+        // Hops that arrived together share one transform of the newest
+        self.since_hop %= self.hop_size;
+
+        let history = std::mem::take(&mut self.history);
+        self.analyze(&history);
+        self.history = history;
+        true
+    }
+
+    /// The spectrum at every hop across a whole recording, as pushing it a
+    /// hop at a time would give. Long recordings are transformed across
+    /// threads with the `parallel` feature. Leaves this analyzer's own
+    /// stream alone.
+    pub fn spectrogram(&self, samples: &[f32]) -> Vec<Vec<f32>> {
+        let history_size = self.constant_q().map_or(self.fft_size, ConstantQ::longest);
+        let ends: Vec<usize> = (1..=samples.len() / self.hop_size)
+            .map(|hop| hop * self.hop_size)
+            .collect();
+        parallel::map_init(
+            &ends,
+            ends.len() * history_size,
+            || self.with_settings(),
+            |analyzer, &end| {
+                analyzer.analyze(&samples[end.saturating_sub(history_size)..end]);
+                analyzer.magnitudes.clone()
+            },
+        )
+    }
+
+    /// A fresh analyzer set up as this one is
+    fn with_settings(&self) -> Self {
+        let mut analyzer = Self::new();
+        analyzer.set_fft_size(self.fft_size);
+        analyzer.set_hop_size(self.hop_size);
+        analyzer.set_mel_bands(self.mel_bands);
+        analyzer.set_scale(self.scale, self.sample_rate);
+        analyzer
+    }
+
+    /// Transform the newest of `history` as a streaming transform does
+    fn analyze(&mut self, history: &[f32]) {
+        match self
+            .constant_q
+            .as_ref()
+            .filter(|_| self.scale == SpectrumScale::ConstantQ)
+        {
+            Some(transform) => transform.transform(history, &mut self.magnitudes),
+            None => self.transform(history, self.fft_size, self.fft_size),
+        }
+    }
+
+    /// The constant-Q transform, while that scale is chosen
+    fn constant_q(&self) -> Option<&ConstantQ> {
+        self.constant_q
+            .as_ref()
+            .filter(|_| self.scale == SpectrumScale::ConstantQ)
+    }
+
+    /// Transform the first `span` of `samples`, Hann-windowed and
+    /// zero-padded to `fft_size`
+    fn transform(&mut self, samples: &[f32], fft_size: usize, span: usize) {
+        self.magnitudes.clear();
+        let fft = match &self.fft {
+            Some(fft) if fft.len() == fft_size => Arc::clone(fft),
+            _ => {
+                let fft = self.planner.plan_fft_forward(fft_size);
+                self.fft = Some(Arc::clone(&fft));
+                fft
+            }
+        };
+
+        // Hann-windowed, zero-padded input
+        self.buffer.clear();
+        self.buffer
+            .extend(samples.iter().take(span).enumerate().map(|(i, &x)| {
+                let window =
+                    0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / span as f32).cos());
+                Complex::new(x * window, 0.0)
+            }));
+        self.buffer.resize(fft_size, Complex::new(0.0, 0.0));
+
+        fft.process(&mut self.buffer);
+
+        // By the windowed length rather than the padded one, so zero-padding
+        // doesn't lower the band power
+        let scale = (span as f32).sqrt();
+        if self.scale == SpectrumScale::Mel {
+            let stale = self
+                .mel
+                .as_ref()
+                .is_none_or(|mel| !mel.matches(self.mel_bands, fft_size, self.sample_rate));
+            if stale {
+                self.mel = Some(MelFilterbank::new(
+                    self.mel_bands,
+                    fft_size,
+                    self.sample_rate,
+                ));
+            }
+            let power: Vec<f32> = self
+                .buffer
+                .iter()
+                .take(fft_size / 2)
+                .map(|c| c.norm_sqr() / (scale * scale))
+                .collect();
+            if let Some(ref mel) = self.mel {
+                mel.apply(&power, &mut self.magnitudes);
+            }
+            return;
+        }
+
+        self.magnitudes.extend(
+            self.buffer
+                .iter()
+                .take(fft_size / 2)
+                .map(|c| 20.0 * (c.norm() / scale).max(0.00001).log10()),
+        );
+    }
+}
+
+/// Power mean of the spectra added since it was last cleared, steadier
+/// than any one of them for measuring a response to noise
+#[derive(Debug, Clone, Default)]
+pub struct SpectrumAverage {
+    power: Vec<f64>,
+    count: usize,
+}
+
+impl SpectrumAverage {
+    /// Add `magnitudes`, in dB. A spectrum with a different number of bins,
+    /// after a change of scale or size, starts the average again.
+    pub fn add(&mut self, magnitudes: &[f32]) {
+        if magnitudes.len() != self.power.len() {
+            self.power = vec![0.0; magnitudes.len()];
+            self.count = 0;
+        }
+        for (total, &db) in self.power.iter_mut().zip(magnitudes) {
+            *total += 10_f64.powf(db as f64 / 10.0);
+        }
+        self.count += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.power.clear();
+        self.count = 0;
+    }
+
+    /// Spectra in the average
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The average in dB, empty before the first spectrum
+    pub fn magnitudes(&self) -> Vec<f32> {
+        let count = self.count.max(1) as f64;
+        self.power
+            .iter()
+            .map(|&total| ((10.0 * (total / count).log10()) as f32).max(DB_MIN))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sine_peaks_in_its_bin() {
+        let mut analyzer = SpectrumAnalyzer::new();
+        // Exactly 32 cycles across a 1024-point FFT
+        let samples: Vec<f32> = (0..1024)
+            .map(|i| (2.0 * std::f32::consts::PI * 32.0 * i as f32 / 1024.0).sin())
+            .collect();
+        analyzer.update(&samples);
+
+        let magnitudes = analyzer.magnitudes();
+        assert_eq!(magnitudes.len(), 512);
+        let peak = magnitudes
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(bin, _)| bin);
+        assert_eq!(peak, Some(32));
+    }
+
+    #[test]
+    fn test_gated_transform() {
+        use crate::oscilloscope::BandPower;
+
+        // 1 kHz for 1000 samples, inside a block that's otherwise silent
+        let tone: Vec<f32> = (0..1000)
+            .map(|i| (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0).sin())
+            .collect();
+        let mut analyzer = SpectrumAnalyzer::new();
+        analyzer.update_gated(&tone);
+        let magnitudes = analyzer.magnitudes();
+        assert_eq!(magnitudes.len(), 512);
+
+        // Zero-padding doesn't dilute it: a full-scale sine still reads 0 dBFS
+        let band = BandPower::of(magnitudes, 48000, 0.0, 24000.0).unwrap();
+        assert!(band.power.abs() < 0.5, "{}", band.power);
+        let peak = magnitudes
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(bin, _)| bin as f32 * 48000.0 / 1024.0)
+            .unwrap();
+        assert!((peak - 1000.0).abs() < 48000.0 / 1024.0);
+
+        analyzer.update_gated(&[]);
+        assert!(analyzer.magnitudes().is_empty());
+    }
+
+    #[test]
+    fn test_push_transforms_once_per_hop() {
+        let mut analyzer = SpectrumAnalyzer::new();
+        analyzer.set_fft_size(1024);
+        analyzer.set_hop_size(256);
+
+        assert!(!analyzer.push(&[0.5; 200]));
+        assert!(analyzer.magnitudes().is_empty());
+        assert!(analyzer.push(&[0.5; 100]));
+        assert_eq!(analyzer.magnitudes().len(), 512);
+
+        // 44 left over from the first hop, so 212 more complete the next
+        assert!(!analyzer.push(&[0.5; 211]));
+        assert!(analyzer.push(&[0.5; 1]));
+    }
+
+    #[test]
+    fn test_push_analyzes_the_newest_samples() {
+        let tone = |cycles: f32| -> Vec<f32> {
+            (0..1024)
+                .map(|i| (2.0 * std::f32::consts::PI * cycles * i as f32 / 1024.0).sin())
+                .collect()
+        };
+        let mut streaming = SpectrumAnalyzer::new();
+        streaming.set_fft_size(1024);
+        streaming.set_hop_size(1024);
+        streaming.push(&tone(16.0));
+        streaming.push(&tone(64.0));
+
+        let mut block = SpectrumAnalyzer::new();
+        block.update(&tone(64.0));
+        assert_eq!(streaming.magnitudes(), block.magnitudes());
+    }
+
+    #[test]
+    fn test_spectrogram_matches_streaming() {
+        // Long enough to be spread across threads
+        let samples: Vec<f32> = (0..200_000)
+            .map(|i| (i as f32 * 0.01).sin() * (i as f32 * 0.0001).cos())
+            .collect();
+        for scale in [SpectrumScale::Linear, SpectrumScale::Mel] {
+            let mut streaming = SpectrumAnalyzer::new();
+            streaming.set_scale(scale, 48000);
+            streaming.set_hop_size(3000);
+            let frames = streaming.spectrogram(&samples);
+
+            let mut pushed = Vec::new();
+            for chunk in samples.chunks(3000) {
+                if streaming.push(chunk) {
+                    pushed.push(streaming.magnitudes().to_vec());
+                }
+            }
+            assert_eq!(frames.len(), samples.len() / 3000);
+            assert_eq!(frames, pushed);
+        }
+    }
+
+    #[test]
+    fn test_sizes_are_clamped() {
+        let mut analyzer = SpectrumAnalyzer::new();
+        analyzer.set_fft_size(1000);
+        assert_eq!(analyzer.fft_size(), 1024);
+        analyzer.set_hop_size(5000);
+        assert_eq!(analyzer.hop_size(), 1024);
+        analyzer.set_fft_size(100_000);
+        assert_eq!(analyzer.fft_size(), 4096);
+        analyzer.set_hop_size(0);
+        assert_eq!(analyzer.hop_size(), 1);
+    }
+
+    #[test]
+    fn test_average_is_of_power() {
+        let mut average = SpectrumAverage::default();
+        assert!(average.magnitudes().is_empty());
+        average.add(&[0.0, -20.0]);
+        average.add(&[DB_MIN, -20.0]);
+        assert_eq!(average.count(), 2);
+        let magnitudes = average.magnitudes();
+        // Half the power of 0 dB, near enough, with the floor's added
+        assert!((magnitudes[0] + 3.01).abs() < 0.01);
+        assert!((magnitudes[1] + 20.0).abs() < 1e-4);
+
+        // A different size starts over
+        average.add(&[-6.0]);
+        assert_eq!(average.count(), 1);
+        assert_eq!(average.magnitudes(), [-6.0]);
+    }
+
+    #[test]
+    fn test_overlap_follows_the_window() {
+        let mut analyzer = SpectrumAnalyzer::new();
+        assert_eq!(analyzer.overlap(), Some(SpectrumOverlap::ThreeQuarters));
+        assert_eq!(analyzer.hop_size(), 1024);
+
+        analyzer.set_overlap(SpectrumOverlap::SevenEighths);
+        assert_eq!(analyzer.hop_size(), 512);
+        analyzer.set_fft_size(1024);
+        assert_eq!(analyzer.hop_size(), 128);
+
+        // A hop in samples stays put
+        analyzer.set_hop_size(300);
+        assert_eq!(analyzer.overlap(), None);
+        analyzer.set_fft_size(4096);
+        assert_eq!(analyzer.hop_size(), 300);
+
+        assert_eq!(
+            SpectrumOverlap::from_label("50"),
+            Some(SpectrumOverlap::Half)
+        );
+        assert_eq!(
+            SpectrumOverlap::from_label("87.5%"),
+            Some(SpectrumOverlap::SevenEighths)
+        );
+        assert_eq!(SpectrumOverlap::from_label("60%"), None);
+        assert_eq!(SpectrumOverlap::None.hop_size(64), 64);
+    }
+
+    #[test]
+    fn test_constant_q_scale() {
+        let mut analyzer = SpectrumAnalyzer::new();
+        analyzer.set_scale(SpectrumScale::ConstantQ, 48000);
+        let samples: Vec<f32> = (0..4800)
+            .map(|i| (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 48000.0).sin())
+            .collect();
+        analyzer.update(&samples);
+
+        let magnitudes = analyzer.magnitudes();
+        assert_eq!(magnitudes.len(), ConstantQ::bins(48000));
+        let peak = magnitudes
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(bin, _)| bin);
+        // A4
+        assert_eq!(peak, Some(45));
+
+        analyzer.set_scale(SpectrumScale::Linear, 48000);
+        analyzer.update(&samples);
+        assert_eq!(analyzer.magnitudes().len(), 2048);
+    }
+
+    #[test]
+    fn test_mel_frames() {
+        let mut analyzer = SpectrumAnalyzer::new();
+        analyzer.set_scale(SpectrumScale::Mel, 48000);
+        analyzer.set_mel_bands(40);
+        analyzer.set_fft_size(1024);
+        analyzer.set_hop_size(512);
+        assert!(analyzer.get_mel_frame().is_none());
+
+        let samples: Vec<f32> = (0..1024)
+            .map(|i| (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0).sin())
+            .collect();
+        assert!(analyzer.push(&samples));
+        let frame = analyzer.get_mel_frame().unwrap();
+        assert_eq!(frame.len(), 40);
+        assert_eq!(frame, analyzer.magnitudes());
+
+        analyzer.set_scale(SpectrumScale::Linear, 48000);
+        assert!(analyzer.get_mel_frame().is_none());
+    }
+
+    #[test]
+    fn test_frequency_labels() {
+        let linear = SpectrumScale::Linear.frequency_labels(48000, 2048);
+        assert_eq!(linear.len(), 6);
+        assert_eq!(linear[0], (0.0, "0".to_string()));
+        assert_eq!(linear[5], (1.0, "24k".to_string()));
+
+        let constant_q = SpectrumScale::ConstantQ.frequency_labels(48000, 120);
+        let text: Vec<&str> = constant_q.iter().map(|(_, text)| text.as_str()).collect();
+        assert_eq!(
+            text,
+            ["C1", "C2", "C3", "C4", "C5", "C6", "C7", "C8", "C9", "C10"]
+        );
+        assert_eq!(constant_q[1].0, 0.1);
+
+        let mel = SpectrumScale::Mel.frequency_labels(16000, 64);
+        let text: Vec<&str> = mel.iter().map(|(_, text)| text.as_str()).collect();
+        assert_eq!(text, ["0", "100", "500", "1k", "2k", "5k"]);
+        assert!(mel.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn test_bin_frequencies() {
+        assert_eq!(SpectrumScale::Linear.bin_frequency(0, 2048, 48000), 0.0);
+        assert_eq!(
+            SpectrumScale::Linear.bin_frequency(1024, 2048, 48000),
+            12000.0
+        );
+        // C1, and an octave up twelve semitones later
+        let c1 = SpectrumScale::ConstantQ.bin_frequency(0, 120, 48000);
+        assert!((c1 - 32.7).abs() < 0.1);
+        assert!((SpectrumScale::ConstantQ.bin_frequency(12, 120, 48000) - 2.0 * c1).abs() < 0.01);
+
+        let mel: Vec<f32> = (0..64)
+            .map(|band| SpectrumScale::Mel.bin_frequency(band, 64, 16000))
+            .collect();
+        assert!(mel.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(mel[0] > 0.0 && mel[63] < 8000.0);
+    }
+
+    #[test]
+    fn test_empty_input_clears_the_spectrum() {
+        let mut analyzer = SpectrumAnalyzer::new();
+        analyzer.update(&[0.5; 64]);
+        assert!(!analyzer.magnitudes().is_empty());
+        analyzer.update(&[]);
+        assert!(analyzer.magnitudes().is_empty());
+    }
+}
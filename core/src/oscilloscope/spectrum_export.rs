@@ -0,0 +1,109 @@
+//! Spectra written out as CSV, with what they were measured with, so a
+//! response can be compared in other tools. REW and spreadsheets alike skip
+//! the `#` lines and read the `frequency_hz,level_db` columns.
+
+use std::fmt::Write;
+
+use crate::oscilloscope::octave_smoothing::OctaveSmoothing;
+use crate::oscilloscope::spectrum::SpectrumScale;
+
+/// How an exported spectrum was taken
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpectrumMetadata {
+    pub sample_rate: u32,
+    pub scale: SpectrumScale,
+    /// Transform length, where the scale has one: the linear scale always,
+    /// the mel scale when known
+    pub fft_size: Option<usize>,
+    /// Spectra power-averaged into it, 1 for a single spectrum
+    pub averages: usize,
+    pub smoothing: OctaveSmoothing,
+    /// Added to the levels to read in dB SPL, `None` for dBFS
+    pub level_offset: Option<f32>,
+}
+
+impl SpectrumMetadata {
+    /// A single spectrum on `scale`, unsmoothed, in dBFS
+    pub fn new(scale: SpectrumScale, sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            scale,
+            fft_size: None,
+            averages: 1,
+            smoothing: OctaveSmoothing::Off,
+            level_offset: None,
+        }
+    }
+
+    /// `magnitudes`, a spectrum's dB levels, as CSV behind these details
+    pub fn to_csv(&self, magnitudes: &[f32]) -> String {
+        let mut text = "# OzeeCubed spectrum\n".to_string();
+        let _ = writeln!(text, "# sample_rate_hz = {}", self.sample_rate);
+        let _ = writeln!(text, "# scale = {}", self.scale.label());
+        if let Some(size) = self.fft_size {
+            let _ = writeln!(text, "# fft_size = {size}");
+        }
+        text.push_str("# window = Hann\n");
+        if self.averages > 1 {
+            let _ = writeln!(text, "# averaging = {} spectra, power mean", self.averages);
+        } else {
+            text.push_str("# averaging = none\n");
+        }
+        let _ = writeln!(text, "# smoothing = {}", self.smoothing.label());
+        let units = if self.level_offset.is_some() {
+            "dB SPL"
+        } else {
+            "dBFS"
+        };
+        let _ = writeln!(text, "# levels = {units}");
+
+        let offset = self.level_offset.unwrap_or(0.0);
+        text.push_str("frequency_hz,level_db\n");
+        for (bin, db) in magnitudes.iter().enumerate() {
+            let frequency = self
+                .scale
+                .bin_frequency(bin, magnitudes.len(), self.sample_rate);
+            let _ = writeln!(text, "{frequency:.2},{:.2}", db + offset);
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_carries_its_metadata() {
+        let metadata = SpectrumMetadata {
+            fft_size: Some(8),
+            averages: 20,
+            smoothing: OctaveSmoothing::Third,
+            level_offset: Some(94.0),
+            ..SpectrumMetadata::new(SpectrumScale::Linear, 48000)
+        };
+        let csv = metadata.to_csv(&[-80.0, -6.0, -12.5, -40.0]);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines.contains(&"# fft_size = 8"));
+        assert!(lines.contains(&"# averaging = 20 spectra, power mean"));
+        assert!(lines.contains(&"# smoothing = 1/3 octave"));
+        assert!(lines.contains(&"# levels = dB SPL"));
+        let data = lines
+            .iter()
+            .position(|&line| line == "frequency_hz,level_db");
+        assert_eq!(
+            &lines[data.unwrap() + 1..],
+            [
+                "0.00,14.00",
+                "6000.00,88.00",
+                "12000.00,81.50",
+                "18000.00,54.00"
+            ]
+        );
+
+        let single = SpectrumMetadata::new(SpectrumScale::Mel, 48000).to_csv(&[]);
+        assert!(single.contains("# averaging = none\n"));
+        assert!(single.contains("# levels = dBFS\n"));
+        assert!(!single.contains("fft_size"));
+    }
+}
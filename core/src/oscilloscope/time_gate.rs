@@ -0,0 +1,108 @@
+use std::ops::Range;
+
+/// One of the two time cursors bounding the gate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateCursor {
+    Start,
+    End,
+}
+
+/// Two time cursors on the waveform display, as fractions of the screen
+/// width, bounding the part of the capture the spectrum is taken from.
+/// Isolates a transient (a drum hit, a click) from the rest of the
+/// rolling buffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeGate {
+    pub start: f32,
+    pub end: f32,
+}
+
+impl Default for TimeGate {
+    /// The middle fifth of the screen
+    fn default() -> Self {
+        Self {
+            start: 0.4,
+            end: 0.6,
+        }
+    }
+}
+
+impl TimeGate {
+    /// The cursor closer to `x`, the one a click there picks up
+    pub fn nearest(&self, x: f32) -> GateCursor {
+        if (x - self.start).abs() <= (x - self.end).abs() {
+            GateCursor::Start
+        } else {
+            GateCursor::End
+        }
+    }
+
+    /// Move `cursor` to `x`, kept on screen. Dragging one cursor past the
+    /// other pushes the other along with it.
+    pub fn set(&mut self, cursor: GateCursor, x: f32) {
+        let x = x.clamp(0.0, 1.0);
+        match cursor {
+            GateCursor::Start => {
+                self.start = x;
+                self.end = self.end.max(x);
+            }
+            GateCursor::End => {
+                self.end = x;
+                self.start = self.start.min(x);
+            }
+        }
+    }
+
+    /// The samples between the cursors, `range` being the part of
+    /// `samples` on screen
+    pub fn samples<'a>(&self, samples: &'a [f32], range: Range<usize>) -> &'a [f32] {
+        let length = range.len() as f32;
+        let first = range.start + (self.start * length).floor() as usize;
+        let last = range.start + (self.end * length).ceil() as usize;
+        let last = last.min(samples.len());
+        &samples[first.min(last)..last]
+    }
+
+    /// Length of the gate in seconds, for `samples_per_screen` at
+    /// `sample_rate`
+    pub fn duration(&self, samples_per_screen: usize, sample_rate: u32) -> f32 {
+        (self.end - self.start) * samples_per_screen as f32 / sample_rate.max(1) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursors() {
+        let mut gate = TimeGate::default();
+        assert_eq!(gate.nearest(0.1), GateCursor::Start);
+        assert_eq!(gate.nearest(0.9), GateCursor::End);
+
+        gate.set(GateCursor::Start, 0.8);
+        assert_eq!((gate.start, gate.end), (0.8, 0.8));
+        gate.set(GateCursor::End, -1.0);
+        assert_eq!((gate.start, gate.end), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_gated_samples() {
+        let samples: Vec<f32> = (0..300).map(|i| i as f32).collect();
+        let gate = TimeGate {
+            start: 0.25,
+            end: 0.5,
+        };
+        // The screen shows samples 100 to 299
+        let gated = gate.samples(&samples, 100..300);
+        assert_eq!(gated.len(), 50);
+        assert_eq!(gated[0], 150.0);
+        assert_eq!(gated[49], 199.0);
+
+        // A short buffer gives what it has rather than panicking
+        assert!(gate.samples(&samples[..120], 100..300).is_empty());
+        assert_eq!(gate.samples(&samples[..160], 100..300).len(), 10);
+
+        assert!((gate.duration(48000, 48000) - 0.25).abs() < 1e-6);
+    }
+}
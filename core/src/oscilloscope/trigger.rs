@@ -1,14 +1,62 @@
+/// Trigger level change per key press, and with Shift held
+pub const LEVEL_STEP: f32 = 0.1;
+pub const FINE_LEVEL_STEP: f32 = 0.01;
+
+/// How long auto triggering waits for an edge before free-running a sweep,
+/// in seconds
+pub const AUTO_TIMEOUT: f32 = 0.1;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TriggerEdge {
     Rising,
     Falling,
 }
 
+/// What the trigger shows while no edge comes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TriggerMode {
+    /// Free-runs a sweep each `AUTO_TIMEOUT`, so there's always a trace
+    #[default]
+    Auto,
+    /// Holds the last sweep until an edge comes, however long that takes
+    Normal,
+}
+
+impl TriggerMode {
+    pub const ALL: [TriggerMode; 2] = [TriggerMode::Auto, TriggerMode::Normal];
+
+    /// Look up a mode by its label (case-insensitive)
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.label().eq_ignore_ascii_case(label))
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            TriggerMode::Auto => TriggerMode::Normal,
+            TriggerMode::Normal => TriggerMode::Auto,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TriggerMode::Auto => "Auto",
+            TriggerMode::Normal => "Normal",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TriggerSettings {
     pub enabled: bool,
     pub edge: TriggerEdge,
     pub level: f32, // Voltage level for trigger
+    pub mode: TriggerMode,
 }
 
 impl Default for TriggerSettings {
@@ -17,6 +65,7 @@ impl Default for TriggerSettings {
             enabled: true,
             edge: TriggerEdge::Rising,
             level: 0.0,
+            mode: TriggerMode::Auto,
         }
     }
 }
@@ -30,6 +79,13 @@ impl TriggerSettings {
         self.level = level.clamp(-10.0, 10.0);
     }
 
+    /// One `FINE_LEVEL_STEP` toward `level`, for a slider dragged with
+    /// Shift held
+    pub fn step_level_toward(&mut self, level: f32) {
+        let step = (level - self.level).clamp(-FINE_LEVEL_STEP, FINE_LEVEL_STEP);
+        self.set_level(self.level + step);
+    }
+
     pub fn toggle_edge(&mut self) {
         self.edge = match self.edge {
             TriggerEdge::Rising => TriggerEdge::Falling,
@@ -38,6 +94,57 @@ impl TriggerSettings {
     }
 }
 
+/// What the trigger shows for a capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoSweep {
+    /// The capture, lined up on its edge
+    Triggered,
+    /// The last sweep shown, while waiting for an edge
+    Waiting,
+    /// The newest samples, untriggered, once the timeout passed without an
+    /// edge
+    FreeRun,
+}
+
+/// Times the wait for an edge, so in `TriggerMode::Auto` a signal that
+/// can't be triggered on updates once a timeout rather than jumping between
+/// wherever each capture happens to start. Counted in samples, so it follows
+/// the input rather than the wall clock.
+#[derive(Debug, Clone)]
+pub struct AutoTrigger {
+    timeout: u64,
+    /// Samples in since the last sweep shown
+    waited: u64,
+}
+
+impl AutoTrigger {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            timeout: ((AUTO_TIMEOUT * sample_rate as f32) as u64).max(1),
+            waited: 0,
+        }
+    }
+
+    /// The sweep for a capture `new_samples` on from the last, which
+    /// `triggered` if an edge was found in it. `TriggerMode::Normal` never
+    /// stops waiting.
+    pub fn sweep(&mut self, mode: TriggerMode, triggered: bool, new_samples: usize) -> AutoSweep {
+        if triggered {
+            self.waited = 0;
+            return AutoSweep::Triggered;
+        }
+        self.waited += new_samples as u64;
+        if mode == TriggerMode::Normal {
+            AutoSweep::Waiting
+        } else if self.waited >= self.timeout {
+            self.waited = 0;
+            AutoSweep::FreeRun
+        } else {
+            AutoSweep::Waiting
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -48,6 +155,7 @@ mod tests {
         assert!(settings.enabled);
         assert_eq!(settings.edge, TriggerEdge::Rising);
         assert_eq!(settings.level, 0.0);
+        assert_eq!(settings.mode, TriggerMode::Auto);
     }
 
     #[test]
@@ -90,6 +198,17 @@ mod tests {
         assert_eq!(settings.level, 7.5);
     }
 
+    #[test]
+    fn test_step_level_toward() {
+        let mut settings = TriggerSettings::default();
+        settings.step_level_toward(5.0);
+        assert_eq!(settings.level, FINE_LEVEL_STEP);
+
+        // Stops on a target closer than a step
+        settings.step_level_toward(0.005);
+        assert_eq!(settings.level, 0.005);
+    }
+
     #[test]
     fn test_toggle_edge() {
         let mut settings = TriggerSettings::default();
@@ -102,10 +221,79 @@ mod tests {
         assert_eq!(settings.edge, TriggerEdge::Rising);
     }
 
+    #[test]
+    fn test_auto_waits_out_the_timeout_before_free_running() {
+        // 100 samples at 1 kHz
+        let mut auto = AutoTrigger::new(1000);
+        assert_eq!(
+            auto.sweep(TriggerMode::Auto, true, 40),
+            AutoSweep::Triggered
+        );
+        assert_eq!(auto.sweep(TriggerMode::Auto, false, 40), AutoSweep::Waiting);
+        assert_eq!(auto.sweep(TriggerMode::Auto, false, 40), AutoSweep::Waiting);
+        assert_eq!(auto.sweep(TriggerMode::Auto, false, 40), AutoSweep::FreeRun);
+        // One sweep, then waiting again
+        assert_eq!(auto.sweep(TriggerMode::Auto, false, 40), AutoSweep::Waiting);
+
+        // An edge restarts the wait
+        assert_eq!(
+            auto.sweep(TriggerMode::Auto, true, 40),
+            AutoSweep::Triggered
+        );
+        assert_eq!(auto.sweep(TriggerMode::Auto, false, 90), AutoSweep::Waiting);
+    }
+
+    #[test]
+    fn test_normal_mode_waits_for_an_edge() {
+        let mut auto = AutoTrigger::new(1000);
+        for _ in 0..10 {
+            assert_eq!(
+                auto.sweep(TriggerMode::Normal, false, 40),
+                AutoSweep::Waiting
+            );
+        }
+        assert_eq!(
+            auto.sweep(TriggerMode::Normal, true, 40),
+            AutoSweep::Triggered
+        );
+
+        assert_eq!(TriggerMode::from_label("normal"), Some(TriggerMode::Normal));
+        assert_eq!(TriggerMode::Auto.next(), TriggerMode::Normal);
+        assert_eq!(TriggerMode::Normal.next(), TriggerMode::Auto);
+    }
+
     #[test]
     fn test_trigger_edge_equality() {
         assert_eq!(TriggerEdge::Rising, TriggerEdge::Rising);
         assert_eq!(TriggerEdge::Falling, TriggerEdge::Falling);
         assert_ne!(TriggerEdge::Rising, TriggerEdge::Falling);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_settings_enums_go_by_their_variant_names() {
+        use crate::display::{LayoutMode, PersistenceMode};
+        use serde::de::value::{Error, StrDeserializer};
+        use serde::de::IntoDeserializer;
+        use serde::Deserialize;
+
+        let name = |text: &'static str| -> StrDeserializer<Error> { text.into_deserializer() };
+        assert_eq!(
+            TriggerEdge::deserialize(name("Falling")),
+            Ok(TriggerEdge::Falling)
+        );
+        assert_eq!(
+            LayoutMode::deserialize(name("SideBySide")),
+            Ok(LayoutMode::SideBySide)
+        );
+        assert_eq!(
+            PersistenceMode::deserialize(name("Graded")),
+            Ok(PersistenceMode::Graded)
+        );
+        assert_eq!(
+            TriggerMode::deserialize(name("Normal")),
+            Ok(TriggerMode::Normal)
+        );
+        assert!(TriggerEdge::deserialize(name("Sideways")).is_err());
+    }
 }
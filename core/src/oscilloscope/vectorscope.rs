@@ -0,0 +1,266 @@
+use std::collections::VecDeque;
+
+/// Frames of points kept and drawn fading out, the newest brightest
+pub const DEFAULT_PERSISTENCE: usize = 8;
+
+/// Most frames of persistence
+pub const MAX_PERSISTENCE: usize = 60;
+
+/// Most points kept from one frame; longer inputs are thinned evenly
+const MAX_POINTS: usize = 4096;
+
+/// Distance from the centre the loudest point is scaled to, as a fraction
+/// of the radius
+const TARGET_LEVEL: f32 = 0.9;
+
+/// Smallest and largest auto-gain
+const GAIN_RANGE: (f32, f32) = (1.0, 1000.0);
+
+/// Fraction of the way the gain moves to its target each frame: quickly
+/// down when the signal gets louder, slowly back up when it gets quieter
+const GAIN_ATTACK: f32 = 0.5;
+const GAIN_RELEASE: f32 = 0.05;
+
+/// Mid and side levels below this RMS count as silence
+const SILENCE: f32 = 1e-6;
+
+/// A stereo sample pair rotated 45° for the vectorscope: mid (L + R) up
+/// and side (R - L) across, so a mono signal is a vertical line, the left
+/// channel alone leans left and out-of-phase channels lie flat
+pub fn rotate(left: f32, right: f32) -> (f32, f32) {
+    let scale = std::f32::consts::FRAC_1_SQRT_2;
+    ((right - left) * scale, (left + right) * scale)
+}
+
+/// How much of a stereo signal is common to both channels
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StereoBalance {
+    /// RMS of the mid signal, (L + R) / √2
+    pub mid: f32,
+    /// RMS of the side signal, (R - L) / √2
+    pub side: f32,
+    /// Phase correlation from +1 (mono) through 0 (unrelated channels) to
+    /// -1 (one channel inverted). 0 in silence.
+    pub correlation: f32,
+}
+
+impl StereoBalance {
+    pub fn of(pairs: &[(f32, f32)]) -> Self {
+        let (mut left, mut right, mut product, mut mid, mut side) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for &(l, r) in pairs {
+            let (x, y) = rotate(l, r);
+            left += l * l;
+            right += r * r;
+            product += l * r;
+            mid += y * y;
+            side += x * x;
+        }
+        let count = pairs.len().max(1) as f32;
+        let norm = (left * right).sqrt();
+        Self {
+            mid: (mid / count).sqrt(),
+            side: (side / count).sqrt(),
+            correlation: if norm > 0.0 { product / norm } else { 0.0 },
+        }
+    }
+
+    /// Side level relative to mid in dB: very negative for near-mono, 0
+    /// for unrelated channels, positive when out of phase. `None` in
+    /// silence.
+    pub fn side_to_mid(&self) -> Option<f32> {
+        (self.mid.max(self.side) > SILENCE)
+            .then(|| 20.0 * (self.side.max(SILENCE) / self.mid.max(SILENCE)).log10())
+    }
+}
+
+/// Audio vectorscope (goniometer): left against right rotated 45°, with
+/// fading persistence, auto-gain so quiet material still fills the display,
+/// and the mid/side balance of the newest frame
+#[derive(Debug, Clone)]
+pub struct Vectorscope {
+    /// Rotated points of each frame, oldest first, after gain: -1 to 1
+    /// across and up
+    history: VecDeque<Vec<(f32, f32)>>,
+    persistence: usize,
+    pub auto_gain: bool,
+    gain: f32,
+    balance: StereoBalance,
+}
+
+impl Default for Vectorscope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vectorscope {
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::new(),
+            persistence: DEFAULT_PERSISTENCE,
+            auto_gain: true,
+            gain: GAIN_RANGE.0,
+            balance: StereoBalance::default(),
+        }
+    }
+
+    /// Add a frame of (left, right) pairs, the newest input since the last
+    pub fn update(&mut self, pairs: &[(f32, f32)]) {
+        if pairs.is_empty() {
+            return;
+        }
+        self.balance = StereoBalance::of(pairs);
+
+        let step = pairs.len().div_ceil(MAX_POINTS);
+        let rotated: Vec<(f32, f32)> = pairs
+            .iter()
+            .step_by(step)
+            .map(|&(left, right)| rotate(left, right))
+            .collect();
+
+        if self.auto_gain {
+            let peak = rotated
+                .iter()
+                .map(|&(x, y)| x.abs().max(y.abs()))
+                .fold(0.0, f32::max);
+            let target =
+                (TARGET_LEVEL / peak.max(f32::MIN_POSITIVE)).clamp(GAIN_RANGE.0, GAIN_RANGE.1);
+            let rate = if target < self.gain {
+                GAIN_ATTACK
+            } else {
+                GAIN_RELEASE
+            };
+            self.gain += (target - self.gain) * rate;
+        } else {
+            self.gain = GAIN_RANGE.0;
+        }
+
+        let gain = self.gain;
+        self.history.push_back(
+            rotated
+                .into_iter()
+                .map(|(x, y)| ((x * gain).clamp(-1.0, 1.0), (y * gain).clamp(-1.0, 1.0)))
+                .collect(),
+        );
+        while self.history.len() > self.persistence {
+            self.history.pop_front();
+        }
+    }
+
+    /// Each kept frame with its brightness, oldest and faintest first
+    pub fn frames(&self) -> impl Iterator<Item = (f32, &[(f32, f32)])> {
+        let count = self.history.len() as f32;
+        self.history
+            .iter()
+            .enumerate()
+            .map(move |(i, points)| ((i + 1) as f32 / count, points.as_slice()))
+    }
+
+    /// Gain applied to the newest frame
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    pub fn balance(&self) -> StereoBalance {
+        self.balance
+    }
+
+    pub fn persistence(&self) -> usize {
+        self.persistence
+    }
+
+    /// Frames kept, clamped to 1 (no persistence) to `MAX_PERSISTENCE`
+    pub fn set_persistence(&mut self, frames: usize) {
+        self.persistence = frames.clamp(1, MAX_PERSISTENCE);
+        while self.history.len() > self.persistence {
+            self.history.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.balance = StereoBalance::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(length: usize, gain: (f32, f32)) -> Vec<(f32, f32)> {
+        (0..length)
+            .map(|i| {
+                let sample = (std::f32::consts::TAU * i as f32 / 48.0).sin();
+                (sample * gain.0, sample * gain.1)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rotation() {
+        let (x, y) = rotate(0.5, 0.5);
+        assert!(x.abs() < 1e-6 && y > 0.7);
+        let (x, y) = rotate(1.0, 0.0);
+        assert!(x < 0.0 && y > 0.0 && (x + y).abs() < 1e-6);
+        let (x, y) = rotate(0.5, -0.5);
+        assert!(x < -0.7 && y.abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_balance() {
+        let mono = StereoBalance::of(&tone(480, (0.5, 0.5)));
+        assert!((mono.correlation - 1.0).abs() < 1e-4);
+        assert!(mono.side_to_mid().unwrap() < -60.0);
+
+        let inverted = StereoBalance::of(&tone(480, (0.5, -0.5)));
+        assert!((inverted.correlation + 1.0).abs() < 1e-4);
+        assert!(inverted.side_to_mid().unwrap() > 60.0);
+
+        // One channel only: equal mid and side, uncorrelated
+        let left = StereoBalance::of(&tone(480, (0.5, 0.0)));
+        assert!(left.correlation.abs() < 1e-4);
+        assert!(left.side_to_mid().unwrap().abs() < 0.01);
+
+        let silence = StereoBalance::of(&[(0.0, 0.0); 16]);
+        assert_eq!(silence.correlation, 0.0);
+        assert!(silence.side_to_mid().is_none());
+    }
+
+    #[test]
+    fn test_auto_gain_fills_the_display() {
+        let mut scope = Vectorscope::new();
+        for _ in 0..100 {
+            scope.update(&tone(480, (0.01, 0.01)));
+        }
+        let (_, newest) = scope.frames().last().unwrap();
+        let peak = newest.iter().map(|&(_, y)| y.abs()).fold(0.0, f32::max);
+        assert!((peak - TARGET_LEVEL).abs() < 0.02, "{peak}");
+
+        // A sudden loud signal is brought down within a few frames
+        for _ in 0..10 {
+            scope.update(&tone(480, (1.0, 1.0)));
+        }
+        assert!(scope.gain() < 1.1, "{}", scope.gain());
+
+        scope.auto_gain = false;
+        scope.update(&tone(480, (0.01, 0.01)));
+        assert_eq!(scope.gain(), 1.0);
+    }
+
+    #[test]
+    fn test_persistence() {
+        let mut scope = Vectorscope::new();
+        scope.set_persistence(3);
+        for _ in 0..5 {
+            scope.update(&tone(48, (0.5, 0.5)));
+        }
+        let brightness: Vec<f32> = scope.frames().map(|(alpha, _)| alpha).collect();
+        assert_eq!(brightness.len(), 3);
+        assert_eq!(brightness[2], 1.0);
+        assert!(brightness[0] < brightness[1]);
+
+        scope.set_persistence(0);
+        assert_eq!(scope.persistence(), 1);
+        assert_eq!(scope.frames().count(), 1);
+    }
+}
@@ -1,11 +1,32 @@
+use std::f32::consts::PI;
+use std::ops::Range;
+
+use crate::oscilloscope::decimation::{self, Decimation};
+use crate::oscilloscope::envelope::EnvelopeFollower;
 use crate::oscilloscope::trigger::{TriggerEdge, TriggerSettings};
 
+/// Samples kept beyond two screens by `append_samples`, so even the fastest
+/// timebases have room to search for an edge (~4 ms at 48 kHz)
+pub const TRIGGER_MARGIN: usize = 200;
+
+/// Samples either side that `Decimation::Smooth` interpolates from
+const LANCZOS_LOBES: usize = 3;
+
+/// Most points `Decimation::Smooth` draws from one sample to the next
+const MAX_SMOOTH_STEPS: usize = 16;
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WaveformData {
+    /// Not part of the settings, so never saved
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub samples: Vec<f32>,
-    pub time_per_division: f32,  // seconds per division
-    pub volts_per_division: f32, // volts per division
+    pub time_per_division: f32,      // seconds per division
+    pub volts_per_division: f32,     // volts per division
+    pub horizontal_divisions: usize, // divisions across the screen
     pub sample_rate: u32,
+    /// How `display_decimated` and `display_zoomed` draw a trace
+    pub decimation: Decimation,
 }
 
 impl WaveformData {
@@ -14,7 +35,9 @@ impl WaveformData {
             samples: Vec::new(),
             time_per_division: 0.001, // 1ms per division
             volts_per_division: 0.5,  // 0.5V per division
+            horizontal_divisions: 10,
             sample_rate,
+            decimation: Decimation::default(),
         }
     }
 
@@ -22,79 +45,337 @@ impl WaveformData {
         self.samples = new_samples;
     }
 
+    /// Replace the samples with a copy of `samples`, reusing the existing
+    /// allocation
+    pub fn copy_samples(&mut self, samples: &[f32]) {
+        self.samples.clear();
+        self.samples.extend_from_slice(samples);
+    }
+
+    /// Append newly captured samples to a rolling buffer of `buffer_size`,
+    /// dropping the oldest. The buffer is kept in place, so streaming input
+    /// doesn't allocate once it has reached its size.
+    pub fn append_samples(&mut self, new_samples: &[f32]) {
+        self.samples.extend_from_slice(new_samples);
+
+        let max_buffer_size = self.buffer_size();
+        if self.samples.len() > max_buffer_size {
+            let to_remove = self.samples.len() - max_buffer_size;
+            self.samples.drain(0..to_remove);
+        }
+    }
+
     pub fn get_display_samples(&self, trigger_settings: &TriggerSettings) -> Vec<(f32, f32)> {
-        if self.samples.is_empty() {
-            return vec![];
+        collect_points(|points| self.get_display_samples_into(trigger_settings, points))
+    }
+
+    /// `get_display_samples` written into `points`, replacing what was
+    /// there. A buffer kept from frame to frame stops allocating once it
+    /// has grown to the display's size; so for each `_into` method here.
+    pub fn get_display_samples_into(
+        &self,
+        trigger_settings: &TriggerSettings,
+        points: &mut Vec<(f32, f32)>,
+    ) {
+        points.clear();
+        if !self.samples.is_empty() {
+            self.display_points_into(self.display_range(trigger_settings), points);
         }
+    }
 
+    /// `samples[range]` in screen coordinates: x across the screen from 0.0
+    /// and y in divisions
+    pub fn display_points(&self, range: Range<usize>) -> Vec<(f32, f32)> {
+        collect_points(|points| self.display_points_into(range, points))
+    }
+
+    pub fn display_points_into(&self, range: Range<usize>, points: &mut Vec<(f32, f32)>) {
         let samples_per_screen = self.calculate_samples_per_screen();
 
-        // Find trigger point
-        let trigger_index = if trigger_settings.enabled {
-            self.find_trigger_point(trigger_settings)
-        } else {
-            // Free-run mode: just use the most recent samples
-            self.samples.len().saturating_sub(samples_per_screen)
-        };
+        // Convert to normalized coordinates
+        points.clear();
+        points.extend(self.samples[range].iter().enumerate().map(|(i, &sample)| {
+            let x = (i as f32) / (samples_per_screen as f32);
+            let y = sample / self.volts_per_division;
+            (x, y)
+        }));
+    }
+
+    /// `get_display_samples` with at most two points per screen column, for
+    /// drawing on a display `columns` pixels wide. See `display_decimated`.
+    pub fn get_display_decimated(
+        &self,
+        trigger_settings: &TriggerSettings,
+        columns: usize,
+    ) -> Vec<(f32, f32)> {
+        self.display_decimated(self.display_range(trigger_settings), columns)
+    }
+
+    /// `display_points` reduced to a display `columns` pixels wide, as
+    /// selected by `decimation`
+    pub fn display_decimated(&self, range: Range<usize>, columns: usize) -> Vec<(f32, f32)> {
+        collect_points(|points| self.display_decimated_into(range, columns, points))
+    }
+
+    pub fn display_decimated_into(
+        &self,
+        range: Range<usize>,
+        columns: usize,
+        points: &mut Vec<(f32, f32)>,
+    ) {
+        match self.decimation {
+            Decimation::PeakDetect => self.display_envelope_into(range, columns, points),
+            Decimation::Filtered => self.display_filtered_into(range, columns, points),
+            Decimation::Step | Decimation::Smooth => {
+                let samples_per_screen = self.calculate_samples_per_screen().max(1);
+                interpolate(
+                    &self.samples[range],
+                    samples_per_screen,
+                    columns,
+                    self.volts_per_division,
+                    self.decimation,
+                    points,
+                );
+            }
+        }
+    }
+
+    /// `display_points` low-pass filtered and decimated to one point per
+    /// screen column, wherever a column spans more than two samples
+    pub fn display_filtered(&self, range: Range<usize>, columns: usize) -> Vec<(f32, f32)> {
+        collect_points(|points| self.display_filtered_into(range, columns, points))
+    }
+
+    pub fn display_filtered_into(
+        &self,
+        range: Range<usize>,
+        columns: usize,
+        points: &mut Vec<(f32, f32)>,
+    ) {
+        let samples_per_screen = self.calculate_samples_per_screen().max(1);
+        let per_column = samples_per_screen as f32 / columns.max(1) as f32;
+        if per_column <= 2.0 {
+            self.display_points_into(range, points);
+            return;
+        }
+
+        decimation::low_pass_into(&self.samples[range], per_column, points);
+        for (position, value) in points.iter_mut() {
+            *position /= samples_per_screen as f32;
+            *value /= self.volts_per_division;
+        }
+    }
+
+    /// `display_points` decimated to the display: wherever a screen column
+    /// spans more than two samples, it contributes only their minimum and
+    /// maximum, in the order they occur. Drawing cost stays bounded by the
+    /// display width at slow timebases, and a glitch narrower than a pixel
+    /// still reaches its full height.
+    pub fn display_envelope(&self, range: Range<usize>, columns: usize) -> Vec<(f32, f32)> {
+        collect_points(|points| self.display_envelope_into(range, columns, points))
+    }
+
+    pub fn display_envelope_into(
+        &self,
+        range: Range<usize>,
+        columns: usize,
+        points: &mut Vec<(f32, f32)>,
+    ) {
+        self.display_channel_into(
+            &self.samples[range],
+            columns,
+            self.volts_per_division,
+            points,
+        );
+    }
+
+    /// Another trace on this timebase, such as a math channel, decimated
+    /// like `display_envelope` but with its own vertical scale. `samples`
+    /// starts at the left edge of the screen.
+    pub fn display_channel(
+        &self,
+        samples: &[f32],
+        columns: usize,
+        units_per_division: f32,
+    ) -> Vec<(f32, f32)> {
+        collect_points(|points| {
+            self.display_channel_into(samples, columns, units_per_division, points)
+        })
+    }
+
+    pub fn display_channel_into(
+        &self,
+        samples: &[f32],
+        columns: usize,
+        units_per_division: f32,
+        points: &mut Vec<(f32, f32)>,
+    ) {
+        let samples_per_screen = self.calculate_samples_per_screen().max(1);
+        peak_detect(
+            samples,
+            samples_per_screen,
+            columns,
+            units_per_division,
+            points,
+        );
+    }
+
+    /// `samples[range]` stretched across the whole screen, at a faster
+    /// timebase than the main trace, drawn between samples as `decimation`
+    /// has it and decimated like `display_envelope`
+    pub fn display_zoomed(&self, range: Range<usize>, columns: usize) -> Vec<(f32, f32)> {
+        collect_points(|points| self.display_zoomed_into(range, columns, points))
+    }
+
+    pub fn display_zoomed_into(
+        &self,
+        range: Range<usize>,
+        columns: usize,
+        points: &mut Vec<(f32, f32)>,
+    ) {
+        let end = range.end.min(self.samples.len());
+        let start = range.start.min(end);
+        interpolate(
+            &self.samples[start..end],
+            range.len().max(1),
+            columns,
+            self.volts_per_division,
+            self.decimation,
+            points,
+        );
+    }
+
+    /// The envelope of `samples[range]` in screen coordinates, like
+    /// `display_points`, with at most one point per screen column. The
+    /// follower runs over everything before the range too, so it has settled
+    /// by the left edge.
+    pub fn display_follower(
+        &self,
+        range: Range<usize>,
+        follower: &EnvelopeFollower,
+        columns: usize,
+    ) -> Vec<(f32, f32)> {
+        collect_points(|points| self.display_follower_into(range, follower, columns, points))
+    }
+
+    pub fn display_follower_into(
+        &self,
+        range: Range<usize>,
+        follower: &EnvelopeFollower,
+        columns: usize,
+        points: &mut Vec<(f32, f32)>,
+    ) {
+        let samples_per_screen = self.calculate_samples_per_screen().max(1);
+        let step = (samples_per_screen / columns.max(1)).max(1);
+
+        points.clear();
+        points.extend(
+            follower
+                .levels(&self.samples[..range.end], self.sample_rate)
+                .skip(range.start)
+                .enumerate()
+                .step_by(step)
+                .map(|(i, level)| {
+                    let x = (i as f32) / (samples_per_screen as f32);
+                    (x, level / self.volts_per_division)
+                }),
+        );
+    }
+
+    /// Range of `samples` shown on screen for the given trigger settings
+    pub fn display_range(&self, trigger_settings: &TriggerSettings) -> Range<usize> {
+        let samples_per_screen = self.calculate_samples_per_screen();
+
+        // Free-running, or with no edge to trigger on, the most recent
+        // samples are shown
+        let trigger_index = self
+            .trigger_index(trigger_settings)
+            .unwrap_or_else(|| self.samples.len().saturating_sub(samples_per_screen));
 
         // Extract the relevant window of samples
         let end_index = (trigger_index + samples_per_screen).min(self.samples.len());
         let start_index = trigger_index.min(end_index.saturating_sub(samples_per_screen));
 
-        // Convert to normalized coordinates
-        self.samples[start_index..end_index]
-            .iter()
-            .enumerate()
-            .map(|(i, &sample)| {
-                let x = (i as f32) / (samples_per_screen as f32);
-                let y = sample / self.volts_per_division;
-                (x, y)
-            })
-            .collect()
+        start_index..end_index
     }
 
     pub fn calculate_samples_per_screen(&self) -> usize {
-        // Assuming 10 divisions horizontally
-        let divisions = 10.0;
-        let total_time = self.time_per_division * divisions;
+        let total_time = self.time_per_division * self.horizontal_divisions as f32;
         (total_time * self.sample_rate as f32) as usize
     }
 
-    fn find_trigger_point(&self, settings: &TriggerSettings) -> usize {
+    /// Samples the rolling buffer holds: the newest screen, and a screen
+    /// plus `TRIGGER_MARGIN` before it for the trigger search, so any signal
+    /// with a period of up to a screen has an edge to trigger on every frame
+    pub fn buffer_size(&self) -> usize {
+        2 * self.calculate_samples_per_screen() + TRIGGER_MARGIN
+    }
+
+    /// Horizontal position of the trigger instant on screen (0.0 = left edge),
+    /// or None when untriggered or no edge was found
+    pub fn trigger_position(&self, trigger_settings: &TriggerSettings) -> Option<f32> {
+        let trigger_index = self.trigger_index(trigger_settings)?;
+        let range = self.display_range(trigger_settings);
+        let samples_per_screen = self.calculate_samples_per_screen().max(1);
+        Some((trigger_index - range.start) as f32 / samples_per_screen as f32)
+    }
+
+    /// Index in `samples` of the edge the display triggers on, or None
+    /// when untriggered or no edge was found
+    pub fn trigger_index(&self, trigger_settings: &TriggerSettings) -> Option<usize> {
+        if !trigger_settings.enabled {
+            return None;
+        }
+        self.find_trigger(trigger_settings)
+    }
+
+    /// The newest edge that still leaves a full screen of samples after it,
+    /// so the display tracks the latest data. A buffer shorter than a screen
+    /// is searched whole.
+    fn find_trigger(&self, settings: &TriggerSettings) -> Option<usize> {
         let threshold = settings.level;
+        let last = self
+            .samples
+            .len()
+            .checked_sub(self.calculate_samples_per_screen())
+            .unwrap_or(self.samples.len().saturating_sub(1));
 
-        for i in 1..self.samples.len() {
+        // Scan back from the newest candidate, stopping at the first edge
+        (1..=last).rev().find(|&i| {
             let prev = self.samples[i - 1];
             let curr = self.samples[i];
 
-            let triggered = match settings.edge {
+            match settings.edge {
                 TriggerEdge::Rising => prev < threshold && curr >= threshold,
                 TriggerEdge::Falling => prev > threshold && curr <= threshold,
-            };
-
-            if triggered {
-                return i;
             }
-        }
-
-        // No trigger found, return start of buffer
-        0
+        })
     }
 
     pub fn increase_time_scale(&mut self) {
-        self.time_per_division *= 2.0;
+        self.time_per_division = step_1_2_5(self.time_per_division, true);
     }
 
     pub fn decrease_time_scale(&mut self) {
-        self.time_per_division = (self.time_per_division / 2.0).max(0.00001);
+        self.time_per_division = step_1_2_5(self.time_per_division, false).max(0.00001);
     }
 
     pub fn increase_voltage_scale(&mut self) {
-        self.volts_per_division *= 2.0;
+        self.volts_per_division = step_1_2_5(self.volts_per_division, true);
     }
 
     pub fn decrease_voltage_scale(&mut self) {
-        self.volts_per_division = (self.volts_per_division / 2.0).max(0.01);
+        self.volts_per_division = step_1_2_5(self.volts_per_division, false).max(0.01);
+    }
+
+    /// Time/div moved by `FINE_STEP` rather than to the next 1-2-5 step
+    pub fn fine_time_scale(&mut self, up: bool) {
+        self.time_per_division = step_fine(self.time_per_division, up).max(0.00001);
+    }
+
+    /// Volts/div moved by `FINE_STEP` rather than to the next 1-2-5 step
+    pub fn fine_voltage_scale(&mut self, up: bool) {
+        self.volts_per_division = step_fine(self.volts_per_division, up).max(0.01);
     }
 
     /// Calculate the frequency of the waveform using zero-crossing detection
@@ -196,6 +477,196 @@ impl WaveformData {
     }
 }
 
+/// A trace built by one of the `_into` methods, in a buffer of its own
+fn collect_points(fill: impl FnOnce(&mut Vec<(f32, f32)>)) -> Vec<(f32, f32)> {
+    let mut points = Vec::new();
+    fill(&mut points);
+    points
+}
+
+/// Screen points of `samples`, `samples_per_screen` of them spanning the
+/// width, written into `points`: every sample while a column spans two or
+/// fewer, otherwise the minimum and maximum of each column in the order
+/// they occur
+fn peak_detect(
+    samples: &[f32],
+    samples_per_screen: usize,
+    columns: usize,
+    units_per_division: f32,
+    points: &mut Vec<(f32, f32)>,
+) {
+    let per_column = samples_per_screen as f32 / columns.max(1) as f32;
+    let point = |i: usize| {
+        let x = (i as f32) / (samples_per_screen as f32);
+        (x, samples[i] / units_per_division)
+    };
+    points.clear();
+    if per_column <= 2.0 {
+        points.extend((0..samples.len()).map(point));
+        return;
+    }
+
+    points.reserve(2 * columns + 2);
+    let mut start = 0;
+    while start < samples.len() {
+        let column = (start as f32 / per_column) as usize;
+        let end =
+            (((column + 1) as f32 * per_column).ceil() as usize).clamp(start + 1, samples.len());
+
+        let mut min = start;
+        let mut max = start;
+        for i in start..end {
+            if samples[i] < samples[min] {
+                min = i;
+            }
+            if samples[i] > samples[max] {
+                max = i;
+            }
+        }
+
+        points.push(point(min.min(max)));
+        if min != max {
+            points.push(point(min.max(max)));
+        }
+        start = end;
+    }
+}
+
+/// `peak_detect`, but with `Decimation::Step` or `Smooth` drawing between
+/// the samples while a column spans two or fewer
+fn interpolate(
+    samples: &[f32],
+    samples_per_screen: usize,
+    columns: usize,
+    units_per_division: f32,
+    decimation: Decimation,
+    points: &mut Vec<(f32, f32)>,
+) {
+    let per_column = samples_per_screen as f32 / columns.max(1) as f32;
+    let x = |position: f32| position / samples_per_screen as f32;
+    match decimation {
+        _ if per_column > 2.0 => {}
+        Decimation::Step => {
+            points.clear();
+            points.reserve(2 * samples.len());
+            for (i, &sample) in samples.iter().enumerate() {
+                let y = sample / units_per_division;
+                points.push((x(i as f32), y));
+                points.push((x((i + 1) as f32), y));
+            }
+            return;
+        }
+        Decimation::Smooth => {
+            // Around two points a column, which is as smooth as it can look
+            let steps = ((2.0 / per_column).ceil() as usize).clamp(1, MAX_SMOOTH_STEPS);
+            points.clear();
+            points.reserve(steps * samples.len());
+            for i in 0..samples.len() {
+                // Nothing follows the last sample to head toward
+                let steps = if i + 1 == samples.len() { 1 } else { steps };
+                for step in 0..steps {
+                    let position = i as f32 + step as f32 / steps as f32;
+                    points.push((
+                        x(position),
+                        lanczos_at(samples, position) / units_per_division,
+                    ));
+                }
+            }
+            return;
+        }
+        Decimation::PeakDetect | Decimation::Filtered => {}
+    }
+    peak_detect(
+        samples,
+        samples_per_screen,
+        columns,
+        units_per_division,
+        points,
+    );
+}
+
+/// `samples` at the fractional index `position`, by Lanczos interpolation.
+/// Near the ends, where some of the kernel falls outside, the weights that
+/// remain are normalized so the trace doesn't sag.
+fn lanczos_at(samples: &[f32], position: f32) -> f32 {
+    let lobes = LANCZOS_LOBES as f32;
+    let kernel = |x: f32| {
+        if x == 0.0 {
+            1.0
+        } else if x.abs() >= lobes {
+            0.0
+        } else {
+            let px = PI * x;
+            lobes * px.sin() * (px / lobes).sin() / (px * px)
+        }
+    };
+
+    let first = (position.floor() as usize + 1).saturating_sub(LANCZOS_LOBES);
+    let last = (position.floor() as usize + LANCZOS_LOBES).min(samples.len() - 1);
+    let (mut sum, mut weights) = (0.0, 0.0);
+    for (i, &sample) in samples.iter().enumerate().take(last + 1).skip(first) {
+        let weight = kernel(position - i as f32);
+        sum += weight * sample;
+        weights += weight;
+    }
+    if weights.abs() > f32::EPSILON {
+        sum / weights
+    } else {
+        0.0
+    }
+}
+
+/// The next value of the 1-2-5 sequence (..., 0.5, 1, 2, 5, 10, ...) above
+/// or below `value`. A value between steps, as the sliders leave it, goes
+/// to the step beside it. A value off the sequence entirely (zero, negative
+/// or not finite) is returned unchanged.
+pub fn step_1_2_5(value: f32, up: bool) -> f32 {
+    const MANTISSAS: [f64; 3] = [1.0, 2.0, 5.0];
+    // Anything this close to a step is on it
+    const TOLERANCE: f64 = 1e-3;
+
+    if !value.is_finite() || value <= 0.0 {
+        return value;
+    }
+
+    let value = value as f64;
+    let decade = value.log10().floor() as i32;
+    let steps = (decade - 1..=decade + 1)
+        .flat_map(|decade| MANTISSAS.map(|mantissa| mantissa * 10_f64.powi(decade)));
+    let step = if up {
+        steps
+            .filter(|&step| step > value * (1.0 + TOLERANCE))
+            .reduce(f64::min)
+    } else {
+        steps
+            .filter(|&step| step < value * (1.0 - TOLERANCE))
+            .reduce(f64::max)
+    };
+    step.unwrap_or(value) as f32
+}
+
+/// Fraction of its value a fine adjustment (Shift held) moves a scale by
+pub const FINE_STEP: f32 = 0.01;
+
+/// `value` one `FINE_STEP` up or down
+pub fn step_fine(value: f32, up: bool) -> f32 {
+    if up {
+        value * (1.0 + FINE_STEP)
+    } else {
+        value / (1.0 + FINE_STEP)
+    }
+}
+
+/// `value` one `FINE_STEP` toward `target`, without passing it. A slider
+/// dragged with Shift held creeps toward the pointer this way.
+pub fn step_fine_toward(value: f32, target: f32) -> f32 {
+    if target > value {
+        step_fine(value, true).min(target)
+    } else {
+        step_fine(value, false).max(target)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -218,6 +689,198 @@ mod tests {
         assert_eq!(waveform.samples, samples);
     }
 
+    #[test]
+    fn test_append_samples_keeps_two_screens_and_margin() {
+        let mut waveform = WaveformData::new(48000); // 480 samples per screen
+        waveform.append_samples(&[1.0; 300]);
+        assert_eq!(waveform.samples.len(), 300);
+
+        let newest: Vec<f32> = (0..2000).map(|i| i as f32).collect();
+        waveform.append_samples(&newest);
+        assert_eq!(waveform.samples.len(), 960 + TRIGGER_MARGIN);
+        assert_eq!(waveform.samples.last(), Some(&1999.0));
+        assert_eq!(waveform.samples[0], (2000 - 960 - TRIGGER_MARGIN) as f32);
+
+        let capacity = waveform.samples.capacity();
+        waveform.append_samples(&[0.0; 100]);
+        assert_eq!(waveform.samples.capacity(), capacity);
+    }
+
+    #[test]
+    fn test_copy_samples() {
+        let mut waveform = WaveformData::new(48000);
+        waveform.append_samples(&[1.0; 10]);
+        waveform.copy_samples(&[0.5, 0.25]);
+        assert_eq!(waveform.samples, vec![0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_display_envelope_is_bounded_by_columns() {
+        let mut waveform = WaveformData::new(48000);
+        waveform.time_per_division = 0.01; // ~4800 samples per screen
+        let samples_per_screen = waveform.calculate_samples_per_screen();
+        waveform.update_samples(
+            (0..samples_per_screen)
+                .map(|i| (i as f32 * 0.1).sin())
+                .collect(),
+        );
+
+        let points = waveform.display_envelope(0..samples_per_screen, 100);
+        assert!(points.len() <= 200);
+        assert!(points.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
+
+    #[test]
+    fn test_display_envelope_keeps_narrow_glitches() {
+        let mut waveform = WaveformData::new(48000);
+        waveform.time_per_division = 0.01;
+        let samples_per_screen = waveform.calculate_samples_per_screen();
+        let mut samples = vec![0.0; samples_per_screen];
+        samples[2345] = 1.0;
+        waveform.update_samples(samples);
+
+        let points = waveform.display_envelope(0..samples_per_screen, 100);
+        let glitch = (2345.0 / samples_per_screen as f32, 2.0);
+        assert!(points.contains(&glitch));
+    }
+
+    #[test]
+    fn test_display_envelope_without_decimation() {
+        let mut waveform = WaveformData::new(48000); // 480 samples per screen
+        waveform.update_samples((0..480).map(|i| i as f32 / 480.0).collect());
+        assert_eq!(
+            waveform.display_envelope(0..480, 1000),
+            waveform.display_points(0..480)
+        );
+    }
+
+    #[test]
+    fn test_display_channel_has_its_own_scale() {
+        let waveform = WaveformData::new(48000); // 480 samples per screen
+        let math = vec![10.0; 480];
+        let points = waveform.display_channel(&math, 1000, 5.0);
+        assert_eq!(points.len(), 480);
+        assert!(points.iter().all(|&(_, y)| y == 2.0));
+    }
+
+    #[test]
+    fn test_display_zoomed_fills_the_screen() {
+        let mut waveform = WaveformData::new(48000);
+        waveform.volts_per_division = 1.0;
+        waveform.update_samples((0..480).map(|i| i as f32).collect());
+        let points = waveform.display_zoomed(100..110, 1000);
+        assert_eq!(points.len(), 10);
+        assert_eq!(points[0], (0.0, 100.0));
+        assert_eq!(points[9], (0.9, 109.0));
+        // A range past the capture gives what there is
+        assert_eq!(waveform.display_zoomed(475..485, 1000).len(), 5);
+    }
+
+    #[test]
+    fn test_display_decimated_follows_mode() {
+        let mut waveform = WaveformData::new(48000);
+        waveform.time_per_division = 0.01; // 4800 samples per screen
+        let samples_per_screen = waveform.calculate_samples_per_screen();
+        // A fast tone the display can't resolve
+        waveform.update_samples(
+            (0..samples_per_screen)
+                .map(|i| (i as f32 * 0.45 * std::f32::consts::TAU).sin())
+                .collect(),
+        );
+        let range = 0..samples_per_screen;
+
+        // Peak detect shows its full extent
+        let peaks = waveform.display_decimated(range.clone(), 100);
+        assert_eq!(peaks, waveform.display_envelope(range.clone(), 100));
+        assert!(peaks.iter().any(|&(_, y)| y > 1.5));
+
+        // Filtered averages it away, one point per column
+        waveform.decimation = Decimation::Filtered;
+        let filtered = waveform.display_decimated(range, 100);
+        assert_eq!(filtered.len(), 100);
+        assert!(filtered.iter().all(|&(_, y)| y.abs() < 0.1));
+    }
+
+    #[test]
+    fn test_step_and_smooth_draw_between_samples() {
+        let mut waveform = WaveformData::new(48000); // 480 samples per screen
+        waveform.volts_per_division = 1.0;
+        waveform.update_samples(vec![0.0, 1.0, 0.0, -1.0]);
+
+        waveform.decimation = Decimation::Step;
+        let points = waveform.display_decimated(0..4, 1000);
+        let x = |i: f32| i / 480.0;
+        assert_eq!(
+            &points[..4],
+            [(x(0.0), 0.0), (x(1.0), 0.0), (x(1.0), 1.0), (x(2.0), 1.0)]
+        );
+
+        // Each sample is kept, with the curve between them
+        waveform.decimation = Decimation::Smooth;
+        let points = waveform.display_decimated(0..4, 1000);
+        assert_eq!(points.len(), 3 * 5 + 1);
+        for (i, &sample) in [0.0, 1.0, 0.0, -1.0].iter().enumerate() {
+            assert!((points[5 * i].1 - sample).abs() < 1e-6);
+        }
+        // Rising past the midpoint between 0 and 1 before the peak
+        assert!(points[4].1 > 0.5);
+    }
+
+    #[test]
+    fn test_step_and_smooth_fall_back_to_peaks_zoomed_out() {
+        let mut waveform = WaveformData::new(48000);
+        waveform.time_per_division = 0.01; // 4800 samples per screen
+        waveform.update_samples((0..4800).map(|i| (i as f32 * 0.05).sin()).collect());
+        let peaks = waveform.display_envelope(0..4800, 100);
+        for decimation in [Decimation::Step, Decimation::Smooth] {
+            waveform.decimation = decimation;
+            assert_eq!(waveform.display_decimated(0..4800, 100), peaks);
+        }
+    }
+
+    #[test]
+    fn test_display_follower() {
+        let mut waveform = WaveformData::new(48000); // 480 samples per screen
+        waveform.update_samples(vec![-1.0; 1000]);
+        let follower = EnvelopeFollower::default();
+
+        let points = waveform.display_follower(520..1000, &follower, 120);
+        assert_eq!(points.len(), 120);
+        assert_eq!(points[1].0, 4.0 / 480.0);
+        // Rectified, in divisions
+        assert!(points.iter().all(|&(_, y)| (y - 2.0).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_display_into_reuses_the_buffer() {
+        let mut waveform = WaveformData::new(48000);
+        waveform.time_per_division = 0.01; // 4800 samples per screen
+        waveform.update_samples((0..5000).map(|i| (i as f32 * 0.05).sin()).collect());
+        let range = 0..4800;
+
+        let mut points = Vec::new();
+        for decimation in Decimation::ALL {
+            waveform.decimation = decimation;
+            waveform.display_decimated_into(range.clone(), 100, &mut points);
+            assert_eq!(points, waveform.display_decimated(range.clone(), 100));
+        }
+        waveform.display_follower_into(
+            range.clone(),
+            &EnvelopeFollower::default(),
+            100,
+            &mut points,
+        );
+        assert_eq!(
+            points,
+            waveform.display_follower(range.clone(), &EnvelopeFollower::default(), 100)
+        );
+
+        // Once grown, the next frame's trace fits where the last one was
+        let capacity = points.capacity();
+        waveform.display_decimated_into(range, 100, &mut points);
+        assert_eq!(points.capacity(), capacity);
+    }
+
     #[test]
     fn test_calculate_samples_per_screen() {
         let waveform = WaveformData::new(48000);
@@ -227,6 +890,13 @@ mod tests {
         assert_eq!(samples_per_screen, 480);
     }
 
+    #[test]
+    fn test_samples_per_screen_follows_divisions() {
+        let mut waveform = WaveformData::new(48000);
+        waveform.horizontal_divisions = 12;
+        assert_eq!(waveform.calculate_samples_per_screen(), 576);
+    }
+
     #[test]
     fn test_increase_time_scale() {
         let mut waveform = WaveformData::new(48000);
@@ -236,15 +906,15 @@ mod tests {
         assert_eq!(waveform.time_per_division, initial * 2.0);
 
         waveform.increase_time_scale();
-        assert_eq!(waveform.time_per_division, initial * 4.0);
+        assert_eq!(waveform.time_per_division, 0.005);
     }
 
     #[test]
     fn test_decrease_time_scale() {
         let mut waveform = WaveformData::new(48000);
-        // Start with default 0.001, increase to 0.004
+        // Start with default 0.001, increase to 0.005
         waveform.increase_time_scale(); // 0.002
-        waveform.increase_time_scale(); // 0.004
+        waveform.increase_time_scale(); // 0.005
 
         waveform.decrease_time_scale();
         assert_eq!(waveform.time_per_division, 0.002);
@@ -275,19 +945,53 @@ mod tests {
 
         waveform.increase_voltage_scale();
         assert_eq!(waveform.volts_per_division, initial * 4.0);
+
+        waveform.increase_voltage_scale();
+        assert_eq!(waveform.volts_per_division, initial * 10.0);
     }
 
     #[test]
     fn test_decrease_voltage_scale() {
         let mut waveform = WaveformData::new(48000);
         // Start with default 0.5, decrease to get to lower values
-        waveform.decrease_voltage_scale(); // 0.25
-        waveform.decrease_voltage_scale(); // 0.125
+        waveform.decrease_voltage_scale(); // 0.2
+        waveform.decrease_voltage_scale(); // 0.1
 
         let current = waveform.volts_per_division;
         assert!(current < 0.5);
     }
 
+    #[test]
+    fn test_1_2_5_steps() {
+        assert_eq!(step_1_2_5(0.00001, true), 0.00002);
+        assert_eq!(step_1_2_5(0.00005, true), 0.0001);
+        assert_eq!(step_1_2_5(0.1, false), 0.05);
+        assert_eq!(step_1_2_5(2.0, false), 1.0);
+        // Between steps, as a slider leaves it
+        assert_eq!(step_1_2_5(0.0037, true), 0.005);
+        assert_eq!(step_1_2_5(0.0037, false), 0.002);
+        assert_eq!(step_1_2_5(9.0, true), 10.0);
+        // Off the sequence, nothing to step to
+        assert_eq!(step_1_2_5(0.0, true), 0.0);
+        assert_eq!(step_1_2_5(-1.0, false), -1.0);
+        assert!(step_1_2_5(f32::NAN, true).is_nan());
+        assert_eq!(step_1_2_5(f32::INFINITY, false), f32::INFINITY);
+    }
+
+    #[test]
+    fn test_fine_steps() {
+        let mut waveform = WaveformData::new(48000);
+        waveform.fine_voltage_scale(true);
+        assert!((waveform.volts_per_division - 0.505).abs() < 1e-6);
+        waveform.fine_voltage_scale(false);
+        assert!((waveform.volts_per_division - 0.5).abs() < 1e-6);
+
+        assert!((step_fine_toward(1.0, 10.0) - 1.01).abs() < 1e-6);
+        assert!((step_fine_toward(1.0, 0.1) - 1.0 / 1.01).abs() < 1e-6);
+        // Never past the target
+        assert_eq!(step_fine_toward(1.0, 1.005), 1.005);
+    }
+
     #[test]
     fn test_decrease_voltage_scale_minimum() {
         let mut waveform = WaveformData::new(48000);
@@ -300,6 +1004,37 @@ mod tests {
         assert_eq!(waveform.volts_per_division, 0.01);
     }
 
+    #[test]
+    fn test_trigger_position() {
+        let mut waveform = WaveformData::new(48000); // 480 samples per screen
+        let mut settings = TriggerSettings::default();
+
+        // Edge early in a long buffer: the window starts at the trigger
+        let mut samples = vec![-1.0; 10];
+        samples.extend(vec![1.0; 990]);
+        waveform.update_samples(samples);
+        assert_eq!(waveform.trigger_position(&settings), Some(0.0));
+
+        // Edge without a full screen after it is skipped
+        let mut samples = vec![-1.0; 600];
+        samples.extend(vec![1.0; 48]);
+        waveform.update_samples(samples);
+        assert_eq!(waveform.trigger_position(&settings), None);
+
+        // Buffer shorter than a screen: the window can't move, so the trigger sits right of the left edge
+        let mut samples = vec![-1.0; 240];
+        samples.extend(vec![1.0; 48]);
+        waveform.update_samples(samples);
+        let position = waveform.trigger_position(&settings).unwrap();
+        assert!((position - 240.0 / 480.0).abs() < 1e-6);
+
+        // No edge, or trigger disabled
+        waveform.update_samples(vec![1.0; 1000]);
+        assert_eq!(waveform.trigger_position(&settings), None);
+        settings.enabled = false;
+        assert_eq!(waveform.trigger_position(&settings), None);
+    }
+
     #[test]
     fn test_find_trigger_point_rising_edge() {
         let mut waveform = WaveformData::new(48000);
@@ -312,8 +1047,8 @@ mod tests {
             ..Default::default()
         };
 
-        let trigger_point = waveform.find_trigger_point(&settings);
-        assert_eq!(trigger_point, 3); // Should trigger between 0.0 and 0.5
+        let trigger_point = waveform.find_trigger(&settings);
+        assert_eq!(trigger_point, Some(3)); // Should trigger between 0.0 and 0.5
     }
 
     #[test]
@@ -328,24 +1063,65 @@ mod tests {
             ..Default::default()
         };
 
-        let trigger_point = waveform.find_trigger_point(&settings);
-        assert_eq!(trigger_point, 2); // Should trigger between 0.5 and 0.0
+        let trigger_point = waveform.find_trigger(&settings);
+        assert_eq!(trigger_point, Some(2)); // Should trigger between 0.5 and 0.0
     }
 
     #[test]
-    fn test_find_trigger_point_no_trigger() {
-        let mut waveform = WaveformData::new(48000);
-        let samples = vec![-1.0, -0.9, -0.8, -0.7, -0.6];
+    fn test_find_trigger_prefers_newest_edge() {
+        let mut waveform = WaveformData::new(48000); // 480 samples per screen
+        let mut samples = vec![];
+        for edge in [10, 200, 400, 700] {
+            samples.resize(edge, -1.0);
+            samples.extend(vec![1.0; 50]);
+        }
+        samples.resize(1000, -1.0);
         waveform.update_samples(samples);
 
+        let settings = TriggerSettings::default();
+        // The edge at 700 has only 300 samples after it
+        assert_eq!(waveform.find_trigger(&settings), Some(400));
+        assert_eq!(waveform.display_range(&settings), 400..880);
+    }
+
+    #[test]
+    fn test_slow_signals_trigger_every_frame() {
+        let settings = TriggerSettings::default();
+        for (frequency, time_per_division) in [(50.0, 0.005), (20.0, 0.01), (5.0, 0.05)] {
+            let mut waveform = WaveformData::new(48000);
+            waveform.time_per_division = time_per_division;
+            let sine = |i: usize| (2.0 * PI * frequency * i as f32 / 48000.0).sin();
+
+            // Streamed in 4 ms blocks, as the acquisition thread reads them
+            let mut read = 0;
+            while read < 3 * 48000 {
+                let block: Vec<f32> = (read..read + 192).map(sine).collect();
+                waveform.append_samples(&block);
+                read += block.len();
+                if waveform.samples.len() == waveform.buffer_size() {
+                    assert!(
+                        waveform.find_trigger(&settings).is_some(),
+                        "{frequency} Hz at {time_per_division} s/div missed at {read}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_trigger_point_no_trigger() {
+        let mut waveform = WaveformData::new(48000); // 480 samples per screen
+        waveform.update_samples(vec![-1.0; 1000]);
+
         let settings = TriggerSettings {
             edge: TriggerEdge::Rising,
             level: 0.0,
             ..Default::default()
         };
 
-        let trigger_point = waveform.find_trigger_point(&settings);
-        assert_eq!(trigger_point, 0); // No trigger found, should return 0
+        // No edge: free-running on the newest samples, not the oldest
+        assert_eq!(waveform.find_trigger(&settings), None);
+        assert_eq!(waveform.display_range(&settings), 520..1000);
     }
 
     #[test]
@@ -394,6 +1170,7 @@ mod tests {
             enabled: true,
             edge: TriggerEdge::Rising,
             level: 0.0,
+            ..Default::default()
         };
 
         let display_samples = waveform.get_display_samples(&settings);
@@ -0,0 +1,145 @@
+use std::ops::Range;
+
+/// Narrowest a zoom region can get, as a fraction of the screen: a 500x
+/// magnification
+pub const MIN_ZOOM_WIDTH: f32 = 0.002;
+
+/// What a drag on the main trace holds of the zoom region
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ZoomHandle {
+    Start,
+    End,
+    /// The whole region, held `offset` to the right of its start
+    Region {
+        offset: f32,
+    },
+}
+
+/// The part of the main trace shown expanded in the zoom pane, as
+/// fractions of the screen width. The zoom pane runs the same record at a
+/// timebase faster by the region's share of the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoomWindow {
+    pub start: f32,
+    pub end: f32,
+}
+
+impl Default for ZoomWindow {
+    /// The middle tenth of the screen
+    fn default() -> Self {
+        Self {
+            start: 0.45,
+            end: 0.55,
+        }
+    }
+}
+
+impl ZoomWindow {
+    pub fn width(&self) -> f32 {
+        self.end - self.start
+    }
+
+    /// What a press at `x` picks up: an edge within `tolerance` of it, the
+    /// region when inside, or, outside, the region recentered on `x`
+    pub fn grab(&mut self, x: f32, tolerance: f32) -> ZoomHandle {
+        if (x - self.start).abs() <= tolerance {
+            ZoomHandle::Start
+        } else if (x - self.end).abs() <= tolerance {
+            ZoomHandle::End
+        } else {
+            if x < self.start || x > self.end {
+                let offset = self.width() / 2.0;
+                self.drag(ZoomHandle::Region { offset }, x);
+            }
+            ZoomHandle::Region {
+                offset: x - self.start,
+            }
+        }
+    }
+
+    /// Move what `handle` holds to `x`, kept on screen. An edge stops short
+    /// of the other one; the region keeps its width.
+    pub fn drag(&mut self, handle: ZoomHandle, x: f32) {
+        match handle {
+            ZoomHandle::Start => {
+                self.start = x.clamp(0.0, self.end - MIN_ZOOM_WIDTH);
+            }
+            ZoomHandle::End => {
+                self.end = x.clamp(self.start + MIN_ZOOM_WIDTH, 1.0);
+            }
+            ZoomHandle::Region { offset } => {
+                let width = self.width();
+                self.start = (x - offset).clamp(0.0, 1.0 - width);
+                self.end = self.start + width;
+            }
+        }
+    }
+
+    /// Narrow (`factor` below 1) or widen the region about its center
+    pub fn scale(&mut self, factor: f32) {
+        let width = (self.width() * factor).clamp(MIN_ZOOM_WIDTH, 1.0);
+        let center = (self.start + self.end) / 2.0;
+        self.start = (center - width / 2.0).clamp(0.0, 1.0 - width);
+        self.end = self.start + width;
+    }
+
+    /// The samples in the region, `range` being the part of the record on
+    /// the main screen
+    pub fn range(&self, range: Range<usize>) -> Range<usize> {
+        let length = range.len() as f32;
+        let first = range.start + (self.start * length).floor() as usize;
+        let last = range.start + (self.end * length).ceil() as usize;
+        first.min(last)..last
+    }
+
+    /// Time per division of the zoom pane, for the main trace's
+    pub fn time_per_division(&self, main: f32) -> f32 {
+        main * self.width()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grabbing_and_dragging() {
+        let mut zoom = ZoomWindow::default();
+        assert_eq!(zoom.grab(0.451, 0.01), ZoomHandle::Start);
+        assert_eq!(zoom.grab(0.545, 0.01), ZoomHandle::End);
+
+        // Inside, the region is held where it was pressed
+        let handle = zoom.grab(0.5, 0.01);
+        assert!(matches!(handle, ZoomHandle::Region { offset } if (offset - 0.05).abs() < 1e-6));
+        zoom.drag(handle, 0.99);
+        assert_eq!((zoom.start, zoom.end), (0.9, 1.0));
+
+        // Outside, it jumps to center on the press
+        zoom.grab(0.2, 0.01);
+        assert!((zoom.start - 0.15).abs() < 1e-6 && (zoom.end - 0.25).abs() < 1e-6);
+
+        // An edge can't cross the other
+        zoom.drag(ZoomHandle::End, 0.0);
+        assert!((zoom.width() - MIN_ZOOM_WIDTH).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_scale_and_range() {
+        let mut zoom = ZoomWindow {
+            start: 0.8,
+            end: 1.0,
+        };
+        zoom.scale(2.0);
+        // Widened about its center, then pushed back on screen
+        assert!((zoom.start - 0.6).abs() < 1e-6 && zoom.end == 1.0);
+        zoom.scale(0.0);
+        assert!((zoom.width() - MIN_ZOOM_WIDTH).abs() < 1e-6);
+
+        let zoom = ZoomWindow {
+            start: 0.25,
+            end: 0.5,
+        };
+        assert_eq!(zoom.range(100..300), 150..200);
+        assert!((zoom.time_per_division(0.01) - 0.0025).abs() < 1e-9);
+    }
+}
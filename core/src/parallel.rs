@@ -0,0 +1,87 @@
+//! Heavy analysis spread across threads.
+//!
+//! With the `parallel` feature, work over at least `PARALLEL_MIN_SAMPLES`
+//! samples runs on rayon's thread pool. Anything smaller, or any build
+//! without the feature, runs on the calling thread just as a plain loop
+//! would, so the live single-channel path takes no locks and allocates
+//! nothing more.
+
+/// Samples of work under which handing it to other threads costs more than
+/// it saves
+pub const PARALLEL_MIN_SAMPLES: usize = 1 << 16;
+
+/// Whether `samples` of work is worth spreading across threads
+#[cfg(feature = "parallel")]
+fn worth_spreading(samples: usize) -> bool {
+    samples >= PARALLEL_MIN_SAMPLES
+}
+
+/// Run `a` and `b`, side by side if they cover `samples` between them
+pub fn join<A, B, RA, RB>(samples: usize, a: A, b: B) -> (RA, RB)
+where
+    A: FnOnce() -> RA + Send,
+    B: FnOnce() -> RB + Send,
+    RA: Send,
+    RB: Send,
+{
+    #[cfg(feature = "parallel")]
+    if worth_spreading(samples) {
+        return rayon::join(a, b);
+    }
+    let _ = samples;
+    (a(), b())
+}
+
+/// `f` of each item, in order. `samples` is the work across all of them.
+pub fn map<T, R, F>(items: &[T], samples: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync + Send,
+{
+    map_init(items, samples, || (), |_, item| f(item))
+}
+
+/// `f` of each item, in order, with scratch state made by `init`: once for
+/// the whole batch on one thread, or once per piece of it split off to
+/// another
+pub fn map_init<T, S, R, I, F>(items: &[T], samples: usize, init: I, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    I: Fn() -> S + Sync + Send,
+    F: Fn(&mut S, &T) -> R + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    if items.len() > 1 && worth_spreading(samples) {
+        use rayon::prelude::*;
+        return items.par_iter().map_init(init, f).collect();
+    }
+    let _ = samples;
+    let mut state = init();
+    items.iter().map(|item| f(&mut state, item)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_results_keep_their_order() {
+        let items: Vec<usize> = (0..1000).collect();
+        for samples in [0, PARALLEL_MIN_SAMPLES] {
+            let squares = map(&items, samples, |&i| i * i);
+            assert!(squares.iter().enumerate().all(|(i, &s)| s == i * i));
+
+            // Scratch state is never shared between items running at once
+            let counted = map_init(&items, samples, Vec::new, |seen, &i| {
+                seen.push(i);
+                seen.windows(2).all(|pair| pair[0] < pair[1])
+            });
+            assert!(counted.into_iter().all(|ascending| ascending));
+        }
+
+        let (a, b) = join(PARALLEL_MIN_SAMPLES, || 1, || "two");
+        assert_eq!((a, b), (1, "two"));
+    }
+}
@@ -0,0 +1,233 @@
+use std::sync::{Arc, RwLock};
+
+use crate::decode::Decoder;
+use crate::parallel;
+
+/// A measurement that isn't built into `Measurements`, e.g. one a third
+/// party adds without patching the waveform code.
+///
+/// Registered with [`register_measurement`]; the frontends list the result
+/// of every registered measurement alongside their own.
+pub trait MeasurementPlugin: Send + Sync {
+    /// Label the result is shown under, and the key it's registered by
+    fn name(&self) -> &'static str;
+
+    /// Shown after the value, e.g. "V" or "dB"
+    fn unit(&self) -> &'static str;
+
+    /// Measure the capture buffer; `None` when there's nothing to measure
+    fn measure(&self, samples: &[f32], sample_rate: u32) -> Option<f32>;
+}
+
+/// A decoder the frontends offer next to their own.
+///
+/// Registered with [`register_decoder`]. Each time the decoder is switched
+/// on the frontend asks for a fresh one, since decoding a live stream keeps
+/// state.
+pub trait DecoderPlugin: Send + Sync {
+    /// Shown in the decoder selection, and the key it's registered by
+    fn name(&self) -> &'static str;
+
+    fn create(&self) -> Box<dyn Decoder>;
+}
+
+/// One plugin's measurement of a capture
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginMeasurement {
+    pub name: &'static str,
+    pub unit: &'static str,
+    pub value: Option<f32>,
+}
+
+static MEASUREMENTS: RwLock<Vec<Arc<dyn MeasurementPlugin>>> = RwLock::new(Vec::new());
+static DECODERS: RwLock<Vec<Arc<dyn DecoderPlugin>>> = RwLock::new(Vec::new());
+
+/// Add a measurement for every capture from now on, replacing one already
+/// registered under the same name
+pub fn register_measurement(plugin: impl MeasurementPlugin + 'static) {
+    register(&MEASUREMENTS, Arc::new(plugin), |p| p.name());
+}
+
+/// Offer a decoder from now on, replacing one already registered under the
+/// same name
+pub fn register_decoder(plugin: impl DecoderPlugin + 'static) {
+    register(&DECODERS, Arc::new(plugin), |p| p.name());
+}
+
+fn register<T: ?Sized>(
+    registry: &RwLock<Vec<Arc<T>>>,
+    plugin: Arc<T>,
+    name: impl Fn(&T) -> &'static str,
+) {
+    // A plugin that panicked can't have left the list itself inconsistent
+    let mut plugins = registry.write().unwrap_or_else(|e| e.into_inner());
+    match plugins.iter().position(|p| name(p) == name(&plugin)) {
+        Some(index) => plugins[index] = plugin,
+        None => plugins.push(plugin),
+    }
+}
+
+/// Registered measurements, in the order they were first registered
+pub fn measurement_plugins() -> Vec<Arc<dyn MeasurementPlugin>> {
+    MEASUREMENTS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+/// Registered decoders, in the order they were first registered
+pub fn decoder_plugins() -> Vec<Arc<dyn DecoderPlugin>> {
+    DECODERS.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Every registered measurement of `samples`, side by side when there are
+/// enough samples to be worth it
+pub fn measure(samples: &[f32], sample_rate: u32) -> Vec<PluginMeasurement> {
+    let plugins = measurement_plugins();
+    parallel::map(&plugins, plugins.len() * samples.len(), |plugin| {
+        PluginMeasurement {
+            name: plugin.name(),
+            unit: plugin.unit(),
+            value: plugin.measure(samples, sample_rate),
+        }
+    })
+}
+
+/// Peak over RMS in dB: 3 dB for a sine, 0 for a square, more for spikier
+/// signals. Ships as an example of a measurement plugin.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CrestFactor;
+
+impl MeasurementPlugin for CrestFactor {
+    fn name(&self) -> &'static str {
+        "Crest"
+    }
+
+    fn unit(&self) -> &'static str {
+        "dB"
+    }
+
+    fn measure(&self, samples: &[f32], _sample_rate: u32) -> Option<f32> {
+        let peak = samples.iter().fold(0.0_f32, |peak, &x| peak.max(x.abs()));
+        let mean_square = samples.iter().map(|&x| x * x).sum::<f32>() / samples.len() as f32;
+        (mean_square > 0.0).then(|| 20.0 * (peak / mean_square.sqrt()).log10())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::DecodedEvent;
+
+    // The registry is shared by every test, so each one registers under its
+    // own names
+
+    struct Constant(&'static str, f32);
+
+    impl MeasurementPlugin for Constant {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn unit(&self) -> &'static str {
+            "V"
+        }
+
+        fn measure(&self, _samples: &[f32], _sample_rate: u32) -> Option<f32> {
+            Some(self.1)
+        }
+    }
+
+    struct Edges;
+
+    impl Decoder for Edges {
+        fn name(&self) -> &'static str {
+            "Edges"
+        }
+
+        fn max_symbol_samples(&self, _sample_rate: u32) -> usize {
+            2
+        }
+
+        fn decode(&self, samples: &[f32], _sample_rate: u32) -> Vec<DecodedEvent> {
+            samples
+                .windows(2)
+                .enumerate()
+                .filter(|(_, pair)| pair[0] < 0.0 && pair[1] >= 0.0)
+                .map(|(i, _)| DecodedEvent {
+                    start: i,
+                    end: i + 2,
+                    value: 1,
+                    label: "↑".to_string(),
+                    error: false,
+                })
+                .collect()
+        }
+    }
+
+    struct EdgesPlugin;
+
+    impl DecoderPlugin for EdgesPlugin {
+        fn name(&self) -> &'static str {
+            "Edges"
+        }
+
+        fn create(&self) -> Box<dyn Decoder> {
+            Box::new(Edges)
+        }
+    }
+
+    fn result(name: &str) -> Option<PluginMeasurement> {
+        measure(&[0.0, 1.0], 48000)
+            .into_iter()
+            .find(|measurement| measurement.name == name)
+    }
+
+    #[test]
+    fn test_measurement_registry() {
+        register_measurement(Constant("Test offset", 0.5));
+        assert_eq!(
+            result("Test offset"),
+            Some(PluginMeasurement {
+                name: "Test offset",
+                unit: "V",
+                value: Some(0.5),
+            })
+        );
+
+        // Registering the same name again replaces the plugin
+        register_measurement(Constant("Test offset", 0.25));
+        let count = measurement_plugins()
+            .iter()
+            .filter(|plugin| plugin.name() == "Test offset")
+            .count();
+        assert_eq!(count, 1);
+        assert_eq!(result("Test offset").unwrap().value, Some(0.25));
+    }
+
+    #[test]
+    fn test_decoder_registry() {
+        register_decoder(EdgesPlugin);
+        let plugin = decoder_plugins()
+            .into_iter()
+            .find(|plugin| plugin.name() == "Edges")
+            .unwrap();
+        let events = plugin.create().decode(&[-1.0, 1.0, -1.0, 1.0], 48000);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].start, 2);
+    }
+
+    #[test]
+    fn test_crest_factor() {
+        let sine: Vec<f32> = (0..4800)
+            .map(|i| (2.0 * std::f32::consts::PI * i as f32 / 48.0).sin())
+            .collect();
+        let crest = CrestFactor.measure(&sine, 48000).unwrap();
+        assert!((crest - 3.01).abs() < 0.05, "crest {crest}");
+
+        let square = [1.0, -1.0, 1.0, -1.0];
+        assert!(CrestFactor.measure(&square, 48000).unwrap().abs() < 1e-6);
+        assert_eq!(CrestFactor.measure(&[0.0; 4], 48000), None);
+        assert_eq!(CrestFactor.measure(&[], 48000), None);
+    }
+}
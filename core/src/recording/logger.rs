@@ -0,0 +1,427 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::replay::{rate_sidecar_path, write_rate_sidecar};
+
+const BYTES_PER_SAMPLE: u64 = 4; // 32-bit float in both formats
+const WAV_HEADER_BYTES: u64 = 44;
+
+/// Seconds of samples between flushes to disk. A WAV header only counts
+/// the samples written as of its last flush, so a crash or power cut loses
+/// at most this much of an unattended log rather than the whole file.
+const FLUSH_SECONDS: u64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    /// 32-bit float mono WAV
+    Wav,
+    /// Headerless little-endian f32 samples, with the sample rate in a
+    /// `.rate` sidecar
+    RawF32,
+}
+
+impl LogFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LogFormat::Wav => "wav",
+            LogFormat::RawF32 => "f32",
+        }
+    }
+
+    fn header_bytes(&self) -> u64 {
+        match self {
+            LogFormat::Wav => WAV_HEADER_BYTES,
+            LogFormat::RawF32 => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LoggerConfig {
+    pub directory: PathBuf,
+    pub prefix: String,
+    pub format: LogFormat,
+    pub sample_rate: u32,
+    pub max_file_bytes: u64, // rotate to a new file once this size is reached
+    pub max_files: usize,    // oldest files are deleted beyond this count (0 = keep all)
+}
+
+impl LoggerConfig {
+    pub fn new(directory: impl Into<PathBuf>, sample_rate: u32) -> Self {
+        LoggerConfig {
+            directory: directory.into(),
+            prefix: "ozeecubed".to_string(),
+            format: LogFormat::Wav,
+            sample_rate,
+            max_file_bytes: 64 * 1024 * 1024, // 64 MiB ≈ 5.8 minutes at 48kHz
+            max_files: 16,
+        }
+    }
+
+    /// Number of samples that fit in a single file under the size cap
+    pub fn samples_per_file(&self) -> u64 {
+        (self
+            .max_file_bytes
+            .saturating_sub(self.format.header_bytes())
+            / BYTES_PER_SAMPLE)
+            .max(1)
+    }
+}
+
+enum LogWriter {
+    Wav(hound::WavWriter<BufWriter<File>>),
+    Raw(BufWriter<File>),
+}
+
+impl LogWriter {
+    fn write(&mut self, samples: &[f32]) -> Result<(), String> {
+        match self {
+            LogWriter::Wav(writer) => {
+                for &sample in samples {
+                    writer
+                        .write_sample(sample)
+                        .map_err(|e| format!("Failed to write WAV sample: {e}"))?;
+                }
+            }
+            LogWriter::Raw(writer) => {
+                for &sample in samples {
+                    writer
+                        .write_all(&sample.to_le_bytes())
+                        .map_err(|e| format!("Failed to write raw sample: {e}"))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write out what's buffered, with a WAV header that counts it
+    fn flush(&mut self) -> Result<(), String> {
+        match self {
+            LogWriter::Wav(writer) => writer
+                .flush()
+                .map_err(|e| format!("Failed to flush WAV file: {e}")),
+            LogWriter::Raw(writer) => writer
+                .flush()
+                .map_err(|e| format!("Failed to flush raw file: {e}")),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self {
+            LogWriter::Wav(writer) => writer
+                .finalize()
+                .map_err(|e| format!("Failed to finalize WAV file: {e}")),
+            LogWriter::Raw(mut writer) => writer
+                .flush()
+                .map_err(|e| format!("Failed to flush raw file: {e}")),
+        }
+    }
+}
+
+/// Continuously writes incoming samples to a rotating set of files on disk.
+///
+/// The logger is fed the raw capture stream, so it records everything regardless
+/// of time base, trigger or what is currently on screen.
+pub struct DataLogger {
+    config: LoggerConfig,
+    session: u64,
+    writer: Option<LogWriter>,
+    samples_in_file: u64,
+    /// Samples written since the file was last flushed
+    samples_unflushed: u64,
+    file_index: usize,
+    files: VecDeque<PathBuf>,
+    total_samples: u64,
+}
+
+impl DataLogger {
+    pub fn new(config: LoggerConfig) -> Result<Self, String> {
+        fs::create_dir_all(&config.directory).map_err(|e| {
+            format!(
+                "Failed to create log directory {}: {e}",
+                config.directory.display()
+            )
+        })?;
+
+        // Session timestamp keeps consecutive runs from overwriting each other
+        let session = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Ok(DataLogger {
+            config,
+            session,
+            writer: None,
+            samples_in_file: 0,
+            samples_unflushed: 0,
+            file_index: 0,
+            files: VecDeque::new(),
+            total_samples: 0,
+        })
+    }
+
+    pub fn write_samples(&mut self, samples: &[f32]) -> Result<(), String> {
+        let samples_per_file = self.config.samples_per_file();
+        let mut remaining = samples;
+
+        while !remaining.is_empty() {
+            if self.writer.is_none() {
+                self.open_next_file()?;
+            }
+
+            let space = (samples_per_file - self.samples_in_file) as usize;
+            let (chunk, rest) = remaining.split_at(space.min(remaining.len()));
+
+            if let Some(writer) = self.writer.as_mut() {
+                writer.write(chunk)?;
+            }
+            self.samples_in_file += chunk.len() as u64;
+            self.samples_unflushed += chunk.len() as u64;
+            self.total_samples += chunk.len() as u64;
+            remaining = rest;
+
+            if self.samples_in_file >= samples_per_file {
+                self.close_current_file()?;
+            }
+        }
+
+        if self.samples_unflushed >= FLUSH_SECONDS * self.config.sample_rate as u64 {
+            if let Some(writer) = self.writer.as_mut() {
+                writer.flush()?;
+            }
+            self.samples_unflushed = 0;
+        }
+
+        Ok(())
+    }
+
+    /// Flush and close the file currently being written
+    pub fn finish(mut self) -> Result<(), String> {
+        self.close_current_file()
+    }
+
+    pub fn config(&self) -> &LoggerConfig {
+        &self.config
+    }
+
+    /// Files currently kept on disk, oldest first
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(PathBuf::as_path)
+    }
+
+    /// The file still being written, which isn't finished until it's
+    /// closed
+    pub fn current_file(&self) -> Option<&Path> {
+        self.writer
+            .as_ref()
+            .and(self.files.back().map(PathBuf::as_path))
+    }
+
+    pub fn total_samples(&self) -> u64 {
+        self.total_samples
+    }
+
+    pub fn recorded_seconds(&self) -> f32 {
+        self.total_samples as f32 / self.config.sample_rate as f32
+    }
+
+    fn open_next_file(&mut self) -> Result<(), String> {
+        let path = self.config.directory.join(format!(
+            "{}_{}_{:04}.{}",
+            self.config.prefix,
+            self.session,
+            self.file_index,
+            self.config.format.extension()
+        ));
+        self.file_index += 1;
+
+        let file = File::create(&path)
+            .map_err(|e| format!("Failed to create log file {}: {e}", path.display()))?;
+        let file = BufWriter::new(file);
+
+        let writer = match self.config.format {
+            LogFormat::Wav => {
+                let spec = hound::WavSpec {
+                    channels: 1,
+                    sample_rate: self.config.sample_rate,
+                    bits_per_sample: 32,
+                    sample_format: hound::SampleFormat::Float,
+                };
+                LogWriter::Wav(
+                    hound::WavWriter::new(file, spec)
+                        .map_err(|e| format!("Failed to write WAV header: {e}"))?,
+                )
+            }
+            LogFormat::RawF32 => {
+                write_rate_sidecar(&path, self.config.sample_rate)?;
+                LogWriter::Raw(file)
+            }
+        };
+
+        self.writer = Some(writer);
+        self.samples_in_file = 0;
+        self.files.push_back(path);
+        self.prune_old_files();
+
+        Ok(())
+    }
+
+    fn close_current_file(&mut self) -> Result<(), String> {
+        self.samples_unflushed = 0;
+        match self.writer.take() {
+            Some(writer) => writer.finish(),
+            None => Ok(()),
+        }
+    }
+
+    fn prune_old_files(&mut self) {
+        if self.config.max_files == 0 {
+            return;
+        }
+
+        while self.files.len() > self.config.max_files {
+            if let Some(oldest) = self.files.pop_front() {
+                if let Err(e) = fs::remove_file(&oldest) {
+                    eprintln!("Failed to remove old log file {}: {e}", oldest.display());
+                }
+                if self.config.format == LogFormat::RawF32 {
+                    let _ = fs::remove_file(rate_sidecar_path(&oldest));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for DataLogger {
+    fn drop(&mut self) {
+        if let Err(e) = self.close_current_file() {
+            eprintln!("{e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ozeecubed_logger_{name}"));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_samples_per_file() {
+        let mut config = LoggerConfig::new("logs", 48000);
+        config.max_file_bytes = 44 + 400;
+        assert_eq!(config.samples_per_file(), 100);
+
+        config.format = LogFormat::RawF32;
+        assert_eq!(config.samples_per_file(), 111);
+    }
+
+    #[test]
+    fn test_wav_round_trip() {
+        let dir = test_dir("wav");
+        let logger_config = LoggerConfig::new(&dir, 48000);
+        let mut logger = DataLogger::new(logger_config).unwrap();
+
+        let samples: Vec<f32> = (0..1000).map(|i| (i as f32 / 100.0).sin()).collect();
+        logger.write_samples(&samples).unwrap();
+        let path = logger.files().next().unwrap().to_path_buf();
+        assert_eq!(logger.current_file(), Some(path.as_path()));
+        logger.finish().unwrap();
+
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec().sample_rate, 48000);
+        let read: Vec<f32> = reader.samples::<f32>().map(|s| s.unwrap()).collect();
+        assert_eq!(read, samples);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_wav_is_readable_while_logging() {
+        let dir = test_dir("wav_flush");
+        let mut logger = DataLogger::new(LoggerConfig::new(&dir, 1000)).unwrap();
+        let path = |logger: &DataLogger| logger.current_file().unwrap().to_path_buf();
+
+        // As if the power went out once FLUSH_SECONDS had been logged
+        logger.write_samples(&[0.25; 1000]).unwrap();
+        logger.write_samples(&[0.25; 4000]).unwrap();
+        let mut reader = hound::WavReader::open(path(&logger)).unwrap();
+        assert_eq!(reader.spec().sample_rate, 1000);
+        let read: Vec<f32> = reader.samples::<f32>().map(|s| s.unwrap()).collect();
+        assert_eq!(read, vec![0.25; 5000]);
+
+        drop(logger);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_raw_rotation() {
+        let dir = test_dir("rotation");
+        let mut config = LoggerConfig::new(&dir, 48000);
+        config.format = LogFormat::RawF32;
+        config.max_file_bytes = 400; // 100 samples per file
+        config.max_files = 0;
+
+        let mut logger = DataLogger::new(config).unwrap();
+        logger.write_samples(&[0.5; 250]).unwrap();
+        assert_eq!(logger.files().count(), 3);
+        assert_eq!(logger.current_file(), logger.files().last());
+        assert_eq!(logger.total_samples(), 250);
+
+        let sizes: Vec<u64> = logger
+            .files()
+            .map(|p| fs::metadata(p).unwrap().len())
+            .collect();
+        logger.finish().unwrap();
+        assert_eq!(&sizes[..2], &[400, 400]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_raw_log_replays_at_its_rate() {
+        let dir = test_dir("raw_rate");
+        let mut config = LoggerConfig::new(&dir, 44100);
+        config.format = LogFormat::RawF32;
+
+        let mut logger = DataLogger::new(config).unwrap();
+        logger.write_samples(&[0.25, -0.5, 1.0]).unwrap();
+        let path = logger.files().next().unwrap().to_path_buf();
+        logger.finish().unwrap();
+
+        let recording = crate::recording::Recording::load(&path).unwrap();
+        assert_eq!(recording.sample_rate, 44100);
+        assert_eq!(recording.samples, vec![0.25, -0.5, 1.0]);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_max_files_prunes_oldest() {
+        let dir = test_dir("prune");
+        let mut config = LoggerConfig::new(&dir, 48000);
+        config.format = LogFormat::RawF32;
+        config.max_file_bytes = 40; // 10 samples per file
+        config.max_files = 2;
+
+        let mut logger = DataLogger::new(config).unwrap();
+        logger.write_samples(&[0.1; 55]).unwrap();
+
+        let kept: Vec<PathBuf> = logger.files().map(Path::to_path_buf).collect();
+        assert_eq!(kept.len(), 2);
+        // Each kept file with its rate sidecar
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 4);
+        assert!(kept[1].to_string_lossy().ends_with("_0005.f32"));
+
+        drop(logger);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
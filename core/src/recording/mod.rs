@@ -0,0 +1,5 @@
+pub mod logger;
+pub mod replay;
+
+pub use logger::{DataLogger, LogFormat, LoggerConfig};
+pub use replay::{Recording, ReplayPlayer};
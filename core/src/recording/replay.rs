@@ -0,0 +1,372 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// Sample rate assumed for headerless raw f32 files without a rate sidecar
+pub const DEFAULT_RAW_SAMPLE_RATE: u32 = 48000;
+
+/// The sidecar next to a raw f32 file that records its sample rate, e.g.
+/// `capture.f32.rate` holding `sample_rate = 44100`
+pub fn rate_sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".rate");
+    PathBuf::from(name)
+}
+
+/// Write the sample rate sidecar for the raw f32 file at `path`
+pub fn write_rate_sidecar(path: &Path, sample_rate: u32) -> Result<(), String> {
+    let sidecar = rate_sidecar_path(path);
+    fs::write(&sidecar, format!("sample_rate = {sample_rate}\n"))
+        .map_err(|e| format!("Failed to write {}: {e}", sidecar.display()))
+}
+
+/// The sample rate recorded in the sidecar for `path`, if there is a
+/// readable one
+fn read_rate_sidecar(path: &Path) -> Option<u32> {
+    let text = fs::read_to_string(rate_sidecar_path(path)).ok()?;
+    text.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "sample_rate")
+            .then(|| value.trim().parse().ok())
+            .flatten()
+            .filter(|&rate| rate > 0)
+    })
+}
+
+const MIN_SPEED: f32 = 0.05;
+const MAX_SPEED: f32 = 16.0;
+
+/// A mono recording held in memory
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+impl Recording {
+    pub fn new(samples: Vec<f32>, sample_rate: u32) -> Self {
+        Recording {
+            samples,
+            sample_rate,
+        }
+    }
+
+    /// Load a recording, choosing the decoder from the file extension. Raw
+    /// f32 files take their rate from a sidecar if they have one.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("wav") => Self::load_wav(path),
+            Some(ext) if ext.eq_ignore_ascii_case("f32") || ext.eq_ignore_ascii_case("raw") => {
+                let sample_rate = read_rate_sidecar(path).unwrap_or(DEFAULT_RAW_SAMPLE_RATE);
+                Self::load_raw_f32(path, sample_rate)
+            }
+            _ => Err(format!("Unsupported recording format: {}", path.display())),
+        }
+    }
+
+    pub fn load_wav(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let mut reader = hound::WavReader::open(path)
+            .map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+        let spec = reader.spec();
+
+        let interleaved: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Float => reader
+                .samples::<f32>()
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("Failed to read samples: {e}"))?,
+            hound::SampleFormat::Int => {
+                let scale = 1.0 / (1_i64 << (spec.bits_per_sample - 1)) as f32;
+                reader
+                    .samples::<i32>()
+                    .map(|s| s.map(|v| v as f32 * scale))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| format!("Failed to read samples: {e}"))?
+            }
+        };
+
+        // Mix down to mono by averaging channels, same as live capture
+        let channels = spec.channels.max(1) as usize;
+        let samples = interleaved
+            .chunks(channels)
+            .map(|chunk| chunk.iter().sum::<f32>() / chunk.len() as f32)
+            .collect();
+
+        Ok(Recording::new(samples, spec.sample_rate))
+    }
+
+    pub fn load_raw_f32(path: impl AsRef<Path>, sample_rate: u32) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes =
+            fs::read(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+
+        let samples = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+
+        Ok(Recording::new(samples, sample_rate))
+    }
+
+    pub fn duration_seconds(&self) -> f32 {
+        self.samples.len() as f32 / self.sample_rate as f32
+    }
+
+    /// Encode as a 32-bit float mono WAV file in memory, the same format the
+    /// data logger writes
+    pub fn to_wav_bytes(&self) -> Result<Vec<u8>, String> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: self.sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut bytes = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut bytes, spec)
+            .map_err(|e| format!("Failed to write WAV header: {e}"))?;
+        for &sample in &self.samples {
+            writer
+                .write_sample(sample)
+                .map_err(|e| format!("Failed to write WAV samples: {e}"))?;
+        }
+        writer
+            .finalize()
+            .map_err(|e| format!("Failed to finish WAV: {e}"))?;
+        Ok(bytes.into_inner())
+    }
+}
+
+/// Plays a recording back as if it were a live source.
+///
+/// The caller advances the player by wall-clock time and reads the window of
+/// samples ending at the play head, which then goes through the usual
+/// trigger/measurement path.
+pub struct ReplayPlayer {
+    recording: Recording,
+    position: f64, // play head in samples
+    playing: bool,
+    speed: f32,
+}
+
+impl ReplayPlayer {
+    pub fn new(recording: Recording) -> Self {
+        ReplayPlayer {
+            recording,
+            position: 0.0,
+            playing: true,
+            speed: 1.0,
+        }
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn toggle_playing(&mut self) {
+        // Restart from the beginning if play is pressed at the end
+        if !self.playing && self.is_at_end() {
+            self.position = 0.0;
+        }
+        self.playing = !self.playing;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    /// Move the play head forward by `elapsed_seconds` of wall-clock time.
+    /// Returns the number of samples the play head moved.
+    pub fn advance(&mut self, elapsed_seconds: f32) -> usize {
+        if !self.playing {
+            return 0;
+        }
+
+        let before = self.position_samples();
+        let step = elapsed_seconds as f64 * self.speed as f64 * self.recording.sample_rate as f64;
+        self.position = (self.position + step).min(self.recording.samples.len() as f64);
+
+        if self.is_at_end() {
+            self.playing = false;
+        }
+
+        self.position_samples() - before
+    }
+
+    /// Step the play head by a signed number of samples (for frame stepping)
+    pub fn step(&mut self, samples: isize) {
+        let target = self.position as isize + samples;
+        self.position = target.clamp(0, self.recording.samples.len() as isize) as f64;
+    }
+
+    /// Seek to a fraction (0.0 to 1.0) of the recording
+    pub fn seek(&mut self, fraction: f32) {
+        self.position = fraction.clamp(0.0, 1.0) as f64 * self.recording.samples.len() as f64;
+    }
+
+    pub fn position_samples(&self) -> usize {
+        self.position as usize
+    }
+
+    pub fn position_fraction(&self) -> f32 {
+        if self.recording.samples.is_empty() {
+            0.0
+        } else {
+            (self.position / self.recording.samples.len() as f64) as f32
+        }
+    }
+
+    pub fn position_seconds(&self) -> f32 {
+        (self.position / self.recording.sample_rate as f64) as f32
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.position_samples() >= self.recording.samples.len()
+    }
+
+    /// The `len` samples leading up to the play head
+    pub fn window(&self, len: usize) -> &[f32] {
+        let end = self.position_samples().min(self.recording.samples.len());
+        let start = end.saturating_sub(len);
+        &self.recording.samples[start..end]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp_player(len: usize) -> ReplayPlayer {
+        let samples = (0..len).map(|i| i as f32).collect();
+        ReplayPlayer::new(Recording::new(samples, 1000))
+    }
+
+    #[test]
+    fn test_advance_respects_speed() {
+        let mut player = ramp_player(10000);
+        assert_eq!(player.advance(0.5), 500);
+
+        player.set_speed(2.0);
+        assert_eq!(player.advance(0.5), 1000);
+        assert_eq!(player.position_samples(), 1500);
+    }
+
+    #[test]
+    fn test_advance_paused() {
+        let mut player = ramp_player(10000);
+        player.pause();
+        assert_eq!(player.advance(1.0), 0);
+        assert_eq!(player.position_samples(), 0);
+    }
+
+    #[test]
+    fn test_stops_at_end() {
+        let mut player = ramp_player(1000);
+        player.advance(5.0);
+        assert!(player.is_at_end());
+        assert!(!player.is_playing());
+
+        // Pressing play at the end starts over
+        player.toggle_playing();
+        assert!(player.is_playing());
+        assert_eq!(player.position_samples(), 0);
+    }
+
+    #[test]
+    fn test_seek_and_window() {
+        let mut player = ramp_player(1000);
+        player.seek(0.5);
+        assert_eq!(player.position_samples(), 500);
+        assert!((player.position_fraction() - 0.5).abs() < 1e-6);
+
+        let window = player.window(3);
+        assert_eq!(window, &[497.0, 498.0, 499.0]);
+
+        player.seek(0.0);
+        assert!(player.window(3).is_empty());
+    }
+
+    #[test]
+    fn test_step_clamps() {
+        let mut player = ramp_player(100);
+        player.step(-10);
+        assert_eq!(player.position_samples(), 0);
+
+        player.step(150);
+        assert_eq!(player.position_samples(), 100);
+    }
+
+    #[test]
+    fn test_set_speed_clamping() {
+        let mut player = ramp_player(100);
+        player.set_speed(100.0);
+        assert_eq!(player.speed(), 16.0);
+
+        player.set_speed(0.0);
+        assert_eq!(player.speed(), 0.05);
+    }
+
+    #[test]
+    fn test_load_wav_mixes_to_mono() {
+        let path = std::env::temp_dir().join("ozeecubed_replay_stereo.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for _ in 0..10 {
+            writer.write_sample(i16::MAX).unwrap();
+            writer.write_sample(0_i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let recording = Recording::load(&path).unwrap();
+        assert_eq!(recording.sample_rate, 44100);
+        assert_eq!(recording.samples.len(), 10);
+        assert!((recording.samples[0] - 0.5).abs() < 0.001);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_wav_bytes_round_trip() {
+        let recording = Recording::new(vec![0.25, -0.5, 1.0], 8000);
+        let bytes = recording.to_wav_bytes().unwrap();
+
+        let mut reader = hound::WavReader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.spec().sample_rate, 8000);
+        assert_eq!(reader.spec().channels, 1);
+        let samples: Vec<f32> = reader.samples::<f32>().map(Result::unwrap).collect();
+        assert_eq!(samples, vec![0.25, -0.5, 1.0]);
+    }
+
+    #[test]
+    fn test_load_raw_f32() {
+        let path = std::env::temp_dir().join("ozeecubed_replay_raw.f32");
+        let bytes: Vec<u8> = [0.25_f32, -0.5, 1.0]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        fs::write(&path, bytes).unwrap();
+
+        let recording = Recording::load(&path).unwrap();
+        assert_eq!(recording.sample_rate, DEFAULT_RAW_SAMPLE_RATE);
+        assert_eq!(recording.samples, vec![0.25, -0.5, 1.0]);
+
+        let _ = fs::remove_file(&path);
+    }
+}
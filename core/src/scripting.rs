@@ -0,0 +1,305 @@
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST, FLOAT, INT};
+
+use crate::oscilloscope::Measurements;
+
+/// Operations one call into a script may run before it's stopped, so a
+/// runaway loop can't hang the frontend
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Something a script asked the frontend to do
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptAction {
+    /// Seconds per division
+    SetTimePerDivision(f32),
+    /// Volts per division
+    SetVoltsPerDivision(f32),
+    SetTriggerEnabled(bool),
+    SetTriggerLevel(f32),
+    /// Run the acquisition again after a stop
+    Start,
+    /// Freeze the display on the current capture
+    Stop,
+    /// Write the capture buffer as a WAV file
+    SaveWav(PathBuf),
+    /// Save the scope window as a PNG
+    Screenshot(PathBuf),
+}
+
+/// What a script sees of the scope on each frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScriptFrame {
+    pub measurements: Measurements,
+    /// Seconds since the script started
+    pub time: f32,
+    pub time_per_division: f32,
+    pub volts_per_division: f32,
+    pub trigger_enabled: bool,
+    pub trigger_level: f32,
+    pub running: bool,
+}
+
+impl ScriptFrame {
+    /// The `m` map handed to `on_frame`. Measurements the scope couldn't
+    /// take are `()`; `m.vpp ?? 0.0` gives a default.
+    fn to_map(self) -> Map {
+        let measurement = |value: Option<f32>| value.map_or(Dynamic::UNIT, |v| (v as FLOAT).into());
+        let m = &self.measurements;
+        let mut map = Map::new();
+        map.insert("frequency".into(), measurement(m.frequency));
+        map.insert("vpp".into(), measurement(m.peak_to_peak));
+        map.insert("rms".into(), measurement(m.rms));
+        map.insert("duty".into(), measurement(m.duty_cycle));
+        map.insert("time".into(), (self.time as FLOAT).into());
+        map.insert("time_div".into(), (self.time_per_division as FLOAT).into());
+        map.insert(
+            "volts_div".into(),
+            (self.volts_per_division as FLOAT).into(),
+        );
+        map.insert("trigger".into(), self.trigger_enabled.into());
+        map.insert("trigger_level".into(), (self.trigger_level as FLOAT).into());
+        map.insert("running".into(), self.running.into());
+        map
+    }
+}
+
+type ActionQueue = Rc<RefCell<Vec<ScriptAction>>>;
+
+/// A Rhai script automating the scope.
+///
+/// The top level runs once when the script is loaded. After that the
+/// frontend calls `on_frame(m)` with each frame's measurements, and the
+/// script answers with calls like `set_time_div(0.001)`, `stop()` or
+/// `save_wav("loud.wav")`, which come back as `ScriptAction`s. An optional
+/// `init()` returns the value `this` starts as inside `on_frame`, for
+/// state kept between frames; without it `this` is an empty map.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    /// `this` inside `on_frame`
+    state: Dynamic,
+    actions: ActionQueue,
+    has_on_frame: bool,
+}
+
+impl Script {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        Self::compile(&source)
+    }
+
+    /// Compile `source` and run its top level
+    pub fn compile(source: &str) -> Result<Self, String> {
+        let actions = ActionQueue::default();
+        let engine = engine(&actions);
+        let ast = engine
+            .compile(source)
+            .map_err(|e| format!("Script error: {e}"))?;
+        engine
+            .run_ast(&ast)
+            .map_err(|e| format!("Script error: {e}"))?;
+
+        let defines = |name: &str, params: usize| {
+            ast.iter_functions()
+                .any(|f| f.name == name && f.params.len() == params)
+        };
+        let has_on_frame = defines("on_frame", 1);
+        let state = if defines("init", 0) {
+            engine
+                .call_fn::<Dynamic>(&mut Scope::new(), &ast, "init", ())
+                .map_err(|e| format!("Script error in init: {e}"))?
+        } else {
+            Map::new().into()
+        };
+
+        Ok(Self {
+            engine,
+            ast,
+            state,
+            actions,
+            has_on_frame,
+        })
+    }
+
+    /// Run `on_frame` on this frame and take everything the script has
+    /// asked for since the last call
+    pub fn on_frame(&mut self, frame: &ScriptFrame) -> Result<Vec<ScriptAction>, String> {
+        if self.has_on_frame {
+            let options = CallFnOptions::new()
+                .eval_ast(false)
+                .bind_this_ptr(&mut self.state);
+            let result = self.engine.call_fn_with_options::<Dynamic>(
+                options,
+                &mut Scope::new(),
+                &self.ast,
+                "on_frame",
+                (frame.to_map(),),
+            );
+            if let Err(e) = result {
+                // Don't act on half a frame
+                self.actions.borrow_mut().clear();
+                return Err(format!("Script error in on_frame: {e}"));
+            }
+        }
+        Ok(self.actions.take())
+    }
+}
+
+/// An engine whose scope functions queue actions on `actions`
+fn engine(actions: &ActionQueue) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    register_setter(
+        &mut engine,
+        "set_time_div",
+        actions,
+        ScriptAction::SetTimePerDivision,
+    );
+    register_setter(
+        &mut engine,
+        "set_volts_div",
+        actions,
+        ScriptAction::SetVoltsPerDivision,
+    );
+    register_setter(
+        &mut engine,
+        "set_trigger_level",
+        actions,
+        ScriptAction::SetTriggerLevel,
+    );
+
+    let queue = actions.clone();
+    engine.register_fn("set_trigger", move |enabled: bool| {
+        queue
+            .borrow_mut()
+            .push(ScriptAction::SetTriggerEnabled(enabled))
+    });
+    let queue = actions.clone();
+    engine.register_fn("start", move || {
+        queue.borrow_mut().push(ScriptAction::Start)
+    });
+    let queue = actions.clone();
+    engine.register_fn("stop", move || queue.borrow_mut().push(ScriptAction::Stop));
+    let queue = actions.clone();
+    engine.register_fn("save_wav", move |path: &str| {
+        queue.borrow_mut().push(ScriptAction::SaveWav(path.into()))
+    });
+    let queue = actions.clone();
+    engine.register_fn("screenshot", move |path: &str| {
+        queue
+            .borrow_mut()
+            .push(ScriptAction::Screenshot(path.into()))
+    });
+    engine
+}
+
+/// Register `name(value)` for both float and integer arguments, since Rhai
+/// won't pass `1` where it wants `1.0`
+fn register_setter(
+    engine: &mut Engine,
+    name: &str,
+    actions: &ActionQueue,
+    action: fn(f32) -> ScriptAction,
+) {
+    let queue = actions.clone();
+    engine.register_fn(name, move |value: FLOAT| {
+        queue.borrow_mut().push(action(value as f32))
+    });
+    let queue = actions.clone();
+    engine.register_fn(name, move |value: INT| {
+        queue.borrow_mut().push(action(value as f32))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(peak_to_peak: Option<f32>) -> ScriptFrame {
+        ScriptFrame {
+            measurements: Measurements {
+                peak_to_peak,
+                ..Measurements::default()
+            },
+            running: true,
+            ..ScriptFrame::default()
+        }
+    }
+
+    #[test]
+    fn test_actions_on_measurement() {
+        let mut script = Script::compile(
+            r#"
+            set_volts_div(1);
+            fn on_frame(m) {
+                if (m.vpp ?? 0.0) > 1.0 {
+                    save_wav("loud.wav");
+                    screenshot("loud.png");
+                    stop();
+                }
+            }
+            "#,
+        )
+        .unwrap();
+
+        // Top-level calls arrive with the first frame
+        assert_eq!(
+            script.on_frame(&frame(None)).unwrap(),
+            vec![ScriptAction::SetVoltsPerDivision(1.0)]
+        );
+        assert!(script.on_frame(&frame(Some(0.5))).unwrap().is_empty());
+        assert_eq!(
+            script.on_frame(&frame(Some(1.5))).unwrap(),
+            vec![
+                ScriptAction::SaveWav("loud.wav".into()),
+                ScriptAction::Screenshot("loud.png".into()),
+                ScriptAction::Stop,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_state_between_frames() {
+        let mut script = Script::compile(
+            r#"
+            fn init() { #{ frames: 0 } }
+            fn on_frame(m) {
+                this.frames += 1;
+                if this.frames == 3 { set_trigger(false); }
+            }
+            "#,
+        )
+        .unwrap();
+
+        assert!(script.on_frame(&frame(None)).unwrap().is_empty());
+        assert!(script.on_frame(&frame(None)).unwrap().is_empty());
+        assert_eq!(
+            script.on_frame(&frame(None)).unwrap(),
+            vec![ScriptAction::SetTriggerEnabled(false)]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(Script::compile("fn on_frame(m) {").is_err());
+
+        let mut script = Script::compile("fn on_frame(m) { stop(); m.vpp * 2.0 }").unwrap();
+        // A missing measurement is `()`, which has no arithmetic, and the
+        // stop queued before the error is dropped
+        assert!(script.on_frame(&frame(None)).is_err());
+        assert_eq!(
+            script.on_frame(&frame(Some(2.0))).unwrap(),
+            vec![ScriptAction::Stop]
+        );
+
+        let mut script = Script::compile("fn on_frame(m) { loop {} }").unwrap();
+        assert!(script.on_frame(&frame(None)).is_err());
+    }
+}
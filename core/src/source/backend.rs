@@ -0,0 +1,323 @@
+//! Instrument-grade sources, which read in volts and have front-end controls
+//! a soundcard lacks: a vertical range, AC or DC coupling and sometimes a
+//! trigger evaluated in hardware.
+//!
+//! A `ScopeBackend` is also a `SignalSource`, so `Acquisition::start_source`
+//! triggers, measures and draws it like any other input. It's set up before
+//! starting, inside the closure that opens it, so the processing and display
+//! code don't need to know which backend is behind the samples. A USB
+//! oscilloscope driver would implement this trait; `InterfaceBackend` does
+//! for audio interfaces calibrated by an `InterfaceProfile`.
+
+use super::{SignalSource, SourceStatus};
+use crate::audio::{AudioCapture, AudioError, InputSelection};
+use crate::dsp::{Biquad, BiquadFilter, BUTTERWORTH_Q};
+use crate::oscilloscope::trigger::TriggerEdge;
+
+/// Corner of the high-pass standing in for AC coupling, in Hz
+const AC_CORNER: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coupling {
+    /// Passes the signal's DC level
+    Dc,
+    /// Blocks the DC level, showing only what changes
+    Ac,
+}
+
+/// A trigger the instrument evaluates itself, before samples reach the
+/// scope
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HardwareTrigger {
+    /// In volts
+    pub level: f32,
+    pub edge: TriggerEdge,
+}
+
+/// What a backend's front end can be set to
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BackendCapabilities {
+    /// Full-scale ranges it switches between, in volts either side of zero
+    pub ranges: Vec<f32>,
+    pub couplings: Vec<Coupling>,
+    pub hardware_trigger: bool,
+    /// Rates it can sample at, highest first
+    pub sample_rates: Vec<u32>,
+}
+
+/// A source whose samples are volts at the probe, with front-end controls
+pub trait ScopeBackend: SignalSource {
+    fn capabilities(&self) -> BackendCapabilities;
+
+    /// Volts either side of zero that full scale stands for
+    fn range(&self) -> f32;
+
+    /// One of `capabilities().ranges`
+    fn set_range(&mut self, volts: f32) -> Result<(), AudioError>;
+
+    fn coupling(&self) -> Coupling;
+
+    fn set_coupling(&mut self, coupling: Coupling) -> Result<(), AudioError>;
+
+    /// Arm `trigger` in hardware, or leave triggering to the scope with
+    /// `None`. A backend triggering in hardware delivers only the samples
+    /// of each capture it triggers. Most can't.
+    fn set_hardware_trigger(&mut self, trigger: Option<HardwareTrigger>) -> Result<(), AudioError> {
+        match trigger {
+            None => Ok(()),
+            Some(_) => Err(AudioError::UnsupportedFormat(
+                "hardware triggering".to_string(),
+            )),
+        }
+    }
+}
+
+/// How an audio interface's input maps to volts, measured once with a known
+/// signal. Only holds at the gain it was measured at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InterfaceProfile {
+    pub name: &'static str,
+    /// Peak volts at digital full scale
+    pub full_scale_volts: f32,
+    /// Whether the input passes DC, as interfaces built for modular synths
+    /// do; most block it
+    pub dc_coupled: bool,
+    /// Rate the interface runs at for the widest bandwidth
+    pub sample_rate: u32,
+}
+
+/// Profiles for common kinds of interface at their usual settings
+pub const INTERFACE_PROFILES: [InterfaceProfile; 3] = [
+    // +20 dBu maximum input level: 7.75 V RMS
+    InterfaceProfile {
+        name: "Pro line input",
+        full_scale_volts: 10.95,
+        dc_coupled: false,
+        sample_rate: 192000,
+    },
+    // 2 V RMS maximum, as on consumer gear
+    InterfaceProfile {
+        name: "Consumer line input",
+        full_scale_volts: 2.83,
+        dc_coupled: false,
+        sample_rate: 96000,
+    },
+    InterfaceProfile {
+        name: "DC-coupled interface",
+        full_scale_volts: 10.0,
+        dc_coupled: true,
+        sample_rate: 96000,
+    },
+];
+
+/// An audio interface as a scope front end: its samples scaled to volts by
+/// a profile, with AC coupling in software when the hardware passes DC.
+/// The range is the one the profile was measured at, and triggering is left
+/// to the scope.
+pub struct InterfaceBackend<S: SignalSource = AudioCapture> {
+    source: S,
+    profile: InterfaceProfile,
+    name: String,
+    coupling: Coupling,
+    /// Mono, left and right blockers, used when AC coupling a DC input
+    blockers: [BiquadFilter; 3],
+}
+
+impl InterfaceBackend {
+    /// Open `device`, the default input if `None`, at the profile's rate
+    pub fn open(device: Option<String>, profile: InterfaceProfile) -> Result<Self, AudioError> {
+        let capture = AudioCapture::open(&InputSelection {
+            device,
+            sample_rate: Some(profile.sample_rate),
+            ..InputSelection::default()
+        })?;
+        Ok(Self::new(capture, profile))
+    }
+}
+
+impl<S: SignalSource> InterfaceBackend<S> {
+    /// Read `source` through `profile`
+    pub fn new(source: S, profile: InterfaceProfile) -> Self {
+        let blocker = Biquad::high_pass(AC_CORNER, BUTTERWORTH_Q, source.sample_rate());
+        Self {
+            name: format!("{} ({})", source.name(), profile.name),
+            coupling: if profile.dc_coupled {
+                Coupling::Dc
+            } else {
+                Coupling::Ac
+            },
+            blockers: [blocker; 3].map(BiquadFilter::new),
+            source,
+            profile,
+        }
+    }
+
+    /// Whether samples go through the software blocker
+    fn blocking(&self) -> bool {
+        self.coupling == Coupling::Ac && self.profile.dc_coupled
+    }
+}
+
+impl<S: SignalSource> SignalSource for InterfaceBackend<S> {
+    fn read_samples(&mut self, max_samples: usize) -> Vec<f32> {
+        let mut samples = self.source.read_samples(max_samples);
+        let blocking = self.blocking();
+        for sample in &mut samples {
+            *sample *= self.profile.full_scale_volts;
+            if blocking {
+                *sample = self.blockers[0].process(*sample);
+            }
+        }
+        samples
+    }
+
+    fn read_stereo(&mut self, max_pairs: usize) -> Vec<(f32, f32)> {
+        let mut pairs = self.source.read_stereo(max_pairs);
+        let blocking = self.blocking();
+        let volts = self.profile.full_scale_volts;
+        let [_, ref mut left, ref mut right] = self.blockers;
+        for (l, r) in &mut pairs {
+            *l *= volts;
+            *r *= volts;
+            if blocking {
+                *l = left.process(*l);
+                *r = right.process(*r);
+            }
+        }
+        pairs
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn status(&self) -> SourceStatus {
+        self.source.status()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<S: SignalSource> ScopeBackend for InterfaceBackend<S> {
+    fn capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities {
+            ranges: vec![self.profile.full_scale_volts],
+            couplings: if self.profile.dc_coupled {
+                vec![Coupling::Dc, Coupling::Ac]
+            } else {
+                vec![Coupling::Ac]
+            },
+            hardware_trigger: false,
+            sample_rates: vec![self.source.sample_rate()],
+        }
+    }
+
+    fn range(&self) -> f32 {
+        self.profile.full_scale_volts
+    }
+
+    /// Fixed by the interface's gain, which the profile was measured at
+    fn set_range(&mut self, volts: f32) -> Result<(), AudioError> {
+        if volts == self.profile.full_scale_volts {
+            Ok(())
+        } else {
+            Err(AudioError::UnsupportedFormat(format!("a {volts} V range")))
+        }
+    }
+
+    fn coupling(&self) -> Coupling {
+        self.coupling
+    }
+
+    fn set_coupling(&mut self, coupling: Coupling) -> Result<(), AudioError> {
+        if coupling == Coupling::Dc && !self.profile.dc_coupled {
+            return Err(AudioError::UnsupportedFormat("DC coupling".to_string()));
+        }
+        if coupling != self.coupling {
+            self.blockers.iter_mut().for_each(BiquadFilter::reset);
+        }
+        self.coupling = coupling;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hands out the same samples at every read
+    struct Steady(Vec<f32>);
+
+    impl SignalSource for Steady {
+        fn read_samples(&mut self, max_samples: usize) -> Vec<f32> {
+            self.0.iter().copied().take(max_samples).collect()
+        }
+
+        fn read_stereo(&mut self, max_pairs: usize) -> Vec<(f32, f32)> {
+            self.0.iter().map(|&s| (s, -s)).take(max_pairs).collect()
+        }
+
+        fn sample_rate(&self) -> u32 {
+            1000
+        }
+
+        fn channels(&self) -> u16 {
+            2
+        }
+
+        fn status(&self) -> SourceStatus {
+            SourceStatus::Running
+        }
+
+        fn name(&self) -> &str {
+            "Steady"
+        }
+    }
+
+    #[test]
+    fn test_samples_read_in_volts() {
+        let profile = INTERFACE_PROFILES[1];
+        let mut backend = InterfaceBackend::new(Steady(vec![0.5, -1.0]), profile);
+        assert_eq!(backend.read_samples(usize::MAX), [1.415, -2.83]);
+        assert_eq!(backend.read_stereo(1), [(1.415, -1.415)]);
+        assert_eq!(backend.name(), "Steady (Consumer line input)");
+
+        // An AC-coupled interface can't be switched to DC, nor its range
+        assert_eq!(backend.coupling(), Coupling::Ac);
+        assert!(backend.set_coupling(Coupling::Dc).is_err());
+        assert!(backend.set_range(1.0).is_err());
+        assert!(backend.set_range(profile.full_scale_volts).is_ok());
+        assert!(backend
+            .set_hardware_trigger(Some(HardwareTrigger {
+                level: 0.0,
+                edge: TriggerEdge::Rising,
+            }))
+            .is_err());
+        assert!(backend.set_hardware_trigger(None).is_ok());
+    }
+
+    #[test]
+    fn test_ac_coupling_blocks_dc() {
+        let profile = INTERFACE_PROFILES[2];
+        let mut backend = InterfaceBackend::new(Steady(vec![0.1; 1000]), profile);
+        assert_eq!(backend.coupling(), Coupling::Dc);
+        assert!(backend.read_samples(usize::MAX).iter().all(|&v| v == 1.0));
+
+        backend.set_coupling(Coupling::Ac).unwrap();
+        // After a few seconds settling, the 1 V level is gone
+        for _ in 0..4 {
+            backend.read_samples(usize::MAX);
+            backend.read_stereo(usize::MAX);
+        }
+        let last = *backend.read_samples(usize::MAX).last().unwrap();
+        assert!(last.abs() < 0.01, "{last} V left");
+        let (left, right) = *backend.read_stereo(usize::MAX).last().unwrap();
+        assert!(left.abs() < 0.01 && right.abs() < 0.01);
+    }
+}
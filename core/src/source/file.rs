@@ -0,0 +1,111 @@
+use super::{Pacer, SignalSource, SourceStatus};
+use crate::recording::Recording;
+
+/// A recording played through once in real time, as if it were coming in
+/// live. For seeking and stepping through one instead, see `ReplayPlayer`.
+#[derive(Debug, Clone)]
+pub struct FileSource {
+    recording: Recording,
+    name: String,
+    pacer: Pacer,
+    position: usize,
+    looping: bool,
+}
+
+impl FileSource {
+    /// Play `recording` under `name`, usually its file name
+    pub fn new(recording: Recording, name: impl Into<String>) -> Self {
+        Self {
+            pacer: Pacer::new(recording.sample_rate),
+            recording,
+            name: name.into(),
+            position: 0,
+            looping: false,
+        }
+    }
+
+    /// Start over at the end rather than ending
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+}
+
+impl SignalSource for FileSource {
+    fn read_samples(&mut self, max_samples: usize) -> Vec<f32> {
+        let samples = &self.recording.samples;
+        let mut due = self.pacer.due(max_samples);
+        let mut read = Vec::with_capacity(due);
+        while due > 0 && !samples.is_empty() {
+            if self.position == samples.len() {
+                if !self.looping {
+                    break;
+                }
+                self.position = 0;
+            }
+            let end = (self.position + due).min(samples.len());
+            read.extend_from_slice(&samples[self.position..end]);
+            due -= end - self.position;
+            self.position = end;
+        }
+        read
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.recording.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn status(&self) -> SourceStatus {
+        if !self.looping && self.position >= self.recording.samples.len() {
+            SourceStatus::Ended
+        } else {
+            SourceStatus::Running
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    fn ramp(len: usize) -> Recording {
+        Recording::new((0..len).map(|i| i as f32).collect(), 10_000)
+    }
+
+    #[test]
+    fn test_plays_through_once() {
+        let mut source = FileSource::new(ramp(100), "ramp.wav");
+        assert_eq!(source.read_samples(usize::MAX), Vec::<f32>::new());
+        assert!(source.status().is_running());
+
+        // 20 ms at 10 kHz is past the end
+        thread::sleep(Duration::from_millis(20));
+        let samples = source.read_samples(usize::MAX);
+        assert_eq!(samples, ramp(100).samples);
+        assert_eq!(source.status(), SourceStatus::Ended);
+        assert_eq!(source.name(), "ramp.wav");
+    }
+
+    #[test]
+    fn test_looping_wraps_around() {
+        let mut source = FileSource::new(ramp(100), "ramp.wav").looping(true);
+        source.read_samples(usize::MAX);
+        thread::sleep(Duration::from_millis(20));
+        let samples = source.read_samples(usize::MAX);
+
+        assert!(samples.len() > 100);
+        assert_eq!(samples[99], 99.0);
+        assert_eq!(samples[100], 0.0);
+        assert!(source.status().is_running());
+    }
+}
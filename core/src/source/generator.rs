@@ -0,0 +1,132 @@
+use std::f64::consts::{FRAC_PI_4, TAU};
+
+use super::{Pacer, SignalSource, SourceStatus};
+
+/// Level of the right channel relative to the left
+const RIGHT_LEVEL: f32 = 0.7;
+
+/// How far the right channel lags the left, in radians
+const RIGHT_LAG: f64 = FRAC_PI_4;
+
+/// A sine test signal in real time, for when there's no input to show.
+///
+/// The right channel is quieter and lags by 45°, so the vectorscope draws
+/// an ellipse leaning left rather than a mono line.
+#[derive(Debug, Clone)]
+pub struct SignalGenerator {
+    frequency: f32,
+    amplitude: f32,
+    sample_rate: u32,
+    pacer: Pacer,
+    /// Index of the next sample, so the sine runs on unbroken across reads
+    position: u64,
+    /// Pairs generated alongside the mono samples, not yet read
+    stereo: Vec<(f32, f32)>,
+}
+
+impl SignalGenerator {
+    /// Name test signal sources go by
+    pub const NAME: &'static str = "Test signal";
+
+    pub fn sine(sample_rate: u32, frequency: f32, amplitude: f32) -> Self {
+        Self {
+            frequency,
+            amplitude,
+            sample_rate,
+            pacer: Pacer::new(sample_rate),
+            position: 0,
+            stereo: Vec::new(),
+        }
+    }
+
+    /// The left and right samples at `index`
+    fn pair(&self, index: u64) -> (f32, f32) {
+        let phase = TAU * self.frequency as f64 * index as f64 / self.sample_rate as f64;
+        (
+            self.amplitude * phase.sin() as f32,
+            self.amplitude * RIGHT_LEVEL * (phase - RIGHT_LAG).sin() as f32,
+        )
+    }
+}
+
+impl SignalSource for SignalGenerator {
+    fn read_samples(&mut self, max_samples: usize) -> Vec<f32> {
+        let count = self.pacer.due(max_samples);
+        let start = self.position;
+        self.position += count as u64;
+
+        let pairs: Vec<(f32, f32)> = (start..self.position)
+            .map(|index| self.pair(index))
+            .collect();
+        // Bounded like a capture ring, should the stereo pairs go unread
+        let excess = (self.stereo.len() + count).saturating_sub(self.sample_rate as usize);
+        self.stereo.drain(..excess.min(self.stereo.len()));
+        self.stereo.extend_from_slice(&pairs);
+
+        pairs.into_iter().map(|(left, _)| left).collect()
+    }
+
+    fn read_stereo(&mut self, max_pairs: usize) -> Vec<(f32, f32)> {
+        let count = max_pairs.min(self.stereo.len());
+        self.stereo.drain(..count).collect()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        2
+    }
+
+    fn status(&self) -> SourceStatus {
+        SourceStatus::Running
+    }
+
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sine_runs_on_across_reads() {
+        let mut generator = SignalGenerator::sine(48000, 1000.0, 0.5);
+        // The first read starts the clock
+        let mut samples = generator.read_samples(usize::MAX);
+        for _ in 0..3 {
+            thread::sleep(Duration::from_millis(5));
+            samples.extend(generator.read_samples(usize::MAX));
+        }
+        assert!(samples.len() >= 3 * 240);
+
+        for (i, &sample) in samples.iter().enumerate() {
+            let expected = 0.5 * (TAU * 1000.0 * i as f64 / 48000.0).sin() as f32;
+            assert!((sample - expected).abs() < 1e-4, "sample {i}");
+        }
+        assert!(samples.iter().all(|s| s.abs() <= 0.5));
+    }
+
+    #[test]
+    fn test_stereo_follows_the_mono_samples() {
+        let mut generator = SignalGenerator::sine(48000, 440.0, 1.0);
+        generator.read_samples(usize::MAX);
+        thread::sleep(Duration::from_millis(5));
+        let mono = generator.read_samples(usize::MAX);
+        let stereo = generator.read_stereo(usize::MAX);
+
+        assert_eq!(mono.len(), stereo.len());
+        assert!(mono.iter().zip(&stereo).all(|(m, (left, _))| m == left));
+        // The right channel is quieter
+        let peak = |values: Vec<f32>| values.into_iter().fold(0.0f32, |a, b| a.max(b.abs()));
+        assert!(
+            peak(stereo.iter().map(|p| p.1).collect()) < peak(stereo.iter().map(|p| p.0).collect())
+        );
+        assert!(generator.read_stereo(usize::MAX).is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+//! Where samples come from.
+//!
+//! Everything the acquisition engine reads goes through `SignalSource`, so
+//! a live input, a file playing in real time, samples streamed from another
+//! machine or a generated test signal are triggered, measured and drawn the
+//! same way, and a frontend switches between them by starting an
+//! `Acquisition` on another source.
+
+use std::time::Instant;
+
+use crate::audio::AudioError;
+
+pub mod backend;
+pub mod file;
+pub mod generator;
+pub mod network;
+#[cfg(feature = "serial")]
+pub mod serial;
+
+pub use backend::{Coupling, InterfaceBackend, ScopeBackend};
+pub use file::FileSource;
+pub use generator::SignalGenerator;
+pub use network::{NetworkSender, NetworkSource};
+#[cfg(feature = "serial")]
+pub use serial::{SerialConfig, SerialSource};
+
+/// Whether a source has more to give
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceStatus {
+    Running,
+    /// Nothing more will come, such as a file played to its end
+    Ended,
+    /// Stopped by an error; nothing more will come
+    Failed(AudioError),
+}
+
+impl SourceStatus {
+    pub fn is_running(&self) -> bool {
+        *self == SourceStatus::Running
+    }
+}
+
+/// Input lost on its way to the reader, counted since the source opened.
+/// Any loss breaks the stream, so measurements spanning it are off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DataLoss {
+    /// Samples of the mono mix that arrived with nowhere to go
+    pub dropped_samples: u64,
+    /// Times input went missing before it arrived, in unknown amounts:
+    /// the device overrunning its buffer, or packets lost on the network
+    pub overruns: u64,
+}
+
+impl DataLoss {
+    pub fn any(&self) -> bool {
+        self.dropped_samples > 0 || self.overruns > 0
+    }
+}
+
+/// A stream of samples arriving in real time
+pub trait SignalSource {
+    /// Up to `max_samples` of the mono mix that arrived since the last
+    /// read, oldest first
+    fn read_samples(&mut self, max_samples: usize) -> Vec<f32>;
+
+    /// The first two channels as (left, right) pairs, up to `max_pairs` of
+    /// those that arrived since the last read. Read alongside
+    /// `read_samples` to keep the two in step. Single-channel sources give
+    /// none, and readers pair the mono samples with themselves.
+    fn read_stereo(&mut self, max_pairs: usize) -> Vec<(f32, f32)> {
+        let _ = max_pairs;
+        Vec::new()
+    }
+
+    fn sample_rate(&self) -> u32;
+
+    fn channels(&self) -> u16;
+
+    fn status(&self) -> SourceStatus;
+
+    /// Input lost so far. Sources that can't lose any report none.
+    fn data_loss(&self) -> DataLoss {
+        DataLoss::default()
+    }
+
+    /// Names the source to the user, and keys per-device settings such as
+    /// calibration
+    fn name(&self) -> &str;
+}
+
+/// Hands out samples no faster than real time, for sources that could
+/// produce them all at once
+#[derive(Debug, Clone)]
+pub(crate) struct Pacer {
+    sample_rate: u32,
+    /// Set by the first read, so a source made early doesn't burst
+    started: Option<Instant>,
+    delivered: u64,
+}
+
+impl Pacer {
+    pub(crate) fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            started: None,
+            delivered: 0,
+        }
+    }
+
+    /// How many samples have come due since the last call, at most `max`
+    pub(crate) fn due(&mut self, max: usize) -> usize {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let elapsed = started.elapsed().as_secs_f64() * self.sample_rate as f64;
+        let due = (elapsed as u64).saturating_sub(self.delivered);
+        let count = due.min(max as u64);
+        self.delivered += count;
+        count as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_pacer_keeps_to_real_time() {
+        let mut pacer = Pacer::new(10_000);
+        assert_eq!(pacer.due(usize::MAX), 0);
+
+        thread::sleep(Duration::from_millis(20));
+        let first = pacer.due(usize::MAX);
+        assert!(first >= 200, "{first} samples after 20 ms");
+
+        // Samples held back by `max` come due on the next call
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(pacer.due(10), 10);
+        assert!(pacer.due(usize::MAX) >= 190);
+    }
+}
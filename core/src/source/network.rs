@@ -0,0 +1,829 @@
+//! Samples streamed from another machine, such as a Raspberry Pi probing
+//! something out of reach.
+//!
+//! Every packet is a 20-byte little-endian header followed by its frames,
+//! interleaved:
+//!
+//! | bytes | field                                     |
+//! |-------|-------------------------------------------|
+//! | 0..4  | `OZS1`                                    |
+//! | 4..8  | sample rate, u32                          |
+//! | 8..10 | channels, u16                             |
+//! | 10    | encoding: 0 for f32, 1 for i16            |
+//! | 11    | reserved, 0                               |
+//! | 12..16| sequence number, u32, one more per packet |
+//! | 16..20| frames in the packet, u32                 |
+//!
+//! Over UDP each datagram is one packet; over TCP packets follow one
+//! another on the stream. A packet carries at most `MAX_PAYLOAD_LEN` bytes
+//! of samples, at no more than `MAX_SAMPLE_RATE`. `NetworkSender` writes them, and
+//! `ozeecubed-cli send` streams the local input with it.
+
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{DataLoss, SignalSource, SourceStatus};
+use crate::audio::AudioError;
+
+/// Starts every packet, so stray traffic is ignored
+pub const PACKET_MAGIC: [u8; 4] = *b"OZS1";
+
+pub const HEADER_LEN: usize = 20;
+
+/// Port the sender and receiver use unless told otherwise
+pub const DEFAULT_NETWORK_PORT: u16 = 7355;
+
+/// Rate reported before the first packet says otherwise
+const DEFAULT_SAMPLE_RATE: u32 = 48000;
+
+/// Largest packet sent over UDP, inside a typical Ethernet MTU
+const MAX_DATAGRAM: usize = 1400;
+
+/// Largest payload a header may announce. Over TCP the receiver buffers a
+/// whole packet before taking it, so a bigger claim drops the sender.
+pub const MAX_PAYLOAD_LEN: usize = 1 << 20;
+
+/// Highest sample rate a header may announce, well past any sound card,
+/// so buffers sized by the rate stay reasonable
+pub const MAX_SAMPLE_RATE: u32 = 1_000_000;
+
+/// Furthest a UDP packet may arrive behind the others and still count as
+/// late. One further back is from a sender that started over, and the
+/// sequence is picked up from it.
+const MAX_LATE_PACKETS: u32 = 64;
+
+/// Seconds of samples held before the oldest are dropped
+const MAX_BUFFERED_SECONDS: u32 = 1;
+
+/// How often `wait_for_sender` looks for the first packet
+const WAIT_POLL: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleEncoding {
+    F32,
+    /// Half the bandwidth, full scale being ±32768
+    I16,
+}
+
+impl SampleEncoding {
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            SampleEncoding::F32 => 4,
+            SampleEncoding::I16 => 2,
+        }
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            SampleEncoding::F32 => 0,
+            SampleEncoding::I16 => 1,
+        }
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(SampleEncoding::F32),
+            1 => Some(SampleEncoding::I16),
+            _ => None,
+        }
+    }
+}
+
+/// What a packet's header says about the frames after it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketHeader {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub encoding: SampleEncoding,
+    pub sequence: u32,
+    pub frames: u32,
+}
+
+impl PacketHeader {
+    /// Bytes of samples following the header
+    pub fn payload_len(&self) -> usize {
+        self.frames as usize * self.channels as usize * self.encoding.bytes_per_sample()
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&PACKET_MAGIC);
+        out.extend_from_slice(&self.sample_rate.to_le_bytes());
+        out.extend_from_slice(&self.channels.to_le_bytes());
+        out.push(self.encoding.code());
+        out.push(0);
+        out.extend_from_slice(&self.sequence.to_le_bytes());
+        out.extend_from_slice(&self.frames.to_le_bytes());
+    }
+
+    /// The header at the start of `bytes`
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_LEN {
+            return Err(format!("Packet of {} bytes is too short", bytes.len()));
+        }
+        if bytes[0..4] != PACKET_MAGIC {
+            return Err("Not an OzeeCubed sample packet".to_string());
+        }
+        let u32_at = |at: usize| {
+            u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+        };
+        let header = PacketHeader {
+            sample_rate: u32_at(4),
+            channels: u16::from_le_bytes([bytes[8], bytes[9]]),
+            encoding: SampleEncoding::from_code(bytes[10])
+                .ok_or_else(|| format!("Unknown sample encoding {}", bytes[10]))?,
+            sequence: u32_at(12),
+            frames: u32_at(16),
+        };
+        if header.sample_rate == 0 || header.channels == 0 {
+            return Err("Packet has no sample rate or channels".to_string());
+        }
+        if header.sample_rate > MAX_SAMPLE_RATE {
+            return Err(format!(
+                "Sample rate of {} Hz is over the {MAX_SAMPLE_RATE} Hz limit",
+                header.sample_rate
+            ));
+        }
+        // In u64 so a hostile frame count can't overflow a 32-bit usize
+        let payload_len = header.frames as u64
+            * header.channels as u64
+            * header.encoding.bytes_per_sample() as u64;
+        if payload_len > MAX_PAYLOAD_LEN as u64 {
+            return Err(format!(
+                "Packet of {payload_len} sample bytes is over the {MAX_PAYLOAD_LEN} byte limit"
+            ));
+        }
+        Ok(header)
+    }
+}
+
+fn network_error(context: &str, e: io::Error) -> AudioError {
+    AudioError::Backend(format!("{context}: {e}"))
+}
+
+enum Outbound {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+/// Streams samples to a `NetworkSource`
+pub struct NetworkSender {
+    outbound: Outbound,
+    sample_rate: u32,
+    channels: u16,
+    encoding: SampleEncoding,
+    sequence: u32,
+    packet: Vec<u8>,
+}
+
+impl NetworkSender {
+    /// Send datagrams to `addr`. Nothing checks that anyone is listening.
+    pub fn udp(
+        addr: impl ToSocketAddrs,
+        sample_rate: u32,
+        channels: u16,
+        encoding: SampleEncoding,
+    ) -> Result<Self, AudioError> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))
+            .map_err(|e| network_error("Failed to open a UDP socket", e))?;
+        socket
+            .connect(addr)
+            .map_err(|e| network_error("Failed to reach the receiver", e))?;
+        Ok(Self::new(
+            Outbound::Udp(socket),
+            sample_rate,
+            channels,
+            encoding,
+        ))
+    }
+
+    /// Connect to a receiver listening on `addr`
+    pub fn tcp(
+        addr: impl ToSocketAddrs,
+        sample_rate: u32,
+        channels: u16,
+        encoding: SampleEncoding,
+    ) -> Result<Self, AudioError> {
+        let stream = TcpStream::connect(addr)
+            .map_err(|e| network_error("Failed to connect to the receiver", e))?;
+        let _ = stream.set_nodelay(true);
+        Ok(Self::new(
+            Outbound::Tcp(stream),
+            sample_rate,
+            channels,
+            encoding,
+        ))
+    }
+
+    fn new(outbound: Outbound, sample_rate: u32, channels: u16, encoding: SampleEncoding) -> Self {
+        Self {
+            outbound,
+            sample_rate,
+            channels: channels.max(1),
+            encoding,
+            sequence: 0,
+            packet: Vec::new(),
+        }
+    }
+
+    /// Send interleaved `samples`, whole frames of `channels` each, as
+    /// however many packets they take
+    pub fn send(&mut self, samples: &[f32]) -> Result<(), AudioError> {
+        let frame_len = self.channels as usize;
+        let frame_bytes = frame_len * self.encoding.bytes_per_sample();
+        let frames_per_packet = match self.outbound {
+            Outbound::Udp(_) => ((MAX_DATAGRAM - HEADER_LEN) / frame_bytes).max(1),
+            Outbound::Tcp(_) => (MAX_PAYLOAD_LEN / frame_bytes).max(1),
+        };
+        let frames = samples.len() / frame_len;
+        for chunk in samples[..frames * frame_len].chunks(frames_per_packet * frame_len) {
+            self.packet.clear();
+            PacketHeader {
+                sample_rate: self.sample_rate,
+                channels: self.channels,
+                encoding: self.encoding,
+                sequence: self.sequence,
+                frames: (chunk.len() / frame_len) as u32,
+            }
+            .write(&mut self.packet);
+            match self.encoding {
+                SampleEncoding::F32 => {
+                    for sample in chunk {
+                        self.packet.extend_from_slice(&sample.to_le_bytes());
+                    }
+                }
+                SampleEncoding::I16 => {
+                    for sample in chunk {
+                        let value = (sample * 32768.0).round().clamp(-32768.0, 32767.0) as i16;
+                        self.packet.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+            }
+            self.sequence = self.sequence.wrapping_add(1);
+
+            let sent = match self.outbound {
+                Outbound::Udp(ref socket) => socket.send(&self.packet).map(|_| ()),
+                Outbound::Tcp(ref mut stream) => stream.write_all(&self.packet),
+            };
+            sent.map_err(|e| network_error("Failed to send samples", e))?;
+        }
+        Ok(())
+    }
+}
+
+enum Inbound {
+    Udp {
+        socket: UdpSocket,
+        datagram: Vec<u8>,
+    },
+    Tcp {
+        listener: TcpListener,
+        /// The sender, one at a time
+        stream: Option<TcpStream>,
+        /// Bytes read past the last whole packet
+        pending: Vec<u8>,
+    },
+}
+
+/// Samples from a `NetworkSender` on another machine, mixed to mono like a
+/// local input. Listens on a UDP port or for one TCP sender at a time; the
+/// rate and channels come from the packets.
+pub struct NetworkSource {
+    inbound: Inbound,
+    name: String,
+    local_addr: SocketAddr,
+    /// Rate and channels of the sender, once it's been heard from
+    format: Option<(u32, u16)>,
+    next_sequence: Option<u32>,
+    lost_packets: u64,
+    /// Samples trimmed while the reader fell behind
+    dropped_samples: u64,
+    mono: VecDeque<f32>,
+    stereo: VecDeque<(f32, f32)>,
+    failure: Option<AudioError>,
+}
+
+impl NetworkSource {
+    /// Take datagrams arriving on `addr`, such as `0.0.0.0:7355`
+    pub fn udp(addr: impl ToSocketAddrs) -> Result<Self, AudioError> {
+        let socket =
+            UdpSocket::bind(addr).map_err(|e| network_error("Failed to listen for UDP", e))?;
+        socket
+            .set_nonblocking(true)
+            .map_err(|e| network_error("Failed to listen for UDP", e))?;
+        let local_addr = socket
+            .local_addr()
+            .map_err(|e| network_error("Failed to listen for UDP", e))?;
+        let inbound = Inbound::Udp {
+            socket,
+            datagram: vec![0; 65536],
+        };
+        Ok(Self::new(
+            inbound,
+            format!("udp://{local_addr}"),
+            local_addr,
+        ))
+    }
+
+    /// Accept a sender connecting to `addr`, and another once it hangs up
+    pub fn tcp(addr: impl ToSocketAddrs) -> Result<Self, AudioError> {
+        let listener =
+            TcpListener::bind(addr).map_err(|e| network_error("Failed to listen for TCP", e))?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|e| network_error("Failed to listen for TCP", e))?;
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| network_error("Failed to listen for TCP", e))?;
+        let inbound = Inbound::Tcp {
+            listener,
+            stream: None,
+            pending: Vec::new(),
+        };
+        Ok(Self::new(
+            inbound,
+            format!("tcp://{local_addr}"),
+            local_addr,
+        ))
+    }
+
+    /// Listen on an input named like `udp://0.0.0.0:7355` or
+    /// `tcp://0.0.0.0`, the port defaulting to `DEFAULT_NETWORK_PORT`
+    pub fn open(url: &str) -> Result<Self, AudioError> {
+        let with_port = |addr: &str| {
+            if addr.contains(':') {
+                addr.to_string()
+            } else {
+                format!("{addr}:{DEFAULT_NETWORK_PORT}")
+            }
+        };
+        if let Some(addr) = url.strip_prefix("udp://") {
+            Self::udp(with_port(addr))
+        } else if let Some(addr) = url.strip_prefix("tcp://") {
+            Self::tcp(with_port(addr))
+        } else {
+            Err(AudioError::DeviceNotFound(url.to_string()))
+        }
+    }
+
+    /// Whether an input name is one for `open` rather than a device
+    pub fn is_url(name: &str) -> bool {
+        name.starts_with("udp://") || name.starts_with("tcp://")
+    }
+
+    fn new(inbound: Inbound, name: String, local_addr: SocketAddr) -> Self {
+        Self {
+            inbound,
+            name,
+            local_addr,
+            format: None,
+            next_sequence: None,
+            lost_packets: 0,
+            dropped_samples: 0,
+            mono: VecDeque::new(),
+            stereo: VecDeque::new(),
+            failure: None,
+        }
+    }
+
+    /// Address it's listening on, with the port picked when bound to 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Block until the first packet, so `sample_rate` and `channels` are
+    /// the sender's, failing if none comes within `timeout`
+    pub fn wait_for_sender(&mut self, timeout: Duration) -> Result<(), AudioError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            self.receive();
+            if let Some(ref failure) = self.failure {
+                return Err(failure.clone());
+            }
+            if self.format.is_some() {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(AudioError::DeviceNotFound(format!(
+                    "no sender on {}",
+                    self.name
+                )));
+            }
+            thread::sleep(WAIT_POLL);
+        }
+    }
+
+    /// Packets that never arrived, going by the gaps in their sequence
+    /// numbers
+    pub fn lost_packets(&self) -> u64 {
+        self.lost_packets
+    }
+
+    /// Take in everything that's arrived, without waiting
+    fn receive(&mut self) {
+        let mut packets = Vec::new();
+        match self.inbound {
+            Inbound::Udp {
+                ref socket,
+                ref mut datagram,
+            } => loop {
+                match socket.recv(datagram) {
+                    Ok(len) => packets.push(datagram[..len].to_vec()),
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    // Such as ICMP errors from an earlier send; keep going
+                    Err(_) => break,
+                }
+            },
+            Inbound::Tcp {
+                ref listener,
+                ref mut stream,
+                ref mut pending,
+            } => {
+                if stream.is_none() {
+                    if let Ok((accepted, _)) = listener.accept() {
+                        if accepted.set_nonblocking(true).is_ok() {
+                            *stream = Some(accepted);
+                            pending.clear();
+                            // A new sender numbers its packets afresh
+                            self.next_sequence = None;
+                        }
+                    }
+                }
+                if let Some(ref mut connection) = stream {
+                    let mut buffer = [0; 16384];
+                    let open = loop {
+                        match connection.read(&mut buffer) {
+                            Ok(0) => break false,
+                            Ok(len) => pending.extend_from_slice(&buffer[..len]),
+                            Err(e) if e.kind() == ErrorKind::WouldBlock => break true,
+                            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                            Err(_) => break false,
+                        }
+                    };
+                    let mut in_step = true;
+                    while pending.len() >= HEADER_LEN {
+                        let Ok(header) = PacketHeader::parse(pending) else {
+                            // Lost track of where packets start, or the
+                            // sender claims more than will be buffered
+                            in_step = false;
+                            break;
+                        };
+                        let len = HEADER_LEN + header.payload_len();
+                        if pending.len() < len {
+                            break;
+                        }
+                        packets.push(pending.drain(..len).collect());
+                    }
+                    if !open || !in_step {
+                        *stream = None;
+                        pending.clear();
+                    }
+                }
+            }
+        }
+        for packet in packets {
+            self.accept(&packet);
+        }
+    }
+
+    /// Unpack one packet into the buffers
+    fn accept(&mut self, packet: &[u8]) {
+        let Ok(header) = PacketHeader::parse(packet) else {
+            return;
+        };
+        let payload = &packet[HEADER_LEN..];
+        if payload.len() < header.payload_len() {
+            return;
+        }
+        match self.format {
+            None => self.format = Some((header.sample_rate, header.channels)),
+            Some(format) if format != (header.sample_rate, header.channels) => {
+                self.failure = Some(AudioError::UnsupportedFormat(format!(
+                    "a change to {} Hz, {} channels mid-stream",
+                    header.sample_rate, header.channels
+                )));
+                return;
+            }
+            Some(_) => {}
+        }
+        if let Some(expected) = self.next_sequence {
+            let ahead = header.sequence.wrapping_sub(expected);
+            let behind = expected.wrapping_sub(header.sequence);
+            if ahead <= u32::MAX / 2 {
+                self.lost_packets += ahead as u64;
+            } else if behind <= MAX_LATE_PACKETS {
+                // A late or repeated packet, already played past
+                return;
+            }
+            // Otherwise the sender restarted, and its count with it
+        }
+        self.next_sequence = Some(header.sequence.wrapping_add(1));
+
+        let channels = header.channels as usize;
+        let samples: Vec<f32> = match header.encoding {
+            SampleEncoding::F32 => payload[..header.payload_len()]
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+            SampleEncoding::I16 => payload[..header.payload_len()]
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+                .collect(),
+        };
+        // Mixed down to mono by averaging channels, same as live capture
+        for frame in samples.chunks_exact(channels) {
+            self.mono
+                .push_back(frame.iter().sum::<f32>() / channels as f32);
+            let left = frame[0];
+            self.stereo
+                .push_back((left, frame.get(1).copied().unwrap_or(left)));
+        }
+
+        let limit = (MAX_BUFFERED_SECONDS * header.sample_rate) as usize;
+        // Bounded like a capture ring, should the reader fall behind
+        let excess = self.mono.len().saturating_sub(limit);
+        self.mono.drain(..excess);
+        self.dropped_samples += excess as u64;
+        let excess = self.stereo.len().saturating_sub(limit);
+        self.stereo.drain(..excess);
+    }
+}
+
+impl SignalSource for NetworkSource {
+    fn read_samples(&mut self, max_samples: usize) -> Vec<f32> {
+        self.receive();
+        let count = max_samples.min(self.mono.len());
+        self.mono.drain(..count).collect()
+    }
+
+    fn read_stereo(&mut self, max_pairs: usize) -> Vec<(f32, f32)> {
+        let count = max_pairs.min(self.stereo.len());
+        self.stereo.drain(..count).collect()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.format.map_or(DEFAULT_SAMPLE_RATE, |(rate, _)| rate)
+    }
+
+    fn channels(&self) -> u16 {
+        self.format.map_or(1, |(_, channels)| channels)
+    }
+
+    fn status(&self) -> SourceStatus {
+        match self.failure {
+            Some(ref failure) => SourceStatus::Failed(failure.clone()),
+            None => SourceStatus::Running,
+        }
+    }
+
+    fn data_loss(&self) -> DataLoss {
+        DataLoss {
+            dropped_samples: self.dropped_samples,
+            overruns: self.lost_packets,
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Read from `source` until `count` samples have come or a second
+    /// has passed
+    fn read(source: &mut NetworkSource, count: usize) -> Vec<f32> {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let mut samples = Vec::new();
+        while samples.len() < count && Instant::now() < deadline {
+            samples.extend(source.read_samples(count - samples.len()));
+            thread::sleep(Duration::from_millis(1));
+        }
+        samples
+    }
+
+    #[test]
+    fn test_header_round_trips() {
+        let header = PacketHeader {
+            sample_rate: 44100,
+            channels: 2,
+            encoding: SampleEncoding::I16,
+            sequence: 7,
+            frames: 128,
+        };
+        let mut bytes = Vec::new();
+        header.write(&mut bytes);
+        assert_eq!(bytes.len(), HEADER_LEN);
+        assert_eq!(PacketHeader::parse(&bytes), Ok(header));
+        assert_eq!(header.payload_len(), 512);
+
+        bytes[0] = b'X';
+        assert!(PacketHeader::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_header_rate_is_capped() {
+        let mut header = PacketHeader {
+            sample_rate: MAX_SAMPLE_RATE,
+            channels: 1,
+            encoding: SampleEncoding::F32,
+            sequence: 0,
+            frames: 1,
+        };
+        let mut bytes = Vec::new();
+        header.write(&mut bytes);
+        assert!(PacketHeader::parse(&bytes).is_ok());
+
+        header.sample_rate = u32::MAX;
+        bytes.clear();
+        header.write(&mut bytes);
+        assert!(PacketHeader::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_oversized_header_drops_the_sender() {
+        let header = PacketHeader {
+            sample_rate: 48000,
+            channels: u16::MAX,
+            encoding: SampleEncoding::F32,
+            sequence: 0,
+            frames: u32::MAX,
+        };
+        let mut bytes = Vec::new();
+        header.write(&mut bytes);
+        assert!(PacketHeader::parse(&bytes).is_err());
+        let mut unknown = bytes.clone();
+        unknown[10] = 9;
+        assert!(PacketHeader::parse(&unknown).is_err());
+
+        let mut source = NetworkSource::tcp("127.0.0.1:0").unwrap();
+        let mut hostile = TcpStream::connect(source.local_addr()).unwrap();
+        hostile.write_all(&bytes).unwrap();
+        hostile.write_all(&[0; 4096]).unwrap();
+        hostile.set_nonblocking(true).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        loop {
+            source.read_samples(usize::MAX);
+            match hostile.read(&mut [0; 1]) {
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                // Closed or reset from the source's end
+                Ok(0) | Err(_) => break,
+                Ok(_) => unreachable!("the source never writes"),
+            }
+            assert!(Instant::now() < deadline, "the sender was never dropped");
+            thread::sleep(Duration::from_millis(1));
+        }
+        let Inbound::Tcp { ref pending, .. } = source.inbound else {
+            unreachable!();
+        };
+        assert!(pending.is_empty());
+
+        // A well-behaved sender still gets through afterwards
+        drop(hostile);
+        let mut sender =
+            NetworkSender::tcp(source.local_addr(), 8000, 1, SampleEncoding::F32).unwrap();
+        sender.send(&[0.25; 100]).unwrap();
+        assert_eq!(read(&mut source, 100), [0.25; 100]);
+    }
+
+    #[test]
+    fn test_udp_stereo_is_mixed_to_mono() {
+        let mut source = NetworkSource::udp("127.0.0.1:0").unwrap();
+        let mut sender =
+            NetworkSender::udp(source.local_addr(), 8000, 2, SampleEncoding::F32).unwrap();
+        // More than one datagram's worth of (0.5, -0.25) frames
+        let frames = 1000;
+        let samples: Vec<f32> = (0..frames).flat_map(|_| [0.5, -0.25]).collect();
+        sender.send(&samples).unwrap();
+
+        source.wait_for_sender(Duration::from_secs(1)).unwrap();
+        assert_eq!(source.sample_rate(), 8000);
+        assert_eq!(source.channels(), 2);
+
+        let mono = read(&mut source, frames);
+        assert_eq!(mono.len(), frames);
+        assert!(mono.iter().all(|&sample| sample == 0.125));
+        let stereo = source.read_stereo(usize::MAX);
+        assert_eq!(stereo.len(), frames);
+        assert!(stereo.iter().all(|&pair| pair == (0.5, -0.25)));
+        assert_eq!(source.lost_packets(), 0);
+    }
+
+    #[test]
+    fn test_tcp_carries_i16_samples() {
+        let mut source = NetworkSource::tcp("127.0.0.1:0").unwrap();
+        let mut sender =
+            NetworkSender::tcp(source.local_addr(), 48000, 1, SampleEncoding::I16).unwrap();
+        let ramp: Vec<f32> = (0..4000).map(|i| (i as f32 / 4000.0) - 0.5).collect();
+        sender.send(&ramp).unwrap();
+        sender.send(&ramp).unwrap();
+
+        let received = read(&mut source, 2 * ramp.len());
+        assert_eq!(received.len(), 2 * ramp.len());
+        for (sent, got) in ramp.iter().chain(&ramp).zip(&received) {
+            assert!((sent - got).abs() <= 1.0 / 32768.0);
+        }
+        assert_eq!(source.name(), format!("tcp://{}", source.local_addr()));
+    }
+
+    #[test]
+    fn test_sequence_gaps_count_as_lost() {
+        let mut source = NetworkSource::udp("127.0.0.1:0").unwrap();
+        let packet = |sequence| {
+            let mut bytes = Vec::new();
+            PacketHeader {
+                sample_rate: 1000,
+                channels: 1,
+                encoding: SampleEncoding::F32,
+                sequence,
+                frames: 1,
+            }
+            .write(&mut bytes);
+            bytes.extend_from_slice(&(sequence as f32).to_le_bytes());
+            bytes
+        };
+        for sequence in [0, 1, 4, 2, 5] {
+            source.accept(&packet(sequence));
+        }
+        // 2 and 3 never came in time; the late 2 is dropped
+        assert_eq!(source.lost_packets(), 2);
+        assert_eq!(source.data_loss().overruns, 2);
+        assert_eq!(source.read_samples(usize::MAX), [0.0, 1.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn test_restarted_udp_sender_is_picked_up() {
+        let mut source = NetworkSource::udp("127.0.0.1:0").unwrap();
+        let packet = |sequence| {
+            let mut bytes = Vec::new();
+            PacketHeader {
+                sample_rate: 1000,
+                channels: 1,
+                encoding: SampleEncoding::F32,
+                sequence,
+                frames: 1,
+            }
+            .write(&mut bytes);
+            bytes.extend_from_slice(&(sequence as f32).to_le_bytes());
+            bytes
+        };
+        for sequence in [500, 501, 0, 1, 2] {
+            source.accept(&packet(sequence));
+        }
+        // The sender started over at 0 rather than sending 501 packets late
+        assert_eq!(source.lost_packets(), 0);
+        assert_eq!(
+            source.read_samples(usize::MAX),
+            [500.0, 501.0, 0.0, 1.0, 2.0]
+        );
+    }
+
+    #[test]
+    fn test_format_change_fails_the_source() {
+        let mut source = NetworkSource::udp("127.0.0.1:0").unwrap();
+        let mut sender =
+            NetworkSender::udp(source.local_addr(), 8000, 1, SampleEncoding::F32).unwrap();
+        sender.send(&[0.0; 100]).unwrap();
+        source.wait_for_sender(Duration::from_secs(1)).unwrap();
+
+        let mut other =
+            NetworkSender::udp(source.local_addr(), 16000, 1, SampleEncoding::F32).unwrap();
+        other.send(&[0.0; 100]).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while source.status().is_running() && Instant::now() < deadline {
+            source.read_samples(usize::MAX);
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(matches!(
+            source.status(),
+            SourceStatus::Failed(AudioError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_opens_by_url() {
+        let source = NetworkSource::open("udp://127.0.0.1:0").unwrap();
+        assert!(source.name().starts_with("udp://127.0.0.1:"));
+        assert!(NetworkSource::is_url(source.name()));
+        assert!(NetworkSource::open("tcp://127.0.0.1:0").is_ok());
+
+        assert!(!NetworkSource::is_url("Built-in Microphone"));
+        assert_eq!(
+            NetworkSource::open("hw:0").map(|_| ()),
+            Err(AudioError::DeviceNotFound("hw:0".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_waiting_gives_up_without_a_sender() {
+        let mut source = NetworkSource::udp("127.0.0.1:0").unwrap();
+        assert!(matches!(
+            source.wait_for_sender(Duration::from_millis(20)),
+            Err(AudioError::DeviceNotFound(_))
+        ));
+    }
+}
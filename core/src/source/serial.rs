@@ -0,0 +1,498 @@
+//! Readings from a microcontroller ADC on a serial port, such as an Arduino
+//! or ESP32 sketch sampling an analog pin, shown as volts.
+//!
+//! The sketch picks one of two framings:
+//!
+//! - `Text`: one frame per line, readings separated by commas, spaces or
+//!   tabs, as `Serial.println(analogRead(A0))` or the Arduino serial
+//!   plotter's format prints them
+//! - `Binary`: each frame is the sync bytes, then one little-endian u16 per
+//!   channel, which carries more samples at a given baud rate
+//!
+//! Neither says how fast the sketch samples, so the rate is configured
+//! alongside the baud rate.
+
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read};
+use std::time::Duration;
+
+pub use serialport::{DataBits, Parity, StopBits};
+
+use super::{DataLoss, SignalSource, SourceStatus};
+use crate::audio::AudioError;
+
+/// How long a read waits for bytes before returning what it has
+const READ_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// Longest text line kept while waiting for its end, so a port sending
+/// binary by mistake can't grow the buffer without bound
+const MAX_LINE: usize = 256;
+
+/// How the sketch frames its readings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SerialFraming {
+    Text,
+    /// Frames start with `sync`, such as `[0xA5, 0x5A]`, to find their
+    /// start again after a dropped byte. Empty for none.
+    Binary {
+        sync: Vec<u8>,
+    },
+}
+
+/// The port and what the sketch on the other end sends
+#[derive(Debug, Clone, PartialEq)]
+pub struct SerialConfig {
+    pub port: String,
+    pub baud_rate: u32,
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub framing: SerialFraming,
+    /// Frames per second the sketch samples at
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// ADC resolution: 10 on an Uno, 12 on an ESP32
+    pub adc_bits: u8,
+    /// Volts a full-scale reading stands for
+    pub reference_volts: f32,
+}
+
+impl SerialConfig {
+    /// An Uno printing `analogRead` lines at 115200 baud
+    pub fn new(port: impl Into<String>) -> Self {
+        Self {
+            port: port.into(),
+            baud_rate: 115200,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            framing: SerialFraming::Text,
+            sample_rate: 1000,
+            channels: 1,
+            adc_bits: 10,
+            reference_volts: 5.0,
+        }
+    }
+
+    /// Parse an input name like
+    /// `serial:/dev/ttyUSB0?baud=230400&rate=2000&bits=12&vref=3.3`.
+    /// Options left out keep `new`'s defaults; the rest are `channels`,
+    /// `framing=text|binary`, `sync` in hex, `data` bits, `parity=none|odd|even`
+    /// and `stop` bits.
+    pub fn parse(name: &str) -> Result<Self, String> {
+        let spec = name
+            .strip_prefix("serial:")
+            .ok_or_else(|| format!("Not a serial input: {name}"))?;
+        let (port, options) = spec.split_once('?').unwrap_or((spec, ""));
+        if port.is_empty() {
+            return Err("Serial input needs a port".to_string());
+        }
+        let mut config = Self::new(port);
+        let mut sync = None;
+        let mut binary = false;
+        for option in options.split('&').filter(|option| !option.is_empty()) {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("Serial option needs a value: {option}"))?;
+            let invalid = || format!("Invalid serial option: {option}");
+            match key {
+                "baud" => config.baud_rate = parse_positive(value).ok_or_else(invalid)?,
+                "rate" => config.sample_rate = parse_positive(value).ok_or_else(invalid)?,
+                "channels" => config.channels = parse_positive(value).ok_or_else(invalid)?,
+                "bits" => {
+                    config.adc_bits = value
+                        .parse()
+                        .ok()
+                        .filter(|bits| (1..=16).contains(bits))
+                        .ok_or_else(invalid)?;
+                }
+                "vref" => {
+                    config.reference_volts = value
+                        .parse()
+                        .ok()
+                        .filter(|&volts: &f32| volts > 0.0)
+                        .ok_or_else(invalid)?;
+                }
+                "framing" => {
+                    binary = match value {
+                        "text" => false,
+                        "binary" => true,
+                        _ => return Err(invalid()),
+                    };
+                }
+                "sync" => sync = Some(parse_hex(value).ok_or_else(invalid)?),
+                "data" => {
+                    config.data_bits = match value {
+                        "5" => DataBits::Five,
+                        "6" => DataBits::Six,
+                        "7" => DataBits::Seven,
+                        "8" => DataBits::Eight,
+                        _ => return Err(invalid()),
+                    };
+                }
+                "parity" => {
+                    config.parity = match value {
+                        "none" => Parity::None,
+                        "odd" => Parity::Odd,
+                        "even" => Parity::Even,
+                        _ => return Err(invalid()),
+                    };
+                }
+                "stop" => {
+                    config.stop_bits = match value {
+                        "1" => StopBits::One,
+                        "2" => StopBits::Two,
+                        _ => return Err(invalid()),
+                    };
+                }
+                _ => return Err(format!("Unknown serial option: {key}")),
+            }
+        }
+        if binary {
+            config.framing = SerialFraming::Binary {
+                sync: sync.unwrap_or_default(),
+            };
+        } else if sync.is_some() {
+            return Err("sync applies to binary framing only".to_string());
+        }
+        Ok(config)
+    }
+
+    /// Whether an input name is one for `parse` rather than a device
+    pub fn is_serial(name: &str) -> bool {
+        name.starts_with("serial:")
+    }
+
+    /// Volts per ADC count
+    fn volts_per_count(&self) -> f32 {
+        self.reference_volts / ((1u32 << self.adc_bits) - 1) as f32
+    }
+}
+
+fn parse_positive<T: std::str::FromStr + PartialOrd + Default>(value: &str) -> Option<T> {
+    value.parse().ok().filter(|parsed| *parsed > T::default())
+}
+
+fn parse_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(value.get(at..at + 2)?, 16).ok())
+        .collect()
+}
+
+/// Splits the bytes off the port into frames of readings, in ADC counts
+#[derive(Debug, Clone)]
+struct FrameDecoder {
+    framing: SerialFraming,
+    channels: usize,
+    /// Bytes past the last whole frame
+    pending: Vec<u8>,
+}
+
+impl FrameDecoder {
+    fn new(framing: SerialFraming, channels: u16) -> Self {
+        Self {
+            framing,
+            channels: channels.max(1) as usize,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Take in `bytes`, handing each whole frame to `frame`
+    fn push(&mut self, bytes: &[u8], mut frame: impl FnMut(&[f32])) {
+        self.pending.extend_from_slice(bytes);
+        match self.framing {
+            SerialFraming::Text => self.split_lines(&mut frame),
+            SerialFraming::Binary { ref sync } => {
+                let sync = sync.clone();
+                self.split_binary(&sync, &mut frame);
+            }
+        }
+    }
+
+    fn split_lines(&mut self, frame: &mut impl FnMut(&[f32])) {
+        let mut readings = Vec::with_capacity(self.channels);
+        let mut consumed = 0;
+        while let Some(end) = self.pending[consumed..].iter().position(|&b| b == b'\n') {
+            let line = &self.pending[consumed..consumed + end];
+            consumed += end + 1;
+            readings.clear();
+            readings.extend(
+                String::from_utf8_lossy(line)
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|field| !field.is_empty())
+                    .map_while(|field| field.parse::<f32>().ok()),
+            );
+            // Banners and partial lines from before the port opened don't
+            // parse, and are skipped
+            if readings.len() >= self.channels {
+                frame(&readings[..self.channels]);
+            }
+        }
+        self.pending.drain(..consumed);
+        if self.pending.len() > MAX_LINE {
+            self.pending.clear();
+        }
+    }
+
+    fn split_binary(&mut self, sync: &[u8], frame: &mut impl FnMut(&[f32])) {
+        let frame_len = sync.len() + 2 * self.channels;
+        let mut readings = Vec::with_capacity(self.channels);
+        let mut at = 0;
+        while self.pending.len() - at >= frame_len {
+            if !self.pending[at..].starts_with(sync) {
+                // Out of step; look for the sync a byte further on
+                at += 1;
+                continue;
+            }
+            readings.clear();
+            readings.extend(
+                self.pending[at + sync.len()..at + frame_len]
+                    .chunks_exact(2)
+                    .map(|b| u16::from_le_bytes([b[0], b[1]]) as f32),
+            );
+            frame(&readings);
+            at += frame_len;
+        }
+        self.pending.drain(..at);
+    }
+}
+
+/// A microcontroller's ADC readings as a source, in volts. Mixed to mono
+/// like a local input when the sketch sends more than one channel.
+pub struct SerialSource {
+    port: Box<dyn Read + Send>,
+    name: String,
+    sample_rate: u32,
+    channels: u16,
+    volts_per_count: f32,
+    decoder: FrameDecoder,
+    mono: VecDeque<f32>,
+    stereo: VecDeque<(f32, f32)>,
+    /// Samples trimmed while the reader fell behind
+    dropped_samples: u64,
+    failure: Option<AudioError>,
+}
+
+impl SerialSource {
+    pub fn open(config: &SerialConfig) -> Result<Self, AudioError> {
+        let port = serialport::new(&config.port, config.baud_rate)
+            .data_bits(config.data_bits)
+            .parity(config.parity)
+            .stop_bits(config.stop_bits)
+            .timeout(READ_TIMEOUT)
+            .open()
+            .map_err(|e| match e.kind() {
+                serialport::ErrorKind::NoDevice => AudioError::DeviceNotFound(config.port.clone()),
+                serialport::ErrorKind::Io(ErrorKind::NotFound) => {
+                    AudioError::DeviceNotFound(config.port.clone())
+                }
+                serialport::ErrorKind::Io(ErrorKind::PermissionDenied) => {
+                    AudioError::PermissionDenied(config.port.clone())
+                }
+                serialport::ErrorKind::InvalidInput => {
+                    AudioError::UnsupportedFormat(format!("{}: {e}", config.port))
+                }
+                _ => AudioError::Backend(format!("Failed to open {}: {e}", config.port)),
+            })?;
+        Ok(Self::from_reader(port, config))
+    }
+
+    /// Read frames from `port`, which gives `Ok(0)` or times out when
+    /// nothing has arrived
+    fn from_reader(port: Box<dyn Read + Send>, config: &SerialConfig) -> Self {
+        Self {
+            port,
+            name: format!("serial:{}", config.port),
+            sample_rate: config.sample_rate,
+            channels: config.channels.max(1),
+            volts_per_count: config.volts_per_count(),
+            decoder: FrameDecoder::new(config.framing.clone(), config.channels),
+            mono: VecDeque::new(),
+            stereo: VecDeque::new(),
+            dropped_samples: 0,
+            failure: None,
+        }
+    }
+
+    /// Take in everything that's arrived
+    fn receive(&mut self) {
+        if self.failure.is_some() {
+            return;
+        }
+        let mut buffer = [0; 4096];
+        loop {
+            match self.port.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(len) => {
+                    let volts_per_count = self.volts_per_count;
+                    let (mono, stereo) = (&mut self.mono, &mut self.stereo);
+                    self.decoder.push(&buffer[..len], |readings| {
+                        let volts = |count: f32| count * volts_per_count;
+                        mono.push_back(
+                            readings.iter().map(|&count| volts(count)).sum::<f32>()
+                                / readings.len() as f32,
+                        );
+                        let left = volts(readings[0]);
+                        stereo.push_back((left, readings.get(1).map_or(left, |&r| volts(r))));
+                    });
+                    if len < buffer.len() {
+                        break;
+                    }
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                // Such as the board being unplugged
+                Err(_) => {
+                    self.failure = Some(AudioError::DeviceUnavailable);
+                    break;
+                }
+            }
+        }
+
+        // Bounded like a capture ring, should the reader fall behind
+        let limit = self.sample_rate as usize;
+        let excess = self.mono.len().saturating_sub(limit);
+        self.mono.drain(..excess);
+        self.dropped_samples += excess as u64;
+        let excess = self.stereo.len().saturating_sub(limit);
+        self.stereo.drain(..excess);
+    }
+}
+
+impl SignalSource for SerialSource {
+    fn read_samples(&mut self, max_samples: usize) -> Vec<f32> {
+        self.receive();
+        let count = max_samples.min(self.mono.len());
+        self.mono.drain(..count).collect()
+    }
+
+    fn read_stereo(&mut self, max_pairs: usize) -> Vec<(f32, f32)> {
+        let count = max_pairs.min(self.stereo.len());
+        self.stereo.drain(..count).collect()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn status(&self) -> SourceStatus {
+        match self.failure {
+            Some(ref failure) => SourceStatus::Failed(failure.clone()),
+            None => SourceStatus::Running,
+        }
+    }
+
+    fn data_loss(&self) -> DataLoss {
+        DataLoss {
+            dropped_samples: self.dropped_samples,
+            ..DataLoss::default()
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, Cursor};
+
+    fn source(bytes: &[u8], config: &SerialConfig) -> SerialSource {
+        SerialSource::from_reader(Box::new(Cursor::new(bytes.to_vec())), config)
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = SerialConfig::parse(
+            "serial:/dev/ttyUSB0?baud=230400&rate=2000&bits=12&vref=3.3&channels=2&framing=binary&sync=a55a",
+        )
+        .unwrap();
+        assert_eq!(config.port, "/dev/ttyUSB0");
+        assert_eq!(config.baud_rate, 230400);
+        assert_eq!(config.sample_rate, 2000);
+        assert_eq!((config.adc_bits, config.reference_volts), (12, 3.3));
+        assert_eq!(config.channels, 2);
+        assert_eq!(
+            config.framing,
+            SerialFraming::Binary {
+                sync: vec![0xA5, 0x5A]
+            }
+        );
+
+        assert_eq!(
+            SerialConfig::parse("serial:COM3").unwrap(),
+            SerialConfig::new("COM3")
+        );
+        assert!(SerialConfig::parse("serial:COM3?baud=0").is_err());
+        assert!(SerialConfig::parse("serial:COM3?sync=a5").is_err());
+        assert!(SerialConfig::parse("serial:?baud=9600").is_err());
+        assert!(SerialConfig::parse("/dev/ttyUSB0").is_err());
+    }
+
+    #[test]
+    fn test_text_lines_become_volts() {
+        let config = SerialConfig::new("test");
+        // The sketch's banners are skipped
+        let mut source = source(b"Booting\r\n0\r\n1023\r\nready\n511\r\n", &config);
+        let samples = source.read_samples(usize::MAX);
+
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[0], 0.0);
+        assert_eq!(samples[1], 5.0);
+        assert!((samples[2] - 2.4976).abs() < 1e-3);
+        assert_eq!(source.name(), "serial:test");
+    }
+
+    #[test]
+    fn test_binary_frames_resync() {
+        let config = SerialConfig {
+            framing: SerialFraming::Binary {
+                sync: vec![0xA5, 0x5A],
+            },
+            channels: 2,
+            adc_bits: 12,
+            reference_volts: 4.095,
+            ..SerialConfig::new("test")
+        };
+        let mut bytes = vec![0x00, 0x5A];
+        for (left, right) in [(1000u16, 2000u16), (4095, 0)] {
+            bytes.extend_from_slice(&[0xA5, 0x5A]);
+            bytes.extend_from_slice(&left.to_le_bytes());
+            bytes.extend_from_slice(&right.to_le_bytes());
+        }
+        let mut source = source(&bytes, &config);
+
+        let mono = source.read_samples(usize::MAX);
+        assert_eq!(mono.len(), 2);
+        assert!((mono[0] - 1.5).abs() < 1e-6);
+        let stereo = source.read_stereo(usize::MAX);
+        assert!((stereo[1].0 - 4.095).abs() < 1e-6);
+        assert_eq!(stereo[1].1, 0.0);
+    }
+
+    #[test]
+    fn test_read_errors_fail_the_source() {
+        struct Unplugged;
+        impl Read for Unplugged {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::from(ErrorKind::BrokenPipe))
+            }
+        }
+        let mut source = SerialSource::from_reader(Box::new(Unplugged), &SerialConfig::new("x"));
+        assert!(source.read_samples(usize::MAX).is_empty());
+        assert_eq!(
+            source.status(),
+            SourceStatus::Failed(AudioError::DeviceUnavailable)
+        );
+    }
+}
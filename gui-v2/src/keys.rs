@@ -0,0 +1,65 @@
+use ozeecubed_core::keymap::Key;
+use winit::keyboard::KeyCode;
+
+/// Physical keys with a character the shared keymap uses, as they read on
+/// a US layout. Keys are matched physically so shortcuts stay put on other
+/// layouts, as the window keys do.
+const CHARACTER_KEYS: [(KeyCode, char); 43] = [
+    (KeyCode::KeyA, 'a'),
+    (KeyCode::KeyB, 'b'),
+    (KeyCode::KeyC, 'c'),
+    (KeyCode::KeyD, 'd'),
+    (KeyCode::KeyE, 'e'),
+    (KeyCode::KeyF, 'f'),
+    (KeyCode::KeyG, 'g'),
+    (KeyCode::KeyH, 'h'),
+    (KeyCode::KeyI, 'i'),
+    (KeyCode::KeyJ, 'j'),
+    (KeyCode::KeyK, 'k'),
+    (KeyCode::KeyL, 'l'),
+    (KeyCode::KeyM, 'm'),
+    (KeyCode::KeyN, 'n'),
+    (KeyCode::KeyO, 'o'),
+    (KeyCode::KeyP, 'p'),
+    (KeyCode::KeyQ, 'q'),
+    (KeyCode::KeyR, 'r'),
+    (KeyCode::KeyS, 's'),
+    (KeyCode::KeyT, 't'),
+    (KeyCode::KeyU, 'u'),
+    (KeyCode::KeyV, 'v'),
+    (KeyCode::KeyW, 'w'),
+    (KeyCode::KeyX, 'x'),
+    (KeyCode::KeyY, 'y'),
+    (KeyCode::KeyZ, 'z'),
+    (KeyCode::Digit0, '0'),
+    (KeyCode::Digit1, '1'),
+    (KeyCode::Digit2, '2'),
+    (KeyCode::Digit3, '3'),
+    (KeyCode::Digit4, '4'),
+    (KeyCode::Digit5, '5'),
+    (KeyCode::Digit6, '6'),
+    (KeyCode::Digit7, '7'),
+    (KeyCode::Digit8, '8'),
+    (KeyCode::Digit9, '9'),
+    (KeyCode::Equal, '='),
+    (KeyCode::NumpadAdd, '+'),
+    (KeyCode::Minus, '-'),
+    (KeyCode::NumpadSubtract, '-'),
+    (KeyCode::Comma, ','),
+    (KeyCode::BracketLeft, '['),
+    (KeyCode::BracketRight, ']'),
+];
+
+/// The keymap's name for a physical key, if it has one
+pub fn keymap_key(key: KeyCode) -> Option<Key> {
+    match key {
+        KeyCode::ArrowUp => Some(Key::ArrowUp),
+        KeyCode::ArrowDown => Some(Key::ArrowDown),
+        KeyCode::ArrowLeft => Some(Key::ArrowLeft),
+        KeyCode::ArrowRight => Some(Key::ArrowRight),
+        _ => CHARACTER_KEYS
+            .iter()
+            .find(|&&(code, _)| code == key)
+            .map(|&(_, character)| Key::Character(character)),
+    }
+}
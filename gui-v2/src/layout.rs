@@ -0,0 +1,94 @@
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+use ozeecubed_core::display::LayoutMode;
+
+/// Gap between the panes of a split window, in logical pixels
+const PANE_GAP: f32 = 2.0;
+
+/// A pane of a window, in physical pixels from the top-left corner
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Region {
+    pub fn full(size: PhysicalSize<u32>) -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            width: size.width as f32,
+            height: size.height as f32,
+        }
+    }
+
+    pub fn contains(&self, position: PhysicalPosition<f64>) -> bool {
+        let (x, y) = (position.x as f32, position.y as f32);
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    /// `position` in the renderer's normalized screen space for this pane,
+    /// (0, 0) top-left to (1, 1) bottom-right
+    pub fn normalized(&self, position: PhysicalPosition<f64>) -> (f32, f32) {
+        (
+            (position.x as f32 - self.x) / self.width.max(1.0),
+            (position.y as f32 - self.y) / self.height.max(1.0),
+        )
+    }
+
+    /// A point in this pane's normalized space, in the window's
+    pub fn window_position(&self, (x, y): (f32, f32), size: [u32; 2]) -> (f32, f32) {
+        (
+            (self.x + x * self.width) / size[0].max(1) as f32,
+            (self.y + y * self.height) / size[1].max(1) as f32,
+        )
+    }
+
+    /// Draw only into this pane for the rest of `render_pass`
+    pub fn apply(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        render_pass.set_viewport(self.x, self.y, self.width, self.height, 0.0, 1.0);
+        render_pass.set_scissor_rect(
+            self.x as u32,
+            self.y as u32,
+            self.width.max(1.0) as u32,
+            self.height.max(1.0) as u32,
+        );
+    }
+}
+
+/// Where the scope and spectrum go in a window of `size`, `None` for one
+/// `layout` leaves out
+pub fn panes(
+    layout: LayoutMode,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+) -> (Option<Region>, Option<Region>) {
+    let full = Region::full(size);
+    let gap = PANE_GAP * scale_factor;
+    match layout {
+        LayoutMode::ScopeOnly => (Some(full), None),
+        LayoutMode::SpectrumOnly => (None, Some(full)),
+        LayoutMode::SideBySide => {
+            let width = ((full.width - gap) / 2.0).floor().max(1.0);
+            let scope = Region { width, ..full };
+            let spectrum = Region {
+                x: full.width - width,
+                width,
+                ..full
+            };
+            (Some(scope), Some(spectrum))
+        }
+        LayoutMode::Stacked => {
+            let height = ((full.height - gap) / 2.0).floor().max(1.0);
+            let scope = Region { height, ..full };
+            let spectrum = Region {
+                y: full.height - height,
+                height,
+                ..full
+            };
+            (Some(scope), Some(spectrum))
+        }
+    }
+}
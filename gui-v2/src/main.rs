@@ -1,3 +1,6 @@
+use ozeecubed_core::config::{parse_position, parse_size, WindowSettings};
+use ozeecubed_core::plugin::{self, CrestFactor};
+use ozeecubed_core::scripting::Script;
 use winit::{
     application::ApplicationHandler,
     event::*,
@@ -6,20 +9,26 @@ use winit::{
     window::WindowId,
 };
 
+mod keys;
+mod layout;
+mod pointer;
 mod renderer;
 mod state;
+mod ui;
 mod window;
 
 use window::WindowManager;
 
 struct App {
     window_manager: Option<WindowManager>,
+    /// Handed to the window manager once it exists
+    args: Option<Args>,
 }
 
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if self.window_manager.is_none() {
-            self.window_manager = Some(WindowManager::new(event_loop));
+        if let (None, Some(args)) = (&self.window_manager, self.args.take()) {
+            self.window_manager = Some(WindowManager::new(event_loop, args.script, args.window));
         }
     }
 
@@ -53,7 +62,16 @@ impl ApplicationHandler for App {
                         },
                     ..
                 } => {
-                    window_manager.handle_keyboard(window_id, key);
+                    window_manager.handle_keyboard(event_loop, window_id, key);
+                }
+                WindowEvent::ModifiersChanged(modifiers) => {
+                    window_manager.set_fine_adjust(modifiers.state().shift_key());
+                }
+                WindowEvent::CursorMoved { .. }
+                | WindowEvent::CursorLeft { .. }
+                | WindowEvent::MouseInput { .. }
+                | WindowEvent::MouseWheel { .. } => {
+                    window_manager.handle_pointer(window_id, &event);
                 }
                 WindowEvent::RedrawRequested => {
                     window_manager.update(window_id);
@@ -80,14 +98,82 @@ impl ApplicationHandler for App {
     }
 }
 
+/// What the command line asks for: a script and changes to how the scope
+/// window opens
+struct Args {
+    script: Option<Script>,
+    window: WindowSettings,
+}
+
+const USAGE: &str = "Usage: ozeecubed-gui-v2 [--script FILE] [--title TEXT] [--size WxH] \
+                     [--position X,Y] [--monitor NAME|N] [--no-decorations]";
+
+/// The script named by `--script FILE`, if any, and the window settings
+/// saved from the last run with any given on the command line over them.
+/// Exits on bad arguments or a script that doesn't compile.
+fn parse_args() -> Args {
+    let mut window = WindowSettings::default_path()
+        .map(|path| WindowSettings::load(&path))
+        .transpose()
+        .unwrap_or_else(|e| {
+            eprintln!("{e}");
+            None
+        })
+        .unwrap_or_default();
+    let mut script_path = None;
+
+    let usage = || -> ! {
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--no-decorations" {
+            window.decorations = false;
+            continue;
+        }
+        let Some(value) = args.next() else { usage() };
+        match flag.as_str() {
+            "--script" => script_path = Some(value),
+            "--title" => window.title = Some(value),
+            "--size" => window.size = parse_size(&value).unwrap_or_else(|| usage()),
+            "--position" => {
+                window.position = Some(parse_position(&value).unwrap_or_else(|| usage()));
+            }
+            "--monitor" => {
+                // The monitor asked for wins over the position remembered
+                window.monitor = Some(value);
+                window.position = None;
+            }
+            _ => usage(),
+        }
+    }
+
+    let script = script_path.map(|path| match Script::load(&path) {
+        Ok(script) => {
+            println!("Running script {path}");
+            script
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    });
+    Args { script, window }
+}
+
 fn main() {
     env_logger::init();
+    // Plugins register here, before the first capture is measured
+    plugin::register_measurement(CrestFactor);
+    let args = parse_args();
 
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
 
     let mut app = App {
         window_manager: None,
+        args: Some(args),
     };
 
     event_loop.run_app(&mut app).unwrap();
@@ -0,0 +1,203 @@
+use winit::{
+    dpi::PhysicalPosition,
+    event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+};
+
+use crate::layout::Region;
+use crate::state::AppState;
+use ozeecubed_core::oscilloscope::{BandCursor, GateCursor, SpectrumScale};
+
+/// Time/div factor per wheel notch, the same step as the arrow keys
+const ZOOM_STEP: f32 = 1.1;
+
+/// Pixels of touchpad scrolling that count as one wheel notch
+const PIXELS_PER_NOTCH: f32 = 50.0;
+
+/// How close to the trigger level line or a gate cursor, in pixels, a
+/// press grabs it
+const GRAB_DISTANCE: f32 = 6.0;
+
+enum Drag {
+    TriggerLevel,
+    /// Last cursor x in normalized screen space
+    Pan(f32),
+    /// A band power cursor on the spectrum
+    Band(BandCursor),
+    /// A time gate cursor on the scope
+    Gate(GateCursor),
+}
+
+/// Mouse interaction with the scope display.
+///
+/// Cursor positions are converted to the renderer's normalized screen space
+/// of the pane they're in, (0, 0) top-left to (1, 1) bottom-right, and from
+/// there to graticule units.
+pub struct Pointer {
+    /// In window pixels
+    cursor: Option<PhysicalPosition<f64>>,
+    drag: Option<Drag>,
+}
+
+impl Pointer {
+    pub fn new() -> Self {
+        Self {
+            cursor: None,
+            drag: None,
+        }
+    }
+
+    /// Mouse input on a scope window split as `scope` and `spectrum`, `None`
+    /// for a pane the layout leaves out. A drag stays with the pane it
+    /// started in.
+    pub fn handle_split_event(
+        &mut self,
+        event: &WindowEvent,
+        scope: Option<Region>,
+        spectrum: Option<Region>,
+        state: &mut AppState,
+    ) {
+        if let WindowEvent::CursorMoved { position, .. } = event {
+            self.cursor = Some(*position);
+        }
+        let on_spectrum = match self.drag {
+            Some(Drag::Band(_)) => true,
+            Some(_) => false,
+            None => self
+                .cursor
+                .zip(spectrum)
+                .is_some_and(|(cursor, spectrum)| spectrum.contains(cursor)),
+        };
+        match (scope, spectrum) {
+            (Some(scope), Some(_)) if !on_spectrum => self.handle_event(event, scope, state),
+            (Some(scope), None) => self.handle_event(event, scope, state),
+            (_, Some(spectrum)) => self.handle_spectrum_event(event, spectrum, state),
+            (None, None) => {}
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &WindowEvent, region: Region, state: &mut AppState) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let (x, y) = region.normalized(*position);
+                self.cursor = Some(*position);
+                match self.drag {
+                    Some(Drag::TriggerLevel) => state.trigger_settings.level = volts_at(state, y),
+                    Some(Drag::Pan(last_x)) => {
+                        state.pan(x - last_x);
+                        self.drag = Some(Drag::Pan(x));
+                    }
+                    Some(Drag::Gate(cursor)) => {
+                        if let Some(ref mut gate) = state.time_gate {
+                            gate.set(cursor, x - state.horizontal_position);
+                        }
+                    }
+                    Some(Drag::Band(_)) | None => {}
+                }
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+                self.drag = None;
+            }
+            WindowEvent::MouseInput {
+                state: button_state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let position = self.cursor.map(|cursor| region.normalized(cursor));
+                self.drag = match (button_state, position) {
+                    (ElementState::Pressed, Some((x, y))) => {
+                        let level_distance = (y - level_y(state)).abs() * region.height;
+                        if state.trigger_settings.enabled && level_distance <= GRAB_DISTANCE {
+                            Some(Drag::TriggerLevel)
+                        } else if let Some(cursor) = grabbed_gate(state, x, region) {
+                            Some(Drag::Gate(cursor))
+                        } else {
+                            Some(Drag::Pan(x))
+                        }
+                    }
+                    _ => None,
+                };
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let notches = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_NOTCH,
+                };
+                // Scrolling up zooms in, keeping the time under the cursor in place
+                let anchor = self
+                    .cursor
+                    .map_or(0.5, |cursor| region.normalized(cursor).0);
+                state.zoom_time(ZOOM_STEP.powf(-notches), anchor);
+            }
+            _ => {}
+        }
+    }
+
+    /// Mouse input on a spectrum window: pressing picks up the nearer band
+    /// cursor, on the linear scale, and dragging moves it
+    pub fn handle_spectrum_event(
+        &mut self,
+        event: &WindowEvent,
+        region: Region,
+        state: &mut AppState,
+    ) {
+        let nyquist = state.waveform.sample_rate as f32 / 2.0;
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let (x, _) = region.normalized(*position);
+                self.cursor = Some(*position);
+                if let (Some(Drag::Band(cursor)), Some(band)) =
+                    (&self.drag, state.band_cursors.as_mut())
+                {
+                    band.set(*cursor, x.clamp(0.0, 1.0) * nyquist);
+                }
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+                self.drag = None;
+            }
+            WindowEvent::MouseInput {
+                state: button_state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let linear = state.spectrum_scale == SpectrumScale::Linear;
+                let position = self.cursor.map(|cursor| region.normalized(cursor));
+                self.drag = match (button_state, position, state.band_cursors.as_mut()) {
+                    (ElementState::Pressed, Some((x, _)), Some(band)) if linear => {
+                        let frequency = x.clamp(0.0, 1.0) * nyquist;
+                        let cursor = band.nearest(frequency);
+                        band.set(cursor, frequency);
+                        Some(Drag::Band(cursor))
+                    }
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The time gate cursor within grabbing distance of screen `x`, if any
+fn grabbed_gate(state: &AppState, x: f32, region: Region) -> Option<GateCursor> {
+    let gate = state.time_gate?;
+    let x = x - state.horizontal_position;
+    let cursor = gate.nearest(x);
+    let cursor_x = match cursor {
+        GateCursor::Start => gate.start,
+        GateCursor::End => gate.end,
+    };
+    ((x - cursor_x).abs() * region.width <= GRAB_DISTANCE).then_some(cursor)
+}
+
+/// Screen y of the trigger level, as drawn by `trigger_level_marker`
+fn level_y(state: &AppState) -> f32 {
+    let divisions = state.graticule.vertical_divisions.max(1) as f32;
+    0.5 - state.trigger_settings.level / state.waveform.volts_per_division / divisions
+}
+
+/// Voltage at screen y; the center line is 0 V
+fn volts_at(state: &AppState, y: f32) -> f32 {
+    let divisions = state.graticule.vertical_divisions.max(1) as f32;
+    (0.5 - y) * divisions * state.waveform.volts_per_division
+}
@@ -0,0 +1,299 @@
+use super::samples::{SampleTraces, TraceGlobals};
+use super::waveform::create_pipeline;
+use crate::layout::Region;
+use crate::state::AppState;
+use ozeecubed_core::display::{PersistenceColormap, PersistenceMode};
+
+/// Float target so slow decays don't band or stall at 8-bit precision
+const ACCUMULATION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Coverage to brightness steepness: one fresh pass of the trace is ~95% bright
+const COMPOSITE_GAIN: f32 = 3.0;
+
+/// Traces add premultiplied color and coverage into the accumulation texture
+const ADDITIVE: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::SrcAlpha,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+};
+
+/// The fade pass scales whatever is already there by the blend constant
+const DECAY: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Zero,
+        dst_factor: wgpu::BlendFactor::Constant,
+        operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Zero,
+        dst_factor: wgpu::BlendFactor::Constant,
+        operation: wgpu::BlendOperation::Add,
+    },
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct CompositeParams {
+    gain: f32,
+    bloom: f32,
+    heat: f32,
+    _padding: [f32; 1],
+}
+
+/// Phosphor-style persistence in an offscreen texture.
+///
+/// Each frame the texture is faded by a fullscreen pass and only the newest
+/// trace is drawn into it, so the cost is the same for ten frames of
+/// persistence or five hundred. A composite pass then maps the accumulated
+/// energy onto the display.
+pub struct Accumulation {
+    view: wgpu::TextureView,
+    traces: SampleTraces,
+    fade_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+    composite_layout: wgpu::BindGroupLayout,
+    composite_bind_group: wgpu::BindGroup,
+    params_buffer: wgpu::Buffer,
+    /// Last state frame drawn in, so faster redraws don't decay or add twice
+    last_frame: Option<usize>,
+    /// Where in the texture the traces went last, so a new layout starts
+    /// from an empty texture
+    region: Option<Region>,
+    /// The state's history generation drawn last, so clearing the persisted
+    /// traces clears the texture
+    generation: usize,
+}
+
+impl Accumulation {
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        let fade_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Fade Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let fade_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Fade Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/fade.wgsl").into()),
+        });
+        let fade_pipeline = create_pipeline(
+            device,
+            ACCUMULATION_FORMAT,
+            DECAY,
+            &fade_layout,
+            &fade_shader,
+            &[],
+            wgpu::PrimitiveTopology::TriangleList,
+        );
+
+        let composite_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Composite Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let composite_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Composite Pipeline Layout"),
+                bind_group_layouts: &[&composite_layout],
+                push_constant_ranges: &[],
+            });
+        let composite_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Composite Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/composite.wgsl").into()),
+        });
+        let composite_pipeline = create_pipeline(
+            device,
+            config.format,
+            wgpu::BlendState::ALPHA_BLENDING,
+            &composite_pipeline_layout,
+            &composite_shader,
+            &[],
+            wgpu::PrimitiveTopology::TriangleList,
+        );
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Composite Params"),
+            size: std::mem::size_of::<CompositeParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let view = create_texture_view(device, config.width, config.height);
+        let composite_bind_group =
+            create_composite_bind_group(device, &composite_layout, &params_buffer, &view);
+
+        Self {
+            view,
+            traces: SampleTraces::new(device, ACCUMULATION_FORMAT, ADDITIVE),
+            fade_pipeline,
+            composite_pipeline,
+            composite_layout,
+            composite_bind_group,
+            params_buffer,
+            last_frame: None,
+            region: None,
+            generation: 0,
+        }
+    }
+
+    /// Start over with an empty texture matching the new surface size
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.view = create_texture_view(device, width, height);
+        self.composite_bind_group = create_composite_bind_group(
+            device,
+            &self.composite_layout,
+            &self.params_buffer,
+            &self.view,
+        );
+    }
+
+    /// Record the fade and the newest trace into the accumulation texture.
+    /// The texture covers the whole window and the traces go into the same
+    /// `region` of it that the composite pass is drawn over.
+    pub fn accumulate(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        state: &AppState,
+        globals: TraceGlobals,
+        region: Region,
+    ) {
+        // A new layout or cleared persistence starts from an empty texture
+        let continuing =
+            self.region == Some(region) && self.generation == state.history_generation();
+        if self.last_frame == Some(state.frame_count()) && continuing {
+            return;
+        }
+        self.last_frame = Some(state.frame_count());
+        let load = if continuing {
+            wgpu::LoadOp::Load
+        } else {
+            wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT)
+        };
+        self.region = Some(region);
+        self.generation = state.history_generation();
+
+        let newest = state.sample_history.back();
+        self.traces.prepare(
+            device,
+            queue,
+            newest.into_iter().map(|window| (window, 1.0)),
+            globals,
+        );
+
+        // Graded mode blooms busy paths, except on light backgrounds; the heat
+        // map colors them instead
+        let heat = state.persistence_colormap == PersistenceColormap::Heat;
+        let bloom =
+            state.persistence_mode == PersistenceMode::Graded && !state.theme.is_light() && !heat;
+        let params = CompositeParams {
+            gain: COMPOSITE_GAIN,
+            bloom: if bloom { 1.0 } else { 0.0 },
+            heat: if heat { 1.0 } else { 0.0 },
+            _padding: [0.0; 1],
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Accumulation Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        let decay = state.persistence_retention() as f64;
+        region.apply(&mut render_pass);
+        render_pass.set_pipeline(&self.fade_pipeline);
+        render_pass.set_blend_constant(wgpu::Color {
+            r: decay,
+            g: decay,
+            b: decay,
+            a: decay,
+        });
+        render_pass.draw(0..3, 0..1);
+
+        self.traces.draw(&mut render_pass);
+    }
+
+    /// Draw the accumulated traces over whatever the pass already holds
+    pub fn composite<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.composite_pipeline);
+        render_pass.set_bind_group(0, &self.composite_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_texture_view(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("Accumulation Texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ACCUMULATION_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_composite_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    params: &wgpu::Buffer,
+    view: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Composite Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+        ],
+    })
+}
@@ -0,0 +1,63 @@
+/// A GPU buffer rewritten in place with `queue.write_buffer` every frame.
+///
+/// It is only reallocated when the data outgrows it, doubling each time, so
+/// steady-state rendering does no buffer allocation at all.
+pub struct GrowableBuffer {
+    buffer: wgpu::Buffer,
+    capacity: wgpu::BufferAddress,
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+}
+
+impl GrowableBuffer {
+    /// `usage` is extended with `COPY_DST`; `capacity` is in bytes
+    pub fn new(
+        device: &wgpu::Device,
+        label: &'static str,
+        usage: wgpu::BufferUsages,
+        capacity: wgpu::BufferAddress,
+    ) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        // wgpu rejects zero-sized bindings, so always keep a few bytes
+        let capacity = capacity.max(16);
+        Self {
+            buffer: create_buffer(device, label, usage, capacity),
+            capacity,
+            label,
+            usage,
+        }
+    }
+
+    /// Upload `data`, growing first if needed. Returns true when the buffer
+    /// was reallocated, so bind groups referencing it must be rebuilt.
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[u8]) -> bool {
+        let size = data.len() as wgpu::BufferAddress;
+        let grown = size > self.capacity;
+        if grown {
+            self.capacity = size.next_power_of_two();
+            self.buffer = create_buffer(device, self.label, self.usage, self.capacity);
+        }
+        if !data.is_empty() {
+            queue.write_buffer(&self.buffer, 0, data);
+        }
+        grown
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+}
+
+fn create_buffer(
+    device: &wgpu::Device,
+    label: &str,
+    usage: wgpu::BufferUsages,
+    size: wgpu::BufferAddress,
+) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage,
+        mapped_at_creation: false,
+    })
+}
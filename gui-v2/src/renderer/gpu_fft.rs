@@ -0,0 +1,317 @@
+use std::num::NonZeroU64;
+
+use super::samples::{create_bind_group, create_trace_pipeline, FrameParams, TraceGlobals};
+use crate::state::MAX_GPU_FFT_SIZE;
+use ozeecubed_core::oscilloscope::spectrum::{DB_MAX, DB_MIN};
+
+/// Threads per compute workgroup, as declared in fft.wgsl
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Window, one pass per radix-4 stage pair or lone radix-2 stage of the
+/// largest transform, and levels
+const MAX_PASSES: usize = 2 + MAX_GPU_FFT_SIZE.trailing_zeros() as usize;
+
+/// One compute pass's view of the transform, as `Params` in fft.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct FftParams {
+    size: u32,
+    count: u32,
+    half: u32,
+    stages: u32,
+    floor: f32,
+    ceiling: f32,
+    _padding: [f32; 2],
+}
+
+/// What a compute pass runs and over how many threads
+struct Pass {
+    params: FftParams,
+    stage: Stage,
+    threads: u32,
+}
+
+#[derive(Clone, Copy)]
+enum Stage {
+    Window,
+    Butterfly,
+    Level,
+}
+
+/// The spectrum computed on the GPU from the newest samples and drawn from
+/// where it was computed.
+///
+/// The samples are the only upload: compute passes window and transform
+/// them and turn the bins into trace heights, which the sample trace
+/// shader draws without them coming back to the CPU. Every buffer is sized
+/// for the largest transform up front, so changing size allocates nothing.
+pub struct GpuFft {
+    window_pipeline: wgpu::ComputePipeline,
+    butterfly_pipeline: wgpu::ComputePipeline,
+    level_pipeline: wgpu::ComputePipeline,
+    compute_bind_group: wgpu::BindGroup,
+    params_buffer: wgpu::Buffer,
+    /// Bytes between passes' params, as uniform offsets must be aligned
+    params_stride: usize,
+    params_bytes: Vec<u8>,
+    passes: Vec<Pass>,
+    sample_buffer: wgpu::Buffer,
+    trace_pipeline: wgpu::RenderPipeline,
+    trace_bind_group: wgpu::BindGroup,
+    globals_buffer: wgpu::Buffer,
+    frame_buffer: wgpu::Buffer,
+    /// Bins of the last transform, none until one has run
+    bins: u32,
+}
+
+impl GpuFft {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let params_size = std::mem::size_of::<FftParams>() as wgpu::BufferAddress;
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("FFT Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: NonZeroU64::new(params_size),
+                    },
+                    count: None,
+                },
+                storage_entry(1, true),
+                storage_entry(2, false),
+                storage_entry(3, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("FFT Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("FFT Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/fft.wgsl").into()),
+        });
+        let compute_pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("FFT Pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+
+        let params_stride = (params_size as usize)
+            .next_multiple_of(device.limits().min_uniform_buffer_offset_alignment as usize);
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("FFT Params"),
+            size: (params_stride * MAX_PASSES) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let buffer = |label, size: usize, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: size as wgpu::BufferAddress,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        let sample_buffer = buffer(
+            "FFT Samples",
+            MAX_GPU_FFT_SIZE * 4,
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        );
+        let data_buffer = buffer(
+            "FFT Data",
+            MAX_GPU_FFT_SIZE * 8,
+            wgpu::BufferUsages::STORAGE,
+        );
+        let level_buffer = buffer(
+            "FFT Levels",
+            MAX_GPU_FFT_SIZE / 2 * 4,
+            wgpu::BufferUsages::STORAGE,
+        );
+        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("FFT Bind Group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &params_buffer,
+                        offset: 0,
+                        size: NonZeroU64::new(params_size),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: sample_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: data_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: level_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let (trace_pipeline, trace_layout) =
+            create_trace_pipeline(device, format, wgpu::BlendState::ALPHA_BLENDING);
+        let globals_buffer = buffer(
+            "FFT Trace Globals",
+            std::mem::size_of::<TraceGlobals>(),
+            wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        );
+        let frame_buffer = buffer(
+            "FFT Trace Frame",
+            std::mem::size_of::<FrameParams>(),
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        );
+        let trace_bind_group = create_bind_group(
+            device,
+            &trace_layout,
+            &globals_buffer,
+            &level_buffer,
+            &frame_buffer,
+        );
+
+        Self {
+            window_pipeline: compute_pipeline("window"),
+            butterfly_pipeline: compute_pipeline("butterfly"),
+            level_pipeline: compute_pipeline("level"),
+            compute_bind_group,
+            params_buffer,
+            params_stride,
+            params_bytes: Vec::new(),
+            passes: Vec::with_capacity(MAX_PASSES),
+            sample_buffer,
+            trace_pipeline,
+            trace_bind_group,
+            globals_buffer,
+            frame_buffer,
+            bins: 0,
+        }
+    }
+
+    /// Transform the newest `size` of `samples`, zero-padding fewer, ready
+    /// for `draw`. `size` is a power of two up to `MAX_GPU_FFT_SIZE`.
+    pub fn compute(
+        &mut self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        samples: &[f32],
+        size: usize,
+        globals: TraceGlobals,
+    ) {
+        let size = size.clamp(4, MAX_GPU_FFT_SIZE);
+        let count = samples.len().min(size);
+        if count == 0 {
+            self.bins = 0;
+            return;
+        }
+        queue.write_buffer(
+            &self.sample_buffer,
+            0,
+            bytemuck::cast_slice(&samples[samples.len() - count..]),
+        );
+
+        let params = FftParams {
+            size: size as u32,
+            count: count as u32,
+            half: 1,
+            stages: 1,
+            floor: DB_MIN,
+            ceiling: DB_MAX,
+            _padding: [0.0; 2],
+        };
+        self.passes.clear();
+        self.passes.push(Pass {
+            params,
+            stage: Stage::Window,
+            threads: size as u32,
+        });
+        // Stages in pairs as radix-4 passes, and a last radix-2 pass when
+        // there's an odd number of them
+        let mut half = 1;
+        while half < size {
+            let stages = if half * 4 <= size { 2 } else { 1 };
+            self.passes.push(Pass {
+                params: FftParams {
+                    half: half as u32,
+                    stages,
+                    ..params
+                },
+                stage: Stage::Butterfly,
+                threads: (size / (2 * stages as usize)) as u32,
+            });
+            half <<= stages;
+        }
+        self.passes.push(Pass {
+            params,
+            stage: Stage::Level,
+            threads: size as u32 / 2,
+        });
+
+        self.params_bytes.clear();
+        for pass in &self.passes {
+            self.params_bytes
+                .extend_from_slice(bytemuck::bytes_of(&pass.params));
+            self.params_bytes.resize(
+                self.params_bytes.len().next_multiple_of(self.params_stride),
+                0,
+            );
+        }
+        queue.write_buffer(&self.params_buffer, 0, &self.params_bytes);
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("FFT Pass"),
+            timestamp_writes: None,
+        });
+        for (i, pass) in self.passes.iter().enumerate() {
+            let pipeline = match pass.stage {
+                Stage::Window => &self.window_pipeline,
+                Stage::Butterfly => &self.butterfly_pipeline,
+                Stage::Level => &self.level_pipeline,
+            };
+            compute_pass.set_pipeline(pipeline);
+            let offset = (i * self.params_stride) as wgpu::DynamicOffset;
+            compute_pass.set_bind_group(0, &self.compute_bind_group, &[offset]);
+            compute_pass.dispatch_workgroups(pass.threads.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+        drop(compute_pass);
+
+        self.bins = size as u32 / 2;
+        queue.write_buffer(&self.globals_buffer, 0, bytemuck::bytes_of(&globals));
+        queue.write_buffer(
+            &self.frame_buffer,
+            0,
+            bytemuck::bytes_of(&FrameParams::whole(self.bins)),
+        );
+    }
+
+    /// The trace of the last `compute`, if there was anything to transform
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.bins < 2 {
+            return;
+        }
+        render_pass.set_pipeline(&self.trace_pipeline);
+        render_pass.set_bind_group(0, &self.trace_bind_group, &[]);
+        render_pass.draw(0..(self.bins - 1) * 6, 0..1);
+    }
+}
@@ -0,0 +1,79 @@
+use glam::Vec2;
+
+/// Vertex of a triangulated line quad.
+///
+/// `edge` carries the signed distance from the line's center in pixels, the
+/// half width and the feather width, so the fragment shader can fade the
+/// outer edge for anti-aliasing.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineVertex {
+    position: [f32; 2],
+    color: [f32; 4],
+    edge: [f32; 3],
+}
+
+impl LineVertex {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+            ],
+        }
+    }
+}
+
+/// How thick lines are drawn, in physical pixels
+#[derive(Debug, Clone, Copy)]
+pub struct LineStyle {
+    pub width: f32,
+    /// Width of the soft edge; zero gives hard, aliased edges
+    pub feather: f32,
+}
+
+/// Append the two triangles covering a line segment given in clip space.
+///
+/// `viewport` is the target size in pixels, needed so the width is uniform
+/// regardless of the aspect ratio.
+pub fn push_segment(
+    vertices: &mut Vec<LineVertex>,
+    from: [f32; 2],
+    to: [f32; 2],
+    color: [f32; 4],
+    style: LineStyle,
+    viewport: Vec2,
+) {
+    let half_viewport = viewport / 2.0;
+    let from_px = Vec2::from(from) * half_viewport;
+    let to_px = Vec2::from(to) * half_viewport;
+
+    let direction = (to_px - from_px).try_normalize().unwrap_or(Vec2::X);
+    let half_width = style.width / 2.0;
+    let extent = half_width + style.feather;
+    let offset = direction.perp() * extent / half_viewport;
+
+    let corner = |point: [f32; 2], side: f32| LineVertex {
+        position: (Vec2::from(point) + offset * side).to_array(),
+        color,
+        edge: [extent * side, half_width, style.feather],
+    };
+    let (a, b) = (corner(from, 1.0), corner(from, -1.0));
+    let (c, d) = (corner(to, 1.0), corner(to, -1.0));
+    vertices.extend_from_slice(&[a, b, c, c, b, d]);
+}
@@ -1,22 +1,78 @@
+mod accumulation;
+mod buffer;
+mod frequency_track;
+mod gpu_fft;
+mod line;
+mod phase;
+mod samples;
+mod screenshot;
+mod spectrum;
+mod text;
+mod ui;
+mod vectorscope;
 mod waveform;
 
+use std::path::PathBuf;
 use std::sync::Arc;
 use winit::{dpi::PhysicalSize, window::Window};
 
+use crate::layout::{panes, Region};
 use crate::state::AppState;
+use crate::ui::UiFrame;
+use frequency_track::{track_labels, FrequencyTrackRenderer};
+use ozeecubed_core::display::{
+    axis_labels, format_volts, measurement_readout, time_gate_label, AxisLabel, LabelAnchor,
+    Palette, Rgba,
+};
+use ozeecubed_core::oscilloscope::trigger::TriggerEdge;
+use phase::{phase_text, PhaseRenderer};
+use spectrum::{spectrum_labels, SpectrumRenderer};
+use text::TextRenderer;
+use ui::UiRenderer;
+use vectorscope::{vectorscope_text, VectorscopeRenderer};
 use waveform::WaveformRenderer;
 
+/// Gap between the status line and the display corner, in normalized units
+const STATUS_MARGIN: f32 = 0.006;
+
+/// What a window displays
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Scope,
+    Spectrum,
+    FrequencyTrack,
+    Vectorscope,
+    Phase,
+}
+
+/// Per-view renderers; only the one a window shows is created
+enum Content {
+    /// The scope window also shows the spectrum in the split layouts
+    Scope {
+        waveform: Box<WaveformRenderer>,
+        spectrum: Box<SpectrumRenderer>,
+    },
+    Spectrum(Box<SpectrumRenderer>),
+    FrequencyTrack(Box<FrequencyTrackRenderer>),
+    Vectorscope(Box<VectorscopeRenderer>),
+    Phase(Box<PhaseRenderer>),
+}
+
 pub struct Renderer {
     surface: wgpu::Surface<'static>,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     size: PhysicalSize<u32>,
-    waveform_renderer: WaveformRenderer,
+    content: Content,
+    text_renderer: TextRenderer,
+    ui_renderer: UiRenderer,
+    /// Where to save the next rendered frame as a PNG, once one is asked for
+    screenshot: Option<PathBuf>,
 }
 
 impl Renderer {
-    pub async fn new(window: Arc<Window>) -> Self {
+    pub async fn new(window: Arc<Window>, view: View) -> Self {
         let size = window.inner_size();
 
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
@@ -55,8 +111,11 @@ impl Renderer {
             .find(|f| f.is_srgb())
             .unwrap_or(surface_caps.formats[0]);
 
+        // Reading frames back for screenshots needs COPY_SRC where supported
+        let usage = wgpu::TextureUsages::RENDER_ATTACHMENT
+            | (surface_caps.usages & wgpu::TextureUsages::COPY_SRC);
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format: surface_format,
             width: size.width,
             height: size.height,
@@ -68,7 +127,22 @@ impl Renderer {
 
         surface.configure(&device, &config);
 
-        let waveform_renderer = WaveformRenderer::new(&device, &config);
+        let content = match view {
+            View::Scope => Content::Scope {
+                waveform: Box::new(WaveformRenderer::new(&device, &queue, &config)),
+                spectrum: Box::new(SpectrumRenderer::new(&device, &config)),
+            },
+            View::Spectrum => Content::Spectrum(Box::new(SpectrumRenderer::new(&device, &config))),
+            View::FrequencyTrack => {
+                Content::FrequencyTrack(Box::new(FrequencyTrackRenderer::new(&device, &config)))
+            }
+            View::Vectorscope => {
+                Content::Vectorscope(Box::new(VectorscopeRenderer::new(&device, &config)))
+            }
+            View::Phase => Content::Phase(Box::new(PhaseRenderer::new(&device, &config))),
+        };
+        let text_renderer = TextRenderer::new(&device, &queue, &config);
+        let ui_renderer = UiRenderer::new(&device, &config);
 
         Self {
             surface,
@@ -76,7 +150,10 @@ impl Renderer {
             queue,
             config,
             size,
-            waveform_renderer,
+            content,
+            text_renderer,
+            ui_renderer,
+            screenshot: None,
         }
     }
 
@@ -86,10 +163,45 @@ impl Renderer {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
+            match &mut self.content {
+                Content::Scope { waveform, .. } => {
+                    waveform.resize(&self.device, new_size.width, new_size.height)
+                }
+                Content::Spectrum(_) => {}
+                Content::FrequencyTrack(track_renderer) => {
+                    track_renderer.resize(new_size.width, new_size.height)
+                }
+                Content::Vectorscope(vectorscope_renderer) => {
+                    vectorscope_renderer.resize(new_size.width, new_size.height)
+                }
+                Content::Phase(phase_renderer) => {
+                    phase_renderer.resize(new_size.width, new_size.height)
+                }
+            }
+        }
+    }
+
+    /// Save the next frame under a timestamped name
+    pub fn request_screenshot(&mut self) {
+        match screenshot::timestamped_path() {
+            Ok(path) => self.screenshot = Some(path),
+            Err(e) => eprintln!("Screenshot failed: {e}"),
         }
     }
 
-    pub fn render(&mut self, state: &AppState) -> Result<(), wgpu::SurfaceError> {
+    /// Save the next frame to `path`
+    pub fn request_screenshot_to(&mut self, path: PathBuf) {
+        self.screenshot = Some(path);
+    }
+
+    /// Draw one frame; `ui` is the control panel, for windows that have one
+    pub fn render(
+        &mut self,
+        state: &AppState,
+        scale_factor: f32,
+        ui: Option<&UiFrame>,
+    ) -> Result<(), wgpu::SurfaceError> {
+        let palette = state.palette();
         let output = self.surface.get_current_texture()?;
         let view = output
             .texture
@@ -109,10 +221,10 @@ impl Renderer {
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 1.0,
+                            r: palette.background[0] as f64,
+                            g: palette.background[1] as f64,
+                            b: palette.background[2] as f64,
+                            a: palette.background[3] as f64,
                         }),
                         store: wgpu::StoreOp::Store,
                     },
@@ -121,22 +233,200 @@ impl Renderer {
                 timestamp_writes: None,
                 occlusion_query_set: None,
             });
-
-            // Waveform rendering will go here
         }
+        self.queue.submit(std::iter::once(encoder.finish()));
 
-        // Render waveform
-        self.waveform_renderer.render(
+        let labels = match &mut self.content {
+            Content::Scope { waveform, spectrum } => {
+                let size = [self.config.width, self.config.height];
+                let (scope_region, spectrum_region) = panes(state.layout, self.size, scale_factor);
+                let mut labels = Vec::new();
+                if let Some(region) = scope_region {
+                    waveform.update_grid(&self.device, &self.queue, &state.graticule, palette);
+                    waveform.render(
+                        &self.device,
+                        &self.queue,
+                        &view,
+                        state,
+                        &palette,
+                        scale_factor,
+                        region,
+                    );
+                    labels.extend(in_region(overlay_text(state, &palette), region, size));
+                }
+                if let Some(region) = spectrum_region {
+                    spectrum.render(
+                        &self.device,
+                        &self.queue,
+                        &view,
+                        state,
+                        &palette,
+                        scale_factor,
+                        region,
+                    );
+                    labels.extend(in_region(spectrum_labels(state, &palette), region, size));
+                }
+                labels
+            }
+            Content::Spectrum(spectrum_renderer) => {
+                spectrum_renderer.render(
+                    &self.device,
+                    &self.queue,
+                    &view,
+                    state,
+                    &palette,
+                    scale_factor,
+                    Region::full(self.size),
+                );
+                spectrum_labels(state, &palette)
+            }
+            Content::FrequencyTrack(track_renderer) => {
+                track_renderer.render(
+                    &self.device,
+                    &self.queue,
+                    &view,
+                    state,
+                    &palette,
+                    scale_factor,
+                );
+                track_labels(state, &palette)
+            }
+            Content::Vectorscope(vectorscope_renderer) => {
+                vectorscope_renderer.render(
+                    &self.device,
+                    &self.queue,
+                    &view,
+                    state,
+                    &palette,
+                    scale_factor,
+                );
+                vectorscope_text(state, &palette, [self.config.width, self.config.height])
+            }
+            Content::Phase(phase_renderer) => {
+                phase_renderer.render(
+                    &self.device,
+                    &self.queue,
+                    &view,
+                    state,
+                    &palette,
+                    scale_factor,
+                );
+                phase_text(state, &palette)
+            }
+        };
+
+        self.text_renderer.render(
             &self.device,
             &self.queue,
             &view,
-            &state.waveform_history,
-            &state.trigger_settings,
+            [self.config.width, self.config.height],
+            scale_factor,
+            &labels,
         );
 
-        self.queue.submit(std::iter::once(encoder.finish()));
+        // Control panel goes on top of everything
+        if let Some(ui) = ui {
+            self.ui_renderer.render(
+                &self.device,
+                &self.queue,
+                &view,
+                [self.config.width, self.config.height],
+                ui,
+            );
+        }
+
+        if let Some(path) = self.screenshot.take() {
+            if !self.config.usage.contains(wgpu::TextureUsages::COPY_SRC) {
+                eprintln!("Screenshot failed: the surface does not support readback");
+            } else {
+                match screenshot::save_screenshot(&self.device, &self.queue, &output.texture, &path)
+                {
+                    Ok(()) => println!("Saved screenshot to {}", path.display()),
+                    Err(e) => eprintln!("Screenshot failed: {e}"),
+                }
+            }
+        }
+
         output.present();
 
         Ok(())
     }
 }
+
+/// Labels placed within a pane, moved to where the pane is in the window
+fn in_region(
+    labels: Vec<(AxisLabel, Rgba)>,
+    region: Region,
+    size: [u32; 2],
+) -> impl Iterator<Item = (AxisLabel, Rgba)> {
+    labels.into_iter().map(move |(label, color)| {
+        let position = region.window_position(label.position, size);
+        (AxisLabel { position, ..label }, color)
+    })
+}
+
+/// Labels and status text drawn over the waveform display
+fn overlay_text(state: &AppState, palette: &Palette) -> Vec<(AxisLabel, Rgba)> {
+    let mut labels = Vec::new();
+
+    if state.graticule.enabled && state.show_axis_labels {
+        labels.extend(
+            axis_labels(
+                &state.graticule,
+                state.waveform.time_per_division,
+                state.waveform.volts_per_division,
+            )
+            .into_iter()
+            .map(|label| (label, palette.text)),
+        );
+    }
+
+    let trigger = &state.trigger_settings;
+    let trigger_status = if trigger.enabled {
+        let edge = match trigger.edge {
+            TriggerEdge::Rising => "Rising",
+            TriggerEdge::Falling => "Falling",
+        };
+        format!(
+            "Trig {} {edge} {}",
+            trigger.mode.label(),
+            format_volts(trigger.level)
+        )
+    } else {
+        "Free run".to_string()
+    };
+    let source = if !state.running {
+        "Stopped"
+    } else if state.is_live() {
+        "Live"
+    } else {
+        "Test signal"
+    };
+    let mut status = format!("{source}  {trigger_status}");
+    if state.show_measurements {
+        status.push('\n');
+        status.push_str(&measurement_readout(&state.measurements));
+    }
+    labels.push((
+        AxisLabel {
+            text: status,
+            position: (STATUS_MARGIN, STATUS_MARGIN),
+            anchor: LabelAnchor::TopLeft,
+        },
+        palette.accent,
+    ));
+
+    if let Some(ref gate) = state.time_gate {
+        labels.push((
+            time_gate_label(
+                gate,
+                state.horizontal_position,
+                state.waveform.calculate_samples_per_screen(),
+                state.waveform.sample_rate,
+            ),
+            palette.accent,
+        ));
+    }
+
+    labels
+}
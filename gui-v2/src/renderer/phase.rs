@@ -0,0 +1,166 @@
+use glam::Vec2;
+
+use super::buffer::GrowableBuffer;
+use super::line::{push_segment, LineStyle, LineVertex};
+use super::waveform::create_pipeline;
+use crate::state::AppState;
+use ozeecubed_core::display::{phase_labels, AxisLabel, Palette, Rgba};
+use ozeecubed_core::oscilloscope::phase::frequency_position;
+
+/// Phase grid lines, every 90°
+const PHASE_DIVISIONS: usize = 4;
+
+/// Frequencies of the vertical grid lines, in Hz
+const FREQUENCY_LINES: [f32; 3] = [100.0, 1000.0, 10000.0];
+
+/// Draws inter-channel phase against log frequency with its grid
+pub struct PhaseRenderer {
+    pipeline: wgpu::RenderPipeline,
+    buffer: GrowableBuffer,
+    vertices: Vec<LineVertex>,
+    viewport: Vec2,
+}
+
+impl PhaseRenderer {
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Phase Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Line Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/line.wgsl").into()),
+        });
+        let pipeline = create_pipeline(
+            device,
+            config.format,
+            wgpu::BlendState::ALPHA_BLENDING,
+            &pipeline_layout,
+            &shader,
+            &[LineVertex::desc()],
+            wgpu::PrimitiveTopology::TriangleList,
+        );
+
+        Self {
+            pipeline,
+            buffer: GrowableBuffer::new(device, "Phase Buffer", wgpu::BufferUsages::VERTEX, 0),
+            vertices: Vec::new(),
+            viewport: Vec2::new(config.width as f32, config.height as f32),
+        }
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.viewport = Vec2::new(width as f32, height as f32);
+    }
+
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        state: &AppState,
+        palette: &Palette,
+        scale_factor: f32,
+    ) {
+        let feather = if state.trace_smoothing {
+            scale_factor
+        } else {
+            0.0
+        };
+        let grid_style = LineStyle {
+            width: scale_factor,
+            feather,
+        };
+        let trace_style = LineStyle {
+            width: state.trace_width * scale_factor,
+            feather,
+        };
+
+        self.vertices.clear();
+
+        // As faint as the spectrum grid
+        let grid = Palette::faded(palette.grid, 0.5);
+        for hertz in FREQUENCY_LINES {
+            let x = frequency_position(hertz, state.waveform.sample_rate) * 2.0 - 1.0;
+            push_segment(
+                &mut self.vertices,
+                [x, -1.0],
+                [x, 1.0],
+                grid,
+                grid_style,
+                self.viewport,
+            );
+        }
+        for i in 0..=PHASE_DIVISIONS {
+            let y = i as f32 / PHASE_DIVISIONS as f32 * 2.0 - 1.0;
+            push_segment(
+                &mut self.vertices,
+                [-1.0, y],
+                [1.0, y],
+                grid,
+                grid_style,
+                self.viewport,
+            );
+        }
+
+        if let Some(ref response) = state.phase {
+            let point = |(x, level): (f32, f32)| [x * 2.0 - 1.0, level * 2.0 - 1.0];
+            for trace in response.traces() {
+                for pair in trace.windows(2) {
+                    push_segment(
+                        &mut self.vertices,
+                        point(pair[0]),
+                        point(pair[1]),
+                        palette.trace,
+                        trace_style,
+                        self.viewport,
+                    );
+                }
+            }
+        }
+
+        self.buffer
+            .write(device, queue, bytemuck::cast_slice(&self.vertices));
+        let vertex_count = self.vertices.len() as u32;
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Phase Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Phase Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_vertex_buffer(0, self.buffer.buffer().slice(..));
+            render_pass.draw(0..vertex_count, 0..1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+/// Phase and frequency labels and the delay readout, while there's a
+/// response
+pub fn phase_text(state: &AppState, palette: &Palette) -> Vec<(AxisLabel, Rgba)> {
+    state
+        .phase
+        .as_ref()
+        .map(|response| {
+            phase_labels(response)
+                .into_iter()
+                .map(|label| (label, palette.text))
+                .collect()
+        })
+        .unwrap_or_default()
+}
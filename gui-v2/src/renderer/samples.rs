@@ -0,0 +1,262 @@
+use glam::Vec2;
+
+use super::buffer::GrowableBuffer;
+use super::line::LineStyle;
+use super::waveform::create_pipeline;
+use crate::state::SampleWindow;
+
+/// Per-draw parameters shared by every history frame
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TraceGlobals {
+    color: [f32; 4],
+    viewport: [f32; 2],
+    y_scale: f32,
+    half_width: f32,
+    feather: f32,
+    x_offset: f32,
+    _padding: [f32; 2],
+}
+
+impl TraceGlobals {
+    /// `y_scale` maps a raw sample value to clip-space y; `x_offset` pans the
+    /// traces in normalized screen widths
+    pub fn new(
+        color: [f32; 4],
+        viewport: Vec2,
+        y_scale: f32,
+        x_offset: f32,
+        style: LineStyle,
+    ) -> Self {
+        Self {
+            color,
+            viewport: viewport.to_array(),
+            y_scale,
+            half_width: style.width / 2.0,
+            feather: style.feather,
+            x_offset,
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+/// Where one history frame lives in the sample buffer
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FrameParams {
+    offset: u32,
+    count: u32,
+    x_scale: f32,
+    alpha: f32,
+}
+
+impl FrameParams {
+    /// The whole buffer as one opaque trace, `count` values across the
+    /// screen
+    pub fn whole(count: u32) -> Self {
+        Self {
+            offset: 0,
+            count,
+            x_scale: 1.0 / count.max(1) as f32,
+            alpha: 1.0,
+        }
+    }
+}
+
+/// Draws traces straight from raw sample windows.
+///
+/// The windows are written into one storage buffer and the vertex shader
+/// derives every quad corner from the sample index, so the CPU does no
+/// per-point work and the buffers are only reallocated when they grow.
+pub struct SampleTraces {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    globals_buffer: wgpu::Buffer,
+    sample_buffer: GrowableBuffer,
+    frame_buffer: GrowableBuffer,
+    samples: Vec<f32>,
+    frames: Vec<FrameParams>,
+    vertex_count: u32,
+}
+
+impl SampleTraces {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        blend: wgpu::BlendState,
+    ) -> Self {
+        let (pipeline, bind_group_layout) = create_trace_pipeline(device, format, blend);
+
+        let globals_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sample Trace Globals"),
+            size: std::mem::size_of::<TraceGlobals>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sample_buffer =
+            GrowableBuffer::new(device, "Sample Buffer", wgpu::BufferUsages::STORAGE, 0);
+        let frame_buffer = GrowableBuffer::new(
+            device,
+            "Sample Frame Buffer",
+            wgpu::BufferUsages::STORAGE,
+            0,
+        );
+        let bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            &globals_buffer,
+            sample_buffer.buffer(),
+            frame_buffer.buffer(),
+        );
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            bind_group,
+            globals_buffer,
+            sample_buffer,
+            frame_buffer,
+            samples: Vec::new(),
+            frames: Vec::new(),
+            vertex_count: 0,
+        }
+    }
+
+    /// Upload the windows for this frame, oldest first, each with the
+    /// opacity it fades to
+    pub fn prepare<'w>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        history: impl ExactSizeIterator<Item = (&'w SampleWindow, f32)>,
+        globals: TraceGlobals,
+    ) {
+        self.samples.clear();
+        self.frames.clear();
+        let mut longest = 0;
+        for (window, alpha) in history {
+            self.frames.push(FrameParams {
+                offset: self.samples.len() as u32,
+                count: window.samples.len() as u32,
+                x_scale: 1.0 / window.samples_per_screen.max(1) as f32,
+                alpha,
+            });
+            self.samples.extend_from_slice(&window.samples);
+            longest = longest.max(window.samples.len());
+        }
+        self.vertex_count = (longest.saturating_sub(1) * 6) as u32;
+        if self.frames.is_empty() {
+            return;
+        }
+
+        queue.write_buffer(&self.globals_buffer, 0, bytemuck::bytes_of(&globals));
+        let samples_grown =
+            self.sample_buffer
+                .write(device, queue, bytemuck::cast_slice(&self.samples));
+        let frames_grown =
+            self.frame_buffer
+                .write(device, queue, bytemuck::cast_slice(&self.frames));
+        if samples_grown || frames_grown {
+            self.bind_group = create_bind_group(
+                device,
+                &self.bind_group_layout,
+                &self.globals_buffer,
+                self.sample_buffer.buffer(),
+                self.frame_buffer.buffer(),
+            );
+        }
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.vertex_count == 0 || self.frames.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..self.vertex_count, 0..self.frames.len() as u32);
+    }
+}
+
+/// The pipeline drawing traces from a storage buffer of values, with the
+/// layout of its bind group: globals, values, then frames
+pub fn create_trace_pipeline(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    blend: wgpu::BlendState,
+) -> (wgpu::RenderPipeline, wgpu::BindGroupLayout) {
+    let storage_entry = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::VERTEX,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Sample Trace Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            storage_entry(1),
+            storage_entry(2),
+        ],
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Sample Trace Pipeline Layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Sample Trace Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/samples.wgsl").into()),
+    });
+    let pipeline = create_pipeline(
+        device,
+        format,
+        blend,
+        &pipeline_layout,
+        &shader,
+        &[],
+        wgpu::PrimitiveTopology::TriangleList,
+    );
+    (pipeline, bind_group_layout)
+}
+
+pub fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    globals: &wgpu::Buffer,
+    samples: &wgpu::Buffer,
+    frames: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Sample Trace Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: globals.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: samples.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: frames.as_entire_binding(),
+            },
+        ],
+    })
+}
@@ -0,0 +1,280 @@
+use glam::Vec2;
+
+use super::buffer::GrowableBuffer;
+use super::gpu_fft::GpuFft;
+use super::line::{push_segment, LineStyle, LineVertex};
+use super::samples::TraceGlobals;
+use super::waveform::create_pipeline;
+use crate::layout::Region;
+use crate::state::AppState;
+use ozeecubed_core::display::{
+    band_cursor_markers, band_power_label, harmonic_markers, harmonic_table, AxisLabel,
+    LabelAnchor, Palette, Rgba,
+};
+use ozeecubed_core::oscilloscope::spectrum::{SpectrumScale, DB_MAX, DB_MIN};
+
+/// Grid divisions of the spectrum display (frequency × dB)
+const FREQUENCY_DIVISIONS: usize = 10;
+const DB_DIVISIONS: usize = 8;
+
+/// Gap between a label and the display edge, in normalized units
+const LABEL_MARGIN: f32 = 0.006;
+
+/// Draws the magnitude spectrum (dB over linear frequency) with its grid
+pub struct SpectrumRenderer {
+    pipeline: wgpu::RenderPipeline,
+    buffer: GrowableBuffer,
+    vertices: Vec<LineVertex>,
+    viewport: Vec2,
+    gpu_fft: GpuFft,
+}
+
+impl SpectrumRenderer {
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Spectrum Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Line Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/line.wgsl").into()),
+        });
+        let pipeline = create_pipeline(
+            device,
+            config.format,
+            wgpu::BlendState::ALPHA_BLENDING,
+            &pipeline_layout,
+            &shader,
+            &[LineVertex::desc()],
+            wgpu::PrimitiveTopology::TriangleList,
+        );
+
+        Self {
+            pipeline,
+            buffer: GrowableBuffer::new(device, "Spectrum Buffer", wgpu::BufferUsages::VERTEX, 0),
+            vertices: Vec::new(),
+            viewport: Vec2::new(config.width as f32, config.height as f32),
+            gpu_fft: GpuFft::new(device, config.format),
+        }
+    }
+
+    /// Draw into `region`, a whole spectrum window or its pane of the scope
+    /// window
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        state: &AppState,
+        palette: &Palette,
+        scale_factor: f32,
+        region: Region,
+    ) {
+        self.viewport = Vec2::new(region.width, region.height);
+        let feather = if state.trace_smoothing {
+            scale_factor
+        } else {
+            0.0
+        };
+        let grid_style = LineStyle {
+            width: scale_factor,
+            feather,
+        };
+        let trace_style = LineStyle {
+            width: state.trace_width * scale_factor,
+            feather,
+        };
+
+        self.vertices.clear();
+
+        // The spectrum grid is drawn fainter than the scope grid, with the
+        // 0 Hz (or C1) and floor lines in the center color. The constant-Q
+        // and mel scales are divided at their labels.
+        let grid = Palette::faded(palette.grid, 0.5);
+        let divisions: Vec<f32> = match state.spectrum_scale {
+            SpectrumScale::Linear => (0..=FREQUENCY_DIVISIONS)
+                .map(|i| i as f32 / FREQUENCY_DIVISIONS as f32)
+                .collect(),
+            SpectrumScale::ConstantQ | SpectrumScale::Mel => state
+                .spectrum_scale
+                .frequency_labels(state.waveform.sample_rate, state.spectrum.len())
+                .into_iter()
+                .map(|(fraction, _)| fraction)
+                .collect(),
+        };
+        for (i, fraction) in divisions.into_iter().enumerate() {
+            let x = fraction * 2.0 - 1.0;
+            let color = if i == 0 { palette.grid_center } else { grid };
+            push_segment(
+                &mut self.vertices,
+                [x, -1.0],
+                [x, 1.0],
+                color,
+                grid_style,
+                self.viewport,
+            );
+        }
+        for i in 0..=DB_DIVISIONS {
+            let y = i as f32 / DB_DIVISIONS as f32 * 2.0 - 1.0;
+            let color = if i == 0 { palette.grid_center } else { grid };
+            push_segment(
+                &mut self.vertices,
+                [-1.0, y],
+                [1.0, y],
+                color,
+                grid_style,
+                self.viewport,
+            );
+        }
+
+        // The GPU FFT's trace is drawn from where it's computed
+        let gpu_fft = state.uses_gpu_fft();
+        let magnitudes: &[f32] = if gpu_fft { &[] } else { &state.spectrum };
+        let bins = magnitudes.len() as f32;
+        for (i, pair) in magnitudes.windows(2).enumerate() {
+            let point = |bin: usize, db: f32| {
+                let level = ((db - DB_MIN) / (DB_MAX - DB_MIN)).clamp(0.0, 1.0);
+                [bin as f32 / bins * 2.0 - 1.0, level * 2.0 - 1.0]
+            };
+            push_segment(
+                &mut self.vertices,
+                point(i, pair[0]),
+                point(i + 1, pair[1]),
+                palette.trace,
+                trace_style,
+                self.viewport,
+            );
+        }
+
+        if let Some(ref analysis) = state.harmonics {
+            let bar_style = LineStyle {
+                width: 3.0 * scale_factor,
+                feather,
+            };
+            let to_ndc = |(x, y): (f32, f32)| [x * 2.0 - 1.0, 1.0 - y * 2.0];
+            for (bottom, top) in harmonic_markers(analysis, state.waveform.sample_rate) {
+                push_segment(
+                    &mut self.vertices,
+                    to_ndc(bottom),
+                    to_ndc(top),
+                    palette.accent,
+                    bar_style,
+                    self.viewport,
+                );
+            }
+        }
+
+        if let (Some(ref band), Some(_)) = (state.band_cursors, state.band_power) {
+            let to_ndc = |(x, y): (f32, f32)| [x * 2.0 - 1.0, 1.0 - y * 2.0];
+            for (top, bottom) in band_cursor_markers(band, state.waveform.sample_rate) {
+                push_segment(
+                    &mut self.vertices,
+                    to_ndc(top),
+                    to_ndc(bottom),
+                    palette.accent,
+                    grid_style,
+                    self.viewport,
+                );
+            }
+        }
+
+        self.buffer
+            .write(device, queue, bytemuck::cast_slice(&self.vertices));
+        let vertex_count = self.vertices.len() as u32;
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Spectrum Encoder"),
+        });
+        if gpu_fft {
+            let globals = TraceGlobals::new(palette.trace, self.viewport, 1.0, 0.0, trace_style);
+            self.gpu_fft.compute(
+                queue,
+                &mut encoder,
+                &state.fft_input,
+                state.gpu_fft_size,
+                globals,
+            );
+        }
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Spectrum Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            region.apply(&mut render_pass);
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_vertex_buffer(0, self.buffer.buffer().slice(..));
+            render_pass.draw(0..vertex_count, 0..1);
+            if gpu_fft {
+                self.gpu_fft.draw(&mut render_pass);
+            }
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+/// Frequency labels along the bottom, dB labels down the left edge, and the
+/// harmonics table and band power readout when shown
+pub fn spectrum_labels(state: &AppState, palette: &Palette) -> Vec<(AxisLabel, Rgba)> {
+    let mut labels = Vec::new();
+
+    let frequencies = state
+        .spectrum_scale
+        .frequency_labels(state.waveform.sample_rate, state.spectrum.len());
+    for (fraction, text) in frequencies {
+        let (x, anchor) = if fraction >= 1.0 {
+            (1.0 - LABEL_MARGIN, LabelAnchor::BottomRight)
+        } else {
+            (fraction + LABEL_MARGIN, LabelAnchor::BottomLeft)
+        };
+        labels.push((
+            AxisLabel {
+                text,
+                position: (x, 1.0 - LABEL_MARGIN),
+                anchor,
+            },
+            palette.text,
+        ));
+    }
+
+    for i in 0..4 {
+        let fraction = i as f32 / 4.0;
+        let db = DB_MAX - fraction * (DB_MAX - DB_MIN);
+        let text = match state.level_offset() {
+            Some(offset) => format!("{:.0} dB SPL", db + offset),
+            None => format!("{db:.0} dB"),
+        };
+        labels.push((
+            AxisLabel {
+                text,
+                position: (LABEL_MARGIN, fraction + LABEL_MARGIN),
+                anchor: LabelAnchor::TopLeft,
+            },
+            palette.text,
+        ));
+    }
+
+    if let Some(ref analysis) = state.harmonics {
+        labels.push((harmonic_table(analysis), palette.text));
+    }
+
+    if let Some(ref band_power) = state.band_power {
+        labels.push((
+            band_power_label(band_power, state.level_offset()),
+            palette.text,
+        ));
+    }
+
+    labels
+}
@@ -0,0 +1,144 @@
+use glyphon::{
+    Attrs, Buffer, Color, Family, FontSystem, Metrics, Resolution, Shaping, SwashCache, TextArea,
+    TextAtlas, TextBounds,
+};
+
+use ozeecubed_core::display::{AxisLabel, LabelAnchor, Rgba};
+
+/// Label size in logical pixels, matching the iced canvas labels
+const FONT_SIZE: f32 = 11.0;
+const LINE_HEIGHT: f32 = 1.3;
+
+/// Draws screen-space text (axis labels, readouts, status) with glyphon
+pub struct TextRenderer {
+    font_system: FontSystem,
+    cache: SwashCache,
+    atlas: TextAtlas,
+    renderer: glyphon::TextRenderer,
+}
+
+impl TextRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        config: &wgpu::SurfaceConfiguration,
+    ) -> Self {
+        let mut atlas = TextAtlas::new(device, queue, config.format);
+        let renderer =
+            glyphon::TextRenderer::new(&mut atlas, device, wgpu::MultisampleState::default(), None);
+
+        Self {
+            font_system: FontSystem::new(),
+            cache: SwashCache::new(),
+            atlas,
+            renderer,
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        size: [u32; 2],
+        scale_factor: f32,
+        labels: &[(AxisLabel, Rgba)],
+    ) {
+        let [width, height] = size;
+        let metrics = Metrics::new(
+            FONT_SIZE * scale_factor,
+            FONT_SIZE * LINE_HEIGHT * scale_factor,
+        );
+
+        let buffers: Vec<Buffer> = labels
+            .iter()
+            .map(|(label, _)| {
+                let mut buffer = Buffer::new(&mut self.font_system, metrics);
+                buffer.set_size(&mut self.font_system, width as f32, height as f32);
+                buffer.set_text(
+                    &mut self.font_system,
+                    &label.text,
+                    Attrs::new().family(Family::Monospace),
+                    Shaping::Basic,
+                );
+                buffer.shape_until_scroll(&mut self.font_system);
+                buffer
+            })
+            .collect();
+
+        let text_areas = labels.iter().zip(&buffers).map(|((label, color), buffer)| {
+            let (text_width, text_height) =
+                buffer.layout_runs().fold((0.0_f32, 0.0), |(w, h), run| {
+                    (w.max(run.line_w), h + metrics.line_height)
+                });
+            let x = label.position.0 * width as f32;
+            let y = label.position.1 * height as f32;
+            let (left, top) = match label.anchor {
+                LabelAnchor::BottomLeft => (x, y - text_height),
+                LabelAnchor::BottomRight => (x - text_width, y - text_height),
+                LabelAnchor::TopRight => (x - text_width, y),
+                LabelAnchor::TopLeft => (x, y),
+            };
+
+            TextArea {
+                buffer,
+                left,
+                top,
+                scale: 1.0,
+                bounds: TextBounds {
+                    left: 0,
+                    top: 0,
+                    right: width as i32,
+                    bottom: height as i32,
+                },
+                default_color: to_color(*color),
+            }
+        });
+
+        if let Err(e) = self.renderer.prepare(
+            device,
+            queue,
+            &mut self.font_system,
+            &mut self.atlas,
+            Resolution { width, height },
+            text_areas,
+            &mut self.cache,
+        ) {
+            eprintln!("Text prepare error: {e:?}");
+            return;
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Text Encoder"),
+        });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Text Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            if let Err(e) = self.renderer.render(&self.atlas, &mut render_pass) {
+                eprintln!("Text render error: {e:?}");
+            }
+        }
+
+        queue.submit(std::iter::once(encoder.finish()));
+        self.atlas.trim();
+    }
+}
+
+fn to_color(color: Rgba) -> Color {
+    let [r, g, b, a] = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    Color::rgba(r, g, b, a)
+}
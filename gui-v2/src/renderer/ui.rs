@@ -0,0 +1,71 @@
+use egui_wgpu::ScreenDescriptor;
+
+use crate::ui::UiFrame;
+
+/// Draws the egui control panel on top of the scope display
+pub struct UiRenderer {
+    renderer: egui_wgpu::Renderer,
+}
+
+impl UiRenderer {
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        Self {
+            renderer: egui_wgpu::Renderer::new(device, config.format, None, 1),
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        size: [u32; 2],
+        frame: &UiFrame,
+    ) {
+        for (id, delta) in &frame.textures_delta.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+
+        let screen = ScreenDescriptor {
+            size_in_pixels: size,
+            pixels_per_point: frame.pixels_per_point,
+        };
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("UI Encoder"),
+        });
+        let callbacks =
+            self.renderer
+                .update_buffers(device, queue, &mut encoder, &frame.primitives, &screen);
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("UI Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            self.renderer
+                .render(&mut render_pass, &frame.primitives, &screen);
+        }
+
+        queue.submit(
+            callbacks
+                .into_iter()
+                .chain(std::iter::once(encoder.finish())),
+        );
+
+        for id in &frame.textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use glam::Vec2;
+
+use super::buffer::GrowableBuffer;
+use super::line::{push_segment, LineStyle, LineVertex};
+use super::waveform::create_pipeline;
+use crate::state::AppState;
+use ozeecubed_core::display::{
+    vectorscope_axes, vectorscope_labels, vectorscope_position, AxisLabel, Palette, Rgba,
+};
+
+/// Draws the rotated stereo pairs with their persistence and guide lines,
+/// in the largest square that fits the window
+pub struct VectorscopeRenderer {
+    pipeline: wgpu::RenderPipeline,
+    buffer: GrowableBuffer,
+    vertices: Vec<LineVertex>,
+    viewport: Vec2,
+}
+
+impl VectorscopeRenderer {
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Vectorscope Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Line Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/line.wgsl").into()),
+        });
+        let pipeline = create_pipeline(
+            device,
+            config.format,
+            wgpu::BlendState::ALPHA_BLENDING,
+            &pipeline_layout,
+            &shader,
+            &[LineVertex::desc()],
+            wgpu::PrimitiveTopology::TriangleList,
+        );
+
+        Self {
+            pipeline,
+            buffer: GrowableBuffer::new(
+                device,
+                "Vectorscope Buffer",
+                wgpu::BufferUsages::VERTEX,
+                0,
+            ),
+            vertices: Vec::new(),
+            viewport: Vec2::new(config.width as f32, config.height as f32),
+        }
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.viewport = Vec2::new(width as f32, height as f32);
+    }
+
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        state: &AppState,
+        palette: &Palette,
+        scale_factor: f32,
+    ) {
+        let feather = if state.trace_smoothing {
+            scale_factor
+        } else {
+            0.0
+        };
+        let grid_style = LineStyle {
+            width: scale_factor,
+            feather,
+        };
+        let trace_style = LineStyle {
+            width: state.trace_width * scale_factor,
+            feather,
+        };
+
+        // Square positions to clip space
+        let scale = square_scale(self.viewport);
+        let point = |(x, y): (f32, f32)| [(x * 2.0 - 1.0) * scale.x, (1.0 - y * 2.0) * scale.y];
+
+        self.vertices.clear();
+
+        // As faint as the spectrum grid
+        let grid = Palette::faded(palette.grid, 0.5);
+        let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        let outline = (0..4).map(|i| (corners[i], corners[(i + 1) % 4]));
+        for (from, to) in outline.chain(vectorscope_axes()) {
+            push_segment(
+                &mut self.vertices,
+                point(from),
+                point(to),
+                grid,
+                grid_style,
+                self.viewport,
+            );
+        }
+
+        for (brightness, points) in state.vectorscope.frames() {
+            // Older frames fade out, as the waveform's persistence does
+            let color = Palette::faded(palette.trace, brightness * brightness);
+            for pair in points.windows(2) {
+                push_segment(
+                    &mut self.vertices,
+                    point(vectorscope_position(pair[0])),
+                    point(vectorscope_position(pair[1])),
+                    color,
+                    trace_style,
+                    self.viewport,
+                );
+            }
+        }
+
+        self.buffer
+            .write(device, queue, bytemuck::cast_slice(&self.vertices));
+        let vertex_count = self.vertices.len() as u32;
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Vectorscope Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Vectorscope Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_vertex_buffer(0, self.buffer.buffer().slice(..));
+            render_pass.draw(0..vertex_count, 0..1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+/// Fraction of the window's width and height the square takes up
+fn square_scale(viewport: Vec2) -> Vec2 {
+    let side = viewport.x.min(viewport.y).max(1.0);
+    Vec2::new(side / viewport.x.max(1.0), side / viewport.y.max(1.0))
+}
+
+/// Channel labels and the balance readout, placed within the square
+pub fn vectorscope_text(
+    state: &AppState,
+    palette: &Palette,
+    [width, height]: [u32; 2],
+) -> Vec<(AxisLabel, Rgba)> {
+    let scale = square_scale(Vec2::new(width as f32, height as f32));
+    let vectorscope = &state.vectorscope;
+    vectorscope_labels(&vectorscope.balance(), vectorscope.gain())
+        .into_iter()
+        .map(|mut label| {
+            let (x, y) = label.position;
+            label.position = (0.5 + (x - 0.5) * scale.x, 0.5 + (y - 0.5) * scale.y);
+            (label, palette.text)
+        })
+        .collect()
+}
@@ -1,7 +1,15 @@
-use std::collections::VecDeque;
-use wgpu::util::DeviceExt;
+use super::accumulation::Accumulation;
+use super::buffer::GrowableBuffer;
+use super::line::{push_segment, LineStyle, LineVertex};
+use super::samples::{SampleTraces, TraceGlobals};
+use glam::Vec2;
 
-use ozeecubed_core::oscilloscope::TriggerSettings;
+use crate::layout::Region;
+use crate::state::AppState;
+use ozeecubed_core::display::{
+    time_gate_markers, trigger_level_marker, trigger_point_marker, Graticule, GridLineKind,
+    Palette, PersistenceMode,
+};
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -33,164 +41,267 @@ impl Vertex {
 
 pub struct WaveformRenderer {
     pipeline: wgpu::RenderPipeline,
-    grid_buffer: wgpu::Buffer,
+    line_pipeline: wgpu::RenderPipeline,
+    sample_traces: SampleTraces,
+    accumulation: Accumulation,
+    viewport: Vec2,
+    grid_buffer: GrowableBuffer,
     grid_vertex_count: u32,
+    line_buffer: GrowableBuffer,
+    line_vertices: Vec<LineVertex>,
+    graticule: Graticule,
+    palette: Palette,
 }
 
 impl WaveformRenderer {
-    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Self {
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Waveform Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/waveform.wgsl").into()),
-        });
-
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        config: &wgpu::SurfaceConfiguration,
+    ) -> Self {
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Waveform Pipeline Layout"),
             bind_group_layouts: &[],
             push_constant_ranges: &[],
         });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Waveform Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::LineList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
+        // Hairline grid
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Waveform Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/waveform.wgsl").into()),
         });
+        let pipeline = create_pipeline(
+            device,
+            config.format,
+            wgpu::BlendState::ALPHA_BLENDING,
+            &pipeline_layout,
+            &shader,
+            &[Vertex::desc()],
+            wgpu::PrimitiveTopology::LineList,
+        );
 
-        // Create grid
-        let grid_vertices = Self::create_grid();
-        let grid_vertex_count = grid_vertices.len() as u32;
-        let grid_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Grid Buffer"),
-            contents: bytemuck::cast_slice(&grid_vertices),
-            usage: wgpu::BufferUsages::VERTEX,
+        // Traces and markers as anti-aliased quads
+        let line_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Line Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/line.wgsl").into()),
         });
+        let line_pipeline = create_pipeline(
+            device,
+            config.format,
+            wgpu::BlendState::ALPHA_BLENDING,
+            &pipeline_layout,
+            &line_shader,
+            &[LineVertex::desc()],
+            wgpu::PrimitiveTopology::TriangleList,
+        );
+
+        // Create grid
+        let graticule = Graticule::default();
+        let palette = Palette::default();
+        let grid_vertices = Self::create_grid(&graticule, &palette);
+        let mut grid_buffer =
+            GrowableBuffer::new(device, "Grid Buffer", wgpu::BufferUsages::VERTEX, 0);
+        grid_buffer.write(device, queue, bytemuck::cast_slice(&grid_vertices));
 
         Self {
             pipeline,
+            line_pipeline,
+            sample_traces: SampleTraces::new(
+                device,
+                config.format,
+                wgpu::BlendState::ALPHA_BLENDING,
+            ),
+            accumulation: Accumulation::new(device, config),
+            viewport: Vec2::new(config.width as f32, config.height as f32),
             grid_buffer,
-            grid_vertex_count,
+            grid_vertex_count: grid_vertices.len() as u32,
+            line_buffer: GrowableBuffer::new(
+                device,
+                "Waveform Buffer",
+                wgpu::BufferUsages::VERTEX,
+                0,
+            ),
+            line_vertices: Vec::new(),
+            graticule,
+            palette,
         }
     }
 
-    fn create_grid() -> Vec<Vertex> {
-        let mut vertices = Vec::new();
-        let grid_color = [0.0, 1.0, 0.16, 0.3]; // Green with alpha
-
-        // Vertical lines (10 divisions)
-        for i in 0..=10 {
-            let x = -1.0 + (i as f32 / 10.0) * 2.0;
-            vertices.push(Vertex {
-                position: [x, -1.0],
-                color: grid_color,
-            });
-            vertices.push(Vertex {
-                position: [x, 1.0],
-                color: grid_color,
-            });
-        }
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.accumulation.resize(device, width, height);
+    }
 
-        // Horizontal lines (8 divisions)
-        for i in 0..=8 {
-            let y = -1.0 + (i as f32 / 8.0) * 2.0;
-            vertices.push(Vertex {
-                position: [-1.0, y],
-                color: grid_color,
-            });
-            vertices.push(Vertex {
-                position: [1.0, y],
-                color: grid_color,
-            });
+    /// Rewrite the grid buffer when the graticule or colors have changed
+    pub fn update_grid(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        graticule: &Graticule,
+        palette: Palette,
+    ) {
+        if palette != self.palette || *graticule != self.graticule {
+            let grid_vertices = Self::create_grid(graticule, &palette);
+            self.grid_buffer
+                .write(device, queue, bytemuck::cast_slice(&grid_vertices));
+            self.grid_vertex_count = grid_vertices.len() as u32;
+            self.graticule = graticule.clone();
+            self.palette = palette;
         }
+    }
 
-        vertices
+    fn create_grid(graticule: &Graticule, palette: &Palette) -> Vec<Vertex> {
+        graticule
+            .lines()
+            .iter()
+            .flat_map(|line| {
+                let color = match line.kind {
+                    GridLineKind::Major => palette.grid,
+                    GridLineKind::Center | GridLineKind::Tick => palette.grid_center,
+                };
+                // Normalized screen space (y down) to clip space (y up)
+                [line.from, line.to].map(|(x, y)| Vertex {
+                    position: [x * 2.0 - 1.0, 1.0 - y * 2.0],
+                    color,
+                })
+            })
+            .collect()
     }
 
+    /// Draw into `region`, the scope's pane of the window
+    #[allow(clippy::too_many_arguments)]
     pub fn render(
-        &self,
+        &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         view: &wgpu::TextureView,
-        waveform_history: &VecDeque<Vec<(f32, f32)>>,
-        _trigger_settings: &TriggerSettings,
+        state: &AppState,
+        palette: &Palette,
+        scale_factor: f32,
+        region: Region,
     ) {
-        // Create all buffers before starting render pass
-        let mut waveform_buffers = Vec::new();
+        self.viewport = Vec2::new(region.width, region.height);
+        let waveform_history = &state.waveform_history;
+        let intensity = &state.intensity;
+        // Point y is in divisions; the screen spans the graticule's divisions
+        let half_divisions = state.graticule.vertical_divisions.max(1) as f32 / 2.0;
+        let feather = if state.trace_smoothing {
+            scale_factor
+        } else {
+            0.0
+        };
+        let trace_style = LineStyle {
+            width: state.trace_width * scale_factor,
+            feather,
+        };
+        let marker_style = LineStyle {
+            width: scale_factor,
+            feather,
+        };
+
+        // Every trace and marker quad goes into one reused vertex buffer, in
+        // draw order, so the whole overlay is a single draw call
+        self.line_vertices.clear();
         let num_frames = waveform_history.len();
 
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Waveform Encoder"),
+        });
+
+        let y_scale = 1.0 / (state.waveform.volts_per_division * half_divisions);
+        let x_offset = state.horizontal_position;
+        let globals =
+            TraceGlobals::new(palette.trace, self.viewport, y_scale, x_offset, trace_style);
+        let gpu_samples = state.uses_gpu_samples() && !state.gpu_persistence;
+        if state.gpu_persistence {
+            self.accumulation
+                .accumulate(device, queue, &mut encoder, state, globals, region);
+        } else if gpu_samples {
+            let count = state.sample_history.len();
+            let history = state
+                .sample_history
+                .iter()
+                .enumerate()
+                .map(|(index, window)| (window, state.history_alpha(index, count)));
+            self.sample_traces.prepare(device, queue, history, globals);
+        }
+
+        // Empty while the GPU path is active; see AppState::uses_gpu_samples
         for (frame_idx, points) in waveform_history.iter().enumerate() {
             if points.len() < 2 {
                 continue;
             }
 
-            // Calculate alpha for persistence effect
-            let alpha = (frame_idx as f32 + 1.0) / num_frames as f32;
-            let color = [0.0, 1.0, 0.16, alpha]; // Green with varying alpha
+            // Older traces fade out; within a trace, brightness follows how
+            // often each segment's path has been traversed recently
+            let age_alpha = state.history_alpha(frame_idx, num_frames);
 
             // Convert points to vertices
-            let mut vertices = Vec::new();
             for window in points.windows(2) {
                 let (x1, y1) = window[0];
                 let (x2, y2) = window[1];
+                let colormap = state.persistence_colormap;
+                let color = match state.persistence_mode {
+                    PersistenceMode::Fade => colormap.faded(palette, age_alpha),
+                    PersistenceMode::Graded => Palette::faded(
+                        colormap.graded(
+                            palette,
+                            intensity.intensity_at((x1 + x2) / 2.0, (y1 + y2) / 2.0),
+                        ),
+                        age_alpha,
+                    ),
+                };
 
                 // Convert from normalized coordinates to clip space
-                let x1_clip = x1 * 2.0 - 1.0;
-                let y1_clip = -(y1 * 2.0 - 1.0); // Flip Y
-                let x2_clip = x2 * 2.0 - 1.0;
-                let y2_clip = -(y2 * 2.0 - 1.0); // Flip Y
-
-                vertices.push(Vertex {
-                    position: [x1_clip, y1_clip],
+                push_segment(
+                    &mut self.line_vertices,
+                    [(x1 + x_offset) * 2.0 - 1.0, y1 / half_divisions],
+                    [(x2 + x_offset) * 2.0 - 1.0, y2 / half_divisions],
                     color,
-                });
-                vertices.push(Vertex {
-                    position: [x2_clip, y2_clip],
-                    color,
-                });
+                    trace_style,
+                    self.viewport,
+                );
             }
+        }
 
-            if !vertices.is_empty() {
-                let vertex_count = vertices.len() as u32;
-                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Waveform Buffer"),
-                    contents: bytemuck::cast_slice(&vertices),
-                    usage: wgpu::BufferUsages::VERTEX,
-                });
-                waveform_buffers.push((buffer, vertex_count));
-            }
+        // Envelope over the traces, or alone; see EnvelopeFollower::shows_raw
+        for window in state.envelope_points.windows(2) {
+            let (x1, y1) = window[0];
+            let (x2, y2) = window[1];
+            push_segment(
+                &mut self.line_vertices,
+                [(x1 + x_offset) * 2.0 - 1.0, y1 / half_divisions],
+                [(x2 + x_offset) * 2.0 - 1.0, y2 / half_divisions],
+                palette.accent,
+                trace_style,
+                self.viewport,
+            );
         }
 
-        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Waveform Encoder"),
-        });
+        // Trigger level, edge and instant indicators on top of the traces
+        let mut markers = trigger_level_marker(
+            &state.trigger_settings,
+            state.waveform.volts_per_division,
+            state.graticule.vertical_divisions,
+        );
+        if let Some(x) = state.trigger_position {
+            markers.extend(trigger_point_marker(x + x_offset));
+        }
+        if let Some(ref gate) = state.time_gate {
+            markers.extend(time_gate_markers(gate, x_offset));
+        }
+        for ((x1, y1), (x2, y2)) in markers {
+            push_segment(
+                &mut self.line_vertices,
+                [x1 * 2.0 - 1.0, 1.0 - y1 * 2.0],
+                [x2 * 2.0 - 1.0, 1.0 - y2 * 2.0],
+                palette.accent,
+                marker_style,
+                self.viewport,
+            );
+        }
+        self.line_buffer
+            .write(device, queue, bytemuck::cast_slice(&self.line_vertices));
+        let line_vertex_count = self.line_vertices.len() as u32;
 
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -208,19 +319,69 @@ impl WaveformRenderer {
                 occlusion_query_set: None,
             });
 
+            region.apply(&mut render_pass);
             render_pass.set_pipeline(&self.pipeline);
 
             // Draw grid
-            render_pass.set_vertex_buffer(0, self.grid_buffer.slice(..));
+            render_pass.set_vertex_buffer(0, self.grid_buffer.buffer().slice(..));
             render_pass.draw(0..self.grid_vertex_count, 0..1);
 
             // Draw waveform history with persistence
-            for (buffer, vertex_count) in &waveform_buffers {
-                render_pass.set_vertex_buffer(0, buffer.slice(..));
-                render_pass.draw(0..*vertex_count, 0..1);
+            if state.gpu_persistence {
+                self.accumulation.composite(&mut render_pass);
+            } else if gpu_samples {
+                self.sample_traces.draw(&mut render_pass);
             }
+            render_pass.set_pipeline(&self.line_pipeline);
+            render_pass.set_vertex_buffer(0, self.line_buffer.buffer().slice(..));
+            render_pass.draw(0..line_vertex_count, 0..1);
         }
 
         queue.submit(std::iter::once(encoder.finish()));
     }
 }
+
+pub(super) fn create_pipeline(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    blend: wgpu::BlendState,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    vertex_layouts: &[wgpu::VertexBufferLayout<'static>],
+    topology: wgpu::PrimitiveTopology,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Waveform Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: vertex_layouts,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(blend),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
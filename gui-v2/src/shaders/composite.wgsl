@@ -0,0 +1,52 @@
+// Maps the accumulated trace energy onto the display.
+//
+// The accumulation texture holds premultiplied trace color in rgb and the
+// summed, decayed coverage in a. Coverage saturates smoothly, so often-drawn
+// paths glow brighter than rare ones.
+
+struct Params {
+    // Coverage to brightness steepness
+    gain: f32,
+    // 1.0 to bloom busy paths toward white, 0.0 for flat color
+    bloom: f32,
+    // 1.0 to color by coverage from blue through green and yellow to red
+    heat: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var accumulation: texture_2d<f32>;
+
+// PersistenceColormap::Heat's stops in ozeecubed_core
+fn heat_color(level: f32) -> vec3<f32> {
+    var stops = array<vec3<f32>, 4>(
+        vec3<f32>(0.0, 0.2, 1.0),
+        vec3<f32>(0.0, 0.9, 0.2),
+        vec3<f32>(1.0, 0.9, 0.0),
+        vec3<f32>(1.0, 0.1, 0.0),
+    );
+    let position = clamp(level, 0.0, 1.0) * 3.0;
+    let index = min(u32(position), 2u);
+    return mix(stops[index], stops[index + 1u], position - f32(index));
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let energy = textureLoad(accumulation, vec2<i32>(position.xy), 0);
+    if energy.a <= 0.0001 {
+        discard;
+    }
+    let color = energy.rgb / energy.a;
+    let intensity = 1.0 - exp(-energy.a * params.gain);
+    if params.heat > 0.5 {
+        // Rare paths stay visible in blue rather than fading out
+        return vec4<f32>(heat_color(intensity), 0.5 + 0.5 * intensity);
+    }
+    let glow = params.bloom * intensity * intensity * 0.6;
+    return vec4<f32>(mix(color, vec3<f32>(1.0), glow), intensity);
+}
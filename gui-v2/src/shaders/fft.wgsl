@@ -0,0 +1,112 @@
+// Radix-2/4 FFT of the newest samples, straight to a spectrum trace.
+// `window` loads the samples Hann-windowed in bit-reversed order, each
+// `butterfly` pass runs one radix-2 stage or two stages as one radix-4
+// pass, and `level` turns the positive-frequency bins into clip-space y
+// for the sample trace shader.
+
+struct Params {
+    // Transform length, a power of two
+    size: u32,
+    // Samples loaded, at most `size`; the rest is zero padding
+    count: u32,
+    // Half-width of the first butterflies in this pass
+    half: u32,
+    // 1 for a radix-2 pass, 2 for a radix-4 one
+    stages: u32,
+    // dB at the bottom and top of the display
+    floor: f32,
+    ceiling: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> samples: array<f32>;
+@group(0) @binding(2) var<storage, read_write> data: array<vec2<f32>>;
+@group(0) @binding(3) var<storage, read_write> levels: array<f32>;
+
+const TAU: f32 = 6.283185307;
+
+fn multiply(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+// e^(-2πi k/n), the forward transform's twiddle factor
+fn twiddle(k: u32, n: u32) -> vec2<f32> {
+    let angle = TAU * f32(k) / f32(n);
+    return vec2<f32>(cos(angle), -sin(angle));
+}
+
+@compute @workgroup_size(64)
+fn window(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= params.size {
+        return;
+    }
+    let bits = countTrailingZeros(params.size);
+    let reversed = reverseBits(i) >> (32u - bits);
+    var value = 0.0;
+    if i < params.count {
+        let hann = 0.5 * (1.0 - cos(TAU * f32(i) / f32(params.count)));
+        value = samples[i] * hann;
+    }
+    data[reversed] = vec2<f32>(value, 0.0);
+}
+
+@compute @workgroup_size(64)
+fn butterfly(@builtin(global_invocation_id) id: vec3<u32>) {
+    let t = id.x;
+    let h = params.half;
+    let j = t % h;
+
+    if params.stages == 1u {
+        if t >= params.size / 2u {
+            return;
+        }
+        let a = (t / h) * 2u * h + j;
+        let b = a + h;
+        let y = multiply(twiddle(j, 2u * h), data[b]);
+        let x = data[a];
+        data[a] = x + y;
+        data[b] = x - y;
+        return;
+    }
+
+    if t >= params.size / 4u {
+        return;
+    }
+    let a = (t / h) * 4u * h + j;
+    let b = a + h;
+    let c = a + 2u * h;
+    let d = a + 3u * h;
+
+    // First stage: pairs h apart
+    let w1 = twiddle(j, 2u * h);
+    let b1 = multiply(w1, data[b]);
+    let d1 = multiply(w1, data[d]);
+    let a2 = data[a] + b1;
+    let b2 = data[a] - b1;
+    let c2 = data[c] + d1;
+    let d2 = data[c] - d1;
+
+    // Second stage: pairs 2h apart, the odd ones a quarter turn further on
+    let w2 = twiddle(j, 4u * h);
+    let w3 = vec2<f32>(w2.y, -w2.x);
+    let c3 = multiply(w2, c2);
+    let d3 = multiply(w3, d2);
+    data[a] = a2 + c3;
+    data[c] = a2 - c3;
+    data[b] = b2 + d3;
+    data[d] = b2 - d3;
+}
+
+@compute @workgroup_size(64)
+fn level(@builtin(global_invocation_id) id: vec3<u32>) {
+    let k = id.x;
+    if k >= params.size / 2u {
+        return;
+    }
+    // By the windowed length, as the CPU analyzer scales
+    let magnitude = length(data[k]) / sqrt(f32(params.count));
+    let db = 20.0 * log2(max(magnitude, 0.00001)) * 0.30103;
+    let fraction = clamp((db - params.floor) / (params.ceiling - params.floor), 0.0, 1.0);
+    levels[k] = fraction * 2.0 - 1.0;
+}
@@ -0,0 +1,31 @@
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    // x: signed distance from the center (px), y: half width (px), z: feather (px)
+    @location(2) edge: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) edge: vec3<f32>,
+}
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.clip_position = vec4<f32>(input.position, 0.0, 1.0);
+    output.color = input.color;
+    output.edge = input.edge;
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = abs(input.edge.x);
+    let half_width = input.edge.y;
+    let feather = max(input.edge.z, 0.0001);
+    // Full coverage inside the line, fading to nothing across the feather
+    let coverage = clamp((half_width - distance) / feather + 0.5, 0.0, 1.0);
+    return vec4<f32>(input.color.rgb, input.color.a * coverage);
+}
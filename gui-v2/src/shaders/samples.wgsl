@@ -0,0 +1,90 @@
+// Expands raw sample windows into anti-aliased trace quads.
+// One instance per history frame, six vertices per segment.
+
+struct Globals {
+    color: vec4<f32>,
+    viewport: vec2<f32>,
+    // Sample value to clip-space y
+    y_scale: f32,
+    half_width: f32,
+    feather: f32,
+    // Horizontal pan in normalized screen widths
+    x_offset: f32,
+}
+
+struct Frame {
+    offset: u32,
+    count: u32,
+    // Sample index to normalized x
+    x_scale: f32,
+    alpha: f32,
+}
+
+@group(0) @binding(0) var<uniform> globals: Globals;
+@group(0) @binding(1) var<storage, read> samples: array<f32>;
+@group(0) @binding(2) var<storage, read> frames: array<Frame>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) edge: vec3<f32>,
+}
+
+fn sample_point(frame: Frame, index: u32) -> vec2<f32> {
+    return vec2<f32>(
+        (f32(index) * frame.x_scale + globals.x_offset) * 2.0 - 1.0,
+        samples[frame.offset + index] * globals.y_scale,
+    );
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    var output: VertexOutput;
+    let frame = frames[instance_index];
+    let segment = vertex_index / 6u;
+    let corner = vertex_index % 6u;
+
+    // Shorter frames than the longest one end in degenerate, off-screen quads
+    if segment + 1u >= frame.count {
+        output.clip_position = vec4<f32>(2.0, 2.0, 0.0, 1.0);
+        output.color = vec4<f32>(0.0);
+        output.edge = vec3<f32>(0.0);
+        return output;
+    }
+
+    let start = sample_point(frame, segment);
+    let end = sample_point(frame, segment + 1u);
+
+    // Extrude perpendicular to the segment in pixels so width ignores aspect
+    let half_viewport = globals.viewport * 0.5;
+    let delta = (end - start) * half_viewport;
+    let length_px = length(delta);
+    var direction = vec2<f32>(1.0, 0.0);
+    if length_px > 0.0 {
+        direction = delta / length_px;
+    }
+    let extent = globals.half_width + globals.feather;
+    let offset = vec2<f32>(-direction.y, direction.x) * extent / half_viewport;
+
+    // Corner order matches the CPU quads: from+, from-, to+, to+, from-, to-
+    let at_end = corner == 2u || corner == 3u || corner == 5u;
+    let side = select(-1.0, 1.0, corner == 0u || corner == 2u || corner == 3u);
+    let base = select(start, end, at_end);
+
+    output.clip_position = vec4<f32>(base + offset * side, 0.0, 1.0);
+    output.color = vec4<f32>(globals.color.rgb, globals.color.a * frame.alpha);
+    output.edge = vec3<f32>(extent * side, globals.half_width, globals.feather);
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = abs(input.edge.x);
+    let half_width = input.edge.y;
+    let feather = max(input.edge.z, 0.0001);
+    let coverage = clamp((half_width - distance) / feather + 0.5, 0.0, 1.0);
+    return vec4<f32>(input.color.rgb, input.color.a * coverage);
+}
@@ -1,20 +1,161 @@
 use std::collections::VecDeque;
-use std::time::Instant;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use winit::keyboard::KeyCode;
 
-use ozeecubed_core::audio::AudioCapture;
-use ozeecubed_core::oscilloscope::{TriggerSettings, WaveformData};
+use crate::keys::keymap_key;
 
-const PERSISTENCE_FRAMES: usize = 10;
+use ozeecubed_core::audio::calibration::{DEFAULT_REFERENCE_SPL, REFERENCE_SPL_RANGE};
+use ozeecubed_core::audio::{Acquisition, AcquisitionSettings, Calibration, CalibrationStore};
+use ozeecubed_core::config::Preferences;
+use ozeecubed_core::display::{
+    DisplayTheme, Graticule, IntensityMap, LayoutMode, Palette, PersistenceColormap,
+    PersistenceDecay, PersistenceMode, TraceColors, MAX_PERSISTENCE_FRAMES, MAX_TRACE_HISTORY,
+};
+use ozeecubed_core::keymap::{action_for_key, Action};
+use ozeecubed_core::oscilloscope::trigger::{FINE_LEVEL_STEP, LEVEL_STEP};
+use ozeecubed_core::oscilloscope::{
+    BandCursors, BandPower, Decimation, EnvelopeFollower, FrequencyTrack, HarmonicAnalysis,
+    Measurements, PhaseAnalyzer, PhaseResponse, SpectrumAnalyzer, SpectrumOverlap, SpectrumScale,
+    TimeGate, TriggerSettings, Vectorscope, WaveformData,
+};
+use ozeecubed_core::plugin::PluginMeasurement;
+use ozeecubed_core::recording::Recording;
+use ozeecubed_core::scripting::{ScriptAction, ScriptFrame};
+use ozeecubed_core::source::{DataLoss, SignalGenerator};
+
+const DEFAULT_PERSISTENCE_FRAMES: usize = 10;
+/// Brightness left after the configured number of persistence frames
+const PERSISTENCE_RESIDUAL: f32 = 0.05;
+
+/// Acquisition runs at this fixed rate, whatever the display refreshes at
+const TICK: Duration = Duration::from_micros(16_667);
+/// Ticks caught up on at most per update, so a stall (a window being
+/// dragged, a breakpoint) doesn't come back as a burst of catch-up work
+const MAX_TICKS_PER_UPDATE: u32 = 4;
+
+/// Transform lengths of the GPU spectrum, past the CPU analyzer's 4096
+const MIN_GPU_FFT_SIZE: usize = 1024;
+pub const MAX_GPU_FFT_SIZE: usize = 65536;
+const DEFAULT_GPU_FFT_SIZE: usize = 16384;
+
+/// Trace width limits in logical pixels
+pub const MIN_TRACE_WIDTH: f32 = 0.5;
+pub const MAX_TRACE_WIDTH: f32 = 6.0;
+
+/// One frame's raw display window, expanded into a trace on the GPU
+#[derive(Debug, Default)]
+pub struct SampleWindow {
+    pub samples: Vec<f32>,
+    pub samples_per_screen: usize,
+}
 
 pub struct AppState {
     pub waveform: WaveformData,
     pub trigger_settings: TriggerSettings,
     pub waveform_history: VecDeque<Vec<(f32, f32)>>,
-    audio_capture: Option<AudioCapture>,
-    audio_buffer: Vec<f32>,
+    pub sample_history: VecDeque<SampleWindow>,
+    pub intensity: IntensityMap,
+    pub persistence_enabled: bool,
+    pub persistence_frames: usize,
+    pub persistence_mode: PersistenceMode,
+    pub persistence_decay: PersistenceDecay,
+    pub persistence_colormap: PersistenceColormap,
+    pub theme: DisplayTheme,
+    /// Trace colors picked in the desktop app's preferences
+    pub trace_colors: TraceColors,
+    /// Shortcuts moved in the desktop app's preferences
+    preferences: Preferences,
+    pub graticule: Graticule,
+    pub show_axis_labels: bool,
+    /// Frequency, Vpp, Vrms and duty cycle over the scope
+    pub show_measurements: bool,
+    pub trace_width: f32,
+    /// Horizontal pan of the traces, as a fraction of the screen width
+    pub horizontal_position: f32,
+    pub trace_smoothing: bool,
+    /// Cleared to freeze the display on the current capture
+    pub running: bool,
+    /// Shift is held, so keys and sliders adjust in fine steps
+    pub fine_adjust: bool,
+    pub gpu_samples: bool,
+    pub gpu_persistence: bool,
+    /// Draw the linear spectrum from an FFT run on the GPU
+    pub gpu_fft: bool,
+    pub gpu_fft_size: usize,
+    /// The newest input, up to `gpu_fft_size` samples, for the GPU FFT
+    pub fft_input: Vec<f32>,
+    /// dB magnitudes of the latest capture
+    pub spectrum: Vec<f32>,
+    /// A spectrum window is open
+    pub spectrum_enabled: bool,
+    /// How the scope window shares itself with the spectrum
+    pub layout: LayoutMode,
+    pub spectrum_scale: SpectrumScale,
+    /// Of the live spectrum's transforms
+    pub spectrum_overlap: SpectrumOverlap,
+    pub envelope: EnvelopeFollower,
+    /// Envelope trace of the latest capture, empty while the follower is off
+    pub envelope_points: Vec<(f32, f32)>,
+    /// Show the harmonics table and bars on the spectrum
+    pub show_harmonics: bool,
+    /// Of the latest spectrum, while shown
+    pub harmonics: Option<HarmonicAnalysis>,
+    /// Frequency cursors of the band power measurement, while it's on
+    pub band_cursors: Option<BandCursors>,
+    /// Of the latest spectrum, on the linear scale only
+    pub band_power: Option<BandPower>,
+    /// Cursors on the scope bounding the samples the spectrum is taken
+    /// from, while gating is on
+    pub time_gate: Option<TimeGate>,
+    /// Only track while a frequency track window is open
+    pub frequency_track_enabled: bool,
+    /// Instantaneous frequency across the screen of the latest capture
+    pub frequency_track: Option<FrequencyTrack>,
+    /// Only pass the stereo input on while a vectorscope window is open
+    pub vectorscope_enabled: bool,
+    pub vectorscope: Vectorscope,
+    /// Only analyze inter-channel phase while a phase window is open
+    pub phase_enabled: bool,
+    /// Of the averaged cross-spectrum, once it has something coherent
+    pub phase: Option<PhaseResponse>,
+    phase_analyzer: PhaseAnalyzer,
+    pub measurements: Measurements,
+    /// Of every registered measurement plugin
+    pub plugin_measurements: Vec<PluginMeasurement>,
+    /// Input lost since the acquisition started
+    pub data_loss: DataLoss,
+    /// dB SPL of the calibrator or test tone being measured
+    pub reference_spl: f32,
+    /// Calibration of the current input, if it has one
+    pub calibration: Option<Calibration>,
+    /// Show levels in dB SPL rather than dBFS, once calibrated
+    pub show_spl: bool,
+    /// Saved calibrations of every input, by device name
+    calibrations: CalibrationStore,
+    /// See `WaveformData::trigger_position`
+    pub trigger_position: Option<f32>,
+    /// Part of `waveform.samples` on screen
+    display_range: Range<usize>,
+    /// Width of the scope in pixels; point traces keep at most two points
+    /// per column
+    pub display_columns: usize,
+    /// Captures and analyzes the input, or the test signal without one,
+    /// off the UI thread
+    acquisition: Option<Acquisition>,
+    /// Whether `acquisition` is reading a real input
+    live: bool,
+    /// Analyzes time-gated spectra
+    spectrum_analyzer: SpectrumAnalyzer,
     last_update: Instant,
+    /// Time since the last tick, not yet acquired
+    pending: Duration,
     frame_count: usize,
+    /// Bumped whenever the persisted traces are dropped, so the accumulation
+    /// texture is too
+    history_generation: usize,
 }
 
 impl AppState {
@@ -23,116 +164,695 @@ impl AppState {
         let trigger_settings = TriggerSettings::default();
 
         // Try to initialize audio capture
-        let audio_capture = match AudioCapture::new() {
-            Ok(capture) => {
+        let settings = AcquisitionSettings::new(&waveform, &trigger_settings, false);
+        let (acquisition, live) = match Acquisition::start(waveform.sample_rate, settings.clone()) {
+            Ok(acquisition) => {
                 println!("Audio capture initialized");
-                Some(capture)
+                (Some(acquisition), true)
             }
             Err(e) => {
                 eprintln!("Failed to initialize audio capture: {e}");
                 eprintln!("Using test signal");
-                None
+                let sample_rate = waveform.sample_rate;
+                let test_signal = Acquisition::start_source(
+                    move || Ok(SignalGenerator::sine(sample_rate, 440.0, 1.0)),
+                    settings,
+                );
+                (test_signal.ok(), false)
             }
         };
 
+        let calibrations = CalibrationStore::default_path()
+            .map(|path| CalibrationStore::load(&path))
+            .transpose()
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to load calibrations: {e}");
+                None
+            })
+            .unwrap_or_default();
+        let device = acquisition
+            .as_ref()
+            .map_or(SignalGenerator::NAME, Acquisition::device_name);
+        let calibration = calibrations.get(device);
+        let phase_analyzer = PhaseAnalyzer::new(waveform.sample_rate);
+        let preferences = Preferences::default_path()
+            .map(|path| Preferences::load(&path))
+            .transpose()
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to load preferences: {e}");
+                None
+            })
+            .unwrap_or_default();
+
         Self {
             waveform,
             trigger_settings,
             waveform_history: VecDeque::new(),
-            audio_capture,
-            audio_buffer: Vec::new(),
+            sample_history: VecDeque::new(),
+            intensity: {
+                let mut intensity = IntensityMap::default();
+                intensity.set_decay_frames(DEFAULT_PERSISTENCE_FRAMES);
+                intensity
+            },
+            persistence_enabled: true,
+            persistence_frames: DEFAULT_PERSISTENCE_FRAMES,
+            persistence_mode: PersistenceMode::default(),
+            persistence_decay: PersistenceDecay::default(),
+            persistence_colormap: PersistenceColormap::default(),
+            theme: DisplayTheme::default(),
+            trace_colors: preferences.trace_colors,
+            preferences,
+            graticule: Graticule::default(),
+            show_axis_labels: true,
+            show_measurements: true,
+            trace_width: 1.5,
+            horizontal_position: 0.0,
+            trace_smoothing: true,
+            running: true,
+            fine_adjust: false,
+            gpu_samples: true,
+            gpu_persistence: true,
+            gpu_fft: false,
+            gpu_fft_size: DEFAULT_GPU_FFT_SIZE,
+            fft_input: Vec::new(),
+            spectrum: Vec::new(),
+            spectrum_enabled: false,
+            layout: LayoutMode::ScopeOnly,
+            spectrum_scale: SpectrumScale::default(),
+            spectrum_overlap: SpectrumOverlap::default(),
+            envelope: EnvelopeFollower::default(),
+            envelope_points: Vec::new(),
+            show_harmonics: false,
+            harmonics: None,
+            band_cursors: None,
+            band_power: None,
+            time_gate: None,
+            frequency_track_enabled: false,
+            frequency_track: None,
+            vectorscope_enabled: false,
+            vectorscope: Vectorscope::new(),
+            phase_enabled: false,
+            phase: None,
+            phase_analyzer,
+            measurements: Measurements::default(),
+            plugin_measurements: Vec::new(),
+            data_loss: DataLoss::default(),
+            reference_spl: DEFAULT_REFERENCE_SPL,
+            calibration,
+            show_spl: true,
+            calibrations,
+            trigger_position: None,
+            display_range: 0..0,
+            display_columns: 1280,
+            acquisition,
+            live,
+            spectrum_analyzer: SpectrumAnalyzer::new(),
             last_update: Instant::now(),
+            pending: Duration::ZERO,
             frame_count: 0,
+            history_generation: 0,
+        }
+    }
+
+    /// Number of display frames produced so far
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    /// Count of times the persisted traces have been dropped
+    pub fn history_generation(&self) -> usize {
+        self.history_generation
+    }
+
+    /// Whether the trace comes from a real input rather than the test signal
+    pub fn is_live(&self) -> bool {
+        self.live
+    }
+
+    /// The theme's colors with the picked trace colors in them
+    pub fn palette(&self) -> Palette {
+        self.trace_colors.apply(self.theme.palette())
+    }
+
+    /// Save the spectrogram of everything analyzed so far, not just the
+    /// spectrum shown, as a PNG in the working directory
+    pub fn export_spectrogram(&self) {
+        let Some(ref acquisition) = self.acquisition else {
+            eprintln!("Spectrogram export failed: no input");
+            return;
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        let path = PathBuf::from(format!("ozeecubed-spectrogram-{timestamp}.png"));
+        match acquisition
+            .spectrogram()
+            .image(&self.palette())
+            .and_then(|image| image.save(&path))
+        {
+            Ok(()) => println!("Saved spectrogram to {}", path.display()),
+            Err(e) => eprintln!("Spectrogram export failed: {e}"),
+        }
+    }
+
+    /// Name the current input's calibration is saved under
+    fn device_name(&self) -> &str {
+        self.acquisition
+            .as_ref()
+            .map_or(SignalGenerator::NAME, Acquisition::device_name)
+    }
+
+    /// Show or hide the band cursors and their power readout
+    pub fn toggle_band_power(&mut self) {
+        self.band_cursors = match self.band_cursors {
+            Some(_) => None,
+            None => Some(BandCursors::default()),
+        };
+    }
+
+    /// Take the spectrum from just the part of the scope between the gate
+    /// cursors, or go back to the whole buffer
+    pub fn toggle_time_gate(&mut self) {
+        self.time_gate = match self.time_gate {
+            Some(_) => None,
+            None => Some(TimeGate::default()),
+        };
+    }
+
+    pub fn set_reference_spl(&mut self, level: f32) {
+        self.reference_spl = level.clamp(REFERENCE_SPL_RANGE.0, REFERENCE_SPL_RANGE.1);
+    }
+
+    /// Calibrate against the reference tone now playing into the input and
+    /// switch to dB SPL. Remembered for this input across runs.
+    pub fn calibrate(&mut self) {
+        match self
+            .measurements
+            .rms
+            .and_then(|rms| Calibration::from_reference(rms, self.reference_spl))
+        {
+            Some(calibration) => {
+                self.set_calibration(Some(calibration));
+                self.show_spl = true;
+            }
+            None => eprintln!("Nothing to calibrate: the input is silent"),
+        }
+    }
+
+    pub fn clear_calibration(&mut self) {
+        self.set_calibration(None);
+    }
+
+    fn set_calibration(&mut self, calibration: Option<Calibration>) {
+        self.calibration = calibration;
+        let device = self.device_name().to_string();
+        self.calibrations.set(&device, calibration);
+        if let Some(path) = CalibrationStore::default_path() {
+            if let Err(e) = self.calibrations.save(&path) {
+                eprintln!("Failed to save calibration: {e}");
+            }
         }
     }
 
+    /// Added to dBFS levels for display: the calibration offset while
+    /// showing dB SPL, otherwise nothing
+    pub fn level_offset(&self) -> Option<f32> {
+        self.calibration
+            .filter(|_| self.show_spl)
+            .map(|calibration| calibration.offset)
+    }
+
+    /// Catch acquisition up with the time since the last call, in fixed
+    /// ticks, then analyze the newest capture once for drawing. Called once
+    /// per redraw, however often the display refreshes.
     pub fn update(&mut self) {
-        // Update at ~60 FPS
         let now = Instant::now();
-        if now.duration_since(self.last_update).as_millis() < 16 {
+        self.pending += now.duration_since(self.last_update);
+        self.last_update = now;
+        if !self.running {
+            // Nothing to catch up on once running again
+            self.pending = Duration::ZERO;
             return;
         }
-        self.last_update = now;
 
-        // Read audio samples
-        if let Some(ref audio_capture) = self.audio_capture {
-            let new_samples = audio_capture.read_samples(usize::MAX);
-            if !new_samples.is_empty() {
-                self.audio_buffer.extend_from_slice(&new_samples);
-
-                let samples_needed = self.waveform.calculate_samples_per_screen();
-                let max_buffer_size = samples_needed + 200;
+        self.pending = self.pending.min(TICK * MAX_TICKS_PER_UPDATE);
+        let mut ticked = false;
+        while self.pending >= TICK {
+            self.pending -= TICK;
+            self.tick();
+            ticked = true;
+        }
+        if ticked {
+            self.analyze();
+        }
+    }
 
-                if self.audio_buffer.len() > max_buffer_size {
-                    let to_remove = self.audio_buffer.len() - max_buffer_size;
-                    self.audio_buffer.drain(0..to_remove);
+    /// One `TICK` of acquisition: the newest capture, and its trace added to
+    /// the persistence history
+    fn tick(&mut self) {
+        // Pick up the latest triggered and analyzed capture
+        if let Some(ref acquisition) = self.acquisition {
+            acquisition.set_settings(AcquisitionSettings {
+                spectrum_scale: self.spectrum_scale,
+                spectrum_overlap: self.spectrum_overlap,
+                stereo: self.vectorscope_enabled || self.phase_enabled,
+                ..AcquisitionSettings::new(
+                    &self.waveform,
+                    &self.trigger_settings,
+                    self.spectrum_shown() && self.time_gate.is_none(),
+                )
+            });
+            if let Some(frame) = acquisition.latest() {
+                self.waveform.copy_samples(&frame.window);
+                self.display_range = frame.display_range;
+                self.trigger_position = frame.trigger_position;
+                self.measurements = frame.measurements;
+                self.plugin_measurements = frame.plugin_measurements;
+                self.data_loss = frame.data_loss;
+                self.update_stereo(&frame.stereo);
+                if let Some(spectrum) = frame.spectrum {
+                    self.spectrum = spectrum;
+                }
+                if self.uses_gpu_fft() {
+                    self.fft_input.extend_from_slice(&frame.new_samples);
+                    let excess = self.fft_input.len().saturating_sub(self.gpu_fft_size);
+                    self.fft_input.drain(..excess);
                 }
-
-                self.waveform.update_samples(self.audio_buffer.clone());
             }
-        } else {
-            // Generate test signal
-            self.generate_test_signal();
         }
 
-        // Update persistence history every frame
+        // Persistence history moves on once per tick, so it fades at the
+        // same rate on any display
         self.frame_count += 1;
-        let points = self.waveform.get_display_samples(&self.trigger_settings);
-        self.add_to_history(points);
+        if !self.envelope.shows_raw() {
+            // The envelope replaces the waveform, history included
+            self.clear_history();
+        } else if self.uses_gpu_samples() {
+            self.add_samples_to_history();
+        } else {
+            let mut points = self.recycled_points();
+            self.waveform.display_decimated_into(
+                self.display_range.clone(),
+                self.display_columns,
+                &mut points,
+            );
+            self.add_to_history(points);
+        }
+    }
+
+    /// Measurements drawn from the newest capture, taken once per redraw
+    /// however many ticks it caught up on
+    fn analyze(&mut self) {
+        // The gate replaces the rolling spectrum with one of just the
+        // samples between its cursors
+        if let Some(gate) = self.time_gate.filter(|_| self.spectrum_shown()) {
+            self.spectrum_analyzer
+                .set_scale(self.spectrum_scale, self.waveform.sample_rate);
+            self.spectrum_analyzer
+                .update_gated(gate.samples(&self.waveform.samples, self.display_range.clone()));
+            self.spectrum = self.spectrum_analyzer.magnitudes().to_vec();
+        }
+        // Harmonics are found on linear FFT bins only
+        self.harmonics = if self.show_harmonics
+            && self.spectrum_shown()
+            && self.spectrum_scale == SpectrumScale::Linear
+        {
+            HarmonicAnalysis::of(&self.spectrum, self.waveform.sample_rate)
+        } else {
+            None
+        };
+        // So is band power
+        self.band_power = self
+            .band_cursors
+            .filter(|_| self.spectrum_shown() && self.spectrum_scale == SpectrumScale::Linear)
+            .and_then(|band| band.measure(&self.spectrum, self.waveform.sample_rate));
+
+        self.frequency_track = if self.frequency_track_enabled {
+            FrequencyTrack::of(
+                &self.waveform,
+                self.display_range.clone(),
+                self.display_columns,
+            )
+        } else {
+            None
+        };
+
+        if self.envelope.is_enabled() {
+            self.waveform.display_follower_into(
+                self.display_range.clone(),
+                &self.envelope,
+                self.display_columns,
+                &mut self.envelope_points,
+            );
+        } else {
+            self.envelope_points.clear();
+        }
+    }
+
+    /// Only analyze while a window or pane shows the spectrum
+    pub fn spectrum_shown(&self) -> bool {
+        self.spectrum_enabled || self.layout.shows_spectrum()
+    }
+
+    /// Whether traces are drawn from raw samples expanded on the GPU.
+    ///
+    /// Accumulation persistence always is. Otherwise graded persistence needs
+    /// the CPU-side intensity map, the heat map a color per trace, and the
+    /// other draw styles than peak detect the CPU-side filter or
+    /// interpolation, so they take the point path.
+    pub fn uses_gpu_samples(&self) -> bool {
+        self.gpu_persistence
+            || (self.gpu_samples
+                && self.persistence_mode == PersistenceMode::Fade
+                && self.persistence_colormap == PersistenceColormap::Trace
+                && self.waveform.decimation == Decimation::PeakDetect)
+    }
+
+    /// Whether the spectrum trace comes from the GPU FFT. Only the linear
+    /// scale is transformed there, and the time gate's spectrum is of just
+    /// its samples, so those stay on the CPU.
+    pub fn uses_gpu_fft(&self) -> bool {
+        self.gpu_fft
+            && self.spectrum_shown()
+            && self.spectrum_scale == SpectrumScale::Linear
+            && self.time_gate.is_none()
+    }
+
+    pub fn toggle_gpu_fft(&mut self) {
+        self.gpu_fft = !self.gpu_fft;
+        self.fft_input.clear();
+    }
+
+    /// Double the GPU FFT length, back to the smallest after the largest
+    pub fn cycle_gpu_fft_size(&mut self) {
+        self.gpu_fft_size = if self.gpu_fft_size >= MAX_GPU_FFT_SIZE {
+            MIN_GPU_FFT_SIZE
+        } else {
+            self.gpu_fft_size * 2
+        };
+    }
+
+    /// Fraction of the accumulated trace energy kept from one frame to the next
+    pub fn persistence_retention(&self) -> f32 {
+        if self.persistence_enabled {
+            self.persistence_decay
+                .retention(TICK)
+                .unwrap_or_else(|| PERSISTENCE_RESIDUAL.powf(1.0 / self.persistence_frames as f32))
+        } else {
+            0.0
+        }
+    }
+
+    /// Opacity of trace `index` of the `count` in the history, oldest
+    /// first. The history moves on once a tick, so with a timed decay a
+    /// trace's age is the ticks it's behind the newest.
+    pub fn history_alpha(&self, index: usize, count: usize) -> f32 {
+        if self.persistence_decay.is_timed() {
+            let behind = count.saturating_sub(index + 1) as u32;
+            self.persistence_decay.alpha(TICK * behind)
+        } else {
+            (index as f32 + 1.0) / count as f32
+        }
+    }
+
+    /// Accumulation persistence costs the same at any depth, so it can go
+    /// far deeper than the trace history
+    pub fn max_persistence_frames(&self) -> usize {
+        if self.gpu_persistence {
+            MAX_PERSISTENCE_FRAMES
+        } else {
+            MAX_TRACE_HISTORY
+        }
+    }
+
+    fn history_limit(&self) -> usize {
+        // The accumulation texture holds the history; only the newest frame is kept
+        if self.gpu_persistence || !self.persistence_enabled {
+            1
+        } else if self.persistence_decay.is_timed() {
+            // Even the shortest fade outlasts the cap at the tick rate
+            MAX_TRACE_HISTORY
+        } else {
+            self.persistence_frames
+        }
+    }
+
+    /// A buffer for the next trace: the oldest one's once the history is
+    /// full, so steady-state capture doesn't allocate
+    fn recycled_points(&mut self) -> Vec<(f32, f32)> {
+        if self.waveform_history.len() >= self.history_limit() {
+            self.waveform_history.pop_front().unwrap_or_default()
+        } else {
+            Vec::new()
+        }
     }
 
     fn add_to_history(&mut self, points: Vec<(f32, f32)>) {
         if !points.is_empty() {
+            self.intensity
+                .accumulate_after(&points, self.persistence_decay, TICK);
             self.waveform_history.push_back(points);
-            if self.waveform_history.len() > PERSISTENCE_FRAMES {
+            while self.waveform_history.len() > self.history_limit() {
                 self.waveform_history.pop_front();
             }
         }
     }
 
-    fn generate_test_signal(&mut self) {
-        let sample_rate = 48000;
-        let frequency = 440.0;
-        let num_samples = (sample_rate as f32 / 60.0) as usize;
+    fn add_samples_to_history(&mut self) {
+        let range = self.display_range.clone();
+        if range.is_empty() {
+            return;
+        }
 
-        let mut samples = Vec::with_capacity(num_samples);
-        let start_idx = self.audio_buffer.len();
+        // Recycle the oldest window's allocation once the history is full
+        let mut window = if self.sample_history.len() >= self.history_limit() {
+            self.sample_history.pop_front().unwrap_or_default()
+        } else {
+            SampleWindow::default()
+        };
+        window.samples.clear();
+        window
+            .samples
+            .extend_from_slice(&self.waveform.samples[range]);
+        window.samples_per_screen = self.waveform.calculate_samples_per_screen();
+        self.sample_history.push_back(window);
 
-        for i in 0..num_samples {
-            let t = (start_idx + i) as f32 / sample_rate as f32;
-            let sample = (2.0 * std::f32::consts::PI * frequency * t).sin();
-            samples.push(sample);
+        while self.sample_history.len() > self.history_limit() {
+            self.sample_history.pop_front();
         }
+    }
 
-        self.audio_buffer.extend_from_slice(&samples);
+    /// Drop both trace histories, e.g. when switching between draw paths
+    fn clear_history(&mut self) {
+        self.waveform_history.clear();
+        self.sample_history.clear();
+        self.intensity.clear();
+        self.history_generation += 1;
+    }
 
-        let samples_needed = self.waveform.calculate_samples_per_screen();
-        let max_buffer_size = samples_needed + 200;
+    /// Start the persisted traces over, e.g. to see what infinite
+    /// persistence catches from here on
+    pub fn clear_persistence(&mut self) {
+        self.clear_history();
+    }
+
+    pub fn toggle_gpu_samples(&mut self) {
+        self.gpu_samples = !self.gpu_samples;
+        self.clear_history();
+    }
 
-        if self.audio_buffer.len() > max_buffer_size {
-            let to_remove = self.audio_buffer.len() - max_buffer_size;
-            self.audio_buffer.drain(0..to_remove);
+    pub fn toggle_gpu_persistence(&mut self) {
+        self.gpu_persistence = !self.gpu_persistence;
+        self.clear_history();
+        // Leaving accumulation mode brings back the lower frame cap
+        self.set_persistence_frames(self.persistence_frames);
+    }
+
+    pub fn toggle_persistence(&mut self) {
+        self.persistence_enabled = !self.persistence_enabled;
+        if !self.persistence_enabled {
+            self.intensity.clear();
         }
+    }
 
-        self.waveform.update_samples(self.audio_buffer.clone());
+    pub fn set_persistence_frames(&mut self, frames: usize) {
+        self.persistence_frames = frames.clamp(1, self.max_persistence_frames());
+        self.intensity.set_decay_frames(self.persistence_frames);
     }
 
-    pub fn handle_key(&mut self, key: KeyCode) {
-        match key {
-            // Time/Div controls
-            KeyCode::ArrowRight => self.waveform.time_per_division *= 1.1,
-            KeyCode::ArrowLeft => self.waveform.time_per_division /= 1.1,
+    pub fn cycle_persistence_decay(&mut self) {
+        self.persistence_decay = self.persistence_decay.next();
+    }
 
-            // Volts/Div controls
-            KeyCode::ArrowUp => self.waveform.volts_per_division *= 1.1,
-            KeyCode::ArrowDown => self.waveform.volts_per_division /= 1.1,
+    pub fn cycle_persistence_colormap(&mut self) {
+        self.persistence_colormap = self.persistence_colormap.next();
+    }
 
-            // Trigger controls
-            KeyCode::KeyT => self.trigger_settings.enabled = !self.trigger_settings.enabled,
-            KeyCode::BracketRight => self.trigger_settings.level += 0.1,
-            KeyCode::BracketLeft => self.trigger_settings.level -= 0.1,
+    pub fn cycle_persistence_mode(&mut self) {
+        self.persistence_mode = self.persistence_mode.next();
+        self.clear_history();
+    }
 
+    pub fn cycle_decimation(&mut self) {
+        self.waveform.decimation = self.waveform.decimation.next();
+        self.clear_history();
+    }
+
+    pub fn set_trace_width(&mut self, width: f32) {
+        self.trace_width = width.clamp(MIN_TRACE_WIDTH, MAX_TRACE_WIDTH);
+    }
+
+    /// Step to the next division preset, keeping the timebase and
+    /// persistence map in step with the new grid
+    /// Shift the traces by `dx` screen widths, keeping part of the record on screen
+    pub fn pan(&mut self, dx: f32) {
+        self.horizontal_position = (self.horizontal_position + dx).clamp(-1.0, 1.0);
+    }
+
+    /// Scale time/div by `factor`, keeping the instant at screen x `anchor` fixed
+    pub fn zoom_time(&mut self, factor: f32, anchor: f32) {
+        self.waveform.time_per_division *= factor;
+        let position = anchor - (anchor - self.horizontal_position) / factor;
+        self.horizontal_position = position.clamp(-1.0, 1.0);
+    }
+
+    pub fn cycle_grid_divisions(&mut self) {
+        self.graticule.cycle_divisions();
+        self.waveform.horizontal_divisions = self.graticule.horizontal_divisions;
+        self.intensity
+            .set_vertical_divisions(self.graticule.vertical_divisions);
+    }
+
+    /// Hand (left, right) pairs to the vectorscope and phase analysis,
+    /// whichever are open
+    fn update_stereo(&mut self, pairs: &[(f32, f32)]) {
+        if self.vectorscope_enabled {
+            self.vectorscope.update(pairs);
+        }
+        if self.phase_enabled && self.phase_analyzer.push(pairs) {
+            self.phase = self.phase_analyzer.response();
+        }
+    }
+
+    /// Forget the phase average, e.g. once its window closes
+    pub fn clear_phase(&mut self) {
+        self.phase_analyzer.clear();
+        self.phase = None;
+    }
+
+    /// What a script sees of this frame, `time` seconds after it started
+    pub fn script_frame(&self, time: f32) -> ScriptFrame {
+        ScriptFrame {
+            measurements: self.measurements,
+            time,
+            time_per_division: self.waveform.time_per_division,
+            volts_per_division: self.waveform.volts_per_division,
+            trigger_enabled: self.trigger_settings.enabled,
+            trigger_level: self.trigger_settings.level,
+            running: self.running,
+        }
+    }
+
+    /// Carry out a script's request. Screenshots need a renderer, so the
+    /// window manager takes those.
+    pub fn apply_script_action(&mut self, action: ScriptAction) {
+        match action {
+            ScriptAction::SetTimePerDivision(seconds) if seconds > 0.0 => {
+                self.waveform.time_per_division = seconds
+            }
+            ScriptAction::SetVoltsPerDivision(volts) if volts > 0.0 => {
+                self.waveform.volts_per_division = volts
+            }
+            ScriptAction::SetTimePerDivision(_) | ScriptAction::SetVoltsPerDivision(_) => {
+                eprintln!("Script asked for a scale that isn't positive");
+            }
+            ScriptAction::SetTriggerEnabled(enabled) => self.trigger_settings.enabled = enabled,
+            ScriptAction::SetTriggerLevel(level) => self.trigger_settings.set_level(level),
+            ScriptAction::Start => self.running = true,
+            ScriptAction::Stop => self.running = false,
+            ScriptAction::SaveWav(path) => match self.save_wav(&path) {
+                Ok(()) => println!("Saved capture to {}", path.display()),
+                Err(e) => eprintln!("{e}"),
+            },
+            ScriptAction::Screenshot(_) => {}
+        }
+    }
+
+    /// Write the capture buffer to `path` as a WAV file
+    pub fn save_wav(&self, path: &Path) -> Result<(), String> {
+        let recording = Recording::new(self.waveform.samples.clone(), self.waveform.sample_rate);
+        fs::write(path, recording.to_wav_bytes()?)
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+
+    /// Trigger level change per key press
+    fn trigger_step(&self) -> f32 {
+        if self.fine_adjust {
+            FINE_LEVEL_STEP
+        } else {
+            LEVEL_STEP
+        }
+    }
+
+    /// Shortcuts of the keymap shared with the iced app first, then the
+    /// wgpu app's own
+    pub fn handle_key(&mut self, key: KeyCode) {
+        if let Some(action) = keymap_key(key).and_then(|key| action_for_key(&self.preferences, key))
+        {
+            self.apply_action(action);
+            return;
+        }
+        match key {
+            KeyCode::Home => self.horizontal_position = 0.0,
+            KeyCode::KeyR => self.graticule.enabled = !self.graticule.enabled,
+            KeyCode::KeyI => self.cycle_grid_divisions(),
+            KeyCode::PageUp => self.set_trace_width(self.trace_width + 0.5),
+            KeyCode::PageDown => self.set_trace_width(self.trace_width - 0.5),
+            KeyCode::KeyS => self.trace_smoothing = !self.trace_smoothing,
+            KeyCode::KeyU => self.toggle_gpu_samples(),
+            KeyCode::KeyO => self.toggle_gpu_persistence(),
+            KeyCode::KeyK => self.show_measurements = !self.show_measurements,
+            KeyCode::KeyX => self.toggle_gpu_fft(),
             _ => {}
         }
     }
+
+    fn apply_action(&mut self, action: Action) {
+        let fine = self.fine_adjust;
+        match action {
+            Action::IncreaseTimeScale if fine => self.waveform.fine_time_scale(true),
+            Action::DecreaseTimeScale if fine => self.waveform.fine_time_scale(false),
+            Action::IncreaseVoltageScale if fine => self.waveform.fine_voltage_scale(true),
+            Action::DecreaseVoltageScale if fine => self.waveform.fine_voltage_scale(false),
+            Action::IncreaseTimeScale => self.waveform.increase_time_scale(),
+            Action::DecreaseTimeScale => self.waveform.decrease_time_scale(),
+            Action::IncreaseVoltageScale => self.waveform.increase_voltage_scale(),
+            Action::DecreaseVoltageScale => self.waveform.decrease_voltage_scale(),
+            Action::IncreaseTriggerLevel => self
+                .trigger_settings
+                .set_level(self.trigger_settings.level + self.trigger_step()),
+            Action::DecreaseTriggerLevel => self
+                .trigger_settings
+                .set_level(self.trigger_settings.level - self.trigger_step()),
+            Action::CycleDecimation => self.cycle_decimation(),
+            Action::ToggleTrigger => self.trigger_settings.toggle_enabled(),
+            Action::ToggleTriggerEdge => self.trigger_settings.toggle_edge(),
+            Action::TogglePersistence => self.toggle_persistence(),
+            Action::CyclePersistenceMode => self.cycle_persistence_mode(),
+            Action::IncreasePersistence => self.set_persistence_frames(self.persistence_frames + 1),
+            Action::DecreasePersistence => {
+                self.set_persistence_frames(self.persistence_frames.saturating_sub(1))
+            }
+            Action::CycleTheme => self.theme = self.theme.next(),
+            Action::ToggleAxisLabels => self.show_axis_labels = !self.show_axis_labels,
+            Action::ToggleHarmonics => self.show_harmonics = !self.show_harmonics,
+            Action::CycleSpectrumScale => self.spectrum_scale = self.spectrum_scale.next(),
+            Action::ToggleBandPower => self.toggle_band_power(),
+            Action::ToggleTimeGate => self.toggle_time_gate(),
+            // The second pane's views open in windows of their own here,
+            // and there's no logging or preferences view
+            Action::CycleLayout => self.layout = self.layout.next(),
+            Action::CyclePlot | Action::ToggleLogging | Action::OpenPreferences => {}
+        }
+    }
 }
@@ -0,0 +1,569 @@
+use std::time::Instant;
+
+use egui::{Event, Modifiers, PointerButton, Pos2, RawInput, Rect, Slider, Vec2, ViewportId};
+use winit::{
+    event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+    window::Window,
+};
+
+use crate::state::{AppState, MAX_TRACE_WIDTH, MIN_TRACE_WIDTH};
+use ozeecubed_core::audio::calibration::{rms_dbfs, REFERENCE_SPL_RANGE};
+use ozeecubed_core::display::{format_time, format_volts};
+use ozeecubed_core::oscilloscope::envelope::{ATTACK_RANGE, RELEASE_RANGE};
+use ozeecubed_core::oscilloscope::trigger::{TriggerEdge, FINE_LEVEL_STEP, LEVEL_STEP};
+use ozeecubed_core::oscilloscope::waveform::step_fine_toward;
+
+/// Points scrolled per wheel notch
+const SCROLL_LINE_HEIGHT: f32 = 50.0;
+
+/// One frame of tessellated control panel output, ready for the GPU
+pub struct UiFrame {
+    pub primitives: Vec<egui::ClippedPrimitive>,
+    pub textures_delta: egui::TexturesDelta,
+    pub pixels_per_point: f32,
+}
+
+/// On-screen controls drawn with egui over the scope display
+pub struct ControlPanel {
+    context: egui::Context,
+    events: Vec<Event>,
+    pointer_pos: Pos2,
+    pixels_per_point: f32,
+    start: Instant,
+}
+
+impl ControlPanel {
+    pub fn new(window: &Window) -> Self {
+        Self {
+            context: egui::Context::default(),
+            events: Vec::new(),
+            pointer_pos: Pos2::ZERO,
+            pixels_per_point: window.scale_factor() as f32,
+            start: Instant::now(),
+        }
+    }
+
+    /// Queue a window event for the next frame.
+    ///
+    /// Returns true when the event landed on the panel and should not
+    /// reach the scope's own handlers.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.pixels_per_point = *scale_factor as f32;
+                false
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer_pos = Pos2::new(
+                    position.x as f32 / self.pixels_per_point,
+                    position.y as f32 / self.pixels_per_point,
+                );
+                self.events.push(Event::PointerMoved(self.pointer_pos));
+                false
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.events.push(Event::PointerGone);
+                false
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let button = match button {
+                    MouseButton::Left => PointerButton::Primary,
+                    MouseButton::Right => PointerButton::Secondary,
+                    MouseButton::Middle => PointerButton::Middle,
+                    _ => return false,
+                };
+                self.events.push(Event::PointerButton {
+                    pos: self.pointer_pos,
+                    button,
+                    pressed: *state == ElementState::Pressed,
+                    modifiers: Modifiers::NONE,
+                });
+                // Releases always reach the scope so a drag can't get stuck
+                *state == ElementState::Pressed
+                    && (self.context.is_pointer_over_area() || self.context.wants_pointer_input())
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => Vec2::new(*x, *y) * SCROLL_LINE_HEIGHT,
+                    MouseScrollDelta::PixelDelta(position) => {
+                        Vec2::new(position.x as f32, position.y as f32) / self.pixels_per_point
+                    }
+                };
+                self.events.push(Event::Scroll(delta));
+                self.context.is_pointer_over_area()
+            }
+            _ => false,
+        }
+    }
+
+    /// Lay out the panel against the current state, applying any control changes
+    pub fn run(&mut self, window: &Window, state: &mut AppState) -> UiFrame {
+        self.pixels_per_point = window.scale_factor() as f32;
+        let size = window.inner_size();
+
+        let mut raw_input = RawInput {
+            screen_rect: Some(Rect::from_min_size(
+                Pos2::ZERO,
+                Vec2::new(size.width as f32, size.height as f32) / self.pixels_per_point,
+            )),
+            time: Some(self.start.elapsed().as_secs_f64()),
+            events: std::mem::take(&mut self.events),
+            ..Default::default()
+        };
+        raw_input
+            .viewports
+            .entry(ViewportId::ROOT)
+            .or_default()
+            .native_pixels_per_point = Some(self.pixels_per_point);
+
+        self.context.set_visuals(if state.theme.is_light() {
+            egui::Visuals::light()
+        } else {
+            egui::Visuals::dark()
+        });
+
+        let output = self
+            .context
+            .run(raw_input, |ctx| build_controls(ctx, state));
+        let primitives = self
+            .context
+            .tessellate(output.shapes, output.pixels_per_point);
+
+        UiFrame {
+            primitives,
+            textures_delta: output.textures_delta,
+            pixels_per_point: output.pixels_per_point,
+        }
+    }
+}
+
+fn build_controls(ctx: &egui::Context, state: &mut AppState) {
+    egui::TopBottomPanel::bottom("controls").show(ctx, |ui| {
+        ui.horizontal_top(|ui| {
+            ui.group(|ui| time_controls(ui, state));
+            ui.group(|ui| voltage_controls(ui, state));
+            ui.group(|ui| trigger_controls(ui, state));
+            ui.group(|ui| persistence_controls(ui, state));
+            ui.group(|ui| envelope_controls(ui, state));
+            ui.group(|ui| display_controls(ui, state));
+            ui.group(|ui| calibration_controls(ui, state));
+            ui.group(|ui| measurements_display(ui, state));
+        });
+    });
+}
+
+fn time_controls(ui: &mut egui::Ui, state: &mut AppState) {
+    ui.vertical(|ui| {
+        ui.strong("Time/Div");
+        ui.horizontal(|ui| {
+            if ui.button("-").clicked() {
+                state.waveform.decrease_time_scale();
+            }
+            ui.label(format_time(state.waveform.time_per_division));
+            if ui.button("+").clicked() {
+                state.waveform.increase_time_scale();
+            }
+        });
+        // Logarithmic slider from 10µs to 1s
+        let mut time_log = state.waveform.time_per_division.log10();
+        if ui
+            .add(Slider::new(&mut time_log, -5.0..=0.0).show_value(false))
+            .changed()
+        {
+            let target = 10_f32.powf(time_log);
+            state.waveform.time_per_division = if state.fine_adjust {
+                step_fine_toward(state.waveform.time_per_division, target)
+            } else {
+                target
+            };
+        }
+        if ui.button(state.waveform.decimation.label()).clicked() {
+            state.cycle_decimation();
+        }
+    });
+}
+
+fn voltage_controls(ui: &mut egui::Ui, state: &mut AppState) {
+    ui.vertical(|ui| {
+        ui.strong("Volts/Div");
+        ui.horizontal(|ui| {
+            if ui.button("-").clicked() {
+                state.waveform.decrease_voltage_scale();
+            }
+            ui.label(format_volts(state.waveform.volts_per_division));
+            if ui.button("+").clicked() {
+                state.waveform.increase_voltage_scale();
+            }
+        });
+        // Logarithmic slider from 0.01V to 10V
+        let mut volts_log = state.waveform.volts_per_division.log10();
+        if ui
+            .add(Slider::new(&mut volts_log, -2.0..=1.0).show_value(false))
+            .changed()
+        {
+            let target = 10_f32.powf(volts_log);
+            state.waveform.volts_per_division = if state.fine_adjust {
+                step_fine_toward(state.waveform.volts_per_division, target)
+            } else {
+                target
+            };
+        }
+    });
+}
+
+fn trigger_controls(ui: &mut egui::Ui, state: &mut AppState) {
+    let fine = state.fine_adjust;
+    let trigger = &mut state.trigger_settings;
+    ui.vertical(|ui| {
+        ui.strong("Trigger");
+        ui.horizontal(|ui| {
+            if ui
+                .button(if trigger.enabled { "ON" } else { "OFF" })
+                .clicked()
+            {
+                trigger.toggle_enabled();
+            }
+            let edge = match trigger.edge {
+                TriggerEdge::Rising => "Rising",
+                TriggerEdge::Falling => "Falling",
+            };
+            if ui.button(edge).clicked() {
+                trigger.toggle_edge();
+            }
+            if ui.button(trigger.mode.label()).clicked() {
+                trigger.mode = trigger.mode.next();
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("-").clicked() {
+                trigger.set_level(trigger.level - LEVEL_STEP);
+            }
+            ui.label(format_volts(trigger.level));
+            if ui.button("+").clicked() {
+                trigger.set_level(trigger.level + LEVEL_STEP);
+            }
+        });
+        let mut level = trigger.level;
+        if ui
+            .add(
+                Slider::new(&mut level, -10.0..=10.0)
+                    .step_by(if fine { FINE_LEVEL_STEP } else { LEVEL_STEP } as f64)
+                    .show_value(false),
+            )
+            .changed()
+        {
+            if fine {
+                trigger.step_level_toward(level);
+            } else {
+                trigger.set_level(level);
+            }
+        }
+    });
+}
+
+fn persistence_controls(ui: &mut egui::Ui, state: &mut AppState) {
+    ui.vertical(|ui| {
+        ui.strong("Persistence");
+        ui.horizontal(|ui| {
+            if ui
+                .button(if state.persistence_enabled {
+                    "ON"
+                } else {
+                    "OFF"
+                })
+                .clicked()
+            {
+                state.toggle_persistence();
+            }
+            if ui.button(state.persistence_mode.label()).clicked() {
+                state.cycle_persistence_mode();
+            }
+            if ui
+                .button(state.persistence_decay.label())
+                .on_hover_text("How long traces take to fade")
+                .clicked()
+            {
+                state.cycle_persistence_decay();
+            }
+            if ui
+                .button(state.persistence_colormap.label())
+                .on_hover_text("Trace color or heat map")
+                .clicked()
+            {
+                state.cycle_persistence_colormap();
+            }
+            if ui
+                .button("Clear")
+                .on_hover_text("Start the persisted traces over")
+                .clicked()
+            {
+                state.clear_persistence();
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("-").clicked() {
+                state.set_persistence_frames(state.persistence_frames.saturating_sub(1));
+            }
+            ui.label(format!("{}", state.persistence_frames));
+            if ui.button("+").clicked() {
+                state.set_persistence_frames(state.persistence_frames + 1);
+            }
+        });
+        let mut frames = state.persistence_frames;
+        if ui
+            .add(Slider::new(&mut frames, 1..=state.max_persistence_frames()).show_value(false))
+            .changed()
+        {
+            state.set_persistence_frames(frames);
+        }
+    });
+}
+
+fn envelope_controls(ui: &mut egui::Ui, state: &mut AppState) {
+    ui.vertical(|ui| {
+        ui.strong("Envelope");
+        let envelope = &mut state.envelope;
+        if ui.button(envelope.mode.label()).clicked() {
+            envelope.mode = envelope.mode.next();
+        }
+        // Logarithmic sliders, like time/div
+        let mut attack = envelope.attack().log10();
+        ui.label(format!("Attack {}", format_time(envelope.attack())));
+        if ui
+            .add(
+                Slider::new(&mut attack, ATTACK_RANGE.0.log10()..=ATTACK_RANGE.1.log10())
+                    .show_value(false),
+            )
+            .changed()
+        {
+            envelope.set_attack(10_f32.powf(attack));
+        }
+        let mut release = envelope.release().log10();
+        ui.label(format!("Release {}", format_time(envelope.release())));
+        if ui
+            .add(
+                Slider::new(
+                    &mut release,
+                    RELEASE_RANGE.0.log10()..=RELEASE_RANGE.1.log10(),
+                )
+                .show_value(false),
+            )
+            .changed()
+        {
+            envelope.set_release(10_f32.powf(release));
+        }
+    });
+}
+
+fn display_controls(ui: &mut egui::Ui, state: &mut AppState) {
+    ui.vertical(|ui| {
+        ui.strong("Display");
+        ui.horizontal(|ui| {
+            if ui.button(state.theme.label()).clicked() {
+                state.theme = state.theme.next();
+            }
+            if ui
+                .button(state.layout.label())
+                .on_hover_text("Share the window with the spectrum")
+                .clicked()
+            {
+                state.layout = state.layout.next();
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .button(if state.graticule.enabled {
+                    "Grid ON"
+                } else {
+                    "Grid OFF"
+                })
+                .clicked()
+            {
+                state.graticule.enabled = !state.graticule.enabled;
+            }
+            if ui.button(state.graticule.divisions_label()).clicked() {
+                state.cycle_grid_divisions();
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .button(if state.show_axis_labels {
+                    "Labels"
+                } else {
+                    "No labels"
+                })
+                .clicked()
+            {
+                state.show_axis_labels = !state.show_axis_labels;
+            }
+            ui.checkbox(&mut state.show_measurements, "Readout")
+                .on_hover_text("Frequency, Vpp, Vrms and duty cycle over the scope");
+        });
+        ui.horizontal(|ui| {
+            let mut width = state.trace_width;
+            if ui
+                .add(
+                    Slider::new(&mut width, MIN_TRACE_WIDTH..=MAX_TRACE_WIDTH)
+                        .step_by(0.5)
+                        .suffix(" px"),
+                )
+                .changed()
+            {
+                state.set_trace_width(width);
+            }
+            ui.checkbox(&mut state.trace_smoothing, "Smooth");
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut state.show_harmonics, "Harmonics")
+                .on_hover_text("Harmonics table and bars on the spectrum");
+            if ui
+                .button(state.spectrum_scale.label())
+                .on_hover_text("Spectrum frequency axis: linear FFT bins, semitones or mel bands")
+                .clicked()
+            {
+                state.spectrum_scale = state.spectrum_scale.next();
+            }
+            if ui
+                .button(format!("Overlap {}", state.spectrum_overlap.label()))
+                .on_hover_text("How much each spectrum window overlaps the last: more gives finer steps in time")
+                .clicked()
+            {
+                state.spectrum_overlap = state.spectrum_overlap.next();
+            }
+            if ui
+                .button("Spectrogram PNG")
+                .on_hover_text("Save every spectrum analyzed so far, with labeled axes")
+                .clicked()
+            {
+                state.export_spectrogram();
+            }
+        });
+        let mut measuring_band = state.band_cursors.is_some();
+        if ui
+            .checkbox(&mut measuring_band, "Band power")
+            .on_hover_text("Power and noise floor between two cursors dragged on the spectrum")
+            .changed()
+        {
+            state.toggle_band_power();
+        }
+        let mut time_gated = state.time_gate.is_some();
+        if ui
+            .checkbox(&mut time_gated, "Time-gated FFT")
+            .on_hover_text("Spectrum of just the samples between two cursors dragged on the scope")
+            .changed()
+        {
+            state.toggle_time_gate();
+        }
+        ui.checkbox(&mut state.vectorscope.auto_gain, "Vectorscope auto gain")
+            .on_hover_text("Scale the vectorscope so quiet material fills it");
+        let mut gpu_persistence = state.gpu_persistence;
+        if ui
+            .checkbox(&mut gpu_persistence, "GPU persistence")
+            .on_hover_text("Accumulate traces in a decaying texture (up to 500 frames)")
+            .changed()
+        {
+            state.toggle_gpu_persistence();
+        }
+        let mut gpu_samples = state.gpu_samples;
+        if ui
+            .checkbox(&mut gpu_samples, "GPU samples")
+            .on_hover_text("Expand raw samples on the GPU (Fade persistence only)")
+            .changed()
+        {
+            state.toggle_gpu_samples();
+        }
+        ui.horizontal(|ui| {
+            let mut gpu_fft = state.gpu_fft;
+            if ui
+                .checkbox(&mut gpu_fft, "GPU FFT")
+                .on_hover_text("Compute and draw the linear spectrum on the GPU")
+                .changed()
+            {
+                state.toggle_gpu_fft();
+            }
+            if ui
+                .button(format!("{} pt", state.gpu_fft_size))
+                .on_hover_text("GPU FFT length")
+                .clicked()
+            {
+                state.cycle_gpu_fft_size();
+            }
+        });
+    });
+}
+
+/// Calibrate with the reference tone playing into the input
+fn calibration_controls(ui: &mut egui::Ui, state: &mut AppState) {
+    ui.vertical(|ui| {
+        ui.strong("Calibration");
+        let mut level = state.reference_spl;
+        if ui
+            .add(
+                Slider::new(&mut level, REFERENCE_SPL_RANGE.0..=REFERENCE_SPL_RANGE.1)
+                    .step_by(1.0)
+                    .suffix(" dB SPL"),
+            )
+            .on_hover_text("Level of the calibrator or test tone")
+            .changed()
+        {
+            state.set_reference_spl(level);
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Calibrate").clicked() {
+                state.calibrate();
+            }
+            if ui.button("Clear").clicked() {
+                state.clear_calibration();
+            }
+        });
+        ui.add_enabled_ui(state.calibration.is_some(), |ui| {
+            ui.checkbox(&mut state.show_spl, "Show dB SPL");
+        });
+    });
+}
+
+fn measurements_display(ui: &mut egui::Ui, state: &AppState) {
+    let measurements = &state.measurements;
+    ui.vertical(|ui| {
+        ui.strong("Measurements");
+        ui.label(match measurements.frequency {
+            Some(freq) if freq >= 1000.0 => format!("Freq: {:.2} kHz", freq / 1000.0),
+            Some(freq) => format!("Freq: {freq:.1} Hz"),
+            None => "Freq: --".to_string(),
+        });
+        ui.label(match measurements.peak_to_peak {
+            Some(pk_pk) => format!("Vpp: {pk_pk:.3} V"),
+            None => "Vpp: --".to_string(),
+        });
+        ui.label(match measurements.rms {
+            Some(rms) => format!("Vrms: {rms:.3} V"),
+            None => "Vrms: --".to_string(),
+        });
+        ui.label(match measurements.duty_cycle {
+            Some(duty) => format!("Duty: {duty:.1}%"),
+            None => "Duty: --".to_string(),
+        });
+        ui.label(match (measurements.rms, state.level_offset()) {
+            (Some(rms), Some(offset)) => format!("Level: {:.1} dB SPL", rms_dbfs(rms) + offset),
+            (Some(rms), None) => format!("Level: {:.1} dBFS", rms_dbfs(rms)),
+            (None, _) => "Level: --".to_string(),
+        });
+        for measurement in &state.plugin_measurements {
+            ui.label(match measurement.value {
+                Some(value) => format!("{}: {value:.3} {}", measurement.name, measurement.unit),
+                None => format!("{}: --", measurement.name),
+            });
+        }
+        // Measurements spanning a gap in the input are off
+        let loss = state.data_loss;
+        if loss.any() {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                format!(
+                    "Input lost: {} dropped, {} overruns",
+                    loss.dropped_samples, loss.overruns
+                ),
+            );
+        }
+    });
+}
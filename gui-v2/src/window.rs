@@ -1,63 +1,307 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 use winit::{
-    dpi::PhysicalSize,
+    dpi::{PhysicalPosition, PhysicalSize},
     event::WindowEvent,
     event_loop::ActiveEventLoop,
     keyboard::KeyCode,
-    window::{Window, WindowAttributes, WindowId},
+    monitor::MonitorHandle,
+    window::{Fullscreen, Window, WindowAttributes, WindowId, WindowLevel},
 };
 
-use crate::renderer::Renderer;
+use ozeecubed_core::config::WindowSettings;
+use ozeecubed_core::scripting::{Script, ScriptAction};
+
+use crate::layout::{panes, Region};
+use crate::pointer::Pointer;
+use crate::renderer::{Renderer, View};
 use crate::state::AppState;
+use crate::ui::ControlPanel;
 
 pub struct WindowManager {
     windows: HashMap<WindowId, WindowState>,
     app_state: AppState,
+    /// Automation from `--script`, dropped at its first error
+    script: Option<Script>,
+    /// When the script was loaded, for its `m.time`
+    script_start: Instant,
+    /// How the scope window opens, from `window.txt` and the command line
+    window_settings: WindowSettings,
 }
 
 struct WindowState {
     window: Arc<Window>,
     renderer: Renderer,
+    view: View,
+    /// Only the scope window carries the control panel
+    panel: Option<ControlPanel>,
+    pointer: Pointer,
+    /// winit can set the window level but not report it back
+    always_on_top: bool,
+}
+
+impl WindowState {
+    /// `settings` only shape the scope window; the others have their own
+    /// sizes
+    fn new(event_loop: &ActiveEventLoop, view: View, settings: &WindowSettings) -> Self {
+        let window_attrs = match view {
+            View::Scope => scope_attributes(event_loop, settings),
+            View::Spectrum => WindowAttributes::default()
+                .with_title("OzeeCubed - Spectrum")
+                .with_inner_size(PhysicalSize::new(960, 540)),
+            View::FrequencyTrack => WindowAttributes::default()
+                .with_title("OzeeCubed - Frequency Track")
+                .with_inner_size(PhysicalSize::new(960, 540)),
+            View::Vectorscope => WindowAttributes::default()
+                .with_title("OzeeCubed - Vectorscope")
+                .with_inner_size(PhysicalSize::new(600, 600)),
+            View::Phase => WindowAttributes::default()
+                .with_title("OzeeCubed - Phase")
+                .with_inner_size(PhysicalSize::new(960, 540)),
+        };
+
+        let window = Arc::new(event_loop.create_window(window_attrs).unwrap());
+        let renderer = pollster::block_on(Renderer::new(Arc::clone(&window), view));
+        let panel = (view == View::Scope).then(|| ControlPanel::new(&window));
+
+        Self {
+            window,
+            renderer,
+            view,
+            panel,
+            pointer: Pointer::new(),
+            always_on_top: false,
+        }
+    }
+
+    fn toggle_fullscreen(&self) {
+        let fullscreen = match self.window.fullscreen() {
+            Some(_) => None,
+            None => Some(Fullscreen::Borderless(None)),
+        };
+        self.window.set_fullscreen(fullscreen);
+    }
+
+    /// Keep the window above others, e.g. over a DAW while tracking
+    fn toggle_always_on_top(&mut self) {
+        self.always_on_top = !self.always_on_top;
+        self.window.set_window_level(if self.always_on_top {
+            WindowLevel::AlwaysOnTop
+        } else {
+            WindowLevel::Normal
+        });
+    }
+}
+
+/// The scope window as `settings` ask. A remembered position is only used
+/// while a monitor still covers it; otherwise the window is centered on the
+/// monitor asked for, or left to the system.
+fn scope_attributes(event_loop: &ActiveEventLoop, settings: &WindowSettings) -> WindowAttributes {
+    let (width, height) = settings.size;
+    let attributes = WindowAttributes::default()
+        .with_title(
+            settings
+                .title
+                .as_deref()
+                .unwrap_or("OzeeCubed - Oscilloscope"),
+        )
+        .with_inner_size(PhysicalSize::new(width, height))
+        .with_decorations(settings.decorations);
+
+    let on_screen = settings.position.filter(|&(x, y)| {
+        event_loop.available_monitors().any(|monitor| {
+            let origin = monitor.position();
+            let size = monitor.size();
+            (origin.x..origin.x + size.width as i32).contains(&x)
+                && (origin.y..origin.y + size.height as i32).contains(&y)
+        })
+    });
+    if let Some((x, y)) = on_screen {
+        return attributes.with_position(PhysicalPosition::new(x, y));
+    }
+    match settings
+        .monitor
+        .as_deref()
+        .and_then(|name| find_monitor(event_loop, name))
+    {
+        Some(monitor) => {
+            let origin = monitor.position();
+            let size = monitor.size();
+            attributes.with_position(PhysicalPosition::new(
+                origin.x + (size.width as i32 - width as i32).max(0) / 2,
+                origin.y + (size.height as i32 - height as i32).max(0) / 2,
+            ))
+        }
+        None => {
+            if let Some(ref name) = settings.monitor {
+                eprintln!("No monitor {name}; opening on the default one");
+            }
+            attributes
+        }
+    }
+}
+
+/// Save where the scope window is, for the next run to open it there.
+/// The rest of the saved settings are kept as they were, so a title or
+/// monitor given on the command line only lasts the one run.
+fn remember_geometry(window: &Window) {
+    if window.fullscreen().is_some() {
+        return;
+    }
+    let Some(path) = WindowSettings::default_path() else {
+        return;
+    };
+    let result = WindowSettings::load(&path).and_then(|mut settings| {
+        let size = window.inner_size();
+        settings.size = (size.width, size.height);
+        if let Ok(position) = window.outer_position() {
+            settings.position = Some((position.x, position.y));
+        }
+        if let Some(name) = window.current_monitor().and_then(|monitor| monitor.name()) {
+            settings.monitor = Some(name);
+        }
+        settings.save(&path)
+    });
+    if let Err(e) = result {
+        eprintln!("{e}");
+    }
+}
+
+/// The monitor called `name`, or numbered `name` from 0
+fn find_monitor(event_loop: &ActiveEventLoop, name: &str) -> Option<MonitorHandle> {
+    let mut monitors = event_loop.available_monitors();
+    match name.parse::<usize>() {
+        Ok(index) => monitors.nth(index),
+        Err(_) => monitors.find(|monitor| monitor.name().as_deref() == Some(name)),
+    }
 }
 
 impl WindowManager {
-    pub fn new(event_loop: &ActiveEventLoop) -> Self {
+    pub fn new(
+        event_loop: &ActiveEventLoop,
+        script: Option<Script>,
+        window_settings: WindowSettings,
+    ) -> Self {
         let mut windows = HashMap::new();
         let app_state = AppState::new();
 
         // Create main window
-        let window_attrs = WindowAttributes::default()
-            .with_title("OzeeCubed - Oscilloscope")
-            .with_inner_size(PhysicalSize::new(1280, 720));
+        let main_window = WindowState::new(event_loop, View::Scope, &window_settings);
+        windows.insert(main_window.window.id(), main_window);
 
-        let window = Arc::new(event_loop.create_window(window_attrs).unwrap());
+        Self {
+            windows,
+            app_state,
+            script,
+            script_start: Instant::now(),
+            window_settings,
+        }
+    }
 
-        let renderer = pollster::block_on(Renderer::new(Arc::clone(&window)));
-        let window_id = window.id();
+    pub fn handle_window_event(&mut self, window_id: WindowId, event: &WindowEvent) -> bool {
+        // Return true if event was handled, false otherwise
+        self.windows
+            .get_mut(&window_id)
+            .and_then(|window_state| window_state.panel.as_mut())
+            .is_some_and(|panel| panel.handle_event(event))
+    }
 
-        windows.insert(window_id, WindowState { window, renderer });
+    /// Shift held makes keys and sliders adjust in fine steps
+    pub fn set_fine_adjust(&mut self, fine: bool) {
+        self.app_state.fine_adjust = fine;
+    }
 
-        Self { windows, app_state }
+    pub fn handle_keyboard(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: WindowId,
+        key: KeyCode,
+    ) {
+        match key {
+            KeyCode::KeyN => self.toggle_window(event_loop, View::Spectrum),
+            KeyCode::KeyM => self.toggle_window(event_loop, View::FrequencyTrack),
+            KeyCode::KeyV => self.toggle_window(event_loop, View::Vectorscope),
+            KeyCode::KeyJ => self.toggle_window(event_loop, View::Phase),
+            // Window keys act on the window that has focus
+            KeyCode::F10 => self.with_window(window_id, |ws| ws.toggle_always_on_top()),
+            KeyCode::F11 => self.with_window(window_id, |ws| ws.toggle_fullscreen()),
+            KeyCode::F12 => self.with_window(window_id, |ws| ws.renderer.request_screenshot()),
+            _ => self.app_state.handle_key(key),
+        }
     }
 
-    pub fn handle_window_event(&mut self, _window_id: WindowId, event: &WindowEvent) -> bool {
-        // Return true if event was handled, false otherwise
-        match event {
-            WindowEvent::MouseInput { .. } | WindowEvent::CursorMoved { .. } => {
-                // UI event handling will go here
-                false
+    fn with_window(&mut self, window_id: WindowId, f: impl FnOnce(&mut WindowState)) {
+        if let Some(window_state) = self.windows.get_mut(&window_id) {
+            f(window_state);
+        }
+    }
+
+    /// Mouse input that the control panel didn't take: trigger and pan on
+    /// the scope, band cursors on the spectrum, in whichever pane of the
+    /// scope window they're in
+    pub fn handle_pointer(&mut self, window_id: WindowId, event: &WindowEvent) {
+        if let Some(window_state) = self.windows.get_mut(&window_id) {
+            let size = window_state.window.inner_size();
+            let pointer = &mut window_state.pointer;
+            match window_state.view {
+                View::Scope => {
+                    let scale_factor = window_state.window.scale_factor() as f32;
+                    let (scope, spectrum) = panes(self.app_state.layout, size, scale_factor);
+                    pointer.handle_split_event(event, scope, spectrum, &mut self.app_state)
+                }
+                View::Spectrum => {
+                    pointer.handle_spectrum_event(event, Region::full(size), &mut self.app_state)
+                }
+                View::FrequencyTrack | View::Vectorscope | View::Phase => {}
             }
-            _ => false,
         }
     }
 
-    pub fn handle_keyboard(&mut self, _window_id: WindowId, key: KeyCode) {
-        self.app_state.handle_key(key);
+    /// Open a window showing `view`, or close it if one is already open
+    pub fn toggle_window(&mut self, event_loop: &ActiveEventLoop, view: View) {
+        let existing = self
+            .windows
+            .iter()
+            .find(|(_, window_state)| window_state.view == view)
+            .map(|(&window_id, _)| window_id);
+
+        match existing {
+            Some(window_id) => self.close_window(window_id),
+            None => {
+                let window_state = WindowState::new(event_loop, view, &self.window_settings);
+                self.windows.insert(window_state.window.id(), window_state);
+                self.sync_views();
+            }
+        }
     }
 
     pub fn close_window(&mut self, window_id: WindowId) {
-        self.windows.remove(&window_id);
+        if let Some(window_state) = self.windows.remove(&window_id) {
+            if window_state.view == View::Scope {
+                remember_geometry(&window_state.window);
+            }
+        }
+        self.sync_views();
+    }
+
+    /// Only do the work for views that some window is showing
+    fn sync_views(&mut self) {
+        let showing = |view| {
+            self.windows
+                .values()
+                .any(|window_state| window_state.view == view)
+        };
+        self.app_state.spectrum_enabled = showing(View::Spectrum);
+        self.app_state.frequency_track_enabled = showing(View::FrequencyTrack);
+        self.app_state.vectorscope_enabled = showing(View::Vectorscope);
+        if !self.app_state.vectorscope_enabled {
+            self.app_state.vectorscope.clear();
+        }
+        self.app_state.phase_enabled = showing(View::Phase);
+        if !self.app_state.phase_enabled {
+            self.app_state.clear_phase();
+        }
     }
 
     pub fn is_empty(&self) -> bool {
@@ -77,13 +321,57 @@ impl WindowManager {
             .unwrap_or(PhysicalSize::new(800, 600))
     }
 
-    pub fn update(&mut self, _window_id: WindowId) {
+    pub fn update(&mut self, window_id: WindowId) {
+        // Point traces are decimated to the width they're drawn at
+        let is_scope = self
+            .windows
+            .get(&window_id)
+            .is_some_and(|window_state| window_state.view == View::Scope);
+        if is_scope {
+            self.app_state.display_columns = self.get_size(window_id).width as usize;
+        }
         self.app_state.update();
+        // Once per scope frame, which also takes its screenshots
+        if is_scope {
+            self.run_script(window_id);
+        }
+    }
+
+    fn run_script(&mut self, window_id: WindowId) {
+        let Some(ref mut script) = self.script else {
+            return;
+        };
+        let frame = self
+            .app_state
+            .script_frame(self.script_start.elapsed().as_secs_f32());
+        match script.on_frame(&frame) {
+            Ok(actions) => {
+                for action in actions {
+                    match action {
+                        ScriptAction::Screenshot(path) => self
+                            .with_window(window_id, |ws| ws.renderer.request_screenshot_to(path)),
+                        action => self.app_state.apply_script_action(action),
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                eprintln!("Script stopped");
+                self.script = None;
+            }
+        }
     }
 
     pub fn render(&mut self, window_id: WindowId) -> Result<(), wgpu::SurfaceError> {
         if let Some(window_state) = self.windows.get_mut(&window_id) {
-            window_state.renderer.render(&self.app_state)
+            let ui = window_state
+                .panel
+                .as_mut()
+                .map(|panel| panel.run(&window_state.window, &mut self.app_state));
+            let scale_factor = window_state.window.scale_factor() as f32;
+            window_state
+                .renderer
+                .render(&self.app_state, scale_factor, ui.as_ref())
         } else {
             Ok(())
         }
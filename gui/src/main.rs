@@ -1,16 +1,55 @@
 mod ui;
 
-use ozeecubed_core::audio::AudioCapture;
-use ozeecubed_core::oscilloscope::{TriggerSettings, WaveformData};
+use ozeecubed_core::audio::calibration::{DEFAULT_REFERENCE_SPL, REFERENCE_SPL_RANGE};
+use ozeecubed_core::audio::{
+    Acquisition, AcquisitionSettings, AudioError, Calibration, CalibrationStore, InputSelection,
+    LatencyMeter, LoopbackResult, LoopbackTest, Monitor,
+};
+use ozeecubed_core::config::Preferences;
+use ozeecubed_core::decode::{
+    DecodedEvent, Decoder, DtmfDecoder, FskConfig, FskDecoder, StreamDecoder, UartConfig,
+    UartDecoder,
+};
+use ozeecubed_core::display::{DisplayTheme, Graticule, LayoutMode, Palette};
+use ozeecubed_core::dsp::{BandwidthLimit, FilterBlock, FilterKind};
+use ozeecubed_core::keymap;
+use ozeecubed_core::oscilloscope::search::PULSE_WIDTH_RANGE;
+use ozeecubed_core::oscilloscope::trigger::{FINE_LEVEL_STEP, LEVEL_STEP};
+use ozeecubed_core::oscilloscope::waveform::{step_1_2_5, step_fine_toward};
+use ozeecubed_core::oscilloscope::{
+    search, AutoRange, EnvelopeFollower, EventSearch, FrequencyTrack, MathExpression, Measurements,
+    SpectrumOverlap, TriggerSettings, WaveformData,
+};
+use ozeecubed_core::plugin::{self, CrestFactor, PluginMeasurement};
+use ozeecubed_core::recording::{DataLogger, LoggerConfig, Recording, ReplayPlayer};
+use ozeecubed_core::source::{
+    DataLoss, NetworkSource, SerialConfig, SerialSource, SignalGenerator,
+};
 
 use iced::keyboard::{self, Key};
 use iced::widget::{column, container, row};
 use iced::{Element, Event, Length, Subscription, Task, Theme};
-use std::time::Duration;
-use ui::controls::{build_controls, ControlMessage, ControlState, LayoutMode, Measurements};
-use ui::{SpectrumCanvas, WaveformCanvas};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use ui::controls::{
+    build_controls, build_loss_warning, build_transport, ControlMessage, ControlState, DecoderMode,
+    PlotMode, TransportState,
+};
+use ui::designer::{DesignerMessage, FilterDesigner};
+use ui::filters::{FilterDialog, FilterMessage};
+use ui::preferences::{control_for_key, modal, PreferenceMessage, PreferencesDialog};
+use ui::{
+    FrequencyTrackCanvas, PhaseCanvas, SpectrumCanvas, VectorscopeCanvas, WaveformCanvas,
+    WaveformFrame, ZoomCanvas,
+};
 
 fn main() -> iced::Result {
+    // Plugins register here, before the first capture is measured
+    plugin::register_measurement(CrestFactor);
+
     iced::application("OzeeCubed", OzScope::update, OzScope::view)
         .subscription(OzScope::subscription)
         .theme(OzScope::theme)
@@ -20,71 +59,326 @@ fn main() -> iced::Result {
 struct OzScope {
     waveform: WaveformData,
     trigger_settings: TriggerSettings,
+    envelope: EnvelopeFollower,
     canvas: WaveformCanvas,
     spectrum_canvas: SpectrumCanvas,
-    audio_capture: Option<AudioCapture>,
-    audio_buffer: Vec<f32>,
+    /// Of the live spectrum's transforms, done on the acquisition thread
+    spectrum_overlap: SpectrumOverlap,
+    frequency_canvas: FrequencyTrackCanvas,
+    vectorscope_canvas: VectorscopeCanvas,
+    phase_canvas: PhaseCanvas,
+    zoom_canvas: ZoomCanvas,
+    /// Captures and analyzes the live input off the UI thread
+    acquisition: Option<Acquisition>,
+    measurements: Measurements,
+    plugin_measurements: Vec<PluginMeasurement>,
+    /// Part of the capture on screen, from the trigger
+    display_range: Range<usize>,
+    /// See `WaveformData::trigger_position`
+    trigger_position: Option<f32>,
     layout_mode: LayoutMode,
+    plot_mode: PlotMode,
+    data_logger: Option<DataLogger>,
+    /// Plays the live input through the speakers, while on
+    monitor: Option<Monitor>,
+    /// How far the display runs behind the live input
+    latency: LatencyMeter,
+    /// Listening for its click, while running
+    loopback: Option<LoopbackTest>,
+    loopback_result: Option<LoopbackResult>,
+    /// Input lost since the live input opened
+    data_loss: DataLoss,
+    /// When `data_loss` last grew
+    lost_at: Option<Instant>,
+    replay: Option<ReplayPlayer>,
+    /// Adapts volts/div to the signal, while on
+    auto_range: Option<AutoRange>,
+    /// Shift is held, so keys and sliders adjust in fine steps
+    fine_adjust: bool,
+    /// Bandwidth limit of channels A and B on the live input
+    bandwidth: [BandwidthLimit; 2],
+    /// Event search over the recording being replayed
+    search: EventSearch,
+    search_hits: Vec<Range<usize>>,
+    /// Index of the hit last jumped to
+    current_hit: Option<usize>,
+    last_tick: Instant,
+    /// Set when a control changes how the trace is drawn, so the next tick
+    /// redraws even without new samples. Otherwise a tick with nothing new
+    /// leaves the frame, its history and the canvas cache alone.
+    settings_changed: bool,
+    decoder_mode: DecoderMode,
+    uart_config: UartConfig,
+    stream_decoder: Option<StreamDecoder>,
+    decoded_overlay: Arc<[DecodedEvent]>,
+    display_theme: DisplayTheme,
+    /// Saved calibrations of every input, by device name
+    calibrations: CalibrationStore,
+    calibration: Option<Calibration>,
+    reference_spl: f32,
+    show_spl: bool,
+    /// Saved preferences, including the shortcut keys in use
+    preferences: Preferences,
+    /// The preferences view, while it's open over the scope
+    preferences_dialog: Option<PreferencesDialog>,
+    filter_dialog: Option<FilterDialog>,
+    /// The filter designer, while it's open in place of the controls
+    designer: Option<FilterDesigner>,
+    /// Math channel expression as typed, compiled whenever it parses
+    math_text: String,
+    math: Option<MathExpression>,
+    math_error: Option<String>,
+    math_enabled: bool,
+    math_per_div: f32,
+    /// The math channel evaluated alongside `waveform.samples`
+    math_samples: Option<Arc<[f32]>>,
+    /// Where each capture's traces are decimated before the canvas gets
+    /// its own copy, kept so that doesn't allocate every tick
+    trace_points: Vec<(f32, f32)>,
 }
 
+const LOG_DIRECTORY: &str = "ozeecubed-logs";
+const EXPORT_DIRECTORY: &str = "ozeecubed-exports";
+const LIVE_SAMPLE_RATE: u32 = 48000;
+/// How long a network input waits for its sender, whose first packet gives
+/// the rate to analyze at
+const NETWORK_WAIT: Duration = Duration::from_secs(3);
+/// How long the warning stays up after input is lost
+const LOSS_WARNING: Duration = Duration::from_secs(3);
+/// Offered in the math channel until something else is typed
+const DEFAULT_MATH: &str = "A - avg(A)";
+/// Search level change per press
+const SEARCH_LEVEL_STEP: f32 = 0.05;
+
 #[derive(Debug, Clone)]
 enum Message {
     AudioUpdate,
     Control(ControlMessage),
+    Preferences(PreferenceMessage),
+    Filters(FilterMessage),
+    Designer(DesignerMessage),
     EventOccurred(Event),
 }
 
 impl OzScope {
     fn new() -> (Self, Task<Message>) {
-        let sample_rate = 48000;
+        let preferences = Preferences::default_path()
+            .map(|path| Preferences::load(&path))
+            .transpose()
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to load preferences: {e}");
+                None
+            })
+            .unwrap_or_default();
 
-        let audio_capture = match AudioCapture::new() {
-            Ok(capture) => {
-                println!("Audio capture initialized successfully");
-                Some(capture)
-            }
-            Err(e) => {
-                eprintln!("Failed to initialize audio capture: {e}");
+        let mut waveform = WaveformData::new(LIVE_SAMPLE_RATE);
+        waveform.time_per_division = preferences.time_per_division;
+        let trigger_settings = TriggerSettings::default();
+        let acquisition = start_acquisition(&preferences, &waveform, &trigger_settings);
+        if let Some(ref acquisition) = acquisition {
+            waveform.sample_rate = acquisition.sample_rate();
+        }
+        let sample_rate = waveform.sample_rate;
+
+        let calibrations = CalibrationStore::default_path()
+            .map(|path| CalibrationStore::load(&path))
+            .transpose()
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to load calibrations: {e}");
                 None
-            }
+            })
+            .unwrap_or_default();
+
+        let mut scope = OzScope {
+            waveform,
+            trigger_settings,
+            envelope: EnvelopeFollower::default(),
+            canvas: WaveformCanvas::new(),
+            spectrum_canvas: SpectrumCanvas::new(),
+            spectrum_overlap: SpectrumOverlap::default(),
+            frequency_canvas: FrequencyTrackCanvas::new(),
+            vectorscope_canvas: VectorscopeCanvas::new(),
+            phase_canvas: PhaseCanvas::new(sample_rate),
+            zoom_canvas: ZoomCanvas::new(),
+            acquisition,
+            measurements: Measurements::default(),
+            display_range: 0..0,
+            trigger_position: None,
+            plugin_measurements: Vec::new(),
+            layout_mode: LayoutMode::SideBySide,
+            plot_mode: PlotMode::Spectrum,
+            data_logger: None,
+            monitor: None,
+            latency: LatencyMeter::default(),
+            loopback: None,
+            loopback_result: None,
+            data_loss: DataLoss::default(),
+            lost_at: None,
+            replay: None,
+            auto_range: None,
+            fine_adjust: false,
+            bandwidth: [BandwidthLimit::Full; 2],
+            search: EventSearch::default(),
+            search_hits: Vec::new(),
+            current_hit: None,
+            last_tick: Instant::now(),
+            settings_changed: true,
+            decoder_mode: DecoderMode::Off,
+            uart_config: UartConfig::default(),
+            stream_decoder: None,
+            decoded_overlay: Arc::default(),
+            display_theme: DisplayTheme::default(),
+            calibrations,
+            calibration: None,
+            reference_spl: DEFAULT_REFERENCE_SPL,
+            show_spl: true,
+            preferences_dialog: None,
+            filter_dialog: None,
+            designer: None,
+            math_text: DEFAULT_MATH.to_string(),
+            math: MathExpression::parse(DEFAULT_MATH).ok(),
+            math_error: None,
+            math_enabled: false,
+            math_per_div: 1.0,
+            math_samples: None,
+            trace_points: Vec::new(),
+            preferences,
         };
+        scope.set_theme(scope.preferences.theme);
+        scope
+            .canvas
+            .set_persistence_frames(scope.preferences.persistence_frames);
+        scope
+            .canvas
+            .set_persistence_mode(scope.preferences.persistence_mode);
+        scope
+            .canvas
+            .set_persistence_decay(scope.preferences.persistence_decay);
+        scope
+            .canvas
+            .set_persistence_colormap(scope.preferences.persistence_colormap);
+        if !scope.preferences.persistence_enabled {
+            scope.canvas.toggle_persistence();
+        }
+        scope.calibration = scope.calibrations.get(scope.device_name());
+        scope.apply_calibration();
 
-        (
-            OzScope {
-                waveform: WaveformData::new(sample_rate),
-                trigger_settings: TriggerSettings::default(),
-                canvas: WaveformCanvas::new(),
-                spectrum_canvas: SpectrumCanvas::new(),
-                audio_capture,
-                audio_buffer: Vec::new(),
-                layout_mode: LayoutMode::SideBySide,
-            },
-            Task::none(),
-        )
+        // A recording passed on the command line starts in replay mode
+        if let Some(path) = std::env::args().nth(1) {
+            scope.start_replay(Path::new(&path));
+        }
+
+        (scope, Task::none())
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::AudioUpdate => {
-                self.update_audio();
-                // Add current waveform to history for persistence effect
-                let trigger_settings = TriggerSettings::default();
-                let points = self.waveform.get_display_samples(&trigger_settings);
-                self.canvas.add_to_history(points);
+                if !self.update_audio() && !self.settings_changed {
+                    return Task::none();
+                }
+                self.settings_changed = false;
+                // Decode the visible buffer for the on-trace overlay
+                self.decoded_overlay =
+                    self.stream_decoder.as_ref().map_or_else(Arc::default, |d| {
+                        d.decoder()
+                            .decode(&self.waveform.samples, self.waveform.sample_rate)
+                            .into()
+                    });
+                // Triggered once here, then shared with every redraw and
+                // the persistence history
+                let columns = self.canvas.columns();
+                let mut frame = WaveformFrame::new(
+                    &self.waveform,
+                    self.display_range.clone(),
+                    self.trigger_position,
+                    &self.envelope,
+                    columns,
+                    &mut self.trace_points,
+                );
+                if let Some(ref math) = self.math_samples {
+                    frame = frame.with_math(
+                        &self.waveform,
+                        math,
+                        self.math_per_div,
+                        columns,
+                        &mut self.trace_points,
+                    );
+                }
+                // The zoom pane is a second view of the same record, at the
+                // timebase of its region
+                if let Some(zoom) = self.canvas.zoom() {
+                    let range = zoom.range(frame.display_range.clone());
+                    self.waveform
+                        .display_zoomed_into(range, columns, &mut self.trace_points);
+                    self.zoom_canvas.set_trace(
+                        self.trace_points.as_slice(),
+                        zoom.time_per_division(self.waveform.time_per_division),
+                        self.waveform.volts_per_division,
+                        self.canvas.graticule(),
+                    );
+                }
+                if self.plot_mode == PlotMode::FrequencyTrack {
+                    self.frequency_canvas.set_track(FrequencyTrack::of(
+                        &self.waveform,
+                        frame.display_range.clone(),
+                        columns,
+                    ));
+                }
+                // The gate replaces the rolling spectrum with one of just
+                // the samples between its cursors
+                if let Some(gate) = self.canvas.time_gate() {
+                    self.spectrum_canvas.update_gated(
+                        gate.samples(&self.waveform.samples, frame.display_range.clone()),
+                        self.waveform.sample_rate,
+                    );
+                }
+                self.canvas.set_frame(frame);
                 self.canvas.clear_cache();
             }
             Message::Control(control) => {
                 self.handle_control(control);
                 self.canvas.clear_cache();
+                self.settings_changed = true;
+            }
+            Message::Preferences(message) => {
+                self.handle_preferences(message);
+                self.canvas.clear_cache();
+                self.settings_changed = true;
             }
+            Message::Filters(message) => self.handle_filters(message),
+            Message::Designer(message) => self.handle_designer(message),
             Message::EventOccurred(event) => {
-                if let Event::Keyboard(keyboard::Event::KeyPressed {
-                    key, modifiers: _, ..
+                if let Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) = event {
+                    self.fine_adjust = modifiers.shift();
+                } else if let Event::Keyboard(keyboard::Event::KeyPressed {
+                    key, modifiers, ..
                 }) = event
                 {
-                    if let Some(control) = Self::key_to_control(&key) {
+                    if let Some(ref mut dialog) = self.preferences_dialog {
+                        // Shortcuts are off while the preferences are open
+                        match key {
+                            Key::Named(keyboard::key::Named::Escape) if dialog.is_binding() => {
+                                dialog.cancel_binding();
+                            }
+                            Key::Named(keyboard::key::Named::Escape) => {
+                                self.preferences_dialog = None;
+                            }
+                            Key::Character(c) if dialog.is_binding() => dialog.bind(c.as_str()),
+                            _ => {}
+                        }
+                    } else if self.filter_dialog.is_some() {
+                        if key == Key::Named(keyboard::key::Named::Escape) {
+                            self.handle_filters(FilterMessage::Close);
+                        }
+                    } else if self.designer.is_some()
+                        && key == Key::Named(keyboard::key::Named::Escape)
+                    {
+                        self.handle_designer(DesignerMessage::Close);
+                    } else if let Some(control) = self.key_to_control(&key, modifiers) {
                         self.handle_control(control);
                         self.canvas.clear_cache();
+                        self.settings_changed = true;
                     }
                 }
             }
@@ -93,98 +387,201 @@ impl OzScope {
     }
 
     fn view(&self) -> Element<'_, Message> {
-        let scope_canvas = self.canvas.view(self.waveform.clone());
-        let spectrum_canvas = self.spectrum_canvas.view().map(|_| Message::AudioUpdate);
-
-        let measurements = Measurements {
-            frequency: self.waveform.calculate_frequency(),
-            peak_to_peak: self.waveform.calculate_peak_to_peak(),
-            rms: self.waveform.calculate_rms(),
-            duty_cycle: self.waveform.calculate_duty_cycle(),
+        let scope_canvas = Element::from(self.canvas.view(
+            &self.waveform,
+            &self.trigger_settings,
+            Arc::clone(&self.decoded_overlay),
+        ))
+        .map(Message::Control);
+        let plot_canvas = match self.plot_mode {
+            PlotMode::Spectrum => self.spectrum_canvas.view().map(Message::Control),
+            PlotMode::FrequencyTrack => self.frequency_canvas.view().map(|_| Message::AudioUpdate),
+            PlotMode::Vectorscope => self.vectorscope_canvas.view().map(|_| Message::AudioUpdate),
+            PlotMode::Phase => self.phase_canvas.view().map(|_| Message::AudioUpdate),
+            PlotMode::Zoom => self.zoom_canvas.view().map(Message::Control),
         };
 
         let control_state = ControlState {
             time_per_div: self.waveform.time_per_division,
+            decimation: self.waveform.decimation,
             volts_per_div: self.waveform.volts_per_division,
+            auto_range: self.auto_range.is_some(),
+            bandwidth: self.bandwidth,
             trigger_enabled: self.trigger_settings.enabled,
+            trigger_mode: self.trigger_settings.mode,
             trigger_level: self.trigger_settings.level,
             persistence_enabled: self.canvas.is_persistence_enabled(),
             persistence_frames: self.canvas.get_persistence_frames(),
+            persistence_mode: self.canvas.persistence_mode(),
+            persistence_decay: self.canvas.persistence_decay(),
+            persistence_colormap: self.canvas.persistence_colormap(),
+            envelope: self.envelope,
+            logging_enabled: self.data_logger.is_some(),
+            logged_seconds: self
+                .data_logger
+                .as_ref()
+                .map_or(0.0, DataLogger::recorded_seconds),
+            monitor_gain: self.monitor.as_ref().map(Monitor::gain_db),
+            monitor_muted: self.monitor.as_ref().is_some_and(Monitor::is_muted),
+            monitor_feedback: self
+                .monitor
+                .as_ref()
+                .is_some_and(Monitor::feedback_detected),
+            latency: self.latency.latency().filter(|_| self.replay.is_none()),
+            loopback_running: self.loopback.is_some(),
+            loopback_result: self.loopback_result,
+            data_loss: self.data_loss,
+            decoder_mode: self.decoder_mode,
+            baud_rate: self.uart_config.baud_rate,
+            decoded_text: self
+                .stream_decoder
+                .as_ref()
+                .map(|d| match self.decoder_mode {
+                    DecoderMode::Dtmf => format_decoded_digits(d.history().iter()),
+                    _ => format_decoded_table(d.history().iter()),
+                })
+                .unwrap_or_default(),
+            theme: self.display_theme,
+            active_filters: self
+                .preferences
+                .filters
+                .iter()
+                .filter(|block| !block.bypassed)
+                .count(),
+            plot_mode: self.plot_mode,
+            vectorscope_auto_gain: self.vectorscope_canvas.is_auto_gain(),
+            show_harmonics: self.spectrum_canvas.is_showing_harmonics(),
+            spectrum_scale: self.spectrum_canvas.scale(),
+            spectrum_overlap: self.spectrum_overlap,
+            spectrum_smoothing: self.spectrum_canvas.smoothing(),
+            show_raw_spectrum: self.spectrum_canvas.is_showing_raw(),
+            spectrum_averages: self.spectrum_canvas.averages(),
+            measuring_band: self.spectrum_canvas.is_measuring_band(),
+            time_gated: self.canvas.time_gate().is_some(),
+            reference_spl: self.reference_spl,
+            calibration: self.calibration,
+            show_spl: self.show_spl,
+            graticule: self.canvas.graticule().clone(),
+            show_axis_labels: self.canvas.is_showing_axis_labels(),
+            math_expression: self.math_text.clone(),
+            math_error: self.math_error.clone(),
+            math_enabled: self.math_enabled,
+            math_per_div: self.math_per_div,
+            input_label: self.preferences.input_label.clone(),
+            math_label: self.preferences.math_label.clone(),
+            palette: self.palette(),
         };
 
-        let controls = build_controls(&control_state, &measurements).map(Message::Control);
+        let controls = build_controls(
+            &control_state,
+            &self.measurements,
+            &self.plugin_measurements,
+        )
+        .map(Message::Control);
 
         // Choose layout based on mode
         let canvas_view: Element<'_, Message> = match self.layout_mode {
-            LayoutMode::SideBySide => row![scope_canvas, spectrum_canvas]
+            LayoutMode::ScopeOnly => scope_canvas,
+            LayoutMode::SpectrumOnly => plot_canvas,
+            LayoutMode::SideBySide => row![scope_canvas, plot_canvas]
                 .spacing(2)
                 .width(Length::Fill)
                 .height(Length::Fill)
                 .into(),
-            LayoutMode::Stacked => column![scope_canvas, spectrum_canvas]
+            LayoutMode::Stacked => column![scope_canvas, plot_canvas]
                 .spacing(2)
                 .width(Length::Fill)
                 .height(Length::Fill)
                 .into(),
         };
 
-        let content = column![canvas_view, controls]
+        let mut content = column![canvas_view]
             .spacing(0)
             .width(Length::Fill)
             .height(Length::Fill);
 
-        container(content)
-            .width(Length::Fill)
-            .height(Length::Fill)
-            .into()
+        if let Some(ref player) = self.replay {
+            let transport_state = TransportState {
+                playing: player.is_playing(),
+                position: player.position_fraction(),
+                position_seconds: player.position_seconds(),
+                duration_seconds: player.recording().duration_seconds(),
+                speed: player.speed(),
+                search: self.search,
+                hits: self.search_hits_as_fractions(player),
+                current_hit: self.current_hit,
+                palette: self.palette(),
+            };
+            content = content.push(build_transport(&transport_state).map(Message::Control));
+        }
+
+        if self.losing_data() {
+            content = content
+                .push(build_loss_warning(self.data_loss, &self.palette()).map(Message::Control));
+        }
+
+        // The designer takes the place of the controls, leaving the
+        // spectrum it draws on in view
+        let content = match self.designer {
+            Some(ref designer) => content.push(designer.view().map(Message::Designer)),
+            None => content.push(controls),
+        };
+
+        let scope = container(content).width(Length::Fill).height(Length::Fill);
+        match (&self.preferences_dialog, &self.filter_dialog) {
+            (Some(dialog), _) => modal(
+                scope,
+                dialog.view().map(Message::Preferences),
+                Message::Preferences(PreferenceMessage::Cancel),
+            ),
+            (None, Some(dialog)) => modal(
+                scope,
+                dialog.view(&self.preferences.filters).map(Message::Filters),
+                Message::Filters(FilterMessage::Close),
+            ),
+            (None, None) => scope.into(),
+        }
     }
 
     fn subscription(&self) -> Subscription<Message> {
+        let ticks = match self.preferences.refresh_rate.interval() {
+            Some(interval) => iced::time::every(interval),
+            None => iced::window::frames(),
+        };
         Subscription::batch(vec![
-            // Update at ~60 FPS
-            iced::time::every(Duration::from_millis(16)).map(|_instant| Message::AudioUpdate),
+            ticks.map(|_instant| Message::AudioUpdate),
             // Listen for keyboard events
             iced::event::listen().map(Message::EventOccurred),
         ])
     }
 
     fn theme(&self) -> Theme {
-        Theme::Dark
+        if self.display_theme.is_light() {
+            Theme::Light
+        } else {
+            Theme::Dark
+        }
     }
 }
 
 impl OzScope {
-    fn key_to_control(key: &Key) -> Option<ControlMessage> {
-        match key {
-            // Time scale controls
-            Key::Character(c) if c.as_str() == "+" || c.as_str() == "=" => {
-                Some(ControlMessage::IncreaseTimeScale)
-            }
-            Key::Character(c) if c.as_str() == "-" => Some(ControlMessage::DecreaseTimeScale),
-            // Voltage scale controls
-            Key::Named(keyboard::key::Named::ArrowUp) => Some(ControlMessage::IncreaseVoltageScale),
-            Key::Named(keyboard::key::Named::ArrowDown) => {
-                Some(ControlMessage::DecreaseVoltageScale)
-            }
-            // Trigger controls
-            Key::Character(c) if c.as_str() == "t" || c.as_str() == "T" => {
-                Some(ControlMessage::ToggleTrigger)
-            }
-            Key::Character(c) if c.as_str() == "e" || c.as_str() == "E" => {
-                Some(ControlMessage::ToggleTriggerEdge)
-            }
-            Key::Named(keyboard::key::Named::ArrowRight) => {
-                Some(ControlMessage::IncreaseTriggerLevel)
-            }
-            Key::Named(keyboard::key::Named::ArrowLeft) => {
-                Some(ControlMessage::DecreaseTriggerLevel)
-            }
-            // Persistence controls
-            Key::Character(c) if c.as_str() == "p" || c.as_str() == "P" => {
-                Some(ControlMessage::TogglePersistence)
+    fn key_to_control(&self, key: &Key, modifiers: keyboard::Modifiers) -> Option<ControlMessage> {
+        // `key` is the unshifted key, so Shift+= still reads as "="
+        let key = match key {
+            Key::Named(keyboard::key::Named::ArrowUp) => keymap::Key::ArrowUp,
+            Key::Named(keyboard::key::Named::ArrowDown) => keymap::Key::ArrowDown,
+            Key::Named(keyboard::key::Named::ArrowLeft) => keymap::Key::ArrowLeft,
+            Key::Named(keyboard::key::Named::ArrowRight) => keymap::Key::ArrowRight,
+            Key::Character(c) => {
+                let mut chars = c.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => keymap::Key::Character(c),
+                    _ => return None,
+                }
             }
-            _ => None,
-        }
+            _ => return None,
+        };
+        control_for_key(&self.preferences, key, modifiers.shift())
     }
 
     fn handle_control(&mut self, control: ControlMessage) {
@@ -196,16 +593,47 @@ impl OzScope {
                 self.waveform.decrease_time_scale();
             }
             ControlMessage::SetTimeScale(value) => {
-                self.waveform.time_per_division = value;
+                self.waveform.time_per_division = if self.fine_adjust {
+                    step_fine_toward(self.waveform.time_per_division, value)
+                } else {
+                    value
+                };
             }
+            ControlMessage::FineTimeScale(up) => {
+                self.waveform.fine_time_scale(up);
+            }
+            ControlMessage::CycleDecimation => {
+                self.waveform.decimation = self.waveform.decimation.next();
+            }
+            // Setting the scale by hand turns auto range off
             ControlMessage::IncreaseVoltageScale => {
+                self.auto_range = None;
                 self.waveform.increase_voltage_scale();
             }
             ControlMessage::DecreaseVoltageScale => {
+                self.auto_range = None;
                 self.waveform.decrease_voltage_scale();
             }
             ControlMessage::SetVoltageScale(value) => {
-                self.waveform.volts_per_division = value;
+                self.auto_range = None;
+                self.waveform.volts_per_division = if self.fine_adjust {
+                    step_fine_toward(self.waveform.volts_per_division, value)
+                } else {
+                    value
+                };
+            }
+            ControlMessage::FineVoltageScale(up) => {
+                self.auto_range = None;
+                self.waveform.fine_voltage_scale(up);
+            }
+            ControlMessage::CycleBandwidth(channel) => {
+                self.bandwidth[channel] = self.bandwidth[channel].next();
+            }
+            ControlMessage::ToggleAutoRange => {
+                self.auto_range = match self.auto_range {
+                    Some(_) => None,
+                    None => Some(AutoRange::default()),
+                };
             }
             ControlMessage::ToggleTrigger => {
                 self.trigger_settings.toggle_enabled();
@@ -213,20 +641,54 @@ impl OzScope {
             ControlMessage::ToggleTriggerEdge => {
                 self.trigger_settings.toggle_edge();
             }
+            ControlMessage::CycleTriggerMode => {
+                self.trigger_settings.mode = self.trigger_settings.mode.next();
+            }
             ControlMessage::IncreaseTriggerLevel => {
                 self.trigger_settings
-                    .set_level(self.trigger_settings.level + 0.1);
+                    .set_level(self.trigger_settings.level + LEVEL_STEP);
             }
             ControlMessage::DecreaseTriggerLevel => {
                 self.trigger_settings
-                    .set_level(self.trigger_settings.level - 0.1);
+                    .set_level(self.trigger_settings.level - LEVEL_STEP);
             }
             ControlMessage::SetTriggerLevel(value) => {
-                self.trigger_settings.set_level(value);
+                if self.fine_adjust {
+                    self.trigger_settings.step_level_toward(value);
+                } else {
+                    self.trigger_settings.set_level(value);
+                }
+            }
+            ControlMessage::FineTriggerLevel(up) => {
+                let step = if up {
+                    FINE_LEVEL_STEP
+                } else {
+                    -FINE_LEVEL_STEP
+                };
+                self.trigger_settings
+                    .set_level(self.trigger_settings.level + step);
             }
             ControlMessage::TogglePersistence => {
                 self.canvas.toggle_persistence();
             }
+            ControlMessage::CyclePersistenceMode => {
+                self.canvas.cycle_persistence_mode();
+            }
+            ControlMessage::CyclePersistenceDecay => {
+                self.canvas.cycle_persistence_decay();
+            }
+            ControlMessage::CyclePersistenceColormap => {
+                self.canvas.cycle_persistence_colormap();
+            }
+            ControlMessage::CycleEnvelope => {
+                self.envelope.mode = self.envelope.mode.next();
+            }
+            ControlMessage::SetEnvelopeAttack(seconds) => {
+                self.envelope.set_attack(seconds);
+            }
+            ControlMessage::SetEnvelopeRelease(seconds) => {
+                self.envelope.set_release(seconds);
+            }
             ControlMessage::IncreasePersistence => {
                 let current = self.canvas.get_persistence_frames();
                 self.canvas.set_persistence_frames(current + 1);
@@ -239,62 +701,882 @@ impl OzScope {
             ControlMessage::SetPersistenceFrames(value) => {
                 self.canvas.set_persistence_frames(value as usize);
             }
+            ControlMessage::ClearPersistence => {
+                self.canvas.clear_persistence();
+            }
             ControlMessage::SetLayoutMode(mode) => {
                 self.layout_mode = mode;
             }
+            ControlMessage::CycleLayout => {
+                self.layout_mode = self.layout_mode.next();
+            }
+            ControlMessage::ToggleLogging => {
+                self.toggle_logging();
+            }
+            ControlMessage::ToggleMonitor => {
+                if self.monitor.is_some() {
+                    self.stop_monitor();
+                } else {
+                    self.start_monitor(None);
+                }
+            }
+            ControlMessage::ToggleMonitorMute => {
+                if let Some(ref monitor) = self.monitor {
+                    monitor.set_muted(!monitor.is_muted());
+                }
+            }
+            ControlMessage::SetMonitorGain(db) => {
+                if let Some(ref monitor) = self.monitor {
+                    monitor.set_gain_db(db);
+                }
+            }
+            ControlMessage::LoopbackTest => self.start_loopback(),
+            ControlMessage::ReplayLatestLog => {
+                let active = self.data_logger.as_ref().and_then(DataLogger::current_file);
+                match latest_log_file(Path::new(LOG_DIRECTORY), active) {
+                    Some(path) => self.start_replay(&path),
+                    None => eprintln!("No recordings found in {LOG_DIRECTORY}/"),
+                }
+            }
+            ControlMessage::TogglePlayback => {
+                if let Some(ref mut player) = self.replay {
+                    player.toggle_playing();
+                }
+            }
+            ControlMessage::SeekReplay(position) => {
+                if let Some(ref mut player) = self.replay {
+                    player.seek(position);
+                }
+                // Stepping through hits carries on from here
+                self.current_hit = None;
+            }
+            ControlMessage::SetReplaySpeed(speed) => {
+                if let Some(ref mut player) = self.replay {
+                    player.set_speed(speed);
+                }
+            }
+            ControlMessage::StepReplay(direction) => {
+                // Step by one screen's worth of samples
+                let screen = self.waveform.calculate_samples_per_screen() as isize;
+                if let Some(ref mut player) = self.replay {
+                    player.pause();
+                    player.step(direction as isize * screen);
+                }
+                self.current_hit = None;
+            }
+            ControlMessage::CycleSearchKind => {
+                self.search.kind = self.search.kind.next();
+                self.run_search();
+            }
+            ControlMessage::IncreaseSearchLevel => {
+                self.search.level += SEARCH_LEVEL_STEP;
+                self.run_search();
+            }
+            ControlMessage::DecreaseSearchLevel => {
+                self.search.level -= SEARCH_LEVEL_STEP;
+                self.run_search();
+            }
+            ControlMessage::IncreaseSearchWidth => {
+                self.search.max_width = (self.search.max_width * 2.0).min(PULSE_WIDTH_RANGE.1);
+                self.run_search();
+            }
+            ControlMessage::DecreaseSearchWidth => {
+                self.search.max_width = (self.search.max_width / 2.0).max(PULSE_WIDTH_RANGE.0);
+                self.run_search();
+            }
+            ControlMessage::PreviousHit => self.jump_to_hit(false),
+            ControlMessage::NextHit => self.jump_to_hit(true),
+            ControlMessage::CycleDecoder => {
+                self.decoder_mode = self.decoder_mode.next();
+                self.rebuild_decoder();
+            }
+            ControlMessage::IncreaseBaudRate => {
+                self.uart_config
+                    .step_baud_rate(true, self.waveform.sample_rate);
+                self.rebuild_decoder();
+            }
+            ControlMessage::DecreaseBaudRate => {
+                self.uart_config
+                    .step_baud_rate(false, self.waveform.sample_rate);
+                self.rebuild_decoder();
+            }
+            ControlMessage::CycleTheme => {
+                self.set_theme(self.display_theme.next());
+            }
+            ControlMessage::CyclePlot => {
+                self.plot_mode = self.plot_mode.next();
+                self.canvas.show_zoom(self.plot_mode == PlotMode::Zoom);
+                self.frequency_canvas.set_track(None);
+                self.vectorscope_canvas.clear();
+                self.phase_canvas.clear();
+            }
+            ControlMessage::ToggleVectorscopeGain => {
+                self.vectorscope_canvas.toggle_auto_gain();
+            }
+            ControlMessage::ToggleHarmonics => {
+                self.spectrum_canvas.toggle_harmonics();
+            }
+            ControlMessage::CycleSpectrumScale => {
+                let scale = self.spectrum_canvas.scale().next();
+                self.spectrum_canvas.set_scale(scale);
+            }
+            ControlMessage::CycleSpectrumOverlap => {
+                self.spectrum_overlap = self.spectrum_overlap.next();
+            }
+            ControlMessage::CycleSpectrumSmoothing => {
+                self.spectrum_canvas.cycle_smoothing();
+            }
+            ControlMessage::ToggleRawSpectrum => {
+                self.spectrum_canvas.toggle_raw();
+            }
+            ControlMessage::ToggleSpectrumAverage => {
+                self.spectrum_canvas.toggle_average();
+            }
+            ControlMessage::ExportSpectrum => self.export_spectrum(),
+            ControlMessage::ExportSpectrogram => self.export_spectrogram(),
+            ControlMessage::ToggleBandPower => {
+                self.spectrum_canvas.toggle_band_power();
+            }
+            ControlMessage::MoveBandCursor(cursor, frequency) => {
+                self.spectrum_canvas.move_band_cursor(cursor, frequency);
+            }
+            ControlMessage::ToggleTimeGate => {
+                self.canvas.toggle_time_gate();
+            }
+            ControlMessage::MoveGateCursor(cursor, x) => {
+                self.canvas.move_gate_cursor(cursor, x);
+            }
+            ControlMessage::SetZoom(zoom) => {
+                self.canvas.set_zoom(zoom);
+            }
+            ControlMessage::ScaleZoom(factor) => {
+                if let Some(mut zoom) = self.canvas.zoom() {
+                    zoom.scale(factor);
+                    self.canvas.set_zoom(zoom);
+                }
+            }
+            ControlMessage::IncreaseReferenceLevel => {
+                self.reference_spl = (self.reference_spl + 1.0).min(REFERENCE_SPL_RANGE.1);
+            }
+            ControlMessage::DecreaseReferenceLevel => {
+                self.reference_spl = (self.reference_spl - 1.0).max(REFERENCE_SPL_RANGE.0);
+            }
+            ControlMessage::Calibrate => {
+                match self
+                    .measurements
+                    .rms
+                    .and_then(|rms| Calibration::from_reference(rms, self.reference_spl))
+                {
+                    Some(calibration) => {
+                        self.set_calibration(Some(calibration));
+                        self.show_spl = true;
+                        self.apply_calibration();
+                    }
+                    None => eprintln!("Nothing to calibrate: the input is silent"),
+                }
+            }
+            ControlMessage::ClearCalibration => {
+                self.set_calibration(None);
+                self.apply_calibration();
+            }
+            ControlMessage::ToggleLevelUnit => {
+                self.show_spl = !self.show_spl;
+                self.apply_calibration();
+            }
+            ControlMessage::ToggleGrid => {
+                self.update_graticule(|g| g.enabled = !g.enabled);
+            }
+            ControlMessage::CycleGridDivisions => {
+                self.update_graticule(Graticule::cycle_divisions);
+            }
+            ControlMessage::ToggleMinorTicks => {
+                self.update_graticule(Graticule::toggle_minor_ticks);
+            }
+            ControlMessage::ToggleGridStyle => {
+                self.update_graticule(|g| g.style = g.style.next());
+            }
+            ControlMessage::ToggleAxisLabels => {
+                self.canvas.toggle_axis_labels();
+            }
+            ControlMessage::OpenPreferences => {
+                self.preferences_dialog = Some(PreferencesDialog::new(&self.preferences));
+            }
+            ControlMessage::OpenFilters => {
+                self.filter_dialog = Some(FilterDialog::default());
+            }
+            ControlMessage::OpenDesigner => {
+                let designer = FilterDesigner::default();
+                self.spectrum_canvas.set_response(Some(designer.design()));
+                self.designer = Some(designer);
+                self.plot_mode = PlotMode::Spectrum;
+                self.canvas.show_zoom(false);
+            }
+            ControlMessage::SetMathExpression(text) => {
+                match MathExpression::parse(&text) {
+                    Ok(expression) => {
+                        self.math = Some(expression);
+                        self.math_error = None;
+                    }
+                    Err(e) => {
+                        self.math = None;
+                        self.math_error = Some(e);
+                    }
+                }
+                self.math_text = text;
+            }
+            ControlMessage::ToggleMath => {
+                self.math_enabled = !self.math_enabled;
+            }
+            ControlMessage::IncreaseMathScale => {
+                self.math_per_div = step_1_2_5(self.math_per_div, true);
+            }
+            ControlMessage::DecreaseMathScale => {
+                self.math_per_div = step_1_2_5(self.math_per_div, false).max(0.001);
+            }
+            ControlMessage::ExitReplay => {
+                self.replay = None;
+                self.search_hits.clear();
+                self.current_hit = None;
+                self.waveform.sample_rate = self
+                    .acquisition
+                    .as_ref()
+                    .map_or(LIVE_SAMPLE_RATE, Acquisition::sample_rate);
+                self.waveform.samples.clear();
+                self.rebuild_decoder();
+            }
         }
     }
 
-    fn update_audio(&mut self) {
-        if let Some(ref audio_capture) = self.audio_capture {
-            // Read ALL available samples for minimal latency
-            let new_samples = audio_capture.read_samples(usize::MAX);
+    fn handle_preferences(&mut self, message: PreferenceMessage) {
+        match message {
+            PreferenceMessage::Save => {
+                let Some(dialog) = self.preferences_dialog.take() else {
+                    return;
+                };
+                let preferences = dialog.preferences().clone();
+                if let Some(path) = Preferences::default_path() {
+                    if let Err(e) = preferences.save(&path) {
+                        eprintln!("Failed to save preferences: {e}");
+                    }
+                }
+                let input_changed = preferences.input_device != self.preferences.input_device
+                    || preferences.sample_rate != self.preferences.sample_rate
+                    || preferences.input_channels != self.preferences.input_channels
+                    || preferences.buffer_size != self.preferences.buffer_size;
+                self.preferences = preferences;
+                self.set_theme(self.preferences.theme);
+                if input_changed {
+                    self.restart_acquisition();
+                }
+            }
+            PreferenceMessage::Cancel => {
+                self.preferences_dialog = None;
+            }
+            message => {
+                if let Some(ref mut dialog) = self.preferences_dialog {
+                    dialog.update(message);
+                }
+            }
+        }
+    }
+
+    /// Edits apply to the live input straight away; the chain is saved
+    /// with the preferences once the editor closes
+    fn handle_filters(&mut self, message: FilterMessage) {
+        match message {
+            FilterMessage::Close => {
+                if self.filter_dialog.take().is_none() {
+                    return;
+                }
+                if let Some(path) = Preferences::default_path() {
+                    if let Err(e) = self.preferences.save(&path) {
+                        eprintln!("Failed to save filters: {e}");
+                    }
+                }
+            }
+            message => {
+                if let Some(ref mut dialog) = self.filter_dialog {
+                    dialog.update(&mut self.preferences.filters, message);
+                }
+            }
+        }
+    }
+
+    /// Inserting adds the design to the end of the input filter chain and
+    /// saves it, like closing the filter editor
+    fn handle_designer(&mut self, message: DesignerMessage) {
+        match message {
+            DesignerMessage::Insert => {
+                let Some(designer) = self.designer.take() else {
+                    return;
+                };
+                self.preferences
+                    .filters
+                    .push(FilterBlock::new(FilterKind::Designed(designer.design())));
+                if let Some(path) = Preferences::default_path() {
+                    if let Err(e) = self.preferences.save(&path) {
+                        eprintln!("Failed to save filters: {e}");
+                    }
+                }
+                self.spectrum_canvas.set_response(None);
+            }
+            DesignerMessage::Close => {
+                self.designer = None;
+                self.spectrum_canvas.set_response(None);
+            }
+            message => {
+                if let Some(ref mut designer) = self.designer {
+                    designer.update(message);
+                    self.spectrum_canvas.set_response(Some(designer.design()));
+                }
+            }
+        }
+    }
+
+    /// Reopen the capture on the input and rate in the preferences
+    fn restart_acquisition(&mut self) {
+        // Reopened at the new input's rate, as it was
+        let monitor = self
+            .monitor
+            .as_ref()
+            .map(|monitor| (monitor.gain_db(), monitor.is_muted()));
+        self.stop_monitor();
+        // A new input has its own buffering, and a click played at the old
+        // one's rate won't be heard
+        self.latency = LatencyMeter::default();
+        self.loopback = None;
+        self.data_loss = DataLoss::default();
+        self.lost_at = None;
+        // The old stream has to close before the device can be reopened
+        self.acquisition = None;
+        self.acquisition =
+            start_acquisition(&self.preferences, &self.waveform, &self.trigger_settings);
+        if self.replay.is_none() {
+            if let Some(ref acquisition) = self.acquisition {
+                self.waveform.sample_rate = acquisition.sample_rate();
+            }
+            self.waveform.samples.clear();
+            self.rebuild_decoder();
+        }
+        self.calibration = self.calibrations.get(self.device_name());
+        self.apply_calibration();
+        if monitor.is_some() {
+            self.start_monitor(monitor);
+        }
+    }
+
+    /// Play the live input through the default output, at `settings`' gain
+    /// and mute or quietly to start with
+    fn start_monitor(&mut self, settings: Option<(f32, bool)>) {
+        let Some(ref acquisition) = self.acquisition else {
+            eprintln!("No live input to monitor");
+            return;
+        };
+        match Monitor::open(acquisition.sample_rate()) {
+            Ok(monitor) => {
+                if let Some((gain, muted)) = settings {
+                    monitor.set_gain_db(gain);
+                    monitor.set_muted(muted);
+                }
+                println!("Monitoring through {}", monitor.device_name());
+                acquisition.set_monitor(Some(monitor.feed()));
+                self.monitor = Some(monitor);
+            }
+            Err(e) => eprintln!("Failed to start monitoring: {e}"),
+        }
+    }
+
+    /// Whether live input was lost recently enough to warn of
+    fn losing_data(&self) -> bool {
+        self.replay.is_none()
+            && self
+                .lost_at
+                .is_some_and(|lost_at| lost_at.elapsed() < LOSS_WARNING)
+    }
+
+    /// Click the output and time its return on the input
+    fn start_loopback(&mut self) {
+        let Some(ref acquisition) = self.acquisition else {
+            eprintln!("No live input to listen on");
+            return;
+        };
+        match LoopbackTest::start(acquisition.sample_rate()) {
+            Ok(test) => {
+                self.loopback = Some(test);
+                self.loopback_result = None;
+            }
+            Err(e) => eprintln!("Failed to start loopback test: {e}"),
+        }
+    }
+
+    fn stop_monitor(&mut self) {
+        if let Some(ref acquisition) = self.acquisition {
+            acquisition.set_monitor(None);
+        }
+        self.monitor = None;
+    }
+
+    fn set_theme(&mut self, theme: DisplayTheme) {
+        self.display_theme = theme;
+        let palette = self.palette();
+        self.canvas.set_palette(palette);
+        self.spectrum_canvas.set_palette(palette);
+        self.frequency_canvas.set_palette(palette);
+        self.vectorscope_canvas.set_palette(palette);
+        self.phase_canvas.set_palette(palette);
+        self.zoom_canvas.set_palette(palette);
+    }
+
+    /// The theme's colors with the trace colors picked in the preferences
+    fn palette(&self) -> Palette {
+        self.preferences
+            .trace_colors
+            .apply(self.display_theme.palette())
+    }
+
+    /// Name the current input's calibration is saved under
+    fn device_name(&self) -> &str {
+        self.acquisition
+            .as_ref()
+            .map_or(SignalGenerator::NAME, Acquisition::device_name)
+    }
+
+    /// Remember the calibration of the current input across runs
+    fn set_calibration(&mut self, calibration: Option<Calibration>) {
+        self.calibration = calibration;
+        let device = self.device_name().to_string();
+        self.calibrations.set(&device, calibration);
+        if let Some(path) = CalibrationStore::default_path() {
+            if let Err(e) = self.calibrations.save(&path) {
+                eprintln!("Failed to save calibration: {e}");
+            }
+        }
+    }
+
+    /// Label the spectrum in the chosen level unit
+    fn apply_calibration(&mut self) {
+        let offset = self
+            .calibration
+            .filter(|_| self.show_spl)
+            .map(|calibration| calibration.offset);
+        self.spectrum_canvas.set_level_offset(offset);
+    }
+
+    fn update_graticule(&mut self, change: impl FnOnce(&mut Graticule)) {
+        let mut graticule = self.canvas.graticule().clone();
+        change(&mut graticule);
+        // Time/div stays true to the grid: the screen spans every division
+        self.waveform.horizontal_divisions = graticule.horizontal_divisions;
+        self.canvas.set_graticule(graticule);
+    }
+
+    fn rebuild_decoder(&mut self) {
+        // The sample rate may have dropped below what the baud rate needs
+        self.uart_config.fit_baud_rate(self.waveform.sample_rate);
+        let decoder: Option<Box<dyn Decoder>> = match self.decoder_mode {
+            DecoderMode::Off => None,
+            DecoderMode::Uart => Some(Box::new(UartDecoder::new(self.uart_config.clone()))),
+            DecoderMode::Dtmf => Some(Box::new(DtmfDecoder::new())),
+            DecoderMode::Afsk => Some(Box::new(FskDecoder::new(FskConfig::bell_202()))),
+            DecoderMode::Plugin(index) => plugin::decoder_plugins()
+                .get(index)
+                .map(|plugin| plugin.create()),
+        };
+        self.stream_decoder =
+            decoder.map(|decoder| StreamDecoder::new(decoder, self.waveform.sample_rate));
+        self.decoded_overlay = Arc::default();
+    }
 
-            if !new_samples.is_empty() {
-                // Near-zero-latency mode: keep only what we need for one stable screen
-                // Trigger detection happens on current data, not historical accumulation
-                self.audio_buffer.extend_from_slice(&new_samples);
+    fn start_replay(&mut self, path: &Path) {
+        match Recording::load(path) {
+            Ok(recording) => {
+                println!(
+                    "Replaying {} ({:.1} s at {} Hz)",
+                    path.display(),
+                    recording.duration_seconds(),
+                    recording.sample_rate
+                );
+                self.waveform.sample_rate = recording.sample_rate;
+                self.replay = Some(ReplayPlayer::new(recording));
+                self.last_tick = Instant::now();
+                self.rebuild_decoder();
+                self.run_search();
+            }
+            Err(e) => eprintln!("Failed to load recording: {e}"),
+        }
+    }
+
+    /// Search the whole recording again, after it or the search changed
+    fn run_search(&mut self) {
+        self.search_hits = match self.replay {
+            Some(ref player) => {
+                let recording = player.recording();
+                self.search.find(&recording.samples, recording.sample_rate)
+            }
+            None => Vec::new(),
+        };
+        self.current_hit = None;
+    }
+
+    /// Pause with the next or previous hit after the last one jumped to,
+    /// or after the play head, in the middle of the screen
+    fn jump_to_hit(&mut self, forward: bool) {
+        let half_screen = self.waveform.calculate_samples_per_screen() / 2;
+        let Some(ref mut player) = self.replay else {
+            return;
+        };
+        let position = match self.current_hit {
+            Some(index) => self.search_hits[index].start,
+            None => player.position_samples().saturating_sub(half_screen),
+        };
+        let found = if forward {
+            search::next_hit(&self.search_hits, position)
+        } else {
+            search::previous_hit(&self.search_hits, position)
+        };
+        if let Some(index) = found {
+            // The screen shows the samples leading up to the play head
+            let target = self.search_hits[index].start + half_screen;
+            player.pause();
+            player.step(target as isize - player.position_samples() as isize);
+            self.current_hit = Some(index);
+        }
+    }
+
+    fn search_hits_as_fractions(&self, player: &ReplayPlayer) -> Vec<(f32, f32)> {
+        let length = player.recording().samples.len().max(1) as f32;
+        self.search_hits
+            .iter()
+            .map(|hit| (hit.start as f32 / length, hit.end as f32 / length))
+            .collect()
+    }
 
-                // Keep exactly what we need: 1 screen + small margin for trigger search
-                let samples_needed = self.waveform.calculate_samples_per_screen();
-                let max_buffer_size = samples_needed + 200; // 200 samples = ~4ms margin
+    fn toggle_logging(&mut self) {
+        if let Some(logger) = self.data_logger.take() {
+            if let Err(e) = logger.finish() {
+                eprintln!("Failed to close log file: {e}");
+            }
+            return;
+        }
+
+        let config = LoggerConfig::new(LOG_DIRECTORY, self.waveform.sample_rate);
+        match DataLogger::new(config) {
+            Ok(logger) => {
+                println!("Logging samples to {LOG_DIRECTORY}/");
+                self.data_logger = Some(logger);
+            }
+            Err(e) => eprintln!("Failed to start logging: {e}"),
+        }
+    }
+
+    /// Write the spectrum as shown to a new file in the export directory
+    fn export_spectrum(&self) {
+        let session = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let path = Path::new(EXPORT_DIRECTORY).join(format!("spectrum-{session}.csv"));
+        let written = fs::create_dir_all(EXPORT_DIRECTORY)
+            .and_then(|()| fs::write(&path, self.spectrum_canvas.to_csv()));
+        match written {
+            Ok(()) => println!("Spectrum exported to {}", path.display()),
+            Err(e) => eprintln!("Failed to export spectrum: {e}"),
+        }
+    }
+
+    /// Write the spectrogram of everything analyzed so far, not just the
+    /// spectrum shown, to a new PNG in the export directory
+    fn export_spectrogram(&self) {
+        let Some(ref acquisition) = self.acquisition else {
+            eprintln!("Failed to export spectrogram: no input");
+            return;
+        };
+        let session = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let path = Path::new(EXPORT_DIRECTORY).join(format!("spectrogram-{session}.png"));
+        let written = fs::create_dir_all(EXPORT_DIRECTORY)
+            .map_err(|e| e.to_string())
+            .and_then(|()| acquisition.spectrogram().image(&self.palette()))
+            .and_then(|image| image.save(&path));
+        match written {
+            Ok(()) => println!("Spectrogram exported to {}", path.display()),
+            Err(e) => eprintln!("Failed to export spectrogram: {e}"),
+        }
+    }
+
+    /// Whether new samples came in
+    fn update_audio(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_tick).as_secs_f32();
+        self.last_tick = now;
 
-                if self.audio_buffer.len() > max_buffer_size {
-                    let to_remove = self.audio_buffer.len() - max_buffer_size;
-                    self.audio_buffer.drain(0..to_remove);
+        if !self.acquire(elapsed) {
+            return false;
+        }
+        if let Some(ref mut auto_range) = self.auto_range {
+            self.waveform.volts_per_division = auto_range.update(
+                &self.waveform.samples[self.display_range.clone()],
+                self.waveform.volts_per_division,
+                self.canvas.graticule().vertical_divisions,
+            );
+        }
+        true
+    }
+
+    /// Take in the next samples from the recording, or from the input or
+    /// test signal the acquisition thread reads. Whether there were any.
+    fn acquire(&mut self, elapsed: f32) -> bool {
+        if let Some(ref mut player) = self.replay {
+            // Recording replaces live capture; keep the same window a live buffer would hold
+            let advanced = player.advance(elapsed);
+            if advanced == 0 && !self.settings_changed {
+                return false;
+            }
+            if let Some(ref mut decoder) = self.stream_decoder {
+                decoder.push(player.window(advanced));
+            }
+            // Recordings are mono, so they plot as the mono axis
+            let pairs: Vec<(f32, f32)> = if self.plot_mode.is_stereo() {
+                player.window(advanced).iter().map(|&s| (s, s)).collect()
+            } else {
+                Vec::new()
+            };
+
+            self.waveform
+                .copy_samples(player.window(self.waveform.buffer_size()));
+            self.locate_trigger();
+            match self.canvas.time_gate() {
+                Some(gate) => self.spectrum_canvas.update_gated(
+                    gate.samples(&self.waveform.samples, self.display_range.clone()),
+                    self.waveform.sample_rate,
+                ),
+                None => self
+                    .spectrum_canvas
+                    .update_spectrum(&self.waveform.samples, self.waveform.sample_rate),
+            }
+            self.measurements = Measurements::of(&self.waveform);
+            self.plugin_measurements =
+                plugin::measure(&self.waveform.samples, self.waveform.sample_rate);
+            self.evaluate_math();
+            self.update_stereo(&pairs);
+            true
+        } else if let Some(ref acquisition) = self.acquisition {
+            acquisition.set_settings(AcquisitionSettings {
+                spectrum_scale: self.spectrum_canvas.scale(),
+                spectrum_overlap: self.spectrum_overlap,
+                stereo: self.plot_mode.is_stereo(),
+                bandwidth: self.bandwidth,
+                filters: self.preferences.filters.clone(),
+                math: self.active_math().cloned(),
+                ..AcquisitionSettings::new(
+                    &self.waveform,
+                    &self.trigger_settings,
+                    self.canvas.time_gate().is_none(),
+                )
+            });
+            // The acquisition thread has already windowed, triggered and
+            // analyzed the capture; only the newest frame is shown
+            let Some(frame) = acquisition.latest() else {
+                return false;
+            };
+            self.latency
+                .update(&frame, Duration::from_secs_f32(elapsed));
+            if frame.data_loss != self.data_loss {
+                self.data_loss = frame.data_loss;
+                self.lost_at = Some(Instant::now());
+            }
+            if let (Some(ref mut test), Some(read_at)) = (&mut self.loopback, frame.read_at) {
+                if let Some(result) = test.feed(&frame.new_samples, read_at) {
+                    self.loopback_result = Some(result);
+                    self.loopback = None;
+                }
+            }
+
+            // Log the raw stream before any display windowing
+            if let Some(ref mut logger) = self.data_logger {
+                if let Err(e) = logger.write_samples(&frame.new_samples) {
+                    eprintln!("Logging stopped: {e}");
+                    self.data_logger = None;
                 }
+            }
 
-                // Update waveform with current buffer
-                self.waveform.update_samples(self.audio_buffer.clone());
+            if let Some(ref mut decoder) = self.stream_decoder {
+                decoder.push(&frame.new_samples);
+            }
 
-                // Update spectrum analyzer
+            self.update_stereo(&frame.stereo);
+            self.waveform.copy_samples(&frame.window);
+            self.display_range = frame.display_range;
+            self.trigger_position = frame.trigger_position;
+            self.measurements = frame.measurements;
+            self.plugin_measurements = frame.plugin_measurements;
+            self.math_samples = frame.math;
+            if let Some(spectrum) = frame.spectrum {
                 self.spectrum_canvas
-                    .update_spectrum(&self.audio_buffer, self.waveform.sample_rate);
+                    .set_spectrum(spectrum, self.waveform.sample_rate);
             }
+            true
         } else {
-            // Fallback: generate test signal if no audio capture
-            self.generate_test_signal();
+            false
         }
     }
 
-    fn generate_test_signal(&mut self) {
-        // Generate a test sine wave as fallback when no audio device is available
-        let sample_rate = self.waveform.sample_rate as f32;
-        let frequency = 440.0; // A4 note
-        let duration = 0.1; // 100ms of samples
+    /// Find the user's trigger in a capture the acquisition thread didn't
+    /// trigger, for the trace, its history and the markers alike
+    fn locate_trigger(&mut self) {
+        self.display_range = self.waveform.display_range(&self.trigger_settings);
+        self.trigger_position = self.waveform.trigger_position(&self.trigger_settings);
+    }
 
-        let num_samples = (sample_rate * duration) as usize;
-        let mut samples = Vec::with_capacity(num_samples);
+    /// The math channel's expression, while it's on and parses
+    fn active_math(&self) -> Option<&MathExpression> {
+        self.math.as_ref().filter(|_| self.math_enabled)
+    }
 
-        // Use audio_buffer length as a simple phase accumulator
-        let phase_offset = self.audio_buffer.len() as f32 / sample_rate;
+    /// Evaluate the math channel of a recording here rather than on the
+    /// acquisition thread. Recordings are mono, so A and B are the same.
+    fn evaluate_math(&mut self) {
+        let samples = &self.waveform.samples;
+        self.math_samples = self.active_math().map(|math| {
+            math.evaluate(samples, samples, self.waveform.sample_rate)
+                .into()
+        });
+    }
 
-        for i in 0..num_samples {
-            let t = (i as f32 / sample_rate) + phase_offset;
-            let sample = (2.0 * std::f32::consts::PI * frequency * t).sin() * 0.5;
-            samples.push(sample);
+    /// Hand (left, right) pairs to whichever stereo plot is showing
+    fn update_stereo(&mut self, pairs: &[(f32, f32)]) {
+        match self.plot_mode {
+            PlotMode::Vectorscope => self.vectorscope_canvas.update(pairs),
+            PlotMode::Phase => self.phase_canvas.update(pairs, self.waveform.sample_rate),
+            PlotMode::Spectrum | PlotMode::FrequencyTrack | PlotMode::Zoom => {}
         }
+    }
+}
 
-        self.waveform.update_samples(samples);
+/// Open the input chosen in the preferences, at its rate. A saved device
+/// that's been unplugged, or a rate it doesn't run at, falls back to the
+/// system's default input, and no input at all to the test signal. An input
+/// such as `udp://0.0.0.0:7355` listens for a sender on another machine, and
+/// one such as `serial:/dev/ttyUSB0` reads a microcontroller's ADC.
+fn start_acquisition(
+    preferences: &Preferences,
+    waveform: &WaveformData,
+    trigger_settings: &TriggerSettings,
+) -> Option<Acquisition> {
+    let input = InputSelection {
+        device: preferences.input_device.clone(),
+        sample_rate: preferences.sample_rate,
+        channels: preferences.input_channels,
+        buffer_size: preferences.buffer_size,
+    };
+    let settings = AcquisitionSettings {
+        filters: preferences.filters.clone(),
+        ..AcquisitionSettings::new(waveform, trigger_settings, true)
+    };
+    let started = match preferences.input_device {
+        Some(ref url) if NetworkSource::is_url(url) => {
+            let url = url.clone();
+            Acquisition::start_source(
+                move || {
+                    let mut source = NetworkSource::open(&url)?;
+                    source.wait_for_sender(NETWORK_WAIT)?;
+                    Ok(source)
+                },
+                settings.clone(),
+            )
+        }
+        Some(ref url) if SerialConfig::is_serial(url) => match SerialConfig::parse(url) {
+            Ok(config) => {
+                Acquisition::start_source(move || SerialSource::open(&config), settings.clone())
+            }
+            Err(e) => Err(AudioError::Backend(e)),
+        },
+        _ => Acquisition::start_on(input.clone(), settings.clone()),
+    };
+    let started = match started {
+        Err(e @ (AudioError::DeviceNotFound(_) | AudioError::UnsupportedFormat(_)))
+            if input != InputSelection::default() =>
+        {
+            eprintln!("{e}; using the default input");
+            Acquisition::start_on(InputSelection::default(), settings.clone())
+        }
+        started => started,
+    };
+    match started {
+        Ok(acquisition) => {
+            println!("Audio capture initialized successfully");
+            Some(acquisition)
+        }
+        Err(e) => {
+            eprintln!("Failed to initialize audio capture: {e}");
+            eprintln!("Using test signal");
+            Acquisition::start_source(
+                || Ok(SignalGenerator::sine(LIVE_SAMPLE_RATE, 440.0, 0.5)),
+                settings,
+            )
+            .ok()
+        }
     }
 }
+
+/// Most recently modified recording in the log directory, passing over
+/// rate sidecars and `active`, the file a running logger hasn't finished
+fn latest_log_file(directory: &Path, active: Option<&Path>) -> Option<PathBuf> {
+    std::fs::read_dir(directory)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let path = entry.path();
+            let recording = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| {
+                    ["wav", "f32", "raw"]
+                        .iter()
+                        .any(|known| ext.eq_ignore_ascii_case(known))
+                });
+            recording && path.is_file() && Some(path.as_path()) != active
+        })
+        .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())
+        .map(|entry| entry.path())
+}
+
+/// Hex and ASCII dump of the most recent decoded bytes, eight per row
+fn format_decoded_table<'a>(events: impl DoubleEndedIterator<Item = &'a DecodedEvent>) -> String {
+    const ROWS: usize = 3;
+    const PER_ROW: usize = 8;
+
+    let mut recent: Vec<&DecodedEvent> = events.rev().take(ROWS * PER_ROW).collect();
+    recent.reverse();
+
+    recent
+        .chunks(PER_ROW)
+        .map(|row| {
+            let hex: Vec<String> = row.iter().map(|e| format!("{:02X}", e.value)).collect();
+            let ascii: String = row
+                .iter()
+                .map(|e| match char::from_u32(e.value) {
+                    Some(c) if !e.error && (c.is_ascii_graphic() || c == ' ') => c,
+                    _ => '.',
+                })
+                .collect();
+            format!("{} {ascii}", hex.join(" "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The most recent decoded digits as a single line
+fn format_decoded_digits<'a>(events: impl DoubleEndedIterator<Item = &'a DecodedEvent>) -> String {
+    const MAX_DIGITS: usize = 20;
+
+    let mut digits: Vec<&str> = events
+        .rev()
+        .take(MAX_DIGITS)
+        .map(|e| e.label.as_str())
+        .collect();
+    digits.reverse();
+    digits.concat()
+}
@@ -1,10 +1,103 @@
-use iced::widget::{button, column, container, row, slider, text};
+use iced::widget::{button, canvas, column, container, row, slider, text, text_input};
 use iced::{Alignment, Element, Length};
+use ozeecubed_core::audio::calibration::{rms_dbfs, Calibration};
+use ozeecubed_core::audio::monitor::MONITOR_GAIN_RANGE;
+use ozeecubed_core::audio::{Latency, LoopbackResult};
+use ozeecubed_core::display::{
+    format_time, format_volts, DisplayTheme, Graticule, LayoutMode, Palette, PersistenceColormap,
+    PersistenceDecay, PersistenceMode, Rgba, MAX_PERSISTENCE_FRAMES,
+};
+use ozeecubed_core::dsp::BandwidthLimit;
+use ozeecubed_core::oscilloscope::envelope::{ATTACK_RANGE, RELEASE_RANGE};
+use ozeecubed_core::oscilloscope::{
+    BandCursor, Decimation, EnvelopeFollower, EventSearch, GateCursor, Measurements,
+    OctaveSmoothing, SpectrumOverlap, SpectrumScale, TriggerMode, ZoomWindow,
+};
+use ozeecubed_core::plugin::{self, PluginMeasurement};
+use ozeecubed_core::source::DataLoss;
 
+use super::timeline::Timeline;
+use super::to_color;
+
+/// What the second display pane shows
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub enum LayoutMode {
-    SideBySide,
-    Stacked,
+pub enum PlotMode {
+    Spectrum,
+    FrequencyTrack,
+    Vectorscope,
+    Phase,
+    /// The highlighted region of the main trace at a faster timebase
+    Zoom,
+}
+
+impl PlotMode {
+    pub fn next(self) -> Self {
+        match self {
+            PlotMode::Spectrum => PlotMode::FrequencyTrack,
+            PlotMode::FrequencyTrack => PlotMode::Vectorscope,
+            PlotMode::Vectorscope => PlotMode::Phase,
+            PlotMode::Phase => PlotMode::Zoom,
+            PlotMode::Zoom => PlotMode::Spectrum,
+        }
+    }
+
+    /// Whether the plot needs the left and right inputs apart
+    pub fn is_stereo(self) -> bool {
+        matches!(self, PlotMode::Vectorscope | PlotMode::Phase)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PlotMode::Spectrum => "Spectrum",
+            PlotMode::FrequencyTrack => "Freq track",
+            PlotMode::Vectorscope => "Vectorscope",
+            PlotMode::Phase => "Phase",
+            PlotMode::Zoom => "Zoom",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecoderMode {
+    Off,
+    Uart,
+    Dtmf,
+    Afsk,
+    /// The registered decoder plugin at this index
+    Plugin(usize),
+}
+
+impl DecoderMode {
+    /// The built-in decoders, then every registered plugin
+    pub fn next(self) -> Self {
+        match self {
+            DecoderMode::Off => DecoderMode::Uart,
+            DecoderMode::Uart => DecoderMode::Dtmf,
+            DecoderMode::Dtmf => DecoderMode::Afsk,
+            DecoderMode::Afsk => DecoderMode::plugin(0),
+            DecoderMode::Plugin(index) => DecoderMode::plugin(index + 1),
+        }
+    }
+
+    fn plugin(index: usize) -> Self {
+        if index < plugin::decoder_plugins().len() {
+            DecoderMode::Plugin(index)
+        } else {
+            DecoderMode::Off
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DecoderMode::Off => "OFF",
+            DecoderMode::Uart => "UART",
+            DecoderMode::Dtmf => "DTMF",
+            DecoderMode::Afsk => "AFSK",
+            DecoderMode::Plugin(index) => plugin::decoder_plugins()
+                .get(index)
+                .map_or("--", |plugin| plugin.name()),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -12,41 +105,289 @@ pub enum ControlMessage {
     IncreaseTimeScale,
     DecreaseTimeScale,
     SetTimeScale(f32),
+    /// Up or down by a fine step rather than 1-2-5, as Shift gives
+    FineTimeScale(bool),
+    CycleDecimation,
     IncreaseVoltageScale,
     DecreaseVoltageScale,
     SetVoltageScale(f32),
+    FineVoltageScale(bool),
+    ToggleAutoRange,
+    /// Step channel A (0) or B (1) to its next bandwidth limit
+    CycleBandwidth(usize),
     ToggleTrigger,
     ToggleTriggerEdge,
+    CycleTriggerMode,
     IncreaseTriggerLevel,
     DecreaseTriggerLevel,
     SetTriggerLevel(f32),
+    FineTriggerLevel(bool),
     TogglePersistence,
+    CyclePersistenceMode,
+    CyclePersistenceDecay,
+    CyclePersistenceColormap,
     IncreasePersistence,
     DecreasePersistence,
-    SetPersistenceFrames(u8),
+    SetPersistenceFrames(u16),
+    ClearPersistence,
+    CycleEnvelope,
+    SetEnvelopeAttack(f32),
+    SetEnvelopeRelease(f32),
     SetLayoutMode(LayoutMode),
-}
-
-#[derive(Debug, Clone)]
-pub struct Measurements {
-    pub frequency: Option<f32>,
-    pub peak_to_peak: Option<f32>,
-    pub rms: Option<f32>,
-    pub duty_cycle: Option<f32>,
+    CycleLayout,
+    CyclePlot,
+    ToggleVectorscopeGain,
+    ToggleLogging,
+    ReplayLatestLog,
+    ToggleMonitor,
+    ToggleMonitorMute,
+    SetMonitorGain(f32),
+    LoopbackTest,
+    TogglePlayback,
+    SeekReplay(f32),
+    SetReplaySpeed(f32),
+    StepReplay(i32),
+    ExitReplay,
+    CycleSearchKind,
+    IncreaseSearchLevel,
+    DecreaseSearchLevel,
+    IncreaseSearchWidth,
+    DecreaseSearchWidth,
+    PreviousHit,
+    NextHit,
+    CycleDecoder,
+    IncreaseBaudRate,
+    DecreaseBaudRate,
+    CycleTheme,
+    ToggleHarmonics,
+    CycleSpectrumScale,
+    CycleSpectrumOverlap,
+    CycleSpectrumSmoothing,
+    /// Show the unsmoothed spectrum faintly behind the smoothed one
+    ToggleRawSpectrum,
+    /// Average the spectra from now on, or stop
+    ToggleSpectrumAverage,
+    /// Write the shown spectrum to a CSV file
+    ExportSpectrum,
+    /// Write every spectrum so far to a labeled PNG
+    ExportSpectrogram,
+    ToggleBandPower,
+    MoveBandCursor(BandCursor, f32),
+    ToggleTimeGate,
+    MoveGateCursor(GateCursor, f32),
+    SetZoom(ZoomWindow),
+    /// Widen (above 1) or narrow the zoom region by this factor
+    ScaleZoom(f32),
+    IncreaseReferenceLevel,
+    DecreaseReferenceLevel,
+    Calibrate,
+    ClearCalibration,
+    ToggleLevelUnit,
+    ToggleGrid,
+    CycleGridDivisions,
+    ToggleMinorTicks,
+    ToggleGridStyle,
+    ToggleAxisLabels,
+    OpenPreferences,
+    OpenFilters,
+    OpenDesigner,
+    SetMathExpression(String),
+    ToggleMath,
+    IncreaseMathScale,
+    DecreaseMathScale,
 }
 
 pub struct ControlState {
     pub time_per_div: f32,
+    pub decimation: Decimation,
     pub volts_per_div: f32,
+    /// Whether volts/div follows the signal
+    pub auto_range: bool,
+    /// Of channels A and B
+    pub bandwidth: [BandwidthLimit; 2],
     pub trigger_enabled: bool,
+    pub trigger_mode: TriggerMode,
     pub trigger_level: f32,
     pub persistence_enabled: bool,
     pub persistence_frames: usize,
+    pub persistence_mode: PersistenceMode,
+    pub persistence_decay: PersistenceDecay,
+    pub persistence_colormap: PersistenceColormap,
+    pub envelope: EnvelopeFollower,
+    pub logging_enabled: bool,
+    pub logged_seconds: f32,
+    /// Monitor gain in dB, while the input is playing through the speakers
+    pub monitor_gain: Option<f32>,
+    pub monitor_muted: bool,
+    /// Muted for feedback rather than by the user
+    pub monitor_feedback: bool,
+    /// Of the live input, once a frame has been shown
+    pub latency: Option<Latency>,
+    pub loopback_running: bool,
+    /// Of the last loopback test
+    pub loopback_result: Option<LoopbackResult>,
+    pub data_loss: DataLoss,
+    pub decoder_mode: DecoderMode,
+    pub baud_rate: u32,
+    pub decoded_text: String, // most recent decoded symbols, newest last
+    pub theme: DisplayTheme,
+    /// Filter blocks on the input that aren't bypassed
+    pub active_filters: usize,
+    pub plot_mode: PlotMode,
+    pub vectorscope_auto_gain: bool,
+    pub show_harmonics: bool,
+    pub spectrum_scale: SpectrumScale,
+    pub spectrum_overlap: SpectrumOverlap,
+    pub spectrum_smoothing: OctaveSmoothing,
+    pub show_raw_spectrum: bool,
+    /// Spectra averaged so far, while averaging
+    pub spectrum_averages: Option<usize>,
+    pub measuring_band: bool,
+    pub time_gated: bool,
+    /// dB SPL of the calibrator or test tone being measured
+    pub reference_spl: f32,
+    /// Calibration of the current input, if it has one
+    pub calibration: Option<Calibration>,
+    /// Show levels in dB SPL rather than dBFS, once calibrated
+    pub show_spl: bool,
+    pub graticule: Graticule,
+    pub show_axis_labels: bool,
+    /// Math channel expression, as typed
+    pub math_expression: String,
+    /// Why the expression doesn't parse
+    pub math_error: Option<String>,
+    pub math_enabled: bool,
+    /// Vertical scale of the math trace
+    pub math_per_div: f32,
+    /// User labels, shown in their trace's color
+    pub input_label: Option<String>,
+    pub math_label: Option<String>,
+    pub palette: Palette,
+}
+
+pub struct TransportState {
+    pub playing: bool,
+    pub position: f32, // fraction of the recording, 0.0 to 1.0
+    pub position_seconds: f32,
+    pub duration_seconds: f32,
+    pub speed: f32,
+    pub search: EventSearch,
+    /// Search hits as fractions of the recording
+    pub hits: Vec<(f32, f32)>,
+    /// Index of the hit last jumped to
+    pub current_hit: Option<usize>,
+    pub palette: Palette,
+}
+
+const REPLAY_SPEEDS: [f32; 6] = [0.1, 0.25, 0.5, 1.0, 2.0, 4.0];
+
+/// Shown while input is being lost, since it breaks the measurements
+pub fn build_loss_warning<'a>(loss: DataLoss, palette: &Palette) -> Element<'a, ControlMessage> {
+    container(
+        text(format!(
+            "INPUT LOST: {} samples dropped, {} overruns. Measurements across the gaps are off.",
+            loss.dropped_samples, loss.overruns
+        ))
+        .size(12)
+        .color(to_color(palette.error)),
+    )
+    .padding([2, 10])
+    .into()
+}
+
+pub fn build_transport<'a>(state: &TransportState) -> Element<'a, ControlMessage> {
+    let speed_buttons = REPLAY_SPEEDS.iter().fold(row![].spacing(2), |row, &speed| {
+        let label = if speed == state.speed {
+            format!("[{speed}x]")
+        } else {
+            format!("{speed}x")
+        };
+        row.push(button(text(label).size(11)).on_press(ControlMessage::SetReplaySpeed(speed)))
+    });
+
+    let transport = row![
+        button("|<").on_press(ControlMessage::StepReplay(-1)),
+        button(if state.playing { "Pause" } else { "Play" })
+            .on_press(ControlMessage::TogglePlayback),
+        button(">|").on_press(ControlMessage::StepReplay(1)),
+        slider(0.0..=1.0, state.position, ControlMessage::SeekReplay)
+            .step(0.0001)
+            .width(Length::Fill),
+        text(format!(
+            "{} / {}",
+            format_time(state.position_seconds),
+            format_time(state.duration_seconds)
+        ))
+        .width(Length::Fixed(120.0)),
+        speed_buttons,
+        button("Live").on_press(ControlMessage::ExitReplay),
+    ]
+    .spacing(10)
+    .align_y(Alignment::Center);
+
+    let search = state.search;
+    let mut search_controls = row![
+        text("Search").size(14),
+        button(text(search.kind.label()).size(11)).on_press(ControlMessage::CycleSearchKind),
+        button("-").on_press(ControlMessage::DecreaseSearchLevel),
+        text(format_volts(search.level)).width(Length::Fixed(50.0)),
+        button("+").on_press(ControlMessage::IncreaseSearchLevel),
+    ]
+    .spacing(5)
+    .align_y(Alignment::Center);
+    if search.kind.uses_width() {
+        search_controls = search_controls.push(
+            row![
+                button("-").on_press(ControlMessage::DecreaseSearchWidth),
+                text(format_time(search.max_width)).width(Length::Fixed(70.0)),
+                button("+").on_press(ControlMessage::IncreaseSearchWidth),
+            ]
+            .spacing(5)
+            .align_y(Alignment::Center),
+        );
+    }
+    let hit_count = match state.current_hit {
+        Some(index) => format!("{} of {} hits", index + 1, state.hits.len()),
+        None => format!("{} hits", state.hits.len()),
+    };
+    search_controls = search_controls
+        .push(button("< Prev").on_press(ControlMessage::PreviousHit))
+        .push(button("Next >").on_press(ControlMessage::NextHit))
+        .push(text(hit_count).size(11));
+
+    let timeline = canvas(Timeline {
+        hits: state.hits.clone(),
+        current: state.current_hit,
+        position: state.position,
+        palette: state.palette,
+    })
+    .width(Length::Fill)
+    .height(Length::Fixed(14.0));
+
+    container(column![transport, timeline, search_controls].spacing(5))
+        .padding(10)
+        .into()
+}
+
+/// A section heading followed by the label of the trace it applies to
+fn trace_heading<'a>(
+    heading: &'a str,
+    label: &Option<String>,
+    color: Rgba,
+) -> Element<'a, ControlMessage> {
+    let mut heading = row![text(heading).size(14)]
+        .spacing(8)
+        .align_y(Alignment::Center);
+    if let Some(label) = label {
+        heading = heading.push(text(label.clone()).size(14).color(to_color(color)));
+    }
+    heading.into()
 }
 
 pub fn build_controls<'a>(
     state: &ControlState,
     measurements: &Measurements,
+    plugin_measurements: &[PluginMeasurement],
 ) -> Element<'a, ControlMessage> {
     let time_per_div = state.time_per_div;
     let volts_per_div = state.volts_per_div;
@@ -54,6 +395,10 @@ pub fn build_controls<'a>(
     let trigger_level = state.trigger_level;
     let persistence_enabled = state.persistence_enabled;
     let persistence_frames = state.persistence_frames;
+    let logging_enabled = state.logging_enabled;
+    let logged_seconds = state.logged_seconds;
+    let decoder_mode = state.decoder_mode;
+    let baud_rate = state.baud_rate;
     // Convert time_per_div to logarithmic scale for slider (10µs to 1s)
     // log10(0.00001) = -5, log10(1.0) = 0
     let time_log = time_per_div.log10();
@@ -62,7 +407,7 @@ pub fn build_controls<'a>(
         text("Time/Div").size(14),
         row![
             button("-").on_press(ControlMessage::DecreaseTimeScale),
-            text(format!("{:.2} ms", time_per_div * 1000.0)).width(Length::Fixed(80.0)),
+            text(format_time(time_per_div)).width(Length::Fixed(80.0)),
             button("+").on_press(ControlMessage::IncreaseTimeScale),
         ]
         .spacing(5)
@@ -72,6 +417,7 @@ pub fn build_controls<'a>(
         })
         .step(0.01)
         .width(Length::Fixed(150.0)),
+        button(state.decimation.label()).on_press(ControlMessage::CycleDecimation),
     ]
     .spacing(5);
 
@@ -80,10 +426,16 @@ pub fn build_controls<'a>(
     let volts_log = volts_per_div.log10();
 
     let voltage_controls = column![
-        text("Volts/Div").size(14),
+        row![
+            text("Volts/Div").size(14),
+            button(text(if state.auto_range { "AUTO" } else { "Auto" }).size(11))
+                .on_press(ControlMessage::ToggleAutoRange),
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center),
         row![
             button("-").on_press(ControlMessage::DecreaseVoltageScale),
-            text(format!("{volts_per_div:.2} V")).width(Length::Fixed(80.0)),
+            text(format_volts(volts_per_div)).width(Length::Fixed(80.0)),
             button("+").on_press(ControlMessage::IncreaseVoltageScale),
         ]
         .spacing(5)
@@ -93,6 +445,15 @@ pub fn build_controls<'a>(
         })
         .step(0.01)
         .width(Length::Fixed(150.0)),
+        row![
+            text("BW").size(11),
+            button(text(format!("A {}", state.bandwidth[0].label())).size(11))
+                .on_press(ControlMessage::CycleBandwidth(0)),
+            button(text(format!("B {}", state.bandwidth[1].label())).size(11))
+                .on_press(ControlMessage::CycleBandwidth(1)),
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center),
     ]
     .spacing(5);
 
@@ -102,11 +463,12 @@ pub fn build_controls<'a>(
             button(if trigger_enabled { "ON" } else { "OFF" })
                 .on_press(ControlMessage::ToggleTrigger),
             button("Edge").on_press(ControlMessage::ToggleTriggerEdge),
+            button(state.trigger_mode.label()).on_press(ControlMessage::CycleTriggerMode),
         ]
         .spacing(5),
         row![
             button("-").on_press(ControlMessage::DecreaseTriggerLevel),
-            text(format!("{trigger_level:.2} V")).width(Length::Fixed(80.0)),
+            text(format_volts(trigger_level)).width(Length::Fixed(80.0)),
             button("+").on_press(ControlMessage::IncreaseTriggerLevel),
         ]
         .spacing(5)
@@ -118,7 +480,7 @@ pub fn build_controls<'a>(
     .spacing(5);
 
     let measurements_display = column![
-        text("Measurements").size(14),
+        trace_heading("Measurements", &state.input_label, state.palette.trace),
         text(if let Some(freq) = measurements.frequency {
             if freq >= 1000.0 {
                 format!("Freq: {:.2} kHz", freq / 1000.0)
@@ -130,13 +492,13 @@ pub fn build_controls<'a>(
         })
         .size(11),
         text(if let Some(pk_pk) = measurements.peak_to_peak {
-            format!("Vpp: {pk_pk:.3} V")
+            format!("Vpp: {}", format_volts(pk_pk))
         } else {
             "Vpp: --".to_string()
         })
         .size(11),
         text(if let Some(rms_val) = measurements.rms {
-            format!("Vrms: {rms_val:.3} V")
+            format!("Vrms: {}", format_volts(rms_val))
         } else {
             "Vrms: --".to_string()
         })
@@ -147,13 +509,74 @@ pub fn build_controls<'a>(
             "Duty: --".to_string()
         })
         .size(11),
+        text(match (measurements.rms, state.calibration) {
+            (Some(rms), Some(calibration)) if state.show_spl => {
+                format!("Level: {:.1} dB SPL", calibration.spl(rms_dbfs(rms)))
+            }
+            (Some(rms), _) => format!("Level: {:.1} dBFS", rms_dbfs(rms)),
+            (None, _) => "Level: --".to_string(),
+        })
+        .size(11),
     ]
     .spacing(3);
+    // Plugins are listed after the built-in measurements
+    let measurements_display =
+        plugin_measurements
+            .iter()
+            .fold(measurements_display, |display, measurement| {
+                display.push(
+                    text(match measurement.value {
+                        Some(value) => {
+                            format!("{}: {value:.3} {}", measurement.name, measurement.unit)
+                        }
+                        None => format!("{}: --", measurement.name),
+                    })
+                    .size(11),
+                )
+            });
+
+    // Calibrate with the reference tone playing into the input
+    let calibration_controls = column![
+        text("Calibration").size(14),
+        row![
+            button("-").on_press(ControlMessage::DecreaseReferenceLevel),
+            text(format!("{:.0} dB SPL", state.reference_spl)).width(Length::Fixed(80.0)),
+            button("+").on_press(ControlMessage::IncreaseReferenceLevel),
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center),
+        row![
+            button("Calibrate").on_press(ControlMessage::Calibrate),
+            button("Clear").on_press(ControlMessage::ClearCalibration),
+        ]
+        .spacing(5),
+        button(if state.calibration.is_some() && state.show_spl {
+            "dB SPL"
+        } else {
+            "dBFS"
+        })
+        .on_press(ControlMessage::ToggleLevelUnit),
+    ]
+    .spacing(5);
 
     let persistence_controls = column![
         text("Persistence").size(14),
-        row![button(if persistence_enabled { "ON" } else { "OFF" })
-            .on_press(ControlMessage::TogglePersistence),]
+        row![
+            button(if persistence_enabled { "ON" } else { "OFF" })
+                .on_press(ControlMessage::TogglePersistence),
+            button(state.persistence_mode.label()).on_press(ControlMessage::CyclePersistenceMode),
+            button(state.persistence_decay.label()).on_press(ControlMessage::CyclePersistenceDecay),
+        ]
+        .spacing(5),
+        row![
+            button(if state.persistence_colormap == PersistenceColormap::Heat {
+                "Heat map"
+            } else {
+                "Trace color"
+            })
+            .on_press(ControlMessage::CyclePersistenceColormap),
+            button("Clear").on_press(ControlMessage::ClearPersistence),
+        ]
         .spacing(5),
         row![
             button("-").on_press(ControlMessage::DecreasePersistence),
@@ -162,31 +585,299 @@ pub fn build_controls<'a>(
         ]
         .spacing(5)
         .align_y(Alignment::Center),
-        slider(1.0..=30.0, persistence_frames as f32, |val| {
-            ControlMessage::SetPersistenceFrames(val as u8)
-        })
+        slider(
+            1.0..=MAX_PERSISTENCE_FRAMES as f32,
+            persistence_frames as f32,
+            |val| { ControlMessage::SetPersistenceFrames(val as u16) }
+        )
         .step(1.0)
         .width(Length::Fixed(150.0)),
     ]
     .spacing(5);
 
+    // Attack and release sliders are logarithmic, like time/div
+    let envelope = &state.envelope;
+    let envelope_controls = column![
+        text("Envelope").size(14),
+        button(envelope.mode.label()).on_press(ControlMessage::CycleEnvelope),
+        text(format!("Attack {}", format_time(envelope.attack()))).size(11),
+        slider(
+            ATTACK_RANGE.0.log10()..=ATTACK_RANGE.1.log10(),
+            envelope.attack().log10(),
+            |val| ControlMessage::SetEnvelopeAttack(10_f32.powf(val))
+        )
+        .step(0.01)
+        .width(Length::Fixed(150.0)),
+        text(format!("Release {}", format_time(envelope.release()))).size(11),
+        slider(
+            RELEASE_RANGE.0.log10()..=RELEASE_RANGE.1.log10(),
+            envelope.release().log10(),
+            |val| ControlMessage::SetEnvelopeRelease(10_f32.powf(val))
+        )
+        .step(0.01)
+        .width(Length::Fixed(150.0)),
+    ]
+    .spacing(5);
+
+    let logging_controls = column![
+        text("Logging").size(14),
+        row![
+            button(if logging_enabled { "REC" } else { "OFF" })
+                .on_press(ControlMessage::ToggleLogging),
+            button("Replay").on_press(ControlMessage::ReplayLatestLog),
+        ]
+        .spacing(5),
+        text(if logging_enabled {
+            format!("{logged_seconds:.0} s")
+        } else {
+            "--".to_string()
+        })
+        .size(11),
+    ]
+    .spacing(5);
+
+    let mut monitor_controls = column![
+        text("Monitor").size(14),
+        row![button(if state.monitor_gain.is_some() {
+            "ON"
+        } else {
+            "OFF"
+        })
+        .on_press(ControlMessage::ToggleMonitor),]
+        .spacing(5),
+    ]
+    .spacing(5);
+    if let Some(gain) = state.monitor_gain {
+        monitor_controls = monitor_controls
+            .push(
+                button(if state.monitor_muted { "Muted" } else { "Mute" })
+                    .on_press(ControlMessage::ToggleMonitorMute),
+            )
+            .push(
+                slider(
+                    MONITOR_GAIN_RANGE.0..=MONITOR_GAIN_RANGE.1,
+                    gain,
+                    ControlMessage::SetMonitorGain,
+                )
+                .step(0.5)
+                .width(Length::Fixed(120.0)),
+            );
+    }
+    let monitor_controls = monitor_controls.push(
+        text(match state.monitor_gain {
+            _ if state.monitor_feedback => "Feedback, muted".to_string(),
+            Some(gain) => format!("{gain:+.1} dB"),
+            None => "--".to_string(),
+        })
+        .size(11),
+    );
+
+    let diagnostics = column![
+        text("Diagnostics").size(14),
+        text(match state.latency {
+            Some(latency) => format!("~{:.0} ms", latency.total().as_secs_f64() * 1000.0),
+            None => "--".to_string(),
+        }),
+        text(match state.latency {
+            Some(latency) => format!(
+                "buf {:.0} / proc {:.0} / frame {:.0}",
+                latency.buffer.as_secs_f64() * 1000.0,
+                latency.processing.as_secs_f64() * 1000.0,
+                latency.frame.as_secs_f64() * 1000.0,
+            ),
+            None => String::new(),
+        })
+        .size(11),
+        text(format!(
+            "Dropped {} / overruns {}",
+            state.data_loss.dropped_samples, state.data_loss.overruns
+        ))
+        .size(11),
+        button("Loopback").on_press(ControlMessage::LoopbackTest),
+        text(match state.loopback_result {
+            _ if state.loopback_running => "Listening...".to_string(),
+            Some(LoopbackResult::RoundTrip(delay)) => {
+                format!("Round trip {:.1} ms", delay.as_secs_f64() * 1000.0)
+            }
+            Some(LoopbackResult::NotHeard) => "No click heard".to_string(),
+            None => "--".to_string(),
+        })
+        .size(11),
+    ]
+    .spacing(5);
+
+    let mut decoder_controls = column![
+        text("Decode").size(14),
+        row![button(decoder_mode.label()).on_press(ControlMessage::CycleDecoder),].spacing(5),
+    ]
+    .spacing(5);
+
+    if decoder_mode == DecoderMode::Uart {
+        decoder_controls = decoder_controls.push(
+            row![
+                button("-").on_press(ControlMessage::DecreaseBaudRate),
+                text(format!("{baud_rate} Bd")).width(Length::Fixed(80.0)),
+                button("+").on_press(ControlMessage::IncreaseBaudRate),
+            ]
+            .spacing(5)
+            .align_y(Alignment::Center),
+        );
+    }
+
+    let decoder_controls = decoder_controls.push(
+        text(if decoder_mode == DecoderMode::Off {
+            "--".to_string()
+        } else {
+            state.decoded_text.clone()
+        })
+        .size(11)
+        .width(Length::Fixed(150.0)),
+    );
+
+    let mut plot_controls =
+        row![button(state.plot_mode.label()).on_press(ControlMessage::CyclePlot)].spacing(5);
+    if state.plot_mode == PlotMode::Vectorscope {
+        plot_controls = plot_controls.push(
+            button(if state.vectorscope_auto_gain {
+                "Auto gain"
+            } else {
+                "Fixed gain"
+            })
+            .on_press(ControlMessage::ToggleVectorscopeGain),
+        );
+    }
+
     let layout_selector = column![
         text("Layout").size(14),
         row![
+            button("▣").on_press(ControlMessage::SetLayoutMode(LayoutMode::ScopeOnly)),
+            button("◫").on_press(ControlMessage::SetLayoutMode(LayoutMode::SpectrumOnly)),
             button("◧").on_press(ControlMessage::SetLayoutMode(LayoutMode::SideBySide)),
             button("⬒").on_press(ControlMessage::SetLayoutMode(LayoutMode::Stacked)),
         ]
         .spacing(5),
+        text("Plot").size(14),
+        plot_controls,
+        text("Theme").size(14),
+        button(state.theme.label()).on_press(ControlMessage::CycleTheme),
+        button("Preferences").on_press(ControlMessage::OpenPreferences),
+        button(text(match state.active_filters {
+            0 => "Filters".to_string(),
+            count => format!("Filters ({count})"),
+        }))
+        .on_press(ControlMessage::OpenFilters),
+        button("Designer").on_press(ControlMessage::OpenDesigner),
+        text("Spectrum").size(14),
+        button(if state.show_harmonics {
+            "Harmonics"
+        } else {
+            "No harmonics"
+        })
+        .on_press(ControlMessage::ToggleHarmonics),
+        button(state.spectrum_scale.label()).on_press(ControlMessage::CycleSpectrumScale),
+        button(text(format!("Overlap {}", state.spectrum_overlap.label())))
+            .on_press(ControlMessage::CycleSpectrumOverlap),
+        button(state.spectrum_smoothing.label()).on_press(ControlMessage::CycleSpectrumSmoothing),
+        button(if state.show_raw_spectrum {
+            "Raw behind"
+        } else {
+            "No raw"
+        })
+        .on_press_maybe(
+            (state.spectrum_smoothing != OctaveSmoothing::Off)
+                .then_some(ControlMessage::ToggleRawSpectrum)
+        ),
+        button(text(match state.spectrum_averages {
+            Some(count) => format!("Averaging ({count})"),
+            None => "No averaging".to_string(),
+        }))
+        .on_press(ControlMessage::ToggleSpectrumAverage),
+        button("Export CSV").on_press(ControlMessage::ExportSpectrum),
+        button("Spectrogram PNG").on_press(ControlMessage::ExportSpectrogram),
+        button(if state.measuring_band {
+            "Band power"
+        } else {
+            "No band"
+        })
+        .on_press(ControlMessage::ToggleBandPower),
+        button(if state.time_gated {
+            "Time gated"
+        } else {
+            "Whole buffer"
+        })
+        .on_press(ControlMessage::ToggleTimeGate),
+    ]
+    .spacing(5);
+
+    let math_controls = column![
+        trace_heading("Math", &state.math_label, state.palette.math),
+        text_input("A - avg(A)", &state.math_expression)
+            .on_input(ControlMessage::SetMathExpression)
+            .size(12)
+            .width(Length::Fixed(150.0)),
+        row![
+            button(if state.math_enabled { "ON" } else { "OFF" })
+                .on_press(ControlMessage::ToggleMath),
+            button("-").on_press(ControlMessage::DecreaseMathScale),
+            text(format!("{}/div", format_volts(state.math_per_div))).size(11),
+            button("+").on_press(ControlMessage::IncreaseMathScale),
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center),
+        text(
+            state
+                .math_error
+                .clone()
+                .unwrap_or_else(|| "A, B: left, right".to_string())
+        )
+        .size(11)
+        .width(Length::Fixed(150.0)),
+    ]
+    .spacing(5);
+
+    let graticule = &state.graticule;
+    let grid_controls = column![
+        text("Grid").size(14),
+        row![
+            button(if graticule.enabled { "ON" } else { "OFF" })
+                .on_press(ControlMessage::ToggleGrid),
+            button(text(graticule.divisions_label())).on_press(ControlMessage::CycleGridDivisions),
+        ]
+        .spacing(5),
+        row![
+            button(if graticule.minor_ticks > 0 {
+                "Ticks"
+            } else {
+                "No ticks"
+            })
+            .on_press(ControlMessage::ToggleMinorTicks),
+            button(graticule.style.label()).on_press(ControlMessage::ToggleGridStyle),
+        ]
+        .spacing(5),
+        button(if state.show_axis_labels {
+            "Labels"
+        } else {
+            "No labels"
+        })
+        .on_press(ControlMessage::ToggleAxisLabels),
     ]
     .spacing(5);
 
     container(
         row![
             layout_selector,
+            grid_controls,
             time_controls,
             voltage_controls,
             trigger_controls,
+            math_controls,
             persistence_controls,
+            envelope_controls,
+            logging_controls,
+            monitor_controls,
+            diagnostics,
+            decoder_controls,
+            calibration_controls,
             measurements_display
         ]
         .spacing(20)
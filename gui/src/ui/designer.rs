@@ -0,0 +1,158 @@
+use iced::widget::{button, column, container, row, slider, text};
+use iced::{Alignment, Element, Length};
+use ozeecubed_core::dsp::{FilterDesign, FilterResponse};
+
+/// Cutoff slider range in Hz, logarithmic
+const CUTOFF_RANGE: (f32, f32) = (10.0, 20000.0);
+
+#[derive(Debug, Clone)]
+pub enum DesignerMessage {
+    CycleFamily,
+    CycleResponse,
+    IncreaseOrder,
+    DecreaseOrder,
+    SetCutoff(f32),
+    SetUpperCutoff(f32),
+    /// Add the design to the end of the input filter chain
+    Insert,
+    Close,
+}
+
+/// Filter designer shown in place of the control strip, so the spectrum
+/// stays in view with the design's response drawn over it
+#[derive(Debug, Default)]
+pub struct FilterDesigner {
+    design: FilterDesign,
+}
+
+impl FilterDesigner {
+    pub fn design(&self) -> FilterDesign {
+        self.design
+    }
+
+    /// Every message but `Insert` and `Close`, which the app handles
+    pub fn update(&mut self, message: DesignerMessage) {
+        let design = &mut self.design;
+        match message {
+            DesignerMessage::CycleFamily => *design = design.with_family(design.family.next()),
+            DesignerMessage::CycleResponse => design.response = design.response.next(),
+            DesignerMessage::IncreaseOrder => design.step_order(true),
+            DesignerMessage::DecreaseOrder => design.step_order(false),
+            DesignerMessage::SetCutoff(frequency) => design.cutoff = frequency,
+            DesignerMessage::SetUpperCutoff(frequency) => design.upper_cutoff = frequency,
+            DesignerMessage::Insert | DesignerMessage::Close => {}
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, DesignerMessage> {
+        let design = self.design;
+        let shape = column![
+            text("Filter Designer").size(14),
+            row![
+                button(design.family.label()).on_press(DesignerMessage::CycleFamily),
+                button(design.response.label()).on_press(DesignerMessage::CycleResponse),
+            ]
+            .spacing(5),
+        ]
+        .spacing(5);
+
+        let order = column![
+            text("Order").size(14),
+            row![
+                button("-").on_press(DesignerMessage::DecreaseOrder),
+                text(format!("{}", design.order)).width(Length::Fixed(50.0)),
+                button("+").on_press(DesignerMessage::IncreaseOrder),
+            ]
+            .spacing(5)
+            .align_y(Alignment::Center),
+        ]
+        .spacing(5);
+
+        let mut cutoffs = column![cutoff_slider(
+            if design.response == FilterResponse::BandPass {
+                "Lower"
+            } else {
+                "Cutoff"
+            },
+            design.cutoff,
+            DesignerMessage::SetCutoff,
+        )]
+        .spacing(5);
+        if design.response == FilterResponse::BandPass {
+            cutoffs = cutoffs.push(cutoff_slider(
+                "Upper",
+                design.upper_cutoff,
+                DesignerMessage::SetUpperCutoff,
+            ));
+        }
+
+        container(
+            row![
+                shape,
+                order,
+                cutoffs,
+                column![
+                    text(design.summary()).size(11),
+                    text("Response drawn over the spectrum, 0 dB at the top").size(11),
+                    row![
+                        button("Insert").on_press(DesignerMessage::Insert),
+                        button("Close").on_press(DesignerMessage::Close),
+                    ]
+                    .spacing(5),
+                ]
+                .spacing(5),
+            ]
+            .spacing(20)
+            .padding(10)
+            .align_y(Alignment::Start),
+        )
+        .into()
+    }
+}
+
+fn cutoff_slider<'a>(
+    label: &'a str,
+    frequency: f32,
+    on_change: fn(f32) -> DesignerMessage,
+) -> Element<'a, DesignerMessage> {
+    column![
+        text(format!("{label} {frequency:.0} Hz")).size(11),
+        slider(
+            CUTOFF_RANGE.0.log10()..=CUTOFF_RANGE.1.log10(),
+            frequency.log10(),
+            move |log| on_change(10_f32.powf(log)),
+        )
+        .step(0.01)
+        .width(Length::Fixed(200.0)),
+    ]
+    .spacing(3)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ozeecubed_core::dsp::FilterFamily;
+
+    #[test]
+    fn test_editing_the_design() {
+        let mut designer = FilterDesigner::default();
+        designer.update(DesignerMessage::CycleResponse);
+        designer.update(DesignerMessage::IncreaseOrder);
+        designer.update(DesignerMessage::SetCutoff(50.0));
+        designer.update(DesignerMessage::CycleFamily);
+        // Switching family keeps the order when it's in range
+        assert_eq!(
+            designer.design(),
+            FilterDesign {
+                family: FilterFamily::WindowedSinc,
+                response: FilterResponse::HighPass,
+                order: 5,
+                cutoff: 50.0,
+                ..FilterDesign::default()
+            }
+        );
+        designer.update(DesignerMessage::DecreaseOrder);
+        assert_eq!(designer.design().order, 4);
+    }
+}
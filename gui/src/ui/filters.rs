@@ -0,0 +1,321 @@
+use iced::widget::{
+    button, column, container, pick_list, row, scrollable, slider, text, text_input,
+};
+use iced::{Alignment, Element, Length};
+use ozeecubed_core::dsp::{Biquad, FilterBlock, FilterKind};
+
+/// Corner frequency slider range in Hz, logarithmic
+const FREQUENCY_RANGE: (f32, f32) = (10.0, 20000.0);
+const Q_RANGE: (f32, f32) = (0.1, 20.0);
+const GAIN_RANGE: (f32, f32) = (-40.0, 40.0);
+
+/// Entry in the "Add" picker, an index into `FilterKind::DEFAULTS`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NewBlock(usize);
+
+impl std::fmt::Display for NewBlock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(FilterKind::DEFAULTS[self.0].label())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum FilterMessage {
+    Add(NewBlock),
+    Remove(usize),
+    MoveUp(usize),
+    MoveDown(usize),
+    ToggleBypass(usize),
+    SetFrequency(usize, f32),
+    SetQ(usize, f32),
+    SetGain(usize, f32),
+    CycleWeighting(usize),
+    /// Text typed into one of a custom biquad's five coefficients
+    EditCoefficient(usize, usize, String),
+    Close,
+}
+
+/// Editor for the input filter chain. Edits apply to the chain as they're
+/// made; the app saves it when the editor closes.
+#[derive(Default)]
+pub struct FilterDialog {
+    /// Coefficient being typed, by block and coefficient, kept as typed
+    /// until it parses
+    editing: Option<(usize, usize, String)>,
+}
+
+impl FilterDialog {
+    /// Every message but `Close`, which the app handles
+    pub fn update(&mut self, blocks: &mut Vec<FilterBlock>, message: FilterMessage) {
+        if !matches!(message, FilterMessage::EditCoefficient(..)) {
+            self.editing = None;
+        }
+        match message {
+            FilterMessage::Add(NewBlock(index)) => {
+                blocks.push(FilterBlock::new(FilterKind::DEFAULTS[index]));
+            }
+            FilterMessage::Remove(index) if index < blocks.len() => {
+                blocks.remove(index);
+            }
+            FilterMessage::MoveUp(index) if index > 0 && index < blocks.len() => {
+                blocks.swap(index - 1, index);
+            }
+            FilterMessage::MoveDown(index) if index + 1 < blocks.len() => {
+                blocks.swap(index, index + 1);
+            }
+            FilterMessage::ToggleBypass(index) => {
+                if let Some(block) = blocks.get_mut(index) {
+                    block.bypassed = !block.bypassed;
+                }
+            }
+            FilterMessage::SetFrequency(index, value) => {
+                if let Some(FilterKind::HighPass { frequency, .. })
+                | Some(FilterKind::LowPass { frequency, .. })
+                | Some(FilterKind::Notch { frequency, .. }) =
+                    blocks.get_mut(index).map(|block| &mut block.kind)
+                {
+                    *frequency = value;
+                }
+            }
+            FilterMessage::SetQ(index, value) => {
+                if let Some(FilterKind::HighPass { q, .. })
+                | Some(FilterKind::LowPass { q, .. })
+                | Some(FilterKind::Notch { q, .. }) =
+                    blocks.get_mut(index).map(|block| &mut block.kind)
+                {
+                    *q = value;
+                }
+            }
+            FilterMessage::SetGain(index, value) => {
+                if let Some(FilterKind::Gain { db }) =
+                    blocks.get_mut(index).map(|block| &mut block.kind)
+                {
+                    *db = value;
+                }
+            }
+            FilterMessage::CycleWeighting(index) => {
+                if let Some(FilterKind::Weighting(weighting)) =
+                    blocks.get_mut(index).map(|block| &mut block.kind)
+                {
+                    *weighting = weighting.next();
+                }
+            }
+            FilterMessage::EditCoefficient(index, coefficient, typed) => {
+                if let Some(FilterKind::Biquad(biquad)) =
+                    blocks.get_mut(index).map(|block| &mut block.kind)
+                {
+                    if let Some(value) = typed.trim().parse().ok().filter(|x: &f64| x.is_finite()) {
+                        *coefficient_mut(biquad, coefficient) = value;
+                    }
+                    self.editing = Some((index, coefficient, typed));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn view(&self, blocks: &[FilterBlock]) -> Element<'_, FilterMessage> {
+        let chain = blocks
+            .iter()
+            .enumerate()
+            .fold(column![].spacing(8), |chain, (index, block)| {
+                chain.push(self.block_view(index, block, blocks.len()))
+            });
+        let chain: Element<'_, FilterMessage> = if blocks.is_empty() {
+            text("No filters: the input is shown as captured")
+                .size(12)
+                .into()
+        } else {
+            scrollable(chain).height(Length::Fixed(320.0)).into()
+        };
+
+        let kinds: Vec<NewBlock> = (0..FilterKind::DEFAULTS.len()).map(NewBlock).collect();
+        container(
+            column![
+                text("Input Filters").size(18),
+                chain,
+                row![
+                    pick_list(kinds, None::<NewBlock>, FilterMessage::Add).placeholder("Add..."),
+                    button("Done").on_press(FilterMessage::Close),
+                ]
+                .spacing(10),
+                text("Applied in order to the live input before display, analysis and logging")
+                    .size(11),
+            ]
+            .spacing(15),
+        )
+        .padding(20)
+        .style(container::rounded_box)
+        .into()
+    }
+
+    fn block_view(
+        &self,
+        index: usize,
+        block: &FilterBlock,
+        count: usize,
+    ) -> Element<'_, FilterMessage> {
+        let header = row![
+            text(format!("{}. {}", index + 1, block.kind.label())).width(Length::Fixed(110.0)),
+            button(if block.bypassed { "Bypassed" } else { "On" })
+                .on_press(FilterMessage::ToggleBypass(index)),
+            button("Up").on_press_maybe((index > 0).then_some(FilterMessage::MoveUp(index))),
+            button("Down")
+                .on_press_maybe((index + 1 < count).then_some(FilterMessage::MoveDown(index))),
+            button("Remove").on_press(FilterMessage::Remove(index)),
+        ]
+        .spacing(5)
+        .align_y(Alignment::Center);
+
+        let settings: Element<'_, FilterMessage> = match block.kind {
+            FilterKind::Gain { db } => labeled_slider(
+                format!("{db:+.1} dB"),
+                slider(GAIN_RANGE.0..=GAIN_RANGE.1, db, move |db| {
+                    FilterMessage::SetGain(index, db)
+                })
+                .step(0.5),
+            ),
+            FilterKind::HighPass { frequency, q }
+            | FilterKind::LowPass { frequency, q }
+            | FilterKind::Notch { frequency, q } => column![
+                labeled_slider(
+                    format!("{frequency:.0} Hz"),
+                    slider(
+                        FREQUENCY_RANGE.0.log10()..=FREQUENCY_RANGE.1.log10(),
+                        frequency.log10(),
+                        move |log| FilterMessage::SetFrequency(index, 10_f32.powf(log)),
+                    )
+                    .step(0.01),
+                ),
+                labeled_slider(
+                    format!("Q {q:.2}"),
+                    slider(Q_RANGE.0..=Q_RANGE.1, q, move |q| {
+                        FilterMessage::SetQ(index, q)
+                    })
+                    .step(0.01),
+                ),
+            ]
+            .spacing(3)
+            .into(),
+            FilterKind::Weighting(weighting) => row![
+                text("Curve").width(Length::Fixed(90.0)),
+                button(weighting.label()).on_press(FilterMessage::CycleWeighting(index)),
+            ]
+            .align_y(Alignment::Center)
+            .into(),
+            FilterKind::Biquad(mut biquad) => {
+                let names = ["b0", "b1", "b2", "a1", "a2"];
+                let inputs = names.iter().enumerate().fold(
+                    row![].spacing(5),
+                    |inputs, (coefficient, name)| {
+                        let value = match self.editing {
+                            Some((i, c, ref typed)) if i == index && c == coefficient => {
+                                typed.clone()
+                            }
+                            _ => coefficient_mut(&mut biquad, coefficient).to_string(),
+                        };
+                        inputs.push(
+                            column![
+                                text(*name).size(11),
+                                text_input(name, &value)
+                                    .on_input(move |typed| {
+                                        FilterMessage::EditCoefficient(index, coefficient, typed)
+                                    })
+                                    .size(12)
+                                    .width(Length::Fixed(70.0)),
+                            ]
+                            .spacing(2),
+                        )
+                    },
+                );
+                let mut settings = column![inputs].spacing(3);
+                if !biquad.is_stable() {
+                    settings = settings.push(text("Unstable: left out of the chain").size(11));
+                }
+                settings.into()
+            }
+            FilterKind::Designed(design) => text(design.summary()).size(12).into(),
+        };
+
+        column![header, settings].spacing(5).into()
+    }
+}
+
+fn labeled_slider<'a>(
+    label: String,
+    slider: impl Into<Element<'a, FilterMessage>>,
+) -> Element<'a, FilterMessage> {
+    row![
+        text(label).size(12).width(Length::Fixed(90.0)),
+        container(slider).width(Length::Fixed(220.0)),
+    ]
+    .align_y(Alignment::Center)
+    .into()
+}
+
+/// Coefficients in the order they're shown: b0, b1, b2, a1, a2
+fn coefficient_mut(biquad: &mut Biquad, coefficient: usize) -> &mut f64 {
+    match coefficient {
+        0 => &mut biquad.b0,
+        1 => &mut biquad.b1,
+        2 => &mut biquad.b2,
+        3 => &mut biquad.a1,
+        _ => &mut biquad.a2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ozeecubed_core::dsp::Weighting;
+
+    #[test]
+    fn test_editing_the_chain() {
+        let mut dialog = FilterDialog::default();
+        let mut blocks = Vec::new();
+        dialog.update(&mut blocks, FilterMessage::Add(NewBlock(0)));
+        dialog.update(&mut blocks, FilterMessage::Add(NewBlock(4)));
+        dialog.update(&mut blocks, FilterMessage::MoveUp(1));
+        dialog.update(&mut blocks, FilterMessage::CycleWeighting(0));
+        dialog.update(&mut blocks, FilterMessage::SetGain(1, -6.0));
+        dialog.update(&mut blocks, FilterMessage::ToggleBypass(1));
+        // Out of range moves are ignored
+        dialog.update(&mut blocks, FilterMessage::MoveDown(1));
+        assert_eq!(
+            blocks,
+            vec![
+                FilterBlock::new(FilterKind::Weighting(Weighting::C)),
+                FilterBlock {
+                    kind: FilterKind::Gain { db: -6.0 },
+                    bypassed: true,
+                },
+            ]
+        );
+
+        dialog.update(&mut blocks, FilterMessage::Remove(0));
+        assert_eq!(blocks.len(), 1);
+    }
+
+    #[test]
+    fn test_coefficients_keep_partial_text() {
+        let mut dialog = FilterDialog::default();
+        let mut blocks = vec![FilterBlock::new(FilterKind::Biquad(Biquad::IDENTITY))];
+        dialog.update(
+            &mut blocks,
+            FilterMessage::EditCoefficient(0, 3, "-".to_string()),
+        );
+        assert_eq!(blocks[0].kind, FilterKind::Biquad(Biquad::IDENTITY));
+        dialog.update(
+            &mut blocks,
+            FilterMessage::EditCoefficient(0, 3, "-0.5".to_string()),
+        );
+        assert_eq!(
+            blocks[0].kind,
+            FilterKind::Biquad(Biquad {
+                a1: -0.5,
+                ..Biquad::IDENTITY
+            })
+        );
+        assert_eq!(dialog.editing, Some((0, 3, "-0.5".to_string())));
+    }
+}
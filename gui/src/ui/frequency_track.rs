@@ -0,0 +1,145 @@
+use iced::alignment::{Horizontal, Vertical};
+use iced::mouse;
+use iced::widget::canvas::{self, Cache, Frame, Geometry, Path, Stroke, Text};
+use iced::{Point, Rectangle, Renderer, Size, Theme};
+
+use super::to_color;
+use ozeecubed_core::display::{frequency_track_labels, LabelAnchor, Palette};
+use ozeecubed_core::oscilloscope::FrequencyTrack;
+
+/// Grid divisions of the frequency track plot (time × frequency)
+const TIME_DIVISIONS: usize = 10;
+const FREQUENCY_DIVISIONS: usize = 4;
+
+/// Instantaneous frequency against time, in place of the spectrum
+pub struct FrequencyTrackCanvas {
+    cache: Cache,
+    track: Option<FrequencyTrack>,
+    palette: Palette,
+}
+
+impl FrequencyTrackCanvas {
+    pub fn new() -> Self {
+        Self {
+            cache: Cache::new(),
+            track: None,
+            palette: Palette::default(),
+        }
+    }
+
+    pub fn set_track(&mut self, track: Option<FrequencyTrack>) {
+        self.track = track;
+        self.cache.clear();
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.cache.clear();
+    }
+
+    pub fn view<'a>(&'a self) -> iced::Element<'a, ()> {
+        iced::widget::canvas(self as &'a Self)
+            .width(iced::Length::Fill)
+            .height(iced::Length::Fill)
+            .into()
+    }
+}
+
+impl canvas::Program<()> for FrequencyTrackCanvas {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let geometry = self.cache.draw(renderer, bounds.size(), |frame| {
+            let width = frame.width();
+            let height = frame.height();
+
+            frame.fill_rectangle(
+                Point::ORIGIN,
+                Size::new(width, height),
+                to_color(self.palette.background),
+            );
+
+            draw_track_grid(frame, width, height, &self.palette);
+
+            if let Some(ref track) = self.track {
+                draw_track(frame, width, height, track, &self.palette);
+            }
+        });
+
+        vec![geometry]
+    }
+}
+
+fn draw_track_grid(frame: &mut Frame, width: f32, height: f32, palette: &Palette) {
+    // Drawn as faint as the spectrum grid
+    let grid_color = to_color(Palette::faded(palette.grid, 0.5));
+
+    for i in 0..=TIME_DIVISIONS {
+        let x = (i as f32 / TIME_DIVISIONS as f32) * width;
+        let line = Path::line(Point::new(x, 0.0), Point::new(x, height));
+        frame.stroke(
+            &line,
+            Stroke::default().with_color(grid_color).with_width(1.0),
+        );
+    }
+
+    for i in 0..=FREQUENCY_DIVISIONS {
+        let y = (i as f32 / FREQUENCY_DIVISIONS as f32) * height;
+        let line = Path::line(Point::new(0.0, y), Point::new(width, y));
+        frame.stroke(
+            &line,
+            Stroke::default().with_color(grid_color).with_width(1.0),
+        );
+    }
+}
+
+fn draw_track(
+    frame: &mut Frame,
+    width: f32,
+    height: f32,
+    track: &FrequencyTrack,
+    palette: &Palette,
+) {
+    if track.points.len() >= 2 {
+        let mut path_builder = canvas::path::Builder::new();
+        for (i, &(x, frequency)) in track.points.iter().enumerate() {
+            let point = Point::new(x * width, height * (1.0 - track.level(frequency)));
+            if i == 0 {
+                path_builder.move_to(point);
+            } else {
+                path_builder.line_to(point);
+            }
+        }
+        frame.stroke(
+            &path_builder.build(),
+            Stroke::default()
+                .with_color(to_color(palette.trace))
+                .with_width(2.0),
+        );
+    }
+
+    for label in frequency_track_labels(track) {
+        let (horizontal_alignment, vertical_alignment) = match label.anchor {
+            LabelAnchor::BottomLeft => (Horizontal::Left, Vertical::Bottom),
+            LabelAnchor::BottomRight => (Horizontal::Right, Vertical::Bottom),
+            LabelAnchor::TopRight => (Horizontal::Right, Vertical::Top),
+            LabelAnchor::TopLeft => (Horizontal::Left, Vertical::Top),
+        };
+        frame.fill_text(Text {
+            content: label.text,
+            position: Point::new(label.position.0 * width, label.position.1 * height),
+            color: to_color(palette.text),
+            size: 11.0.into(),
+            horizontal_alignment,
+            vertical_alignment,
+            ..Default::default()
+        });
+    }
+}
@@ -1,22 +1,142 @@
 pub mod controls;
+pub mod designer;
+pub mod filters;
+pub mod frequency_track;
+pub mod phase;
+pub mod preferences;
 pub mod spectrum;
+pub mod timeline;
+pub mod touch;
+pub mod vectorscope;
+pub mod zoom;
 
 use iced::mouse;
-use iced::widget::canvas::{self, Cache, Canvas, Frame, Geometry, Path, Program, Stroke};
+use iced::widget::canvas::{self, event, Cache, Canvas, Event, Frame, Geometry, Program, Stroke};
 use iced::{Color, Point, Rectangle, Size, Theme};
 use std::collections::VecDeque;
-
-use ozeecubed_core::oscilloscope::WaveformData;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use controls::ControlMessage;
+pub use frequency_track::FrequencyTrackCanvas;
+use ozeecubed_core::decode::DecodedEvent;
+use ozeecubed_core::display::{
+    axis_labels, time_gate_label, time_gate_markers, trigger_level_marker, trigger_point_marker,
+    AxisLabel, Graticule, GridLineKind, IntensityMap, LabelAnchor, Palette, PersistenceColormap,
+    PersistenceDecay, PersistenceMode, Rgba, Segment, MAX_PERSISTENCE_FRAMES, MAX_TRACE_HISTORY,
+};
+use ozeecubed_core::oscilloscope::{
+    EnvelopeFollower, GateCursor, TimeGate, TriggerSettings, WaveformData, ZoomHandle, ZoomWindow,
+};
+pub use phase::PhaseCanvas;
 pub use spectrum::SpectrumCanvas;
+use touch::{Gesture, Touches};
+pub use vectorscope::VectorscopeCanvas;
+pub use zoom::ZoomCanvas;
+
+/// Convert a core palette color to an iced color
+pub(crate) fn to_color(rgba: Rgba) -> Color {
+    Color::from_rgba(rgba[0], rgba[1], rgba[2], rgba[3])
+}
+
+/// Trace points in screen coordinates, shared rather than copied
+pub type Points = Arc<[(f32, f32)]>;
+
+/// What the canvas draws of one capture. Built once per tick and shared
+/// with every redraw, so a redraw doesn't copy the capture.
+#[derive(Debug, Clone, Default)]
+pub struct WaveformFrame {
+    pub points: Points,
+    /// Envelope trace, when the follower is on
+    pub envelope: Option<Points>,
+    /// Whether `points` is drawn; false when the envelope replaces it
+    pub show_raw: bool,
+    /// Math channel trace, on its own vertical scale
+    pub math: Option<Points>,
+    /// Part of the capture on screen
+    pub display_range: Range<usize>,
+    pub samples_per_screen: usize,
+    /// See `WaveformData::trigger_position`
+    pub trigger_position: Option<f32>,
+}
+
+impl WaveformFrame {
+    /// `display_range` of the capture, as the trigger placed it, decimated
+    /// for a canvas `columns` pixels wide. Each trace is built in `scratch`,
+    /// kept by the caller from one capture to the next, and copied once
+    /// into the frame.
+    pub fn new(
+        waveform: &WaveformData,
+        display_range: Range<usize>,
+        trigger_position: Option<f32>,
+        follower: &EnvelopeFollower,
+        columns: usize,
+        scratch: &mut Vec<(f32, f32)>,
+    ) -> Self {
+        waveform.display_decimated_into(display_range.clone(), columns, scratch);
+        let points = scratch.as_slice().into();
+        let envelope = follower.is_enabled().then(|| {
+            waveform.display_follower_into(display_range.clone(), follower, columns, scratch);
+            scratch.as_slice().into()
+        });
+        Self {
+            points,
+            envelope,
+            show_raw: follower.shows_raw(),
+            math: None,
+            display_range,
+            samples_per_screen: waveform.calculate_samples_per_screen(),
+            trigger_position,
+        }
+    }
+
+    /// Add the math trace, `math` being evaluated alongside the whole
+    /// capture
+    pub fn with_math(
+        mut self,
+        waveform: &WaveformData,
+        math: &[f32],
+        units_per_division: f32,
+        columns: usize,
+        scratch: &mut Vec<(f32, f32)>,
+    ) -> Self {
+        let start = self.display_range.start.min(math.len());
+        let end = self.display_range.end.min(math.len());
+        waveform.display_channel_into(&math[start..end], columns, units_per_division, scratch);
+        self.math = Some(scratch.as_slice().into());
+        self
+    }
+}
 
-const GRID_GREEN: Color = Color::from_rgba(0.0, 1.0, 0.0, 0.3);
-const BACKGROUND: Color = Color::BLACK;
+/// Width assumed for decimation until the canvas has been drawn
+const DEFAULT_COLUMNS: usize = 1024;
 
 pub struct WaveformCanvas {
     cache: Cache,
-    history: VecDeque<Vec<(f32, f32)>>,
+    frame: Arc<WaveformFrame>,
+    /// Width of the canvas in pixels, as last drawn
+    columns: Arc<AtomicUsize>,
+    /// Recent traces with when they arrived, oldest first
+    history: VecDeque<(Instant, Points)>,
     persistence_enabled: bool,
     persistence_frames: usize,
+    persistence_mode: PersistenceMode,
+    persistence_decay: PersistenceDecay,
+    persistence_colormap: PersistenceColormap,
+    /// Shared with the frame being drawn; copied only if it's still in use
+    /// when the next capture arrives
+    intensity: Arc<IntensityMap>,
+    graticule: Graticule,
+    show_axis_labels: bool,
+    palette: Palette,
+    /// Cursors bounding the part of the capture the spectrum is taken
+    /// from, while the time gate is on
+    gate: Option<TimeGate>,
+    /// Region shown in the zoom pane, kept while the pane is hidden
+    zoom: ZoomWindow,
+    zooming: bool,
 }
 
 impl Default for WaveformCanvas {
@@ -26,44 +146,194 @@ impl Default for WaveformCanvas {
 }
 
 pub struct WaveformWithHistory {
-    pub waveform: WaveformData,
-    pub history: VecDeque<Vec<(f32, f32)>>,
+    pub frame: Arc<WaveformFrame>,
+    pub columns: Arc<AtomicUsize>,
+    pub time_per_division: f32,
+    pub volts_per_division: f32,
+    pub sample_rate: u32,
+    pub trigger_settings: TriggerSettings,
+    pub history: VecDeque<(Instant, Points)>,
     pub persistence_enabled: bool,
+    pub persistence_decay: PersistenceDecay,
+    pub persistence_colormap: PersistenceColormap,
+    pub persistence_mode: PersistenceMode,
+    /// Set when persistence is drawn from the map's cells: always when
+    /// graded, and when fading for longer than the trace history holds
+    pub intensity: Option<Arc<IntensityMap>>,
+    pub decoded: Arc<[DecodedEvent]>,
+    pub graticule: Graticule,
+    pub show_axis_labels: bool,
+    pub palette: Palette,
+    pub gate: Option<TimeGate>,
+    pub zoom: Option<ZoomWindow>,
 }
 
 impl WaveformCanvas {
     pub fn new() -> Self {
         WaveformCanvas {
             cache: Cache::default(),
+            frame: Arc::default(),
+            columns: Arc::new(AtomicUsize::new(DEFAULT_COLUMNS)),
             history: VecDeque::new(),
             persistence_enabled: true,
             persistence_frames: 10,
+            persistence_mode: PersistenceMode::default(),
+            persistence_decay: PersistenceDecay::default(),
+            persistence_colormap: PersistenceColormap::default(),
+            intensity: Arc::default(),
+            graticule: Graticule::default(),
+            show_axis_labels: true,
+            palette: Palette::default(),
+            gate: None,
+            zoom: ZoomWindow::default(),
+            zooming: false,
         }
     }
 
-    pub fn view<Message>(&self, waveform: WaveformData) -> Canvas<WaveformWithHistory, Message> {
+    pub fn view(
+        &self,
+        waveform: &WaveformData,
+        trigger_settings: &TriggerSettings,
+        decoded: Arc<[DecodedEvent]>,
+    ) -> Canvas<WaveformWithHistory, ControlMessage> {
         let data = WaveformWithHistory {
-            waveform,
+            frame: Arc::clone(&self.frame),
+            columns: Arc::clone(&self.columns),
+            time_per_division: waveform.time_per_division,
+            volts_per_division: waveform.volts_per_division,
+            sample_rate: waveform.sample_rate,
+            trigger_settings: trigger_settings.clone(),
             history: self.history.clone(),
             persistence_enabled: self.persistence_enabled,
+            persistence_decay: self.persistence_decay,
+            persistence_colormap: self.persistence_colormap,
+            persistence_mode: self.persistence_mode,
+            intensity: self.draws_from_map().then(|| Arc::clone(&self.intensity)),
+            decoded,
+            graticule: self.graticule.clone(),
+            show_axis_labels: self.show_axis_labels,
+            palette: self.palette,
+            gate: self.gate,
+            zoom: self.zoom(),
         };
         Canvas::new(data)
             .width(iced::Length::Fill)
             .height(iced::Length::Fill)
     }
 
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.cache.clear();
+    }
+
+    pub fn graticule(&self) -> &Graticule {
+        &self.graticule
+    }
+
+    pub fn set_graticule(&mut self, graticule: Graticule) {
+        Arc::make_mut(&mut self.intensity).set_vertical_divisions(graticule.vertical_divisions);
+        self.graticule = graticule;
+        self.cache.clear();
+    }
+
+    pub fn toggle_axis_labels(&mut self) {
+        self.show_axis_labels = !self.show_axis_labels;
+        self.cache.clear();
+    }
+
+    pub fn is_showing_axis_labels(&self) -> bool {
+        self.show_axis_labels
+    }
+
+    /// Show or hide the time gate cursors
+    pub fn toggle_time_gate(&mut self) {
+        self.gate = match self.gate {
+            Some(_) => None,
+            None => Some(TimeGate::default()),
+        };
+        self.cache.clear();
+    }
+
+    pub fn time_gate(&self) -> Option<TimeGate> {
+        self.gate
+    }
+
+    pub fn move_gate_cursor(&mut self, cursor: GateCursor, x: f32) {
+        if let Some(ref mut gate) = self.gate {
+            gate.set(cursor, x);
+            self.cache.clear();
+        }
+    }
+
+    /// Highlight the zoom region, and let it be dragged, while the zoom
+    /// pane is showing
+    pub fn show_zoom(&mut self, zooming: bool) {
+        self.zooming = zooming;
+        self.cache.clear();
+    }
+
+    pub fn zoom(&self) -> Option<ZoomWindow> {
+        self.zooming.then_some(self.zoom)
+    }
+
+    pub fn set_zoom(&mut self, zoom: ZoomWindow) {
+        self.zoom = zoom;
+        self.cache.clear();
+    }
+
     pub fn clear_cache(&mut self) {
         self.cache.clear();
     }
 
-    pub fn add_to_history(&mut self, points: Vec<(f32, f32)>) {
+    /// Width of the canvas in pixels, to decimate frames for
+    pub fn columns(&self) -> usize {
+        self.columns.load(Ordering::Relaxed)
+    }
+
+    /// Show a new capture, adding it to the persistence history
+    pub fn set_frame(&mut self, frame: WaveformFrame) {
+        if frame.show_raw {
+            self.add_to_history(Arc::clone(&frame.points));
+        }
+        self.frame = Arc::new(frame);
+    }
+
+    pub fn add_to_history(&mut self, points: impl Into<Points>) {
+        let points = points.into();
         if self.persistence_enabled && !points.is_empty() {
-            self.history.push_back(points);
+            let now = Instant::now();
+            let elapsed = self
+                .history
+                .back()
+                .map_or(Duration::ZERO, |&(time, _)| now.duration_since(time));
+            // Both representations are kept so switching modes is seamless
+            Arc::make_mut(&mut self.intensity).accumulate_after(
+                &points,
+                self.persistence_decay,
+                elapsed,
+            );
+            self.history.push_back((now, points));
+            self.trim_history(now);
+        }
+    }
 
-            // Keep only the configured number of frames
-            while self.history.len() > self.persistence_frames {
-                self.history.pop_front();
-            }
+    /// Keep the configured number of frames, or with a timed decay the
+    /// traces that haven't faded yet, up to the most the history holds.
+    /// Longer persistence is drawn from the intensity map.
+    fn trim_history(&mut self, now: Instant) {
+        let decay = self.persistence_decay;
+        let limit = if decay.is_timed() {
+            MAX_TRACE_HISTORY
+        } else {
+            self.persistence_frames.min(MAX_TRACE_HISTORY)
+        };
+        while self.history.len() > limit
+            || self
+                .history
+                .front()
+                .is_some_and(|&(time, _)| decay.has_faded(now.duration_since(time)))
+        {
+            self.history.pop_front();
         }
     }
 
@@ -71,15 +341,69 @@ impl WaveformCanvas {
         self.persistence_enabled = !self.persistence_enabled;
         if !self.persistence_enabled {
             self.history.clear();
+            Arc::make_mut(&mut self.intensity).clear();
         }
     }
 
+    /// Whether persistence is drawn from the intensity map's cells rather
+    /// than the trace history
+    fn draws_from_map(&self) -> bool {
+        self.persistence_enabled
+            && (self.persistence_mode == PersistenceMode::Graded
+                || self
+                    .persistence_decay
+                    .outlasts_history(self.persistence_frames))
+    }
+
+    /// Start the persisted traces over, e.g. to see what infinite
+    /// persistence catches from here on
+    pub fn clear_persistence(&mut self) {
+        self.history.clear();
+        Arc::make_mut(&mut self.intensity).clear();
+    }
+
+    pub fn cycle_persistence_mode(&mut self) {
+        self.persistence_mode = self.persistence_mode.next();
+    }
+
+    pub fn persistence_mode(&self) -> PersistenceMode {
+        self.persistence_mode
+    }
+
+    pub fn set_persistence_mode(&mut self, mode: PersistenceMode) {
+        self.persistence_mode = mode;
+    }
+
     pub fn set_persistence_frames(&mut self, frames: usize) {
-        self.persistence_frames = frames.clamp(1, 30);
+        self.persistence_frames = frames.clamp(1, MAX_PERSISTENCE_FRAMES);
+        Arc::make_mut(&mut self.intensity).set_decay_frames(self.persistence_frames);
         // Trim history if new limit is smaller
-        while self.history.len() > self.persistence_frames {
-            self.history.pop_front();
-        }
+        self.trim_history(Instant::now());
+    }
+
+    pub fn persistence_decay(&self) -> PersistenceDecay {
+        self.persistence_decay
+    }
+
+    pub fn set_persistence_decay(&mut self, decay: PersistenceDecay) {
+        self.persistence_decay = decay;
+        self.trim_history(Instant::now());
+    }
+
+    pub fn cycle_persistence_decay(&mut self) {
+        self.set_persistence_decay(self.persistence_decay.next());
+    }
+
+    pub fn persistence_colormap(&self) -> PersistenceColormap {
+        self.persistence_colormap
+    }
+
+    pub fn set_persistence_colormap(&mut self, colormap: PersistenceColormap) {
+        self.persistence_colormap = colormap;
+    }
+
+    pub fn cycle_persistence_colormap(&mut self) {
+        self.persistence_colormap = self.persistence_colormap.next();
     }
 
     pub fn is_persistence_enabled(&self) -> bool {
@@ -91,13 +415,173 @@ impl WaveformCanvas {
     }
 
     #[cfg(test)]
-    pub fn get_history(&self) -> &VecDeque<Vec<(f32, f32)>> {
+    pub fn get_history(&self) -> &VecDeque<(Instant, Points)> {
         &self.history
     }
 }
 
-impl<Message> Program<Message> for WaveformWithHistory {
-    type State = ();
+/// Pixels either side of a zoom region edge that pick the edge up
+const ZOOM_EDGE_GRAB: f32 = 6.0;
+
+/// Time/div a pinch stays within, matching the slider
+const PINCH_TIME_RANGE: (f32, f32) = (0.00001, 1.0);
+
+/// What a drag on the scope is moving
+#[derive(Debug, Clone, Copy)]
+pub enum ScopeDrag {
+    Gate(GateCursor),
+    Zoom(ZoomHandle),
+    /// The trigger level, by touch: the height the finger came down at and
+    /// the level then
+    Trigger {
+        from: f32,
+        level: f32,
+    },
+}
+
+/// The drag under way and the fingers on the scope
+#[derive(Debug, Default)]
+pub struct ScopeState {
+    drag: Option<ScopeDrag>,
+    touches: Touches,
+}
+
+impl WaveformWithHistory {
+    /// Pick up the nearer time gate cursor, or otherwise the zoom region or
+    /// one of its edges, moving it to `position`
+    fn grab(&self, position: Point, bounds: Rectangle) -> Option<(ScopeDrag, ControlMessage)> {
+        let x = position.x / bounds.width.max(1.0);
+        match (self.gate, self.zoom) {
+            (Some(gate), _) => {
+                let grabbed = gate.nearest(x);
+                Some((
+                    ScopeDrag::Gate(grabbed),
+                    ControlMessage::MoveGateCursor(grabbed, x),
+                ))
+            }
+            (None, Some(mut zoom)) => {
+                let handle = zoom.grab(x, ZOOM_EDGE_GRAB / bounds.width.max(1.0));
+                Some((ScopeDrag::Zoom(handle), ControlMessage::SetZoom(zoom)))
+            }
+            (None, None) => None,
+        }
+    }
+
+    /// The message moving what `drag` holds to `position`
+    fn drag_to(
+        &self,
+        drag: ScopeDrag,
+        position: Point,
+        bounds: Rectangle,
+    ) -> Option<ControlMessage> {
+        let x = position.x / bounds.width.max(1.0);
+        match drag {
+            ScopeDrag::Gate(cursor) => Some(ControlMessage::MoveGateCursor(cursor, x)),
+            ScopeDrag::Zoom(handle) => self.zoom.map(|mut zoom| {
+                zoom.drag(handle, x);
+                ControlMessage::SetZoom(zoom)
+            }),
+            ScopeDrag::Trigger { from, level } => {
+                let screen_volts =
+                    self.volts_per_division * self.graticule.vertical_divisions as f32;
+                let moved = (from - position.y) / bounds.height.max(1.0) * screen_volts;
+                Some(ControlMessage::SetTriggerLevel(level + moved))
+            }
+        }
+    }
+
+    /// A finger picks up what a mouse press would, or otherwise drags the
+    /// trigger level; two fingers pinch time/div
+    fn touch(
+        &self,
+        state: &mut ScopeState,
+        event: iced::touch::Event,
+        bounds: Rectangle,
+    ) -> (event::Status, Option<ControlMessage>) {
+        let Some(gesture) = state.touches.update(event, bounds) else {
+            return if state.touches.is_active() {
+                (event::Status::Captured, None)
+            } else {
+                (event::Status::Ignored, None)
+            };
+        };
+        let message = match gesture {
+            Gesture::Press(position) => match self.grab(position, bounds) {
+                Some((drag, message)) => {
+                    state.drag = Some(drag);
+                    Some(message)
+                }
+                None => {
+                    state.drag = Some(ScopeDrag::Trigger {
+                        from: position.y,
+                        level: self.trigger_settings.level,
+                    });
+                    None
+                }
+            },
+            Gesture::Drag(position) => state
+                .drag
+                .and_then(|drag| self.drag_to(drag, position, bounds)),
+            Gesture::Pinch(spread) => {
+                state.drag = None;
+                let time =
+                    (self.time_per_division / spread).clamp(PINCH_TIME_RANGE.0, PINCH_TIME_RANGE.1);
+                Some(ControlMessage::SetTimeScale(time))
+            }
+            Gesture::Release => {
+                state.drag = None;
+                None
+            }
+        };
+        (event::Status::Captured, message)
+    }
+}
+
+impl Program<ControlMessage> for WaveformWithHistory {
+    type State = ScopeState;
+
+    /// Pressing on the scope picks up the nearer time gate cursor, or
+    /// otherwise the zoom region or one of its edges, and dragging moves it.
+    /// Touch does the same, and also drags the trigger level and pinches
+    /// time/div.
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<ControlMessage>) {
+        match event {
+            Event::Touch(event) => self.touch(state, event, bounds),
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let Some((drag, message)) = cursor
+                    .position_in(bounds)
+                    .and_then(|position| self.grab(position, bounds))
+                else {
+                    return (event::Status::Ignored, None);
+                };
+                state.drag = Some(drag);
+                (event::Status::Captured, Some(message))
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. }) => {
+                match (state.drag, cursor.position()) {
+                    (Some(dragged), Some(position)) => {
+                        let position = Point::new(position.x - bounds.x, position.y - bounds.y);
+                        (
+                            event::Status::Captured,
+                            self.drag_to(dragged, position, bounds),
+                        )
+                    }
+                    _ => (event::Status::Ignored, None),
+                }
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                state.drag = None;
+                (event::Status::Ignored, None)
+            }
+            _ => (event::Status::Ignored, None),
+        }
+    }
 
     fn draw(
         &self,
@@ -108,97 +592,307 @@ impl<Message> Program<Message> for WaveformWithHistory {
         _cursor: mouse::Cursor,
     ) -> Vec<Geometry> {
         let mut frame = Frame::new(renderer, bounds.size());
+        self.columns
+            .store(bounds.width.ceil().max(1.0) as usize, Ordering::Relaxed);
 
         // Draw background
-        frame.fill_rectangle(Point::ORIGIN, bounds.size(), BACKGROUND);
+        frame.fill_rectangle(
+            Point::ORIGIN,
+            bounds.size(),
+            to_color(self.palette.background),
+        );
 
         // Draw grid
-        draw_grid(&mut frame, bounds.size());
-
-        // Draw historical waveforms with fading alpha
-        if self.persistence_enabled {
+        draw_grid(&mut frame, bounds.size(), &self.graticule, &self.palette);
+        if self.show_axis_labels {
+            draw_axis_labels(
+                &mut frame,
+                bounds.size(),
+                (self.time_per_division, self.volts_per_division),
+                &self.graticule,
+                &self.palette,
+            );
+        }
+        let divisions = self.graticule.vertical_divisions;
+
+        if !self.frame.show_raw {
+            // The envelope trace replaces the waveform and its history
+        } else if let Some(ref intensity) = self.intensity {
+            // Analog-style grading: brightness follows how often a path is traversed
+            draw_intensity(
+                &mut frame,
+                bounds.size(),
+                intensity,
+                self.persistence_mode,
+                self.persistence_colormap,
+                &self.palette,
+            );
+        } else if self.persistence_enabled {
+            // Draw historical waveforms with fading alpha
             let history_count = self.history.len();
-            for (i, points) in self.history.iter().enumerate() {
-                // Calculate alpha based on age (older = more transparent)
-                let age_factor = (i + 1) as f32 / (history_count + 1) as f32;
-                let alpha = age_factor * 0.6; // Max 60% opacity for history
-                draw_waveform_points(&mut frame, bounds.size(), points, alpha);
+            let now = Instant::now();
+            for (i, (time, points)) in self.history.iter().enumerate() {
+                // Calculate alpha based on age (older = more transparent),
+                // by the clock when the decay is timed
+                let age_factor = if self.persistence_decay.is_timed() {
+                    self.persistence_decay.alpha(now.duration_since(*time))
+                } else {
+                    (i + 1) as f32 / (history_count + 1) as f32
+                };
+                let color = self.persistence_colormap.faded(&self.palette, age_factor);
+                let color = Palette::faded(color, 0.6); // Max 60% opacity for history
+                draw_waveform_points(
+                    &mut frame,
+                    bounds.size(),
+                    points,
+                    divisions,
+                    to_color(color),
+                );
             }
         }
 
         // Draw current waveform (full brightness)
-        draw_waveform(&mut frame, bounds.size(), &self.waveform);
+        if self.frame.show_raw {
+            draw_waveform_points(
+                &mut frame,
+                bounds.size(),
+                &self.frame.points,
+                divisions,
+                to_color(self.palette.trace),
+            );
+        }
+        if let Some(ref envelope) = self.frame.envelope {
+            draw_waveform_points(
+                &mut frame,
+                bounds.size(),
+                envelope,
+                divisions,
+                to_color(self.palette.accent),
+            );
+        }
+        if let Some(ref math) = self.frame.math {
+            draw_waveform_points(
+                &mut frame,
+                bounds.size(),
+                math,
+                divisions,
+                to_color(self.palette.math),
+            );
+        }
+
+        // Trigger level, edge and instant, drawn over the trace so they stay visible
+        let mut markers = trigger_level_marker(
+            &self.trigger_settings,
+            self.volts_per_division,
+            self.graticule.vertical_divisions,
+        );
+        if let Some(x) = self.frame.trigger_position {
+            markers.extend(trigger_point_marker(x));
+        }
+        if let Some(zoom) = self.zoom {
+            let x = zoom.start * bounds.width;
+            let width = zoom.width() * bounds.width;
+            frame.fill_rectangle(
+                Point::new(x, 0.0),
+                Size::new(width, bounds.height),
+                to_color(Palette::faded(self.palette.accent, 0.15)),
+            );
+            for edge in [zoom.start, zoom.end] {
+                markers.push(((edge, 0.0), (edge, 1.0)));
+            }
+        }
+        if let Some(ref gate) = self.gate {
+            markers.extend(time_gate_markers(gate, 0.0));
+            let label = time_gate_label(gate, 0.0, self.frame.samples_per_screen, self.sample_rate);
+            draw_labels(&mut frame, bounds.size(), vec![label], &self.palette);
+        }
+        draw_segments(&mut frame, bounds.size(), &markers, &self.palette);
+
+        // Draw protocol decode overlay above the trace
+        draw_decoded(
+            &mut frame,
+            bounds.size(),
+            &self.frame,
+            &self.decoded,
+            &self.palette,
+        );
 
         vec![frame.into_geometry()]
     }
 }
 
-fn draw_grid(frame: &mut Frame, size: Size) {
-    let divisions_x = 10;
-    let divisions_y = 8;
-
-    let width = size.width;
-    let height = size.height;
+fn draw_grid(frame: &mut Frame, size: Size, graticule: &Graticule, palette: &Palette) {
+    // One path per line kind so the whole grid is three strokes
+    let mut major = canvas::path::Builder::new();
+    let mut center = canvas::path::Builder::new();
+    let mut ticks = canvas::path::Builder::new();
+
+    for line in graticule.lines() {
+        let builder = match line.kind {
+            GridLineKind::Major => &mut major,
+            GridLineKind::Center => &mut center,
+            GridLineKind::Tick => &mut ticks,
+        };
+        builder.move_to(Point::new(
+            line.from.0 * size.width,
+            line.from.1 * size.height,
+        ));
+        builder.line_to(Point::new(line.to.0 * size.width, line.to.1 * size.height));
+    }
 
-    // Draw vertical lines
-    for i in 0..=divisions_x {
-        let x = (i as f32 / divisions_x as f32) * width;
-        let path = Path::line(Point::new(x, 0.0), Point::new(x, height));
+    let grid_color = to_color(palette.grid);
+    let center_color = to_color(palette.grid_center);
+    for (builder, color, width) in [
+        (major, grid_color, 1.0),
+        (center, center_color, 2.0),
+        (ticks, center_color, 1.0),
+    ] {
         frame.stroke(
-            &path,
-            Stroke::default().with_color(GRID_GREEN).with_width(1.0),
+            &builder.build(),
+            Stroke::default().with_color(color).with_width(width),
         );
     }
+}
 
-    // Draw horizontal lines
-    for i in 0..=divisions_y {
-        let y = (i as f32 / divisions_y as f32) * height;
-        let path = Path::line(Point::new(0.0, y), Point::new(width, y));
-        frame.stroke(
-            &path,
-            Stroke::default().with_color(GRID_GREEN).with_width(1.0),
-        );
+fn draw_axis_labels(
+    frame: &mut Frame,
+    size: Size,
+    (time_per_division, volts_per_division): (f32, f32),
+    graticule: &Graticule,
+    palette: &Palette,
+) {
+    let labels = axis_labels(graticule, time_per_division, volts_per_division);
+    draw_labels(frame, size, labels, palette);
+}
+
+fn draw_labels(frame: &mut Frame, size: Size, labels: Vec<AxisLabel>, palette: &Palette) {
+    use iced::alignment::{Horizontal, Vertical};
+
+    for label in labels {
+        let (horizontal_alignment, vertical_alignment) = match label.anchor {
+            LabelAnchor::BottomLeft => (Horizontal::Left, Vertical::Bottom),
+            LabelAnchor::BottomRight => (Horizontal::Right, Vertical::Bottom),
+            LabelAnchor::TopRight => (Horizontal::Right, Vertical::Top),
+            LabelAnchor::TopLeft => (Horizontal::Left, Vertical::Top),
+        };
+        frame.fill_text(canvas::Text {
+            content: label.text,
+            position: Point::new(
+                label.position.0 * size.width,
+                label.position.1 * size.height,
+            ),
+            color: to_color(palette.text),
+            size: 11.0.into(),
+            horizontal_alignment,
+            vertical_alignment,
+            ..Default::default()
+        });
     }
+}
 
-    // Draw center lines brighter
-    let center_x = width / 2.0;
-    let center_y = height / 2.0;
+fn draw_segments(frame: &mut Frame, size: Size, segments: &[Segment], palette: &Palette) {
+    if segments.is_empty() {
+        return;
+    }
 
-    let path = Path::line(Point::new(center_x, 0.0), Point::new(center_x, height));
+    let mut builder = canvas::path::Builder::new();
+    for &((x0, y0), (x1, y1)) in segments {
+        builder.move_to(Point::new(x0 * size.width, y0 * size.height));
+        builder.line_to(Point::new(x1 * size.width, y1 * size.height));
+    }
     frame.stroke(
-        &path,
+        &builder.build(),
         Stroke::default()
-            .with_color(Color::from_rgba(0.0, 1.0, 0.0, 0.5))
-            .with_width(2.0),
+            .with_color(to_color(palette.accent))
+            .with_width(1.5),
     );
+}
 
-    let path = Path::line(Point::new(0.0, center_y), Point::new(width, center_y));
-    frame.stroke(
-        &path,
-        Stroke::default()
-            .with_color(Color::from_rgba(0.0, 1.0, 0.0, 0.5))
-            .with_width(2.0),
+fn draw_intensity(
+    frame: &mut Frame,
+    size: Size,
+    intensity: &IntensityMap,
+    mode: PersistenceMode,
+    colormap: PersistenceColormap,
+    palette: &Palette,
+) {
+    let cell = Size::new(
+        size.width / intensity.columns() as f32,
+        size.height / intensity.rows() as f32,
     );
+
+    for (column, row, value) in intensity.cells(mode) {
+        let position = Point::new(column as f32 * cell.width, row as f32 * cell.height);
+        frame.fill_rectangle(
+            position,
+            cell,
+            to_color(colormap.cell(palette, mode, value)),
+        );
+    }
 }
 
-fn draw_waveform(frame: &mut Frame, size: Size, waveform: &WaveformData) {
-    if waveform.samples.is_empty() {
+fn draw_decoded(
+    frame: &mut Frame,
+    size: Size,
+    waveform: &WaveformFrame,
+    events: &[DecodedEvent],
+    palette: &Palette,
+) {
+    if events.is_empty() {
         return;
     }
 
-    // Get display samples (normalized)
-    let trigger_settings = ozeecubed_core::oscilloscope::TriggerSettings::default();
-    let points = waveform.get_display_samples(&trigger_settings);
+    // Same window as the drawn trace
+    let range = waveform.display_range.clone();
+    let samples_per_screen = waveform.samples_per_screen as f32;
+    let to_x = |index: usize| {
+        let offset = index as f32 - range.start as f32;
+        (offset / samples_per_screen).clamp(0.0, 1.0) * size.width
+    };
+
+    let y = 24.0;
+    for event in events
+        .iter()
+        .filter(|e| e.end > range.start && e.start < range.end)
+    {
+        let color = to_color(if event.error {
+            palette.error
+        } else {
+            palette.accent
+        });
+        let x_start = to_x(event.start);
+        let x_end = to_x(event.end);
+
+        // Bracket spanning the symbol
+        let mut bracket = canvas::path::Builder::new();
+        bracket.move_to(Point::new(x_start, y + 6.0));
+        bracket.line_to(Point::new(x_start, y));
+        bracket.line_to(Point::new(x_end, y));
+        bracket.line_to(Point::new(x_end, y + 6.0));
+        frame.stroke(
+            &bracket.build(),
+            Stroke::default().with_color(color).with_width(1.0),
+        );
 
-    if points.is_empty() {
-        return;
+        frame.fill_text(canvas::Text {
+            content: event.label.clone(),
+            position: Point::new((x_start + x_end) / 2.0, y - 4.0),
+            color,
+            size: 12.0.into(),
+            horizontal_alignment: iced::alignment::Horizontal::Center,
+            vertical_alignment: iced::alignment::Vertical::Bottom,
+            ..Default::default()
+        });
     }
-
-    // Draw with full opacity
-    draw_waveform_points(frame, size, &points, 1.0);
 }
 
-fn draw_waveform_points(frame: &mut Frame, size: Size, points: &[(f32, f32)], alpha: f32) {
+fn draw_waveform_points(
+    frame: &mut Frame,
+    size: Size,
+    points: &[(f32, f32)],
+    vertical_divisions: usize,
+    color: Color,
+) {
     if points.is_empty() {
         return;
     }
@@ -206,13 +900,14 @@ fn draw_waveform_points(frame: &mut Frame, size: Size, points: &[(f32, f32)], al
     let width = size.width;
     let height = size.height;
     let center_y = height / 2.0;
+    let division_height = height / vertical_divisions.max(1) as f32;
 
     let mut path_builder = canvas::path::Builder::new();
 
     // Convert normalized coordinates to screen coordinates
     for (i, &(x_norm, y_norm)) in points.iter().enumerate() {
         let x = x_norm * width;
-        let y = center_y - (y_norm * height / 8.0); // 8 vertical divisions
+        let y = center_y - y_norm * division_height;
 
         if i == 0 {
             path_builder.move_to(Point::new(x, y));
@@ -222,13 +917,13 @@ fn draw_waveform_points(frame: &mut Frame, size: Size, points: &[(f32, f32)], al
     }
 
     let path = path_builder.build();
-    let color = Color::from_rgba(0.0, 1.0, 0.0, alpha);
     frame.stroke(&path, Stroke::default().with_color(color).with_width(2.0));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ozeecubed_core::oscilloscope::trigger::TriggerEdge;
 
     #[test]
     fn test_canvas_new() {
@@ -309,6 +1004,64 @@ mod tests {
         assert_eq!(canvas.get_history().len(), 10);
     }
 
+    #[test]
+    fn test_frame_follows_the_trigger_edge() {
+        let mut waveform = WaveformData::new(48000);
+        waveform.update_samples(
+            (0..4800)
+                .map(|i| (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0).sin())
+                .collect(),
+        );
+        let falling = TriggerSettings {
+            edge: TriggerEdge::Falling,
+            ..TriggerSettings::default()
+        };
+        let mut canvas = WaveformCanvas::new();
+        canvas.set_frame(WaveformFrame::new(
+            &waveform,
+            waveform.display_range(&falling),
+            waveform.trigger_position(&falling),
+            &EnvelopeFollower::default(),
+            DEFAULT_COLUMNS,
+            &mut Vec::new(),
+        ));
+
+        // The history keeps the trace as drawn, starting down through zero
+        let (_, trace) = canvas.get_history().back().unwrap();
+        assert!(trace[0].1.abs() < 0.3);
+        assert!(trace[1].1 < trace[0].1);
+        assert_eq!(canvas.frame.trigger_position, Some(0.0));
+    }
+
+    #[test]
+    fn test_intensity_map_follows_history() {
+        let mut canvas = WaveformCanvas::new();
+        assert_eq!(canvas.persistence_mode(), PersistenceMode::Graded);
+
+        canvas.add_to_history(vec![(0.0, 0.5), (1.0, 0.5)]);
+        assert!(canvas.intensity.lit_cells().count() > 0);
+
+        canvas.cycle_persistence_mode();
+        assert_eq!(canvas.persistence_mode(), PersistenceMode::Fade);
+
+        canvas.toggle_persistence(); // Turn off
+        assert_eq!(canvas.intensity.lit_cells().count(), 0);
+    }
+
+    #[test]
+    fn test_timed_decay_keeps_more_frames() {
+        let mut canvas = WaveformCanvas::new();
+        canvas.set_persistence_decay(PersistenceDecay::TwoSeconds);
+        for _ in 0..40 {
+            canvas.add_to_history(vec![(0.0, 0.5), (1.0, 0.5)]);
+        }
+        // Nothing has had time to fade, so only the cap applies
+        assert_eq!(canvas.get_history().len(), MAX_TRACE_HISTORY);
+
+        canvas.set_persistence_decay(PersistenceDecay::Frames);
+        assert_eq!(canvas.get_history().len(), 10);
+    }
+
     #[test]
     fn test_set_persistence_frames() {
         let mut canvas = WaveformCanvas::new();
@@ -329,8 +1082,28 @@ mod tests {
         assert_eq!(canvas.get_persistence_frames(), 1);
 
         // Test maximum
-        canvas.set_persistence_frames(100);
-        assert_eq!(canvas.get_persistence_frames(), 30);
+        canvas.set_persistence_frames(1000);
+        assert_eq!(canvas.get_persistence_frames(), MAX_PERSISTENCE_FRAMES);
+    }
+
+    #[test]
+    fn test_long_persistence_draws_from_the_map() {
+        let mut canvas = WaveformCanvas::new();
+        canvas.set_persistence_mode(PersistenceMode::Fade);
+        canvas.set_persistence_frames(MAX_PERSISTENCE_FRAMES);
+        for _ in 0..MAX_TRACE_HISTORY + 10 {
+            canvas.add_to_history(vec![(0.0, 0.5), (1.0, 0.5)]);
+        }
+        // Only the newest traces are kept as points
+        assert_eq!(canvas.get_history().len(), MAX_TRACE_HISTORY);
+        assert!(canvas.draws_from_map());
+
+        canvas.set_persistence_frames(10);
+        assert!(!canvas.draws_from_map());
+
+        canvas.clear_persistence();
+        assert!(canvas.get_history().is_empty());
+        assert_eq!(canvas.intensity.cells(PersistenceMode::Fade).count(), 0);
     }
 
     #[test]
@@ -369,8 +1142,8 @@ mod tests {
 
         // Check that the oldest (points1) was removed
         let history: Vec<_> = canvas.get_history().iter().collect();
-        assert_eq!(history[0], &points2);
-        assert_eq!(history[1], &points3);
-        assert_eq!(history[2], &points4);
+        assert_eq!(history[0].1[..], points2[..]);
+        assert_eq!(history[1].1[..], points3[..]);
+        assert_eq!(history[2].1[..], points4[..]);
     }
 }
@@ -0,0 +1,175 @@
+use iced::alignment::{Horizontal, Vertical};
+use iced::mouse;
+use iced::widget::canvas::{self, Cache, Frame, Geometry, Path, Stroke, Text};
+use iced::{Point, Rectangle, Renderer, Size, Theme};
+
+use super::to_color;
+use ozeecubed_core::display::{phase_labels, LabelAnchor, Palette};
+use ozeecubed_core::oscilloscope::phase::frequency_position;
+use ozeecubed_core::oscilloscope::{PhaseAnalyzer, PhaseResponse};
+
+/// Phase grid lines, every 90°
+const PHASE_DIVISIONS: usize = 4;
+
+/// Frequencies of the vertical grid lines, in Hz
+const FREQUENCY_LINES: [f32; 3] = [100.0, 1000.0, 10000.0];
+
+/// Phase between the left and right inputs against frequency, in place of
+/// the spectrum
+pub struct PhaseCanvas {
+    cache: Cache,
+    analyzer: PhaseAnalyzer,
+    response: Option<PhaseResponse>,
+    palette: Palette,
+}
+
+impl PhaseCanvas {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            cache: Cache::new(),
+            analyzer: PhaseAnalyzer::new(sample_rate),
+            response: None,
+            palette: Palette::default(),
+        }
+    }
+
+    /// Add the (left, right) pairs read since the last update, starting
+    /// over when the sample rate changes
+    pub fn update(&mut self, pairs: &[(f32, f32)], sample_rate: u32) {
+        if sample_rate != self.analyzer.sample_rate() {
+            self.analyzer = PhaseAnalyzer::new(sample_rate);
+            self.response = None;
+            self.cache.clear();
+        }
+        if self.analyzer.push(pairs) {
+            self.response = self.analyzer.response();
+            self.cache.clear();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.analyzer.clear();
+        self.response = None;
+        self.cache.clear();
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.cache.clear();
+    }
+
+    pub fn view<'a>(&'a self) -> iced::Element<'a, ()> {
+        iced::widget::canvas(self as &'a Self)
+            .width(iced::Length::Fill)
+            .height(iced::Length::Fill)
+            .into()
+    }
+}
+
+impl canvas::Program<()> for PhaseCanvas {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let geometry = self.cache.draw(renderer, bounds.size(), |frame| {
+            let width = frame.width();
+            let height = frame.height();
+
+            frame.fill_rectangle(
+                Point::ORIGIN,
+                Size::new(width, height),
+                to_color(self.palette.background),
+            );
+
+            draw_phase_grid(
+                frame,
+                width,
+                height,
+                self.analyzer.sample_rate(),
+                &self.palette,
+            );
+
+            if let Some(ref response) = self.response {
+                draw_phase(frame, width, height, response, &self.palette);
+            }
+        });
+
+        vec![geometry]
+    }
+}
+
+fn draw_phase_grid(
+    frame: &mut Frame,
+    width: f32,
+    height: f32,
+    sample_rate: u32,
+    palette: &Palette,
+) {
+    // Drawn as faint as the spectrum grid
+    let grid_color = to_color(Palette::faded(palette.grid, 0.5));
+
+    for hertz in FREQUENCY_LINES {
+        let x = frequency_position(hertz, sample_rate) * width;
+        let line = Path::line(Point::new(x, 0.0), Point::new(x, height));
+        frame.stroke(
+            &line,
+            Stroke::default().with_color(grid_color).with_width(1.0),
+        );
+    }
+
+    for i in 0..=PHASE_DIVISIONS {
+        let y = (i as f32 / PHASE_DIVISIONS as f32) * height;
+        let line = Path::line(Point::new(0.0, y), Point::new(width, y));
+        frame.stroke(
+            &line,
+            Stroke::default().with_color(grid_color).with_width(1.0),
+        );
+    }
+}
+
+fn draw_phase(
+    frame: &mut Frame,
+    width: f32,
+    height: f32,
+    response: &PhaseResponse,
+    palette: &Palette,
+) {
+    let mut path_builder = canvas::path::Builder::new();
+    for trace in response.traces() {
+        let to_point = |(x, level): (f32, f32)| Point::new(x * width, height * (1.0 - level));
+        path_builder.move_to(to_point(trace[0]));
+        for &point in &trace[1..] {
+            path_builder.line_to(to_point(point));
+        }
+    }
+    frame.stroke(
+        &path_builder.build(),
+        Stroke::default()
+            .with_color(to_color(palette.trace))
+            .with_width(2.0),
+    );
+
+    for label in phase_labels(response) {
+        let (horizontal_alignment, vertical_alignment) = match label.anchor {
+            LabelAnchor::BottomLeft => (Horizontal::Left, Vertical::Bottom),
+            LabelAnchor::BottomRight => (Horizontal::Right, Vertical::Bottom),
+            LabelAnchor::TopRight => (Horizontal::Right, Vertical::Top),
+            LabelAnchor::TopLeft => (Horizontal::Left, Vertical::Top),
+        };
+        frame.fill_text(Text {
+            content: label.text,
+            position: Point::new(label.position.0 * width, label.position.1 * height),
+            color: to_color(palette.text),
+            size: 11.0.into(),
+            horizontal_alignment,
+            vertical_alignment,
+            ..Default::default()
+        });
+    }
+}
@@ -0,0 +1,598 @@
+use std::fmt;
+
+use iced::widget::{
+    button, center, column, container, mouse_area, opaque, pick_list, row, scrollable, slider,
+    stack, text, text_input,
+};
+use iced::{Alignment, Color, Element, Length};
+use ozeecubed_core::audio::{input_configs, input_device_names, InputConfig};
+use ozeecubed_core::config::Preferences;
+use ozeecubed_core::display::{
+    format_time, next_trace_color, trace_color_label, MAX_PERSISTENCE_FRAMES,
+};
+use ozeecubed_core::keymap::{action_for_key, key_for, Action, Key, KeyAction, KEY_ACTIONS};
+use ozeecubed_core::source::{NetworkSource, SerialConfig};
+
+use super::controls::ControlMessage;
+
+/// Device picker entry for whatever the system's default input is
+const DEFAULT_DEVICE: &str = "System default";
+
+/// Capture rates offered besides the device's own default
+const SAMPLE_RATES: [u32; 5] = [44100, 48000, 88200, 96000, 192000];
+
+/// Channel counts offered besides the device's own default
+const CHANNEL_COUNTS: [u16; 2] = [1, 2];
+
+/// Buffer sizes offered, in frames, besides the host's choice. The small
+/// ones cut latency on a system that keeps up.
+const BUFFER_SIZES: [u32; 6] = [64, 128, 256, 512, 1024, 2048];
+
+/// The control for `action`, a fine step if `fine` and it has one
+pub fn control_for(action: Action, fine: bool) -> ControlMessage {
+    let fine = fine && action.has_fine_step();
+    match action {
+        Action::IncreaseTimeScale if fine => ControlMessage::FineTimeScale(true),
+        Action::DecreaseTimeScale if fine => ControlMessage::FineTimeScale(false),
+        Action::IncreaseVoltageScale if fine => ControlMessage::FineVoltageScale(true),
+        Action::DecreaseVoltageScale if fine => ControlMessage::FineVoltageScale(false),
+        Action::IncreaseTriggerLevel if fine => ControlMessage::FineTriggerLevel(true),
+        Action::DecreaseTriggerLevel if fine => ControlMessage::FineTriggerLevel(false),
+        Action::IncreaseTimeScale => ControlMessage::IncreaseTimeScale,
+        Action::DecreaseTimeScale => ControlMessage::DecreaseTimeScale,
+        Action::IncreaseVoltageScale => ControlMessage::IncreaseVoltageScale,
+        Action::DecreaseVoltageScale => ControlMessage::DecreaseVoltageScale,
+        Action::IncreaseTriggerLevel => ControlMessage::IncreaseTriggerLevel,
+        Action::DecreaseTriggerLevel => ControlMessage::DecreaseTriggerLevel,
+        Action::OpenPreferences => ControlMessage::OpenPreferences,
+        Action::CycleDecimation => ControlMessage::CycleDecimation,
+        Action::CyclePlot => ControlMessage::CyclePlot,
+        Action::CycleLayout => ControlMessage::CycleLayout,
+        Action::ToggleTrigger => ControlMessage::ToggleTrigger,
+        Action::ToggleTriggerEdge => ControlMessage::ToggleTriggerEdge,
+        Action::TogglePersistence => ControlMessage::TogglePersistence,
+        Action::CyclePersistenceMode => ControlMessage::CyclePersistenceMode,
+        Action::IncreasePersistence => ControlMessage::IncreasePersistence,
+        Action::DecreasePersistence => ControlMessage::DecreasePersistence,
+        Action::ToggleLogging => ControlMessage::ToggleLogging,
+        Action::CycleTheme => ControlMessage::CycleTheme,
+        Action::ToggleAxisLabels => ControlMessage::ToggleAxisLabels,
+        Action::ToggleHarmonics => ControlMessage::ToggleHarmonics,
+        Action::CycleSpectrumScale => ControlMessage::CycleSpectrumScale,
+        Action::ToggleBandPower => ControlMessage::ToggleBandPower,
+        Action::ToggleTimeGate => ControlMessage::ToggleTimeGate,
+    }
+}
+
+/// Whether an input is a network or serial one, opened from its URL rather
+/// than picked from the devices
+pub fn is_input_url(device: &str) -> bool {
+    NetworkSource::is_url(device) || SerialConfig::is_serial(device)
+}
+
+/// What `device` supports, for a device rather than a URL; none if it
+/// can't be asked
+fn device_configs(device: Option<&str>) -> Vec<InputConfig> {
+    match device {
+        Some(device) if is_input_url(device) => Vec::new(),
+        device => input_configs(device).unwrap_or_default(),
+    }
+}
+
+/// The shortcut on `key`, through the keymap both desktop apps share
+pub fn control_for_key(preferences: &Preferences, key: Key, fine: bool) -> Option<ControlMessage> {
+    action_for_key(preferences, key).map(|action| control_for(action, fine))
+}
+
+/// Sample rate picker entry, `None` for the device's default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SampleRate(Option<u32>);
+
+impl fmt::Display for SampleRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(rate) => write!(f, "{rate} Hz"),
+            None => write!(f, "Device default"),
+        }
+    }
+}
+
+/// Channel count picker entry, `None` for the device's default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Channels(Option<u16>);
+
+impl fmt::Display for Channels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(1) => write!(f, "Mono"),
+            Some(2) => write!(f, "Stereo"),
+            Some(channels) => write!(f, "{channels} channels"),
+            None => write!(f, "Default channels"),
+        }
+    }
+}
+
+/// Buffer size picker entry, `None` for the host's choice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferSize(Option<u32>);
+
+impl fmt::Display for BufferSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(frames) => write!(f, "{frames} frame buffer"),
+            None => write!(f, "Default buffer"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum PreferenceMessage {
+    SelectDevice(String),
+    SelectSampleRate(SampleRate),
+    SelectChannels(Channels),
+    SelectBufferSize(BufferSize),
+    /// Read a network or serial input at this URL instead of a device
+    SetInputUrl(String),
+    CycleTheme,
+    CycleRefreshRate,
+    CycleTraceColor,
+    CycleMathColor,
+    SetInputLabel(String),
+    SetMathLabel(String),
+    SetTimeScale(f32),
+    TogglePersistence,
+    CyclePersistenceMode,
+    CyclePersistenceDecay,
+    CyclePersistenceColormap,
+    SetPersistenceFrames(u16),
+    /// Wait for the next key press to move this action to
+    StartBinding(&'static str),
+    ResetKeys,
+    Save,
+    Cancel,
+}
+
+/// Preferences being edited, applied only when saved
+pub struct PreferencesDialog {
+    draft: Preferences,
+    /// Inputs found when the dialog opened
+    devices: Vec<String>,
+    /// What the selected device supports, when it's a device
+    configs: Vec<InputConfig>,
+    /// URL typed for a network or serial input, used once it's a whole one
+    input_url: String,
+    /// Action waiting for its new key
+    binding: Option<&'static str>,
+}
+
+impl PreferencesDialog {
+    pub fn new(preferences: &Preferences) -> Self {
+        Self {
+            draft: preferences.clone(),
+            devices: input_device_names(),
+            configs: device_configs(preferences.input_device.as_deref()),
+            input_url: preferences
+                .input_device
+                .clone()
+                .filter(|device| is_input_url(device))
+                .unwrap_or_default(),
+            binding: None,
+        }
+    }
+
+    pub fn preferences(&self) -> &Preferences {
+        &self.draft
+    }
+
+    /// Every message but `Save` and `Cancel`, which the app handles
+    pub fn update(&mut self, message: PreferenceMessage) {
+        match message {
+            PreferenceMessage::SelectDevice(device) => {
+                self.draft.input_device = (device != DEFAULT_DEVICE).then_some(device);
+                self.configs = device_configs(self.draft.input_device.as_deref());
+                self.input_url.clear();
+            }
+            PreferenceMessage::SetInputUrl(url) => {
+                if is_input_url(&url) {
+                    self.draft.input_device = Some(url.clone());
+                    self.configs.clear();
+                } else if self.draft.input_device.as_deref().is_some_and(is_input_url) {
+                    self.draft.input_device = None;
+                    self.configs = device_configs(None);
+                }
+                self.input_url = url;
+            }
+            PreferenceMessage::SelectSampleRate(SampleRate(rate)) => {
+                self.draft.sample_rate = rate;
+            }
+            PreferenceMessage::SelectChannels(Channels(channels)) => {
+                self.draft.input_channels = channels;
+            }
+            PreferenceMessage::SelectBufferSize(BufferSize(frames)) => {
+                self.draft.buffer_size = frames;
+            }
+            PreferenceMessage::CycleTheme => {
+                self.draft.theme = self.draft.theme.next();
+            }
+            PreferenceMessage::CycleRefreshRate => {
+                self.draft.refresh_rate = self.draft.refresh_rate.next();
+            }
+            PreferenceMessage::CycleTraceColor => {
+                let colors = &mut self.draft.trace_colors;
+                colors.trace = next_trace_color(colors.trace);
+            }
+            PreferenceMessage::CycleMathColor => {
+                let colors = &mut self.draft.trace_colors;
+                colors.math = next_trace_color(colors.math);
+            }
+            PreferenceMessage::SetInputLabel(label) => {
+                self.draft.input_label = non_empty(label);
+            }
+            PreferenceMessage::SetMathLabel(label) => {
+                self.draft.math_label = non_empty(label);
+            }
+            PreferenceMessage::SetTimeScale(seconds) => {
+                self.draft.time_per_division = seconds;
+            }
+            PreferenceMessage::TogglePersistence => {
+                self.draft.persistence_enabled = !self.draft.persistence_enabled;
+            }
+            PreferenceMessage::CyclePersistenceMode => {
+                self.draft.persistence_mode = self.draft.persistence_mode.next();
+            }
+            PreferenceMessage::CyclePersistenceDecay => {
+                self.draft.persistence_decay = self.draft.persistence_decay.next();
+            }
+            PreferenceMessage::CyclePersistenceColormap => {
+                self.draft.persistence_colormap = self.draft.persistence_colormap.next();
+            }
+            PreferenceMessage::SetPersistenceFrames(frames) => {
+                self.draft.persistence_frames = frames as usize;
+            }
+            PreferenceMessage::StartBinding(name) => {
+                self.binding = Some(name);
+            }
+            PreferenceMessage::ResetKeys => {
+                for action in &KEY_ACTIONS {
+                    self.draft.bind_key(action.name, None);
+                }
+                self.binding = None;
+            }
+            PreferenceMessage::Save | PreferenceMessage::Cancel => {}
+        }
+    }
+
+    /// Whether a key press should go to `bind` rather than the shortcuts
+    pub fn is_binding(&self) -> bool {
+        self.binding.is_some()
+    }
+
+    pub fn cancel_binding(&mut self) {
+        self.binding = None;
+    }
+
+    /// Move the action waiting for a key to `key`. An action already on
+    /// `key` swaps onto the key being vacated, so no two share one.
+    pub fn bind(&mut self, key: &str) {
+        let mut chars = key.chars();
+        let key = match (chars.next(), chars.next()) {
+            (Some(key), None) if key.is_ascii_alphanumeric() => key.to_ascii_lowercase(),
+            // Letters and digits only, clear of the fixed shortcuts
+            _ => return,
+        };
+        let Some(action) = self
+            .binding
+            .take()
+            .and_then(|name| KEY_ACTIONS.iter().find(|action| action.name == name))
+        else {
+            return;
+        };
+
+        let vacated = key_for(&self.draft, action);
+        if let Some(other) = KEY_ACTIONS
+            .iter()
+            .find(|other| other.name != action.name && key_for(&self.draft, other) == key)
+        {
+            self.set_key(other, vacated);
+        }
+        self.set_key(action, key);
+    }
+
+    /// Only keys away from the default are saved
+    fn set_key(&mut self, action: &KeyAction, key: char) {
+        self.draft
+            .bind_key(action.name, (key != action.default_key).then_some(key));
+    }
+
+    pub fn view(&self) -> Element<'_, PreferenceMessage> {
+        let draft = &self.draft;
+
+        let devices: Vec<String> = std::iter::once(DEFAULT_DEVICE.to_string())
+            .chain(self.devices.iter().cloned())
+            .collect();
+        let device = draft
+            .input_device
+            .clone()
+            .unwrap_or_else(|| DEFAULT_DEVICE.to_string());
+        let rates: Vec<SampleRate> = std::iter::once(SampleRate(None))
+            .chain(SAMPLE_RATES.iter().map(|&rate| SampleRate(Some(rate))))
+            .collect();
+
+        let channels: Vec<Channels> = std::iter::once(Channels(None))
+            .chain(CHANNEL_COUNTS.iter().map(|&count| Channels(Some(count))))
+            .collect();
+        let buffer_sizes: Vec<BufferSize> = std::iter::once(BufferSize(None))
+            .chain(BUFFER_SIZES.iter().map(|&frames| BufferSize(Some(frames))))
+            .collect();
+
+        let mut input = column![
+            text("Input").size(14),
+            pick_list(devices, Some(device), PreferenceMessage::SelectDevice)
+                .width(Length::Fixed(260.0)),
+            text_input("udp://0.0.0.0:7355 or serial:/dev/ttyUSB0", &self.input_url)
+                .on_input(PreferenceMessage::SetInputUrl)
+                .width(Length::Fixed(260.0)),
+            pick_list(
+                rates,
+                Some(SampleRate(draft.sample_rate)),
+                PreferenceMessage::SelectSampleRate
+            )
+            .width(Length::Fixed(260.0)),
+            row![
+                pick_list(
+                    channels,
+                    Some(Channels(draft.input_channels)),
+                    PreferenceMessage::SelectChannels
+                )
+                .width(Length::Fixed(125.0)),
+                pick_list(
+                    buffer_sizes,
+                    Some(BufferSize(draft.buffer_size)),
+                    PreferenceMessage::SelectBufferSize
+                )
+                .width(Length::Fixed(130.0)),
+            ]
+            .spacing(5),
+        ]
+        .spacing(5);
+        if !self.configs.is_empty() {
+            input = input.push(text("Supported").size(11));
+            for config in &self.configs {
+                input = input.push(text(config.to_string()).size(11));
+            }
+        }
+
+        // Logarithmic, like the time/div control
+        let startup = column![
+            text("Display").size(14),
+            row![
+                text("Theme").width(Length::Fixed(120.0)),
+                button(draft.theme.label()).on_press(PreferenceMessage::CycleTheme),
+            ]
+            .align_y(Alignment::Center),
+            row![
+                text("Refresh rate").width(Length::Fixed(120.0)),
+                button(draft.refresh_rate.label()).on_press(PreferenceMessage::CycleRefreshRate),
+            ]
+            .align_y(Alignment::Center),
+            row![
+                text("Trace color").width(Length::Fixed(120.0)),
+                button(text(trace_color_label(draft.trace_colors.trace)))
+                    .on_press(PreferenceMessage::CycleTraceColor),
+            ]
+            .align_y(Alignment::Center),
+            row![
+                text("Math color").width(Length::Fixed(120.0)),
+                button(text(trace_color_label(draft.trace_colors.math)))
+                    .on_press(PreferenceMessage::CycleMathColor),
+            ]
+            .align_y(Alignment::Center),
+            row![
+                text("Input label").width(Length::Fixed(120.0)),
+                text_input("Input", draft.input_label.as_deref().unwrap_or(""))
+                    .on_input(PreferenceMessage::SetInputLabel)
+                    .width(Length::Fixed(150.0)),
+            ]
+            .align_y(Alignment::Center),
+            row![
+                text("Math label").width(Length::Fixed(120.0)),
+                text_input("Math", draft.math_label.as_deref().unwrap_or(""))
+                    .on_input(PreferenceMessage::SetMathLabel)
+                    .width(Length::Fixed(150.0)),
+            ]
+            .align_y(Alignment::Center),
+            row![
+                text(format!("Time/div {}", format_time(draft.time_per_division)))
+                    .width(Length::Fixed(120.0)),
+                slider(-5.0..=0.0, draft.time_per_division.log10(), |val| {
+                    PreferenceMessage::SetTimeScale(10_f32.powf(val))
+                })
+                .step(0.01)
+                .width(Length::Fixed(150.0)),
+            ]
+            .align_y(Alignment::Center),
+            row![
+                text("Persistence").width(Length::Fixed(120.0)),
+                button(if draft.persistence_enabled {
+                    "ON"
+                } else {
+                    "OFF"
+                })
+                .on_press(PreferenceMessage::TogglePersistence),
+                button(draft.persistence_mode.label())
+                    .on_press(PreferenceMessage::CyclePersistenceMode),
+                button(draft.persistence_decay.label())
+                    .on_press(PreferenceMessage::CyclePersistenceDecay),
+                button(draft.persistence_colormap.label())
+                    .on_press(PreferenceMessage::CyclePersistenceColormap),
+            ]
+            .spacing(5)
+            .align_y(Alignment::Center),
+            row![
+                text(format!("{} frames", draft.persistence_frames)).width(Length::Fixed(120.0)),
+                slider(
+                    1.0..=MAX_PERSISTENCE_FRAMES as f32,
+                    draft.persistence_frames as f32,
+                    |val| PreferenceMessage::SetPersistenceFrames(val as u16)
+                )
+                .step(1.0)
+                .width(Length::Fixed(150.0)),
+            ]
+            .align_y(Alignment::Center),
+        ]
+        .spacing(5);
+
+        let keys = KEY_ACTIONS
+            .iter()
+            .fold(column![].spacing(3), |keys, action| {
+                let key = if self.binding == Some(action.name) {
+                    "Press a key".to_string()
+                } else {
+                    key_for(draft, action).to_ascii_uppercase().to_string()
+                };
+                keys.push(
+                    row![
+                        text(action.label).size(12).width(Length::Fixed(150.0)),
+                        button(text(key).size(12))
+                            .on_press(PreferenceMessage::StartBinding(action.name)),
+                    ]
+                    .align_y(Alignment::Center),
+                )
+            });
+        let keys = column![
+            text("Keys").size(14),
+            scrollable(keys).height(Length::Fixed(220.0)),
+            button("Default keys").on_press(PreferenceMessage::ResetKeys),
+        ]
+        .spacing(5);
+
+        container(
+            column![
+                text("Preferences").size(18),
+                row![column![input, startup].spacing(15), keys].spacing(20),
+                text("Input changes reopen the capture; time/div and persistence apply at startup")
+                    .size(11),
+                row![
+                    button("Cancel").on_press(PreferenceMessage::Cancel),
+                    button("Save").on_press(PreferenceMessage::Save),
+                ]
+                .spacing(10),
+            ]
+            .spacing(15),
+        )
+        .padding(20)
+        .style(container::rounded_box)
+        .into()
+    }
+}
+
+/// `content` over a dimmed `base`, which takes no input while it's shown.
+/// Clicking outside `content` sends `on_blur`.
+/// A label left blank is no label
+fn non_empty(label: String) -> Option<String> {
+    (!label.trim().is_empty()).then_some(label)
+}
+
+pub fn modal<'a, Message: Clone + 'a>(
+    base: impl Into<Element<'a, Message>>,
+    content: impl Into<Element<'a, Message>>,
+    on_blur: Message,
+) -> Element<'a, Message> {
+    stack![
+        base.into(),
+        opaque(
+            mouse_area(center(opaque(content)).style(|_theme| {
+                container::Style {
+                    background: Some(
+                        Color {
+                            a: 0.8,
+                            ..Color::BLACK
+                        }
+                        .into(),
+                    ),
+                    ..container::Style::default()
+                }
+            }))
+            .on_press(on_blur)
+        )
+    ]
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dialog() -> PreferencesDialog {
+        PreferencesDialog {
+            draft: Preferences::default(),
+            devices: Vec::new(),
+            configs: Vec::new(),
+            input_url: String::new(),
+            binding: None,
+        }
+    }
+
+    #[test]
+    fn test_input_url_applies_once_a_whole_one() {
+        let mut dialog = dialog();
+        dialog.update(PreferenceMessage::SelectDevice("USB Audio".to_string()));
+        dialog.update(PreferenceMessage::SetInputUrl("udp:/".to_string()));
+        assert_eq!(
+            dialog.preferences().input_device.as_deref(),
+            Some("USB Audio")
+        );
+
+        dialog.update(PreferenceMessage::SetInputUrl("udp://0.0.0.0".to_string()));
+        assert_eq!(
+            dialog.preferences().input_device.as_deref(),
+            Some("udp://0.0.0.0")
+        );
+        dialog.update(PreferenceMessage::SetInputUrl(
+            "serial:/dev/ttyACM0?baud=9600".to_string(),
+        ));
+        assert_eq!(
+            dialog.preferences().input_device.as_deref(),
+            Some("serial:/dev/ttyACM0?baud=9600")
+        );
+        // Cleared, it's back to the default input
+        dialog.update(PreferenceMessage::SetInputUrl(String::new()));
+        assert_eq!(dialog.preferences().input_device, None);
+    }
+
+    #[test]
+    fn test_rebinding_swaps_keys() {
+        let mut dialog = dialog();
+        dialog.update(PreferenceMessage::StartBinding("toggle_trigger"));
+        assert!(dialog.is_binding());
+        // Already the theme key, which moves to the trigger's old one
+        dialog.bind("C");
+        assert!(!dialog.is_binding());
+
+        let preferences = dialog.preferences();
+        assert!(matches!(
+            control_for_key(preferences, Key::Character('c'), false),
+            Some(ControlMessage::ToggleTrigger)
+        ));
+        assert!(matches!(
+            control_for_key(preferences, Key::Character('T'), false),
+            Some(ControlMessage::CycleTheme)
+        ));
+    }
+
+    #[test]
+    fn test_fixed_keys_cannot_be_taken() {
+        let mut dialog = dialog();
+        dialog.update(PreferenceMessage::StartBinding("toggle_trigger"));
+        dialog.bind("+");
+        assert!(dialog.is_binding());
+        assert!(matches!(
+            control_for_key(dialog.preferences(), Key::Character('t'), false),
+            Some(ControlMessage::ToggleTrigger)
+        ));
+
+        dialog.bind("x");
+        dialog.update(PreferenceMessage::ResetKeys);
+        assert_eq!(dialog.preferences(), &Preferences::default());
+    }
+}
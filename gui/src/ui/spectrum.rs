@@ -1,81 +1,333 @@
+use iced::alignment::Horizontal;
 use iced::mouse;
-use iced::widget::canvas::{self, Cache, Frame, Geometry, Path, Stroke, Text};
-use iced::{Color, Point, Rectangle, Renderer, Size, Theme};
-use rustfft::num_complex::Complex;
-use rustfft::FftPlanner;
+use iced::widget::canvas::{self, event, Cache, Event, Frame, Geometry, Path, Stroke, Text};
+use iced::{Color, Font, Point, Rectangle, Renderer, Size, Theme};
+
+use super::controls::ControlMessage;
+use super::to_color;
+use super::touch::{Gesture, Touches};
+use ozeecubed_core::display::{
+    band_cursor_markers, band_power_label, harmonic_markers, harmonic_table, Palette,
+};
+use ozeecubed_core::dsp::{FilterBlock, FilterChain, FilterDesign, FilterKind};
+use ozeecubed_core::oscilloscope::{
+    BandCursor, BandCursors, BandPower, HarmonicAnalysis, OctaveSmoothing, SpectrumAnalyzer,
+    SpectrumAverage, SpectrumMetadata, SpectrumScale,
+};
 
 pub struct SpectrumCanvas {
     cache: Cache,
+    analyzer: SpectrumAnalyzer,
     spectrum: Vec<f32>,
+    /// Spectra averaged since averaging was turned on, shown as `spectrum`
+    average: Option<SpectrumAverage>,
+    smoothing: OctaveSmoothing,
+    /// `spectrum` smoothed, drawn in its place while smoothing is on
+    smoothed: Vec<f32>,
+    /// Draw the unsmoothed curve faintly behind the smoothed one
+    show_raw: bool,
     sample_rate: u32,
+    palette: Palette,
+    show_harmonics: bool,
+    harmonics: Option<HarmonicAnalysis>,
+    /// Added to the dB labels to read in dB SPL
+    level_offset: Option<f32>,
+    /// Frequency cursors of the band power measurement, while it's on
+    band: Option<BandCursors>,
+    /// Of the latest spectrum, on the linear scale only
+    band_power: Option<BandPower>,
+    /// Filter whose response is drawn over the spectrum, while the
+    /// designer is open
+    response: Option<FilterChain>,
 }
 
 impl SpectrumCanvas {
     pub fn new() -> Self {
         Self {
             cache: Cache::new(),
+            analyzer: SpectrumAnalyzer::new(),
             spectrum: Vec::new(),
+            average: None,
+            smoothing: OctaveSmoothing::default(),
+            smoothed: Vec::new(),
+            show_raw: true,
             sample_rate: 48000,
+            palette: Palette::default(),
+            show_harmonics: false,
+            harmonics: None,
+            level_offset: None,
+            band: None,
+            band_power: None,
+            response: None,
         }
     }
 
     pub fn update_spectrum(&mut self, samples: &[f32], sample_rate: u32) {
+        self.analyzer.update(samples);
+        self.set_spectrum(self.analyzer.magnitudes().to_vec(), sample_rate);
+    }
+
+    /// Show the spectrum of just `samples`, the part of the capture
+    /// between the time gate cursors
+    pub fn update_gated(&mut self, samples: &[f32], sample_rate: u32) {
+        self.analyzer.update_gated(samples);
+        self.set_spectrum(self.analyzer.magnitudes().to_vec(), sample_rate);
+    }
+
+    /// Show dB magnitudes already computed elsewhere
+    pub fn set_spectrum(&mut self, magnitudes: Vec<f32>, sample_rate: u32) {
         self.sample_rate = sample_rate;
-        self.spectrum = self.compute_spectrum(samples);
+        if let Some(ref mut response) = self.response {
+            response.set_sample_rate(sample_rate);
+        }
+        self.spectrum = match self.average {
+            Some(ref mut average) => {
+                average.add(&magnitudes);
+                average.magnitudes()
+            }
+            None => magnitudes,
+        };
+        self.smooth();
+        // Harmonics are found on linear FFT bins only
+        self.harmonics = if self.show_harmonics && self.scale() == SpectrumScale::Linear {
+            HarmonicAnalysis::of(&self.spectrum, sample_rate)
+        } else {
+            None
+        };
+        self.measure_band();
         self.cache.clear();
     }
 
-    pub fn view<'a>(&'a self) -> iced::Element<'a, ()> {
-        iced::widget::canvas(self as &'a Self)
-            .width(iced::Length::Fill)
-            .height(iced::Length::Fill)
-            .into()
+    /// Harmonics and band power are still measured on the unsmoothed bins
+    fn smooth(&mut self) {
+        self.smoothing.smooth_into(
+            &self.spectrum,
+            self.scale(),
+            self.sample_rate,
+            &mut self.smoothed,
+        );
+    }
+
+    pub fn smoothing(&self) -> OctaveSmoothing {
+        self.smoothing
+    }
+
+    pub fn cycle_smoothing(&mut self) {
+        self.smoothing = self.smoothing.next();
+        self.smooth();
+        self.cache.clear();
+    }
+
+    /// Start averaging the spectra from here on, or go back to showing
+    /// each one
+    pub fn toggle_average(&mut self) {
+        self.average = match self.average {
+            Some(_) => None,
+            None => Some(SpectrumAverage::default()),
+        };
+    }
+
+    /// Spectra in the average, while averaging
+    pub fn averages(&self) -> Option<usize> {
+        self.average.as_ref().map(SpectrumAverage::count)
+    }
+
+    /// The spectrum as shown, smoothed or averaged, as CSV for other tools
+    pub fn to_csv(&self) -> String {
+        let shown = match self.smoothing {
+            OctaveSmoothing::Off => &self.spectrum,
+            _ => &self.smoothed,
+        };
+        let scale = self.scale();
+        let metadata = SpectrumMetadata {
+            // Linear bins run up to Nyquist, half the transform
+            fft_size: (scale == SpectrumScale::Linear).then_some(shown.len() * 2),
+            averages: self.averages().unwrap_or(1),
+            smoothing: self.smoothing,
+            level_offset: self.level_offset,
+            ..SpectrumMetadata::new(scale, self.sample_rate)
+        };
+        metadata.to_csv(shown)
+    }
+
+    pub fn is_showing_raw(&self) -> bool {
+        self.show_raw
+    }
+
+    pub fn toggle_raw(&mut self) {
+        self.show_raw = !self.show_raw;
+        self.cache.clear();
+    }
+
+    pub fn toggle_harmonics(&mut self) {
+        self.show_harmonics = !self.show_harmonics;
+        self.harmonics = None;
+        self.cache.clear();
+    }
+
+    pub fn is_showing_harmonics(&self) -> bool {
+        self.show_harmonics
+    }
+
+    /// Show or hide the band cursors and their power readout
+    pub fn toggle_band_power(&mut self) {
+        self.band = match self.band {
+            Some(_) => None,
+            None => Some(BandCursors::default()),
+        };
+        self.measure_band();
+        self.cache.clear();
+    }
+
+    pub fn is_measuring_band(&self) -> bool {
+        self.band.is_some()
+    }
+
+    pub fn move_band_cursor(&mut self, cursor: BandCursor, frequency: f32) {
+        if let Some(ref mut band) = self.band {
+            band.set(cursor, frequency);
+            self.measure_band();
+            self.cache.clear();
+        }
+    }
+
+    /// Band power is integrated over linear FFT bins only
+    fn measure_band(&mut self) {
+        self.band_power = self
+            .band
+            .filter(|_| self.scale() == SpectrumScale::Linear)
+            .and_then(|band| band.measure(&self.spectrum, self.sample_rate));
+    }
+
+    fn nyquist(&self) -> f32 {
+        self.sample_rate as f32 / 2.0
+    }
+
+    pub fn scale(&self) -> SpectrumScale {
+        self.analyzer.scale()
     }
 
-    fn compute_spectrum(&self, samples: &[f32]) -> Vec<f32> {
-        if samples.is_empty() {
-            return vec![];
+    /// Spectra computed elsewhere have to be switched to match
+    pub fn set_scale(&mut self, scale: SpectrumScale) {
+        self.analyzer.set_scale(scale, self.sample_rate);
+        if let Some(ref mut average) = self.average {
+            average.clear();
         }
+        self.spectrum.clear();
+        self.smoothed.clear();
+        self.harmonics = None;
+        self.band_power = None;
+        self.cache.clear();
+    }
 
-        // Use a power of 2 for FFT efficiency
-        let fft_size = samples.len().next_power_of_two().min(4096);
-        let mut buffer: Vec<Complex<f32>> = samples
-            .iter()
-            .take(fft_size)
-            .map(|&x| Complex::new(x, 0.0))
-            .collect();
-
-        // Pad with zeros if needed
-        buffer.resize(fft_size, Complex::new(0.0, 0.0));
-
-        // Apply Hann window to reduce spectral leakage
-        for (i, sample) in buffer.iter_mut().enumerate() {
-            let window =
-                0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / fft_size as f32).cos());
-            *sample *= window;
+    /// Label levels in dB SPL with this calibration offset, or in dBFS
+    /// with `None`
+    pub fn set_level_offset(&mut self, offset: Option<f32>) {
+        if offset != self.level_offset {
+            self.level_offset = offset;
+            self.cache.clear();
         }
+    }
+
+    /// Draw `design`'s magnitude response over the spectrum, or stop
+    pub fn set_response(&mut self, design: Option<FilterDesign>) {
+        self.response = design.map(|design| {
+            let mut chain = FilterChain::new(self.sample_rate);
+            chain.set_blocks(&[FilterBlock::new(FilterKind::Designed(design))]);
+            chain
+        });
+        self.cache.clear();
+    }
 
-        // Perform FFT
-        let mut planner = FftPlanner::new();
-        let fft = planner.plan_fft_forward(fft_size);
-        fft.process(&mut buffer);
-
-        // Compute magnitude spectrum (only first half - positive frequencies)
-        // Convert to dB scale
-        buffer
-            .iter()
-            .take(fft_size / 2)
-            .map(|c| {
-                let magnitude = c.norm() / (fft_size as f32).sqrt();
-                // Convert to dB, with floor at -80 dB
-                20.0 * (magnitude.max(0.00001)).log10()
-            })
-            .collect()
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.cache.clear();
+    }
+
+    pub fn view<'a>(&'a self) -> iced::Element<'a, ControlMessage> {
+        iced::widget::canvas(self as &'a Self)
+            .width(iced::Length::Fill)
+            .height(iced::Length::Fill)
+            .into()
     }
 }
 
-impl canvas::Program<()> for SpectrumCanvas {
-    type State = ();
+/// The band cursor being dragged and the fingers on the spectrum
+#[derive(Debug, Default)]
+pub struct SpectrumState {
+    drag: Option<BandCursor>,
+    touches: Touches,
+}
+
+impl canvas::Program<ControlMessage> for SpectrumCanvas {
+    type State = SpectrumState;
+
+    /// Pressing on the spectrum, with the mouse or a finger, picks up the
+    /// nearer band cursor, and dragging moves it
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<ControlMessage>) {
+        let (Some(band), SpectrumScale::Linear) = (self.band, self.scale()) else {
+            return (event::Status::Ignored, None);
+        };
+        let frequency_at =
+            |position: Point| (position.x / bounds.width.max(1.0)).clamp(0.0, 1.0) * self.nyquist();
+        let grab = |drag: &mut Option<BandCursor>, position: Point| {
+            let frequency = frequency_at(position);
+            let grabbed = band.nearest(frequency);
+            *drag = Some(grabbed);
+            Some(ControlMessage::MoveBandCursor(grabbed, frequency))
+        };
+
+        match event {
+            Event::Touch(event) => match state.touches.update(event, bounds) {
+                Some(Gesture::Press(position)) => {
+                    (event::Status::Captured, grab(&mut state.drag, position))
+                }
+                Some(Gesture::Drag(position)) => (
+                    event::Status::Captured,
+                    state.drag.map(|dragged| {
+                        ControlMessage::MoveBandCursor(dragged, frequency_at(position))
+                    }),
+                ),
+                Some(Gesture::Pinch(_) | Gesture::Release) => {
+                    state.drag = None;
+                    (event::Status::Captured, None)
+                }
+                None if state.touches.is_active() => (event::Status::Captured, None),
+                None => (event::Status::Ignored, None),
+            },
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                match cursor.position_in(bounds) {
+                    Some(position) => (event::Status::Captured, grab(&mut state.drag, position)),
+                    None => (event::Status::Ignored, None),
+                }
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. }) => match (state.drag, cursor.position())
+            {
+                (Some(dragged), Some(position)) => {
+                    let position = Point::new(position.x - bounds.x, position.y - bounds.y);
+                    (
+                        event::Status::Captured,
+                        Some(ControlMessage::MoveBandCursor(
+                            dragged,
+                            frequency_at(position),
+                        )),
+                    )
+                }
+                _ => (event::Status::Ignored, None),
+            },
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                state.drag = None;
+                (event::Status::Ignored, None)
+            }
+            _ => (event::Status::Ignored, None),
+        }
+    }
 
     fn draw(
         &self,
@@ -89,19 +341,62 @@ impl canvas::Program<()> for SpectrumCanvas {
             let width = frame.width();
             let height = frame.height();
 
-            // Draw dark background
+            // Draw background
             frame.fill_rectangle(
                 Point::ORIGIN,
                 Size::new(width, height),
-                Color::from_rgb8(10, 10, 10),
+                to_color(self.palette.background),
             );
 
             // Draw grid
-            draw_spectrum_grid(frame, width, height);
+            let scale = self.scale();
+            let labels = scale.frequency_labels(self.sample_rate, self.spectrum.len());
+            draw_spectrum_grid(
+                frame,
+                width,
+                height,
+                scale,
+                &labels,
+                self.level_offset.unwrap_or(0.0),
+                &self.palette,
+            );
 
             // Draw spectrum
-            if !self.spectrum.is_empty() {
-                draw_spectrum(frame, width, height, &self.spectrum);
+            if self.smoothing == OctaveSmoothing::Off {
+                draw_spectrum(frame, width, height, &self.spectrum, &self.palette);
+            } else {
+                if self.show_raw {
+                    let faint = to_color(Palette::faded(self.palette.trace, 0.3));
+                    draw_curve(frame, width, height, &self.spectrum, faint);
+                }
+                draw_spectrum(frame, width, height, &self.smoothed, &self.palette);
+            }
+
+            if let Some(ref response) = self.response {
+                // Sampled at the spectrum's bins, so it lines up on every scale
+                let bins = self.spectrum.len().max(2);
+                let curve: Vec<f32> = (0..bins)
+                    .map(|bin| {
+                        let frequency = scale.bin_frequency(bin, bins, self.sample_rate);
+                        20.0 * response.magnitude(frequency).max(1e-9).log10() as f32
+                    })
+                    .collect();
+                draw_curve(frame, width, height, &curve, to_color(self.palette.accent));
+            }
+
+            if let Some(ref analysis) = self.harmonics {
+                draw_harmonics(frame, analysis, self.sample_rate, &self.palette);
+            }
+
+            if let (Some(ref band), Some(ref band_power)) = (self.band, self.band_power) {
+                draw_band(
+                    frame,
+                    band,
+                    band_power,
+                    self.sample_rate,
+                    self.level_offset,
+                    &self.palette,
+                );
             }
         });
 
@@ -109,14 +404,33 @@ impl canvas::Program<()> for SpectrumCanvas {
     }
 }
 
-fn draw_spectrum_grid(frame: &mut Frame, width: f32, height: f32) {
-    let grid_color = Color::from_rgba8(0, 255, 65, 0.15);
-    let center_color = Color::from_rgba8(0, 255, 65, 0.3);
-
-    // Vertical lines (frequency divisions)
+/// `labels` are the frequency axis labels, as (fraction of the width, text)
+fn draw_spectrum_grid(
+    frame: &mut Frame,
+    width: f32,
+    height: f32,
+    scale: SpectrumScale,
+    labels: &[(f32, String)],
+    level_offset: f32,
+    palette: &Palette,
+) {
+    // The spectrum grid is drawn fainter than the scope grid
+    let grid_color = to_color(Palette::faded(palette.grid, 0.5));
+    let center_color = to_color(palette.grid);
+
+    // Vertical lines (frequency divisions, or at the labels on the constant-Q
+    // and mel scales)
     let num_v_divs = 10;
-    for i in 0..=num_v_divs {
-        let x = (i as f32 / num_v_divs as f32) * width;
+    let divisions: Vec<f32> = match scale {
+        SpectrumScale::Linear => (0..=num_v_divs)
+            .map(|i| i as f32 / num_v_divs as f32)
+            .collect(),
+        SpectrumScale::ConstantQ | SpectrumScale::Mel => {
+            labels.iter().map(|&(fraction, _)| fraction).collect()
+        }
+    };
+    for (i, fraction) in divisions.into_iter().enumerate() {
+        let x = fraction * width;
         let color = if i == 0 { center_color } else { grid_color };
 
         let line = Path::line(Point::new(x, 0.0), Point::new(x, height));
@@ -138,18 +452,11 @@ fn draw_spectrum_grid(frame: &mut Frame, width: f32, height: f32) {
     }
 
     // Draw frequency labels at bottom
-    let label_color = Color::from_rgba8(0, 255, 65, 0.7);
-    for i in 0..=5 {
-        let x = (i as f32 / 5.0) * width;
-        let freq_khz = (i as f32 / 5.0) * 24.0; // 0-24 kHz for 48kHz sample rate
-        let label = if freq_khz == 0.0 {
-            "0".to_string()
-        } else {
-            format!("{freq_khz:.0}k")
-        };
-
+    let label_color = to_color(palette.text);
+    for (fraction, label) in labels {
+        let x = fraction * width;
         frame.fill_text(Text {
-            content: label,
+            content: label.clone(),
             position: Point::new(x + 5.0, height - 18.0),
             color: label_color,
             size: 11.0.into(),
@@ -160,7 +467,7 @@ fn draw_spectrum_grid(frame: &mut Frame, width: f32, height: f32) {
     // Draw dB labels on left
     for i in 0..=4 {
         let y = (i as f32 / 4.0) * height;
-        let db = -(80.0 - (i as f32 / 4.0) * 80.0); // -80 dB to 0 dB
+        let db = -(80.0 - (i as f32 / 4.0) * 80.0) + level_offset; // -80 dB to 0 dB
         let label = format!("{db:.0}");
 
         frame.fill_text(Text {
@@ -173,13 +480,81 @@ fn draw_spectrum_grid(frame: &mut Frame, width: f32, height: f32) {
     }
 }
 
-fn draw_spectrum(frame: &mut Frame, width: f32, height: f32, spectrum: &[f32]) {
+/// Bars on the harmonics and their table in the top-right corner
+fn draw_harmonics(
+    frame: &mut Frame,
+    analysis: &HarmonicAnalysis,
+    sample_rate: u32,
+    palette: &Palette,
+) {
+    let size = frame.size();
+    let accent = to_color(palette.accent);
+    for ((x0, y0), (x1, y1)) in harmonic_markers(analysis, sample_rate) {
+        let bar = Path::line(
+            Point::new(x0 * size.width, y0 * size.height),
+            Point::new(x1 * size.width, y1 * size.height),
+        );
+        frame.stroke(&bar, Stroke::default().with_color(accent).with_width(3.0));
+    }
+
+    let table = harmonic_table(analysis);
+    frame.fill_text(Text {
+        content: table.text,
+        position: Point::new(
+            table.position.0 * size.width,
+            table.position.1 * size.height,
+        ),
+        color: to_color(palette.text),
+        size: 11.0.into(),
+        font: Font::MONOSPACE,
+        horizontal_alignment: Horizontal::Right,
+        ..Default::default()
+    });
+}
+
+/// The band cursors and the band's power and noise floor beside the top
+/// dB label
+fn draw_band(
+    frame: &mut Frame,
+    band: &BandCursors,
+    band_power: &BandPower,
+    sample_rate: u32,
+    level_offset: Option<f32>,
+    palette: &Palette,
+) {
+    let size = frame.size();
+    let accent = to_color(palette.accent);
+    for ((x0, y0), (x1, y1)) in band_cursor_markers(band, sample_rate) {
+        let line = Path::line(
+            Point::new(x0 * size.width, y0 * size.height),
+            Point::new(x1 * size.width, y1 * size.height),
+        );
+        frame.stroke(&line, Stroke::default().with_color(accent).with_width(1.0));
+    }
+
+    let readout = band_power_label(band_power, level_offset);
+    frame.fill_text(Text {
+        content: readout.text,
+        position: Point::new(
+            readout.position.0 * size.width,
+            readout.position.1 * size.height,
+        ),
+        color: to_color(palette.text),
+        size: 11.0.into(),
+        ..Default::default()
+    });
+}
+
+fn draw_spectrum(frame: &mut Frame, width: f32, height: f32, spectrum: &[f32], palette: &Palette) {
+    draw_curve(frame, width, height, spectrum, to_color(palette.trace));
+}
+
+/// dB values, one per bin, on the spectrum's -80 to 0 dB axis
+fn draw_curve(frame: &mut Frame, width: f32, height: f32, spectrum: &[f32], color: Color) {
     if spectrum.len() < 2 {
         return;
     }
 
-    let waveform_color = Color::from_rgb8(0, 255, 65);
-
     // Build path for spectrum curve
     let mut path_builder = canvas::path::Builder::new();
 
@@ -201,8 +576,5 @@ fn draw_spectrum(frame: &mut Frame, width: f32, height: f32, spectrum: &[f32]) {
     }
 
     let path = path_builder.build();
-    frame.stroke(
-        &path,
-        Stroke::default().with_color(waveform_color).with_width(2.0),
-    );
+    frame.stroke(&path, Stroke::default().with_color(color).with_width(2.0));
 }
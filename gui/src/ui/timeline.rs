@@ -0,0 +1,80 @@
+use iced::mouse;
+use iced::widget::canvas::{self, event, Event, Geometry, Path, Stroke};
+use iced::{Point, Rectangle, Renderer, Size, Theme};
+
+use super::controls::ControlMessage;
+use super::to_color;
+use ozeecubed_core::display::Palette;
+
+/// The whole recording as a strip, with a tick at every search hit and a
+/// line at the play head. A click seeks there.
+pub struct Timeline {
+    /// Hit spans as fractions of the recording
+    pub hits: Vec<(f32, f32)>,
+    /// Index of the hit last jumped to
+    pub current: Option<usize>,
+    pub position: f32,
+    pub palette: Palette,
+}
+
+impl canvas::Program<ControlMessage> for Timeline {
+    type State = ();
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<ControlMessage>) {
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                match cursor.position_in(bounds) {
+                    Some(position) => (
+                        event::Status::Captured,
+                        Some(ControlMessage::SeekReplay(position.x / bounds.width)),
+                    ),
+                    None => (event::Status::Ignored, None),
+                }
+            }
+            _ => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let mut frame = canvas::Frame::new(renderer, bounds.size());
+        let size = frame.size();
+        frame.fill_rectangle(Point::ORIGIN, size, to_color(self.palette.background));
+
+        for (index, &(start, end)) in self.hits.iter().enumerate() {
+            let color = if Some(index) == self.current {
+                self.palette.accent
+            } else {
+                self.palette.trace
+            };
+            // At least a pixel wide, however short the hit
+            let width = ((end - start) * size.width).max(1.0);
+            frame.fill_rectangle(
+                Point::new(start * size.width, 0.0),
+                Size::new(width, size.height),
+                to_color(color),
+            );
+        }
+
+        let x = self.position * size.width;
+        frame.stroke(
+            &Path::line(Point::new(x, 0.0), Point::new(x, size.height)),
+            Stroke::default()
+                .with_color(to_color(self.palette.text))
+                .with_width(2.0),
+        );
+        vec![frame.into_geometry()]
+    }
+}
@@ -0,0 +1,125 @@
+use iced::touch::{self, Finger};
+use iced::{Point, Rectangle};
+
+/// Spread under which two fingers are too close to pinch with
+const MIN_PINCH_SPREAD: f32 = 10.0;
+
+/// What the fingers on a canvas are doing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    /// A first finger came down, at this point in the canvas
+    Press(Point),
+    /// The only finger moved here
+    Drag(Point),
+    /// Two fingers moved: their spread over what it was at the last event
+    Pinch(f32),
+    /// The last finger came up
+    Release,
+}
+
+/// Fingers on a canvas, in canvas coordinates. A finger that comes down
+/// outside the canvas isn't followed.
+#[derive(Debug, Default)]
+pub struct Touches {
+    fingers: Vec<(Finger, Point)>,
+}
+
+impl Touches {
+    /// The gesture `event` continues, if any. `None` for a finger this
+    /// canvas isn't following.
+    pub fn update(&mut self, event: touch::Event, bounds: Rectangle) -> Option<Gesture> {
+        let local = |position: Point| Point::new(position.x - bounds.x, position.y - bounds.y);
+        match event {
+            touch::Event::FingerPressed { id, position } => {
+                if !bounds.contains(position) {
+                    return None;
+                }
+                self.fingers.push((id, local(position)));
+                // A second finger starts a pinch, ending any drag
+                (self.fingers.len() == 1).then_some(Gesture::Press(local(position)))
+            }
+            touch::Event::FingerMoved { id, position } => {
+                let before = self.spread();
+                let finger = self.fingers.iter_mut().find(|(finger, _)| *finger == id)?;
+                finger.1 = local(position);
+                match self.fingers.len() {
+                    1 => Some(Gesture::Drag(local(position))),
+                    2 => before
+                        .zip(self.spread())
+                        .filter(|&(before, after)| before.min(after) > MIN_PINCH_SPREAD)
+                        .map(|(before, after)| Gesture::Pinch(after / before)),
+                    _ => None,
+                }
+            }
+            touch::Event::FingerLifted { id, .. } | touch::Event::FingerLost { id, .. } => {
+                let count = self.fingers.len();
+                self.fingers.retain(|(finger, _)| *finger != id);
+                (count == 1 && self.fingers.is_empty()).then_some(Gesture::Release)
+            }
+        }
+    }
+
+    /// Whether any finger is down on the canvas
+    pub fn is_active(&self) -> bool {
+        !self.fingers.is_empty()
+    }
+
+    /// Distance between the first two fingers
+    fn spread(&self) -> Option<f32> {
+        match self.fingers.as_slice() {
+            [(_, a), (_, b), ..] => Some(a.distance(*b)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::Size;
+
+    #[test]
+    fn test_drag_then_pinch() {
+        let bounds = Rectangle::new(Point::new(100.0, 100.0), Size::new(400.0, 300.0));
+        let mut touches = Touches::default();
+        let pressed = touches.update(
+            touch::Event::FingerPressed {
+                id: Finger(1),
+                position: Point::new(150.0, 200.0),
+            },
+            bounds,
+        );
+        assert_eq!(pressed, Some(Gesture::Press(Point::new(50.0, 100.0))));
+
+        // Outside the canvas, so not followed
+        let outside = touch::Event::FingerPressed {
+            id: Finger(9),
+            position: Point::new(10.0, 10.0),
+        };
+        assert_eq!(touches.update(outside, bounds), None);
+
+        touches.update(
+            touch::Event::FingerPressed {
+                id: Finger(2),
+                position: Point::new(250.0, 200.0),
+            },
+            bounds,
+        );
+        let pinched = touches.update(
+            touch::Event::FingerMoved {
+                id: Finger(2),
+                position: Point::new(350.0, 200.0),
+            },
+            bounds,
+        );
+        assert_eq!(pinched, Some(Gesture::Pinch(2.0)));
+
+        let lifted = |id| touch::Event::FingerLifted {
+            id: Finger(id),
+            position: Point::ORIGIN,
+        };
+        assert_eq!(touches.update(lifted(2), bounds), None);
+        assert_eq!(touches.update(lifted(1), bounds), Some(Gesture::Release));
+        assert!(!touches.is_active());
+    }
+}
@@ -0,0 +1,164 @@
+use iced::alignment::{Horizontal, Vertical};
+use iced::mouse;
+use iced::widget::canvas::{self, Cache, Frame, Geometry, Stroke, Text};
+use iced::{Point, Rectangle, Renderer, Size, Theme};
+
+use super::to_color;
+use ozeecubed_core::display::{
+    vectorscope_axes, vectorscope_labels, vectorscope_position, LabelAnchor, Palette,
+};
+use ozeecubed_core::oscilloscope::Vectorscope;
+
+/// Left against right rotated 45°, in place of the spectrum
+pub struct VectorscopeCanvas {
+    cache: Cache,
+    scope: Vectorscope,
+    palette: Palette,
+}
+
+impl VectorscopeCanvas {
+    pub fn new() -> Self {
+        Self {
+            cache: Cache::new(),
+            scope: Vectorscope::new(),
+            palette: Palette::default(),
+        }
+    }
+
+    /// Add the (left, right) pairs read since the last update
+    pub fn update(&mut self, pairs: &[(f32, f32)]) {
+        if !pairs.is_empty() {
+            self.scope.update(pairs);
+            self.cache.clear();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.scope.clear();
+        self.cache.clear();
+    }
+
+    pub fn toggle_auto_gain(&mut self) {
+        self.scope.auto_gain = !self.scope.auto_gain;
+    }
+
+    pub fn is_auto_gain(&self) -> bool {
+        self.scope.auto_gain
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.cache.clear();
+    }
+
+    pub fn view<'a>(&'a self) -> iced::Element<'a, ()> {
+        iced::widget::canvas(self as &'a Self)
+            .width(iced::Length::Fill)
+            .height(iced::Length::Fill)
+            .into()
+    }
+}
+
+impl canvas::Program<()> for VectorscopeCanvas {
+    type State = ();
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let geometry = self.cache.draw(renderer, bounds.size(), |frame| {
+            frame.fill_rectangle(
+                Point::ORIGIN,
+                frame.size(),
+                to_color(self.palette.background),
+            );
+
+            // The largest square that fits, centred
+            let side = frame.width().min(frame.height());
+            let origin = Point::new((frame.width() - side) / 2.0, (frame.height() - side) / 2.0);
+            let to_point =
+                |(x, y): (f32, f32)| Point::new(origin.x + x * side, origin.y + y * side);
+
+            draw_axes(frame, &to_point, &self.palette);
+            draw_points(frame, &to_point, &self.scope, &self.palette);
+            draw_labels(frame, &to_point, &self.scope, &self.palette);
+        });
+
+        vec![geometry]
+    }
+}
+
+fn draw_axes(frame: &mut Frame, to_point: &impl Fn((f32, f32)) -> Point, palette: &Palette) {
+    // Drawn as faint as the spectrum grid
+    let grid_color = to_color(Palette::faded(palette.grid, 0.5));
+
+    let mut builder = canvas::path::Builder::new();
+    let (top_left, bottom_right) = (to_point((0.0, 0.0)), to_point((1.0, 1.0)));
+    builder.rectangle(
+        top_left,
+        Size::new(bottom_right.x - top_left.x, bottom_right.y - top_left.y),
+    );
+    for (from, to) in vectorscope_axes() {
+        builder.move_to(to_point(from));
+        builder.line_to(to_point(to));
+    }
+    frame.stroke(
+        &builder.build(),
+        Stroke::default().with_color(grid_color).with_width(1.0),
+    );
+}
+
+fn draw_points(
+    frame: &mut Frame,
+    to_point: &impl Fn((f32, f32)) -> Point,
+    scope: &Vectorscope,
+    palette: &Palette,
+) {
+    for (brightness, points) in scope.frames() {
+        if points.len() < 2 {
+            continue;
+        }
+        let mut builder = canvas::path::Builder::new();
+        builder.move_to(to_point(vectorscope_position(points[0])));
+        for &point in &points[1..] {
+            builder.line_to(to_point(vectorscope_position(point)));
+        }
+        // Older frames fade out, as the waveform's persistence does
+        let color = Palette::faded(palette.trace, brightness * brightness);
+        frame.stroke(
+            &builder.build(),
+            Stroke::default()
+                .with_color(to_color(color))
+                .with_width(1.0),
+        );
+    }
+}
+
+fn draw_labels(
+    frame: &mut Frame,
+    to_point: &impl Fn((f32, f32)) -> Point,
+    scope: &Vectorscope,
+    palette: &Palette,
+) {
+    for label in vectorscope_labels(&scope.balance(), scope.gain()) {
+        let (horizontal_alignment, vertical_alignment) = match label.anchor {
+            LabelAnchor::BottomLeft => (Horizontal::Left, Vertical::Bottom),
+            LabelAnchor::BottomRight => (Horizontal::Right, Vertical::Bottom),
+            LabelAnchor::TopRight => (Horizontal::Right, Vertical::Top),
+            LabelAnchor::TopLeft => (Horizontal::Left, Vertical::Top),
+        };
+        frame.fill_text(Text {
+            content: label.text,
+            position: to_point(label.position),
+            color: to_color(palette.text),
+            size: 11.0.into(),
+            horizontal_alignment,
+            vertical_alignment,
+            ..Default::default()
+        });
+    }
+}
@@ -0,0 +1,131 @@
+use iced::mouse;
+use iced::widget::canvas::{self, event, Cache, Event, Geometry};
+use iced::{Point, Rectangle, Renderer, Theme};
+
+use super::controls::ControlMessage;
+use super::touch::{Gesture, Touches};
+use super::{draw_axis_labels, draw_grid, draw_waveform_points, to_color, Points};
+use ozeecubed_core::display::{Graticule, Palette};
+
+/// How much one notch of the mouse wheel narrows or widens the zoom region
+const WHEEL_FACTOR: f32 = 0.8;
+
+/// The zoom region of the main trace, expanded to fill the second pane at
+/// its faster timebase
+pub struct ZoomCanvas {
+    cache: Cache,
+    points: Points,
+    time_per_division: f32,
+    volts_per_division: f32,
+    graticule: Graticule,
+    palette: Palette,
+}
+
+impl ZoomCanvas {
+    pub fn new() -> Self {
+        Self {
+            cache: Cache::new(),
+            points: Points::default(),
+            time_per_division: 0.0,
+            volts_per_division: 1.0,
+            graticule: Graticule::default(),
+            palette: Palette::default(),
+        }
+    }
+
+    /// Show the region's trace, drawn with the main trace's grid and
+    /// vertical scale
+    pub fn set_trace(
+        &mut self,
+        points: impl Into<Points>,
+        time_per_division: f32,
+        volts_per_division: f32,
+        graticule: &Graticule,
+    ) {
+        self.points = points.into();
+        self.time_per_division = time_per_division;
+        self.volts_per_division = volts_per_division;
+        self.graticule = graticule.clone();
+        self.cache.clear();
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.cache.clear();
+    }
+
+    pub fn view<'a>(&'a self) -> iced::Element<'a, ControlMessage> {
+        iced::widget::canvas(self as &'a Self)
+            .width(iced::Length::Fill)
+            .height(iced::Length::Fill)
+            .into()
+    }
+}
+
+impl canvas::Program<ControlMessage> for ZoomCanvas {
+    /// Fingers on the pane
+    type State = Touches;
+
+    /// The mouse wheel or a pinch narrows or widens the region
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (event::Status, Option<ControlMessage>) {
+        match event {
+            Event::Touch(event) => match state.update(event, bounds) {
+                // Spreading the fingers zooms in, narrowing the region
+                Some(Gesture::Pinch(spread)) => (
+                    event::Status::Captured,
+                    Some(ControlMessage::ScaleZoom(1.0 / spread)),
+                ),
+                Some(_) => (event::Status::Captured, None),
+                None if state.is_active() => (event::Status::Captured, None),
+                None => (event::Status::Ignored, None),
+            },
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) if cursor.is_over(bounds) => {
+                let lines = match delta {
+                    mouse::ScrollDelta::Lines { y, .. } => y,
+                    mouse::ScrollDelta::Pixels { y, .. } => y / 40.0,
+                };
+                (
+                    event::Status::Captured,
+                    Some(ControlMessage::ScaleZoom(WHEEL_FACTOR.powf(lines))),
+                )
+            }
+            _ => (event::Status::Ignored, None),
+        }
+    }
+
+    fn draw(
+        &self,
+        _state: &Self::State,
+        renderer: &Renderer,
+        _theme: &Theme,
+        bounds: Rectangle,
+        _cursor: mouse::Cursor,
+    ) -> Vec<Geometry> {
+        let geometry = self.cache.draw(renderer, bounds.size(), |frame| {
+            let size = frame.size();
+            frame.fill_rectangle(Point::ORIGIN, size, to_color(self.palette.background));
+            draw_grid(frame, size, &self.graticule, &self.palette);
+            draw_axis_labels(
+                frame,
+                size,
+                (self.time_per_division, self.volts_per_division),
+                &self.graticule,
+                &self.palette,
+            );
+            draw_waveform_points(
+                frame,
+                size,
+                &self.points,
+                self.graticule.vertical_divisions,
+                to_color(self.palette.trace),
+            );
+        });
+        vec![geometry]
+    }
+}
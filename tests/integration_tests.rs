@@ -22,6 +22,7 @@ fn test_oscilloscope_workflow() {
         enabled: true,
         edge: TriggerEdge::Rising,
         level: 0.0,
+        ..Default::default()
     };
 
     // Get display samples
@@ -111,6 +112,7 @@ fn test_trigger_edge_detection() {
         enabled: true,
         edge: TriggerEdge::Rising,
         level: 0.0,
+        ..Default::default()
     };
 
     let rising_samples = waveform.get_display_samples(&rising_settings);
@@ -120,6 +122,7 @@ fn test_trigger_edge_detection() {
         enabled: true,
         edge: TriggerEdge::Falling,
         level: 0.0,
+        ..Default::default()
     };
     let falling_samples = waveform.get_display_samples(&falling_settings);
 
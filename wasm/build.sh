@@ -19,10 +19,19 @@ if ! rustup target list | grep -q "wasm32-unknown-unknown (installed)"; then
     rustup target add wasm32-unknown-unknown
 fi
 
+# Optional WebGPU renderer: WEBGPU=1 ./build.sh
+FEATURES=""
+if [ "${WEBGPU:-0}" = "1" ]; then
+    echo "🧪 Including the WebGPU renderer (falls back to WebGL at runtime)"
+    FEATURES="--features ozeecubed-wasm/webgpu"
+    # wgpu's WebGPU backend uses web-sys APIs that are still unstable
+    export RUSTFLAGS="${RUSTFLAGS:-} --cfg=web_sys_unstable_apis"
+fi
+
 # Build the WASM binary (from project root)
 echo "🔨 Compiling to WASM..."
 cd ..
-cargo build --target wasm32-unknown-unknown --release
+cargo build --target wasm32-unknown-unknown --release $FEATURES
 
 # Generate JS bindings
 echo "🔗 Generating JS bindings..."
@@ -35,7 +44,7 @@ wasm-bindgen ../target/wasm32-unknown-unknown/release/ozeecubed_wasm.wasm \
 echo "📋 Copying to docs/ozeecubed for GitHub Pages..."
 mkdir -p ../docs/ozeecubed
 cp -r pkg ../docs/ozeecubed/
-cp index.html ../docs/ozeecubed/
+cp index.html minimal.html worker.js ../docs/ozeecubed/
 
 echo "✅ Build complete!"
 echo "📂 Output in ./pkg/"
@@ -44,6 +53,9 @@ echo ""
 echo "To test locally:"
 echo "  cd wasm && python3 -m http.server 8888"
 echo "  Open: http://localhost:8888"
+echo "  (the worker pipeline also needs Cross-Origin-Opener-Policy: same-origin"
+echo "   and Cross-Origin-Embedder-Policy: require-corp; without them audio is"
+echo "   processed on the main thread)"
 echo ""
 echo "GitHub Pages URL (after push):"
 echo "  https://<username>.github.io/ozeecubed/ozeecubed/"
@@ -1,100 +1,391 @@
 use std::cell::RefCell;
 use std::rc::Rc;
+
+use crate::ring::SampleRing;
+use ozeecubed_core::audio::AudioError;
+use ozeecubed_core::source::{SignalSource, SourceStatus};
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{AudioContext, MediaStream, MediaStreamConstraints, ScriptProcessorNode};
+use web_sys::{
+    AudioContext, AudioNode, AudioWorkletNode, AudioWorkletNodeOptions, Blob, BlobPropertyBag,
+    ConstrainDomStringParameters, MediaDeviceInfo, MediaDeviceKind, MediaStream,
+    MediaStreamAudioSourceNode, MediaStreamConstraints, MediaStreamTrack, MediaStreamTrackState,
+    MediaTrackConstraints, MessageEvent, ScriptProcessorNode, Url,
+};
+
+/// Most samples held between reads (one second at 48 kHz)
+const MAX_BUFFERED_SAMPLES: usize = 48000;
+
+/// Name the worklet processor is registered under
+const PROCESSOR_NAME: &str = "ozeecubed-capture";
+
+/// AudioWorklet processor for the first input channel. By default it batches
+/// render quanta into 1024-sample blocks and transfers them to the main
+/// thread; given a `SampleRing` it writes straight into shared memory.
+const PROCESSOR_SOURCE: &str = r#"
+class OzeeCubedCapture extends AudioWorkletProcessor {
+    constructor(options) {
+        super();
+        const ring = options.processorOptions;
+        if (ring && ring.samples) {
+            this.indices = new Int32Array(ring.indices);
+            this.ring = new Float32Array(ring.samples);
+        } else {
+            this.block = new Float32Array(1024);
+            this.filled = 0;
+        }
+    }
+
+    process(inputs) {
+        const channel = inputs[0] && inputs[0][0];
+        if (!channel) {
+            return true;
+        }
+        if (this.ring) {
+            // Continue from the published count, so a new capture node can
+            // take over a ring from an old one
+            const written = Atomics.load(this.indices, 0) >>> 0;
+            for (let i = 0; i < channel.length; i++) {
+                this.ring[(written + i) % this.ring.length] = channel[i];
+            }
+            Atomics.store(this.indices, 0, (written + channel.length) | 0);
+            return true;
+        }
+        let offset = 0;
+        while (offset < channel.length) {
+            const count = Math.min(channel.length - offset, this.block.length - this.filled);
+            this.block.set(channel.subarray(offset, offset + count), this.filled);
+            this.filled += count;
+            offset += count;
+            if (this.filled === this.block.length) {
+                this.port.postMessage(this.block, [this.block.buffer]);
+                this.block = new Float32Array(1024);
+                this.filled = 0;
+            }
+        }
+        return true;
+    }
+}
+
+registerProcessor('ozeecubed-capture', OzeeCubedCapture);
+"#;
+
+/// Node delivering samples into the shared buffer
+enum CaptureNode {
+    /// Runs on the audio rendering thread
+    Worklet(AudioWorkletNode),
+    /// Deprecated main-thread fallback for browsers without AudioWorklet
+    ScriptProcessor(ScriptProcessorNode),
+}
+
+/// An audio input the browser reports
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioInput {
+    pub device_id: String,
+    /// Empty until the page has been granted microphone access
+    pub label: String,
+}
+
+/// List the audio inputs, in the browser's order
+pub async fn list_audio_inputs() -> Result<Vec<AudioInput>, AudioError> {
+    let window = web_sys::window().ok_or_else(|| backend("No window found"))?;
+    let promise = window
+        .navigator()
+        .media_devices()
+        .map_err(|_| AudioError::NoDevice)?
+        .enumerate_devices()
+        .map_err(|_| backend("Failed to enumerate devices"))?;
+    let devices = JsFuture::from(promise)
+        .await
+        .map_err(|_| backend("Failed to await device list"))?;
+
+    Ok(js_sys::Array::from(&devices)
+        .iter()
+        .filter_map(|device| device.dyn_into::<MediaDeviceInfo>().ok())
+        .filter(|device| device.kind() == MediaDeviceKind::Audioinput)
+        .map(|device| AudioInput {
+            device_id: device.device_id(),
+            label: device.label(),
+        })
+        .collect())
+}
 
 pub struct WebAudioCapture {
-    _context: AudioContext,
-    _stream: MediaStream,
-    _processor: ScriptProcessorNode,
+    context: AudioContext,
+    stream: MediaStream,
+    /// Label of the input, empty if the browser doesn't give one
+    label: String,
+    _node: CaptureNode,
     sample_buffer: Rc<RefCell<Vec<f32>>>,
 }
 
 impl WebAudioCapture {
-    pub async fn new() -> Result<Self, String> {
-        let window = web_sys::window().ok_or("No window found")?;
+    /// Capture from `device_id`, or the browser's default input if `None`.
+    ///
+    /// With a `ring` the samples go into it for a worker to read, and
+    /// `read_samples` returns nothing; this needs AudioWorklet support.
+    pub async fn new(
+        device_id: Option<&str>,
+        ring: Option<&SampleRing>,
+    ) -> Result<Self, AudioError> {
+        let window = web_sys::window().ok_or_else(|| backend("No window found"))?;
         let navigator = window.navigator();
 
         // Request microphone access
         let constraints = MediaStreamConstraints::new();
-        constraints.set_audio(&JsValue::from(true));
+        match device_id {
+            Some(device_id) => {
+                // Exact, so a missing device fails instead of silently
+                // opening another one
+                let exact = ConstrainDomStringParameters::new();
+                exact.set_exact(&JsValue::from_str(device_id));
+                let audio = MediaTrackConstraints::new();
+                audio.set_device_id(&exact);
+                constraints.set_audio(&audio);
+            }
+            None => constraints.set_audio(&JsValue::from(true)),
+        }
         constraints.set_video(&JsValue::from(false));
 
         let media_promise = navigator
             .media_devices()
-            .map_err(|_| "No media devices")?
+            .map_err(|_| AudioError::NoDevice)?
             .get_user_media_with_constraints(&constraints)
-            .map_err(|_| "Failed to get user media")?;
+            .map_err(|e| media_error(&e, device_id))?;
 
         let media_result = JsFuture::from(media_promise)
             .await
-            .map_err(|_| "Failed to await media promise")?;
+            .map_err(|e| media_error(&e, device_id))?;
 
         let stream: MediaStream = media_result
             .dyn_into()
-            .map_err(|_| "Failed to cast to MediaStream")?;
+            .map_err(|_| backend("Failed to cast to MediaStream"))?;
 
         // Create audio context
-        let context = AudioContext::new().map_err(|_| "Failed to create AudioContext")?;
+        let context = AudioContext::new().map_err(|_| backend("Failed to create AudioContext"))?;
 
         // Create source from microphone stream
         let source = context
             .create_media_stream_source(&stream)
-            .map_err(|_| "Failed to create media stream source")?;
-
-        // Create script processor (deprecated but widely supported)
-        let buffer_size = 4096;
-        let processor = context
-            .create_script_processor_with_buffer_size_and_number_of_input_channels_and_number_of_output_channels(
-                buffer_size,
-                1,
-                1,
-            )
-            .map_err(|_| "Failed to create script processor")?;
-
-        // Set up audio processing callback
-        let sample_buffer = Rc::new(RefCell::new(Vec::new()));
-        let sample_buffer_clone = sample_buffer.clone();
-
-        let onaudioprocess = Closure::wrap(Box::new(move |event: web_sys::AudioProcessingEvent| {
-            let input_buffer = event.input_buffer().unwrap();
-            let input_data = input_buffer.get_channel_data(0).unwrap();
+            .map_err(|_| backend("Failed to create media stream source"))?;
 
-            let mut buffer = sample_buffer_clone.borrow_mut();
-            buffer.extend_from_slice(&input_data);
+        let sample_buffer = Rc::new(RefCell::new(Vec::new()));
 
-            // Keep buffer size reasonable
-            if buffer.len() > 48000 {
-                let len = buffer.len();
-                buffer.drain(0..len - 48000);
+        // Prefer the worklet; it needs a secure context and a recent browser
+        let node = match create_worklet(&context, &sample_buffer, ring).await {
+            Ok(node) => CaptureNode::Worklet(node),
+            Err(e) if ring.is_some() => return Err(e),
+            Err(e) => {
+                web_sys::console::warn_1(
+                    &format!("AudioWorklet unavailable ({e}), using ScriptProcessorNode").into(),
+                );
+                CaptureNode::ScriptProcessor(create_script_processor(&context, &sample_buffer)?)
             }
-        }) as Box<dyn FnMut(_)>);
-
-        processor.set_onaudioprocess(Some(onaudioprocess.as_ref().unchecked_ref()));
-        onaudioprocess.forget();
+        };
 
-        // Connect the audio graph
-        source
-            .connect_with_audio_node(&processor)
-            .map_err(|_| "Failed to connect source to processor")?;
-        processor
-            .connect_with_audio_node(&context.destination())
-            .map_err(|_| "Failed to connect processor to destination")?;
+        let node_ref: &AudioNode = match &node {
+            CaptureNode::Worklet(node) => node,
+            CaptureNode::ScriptProcessor(node) => node,
+        };
+        connect(&context, &source, node_ref)?;
 
+        let label = audio_track(&stream)
+            .map(|track| track.label())
+            .unwrap_or_default();
         Ok(Self {
-            _context: context,
-            _stream: stream,
-            _processor: processor,
+            context,
+            stream,
+            label,
+            _node: node,
             sample_buffer,
         })
     }
 
-    pub fn read_samples(&self, max_samples: usize) -> Vec<f32> {
+    /// Id of the device actually opened
+    pub fn device_id(&self) -> Option<String> {
+        audio_track(&self.stream)?.get_settings().get_device_id()
+    }
+}
+
+impl SignalSource for WebAudioCapture {
+    fn read_samples(&mut self, max_samples: usize) -> Vec<f32> {
         let mut buffer = self.sample_buffer.borrow_mut();
         let take_count = max_samples.min(buffer.len());
         let samples: Vec<f32> = buffer.drain(0..take_count).collect();
         samples
     }
+
+    fn sample_rate(&self) -> u32 {
+        self.context.sample_rate() as u32
+    }
+
+    /// The worklet and script processor take the first channel only
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    /// Failed once the track ends, as when the device is unplugged or the
+    /// permission revoked
+    fn status(&self) -> SourceStatus {
+        match audio_track(&self.stream) {
+            Some(track) if track.ready_state() == MediaStreamTrackState::Live => {
+                SourceStatus::Running
+            }
+            _ => SourceStatus::Failed(AudioError::DeviceUnavailable),
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.label
+    }
+}
+
+fn audio_track(stream: &MediaStream) -> Option<MediaStreamTrack> {
+    stream.get_audio_tracks().get(0).dyn_into().ok()
+}
+
+impl Drop for WebAudioCapture {
+    /// Release the device; otherwise the browser keeps its recording
+    /// indicator on until the page closes
+    fn drop(&mut self) {
+        for track in self.stream.get_tracks().iter() {
+            if let Ok(track) = track.dyn_into::<MediaStreamTrack>() {
+                track.stop();
+            }
+        }
+        let _ = self.context.close();
+    }
+}
+
+/// Load the capture processor from a blob URL and create its node
+async fn create_worklet(
+    context: &AudioContext,
+    sample_buffer: &Rc<RefCell<Vec<f32>>>,
+    ring: Option<&SampleRing>,
+) -> Result<AudioWorkletNode, AudioError> {
+    let worklet = context
+        .audio_worklet()
+        .map_err(|_| backend("AudioContext has no audioWorklet"))?;
+
+    let options = BlobPropertyBag::new();
+    options.set_type("application/javascript");
+    let blob = Blob::new_with_str_sequence_and_options(
+        &js_sys::Array::of1(&JsValue::from_str(PROCESSOR_SOURCE)),
+        &options,
+    )
+    .map_err(|_| backend("Failed to create processor blob"))?;
+    let url = Url::create_object_url_with_blob(&blob)
+        .map_err(|_| backend("Failed to create processor URL"))?;
+
+    let loaded = match worklet.add_module(&url) {
+        Ok(promise) => JsFuture::from(promise).await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+    let _ = Url::revoke_object_url(&url);
+    loaded.map_err(|_| backend("Failed to load capture processor"))?;
+
+    let options = AudioWorkletNodeOptions::new();
+    if let Some(ring) = ring {
+        let buffers = js_sys::Object::new();
+        let _ = js_sys::Reflect::set(&buffers, &"indices".into(), &ring.indices_buffer());
+        let _ = js_sys::Reflect::set(&buffers, &"samples".into(), &ring.samples_buffer());
+        options.set_processor_options(Some(&buffers));
+    }
+    let node = AudioWorkletNode::new_with_options(context, PROCESSOR_NAME, &options)
+        .map_err(|_| backend("Failed to create AudioWorkletNode"))?;
+    if ring.is_some() {
+        return Ok(node);
+    }
+    let port = node
+        .port()
+        .map_err(|_| backend("AudioWorkletNode has no port"))?;
+
+    let sample_buffer = sample_buffer.clone();
+    let onmessage = Closure::wrap(Box::new(move |event: MessageEvent| {
+        let block: js_sys::Float32Array = event.data().unchecked_into();
+        push_samples(&mut sample_buffer.borrow_mut(), &block.to_vec());
+    }) as Box<dyn FnMut(_)>);
+
+    port.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+    onmessage.forget();
+
+    Ok(node)
+}
+
+fn create_script_processor(
+    context: &AudioContext,
+    sample_buffer: &Rc<RefCell<Vec<f32>>>,
+) -> Result<ScriptProcessorNode, AudioError> {
+    let buffer_size = 4096;
+    let processor = context
+        .create_script_processor_with_buffer_size_and_number_of_input_channels_and_number_of_output_channels(
+            buffer_size,
+            1,
+            1,
+        )
+        .map_err(|_| backend("Failed to create script processor"))?;
+
+    // Set up audio processing callback
+    let sample_buffer = sample_buffer.clone();
+    let onaudioprocess = Closure::wrap(Box::new(move |event: web_sys::AudioProcessingEvent| {
+        let input_buffer = event.input_buffer().unwrap();
+        let input_data = input_buffer.get_channel_data(0).unwrap();
+        push_samples(&mut sample_buffer.borrow_mut(), &input_data);
+    }) as Box<dyn FnMut(_)>);
+
+    processor.set_onaudioprocess(Some(onaudioprocess.as_ref().unchecked_ref()));
+    onaudioprocess.forget();
+
+    Ok(processor)
+}
+
+/// Wire source -> capture node -> destination; the node outputs silence, but
+/// browsers only run nodes that reach the destination
+fn connect(
+    context: &AudioContext,
+    source: &MediaStreamAudioSourceNode,
+    node: &AudioNode,
+) -> Result<(), AudioError> {
+    source
+        .connect_with_audio_node(node)
+        .map_err(|_| backend("Failed to connect source to processor"))?;
+    node.connect_with_audio_node(&context.destination())
+        .map_err(|_| backend("Failed to connect processor to destination"))?;
+    Ok(())
+}
+
+/// A browser call that failed for no reason worth telling apart
+fn backend(message: &str) -> AudioError {
+    AudioError::Backend(message.to_string())
+}
+
+/// Why `getUserMedia` refused, from the DOMException it rejected with
+fn media_error(error: &JsValue, device_id: Option<&str>) -> AudioError {
+    let (name, message) = match error.dyn_ref::<web_sys::DomException>() {
+        Some(exception) => (exception.name(), exception.message()),
+        None => (String::new(), format!("{error:?}")),
+    };
+    match (name.as_str(), device_id) {
+        ("NotAllowedError" | "SecurityError", _) => AudioError::PermissionDenied(message),
+        ("NotFoundError" | "OverconstrainedError", Some(device_id)) => {
+            AudioError::DeviceNotFound(device_id.to_string())
+        }
+        ("NotFoundError", None) => AudioError::NoDevice,
+        ("NotReadableError" | "AbortError", _) => AudioError::DeviceUnavailable,
+        ("OverconstrainedError", None) => AudioError::UnsupportedFormat(message),
+        _ => AudioError::Backend(format!("Failed to get user media: {message}")),
+    }
+}
+
+fn push_samples(buffer: &mut Vec<f32>, samples: &[f32]) {
+    buffer.extend_from_slice(samples);
+
+    // Keep buffer size reasonable
+    if buffer.len() > MAX_BUFFERED_SAMPLES {
+        let len = buffer.len();
+        buffer.drain(0..len - MAX_BUFFERED_SAMPLES);
+    }
 }
@@ -0,0 +1,68 @@
+use ozeecubed_core::audio::AudioError;
+use wasm_bindgen::JsValue;
+
+/// Why a renderer couldn't be set up on the canvas. `Unsupported` is the
+/// one to fall back from; the rest point at the page or the driver.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RenderError {
+    #[error("No document to render into")]
+    NoDocument,
+    #[error("Canvas not found: {0}")]
+    CanvasNotFound(String),
+    #[error("Element is not a canvas: {0}")]
+    NotACanvas(String),
+    /// The browser, or this canvas, doesn't offer the API
+    #[error("{0} is not available")]
+    Unsupported(&'static str),
+    #[error("Failed to create {0}")]
+    Resource(&'static str),
+    #[error("Shader failed to compile: {0}")]
+    Shader(String),
+    #[error("Shader program failed to link: {0}")]
+    Link(String),
+    /// The GPU device or surface was refused
+    #[error("{0}")]
+    Device(String),
+}
+
+impl RenderError {
+    /// The `name` the error carries in JavaScript
+    fn name(&self) -> &'static str {
+        match self {
+            RenderError::NoDocument => "NoDocument",
+            RenderError::CanvasNotFound(_) => "CanvasNotFound",
+            RenderError::NotACanvas(_) => "NotACanvas",
+            RenderError::Unsupported(_) => "Unsupported",
+            RenderError::Resource(_) => "Resource",
+            RenderError::Shader(_) => "Shader",
+            RenderError::Link(_) => "Link",
+            RenderError::Device(_) => "Device",
+        }
+    }
+}
+
+impl From<RenderError> for JsValue {
+    fn from(error: RenderError) -> Self {
+        js_error(error.name(), &error.to_string())
+    }
+}
+
+/// An `AudioError` as a JavaScript `Error` whose `name` is its variant, so
+/// a page can tell a denied permission from a missing device
+pub fn audio_error(error: &AudioError) -> JsValue {
+    let name = match error {
+        AudioError::NoDevice => "NoDevice",
+        AudioError::DeviceNotFound(_) => "DeviceNotFound",
+        AudioError::DeviceUnavailable => "DeviceUnavailable",
+        AudioError::PermissionDenied(_) => "PermissionDenied",
+        AudioError::UnsupportedFormat(_) => "UnsupportedFormat",
+        AudioError::Backend(_) => "AudioError",
+    };
+    js_error(name, &error.to_string())
+}
+
+fn js_error(name: &str, message: &str) -> JsValue {
+    let error = js_sys::Error::new(message);
+    error.set_name(name);
+    error.into()
+}
@@ -0,0 +1,92 @@
+use ozeecubed_core::recording::{Recording, ReplayPlayer};
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{AudioBuffer, OfflineAudioContext};
+
+/// A decoded audio file played back in real time into the scope, in place
+/// of the live input. Paces the shared `ReplayPlayer` by the page clock.
+pub struct FilePlayer {
+    player: ReplayPlayer,
+    /// `performance.now()` at the previous advance while playing
+    last_time: Option<f64>,
+}
+
+impl FilePlayer {
+    /// Decode any format the browser supports, resampled to `sample_rate`
+    /// so it matches the scope's timebase. Only the first channel is kept.
+    pub async fn decode(data: &js_sys::ArrayBuffer, sample_rate: u32) -> Result<Self, String> {
+        let context = OfflineAudioContext::new_with_number_of_channels_and_length_and_sample_rate(
+            1,
+            1,
+            sample_rate as f32,
+        )
+        .map_err(|_| "Failed to create OfflineAudioContext")?;
+        let promise = context
+            .decode_audio_data(data)
+            .map_err(|_| "Failed to start decoding")?;
+        let buffer: AudioBuffer = JsFuture::from(promise)
+            .await
+            .map_err(|_| "Unsupported or corrupt audio file")?
+            .dyn_into()
+            .map_err(|_| "Decoding did not produce an AudioBuffer")?;
+        let samples = buffer
+            .get_channel_data(0)
+            .map_err(|_| "Audio file has no channels")?;
+
+        let mut player = ReplayPlayer::new(Recording::new(samples, sample_rate));
+        player.pause();
+        Ok(Self {
+            player,
+            last_time: None,
+        })
+    }
+
+    /// Samples played since the previous call, given the current time in
+    /// milliseconds. Playback pauses by itself at the end of the file.
+    pub fn advance(&mut self, now_ms: f64) -> Vec<f32> {
+        if !self.player.is_playing() {
+            self.last_time = None;
+            return Vec::new();
+        }
+
+        let elapsed = now_ms - self.last_time.unwrap_or(now_ms);
+        self.last_time = Some(now_ms);
+        let moved = self.player.advance((elapsed / 1000.0) as f32);
+        self.player.window(moved).to_vec()
+    }
+
+    pub fn play(&mut self) {
+        if !self.player.is_playing() {
+            self.player.toggle_playing();
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.player.pause();
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.player.is_playing()
+    }
+
+    /// Move the playhead, clamped to the file
+    pub fn seek(&mut self, seconds: f64) {
+        let duration = self.duration_seconds();
+        if duration > 0.0 {
+            self.player.seek((seconds / duration) as f32);
+        }
+    }
+
+    pub fn position_seconds(&self) -> f64 {
+        self.player.position_seconds() as f64
+    }
+
+    pub fn duration_seconds(&self) -> f64 {
+        self.player.recording().duration_seconds() as f64
+    }
+
+    /// Up to `count` samples leading up to the playhead
+    pub fn recent(&self, count: usize) -> &[f32] {
+        self.player.window(count)
+    }
+}
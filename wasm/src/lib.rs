@@ -1,12 +1,54 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
-use ozeecubed_core::oscilloscope::{TriggerSettings, WaveformData};
+use ozeecubed_core::audio::calibration::{rms_dbfs, Calibration, REFERENCE_SPL_RANGE};
+use ozeecubed_core::display::{
+    color_from_hex, color_to_hex, trigger_level_marker, trigger_point_marker, DisplayTheme,
+    Graticule, IntensityMap, LineStyle, Palette, PersistenceColormap, PersistenceDecay,
+    PersistenceMode, Segment, TraceColors, MAX_PERSISTENCE_FRAMES, MAX_TRACE_HISTORY,
+};
+use ozeecubed_core::oscilloscope::trigger::{TriggerEdge, TriggerMode};
+use ozeecubed_core::oscilloscope::{
+    AutoSweep, AutoTrigger, Decimation, EnvelopeFollower, EnvelopeMode, SpectrogramHistory,
+    SpectrumAnalyzer, SpectrumOverlap, SpectrumScale, TriggerSettings, WaveformData,
+};
+use ozeecubed_core::recording::Recording;
+use ozeecubed_core::source::SignalSource;
+
+/// Bounds for the persistence depth, matching the desktop app
+const MIN_PERSISTENCE_FRAMES: usize = 1;
+const DEFAULT_PERSISTENCE_FRAMES: usize = 10;
+
+/// Canvas width assumed for decimation before a renderer exists
+const DEFAULT_DISPLAY_COLUMNS: usize = 1024;
+
+/// Longest capture `start_recording` keeps, bounding its memory use
+const MAX_RECORDING_SECONDS: usize = 300;
 
 mod audio;
+mod error;
+mod file_player;
+mod render_loop;
+mod ring;
+mod touch;
+mod url_state;
 mod webgl;
+#[cfg(all(feature = "webgpu", target_arch = "wasm32"))]
+mod webgpu;
+mod worker;
 
 use audio::WebAudioCapture;
+use file_player::FilePlayer;
+use render_loop::RenderLoop;
+use touch::{Gesture, PinchAxis, TouchTracker};
+use url_state::ScopeConfig;
 use webgl::WebGLRenderer;
+#[cfg(all(feature = "webgpu", target_arch = "wasm32"))]
+use webgpu::WebGpuRenderer;
+use worker::WorkerPipeline;
 
 #[wasm_bindgen(start)]
 pub async fn main() -> Result<(), JsValue> {
@@ -16,13 +58,150 @@ pub async fn main() -> Result<(), JsValue> {
     Ok(())
 }
 
+/// Readouts returned by `OzScopeWasm::get_measurements`; each is `null` in
+/// JavaScript when the current signal can't be measured
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Measurements {
+    /// Hz
+    frequency: Option<f32>,
+    /// Volts
+    peak_to_peak: Option<f32>,
+    /// Volts
+    rms: Option<f32>,
+    /// Percent of the period spent above zero
+    duty_cycle: Option<f32>,
+}
+
+impl Measurements {
+    fn of(waveform: &WaveformData) -> Self {
+        Self {
+            frequency: waveform.calculate_frequency(),
+            peak_to_peak: waveform.calculate_peak_to_peak(),
+            rms: waveform.calculate_rms(),
+            duty_cycle: waveform.calculate_duty_cycle(),
+        }
+    }
+
+    fn to_js(&self) -> Result<JsValue, JsValue> {
+        // JSON-compatible so unmeasurable values come through as null
+        self.serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(JsValue::from)
+    }
+}
+
+/// What gets drawn: the triggered trace, x in 0..1 and y in divisions, and
+/// where the trigger fired
+#[derive(Default)]
+struct Frame {
+    points: Vec<(f32, f32)>,
+    trigger_x: Option<f32>,
+    /// Envelope trace in the same coordinates, empty while the follower is off
+    envelope: Vec<(f32, f32)>,
+}
+
+impl Frame {
+    /// Rebuilt from the capture, decimated for a canvas `columns` pixels
+    /// wide, in this frame's own buffers
+    fn update(
+        &mut self,
+        waveform: &WaveformData,
+        trigger_settings: &TriggerSettings,
+        envelope: &EnvelopeFollower,
+        columns: usize,
+    ) {
+        let range = waveform.display_range(trigger_settings);
+        waveform.display_decimated_into(range.clone(), columns, &mut self.points);
+        self.trigger_x = waveform.trigger_position(trigger_settings);
+        if envelope.is_enabled() {
+            waveform.display_follower_into(range, envelope, columns, &mut self.envelope);
+        } else {
+            self.envelope.clear();
+        }
+    }
+}
+
+/// Whether the last sweep stays on screen for a capture `arrived` samples
+/// on from the last, the trigger still waiting for an edge: until the auto
+/// timeout, or for good in normal mode
+fn waiting_for_trigger(
+    auto: &mut AutoTrigger,
+    waveform: &WaveformData,
+    trigger_settings: &TriggerSettings,
+    arrived: usize,
+) -> bool {
+    let triggered = waveform.trigger_index(trigger_settings).is_some();
+    trigger_settings.enabled
+        && auto.sweep(trigger_settings.mode, triggered, arrived) == AutoSweep::Waiting
+}
+
+/// A time in µs, ms or s, for the page's readouts to match the display
+#[wasm_bindgen]
+pub fn format_time(seconds: f32) -> String {
+    ozeecubed_core::display::format_time(seconds)
+}
+
+/// A voltage in µV, mV or V, like `format_time`
+#[wasm_bindgen]
+pub fn format_volts(volts: f32) -> String {
+    ozeecubed_core::display::format_volts(volts)
+}
+
 #[wasm_bindgen]
 pub struct OzScopeWasm {
     waveform: WaveformData,
     trigger_settings: TriggerSettings,
+    envelope: EnvelopeFollower,
     audio_capture: Option<WebAudioCapture>,
-    renderer: Option<WebGLRenderer>,
-    audio_buffer: Vec<f32>,
+    /// Takes the place of the live input while loaded
+    file_player: Option<FilePlayer>,
+    /// Moves buffering, triggering and measuring off the main thread
+    pipeline: Option<WorkerPipeline>,
+    /// Readouts from the last worker frame
+    worker_measurements: Option<JsValue>,
+    renderer: Option<Renderer>,
+    frame: Frame,
+    /// Keeps `frame` while the trigger waits for an edge
+    auto: AutoTrigger,
+    theme: DisplayTheme,
+    trace_colors: TraceColors,
+    graticule: Graticule,
+    /// Stopped scopes keep showing the last capture
+    running: bool,
+    /// Samples captured since `start_recording`, while recording
+    recording: Option<Vec<f32>>,
+    /// Mel spectrogram of the input, fed only while `mel_enabled`
+    mel: SpectrumAnalyzer,
+    mel_enabled: bool,
+    /// Mel frames produced so far
+    mel_frames: u32,
+    /// Every mel frame, for `export_spectrogram`
+    spectrogram: SpectrogramHistory,
+    /// Calibration of the current input, restored by the page per device
+    calibration: Option<Calibration>,
+    /// Show levels in dB SPL rather than dBFS, once calibrated
+    show_spl: bool,
+    /// Recent traces with when they were drawn, in page milliseconds
+    history: VecDeque<(f64, Vec<(f32, f32)>)>,
+    persistence_enabled: bool,
+    persistence_frames: usize,
+    persistence_mode: PersistenceMode,
+    persistence_decay: PersistenceDecay,
+    persistence_colormap: PersistenceColormap,
+    /// Kept alongside `history` so switching modes is seamless
+    intensity: IntensityMap,
+    /// Trace offset in screen widths; positive moves the trace right
+    horizontal_position: f32,
+    touch: TouchTracker,
+    /// Set when the scope drives itself, see `start`
+    render_loop: Option<RenderLoop>,
+}
+
+impl Drop for OzScopeWasm {
+    /// Freeing a self-driven scope ends its loop before the next frame
+    fn drop(&mut self) {
+        self.stop();
+    }
 }
 
 impl Default for OzScopeWasm {
@@ -40,74 +219,1282 @@ impl OzScopeWasm {
         Self {
             waveform: WaveformData::new(48000),
             trigger_settings: TriggerSettings::default(),
+            envelope: EnvelopeFollower::default(),
             audio_capture: None,
+            file_player: None,
+            pipeline: None,
+            worker_measurements: None,
             renderer: None,
-            audio_buffer: Vec::new(),
+            frame: Frame::default(),
+            auto: AutoTrigger::new(48000),
+            theme: DisplayTheme::default(),
+            trace_colors: TraceColors::default(),
+            graticule: Graticule::default(),
+            running: true,
+            recording: None,
+            mel: {
+                let mut mel = SpectrumAnalyzer::new();
+                mel.set_scale(SpectrumScale::Mel, 48000);
+                mel
+            },
+            mel_enabled: false,
+            mel_frames: 0,
+            spectrogram: SpectrogramHistory::new(),
+            calibration: None,
+            show_spl: true,
+            history: VecDeque::new(),
+            persistence_enabled: true,
+            persistence_frames: DEFAULT_PERSISTENCE_FRAMES,
+            persistence_mode: PersistenceMode::default(),
+            persistence_decay: PersistenceDecay::default(),
+            persistence_colormap: PersistenceColormap::default(),
+            intensity: {
+                let mut intensity = IntensityMap::default();
+                intensity.set_decay_frames(DEFAULT_PERSISTENCE_FRAMES);
+                intensity
+            },
+            horizontal_position: 0.0,
+            touch: TouchTracker::new(),
+            render_loop: None,
+        }
+    }
+
+    /// Create a scope that runs by itself: render to `canvas_id`, capture the
+    /// default input, and update and render every animation frame. A page
+    /// needs nothing more than `await OzScopeWasm.start('scope')`; the scope
+    /// is returned for further control.
+    ///
+    /// Scopes made with `new` are instead driven by calling `update()` and
+    /// `render()` from the page's own loop.
+    pub async fn start(canvas_id: &str) -> Result<JsValue, JsValue> {
+        let mut scope = Self::new();
+        scope.init_gpu_renderer(canvas_id).await?;
+        // Without an input the scope still runs, showing an empty display
+        let _ = scope.init_audio().await;
+
+        let render_loop = RenderLoop::new();
+        scope.render_loop = Some(render_loop.clone());
+        let scope = JsValue::from(scope);
+        render_loop.start(scope.clone().unchecked_into())?;
+        Ok(scope)
+    }
+
+    /// End the loop installed by `start`; the scope can still be driven
+    /// manually afterwards
+    pub fn stop(&mut self) {
+        if let Some(render_loop) = self.render_loop.take() {
+            render_loop.stop();
         }
     }
 
+    /// Whether a loop installed by `start` is running
+    pub fn is_looping(&self) -> bool {
+        self.render_loop.as_ref().is_some_and(RenderLoop::is_active)
+    }
+
     pub async fn init_audio(&mut self) -> Result<(), JsValue> {
-        match WebAudioCapture::new().await {
+        self.start_audio(None).await
+    }
+
+    /// Capture the default input through a Web Worker: the AudioWorklet
+    /// writes into a SharedArrayBuffer ring, and the worker at `worker_url`
+    /// (`worker.js`) triggers and measures, leaving this thread only to
+    /// draw. The page must be cross-origin isolated; on error nothing has
+    /// changed, and `init_audio` is the fallback.
+    ///
+    /// Loaded files still play through this thread.
+    pub async fn init_worker_pipeline(&mut self, worker_url: &str) -> Result<(), JsValue> {
+        let pipeline = WorkerPipeline::new(worker_url).map_err(|e| JsValue::from_str(&e))?;
+        let capture = WebAudioCapture::new(None, Some(pipeline.ring()))
+            .await
+            .map_err(|e| error::audio_error(&e))?;
+        pipeline
+            .start(capture.sample_rate())
+            .map_err(|e| JsValue::from_str(&e))?;
+        web_sys::console::log_1(&"Audio capture initialized with worker pipeline".into());
+        self.set_sample_rate(capture.sample_rate());
+        self.audio_capture = Some(capture);
+        self.pipeline = Some(pipeline);
+        Ok(())
+    }
+
+    pub fn uses_worker_pipeline(&self) -> bool {
+        self.pipeline.is_some()
+    }
+
+    /// Capture from a specific input, by a `deviceId` from `list_audio_inputs`
+    pub async fn init_audio_with_device(&mut self, device_id: &str) -> Result<(), JsValue> {
+        self.start_audio(Some(device_id)).await
+    }
+
+    /// Switch to another input. The current one keeps running until the new
+    /// one has opened, and stays in use if it fails to.
+    pub async fn set_audio_device(&mut self, device_id: &str) -> Result<(), JsValue> {
+        self.start_audio(Some(device_id)).await?;
+        self.waveform.samples.clear();
+        Ok(())
+    }
+
+    /// `deviceId` of the input being captured, if any
+    pub fn audio_device_id(&self) -> Option<String> {
+        self.audio_capture.as_ref()?.device_id()
+    }
+
+    /// Audio inputs as `[{ deviceId, label }]`. Labels are empty until
+    /// microphone access has been granted.
+    pub async fn list_audio_inputs() -> Result<JsValue, JsValue> {
+        let inputs = audio::list_audio_inputs()
+            .await
+            .map_err(|e| error::audio_error(&e))?;
+        serde_wasm_bindgen::to_value(&inputs).map_err(JsValue::from)
+    }
+
+    pub fn init_renderer(&mut self, canvas_id: &str) -> Result<(), JsValue> {
+        let mut renderer = WebGLRenderer::new(canvas_id)?;
+        renderer.set_graticule(&self.graticule);
+        let version = if renderer.is_webgl2() {
+            "WebGL2"
+        } else {
+            "WebGL"
+        };
+        web_sys::console::log_1(&format!("{version} renderer initialized").into());
+        self.renderer = Some(Renderer::WebGl(renderer));
+        Ok(())
+    }
+
+    /// Render with WebGPU where the browser supports it and the build has the
+    /// `webgpu` feature, otherwise fall back to WebGL
+    pub async fn init_gpu_renderer(&mut self, canvas_id: &str) -> Result<(), JsValue> {
+        #[cfg(all(feature = "webgpu", target_arch = "wasm32"))]
+        match WebGpuRenderer::new(canvas_id).await {
+            Ok(mut renderer) => {
+                renderer.set_graticule(&self.graticule);
+                web_sys::console::log_1(&"WebGPU renderer initialized".into());
+                self.renderer = Some(Renderer::WebGpu(Box::new(renderer)));
+                return Ok(());
+            }
+            Err(e) => {
+                web_sys::console::warn_1(&format!("WebGPU unavailable ({e}), using WebGL").into());
+            }
+        }
+
+        self.init_renderer(canvas_id)
+    }
+
+    pub fn update(&mut self) {
+        if self.file_player.is_none() {
+            let state = self.export_state();
+            let columns = self.display_columns();
+            if let Some(ref mut pipeline) = self.pipeline {
+                pipeline.configure(state, columns);
+                // The worker does the processing; samples are only read
+                // here to be recorded
+                let new_samples = pipeline.drain();
+                let received = pipeline.take_frame();
+                if self.running {
+                    self.record(&new_samples);
+                    self.analyze_mel(&new_samples);
+                    if let Some(received) = received {
+                        self.frame = received.frame;
+                        self.worker_measurements = Some(received.measurements);
+                        self.push_history();
+                    }
+                }
+                return;
+            }
+        }
+
+        // Update audio buffer
+        let new_samples = if let Some(ref mut file_player) = self.file_player {
+            Some(file_player.advance(now_ms()))
+        } else {
+            self.audio_capture
+                .as_mut()
+                .map(|capture| capture.read_samples(usize::MAX))
+        };
+        let arrived = new_samples.as_ref().map_or(0, Vec::len);
+        if let Some(new_samples) = new_samples {
+            if !self.running {
+                // Drop what arrived while stopped so Run resumes at live input
+                return;
+            }
+
+            self.record(&new_samples);
+            self.analyze_mel(&new_samples);
+            self.waveform.append_samples(&new_samples);
+        }
+
+        if self.running {
+            if !waiting_for_trigger(
+                &mut self.auto,
+                &self.waveform,
+                &self.trigger_settings,
+                arrived,
+            ) {
+                let columns = self.display_columns();
+                self.frame.update(
+                    &self.waveform,
+                    &self.trigger_settings,
+                    &self.envelope,
+                    columns,
+                );
+            }
+            self.push_history();
+        }
+    }
+
+    pub fn render(&mut self) {
+        if let Some(ref mut renderer) = self.renderer {
+            let mut markers = trigger_level_marker(
+                &self.trigger_settings,
+                self.waveform.volts_per_division,
+                self.graticule.vertical_divisions,
+            );
+            if let Some(x) = self.frame.trigger_x {
+                markers.extend(trigger_point_marker(x + self.horizontal_position));
+            }
+            // The envelope goes with the markers to be drawn in the accent color
+            let divisions = self.graticule.vertical_divisions.max(1) as f32;
+            let to_screen =
+                |(x, y): (f32, f32)| (x + self.horizontal_position, 0.5 - y / divisions);
+            markers.extend(
+                self.frame
+                    .envelope
+                    .windows(2)
+                    .map(|pair| (to_screen(pair[0]), to_screen(pair[1]))),
+            );
+            let palette = self.trace_colors.apply(self.theme.palette());
+            let x_offset = self.horizontal_position;
+            if !self.envelope.shows_raw() {
+                renderer.render(
+                    std::iter::empty(),
+                    None,
+                    None,
+                    x_offset,
+                    &markers,
+                    &palette,
+                    PersistenceColormap::Trace,
+                );
+            } else if self.persistence_enabled
+                && self
+                    .persistence_decay
+                    .outlasts_history(self.persistence_frames)
+            {
+                renderer.render(
+                    std::iter::empty(),
+                    None,
+                    Some((&self.intensity, self.persistence_mode)),
+                    x_offset,
+                    &markers,
+                    &palette,
+                    self.persistence_colormap,
+                );
+            } else if self.persistence_enabled && !self.history.is_empty() {
+                let intensity =
+                    (self.persistence_mode == PersistenceMode::Graded).then_some(&self.intensity);
+                renderer.render(
+                    history_alphas(&self.history, self.persistence_decay),
+                    intensity,
+                    None,
+                    x_offset,
+                    &markers,
+                    &palette,
+                    self.persistence_colormap,
+                );
+            } else {
+                renderer.render(
+                    std::iter::once((self.frame.points.as_slice(), 1.0)),
+                    None,
+                    None,
+                    x_offset,
+                    &markers,
+                    &palette,
+                    PersistenceColormap::Trace,
+                );
+            }
+        }
+    }
+
+    /// Current readouts as `{ frequency, peakToPeak, rms, dutyCycle }`
+    pub fn get_measurements(&self) -> Result<JsValue, JsValue> {
+        match (&self.pipeline, &self.worker_measurements) {
+            (Some(_), Some(measurements)) if self.file_player.is_none() => Ok(measurements.clone()),
+            _ => Measurements::of(&self.waveform).to_js(),
+        }
+    }
+
+    /// RMS level of the input, in dB SPL while calibrated and showing dB
+    /// SPL, otherwise in dBFS. `undefined` when it can't be measured.
+    pub fn level(&self) -> Option<f32> {
+        let dbfs = rms_dbfs(self.current_rms()?);
+        Some(match self.calibration {
+            Some(calibration) if self.show_spl => calibration.spl(dbfs),
+            _ => dbfs,
+        })
+    }
+
+    /// Whether `level` is in dB SPL
+    pub fn level_is_spl(&self) -> bool {
+        self.calibration.is_some() && self.show_spl
+    }
+
+    /// Calibrate against a reference tone of `reference_spl` dB SPL (40 to
+    /// 140) now playing into the input, and switch to dB SPL. Returns the
+    /// offset for the page to save against the input, or `undefined` when
+    /// the input is silent.
+    pub fn calibrate(&mut self, reference_spl: f32) -> Option<f32> {
+        let reference_spl = reference_spl.clamp(REFERENCE_SPL_RANGE.0, REFERENCE_SPL_RANGE.1);
+        let calibration = Calibration::from_reference(self.current_rms()?, reference_spl)?;
+        self.calibration = Some(calibration);
+        self.show_spl = true;
+        Some(calibration.offset)
+    }
+
+    /// Restore an offset returned by `calibrate`; `undefined` clears the
+    /// calibration
+    pub fn set_calibration_offset(&mut self, offset: Option<f32>) {
+        self.calibration = offset
+            .filter(|offset| offset.is_finite())
+            .map(|offset| Calibration { offset });
+    }
+
+    pub fn calibration_offset(&self) -> Option<f32> {
+        self.calibration.map(|calibration| calibration.offset)
+    }
+
+    pub fn set_show_spl(&mut self, show: bool) {
+        self.show_spl = show;
+    }
+
+    pub fn show_spl(&self) -> bool {
+        self.show_spl
+    }
+
+    /// Apply a touch event from the canvas: one finger drags the trigger
+    /// level, a horizontal pinch scales time/div, a vertical pinch volts/div,
+    /// and moving two fingers sideways pans the trace
+    pub fn handle_touch(&mut self, event: &web_sys::TouchEvent) {
+        match self.touch.handle_event(event) {
+            Some(Gesture::Drag(dy)) if self.trigger_settings.enabled => {
+                let divisions = self.graticule.vertical_divisions.max(1) as f32;
+                let dv = dy * divisions * self.waveform.volts_per_division;
+                self.trigger_settings
+                    .set_level(self.trigger_settings.level - dv);
+            }
+            Some(Gesture::Pinch {
+                axis,
+                scale,
+                anchor,
+                pan,
+            }) => {
+                self.pan(pan);
+                // Spreading the fingers zooms in
+                match axis {
+                    PinchAxis::Horizontal => self.zoom_time(1.0 / scale, anchor),
+                    PinchAxis::Vertical => {
+                        self.waveform.volts_per_division =
+                            (self.waveform.volts_per_division / scale).max(0.01)
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Move the trace back to its untouched horizontal position
+    pub fn reset_position(&mut self) {
+        self.horizontal_position = 0.0;
+    }
+
+    pub fn horizontal_position(&self) -> f32 {
+        self.horizontal_position
+    }
+
+    /// The current setup encoded for a URL fragment (without the `#`)
+    pub fn export_state(&self) -> String {
+        self.config().to_fragment()
+    }
+
+    /// The current setup as an object, in the same shape as
+    /// `import_settings` takes, e.g. for saving as a preset
+    pub fn export_settings(&self) -> Result<JsValue, JsValue> {
+        self.config()
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(JsValue::from)
+    }
+
+    /// Restore a setup produced by `export_state`. Settings the fragment
+    /// doesn't mention are left alone; on a malformed value nothing changes.
+    pub fn import_state(&mut self, fragment: &str) -> Result<(), JsValue> {
+        let config = ScopeConfig::from_fragment(fragment).map_err(|e| JsValue::from_str(&e))?;
+        self.apply_config(config);
+        Ok(())
+    }
+
+    /// Restore a setup produced by `export_settings`, like `import_state`
+    pub fn import_settings(&mut self, settings: JsValue) -> Result<(), JsValue> {
+        let config: ScopeConfig = serde_wasm_bindgen::from_value(settings)?;
+        self.apply_config(config);
+        Ok(())
+    }
+
+    /// Decode an audio file (e.g. from a file input's `arrayBuffer()`) and
+    /// show it instead of the live input, paused at the start
+    pub async fn load_audio_file(&mut self, data: js_sys::ArrayBuffer) -> Result<(), JsValue> {
+        let player = FilePlayer::decode(&data, self.waveform.sample_rate)
+            .await
+            .map_err(|e| JsValue::from_str(&e))?;
+        self.file_player = Some(player);
+        self.history.clear();
+        self.intensity.clear();
+        self.show_file_position();
+        Ok(())
+    }
+
+    /// Drop the loaded file and go back to the live input
+    pub fn close_audio_file(&mut self) {
+        self.file_player = None;
+        self.waveform.samples.clear();
+    }
+
+    pub fn has_audio_file(&self) -> bool {
+        self.file_player.is_some()
+    }
+
+    pub fn play_file(&mut self) {
+        if let Some(ref mut file_player) = self.file_player {
+            file_player.play();
+        }
+    }
+
+    pub fn pause_file(&mut self) {
+        if let Some(ref mut file_player) = self.file_player {
+            file_player.pause();
+        }
+    }
+
+    pub fn file_playing(&self) -> bool {
+        self.file_player
+            .as_ref()
+            .is_some_and(FilePlayer::is_playing)
+    }
+
+    /// Move the playhead; the display jumps to the signal just before it
+    pub fn seek_file(&mut self, seconds: f64) {
+        if let Some(ref mut file_player) = self.file_player {
+            file_player.seek(seconds);
+            self.show_file_position();
+        }
+    }
+
+    /// Playhead in seconds (0 with no file loaded)
+    pub fn file_position(&self) -> f64 {
+        self.file_player
+            .as_ref()
+            .map_or(0.0, FilePlayer::position_seconds)
+    }
+
+    /// Length of the loaded file in seconds (0 with no file loaded)
+    pub fn file_duration(&self) -> f64 {
+        self.file_player
+            .as_ref()
+            .map_or(0.0, FilePlayer::duration_seconds)
+    }
+
+    /// Start saving everything the scope acquires, live input or file, up to
+    /// five minutes. Restarting discards the previous take.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Vec::new());
+    }
+
+    /// Stop recording and return the take as a WAV file
+    pub fn stop_recording(&mut self) -> Result<js_sys::Uint8Array, JsValue> {
+        let samples = self
+            .recording
+            .take()
+            .ok_or_else(|| JsValue::from_str("Not recording"))?;
+        let wav = Recording::new(samples, self.waveform.sample_rate)
+            .to_wav_bytes()
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(js_sys::Uint8Array::from(wav.as_slice()))
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Length of the current take in seconds
+    pub fn recording_seconds(&self) -> f64 {
+        self.recording.as_ref().map_or(0.0, |samples| {
+            samples.len() as f64 / self.waveform.sample_rate as f64
+        })
+    }
+
+    /// Compute mel spectrogram frames from the input, for `get_mel_frame`
+    pub fn set_mel_enabled(&mut self, enabled: bool) {
+        self.mel_enabled = enabled;
+    }
+
+    pub fn mel_enabled(&self) -> bool {
+        self.mel_enabled
+    }
+
+    /// The newest mel spectrogram frame: the energy in dB of each mel band,
+    /// lowest first. `undefined` until the first frame after enabling.
+    pub fn get_mel_frame(&self) -> Option<Vec<f32>> {
+        self.mel.get_mel_frame().map(<[f32]>::to_vec)
+    }
+
+    /// The mel frames computed so far, not just the newest, as a PNG with
+    /// its frequency and time axes labeled
+    pub fn export_spectrogram(&self) -> Result<js_sys::Uint8Array, JsValue> {
+        let palette = self.trace_colors.apply(self.theme.palette());
+        let png = self
+            .spectrogram
+            .image(&palette)
+            .and_then(|image| image.to_png())
+            .map_err(|e| JsValue::from_str(&e))?;
+        Ok(js_sys::Uint8Array::from(png.as_slice()))
+    }
+
+    /// Forget the mel frames kept for `export_spectrogram`
+    pub fn clear_spectrogram(&mut self) {
+        self.spectrogram.clear();
+    }
+
+    /// Mel frames computed so far; a change means `get_mel_frame` is new
+    pub fn mel_frame_count(&self) -> u32 {
+        self.mel_frames
+    }
+
+    /// Number of mel bands, clamped to 8 to 256
+    pub fn set_mel_bands(&mut self, bands: usize) {
+        self.mel.set_mel_bands(bands);
+    }
+
+    pub fn mel_bands(&self) -> usize {
+        self.mel.mel_bands()
+    }
+
+    /// Analysis window in samples, rounded up to a power of two from 64 to
+    /// 4096
+    pub fn set_mel_window(&mut self, samples: usize) {
+        self.mel.set_fft_size(samples);
+    }
+
+    pub fn mel_window(&self) -> usize {
+        self.mel.fft_size()
+    }
+
+    /// Samples between frames, at most the window
+    pub fn set_mel_hop(&mut self, samples: usize) {
+        self.mel.set_hop_size(samples);
+    }
+
+    pub fn mel_hop(&self) -> usize {
+        self.mel.hop_size()
+    }
+
+    /// Samples between frames as the window's overlap, such as "75%", kept
+    /// as the window changes
+    pub fn set_mel_overlap(&mut self, overlap: &str) -> Result<(), JsValue> {
+        let overlap = SpectrumOverlap::from_label(overlap)
+            .ok_or_else(|| JsValue::from_str(&format!("Unsupported overlap: {overlap}")))?;
+        self.mel.set_overlap(overlap);
+        Ok(())
+    }
+
+    pub fn set_running(&mut self, running: bool) {
+        self.running = running;
+    }
+
+    pub fn toggle_running(&mut self) {
+        self.running = !self.running;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Seconds per division; must be a positive number
+    pub fn set_time_per_div(&mut self, value: f32) -> Result<(), JsValue> {
+        self.waveform.time_per_division = positive_scale("time per division", value)?;
+        Ok(())
+    }
+
+    /// Step the time per division up the 1-2-5 sequence
+    pub fn increase_time_scale(&mut self) {
+        self.waveform.increase_time_scale();
+    }
+
+    /// Step the time per division down the 1-2-5 sequence
+    pub fn decrease_time_scale(&mut self) {
+        self.waveform.decrease_time_scale();
+    }
+
+    pub fn time_per_div(&self) -> f32 {
+        self.waveform.time_per_division
+    }
+
+    /// Select how traces are reduced to the canvas by name ("peak" or
+    /// "filtered")
+    pub fn set_decimation(&mut self, name: &str) -> Result<(), JsValue> {
+        self.waveform.decimation = Decimation::from_label(name)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown decimation: {name}")))?;
+        Ok(())
+    }
+
+    /// Switch to the next draw style and return its name
+    pub fn cycle_decimation(&mut self) -> String {
+        self.waveform.decimation = self.waveform.decimation.next();
+        self.waveform.decimation.label().to_string()
+    }
+
+    pub fn decimation(&self) -> String {
+        self.waveform.decimation.label().to_string()
+    }
+
+    /// Volts per division; must be a positive number
+    pub fn set_volts_per_div(&mut self, value: f32) -> Result<(), JsValue> {
+        self.waveform.volts_per_division = positive_scale("volts per division", value)?;
+        Ok(())
+    }
+
+    /// Step the volts per division up the 1-2-5 sequence
+    pub fn increase_voltage_scale(&mut self) {
+        self.waveform.increase_voltage_scale();
+    }
+
+    /// Step the volts per division down the 1-2-5 sequence
+    pub fn decrease_voltage_scale(&mut self) {
+        self.waveform.decrease_voltage_scale();
+    }
+
+    pub fn volts_per_div(&self) -> f32 {
+        self.waveform.volts_per_division
+    }
+
+    pub fn set_trigger_enabled(&mut self, enabled: bool) {
+        self.trigger_settings.enabled = enabled;
+    }
+
+    pub fn toggle_trigger(&mut self) {
+        self.trigger_settings.toggle_enabled();
+    }
+
+    pub fn trigger_enabled(&self) -> bool {
+        self.trigger_settings.enabled
+    }
+
+    /// Set the trigger level in volts, clamped to ±10 V
+    pub fn set_trigger_level(&mut self, level: f32) {
+        self.trigger_settings.set_level(level);
+    }
+
+    /// Raise the trigger level by 0.1 V
+    pub fn increase_trigger_level(&mut self) {
+        self.trigger_settings
+            .set_level(self.trigger_settings.level + 0.1);
+    }
+
+    /// Lower the trigger level by 0.1 V
+    pub fn decrease_trigger_level(&mut self) {
+        self.trigger_settings
+            .set_level(self.trigger_settings.level - 0.1);
+    }
+
+    pub fn trigger_level(&self) -> f32 {
+        self.trigger_settings.level
+    }
+
+    pub fn set_trigger_rising(&mut self, rising: bool) {
+        self.trigger_settings.edge = if rising {
+            TriggerEdge::Rising
+        } else {
+            TriggerEdge::Falling
+        };
+    }
+
+    pub fn toggle_trigger_edge(&mut self) {
+        self.trigger_settings.toggle_edge();
+    }
+
+    pub fn trigger_rising(&self) -> bool {
+        self.trigger_settings.edge == TriggerEdge::Rising
+    }
+
+    /// `Auto` free-runs a sweep when no edge comes in time; `Normal` holds
+    /// the last one until an edge does
+    pub fn set_trigger_mode(&mut self, name: &str) -> Result<(), JsValue> {
+        self.trigger_settings.mode = TriggerMode::from_label(name)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown trigger mode: {name}")))?;
+        Ok(())
+    }
+
+    /// Switch to the other trigger mode and return its name
+    pub fn cycle_trigger_mode(&mut self) -> String {
+        self.trigger_settings.mode = self.trigger_settings.mode.next();
+        self.trigger_settings.mode.label().to_string()
+    }
+
+    pub fn trigger_mode(&self) -> String {
+        self.trigger_settings.mode.label().to_string()
+    }
+
+    pub fn set_persistence_enabled(&mut self, enabled: bool) {
+        self.persistence_enabled = enabled;
+        if !enabled {
+            self.history.clear();
+            self.intensity.clear();
+        }
+    }
+
+    /// Start the persisted traces over, e.g. to see what infinite
+    /// persistence catches from here on
+    pub fn clear_persistence(&mut self) {
+        self.history.clear();
+        self.intensity.clear();
+    }
+
+    pub fn toggle_persistence(&mut self) {
+        self.set_persistence_enabled(!self.persistence_enabled);
+    }
+
+    pub fn persistence_enabled(&self) -> bool {
+        self.persistence_enabled
+    }
+
+    /// Number of past traces kept on screen, clamped to 1..=500
+    pub fn set_persistence_frames(&mut self, frames: usize) {
+        self.persistence_frames = frames.clamp(MIN_PERSISTENCE_FRAMES, MAX_PERSISTENCE_FRAMES);
+        self.intensity.set_decay_frames(self.persistence_frames);
+        self.trim_history(now_ms());
+    }
+
+    pub fn persistence_frames(&self) -> usize {
+        self.persistence_frames
+    }
+
+    /// Select how persistence is drawn by name ("fade" or "graded")
+    pub fn set_persistence_mode(&mut self, name: &str) -> Result<(), JsValue> {
+        self.persistence_mode = PersistenceMode::from_label(name)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown persistence mode: {name}")))?;
+        Ok(())
+    }
+
+    /// Switch to the other persistence mode and return its name
+    pub fn cycle_persistence_mode(&mut self) -> String {
+        self.persistence_mode = self.persistence_mode.next();
+        self.persistence_mode.label().to_string()
+    }
+
+    pub fn persistence_mode(&self) -> String {
+        self.persistence_mode.label().to_string()
+    }
+
+    /// Select how long traces take to fade by name: "frames" for the
+    /// persistence frame count, "0.5 s", "2 s" or "infinite"
+    pub fn set_persistence_decay(&mut self, name: &str) -> Result<(), JsValue> {
+        self.persistence_decay = PersistenceDecay::from_label(name)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown persistence decay: {name}")))?;
+        self.trim_history(now_ms());
+        Ok(())
+    }
+
+    /// Switch to the next persistence decay and return its name
+    pub fn cycle_persistence_decay(&mut self) -> String {
+        self.persistence_decay = self.persistence_decay.next();
+        self.trim_history(now_ms());
+        self.persistence_decay.label().to_string()
+    }
+
+    pub fn persistence_decay(&self) -> String {
+        self.persistence_decay.label().to_string()
+    }
+
+    /// Select how persisted traces are colored by name: "trace" for the
+    /// trace color or "heat" for the blue-to-red heat map
+    pub fn set_persistence_colormap(&mut self, name: &str) -> Result<(), JsValue> {
+        self.persistence_colormap = PersistenceColormap::from_label(name)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown persistence colormap: {name}")))?;
+        Ok(())
+    }
+
+    /// Switch to the other persistence colormap and return its name
+    pub fn cycle_persistence_colormap(&mut self) -> String {
+        self.persistence_colormap = self.persistence_colormap.next();
+        self.persistence_colormap.label().to_string()
+    }
+
+    pub fn persistence_colormap(&self) -> String {
+        self.persistence_colormap.label().to_string()
+    }
+
+    /// Select how the envelope trace is drawn by name ("off", "overlay" or
+    /// "only")
+    pub fn set_envelope_mode(&mut self, name: &str) -> Result<(), JsValue> {
+        self.envelope.mode = EnvelopeMode::from_label(name)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown envelope mode: {name}")))?;
+        Ok(())
+    }
+
+    /// Switch to the next envelope mode and return its name
+    pub fn cycle_envelope_mode(&mut self) -> String {
+        self.envelope.mode = self.envelope.mode.next();
+        self.envelope.mode.label().to_string()
+    }
+
+    pub fn envelope_mode(&self) -> String {
+        self.envelope.mode.label().to_string()
+    }
+
+    /// Envelope attack time in seconds, clamped to 0.1 ms to 1 s
+    pub fn set_envelope_attack(&mut self, seconds: f32) {
+        self.envelope.set_attack(seconds);
+    }
+
+    pub fn envelope_attack(&self) -> f32 {
+        self.envelope.attack()
+    }
+
+    /// Envelope release time in seconds, clamped to 1 ms to 5 s
+    pub fn set_envelope_release(&mut self, seconds: f32) {
+        self.envelope.set_release(seconds);
+    }
+
+    pub fn envelope_release(&self) -> f32 {
+        self.envelope.release()
+    }
+
+    /// Select a display theme by name ("green", "amber", "white", "print", "colorblind")
+    pub fn set_theme(&mut self, name: &str) -> Result<(), JsValue> {
+        self.theme = DisplayTheme::from_label(name)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown theme: {name}")))?;
+        Ok(())
+    }
+
+    /// Switch to the next theme and return its name
+    pub fn cycle_theme(&mut self) -> String {
+        self.theme = self.theme.next();
+        self.theme.label().to_string()
+    }
+
+    /// Current theme name, lowercase as accepted by `set_theme`
+    pub fn theme(&self) -> String {
+        self.theme.label().to_lowercase()
+    }
+
+    /// Draw the trace in `color` ("#rrggbb") rather than the theme's; an
+    /// empty string goes back to the theme's
+    pub fn set_trace_color(&mut self, color: &str) -> Result<(), JsValue> {
+        self.trace_colors.trace = if color.is_empty() {
+            None
+        } else {
+            Some(
+                color_from_hex(color)
+                    .ok_or_else(|| JsValue::from_str(&format!("Invalid color: {color}")))?,
+            )
+        };
+        Ok(())
+    }
+
+    /// The picked trace color as "#rrggbb", empty while it's the theme's
+    pub fn trace_color(&self) -> String {
+        self.trace_colors
+            .trace
+            .map(color_to_hex)
+            .unwrap_or_default()
+    }
+
+    pub fn set_grid_enabled(&mut self, enabled: bool) {
+        self.graticule.enabled = enabled;
+        self.apply_graticule();
+    }
+
+    pub fn grid_enabled(&self) -> bool {
+        self.graticule.enabled
+    }
+
+    /// Divisions as "HxV", e.g. "10x8"
+    pub fn grid_divisions(&self) -> String {
+        format!(
+            "{}x{}",
+            self.graticule.horizontal_divisions, self.graticule.vertical_divisions
+        )
+    }
+
+    pub fn set_grid_divisions(&mut self, horizontal: usize, vertical: usize) {
+        self.graticule.horizontal_divisions = horizontal.max(1);
+        self.graticule.vertical_divisions = vertical.max(1);
+        self.apply_graticule();
+    }
+
+    /// Minor ticks per division along the center axes (0 hides them)
+    pub fn set_minor_ticks(&mut self, ticks: usize) {
+        self.graticule.minor_ticks = ticks;
+        self.apply_graticule();
+    }
+
+    pub fn set_grid_dotted(&mut self, dotted: bool) {
+        self.graticule.style = if dotted {
+            LineStyle::Dotted
+        } else {
+            LineStyle::Solid
+        };
+        self.apply_graticule();
+    }
+
+    pub fn grid_dotted(&self) -> bool {
+        self.graticule.style == LineStyle::Dotted
+    }
+}
+
+/// Page clock in milliseconds, for pacing file playback
+/// Each trace of `history`, oldest first, with its opacity: by its place in
+/// the history, or its age with a timed decay
+fn history_alphas(
+    history: &VecDeque<(f64, Vec<(f32, f32)>)>,
+    decay: PersistenceDecay,
+) -> impl ExactSizeIterator<Item = (&[(f32, f32)], f32)> {
+    let now = now_ms();
+    let count = history.len();
+    history
+        .iter()
+        .enumerate()
+        .map(move |(index, (time, points))| {
+            let alpha = if decay.is_timed() {
+                decay.alpha(age(now, *time))
+            } else {
+                (index as f32 + 1.0) / count as f32
+            };
+            (points.as_slice(), alpha)
+        })
+}
+
+/// Time from `then` to `now`, both in page milliseconds
+fn age(now: f64, then: f64) -> Duration {
+    Duration::from_secs_f64((now - then).max(0.0) / 1000.0)
+}
+
+/// `value` if it can be a scale, as `url_state` requires of one
+fn positive_scale(name: &str, value: f32) -> Result<f32, JsValue> {
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err(JsValue::from_str(&format!(
+            "The {name} must be a positive number, not {value}"
+        )))
+    }
+}
+
+fn now_ms() -> f64 {
+    web_sys::window()
+        .and_then(|window| window.performance())
+        .map_or(0.0, |performance| performance.now())
+}
+
+/// The active rendering backend
+enum Renderer {
+    WebGl(WebGLRenderer),
+    #[cfg(all(feature = "webgpu", target_arch = "wasm32"))]
+    WebGpu(Box<WebGpuRenderer>),
+}
+
+impl Renderer {
+    fn width(&self) -> usize {
+        match self {
+            Renderer::WebGl(renderer) => renderer.width(),
+            #[cfg(all(feature = "webgpu", target_arch = "wasm32"))]
+            Renderer::WebGpu(renderer) => renderer.width(),
+        }
+    }
+
+    fn set_graticule(&mut self, graticule: &Graticule) {
+        match self {
+            Renderer::WebGl(renderer) => renderer.set_graticule(graticule),
+            #[cfg(all(feature = "webgpu", target_arch = "wasm32"))]
+            Renderer::WebGpu(renderer) => renderer.set_graticule(graticule),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render<'a>(
+        &mut self,
+        history: impl ExactSizeIterator<Item = (&'a [(f32, f32)], f32)>,
+        intensity: Option<&IntensityMap>,
+        cells: Option<(&IntensityMap, PersistenceMode)>,
+        x_offset: f32,
+        markers: &[Segment],
+        palette: &Palette,
+        colormap: PersistenceColormap,
+    ) {
+        match self {
+            Renderer::WebGl(renderer) => renderer.render(
+                history, intensity, cells, x_offset, markers, palette, colormap,
+            ),
+            #[cfg(all(feature = "webgpu", target_arch = "wasm32"))]
+            Renderer::WebGpu(renderer) => renderer.render(
+                history, intensity, cells, x_offset, markers, palette, colormap,
+            ),
+        }
+    }
+}
+
+impl OzScopeWasm {
+    async fn start_audio(&mut self, device_id: Option<&str>) -> Result<(), JsValue> {
+        let ring = self.pipeline.as_ref().map(WorkerPipeline::ring);
+        match WebAudioCapture::new(device_id, ring).await {
             Ok(capture) => {
                 web_sys::console::log_1(&"Audio capture initialized".into());
+                self.set_sample_rate(capture.sample_rate());
                 self.audio_capture = Some(capture);
                 Ok(())
             }
             Err(e) => {
                 web_sys::console::error_1(&format!("Failed to init audio: {e}").into());
-                Err(JsValue::from_str(&e))
+                Err(error::audio_error(&e))
             }
         }
     }
 
-    pub fn init_renderer(&mut self, canvas_id: &str) -> Result<(), JsValue> {
-        let renderer = WebGLRenderer::new(canvas_id)?;
-        web_sys::console::log_1(&"WebGL renderer initialized".into());
-        self.renderer = Some(renderer);
-        Ok(())
+    /// Follow the rate the input runs at, which is the device's own: the
+    /// timebase, readouts and recordings all count samples by it. Samples
+    /// taken at the old rate are dropped, as they'd no longer line up.
+    fn set_sample_rate(&mut self, sample_rate: u32) {
+        if sample_rate == 0 || sample_rate == self.waveform.sample_rate {
+            return;
+        }
+        self.waveform.sample_rate = sample_rate;
+        self.waveform.samples.clear();
+        self.auto = AutoTrigger::new(sample_rate);
+        if let Some(recording) = self.recording.as_mut() {
+            recording.clear();
+        }
     }
 
-    pub fn update(&mut self) {
-        // Update audio buffer
-        if let Some(ref audio_capture) = self.audio_capture {
-            let new_samples = audio_capture.read_samples(usize::MAX);
+    /// Fill the display with the file's samples up to the playhead, so
+    /// seeking shows something even while paused
+    fn show_file_position(&mut self) {
+        if let Some(ref file_player) = self.file_player {
+            let count = self.waveform.buffer_size();
+            self.waveform.copy_samples(file_player.recent(count));
+        }
+    }
 
-            if !new_samples.is_empty() {
-                self.audio_buffer.extend_from_slice(&new_samples);
+    /// Traces keep at most two points per pixel column of the canvas
+    fn display_columns(&self) -> usize {
+        self.renderer
+            .as_ref()
+            .map_or(DEFAULT_DISPLAY_COLUMNS, Renderer::width)
+    }
 
-                let samples_needed = self.waveform.calculate_samples_per_screen();
-                let max_buffer_size = samples_needed + 200;
+    fn record(&mut self, samples: &[f32]) {
+        if let Some(ref mut recording) = self.recording {
+            let limit = MAX_RECORDING_SECONDS * self.waveform.sample_rate as usize;
+            let room = limit.saturating_sub(recording.len());
+            recording.extend_from_slice(&samples[..samples.len().min(room)]);
+        }
+    }
 
-                if self.audio_buffer.len() > max_buffer_size {
-                    let to_remove = self.audio_buffer.len() - max_buffer_size;
-                    self.audio_buffer.drain(0..to_remove);
-                }
+    /// RMS of the latest capture, wherever it was measured
+    fn current_rms(&self) -> Option<f32> {
+        match (&self.pipeline, &self.worker_measurements) {
+            (Some(_), Some(measurements)) if self.file_player.is_none() => {
+                js_sys::Reflect::get(measurements, &"rms".into())
+                    .ok()?
+                    .as_f64()
+                    .map(|rms| rms as f32)
+            }
+            _ => self.waveform.calculate_rms(),
+        }
+    }
+
+    /// Every setting a fragment or settings object can carry
+    fn config(&self) -> ScopeConfig {
+        ScopeConfig {
+            time_per_division: Some(self.waveform.time_per_division),
+            volts_per_division: Some(self.waveform.volts_per_division),
+            horizontal_position: Some(self.horizontal_position),
+            decimation: Some(self.waveform.decimation),
+            trigger_enabled: Some(self.trigger_settings.enabled),
+            trigger_edge: Some(self.trigger_settings.edge),
+            trigger_level: Some(self.trigger_settings.level),
+            trigger_mode: Some(self.trigger_settings.mode),
+            grid_enabled: Some(self.graticule.enabled),
+            divisions: Some((
+                self.graticule.horizontal_divisions,
+                self.graticule.vertical_divisions,
+            )),
+            minor_ticks: Some(self.graticule.minor_ticks),
+            grid_style: Some(self.graticule.style),
+            theme: Some(self.theme),
+            trace_color: self.trace_colors.trace,
+            persistence_enabled: Some(self.persistence_enabled),
+            persistence_frames: Some(self.persistence_frames),
+            persistence_mode: Some(self.persistence_mode),
+            persistence_decay: Some(self.persistence_decay),
+            persistence_colormap: Some(self.persistence_colormap),
+            envelope_mode: Some(self.envelope.mode),
+            envelope_attack: Some(self.envelope.attack()),
+            envelope_release: Some(self.envelope.release()),
+        }
+    }
+
+    /// Settings `config` doesn't mention are left alone
+    fn apply_config(&mut self, config: ScopeConfig) {
+        if let Some(value) = config.time_per_division {
+            self.waveform.time_per_division = value;
+        }
+        if let Some(value) = config.volts_per_division {
+            self.waveform.volts_per_division = value;
+        }
+        if let Some(value) = config.horizontal_position {
+            self.horizontal_position = value.clamp(-1.0, 1.0);
+        }
+        if let Some(mode) = config.decimation {
+            self.waveform.decimation = mode;
+        }
+        if let Some(value) = config.trigger_enabled {
+            self.trigger_settings.enabled = value;
+        }
+        if let Some(edge) = config.trigger_edge {
+            self.trigger_settings.edge = edge;
+        }
+        if let Some(value) = config.trigger_level {
+            self.trigger_settings.set_level(value);
+        }
+        if let Some(mode) = config.trigger_mode {
+            self.trigger_settings.mode = mode;
+        }
+        if let Some(value) = config.grid_enabled {
+            self.graticule.enabled = value;
+        }
+        if let Some((horizontal, vertical)) = config.divisions {
+            self.graticule.horizontal_divisions = horizontal.max(1);
+            self.graticule.vertical_divisions = vertical.max(1);
+        }
+        if let Some(value) = config.minor_ticks {
+            self.graticule.minor_ticks = value;
+        }
+        if let Some(style) = config.grid_style {
+            self.graticule.style = style;
+        }
+        self.apply_graticule();
+        if let Some(theme) = config.theme {
+            self.theme = theme;
+        }
+        if let Some(color) = config.trace_color {
+            self.trace_colors.trace = Some(color);
+        }
+        if let Some(enabled) = config.persistence_enabled {
+            self.set_persistence_enabled(enabled);
+        }
+        if let Some(frames) = config.persistence_frames {
+            self.set_persistence_frames(frames);
+        }
+        if let Some(mode) = config.persistence_mode {
+            self.persistence_mode = mode;
+        }
+        if let Some(decay) = config.persistence_decay {
+            self.persistence_decay = decay;
+        }
+        if let Some(colormap) = config.persistence_colormap {
+            self.persistence_colormap = colormap;
+        }
+        if let Some(mode) = config.envelope_mode {
+            self.envelope.mode = mode;
+        }
+        if let Some(seconds) = config.envelope_attack {
+            self.envelope.set_attack(seconds);
+        }
+        if let Some(seconds) = config.envelope_release {
+            self.envelope.set_release(seconds);
+        }
+    }
 
-                self.waveform.update_samples(self.audio_buffer.clone());
+    fn analyze_mel(&mut self, samples: &[f32]) {
+        if self.mel_enabled {
+            self.mel
+                .set_scale(SpectrumScale::Mel, self.waveform.sample_rate);
+            let new_frame = self.mel.push(samples);
+            if new_frame {
+                self.mel_frames = self.mel_frames.wrapping_add(1);
             }
+            self.spectrogram.push(
+                samples.len(),
+                self.mel.get_mel_frame().filter(|_| new_frame),
+                SpectrumScale::Mel,
+                self.waveform.sample_rate,
+            );
+        }
+    }
+
+    fn push_history(&mut self) {
+        if self.persistence_enabled && self.envelope.shows_raw() && !self.frame.points.is_empty() {
+            let now = now_ms();
+            let elapsed = self
+                .history
+                .back()
+                .map_or(0.0, |&(time, _)| (now - time).max(0.0));
+            self.intensity.accumulate_after(
+                &self.frame.points,
+                self.persistence_decay,
+                Duration::from_secs_f64(elapsed / 1000.0),
+            );
+            // The oldest trace's buffer is reused once the history is full
+            let (_, mut points) = if self.history.len() >= self.history_limit() {
+                self.history.pop_front().unwrap_or_default()
+            } else {
+                (now, Vec::new())
+            };
+            points.clear();
+            points.extend_from_slice(&self.frame.points);
+            self.history.push_back((now, points));
+            self.trim_history(now);
         }
     }
 
-    pub fn render(&self) {
-        if let Some(ref renderer) = self.renderer {
-            let points = self.waveform.get_display_samples(&self.trigger_settings);
-            renderer.render(&points);
+    /// The persistence frame count, or with a timed decay as many traces as
+    /// the history holds; longer persistence is drawn from the intensity map
+    fn history_limit(&self) -> usize {
+        if self.persistence_decay.is_timed() {
+            MAX_TRACE_HISTORY
+        } else {
+            self.persistence_frames.min(MAX_TRACE_HISTORY)
         }
     }
 
-    pub fn set_time_per_div(&mut self, value: f32) {
-        self.waveform.time_per_division = value;
+    /// Drop traces beyond the limit and, with a timed decay, faded ones
+    fn trim_history(&mut self, now: f64) {
+        let limit = self.history_limit();
+        let decay = self.persistence_decay;
+        while self.history.len() > limit
+            || self
+                .history
+                .front()
+                .is_some_and(|&(time, _)| decay.has_faded(age(now, time)))
+        {
+            self.history.pop_front();
+        }
     }
 
-    pub fn set_volts_per_div(&mut self, value: f32) {
-        self.waveform.volts_per_division = value;
+    fn pan(&mut self, dx: f32) {
+        self.horizontal_position = (self.horizontal_position + dx).clamp(-1.0, 1.0);
     }
 
-    pub fn set_trigger_enabled(&mut self, enabled: bool) {
-        self.trigger_settings.enabled = enabled;
+    /// Scale time/div by `factor`, keeping the instant at screen x `anchor` fixed
+    fn zoom_time(&mut self, factor: f32, anchor: f32) {
+        self.waveform.time_per_division = (self.waveform.time_per_division * factor).max(0.00001);
+        let position = anchor - (anchor - self.horizontal_position) / factor;
+        self.horizontal_position = position.clamp(-1.0, 1.0);
     }
 
-    pub fn set_trigger_level(&mut self, level: f32) {
-        self.trigger_settings.level = level;
+    fn apply_graticule(&mut self) {
+        self.waveform.horizontal_divisions = self.graticule.horizontal_divisions;
+        self.intensity
+            .set_vertical_divisions(self.graticule.vertical_divisions);
+        if let Some(ref mut renderer) = self.renderer {
+            renderer.set_graticule(&self.graticule);
+        }
     }
 }
@@ -0,0 +1,87 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+#[wasm_bindgen]
+extern "C" {
+    /// The JavaScript wrapper of an `OzScopeWasm`. Calling through it rather
+    /// than holding the Rust value keeps wasm-bindgen's borrow checks, so a
+    /// frame that lands while an async method is running fails cleanly.
+    pub type JsScope;
+
+    #[wasm_bindgen(method, catch)]
+    fn update(this: &JsScope) -> Result<(), JsValue>;
+
+    #[wasm_bindgen(method, catch)]
+    fn render(this: &JsScope) -> Result<(), JsValue>;
+}
+
+type FrameCallback = Closure<dyn FnMut()>;
+
+/// Calls `update()` then `render()` on a scope every animation frame.
+///
+/// Clones share the same loop. It ends when `stop` is called on any of them
+/// or when a frame throws.
+#[derive(Clone)]
+pub struct RenderLoop {
+    active: Rc<Cell<bool>>,
+}
+
+impl RenderLoop {
+    pub fn new() -> Self {
+        Self {
+            active: Rc::new(Cell::new(false)),
+        }
+    }
+
+    pub fn start(&self, scope: JsScope) -> Result<(), JsValue> {
+        self.active.set(true);
+
+        // The callback re-schedules itself, so it has to reach its own closure
+        let callback: Rc<RefCell<Option<FrameCallback>>> = Rc::new(RefCell::new(None));
+        let next_frame = callback.clone();
+        let running = self.active.clone();
+        *callback.borrow_mut() = Some(Closure::new(move || {
+            let frame = if running.get() {
+                scope.update().and_then(|_| scope.render())
+            } else {
+                Ok(())
+            };
+            if let Err(ref e) = frame {
+                web_sys::console::error_2(&"Render loop stopped:".into(), e);
+                running.set(false);
+            }
+            if !running.get() {
+                // Drops this closure and with it the reference to the scope
+                next_frame.borrow_mut().take();
+                return;
+            }
+            if let Some(ref callback) = *next_frame.borrow() {
+                let _ = request_animation_frame(callback);
+            }
+        }));
+
+        if let Some(ref callback) = *callback.borrow() {
+            if let Err(e) = request_animation_frame(callback) {
+                self.active.set(false);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        self.active.set(false);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.get()
+    }
+}
+
+fn request_animation_frame(callback: &FrameCallback) -> Result<i32, JsValue> {
+    web_sys::window()
+        .ok_or("No window found")?
+        .request_animation_frame(callback.as_ref().unchecked_ref())
+}
@@ -0,0 +1,72 @@
+use js_sys::{Atomics, Float32Array, Int32Array, SharedArrayBuffer};
+use wasm_bindgen::JsValue;
+
+/// Samples the ring holds, about 1.4 s at 48 kHz
+pub const RING_CAPACITY: u32 = 1 << 16;
+
+/// Single-producer, single-consumer sample ring in shared memory.
+///
+/// The AudioWorklet writes samples at `count % capacity` and then publishes
+/// the new total with `Atomics.store` on `indices[0]`; the reader keeps its
+/// own count of what it has consumed. Counts wrap at 2^32, which is
+/// harmless since only their difference is used.
+#[derive(Clone)]
+pub struct SampleRing {
+    indices: Int32Array,
+    samples: Float32Array,
+}
+
+impl SampleRing {
+    pub fn new() -> Self {
+        Self {
+            indices: Int32Array::new(&SharedArrayBuffer::new(4)),
+            samples: Float32Array::new(&SharedArrayBuffer::new(RING_CAPACITY * 4)),
+        }
+    }
+
+    /// Re-open a ring passed in from another thread
+    pub fn from_buffers(indices: &SharedArrayBuffer, samples: &SharedArrayBuffer) -> Self {
+        Self {
+            indices: Int32Array::new(indices),
+            samples: Float32Array::new(samples),
+        }
+    }
+
+    pub fn indices_buffer(&self) -> JsValue {
+        self.indices.buffer().into()
+    }
+
+    pub fn samples_buffer(&self) -> JsValue {
+        self.samples.buffer().into()
+    }
+
+    /// Total samples written so far
+    pub fn written(&self) -> u32 {
+        Atomics::load(&self.indices, 0).unwrap_or(0) as u32
+    }
+
+    /// Samples written since `*read`, advancing it. If the reader fell more
+    /// than a ring behind, the overwritten samples are skipped.
+    pub fn read(&self, read: &mut u32) -> Vec<f32> {
+        let written = self.written();
+        let capacity = self.samples.length();
+        let available = written.wrapping_sub(*read);
+        if available > capacity {
+            *read = written.wrapping_sub(capacity);
+        }
+        let count = written.wrapping_sub(*read);
+
+        let mut samples = vec![0.0; count as usize];
+        let start = *read % capacity;
+        let first = count.min(capacity - start);
+        self.samples
+            .subarray(start, start + first)
+            .copy_to(&mut samples[..first as usize]);
+        self.samples
+            .subarray(0, count - first)
+            .copy_to(&mut samples[first as usize..]);
+
+        *read = written;
+        samples
+    }
+}
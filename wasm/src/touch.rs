@@ -0,0 +1,117 @@
+use wasm_bindgen::JsCast;
+use web_sys::{Element, TouchEvent};
+
+/// Two-finger spans shorter than this, in CSS pixels, are too noisy to scale by
+const MIN_PINCH_SPAN: f32 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinchAxis {
+    /// Scales time/div
+    Horizontal,
+    /// Scales volts/div
+    Vertical,
+}
+
+/// What the fingers did since the previous touch event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    /// One finger moved down by this fraction of the canvas height
+    Drag(f32),
+    /// Two fingers moved. `scale` is the new span over the old one along
+    /// `axis`, `anchor` the midpoint's screen x, and `pan` how far the
+    /// midpoint moved horizontally, all in normalized screen space.
+    Pinch {
+        axis: PinchAxis,
+        scale: f32,
+        anchor: f32,
+        pan: f32,
+    },
+}
+
+/// Turns the raw touch list of successive touch events into gestures.
+///
+/// A gesture runs for as long as the number of fingers stays the same; a
+/// finger landing or lifting starts a new one from the current positions.
+/// The pinch axis is chosen from the fingers' layout when the gesture starts
+/// and kept until it ends, so a pinch doesn't flip between time and volts.
+pub struct TouchTracker {
+    /// Positions from the previous event, normalized to the canvas
+    last: Vec<(f32, f32)>,
+    axis: Option<PinchAxis>,
+}
+
+impl TouchTracker {
+    pub fn new() -> Self {
+        Self {
+            last: Vec::new(),
+            axis: None,
+        }
+    }
+
+    /// Feed a touch event received on the canvas element
+    pub fn handle_event(&mut self, event: &TouchEvent) -> Option<Gesture> {
+        let canvas: Element = event.current_target()?.dyn_into().ok()?;
+        let rect = canvas.get_bounding_client_rect();
+        let width = rect.width().max(1.0);
+        let height = rect.height().max(1.0);
+
+        let list = event.touches();
+        let touches: Vec<(f32, f32)> = (0..list.length())
+            .filter_map(|i| list.get(i))
+            .map(|touch| {
+                (
+                    ((touch.client_x() as f64 - rect.left()) / width) as f32,
+                    ((touch.client_y() as f64 - rect.top()) / height) as f32,
+                )
+            })
+            .collect();
+
+        self.update(&touches, (width as f32, height as f32))
+    }
+
+    /// Feed the current touch positions, normalized to the canvas of `size`
+    /// CSS pixels
+    pub fn update(&mut self, touches: &[(f32, f32)], size: (f32, f32)) -> Option<Gesture> {
+        let previous = std::mem::replace(&mut self.last, touches.to_vec());
+        if previous.len() != touches.len() {
+            self.axis = None;
+            return None;
+        }
+
+        match (previous.as_slice(), touches) {
+            ([(_, y0)], [(_, y1)]) => Some(Gesture::Drag(y1 - y0)),
+            ([a0, b0], [a1, b1]) => {
+                let span = |a: &(f32, f32), b: &(f32, f32)| {
+                    ((a.0 - b.0).abs() * size.0, (a.1 - b.1).abs() * size.1)
+                };
+                let (old_x, old_y) = span(a0, b0);
+                let (new_x, new_y) = span(a1, b1);
+
+                let axis = *self.axis.get_or_insert(if old_x >= old_y {
+                    PinchAxis::Horizontal
+                } else {
+                    PinchAxis::Vertical
+                });
+                let (old_span, new_span) = match axis {
+                    PinchAxis::Horizontal => (old_x, new_x),
+                    PinchAxis::Vertical => (old_y, new_y),
+                };
+                let scale = if old_span >= MIN_PINCH_SPAN && new_span >= MIN_PINCH_SPAN {
+                    new_span / old_span
+                } else {
+                    1.0
+                };
+
+                let anchor = (a1.0 + b1.0) / 2.0;
+                let pan = anchor - (a0.0 + b0.0) / 2.0;
+                Some(Gesture::Pinch {
+                    axis,
+                    scale,
+                    anchor,
+                    pan,
+                })
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use serde::{Deserialize, Serialize};
+
+use ozeecubed_core::display::{
+    color_from_hex, color_to_hex, DisplayTheme, LineStyle, PersistenceColormap, PersistenceDecay,
+    PersistenceMode, Rgba,
+};
+use ozeecubed_core::oscilloscope::trigger::{TriggerEdge, TriggerMode};
+use ozeecubed_core::oscilloscope::{Decimation, EnvelopeMode};
+
+/// Scope setup as carried in a URL fragment, e.g.
+/// `tdiv=0.001&vdiv=0.5&trig=on&edge=rising&level=0.2&div=10x8&theme=amber`.
+///
+/// Every field is optional: a fragment only overrides the settings it names,
+/// and keys it doesn't know are skipped so links from newer builds still load.
+/// As an object, through serde, the same settings go by their field names in
+/// camelCase.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopeConfig {
+    pub time_per_division: Option<f32>,
+    pub volts_per_division: Option<f32>,
+    pub horizontal_position: Option<f32>,
+    pub decimation: Option<Decimation>,
+    pub trigger_enabled: Option<bool>,
+    pub trigger_edge: Option<TriggerEdge>,
+    pub trigger_level: Option<f32>,
+    pub trigger_mode: Option<TriggerMode>,
+    pub grid_enabled: Option<bool>,
+    /// Horizontal by vertical
+    pub divisions: Option<(usize, usize)>,
+    pub minor_ticks: Option<usize>,
+    pub grid_style: Option<LineStyle>,
+    pub theme: Option<DisplayTheme>,
+    /// Picked over the theme's trace color; `rrggbb` in the fragment
+    pub trace_color: Option<Rgba>,
+    pub persistence_enabled: Option<bool>,
+    pub persistence_frames: Option<usize>,
+    pub persistence_mode: Option<PersistenceMode>,
+    pub persistence_decay: Option<PersistenceDecay>,
+    pub persistence_colormap: Option<PersistenceColormap>,
+    pub envelope_mode: Option<EnvelopeMode>,
+    /// Seconds
+    pub envelope_attack: Option<f32>,
+    /// Seconds
+    pub envelope_release: Option<f32>,
+}
+
+impl ScopeConfig {
+    /// Parse a fragment, with or without its leading `#`
+    pub fn from_fragment(fragment: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+
+        for pair in fragment.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("Missing value for '{pair}'"))?;
+            match key {
+                "tdiv" => config.time_per_division = Some(parse_scale(key, value)?),
+                "vdiv" => config.volts_per_division = Some(parse_scale(key, value)?),
+                "pos" => config.horizontal_position = Some(parse_number(key, value)?),
+                "decim" => {
+                    config.decimation =
+                        Some(Decimation::from_label(value).ok_or_else(|| invalid(key, value))?)
+                }
+                "trig" => config.trigger_enabled = Some(parse_switch(key, value)?),
+                "edge" => {
+                    config.trigger_edge = Some(match value {
+                        "rising" => TriggerEdge::Rising,
+                        "falling" => TriggerEdge::Falling,
+                        _ => return Err(invalid(key, value)),
+                    })
+                }
+                "level" => config.trigger_level = Some(parse_number(key, value)?),
+                "trigmode" => {
+                    config.trigger_mode =
+                        Some(TriggerMode::from_label(value).ok_or_else(|| invalid(key, value))?)
+                }
+                "grid" => config.grid_enabled = Some(parse_switch(key, value)?),
+                "div" => {
+                    let (horizontal, vertical) =
+                        value.split_once('x').ok_or_else(|| invalid(key, value))?;
+                    config.divisions = Some((
+                        horizontal.parse().map_err(|_| invalid(key, value))?,
+                        vertical.parse().map_err(|_| invalid(key, value))?,
+                    ));
+                }
+                "ticks" => {
+                    config.minor_ticks = Some(value.parse().map_err(|_| invalid(key, value))?)
+                }
+                "style" => {
+                    config.grid_style = Some(match value {
+                        "solid" => LineStyle::Solid,
+                        "dotted" => LineStyle::Dotted,
+                        _ => return Err(invalid(key, value)),
+                    })
+                }
+                "theme" => {
+                    config.theme =
+                        Some(DisplayTheme::from_label(value).ok_or_else(|| invalid(key, value))?)
+                }
+                "trace" => {
+                    config.trace_color = Some(
+                        color_from_hex(&format!("#{value}")).ok_or_else(|| invalid(key, value))?,
+                    )
+                }
+                "persist" => config.persistence_enabled = Some(parse_switch(key, value)?),
+                "frames" => {
+                    config.persistence_frames =
+                        Some(value.parse().map_err(|_| invalid(key, value))?)
+                }
+                "mode" => {
+                    config.persistence_mode = Some(
+                        PersistenceMode::from_label(value).ok_or_else(|| invalid(key, value))?,
+                    )
+                }
+                "decay" => {
+                    config.persistence_decay = Some(
+                        PersistenceDecay::from_label(value).ok_or_else(|| invalid(key, value))?,
+                    )
+                }
+                "colors" => {
+                    config.persistence_colormap = Some(
+                        PersistenceColormap::from_label(value)
+                            .ok_or_else(|| invalid(key, value))?,
+                    )
+                }
+                "env" => {
+                    config.envelope_mode =
+                        Some(EnvelopeMode::from_label(value).ok_or_else(|| invalid(key, value))?)
+                }
+                "attack" => config.envelope_attack = Some(parse_scale(key, value)?),
+                "release" => config.envelope_release = Some(parse_scale(key, value)?),
+                _ => {}
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Encode the fields that are set, without a leading `#`
+    pub fn to_fragment(&self) -> String {
+        let mut pairs = Vec::new();
+        let switch = |on: bool| if on { "on" } else { "off" };
+
+        if let Some(value) = self.time_per_division {
+            pairs.push(format!("tdiv={value}"));
+        }
+        if let Some(value) = self.volts_per_division {
+            pairs.push(format!("vdiv={value}"));
+        }
+        if let Some(value) = self.horizontal_position {
+            pairs.push(format!("pos={value}"));
+        }
+        if let Some(mode) = self.decimation {
+            pairs.push(format!("decim={}", mode.label().to_lowercase()));
+        }
+        if let Some(value) = self.trigger_enabled {
+            pairs.push(format!("trig={}", switch(value)));
+        }
+        if let Some(edge) = self.trigger_edge {
+            let edge = match edge {
+                TriggerEdge::Rising => "rising",
+                TriggerEdge::Falling => "falling",
+            };
+            pairs.push(format!("edge={edge}"));
+        }
+        if let Some(value) = self.trigger_level {
+            pairs.push(format!("level={value}"));
+        }
+        if let Some(mode) = self.trigger_mode {
+            pairs.push(format!("trigmode={}", mode.label().to_lowercase()));
+        }
+        if let Some(value) = self.grid_enabled {
+            pairs.push(format!("grid={}", switch(value)));
+        }
+        if let Some((horizontal, vertical)) = self.divisions {
+            pairs.push(format!("div={horizontal}x{vertical}"));
+        }
+        if let Some(value) = self.minor_ticks {
+            pairs.push(format!("ticks={value}"));
+        }
+        if let Some(style) = self.grid_style {
+            pairs.push(format!("style={}", style.label().to_lowercase()));
+        }
+        if let Some(theme) = self.theme {
+            pairs.push(format!("theme={}", theme.label().to_lowercase()));
+        }
+        if let Some(color) = self.trace_color {
+            pairs.push(format!("trace={}", &color_to_hex(color)[1..]));
+        }
+        if let Some(value) = self.persistence_enabled {
+            pairs.push(format!("persist={}", switch(value)));
+        }
+        if let Some(value) = self.persistence_frames {
+            pairs.push(format!("frames={value}"));
+        }
+        if let Some(mode) = self.persistence_mode {
+            pairs.push(format!("mode={}", mode.label().to_lowercase()));
+        }
+        if let Some(decay) = self.persistence_decay {
+            // Spaces don't survive a fragment, and the labels read as well without
+            pairs.push(format!(
+                "decay={}",
+                decay.label().replace(' ', "").to_lowercase()
+            ));
+        }
+        if let Some(colormap) = self.persistence_colormap {
+            pairs.push(format!("colors={}", colormap.label().to_lowercase()));
+        }
+        if let Some(mode) = self.envelope_mode {
+            pairs.push(format!("env={}", mode.label().to_lowercase()));
+        }
+        if let Some(value) = self.envelope_attack {
+            pairs.push(format!("attack={value}"));
+        }
+        if let Some(value) = self.envelope_release {
+            pairs.push(format!("release={value}"));
+        }
+
+        pairs.join("&")
+    }
+}
+
+fn invalid(key: &str, value: &str) -> String {
+    format!("Invalid value for '{key}': '{value}'")
+}
+
+fn parse_number(key: &str, value: &str) -> Result<f32, String> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .ok_or_else(|| invalid(key, value))
+}
+
+/// A per-division scale, which must be positive
+fn parse_scale(key: &str, value: &str) -> Result<f32, String> {
+    parse_number(key, value).and_then(|number| {
+        if number > 0.0 {
+            Ok(number)
+        } else {
+            Err(invalid(key, value))
+        }
+    })
+}
+
+fn parse_switch(key: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(invalid(key, value)),
+    }
+}
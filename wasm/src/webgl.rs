@@ -1,187 +1,546 @@
+use crate::error::RenderError;
+use ozeecubed_core::display::{
+    Graticule, GridLineKind, IntensityMap, Palette, PersistenceColormap, PersistenceMode, Rgba,
+    Segment,
+};
 use wasm_bindgen::JsCast;
-use wasm_bindgen::JsValue;
 use web_sys::{
-    HtmlCanvasElement, WebGlBuffer, WebGlProgram, WebGlRenderingContext as GL, WebGlShader,
+    HtmlCanvasElement, WebGl2RenderingContext, WebGlBuffer, WebGlProgram,
+    WebGlRenderingContext as GL, WebGlShader, WebGlUniformLocation, WebGlVertexArrayObject,
 };
 
+// GLSL ES 1.00 compiles on both WebGL1 and WebGL2 contexts
 const VERTEX_SHADER: &str = r#"
 attribute vec2 position;
+attribute vec4 vertex_color;
+uniform vec2 scale;
+uniform vec2 offset;
+varying vec4 v_color;
 void main() {
-    gl_Position = vec4(position, 0.0, 1.0);
+    gl_Position = vec4(position * scale + offset, 0.0, 1.0);
+    v_color = vertex_color;
 }
 "#;
 
 const FRAGMENT_SHADER: &str = r#"
 precision mediump float;
 uniform vec4 color;
+varying vec4 v_color;
 void main() {
-    gl_FragColor = color;
+    gl_FragColor = color * v_color;
 }
 "#;
 
+const POSITION_LOCATION: u32 = 0;
+
+/// Per-vertex tint; meshes without colors leave the array disabled and get
+/// the constant white set at startup, so only the `color` uniform applies
+const VERTEX_COLOR_LOCATION: u32 = 1;
+
+/// Floats per vertex of a colored mesh: position then RGBA
+const COLORED_VERTEX_FLOATS: i32 = 6;
+
+/// Maps geometry into clip space as `position * scale + offset`
+#[derive(Clone, Copy)]
+struct Transform {
+    scale: [f32; 2],
+    offset: [f32; 2],
+}
+
+/// Normalized screen space (y down) to clip space (y up)
+const SCREEN_TRANSFORM: Transform = Transform {
+    scale: [2.0, -2.0],
+    offset: [-1.0, 1.0],
+};
+
+/// WebGL2 where the browser has it, otherwise WebGL1 without vertex arrays.
+/// Both share the same entry points and enum values for everything used here.
+enum Context {
+    WebGl2(WebGl2RenderingContext),
+    WebGl1(GL),
+}
+
+/// Call the same method on whichever context is active
+macro_rules! gl {
+    ($context:expr, $method:ident($($arg:expr),* $(,)?)) => {
+        match $context {
+            Context::WebGl2(gl) => gl.$method($($arg),*),
+            Context::WebGl1(gl) => gl.$method($($arg),*),
+        }
+    };
+}
+
+/// A vertex buffer of 2D positions, optionally interleaved with RGBA colors,
+/// with its attribute bindings captured in a vertex array object on WebGL2
+struct Mesh {
+    buffer: WebGlBuffer,
+    vertex_array: Option<WebGlVertexArrayObject>,
+    colored: bool,
+}
+
+impl Mesh {
+    fn new(context: &Context, colored: bool) -> Result<Self, RenderError> {
+        let buffer = gl!(context, create_buffer()).ok_or(RenderError::Resource("buffer"))?;
+        let mut mesh = Self {
+            buffer,
+            vertex_array: None,
+            colored,
+        };
+        if let Context::WebGl2(gl) = context {
+            let vertex_array = gl
+                .create_vertex_array()
+                .ok_or(RenderError::Resource("vertex array"))?;
+            gl.bind_vertex_array(Some(&vertex_array));
+            mesh.set_attributes(context);
+            gl.bind_vertex_array(None);
+            mesh.vertex_array = Some(vertex_array);
+        }
+        Ok(mesh)
+    }
+
+    fn set_attributes(&self, context: &Context) {
+        gl!(context, bind_buffer(GL::ARRAY_BUFFER, Some(&self.buffer)));
+        if self.colored {
+            let stride = COLORED_VERTEX_FLOATS * 4;
+            gl!(
+                context,
+                vertex_attrib_pointer_with_i32(POSITION_LOCATION, 2, GL::FLOAT, false, stride, 0)
+            );
+            gl!(
+                context,
+                vertex_attrib_pointer_with_i32(
+                    VERTEX_COLOR_LOCATION,
+                    4,
+                    GL::FLOAT,
+                    false,
+                    stride,
+                    8,
+                )
+            );
+            gl!(context, enable_vertex_attrib_array(VERTEX_COLOR_LOCATION));
+        } else {
+            gl!(
+                context,
+                vertex_attrib_pointer_with_i32(POSITION_LOCATION, 2, GL::FLOAT, false, 0, 0)
+            );
+            gl!(context, disable_vertex_attrib_array(VERTEX_COLOR_LOCATION));
+        }
+        gl!(context, enable_vertex_attrib_array(POSITION_LOCATION));
+    }
+
+    fn upload(&self, context: &Context, vertices: &[f32], usage: u32) {
+        gl!(context, bind_buffer(GL::ARRAY_BUFFER, Some(&self.buffer)));
+        unsafe {
+            let array = js_sys::Float32Array::view(vertices);
+            gl!(
+                context,
+                buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &array, usage)
+            );
+        }
+    }
+
+    fn bind(&self, context: &Context) {
+        match (context, &self.vertex_array) {
+            (Context::WebGl2(gl), Some(vertex_array)) => gl.bind_vertex_array(Some(vertex_array)),
+            _ => self.set_attributes(context),
+        }
+    }
+}
+
 pub struct WebGLRenderer {
-    gl: GL,
+    context: Context,
     program: WebGlProgram,
-    position_buffer: WebGlBuffer,
-    grid_buffer: WebGlBuffer,
+    color_location: Option<WebGlUniformLocation>,
+    scale_location: Option<WebGlUniformLocation>,
+    offset_location: Option<WebGlUniformLocation>,
+    trace_mesh: Mesh,
+    graded_mesh: Mesh,
+    marker_mesh: Mesh,
+    grid_mesh: Mesh,
+    // Grid vertices are uploaded grouped by kind: major, then center, then ticks
+    grid_major_vertices: i32,
+    grid_center_vertices: i32,
+    grid_tick_vertices: i32,
+    vertical_divisions: usize,
+    /// Scratch space reused for per-frame vertex uploads
+    vertices: Vec<f32>,
 }
 
 impl WebGLRenderer {
-    pub fn new(canvas_id: &str) -> Result<Self, JsValue> {
-        let document = web_sys::window().unwrap().document().unwrap();
+    pub fn new(canvas_id: &str) -> Result<Self, RenderError> {
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or(RenderError::NoDocument)?;
         let canvas = document
             .get_element_by_id(canvas_id)
-            .ok_or("Canvas not found")?
-            .dyn_into::<HtmlCanvasElement>()?;
+            .ok_or_else(|| RenderError::CanvasNotFound(canvas_id.to_string()))?
+            .dyn_into::<HtmlCanvasElement>()
+            .map_err(|_| RenderError::NotACanvas(canvas_id.to_string()))?;
 
-        let gl = canvas
-            .get_context("webgl")?
-            .ok_or("No WebGL context")?
-            .dyn_into::<GL>()?;
+        fn unsupported<E>(_: E) -> RenderError {
+            RenderError::Unsupported("WebGL")
+        }
+        let context = match canvas.get_context("webgl2").map_err(unsupported)? {
+            Some(context) => Context::WebGl2(context.dyn_into().map_err(unsupported)?),
+            None => Context::WebGl1(
+                canvas
+                    .get_context("webgl")
+                    .map_err(unsupported)?
+                    .ok_or(RenderError::Unsupported("WebGL"))?
+                    .dyn_into()
+                    .map_err(unsupported)?,
+            ),
+        };
 
         // Compile shaders
-        let vertex_shader = compile_shader(&gl, GL::VERTEX_SHADER, VERTEX_SHADER)?;
-        let fragment_shader = compile_shader(&gl, GL::FRAGMENT_SHADER, FRAGMENT_SHADER)?;
+        let vertex_shader = compile_shader(&context, GL::VERTEX_SHADER, VERTEX_SHADER)?;
+        let fragment_shader = compile_shader(&context, GL::FRAGMENT_SHADER, FRAGMENT_SHADER)?;
 
         // Link program
-        let program = link_program(&gl, &vertex_shader, &fragment_shader)?;
-        gl.use_program(Some(&program));
+        let program = link_program(&context, &vertex_shader, &fragment_shader)?;
+        gl!(&context, use_program(Some(&program)));
+        gl!(
+            &context,
+            vertex_attrib4f(VERTEX_COLOR_LOCATION, 1.0, 1.0, 1.0, 1.0)
+        );
+
+        let color_location = gl!(&context, get_uniform_location(&program, "color"));
+        let scale_location = gl!(&context, get_uniform_location(&program, "scale"));
+        let offset_location = gl!(&context, get_uniform_location(&program, "offset"));
 
         // Create buffers
-        let position_buffer = gl.create_buffer().ok_or("Failed to create buffer")?;
-        let grid_buffer = gl.create_buffer().ok_or("Failed to create buffer")?;
-
-        // Set up grid
-        let mut grid_vertices = Vec::new();
-
-        // Vertical grid lines (10 divisions)
-        for i in 0..=10 {
-            let x = (i as f32 / 10.0) * 2.0 - 1.0;
-            grid_vertices.push(x);
-            grid_vertices.push(-1.0);
-            grid_vertices.push(x);
-            grid_vertices.push(1.0);
-        }
+        let trace_mesh = Mesh::new(&context, false)?;
+        let graded_mesh = Mesh::new(&context, true)?;
+        let marker_mesh = Mesh::new(&context, false)?;
+        let grid_mesh = Mesh::new(&context, false)?;
 
-        // Horizontal grid lines (8 divisions)
-        for i in 0..=8 {
-            let y = (i as f32 / 8.0) * 2.0 - 1.0;
-            grid_vertices.push(-1.0);
-            grid_vertices.push(y);
-            grid_vertices.push(1.0);
-            grid_vertices.push(y);
-        }
+        let mut renderer = Self {
+            context,
+            program,
+            color_location,
+            scale_location,
+            offset_location,
+            trace_mesh,
+            graded_mesh,
+            marker_mesh,
+            grid_mesh,
+            grid_major_vertices: 0,
+            grid_center_vertices: 0,
+            grid_tick_vertices: 0,
+            vertical_divisions: 0,
+            vertices: Vec::new(),
+        };
+        renderer.set_graticule(&Graticule::default());
 
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&grid_buffer));
-        unsafe {
-            let grid_array = js_sys::Float32Array::view(&grid_vertices);
-            gl.buffer_data_with_array_buffer_view(GL::ARRAY_BUFFER, &grid_array, GL::STATIC_DRAW);
+        Ok(renderer)
+    }
+
+    /// Whether a WebGL2 context was obtained
+    pub fn is_webgl2(&self) -> bool {
+        matches!(self.context, Context::WebGl2(_))
+    }
+
+    /// Drawing buffer width in pixels
+    pub fn width(&self) -> usize {
+        gl!(&self.context, drawing_buffer_width()).max(1) as usize
+    }
+
+    /// Regenerate the grid geometry from the shared graticule layout
+    pub fn set_graticule(&mut self, graticule: &Graticule) {
+        let lines = graticule.lines();
+        let mut vertices = Vec::new();
+        let mut counts = [0; 3];
+
+        for (slot, kind) in [
+            GridLineKind::Major,
+            GridLineKind::Center,
+            GridLineKind::Tick,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            for line in lines.iter().filter(|line| line.kind == kind) {
+                for (x, y) in [line.from, line.to] {
+                    vertices.push(x);
+                    vertices.push(y);
+                }
+                counts[slot] += 2;
+            }
         }
 
-        Ok(Self {
-            gl,
-            program,
-            position_buffer,
-            grid_buffer,
-        })
+        self.grid_mesh
+            .upload(&self.context, &vertices, GL::STATIC_DRAW);
+
+        [
+            self.grid_major_vertices,
+            self.grid_center_vertices,
+            self.grid_tick_vertices,
+        ] = counts;
+        self.vertical_divisions = graticule.vertical_divisions.max(1);
     }
 
-    pub fn render(&self, points: &[(f32, f32)]) {
-        let gl = &self.gl;
+    /// Draw the trace history over the grid, then overlay marker segments
+    /// (normalized screen space) in the accent color.
+    ///
+    /// `history` runs oldest first, each trace with the opacity it's drawn
+    /// at, fading as it ages. With an `intensity` map the
+    /// traces are graded instead: each segment is colored by how often its
+    /// path has been traversed, as on the desktop. Persistence longer than the
+    /// history holds comes as the `cells` of an intensity map, read for the
+    /// given mode and drawn as filled rectangles. `colormap` picks the trace
+    /// color or the heat map for all of these. Traces are shifted right by
+    /// `x_offset` screen widths.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render<'a>(
+        &mut self,
+        history: impl ExactSizeIterator<Item = (&'a [(f32, f32)], f32)>,
+        intensity: Option<&IntensityMap>,
+        cells: Option<(&IntensityMap, PersistenceMode)>,
+        x_offset: f32,
+        markers: &[Segment],
+        palette: &Palette,
+        colormap: PersistenceColormap,
+    ) {
+        let context = &self.context;
 
         // Clear
-        gl.clear_color(0.0, 0.0, 0.0, 1.0);
-        gl.clear(GL::COLOR_BUFFER_BIT);
+        let [r, g, b, a] = palette.background;
+        gl!(context, clear_color(r, g, b, a));
+        gl!(context, clear(GL::COLOR_BUFFER_BIT));
 
-        gl.use_program(Some(&self.program));
-
-        let position_location = gl.get_attrib_location(&self.program, "position") as u32;
-        let color_location = gl.get_uniform_location(&self.program, "color");
+        gl!(context, use_program(Some(&self.program)));
+        gl!(context, enable(GL::BLEND));
+        gl!(context, blend_func(GL::SRC_ALPHA, GL::ONE_MINUS_SRC_ALPHA));
 
         // Draw grid
-        gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.grid_buffer));
-        gl.vertex_attrib_pointer_with_i32(position_location, 2, GL::FLOAT, false, 0, 0);
-        gl.enable_vertex_attrib_array(position_location);
-        gl.uniform4f(color_location.as_ref(), 0.0, 1.0, 0.0, 0.15);
-        gl.line_width(1.0);
-        gl.draw_arrays(GL::LINES, 0, (10 + 1 + 8 + 1) * 2);
-
-        // Draw center lines brighter
-        gl.uniform4f(color_location.as_ref(), 0.0, 1.0, 0.0, 0.5);
-        gl.line_width(2.0);
-        // Vertical center
-        gl.draw_arrays(GL::LINES, 10 * 2, 2);
-        // Horizontal center
-        gl.draw_arrays(GL::LINES, (10 + 1) * 2 + 8, 2);
-
-        // Draw waveform
-        if !points.is_empty() {
-            let mut vertices = Vec::new();
-            for (x_norm, y_norm) in points {
-                let x = x_norm * 2.0 - 1.0;
-                let y = -y_norm / 4.0; // Scale for 8 divisions
-                vertices.push(x);
-                vertices.push(y);
+        self.grid_mesh.bind(context);
+        self.set_transform(SCREEN_TRANSFORM);
+        self.set_color(palette.grid);
+        gl!(context, line_width(1.0));
+        gl!(context, draw_arrays(GL::LINES, 0, self.grid_major_vertices));
+
+        // Draw center lines and ticks brighter
+        self.set_color(palette.grid_center);
+        gl!(context, line_width(2.0));
+        gl!(
+            context,
+            draw_arrays(
+                GL::LINES,
+                self.grid_major_vertices,
+                self.grid_center_vertices,
+            )
+        );
+        gl!(context, line_width(1.0));
+        gl!(
+            context,
+            draw_arrays(
+                GL::LINES,
+                self.grid_major_vertices + self.grid_center_vertices,
+                self.grid_tick_vertices,
+            )
+        );
+
+        // Points are x in 0..1 and y in divisions from the center line
+        let half_divisions = self.vertical_divisions as f32 / 2.0;
+        let trace_transform = Transform {
+            scale: [2.0, 1.0 / half_divisions],
+            offset: [x_offset * 2.0 - 1.0, 0.0],
+        };
+        let num_traces = history.len();
+        self.vertices.clear();
+
+        if let Some((map, mode)) = cells {
+            // Two triangles per lit cell, in a single draw
+            for (column, row, level) in map.cells(mode) {
+                let ((left, top), (right, bottom)) = map.cell_bounds(column, row);
+                let color = colormap.cell(palette, mode, level);
+                for corner in [
+                    [left, top],
+                    [right, top],
+                    [left, bottom],
+                    [left, bottom],
+                    [right, top],
+                    [right, bottom],
+                ] {
+                    self.vertices.extend(corner);
+                    self.vertices.extend(color);
+                }
             }
 
-            gl.bind_buffer(GL::ARRAY_BUFFER, Some(&self.position_buffer));
-            unsafe {
-                let vert_array = js_sys::Float32Array::view(&vertices);
-                gl.buffer_data_with_array_buffer_view(
-                    GL::ARRAY_BUFFER,
-                    &vert_array,
-                    GL::DYNAMIC_DRAW,
+            if !self.vertices.is_empty() {
+                self.graded_mesh
+                    .upload(context, &self.vertices, GL::DYNAMIC_DRAW);
+                self.graded_mesh.bind(context);
+                self.set_transform(trace_transform);
+                self.set_color([1.0; 4]);
+                gl!(
+                    context,
+                    draw_arrays(
+                        GL::TRIANGLES,
+                        0,
+                        self.vertices.len() as i32 / COLORED_VERTEX_FLOATS,
+                    )
                 );
+                self.vertices.clear();
             }
+        }
+
+        if let Some(intensity) = intensity {
+            // Graded: colors vary per segment, so every segment of every trace
+            // carries its own vertex colors and goes out in a single draw
+            for (points, age_alpha) in history {
+                for pair in points.windows(2) {
+                    let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+                    let graded = colormap.graded(
+                        palette,
+                        intensity.intensity_at((x1 + x2) / 2.0, (y1 + y2) / 2.0),
+                    );
+                    let color = Palette::faded(graded, age_alpha);
+                    self.vertices.extend([x1, y1]);
+                    self.vertices.extend(color);
+                    self.vertices.extend([x2, y2]);
+                    self.vertices.extend(color);
+                }
+            }
+
+            if !self.vertices.is_empty() {
+                self.graded_mesh
+                    .upload(context, &self.vertices, GL::DYNAMIC_DRAW);
+                self.graded_mesh.bind(context);
+                self.set_transform(trace_transform);
+                self.set_color([1.0; 4]);
+                gl!(context, line_width(2.0));
+                gl!(
+                    context,
+                    draw_arrays(
+                        GL::LINES,
+                        0,
+                        self.vertices.len() as i32 / COLORED_VERTEX_FLOATS,
+                    )
+                );
+            }
+        } else {
+            // Fade: every trace goes into one upload, then one alpha-blended
+            // pass per trace
+            let mut ranges = Vec::with_capacity(num_traces);
+            for (points, age_alpha) in history {
+                let first = (self.vertices.len() / 2) as i32;
+                self.vertices
+                    .extend(points.iter().flat_map(|&(x, y)| [x, y]));
+                ranges.push((first, points.len() as i32, age_alpha));
+            }
+
+            if !self.vertices.is_empty() {
+                self.trace_mesh
+                    .upload(context, &self.vertices, GL::DYNAMIC_DRAW);
+                self.trace_mesh.bind(context);
+                self.set_transform(trace_transform);
+                gl!(context, line_width(2.0));
+                for (first, count, age_alpha) in ranges {
+                    self.set_color(colormap.faded(palette, age_alpha));
+                    gl!(context, draw_arrays(GL::LINE_STRIP, first, count));
+                }
+            }
+        }
 
-            gl.vertex_attrib_pointer_with_i32(position_location, 2, GL::FLOAT, false, 0, 0);
-            gl.enable_vertex_attrib_array(position_location);
-            gl.uniform4f(color_location.as_ref(), 0.0, 1.0, 0.0, 1.0);
-            gl.line_width(2.0);
-            gl.draw_arrays(GL::LINE_STRIP, 0, (vertices.len() / 2) as i32);
+        // Draw markers
+        if !markers.is_empty() {
+            self.vertices.clear();
+            self.vertices.extend(
+                markers
+                    .iter()
+                    .flat_map(|&((x0, y0), (x1, y1))| [x0, y0, x1, y1]),
+            );
+
+            self.marker_mesh
+                .upload(context, &self.vertices, GL::DYNAMIC_DRAW);
+            self.marker_mesh.bind(context);
+            self.set_transform(SCREEN_TRANSFORM);
+            self.set_color(palette.accent);
+            gl!(context, line_width(1.0));
+            gl!(
+                context,
+                draw_arrays(GL::LINES, 0, (self.vertices.len() / 2) as i32)
+            );
         }
     }
+
+    fn set_color(&self, color: Rgba) {
+        gl!(
+            &self.context,
+            uniform4f(
+                self.color_location.as_ref(),
+                color[0],
+                color[1],
+                color[2],
+                color[3],
+            )
+        );
+    }
+
+    fn set_transform(&self, transform: Transform) {
+        let [scale_x, scale_y] = transform.scale;
+        let [offset_x, offset_y] = transform.offset;
+        gl!(
+            &self.context,
+            uniform2f(self.scale_location.as_ref(), scale_x, scale_y)
+        );
+        gl!(
+            &self.context,
+            uniform2f(self.offset_location.as_ref(), offset_x, offset_y)
+        );
+    }
 }
 
-fn compile_shader(gl: &GL, shader_type: u32, source: &str) -> Result<WebGlShader, String> {
-    let shader = gl
-        .create_shader(shader_type)
-        .ok_or("Unable to create shader")?;
-    gl.shader_source(&shader, source);
-    gl.compile_shader(&shader);
+fn compile_shader(
+    context: &Context,
+    shader_type: u32,
+    source: &str,
+) -> Result<WebGlShader, RenderError> {
+    let shader = gl!(context, create_shader(shader_type)).ok_or(RenderError::Resource("shader"))?;
+    gl!(context, shader_source(&shader, source));
+    gl!(context, compile_shader(&shader));
 
-    if gl
-        .get_shader_parameter(&shader, GL::COMPILE_STATUS)
+    if gl!(context, get_shader_parameter(&shader, GL::COMPILE_STATUS))
         .as_bool()
         .unwrap_or(false)
     {
         Ok(shader)
     } else {
-        Err(gl
-            .get_shader_info_log(&shader)
-            .unwrap_or_else(|| "Unknown error".into()))
+        let log = gl!(context, get_shader_info_log(&shader));
+        Err(RenderError::Shader(
+            log.unwrap_or_else(|| "Unknown error".into()),
+        ))
     }
 }
 
 fn link_program(
-    gl: &GL,
+    context: &Context,
     vertex_shader: &WebGlShader,
     fragment_shader: &WebGlShader,
-) -> Result<WebGlProgram, String> {
-    let program = gl.create_program().ok_or("Unable to create program")?;
-    gl.attach_shader(&program, vertex_shader);
-    gl.attach_shader(&program, fragment_shader);
-    gl.link_program(&program);
-
-    if gl
-        .get_program_parameter(&program, GL::LINK_STATUS)
+) -> Result<WebGlProgram, RenderError> {
+    let program = gl!(context, create_program()).ok_or(RenderError::Resource("program"))?;
+    gl!(context, attach_shader(&program, vertex_shader));
+    gl!(context, attach_shader(&program, fragment_shader));
+    gl!(
+        context,
+        bind_attrib_location(&program, POSITION_LOCATION, "position")
+    );
+    gl!(
+        context,
+        bind_attrib_location(&program, VERTEX_COLOR_LOCATION, "vertex_color")
+    );
+    gl!(context, link_program(&program));
+
+    if gl!(context, get_program_parameter(&program, GL::LINK_STATUS))
         .as_bool()
         .unwrap_or(false)
     {
         Ok(program)
     } else {
-        Err(gl
-            .get_program_info_log(&program)
-            .unwrap_or_else(|| "Unknown error".into()))
+        let log = gl!(context, get_program_info_log(&program));
+        Err(RenderError::Link(
+            log.unwrap_or_else(|| "Unknown error".into()),
+        ))
     }
 }
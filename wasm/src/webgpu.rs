@@ -0,0 +1,363 @@
+use crate::error::RenderError;
+use ozeecubed_core::display::{
+    Graticule, GridLine, GridLineKind, IntensityMap, Palette, PersistenceColormap, PersistenceMode,
+    Rgba, Segment,
+};
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use web_sys::HtmlCanvasElement;
+
+/// The desktop gui-v2 grid shader: colored vertices straight to clip space
+const SHADER: &str = include_str!("../../gui-v2/src/shaders/waveform.wgsl");
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    position: [f32; 2],
+    color: [f32; 4],
+}
+
+impl Vertex {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// WebGPU counterpart of `WebGLRenderer`, drawing the same grid, faded trace
+/// history and markers through wgpu in a single line-list draw
+pub struct WebGpuRenderer {
+    canvas: HtmlCanvasElement,
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    pipeline: wgpu::RenderPipeline,
+    /// Draws the intensity map's cells, which follow the grid in the vertex
+    /// buffer
+    cell_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    vertices: Vec<Vertex>,
+    grid_lines: Vec<GridLine>,
+    vertical_divisions: usize,
+}
+
+impl WebGpuRenderer {
+    /// Canvas width in pixels
+    pub fn width(&self) -> usize {
+        self.canvas.width().max(1) as usize
+    }
+
+    pub async fn new(canvas_id: &str) -> Result<Self, RenderError> {
+        let window = web_sys::window().ok_or(RenderError::NoDocument)?;
+        let has_webgpu =
+            js_sys::Reflect::has(&window.navigator(), &JsValue::from_str("gpu")).unwrap_or(false);
+        if !has_webgpu {
+            return Err(RenderError::Unsupported("WebGPU"));
+        }
+
+        let canvas = window
+            .document()
+            .ok_or(RenderError::NoDocument)?
+            .get_element_by_id(canvas_id)
+            .ok_or_else(|| RenderError::CanvasNotFound(canvas_id.to_string()))?
+            .dyn_into::<HtmlCanvasElement>()
+            .map_err(|_| RenderError::NotACanvas(canvas_id.to_string()))?;
+
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::BROWSER_WEBGPU,
+            ..Default::default()
+        });
+
+        // Settle on an adapter before claiming the canvas, so a failure here
+        // still leaves it free for a WebGL context
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await
+            .ok_or(RenderError::Unsupported("A WebGPU adapter"))?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    required_features: wgpu::Features::empty(),
+                    required_limits: wgpu::Limits::downlevel_webgl2_defaults()
+                        .using_resolution(adapter.limits()),
+                },
+                None,
+            )
+            .await
+            .map_err(|e| RenderError::Device(format!("Failed to get WebGPU device: {e}")))?;
+
+        let surface = instance
+            .create_surface(wgpu::SurfaceTarget::Canvas(canvas.clone()))
+            .map_err(|e| RenderError::Device(format!("Failed to create WebGPU surface: {e}")))?;
+
+        let surface_caps = surface.get_capabilities(&adapter);
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_caps.formats[0],
+            width: canvas.width().max(1),
+            height: canvas.height().max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        surface.configure(&device, &config);
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Scope Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Scope Shader"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        // The same colored vertices as lines, or as the intensity map's cells
+        let create_pipeline = |label, topology| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: config.format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let pipeline = create_pipeline("Scope Pipeline", wgpu::PrimitiveTopology::LineList);
+        let cell_pipeline =
+            create_pipeline("Scope Cell Pipeline", wgpu::PrimitiveTopology::TriangleList);
+
+        let vertex_buffer = create_vertex_buffer(&device, 1024);
+
+        let mut renderer = Self {
+            canvas,
+            surface,
+            device,
+            queue,
+            config,
+            pipeline,
+            cell_pipeline,
+            vertex_buffer,
+            vertices: Vec::new(),
+            grid_lines: Vec::new(),
+            vertical_divisions: 0,
+        };
+        renderer.set_graticule(&Graticule::default());
+
+        Ok(renderer)
+    }
+
+    /// Take the grid layout from the shared graticule; colors come per frame
+    pub fn set_graticule(&mut self, graticule: &Graticule) {
+        self.grid_lines = graticule.lines();
+        self.vertical_divisions = graticule.vertical_divisions.max(1);
+    }
+
+    /// Same contract as `WebGLRenderer::render`: `history` runs oldest first
+    /// with each trace's opacity, graded by `intensity` if given, `cells` of
+    /// an intensity map for persistence longer than the history, colored by
+    /// `colormap` and shifted right by `x_offset`
+    #[allow(clippy::too_many_arguments)]
+    pub fn render<'a>(
+        &mut self,
+        history: impl ExactSizeIterator<Item = (&'a [(f32, f32)], f32)>,
+        intensity: Option<&IntensityMap>,
+        cells: Option<(&IntensityMap, PersistenceMode)>,
+        x_offset: f32,
+        markers: &[Segment],
+        palette: &Palette,
+        colormap: PersistenceColormap,
+    ) {
+        self.fit_canvas();
+        self.vertices.clear();
+
+        // Points are x in 0..1 and y in divisions from the center line
+        let half_divisions = self.vertical_divisions as f32 / 2.0;
+        let trace = |(x, y): (f32, f32)| [(x + x_offset) * 2.0 - 1.0, y / half_divisions];
+
+        // Normalized screen space (y down) to clip space (y up)
+        let screen = |(x, y): (f32, f32)| [x * 2.0 - 1.0, 1.0 - y * 2.0];
+
+        for line in &self.grid_lines {
+            let color = match line.kind {
+                GridLineKind::Major => palette.grid,
+                GridLineKind::Center | GridLineKind::Tick => palette.grid_center,
+            };
+            push_line(
+                &mut self.vertices,
+                screen(line.from),
+                screen(line.to),
+                color,
+            );
+        }
+
+        // Cells are triangles between the grid's lines and the traces'
+        let grid_vertices = self.vertices.len() as u32;
+        if let Some((map, mode)) = cells {
+            for (column, row, level) in map.cells(mode) {
+                let ((left, top), (right, bottom)) = map.cell_bounds(column, row);
+                let color = colormap.cell(palette, mode, level);
+                for corner in [
+                    (left, top),
+                    (right, top),
+                    (left, bottom),
+                    (left, bottom),
+                    (right, top),
+                    (right, bottom),
+                ] {
+                    self.vertices.push(Vertex {
+                        position: trace(corner),
+                        color,
+                    });
+                }
+            }
+        }
+        let cell_vertices = self.vertices.len() as u32;
+
+        for (points, age_alpha) in history {
+            for pair in points.windows(2) {
+                let ((x1, y1), (x2, y2)) = (pair[0], pair[1]);
+                let color = match intensity {
+                    Some(intensity) => Palette::faded(
+                        colormap.graded(
+                            palette,
+                            intensity.intensity_at((x1 + x2) / 2.0, (y1 + y2) / 2.0),
+                        ),
+                        age_alpha,
+                    ),
+                    None => colormap.faded(palette, age_alpha),
+                };
+                push_line(&mut self.vertices, trace(pair[0]), trace(pair[1]), color);
+            }
+        }
+
+        for &(from, to) in markers {
+            push_line(&mut self.vertices, screen(from), screen(to), palette.accent);
+        }
+
+        let bytes: &[u8] = bytemuck::cast_slice(&self.vertices);
+        if bytes.len() as wgpu::BufferAddress > self.vertex_buffer.size() {
+            self.vertex_buffer = create_vertex_buffer(&self.device, bytes.len());
+        }
+        self.queue.write_buffer(&self.vertex_buffer, 0, bytes);
+
+        let output = match self.surface.get_current_texture() {
+            Ok(output) => output,
+            Err(e) => {
+                web_sys::console::warn_1(&format!("WebGPU frame skipped: {e:?}").into());
+                return;
+            }
+        };
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Scope Encoder"),
+            });
+        {
+            let [r, g, b, a] = palette.background;
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Scope Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: r as f64,
+                            g: g as f64,
+                            b: b as f64,
+                            a: a as f64,
+                        }),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.draw(0..grid_vertices, 0..1);
+            if cell_vertices > grid_vertices {
+                render_pass.set_pipeline(&self.cell_pipeline);
+                render_pass.draw(grid_vertices..cell_vertices, 0..1);
+                render_pass.set_pipeline(&self.pipeline);
+            }
+            render_pass.draw(cell_vertices..self.vertices.len() as u32, 0..1);
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+    }
+
+    /// Reconfigure the surface when the page resizes the canvas
+    fn fit_canvas(&mut self) {
+        let width = self.canvas.width().max(1);
+        let height = self.canvas.height().max(1);
+        if width != self.config.width || height != self.config.height {
+            self.config.width = width;
+            self.config.height = height;
+            self.surface.configure(&self.device, &self.config);
+        }
+    }
+}
+
+fn push_line(vertices: &mut Vec<Vertex>, from: [f32; 2], to: [f32; 2], color: Rgba) {
+    vertices.push(Vertex {
+        position: from,
+        color,
+    });
+    vertices.push(Vertex {
+        position: to,
+        color,
+    });
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, size: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Scope Vertex Buffer"),
+        size: size.next_power_of_two() as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
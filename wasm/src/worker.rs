@@ -0,0 +1,287 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use js_sys::{Float32Array, Object, Reflect, SharedArrayBuffer};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{MessageEvent, Worker, WorkerOptions, WorkerType};
+
+use ozeecubed_core::oscilloscope::{AutoTrigger, EnvelopeFollower, TriggerSettings, WaveformData};
+
+use crate::ring::SampleRing;
+use crate::url_state::ScopeConfig;
+use crate::{waiting_for_trigger, Frame, Measurements};
+
+/// The processing half of the worker pipeline, run inside `worker.js`.
+///
+/// Drains the shared ring filled by the capture worklet, triggers, and
+/// measures, producing frames of
+/// `{ points: Float32Array, triggerX, envelope: Float32Array, measurements }`
+/// for the page, where `points` and `envelope` hold interleaved x, y pairs.
+#[wasm_bindgen]
+pub struct ScopeWorker {
+    ring: SampleRing,
+    read_position: u32,
+    waveform: WaveformData,
+    trigger_settings: TriggerSettings,
+    envelope: EnvelopeFollower,
+    /// Width of the page's canvas, to decimate traces for
+    columns: usize,
+    /// The newest traces, rebuilt in place each time
+    display: Frame,
+    /// Times the wait for an edge
+    auto: AutoTrigger,
+}
+
+#[wasm_bindgen]
+impl ScopeWorker {
+    #[wasm_bindgen(constructor)]
+    pub fn new(indices: &SharedArrayBuffer, samples: &SharedArrayBuffer, sample_rate: u32) -> Self {
+        let ring = SampleRing::from_buffers(indices, samples);
+        // Start from whatever is live now rather than replaying the ring
+        let read_position = ring.written();
+        Self {
+            ring,
+            read_position,
+            waveform: WaveformData::new(sample_rate),
+            trigger_settings: TriggerSettings::default(),
+            envelope: EnvelopeFollower::default(),
+            columns: 1024,
+            display: Frame::default(),
+            auto: AutoTrigger::new(sample_rate),
+        }
+    }
+
+    /// Apply the page's settings, as encoded by `OzScopeWasm::export_state`,
+    /// and the width its canvas draws at
+    pub fn configure(&mut self, state: &str, columns: usize) -> Result<(), JsValue> {
+        self.columns = columns.max(1);
+        let config = ScopeConfig::from_fragment(state).map_err(|e| JsValue::from_str(&e))?;
+        if let Some(value) = config.time_per_division {
+            self.waveform.time_per_division = value;
+        }
+        if let Some(value) = config.volts_per_division {
+            self.waveform.volts_per_division = value;
+        }
+        if let Some(mode) = config.decimation {
+            self.waveform.decimation = mode;
+        }
+        if let Some((horizontal, _)) = config.divisions {
+            self.waveform.horizontal_divisions = horizontal.max(1);
+        }
+        if let Some(value) = config.trigger_enabled {
+            self.trigger_settings.enabled = value;
+        }
+        if let Some(edge) = config.trigger_edge {
+            self.trigger_settings.edge = edge;
+        }
+        if let Some(value) = config.trigger_level {
+            self.trigger_settings.set_level(value);
+        }
+        if let Some(mode) = config.trigger_mode {
+            self.trigger_settings.mode = mode;
+        }
+        if let Some(mode) = config.envelope_mode {
+            self.envelope.mode = mode;
+        }
+        if let Some(seconds) = config.envelope_attack {
+            self.envelope.set_attack(seconds);
+        }
+        if let Some(seconds) = config.envelope_release {
+            self.envelope.set_release(seconds);
+        }
+        Ok(())
+    }
+
+    /// Process whatever arrived since the last call; `null` if nothing did,
+    /// or the trigger is still waiting for an edge and the page should keep
+    /// the last frame
+    pub fn process(&mut self) -> Result<JsValue, JsValue> {
+        let samples = self.ring.read(&mut self.read_position);
+        if samples.is_empty() {
+            return Ok(JsValue::NULL);
+        }
+        self.waveform.append_samples(&samples);
+        if waiting_for_trigger(
+            &mut self.auto,
+            &self.waveform,
+            &self.trigger_settings,
+            samples.len(),
+        ) {
+            return Ok(JsValue::NULL);
+        }
+
+        self.display.update(
+            &self.waveform,
+            &self.trigger_settings,
+            &self.envelope,
+            self.columns,
+        );
+        let display = &self.display;
+        let interleave = |points: &[(f32, f32)]| -> Vec<f32> {
+            points.iter().flat_map(|&(x, y)| [x, y]).collect()
+        };
+
+        let frame = Object::new();
+        Reflect::set(
+            &frame,
+            &"points".into(),
+            &Float32Array::from(interleave(&display.points).as_slice()),
+        )?;
+        Reflect::set(
+            &frame,
+            &"triggerX".into(),
+            &display.trigger_x.map_or(JsValue::NULL, JsValue::from),
+        )?;
+        Reflect::set(
+            &frame,
+            &"envelope".into(),
+            &Float32Array::from(interleave(&display.envelope).as_slice()),
+        )?;
+        Reflect::set(
+            &frame,
+            &"measurements".into(),
+            &Measurements::of(&self.waveform).to_js()?,
+        )?;
+        Ok(frame.into())
+    }
+}
+
+/// A frame received from the worker
+pub struct WorkerFrame {
+    pub frame: Frame,
+    pub measurements: JsValue,
+}
+
+/// The page's half of the worker pipeline: owns the ring and the worker,
+/// forwards settings, and keeps the newest frame the worker sent
+pub struct WorkerPipeline {
+    worker: Worker,
+    ring: SampleRing,
+    /// How far this thread has read the ring, for recording
+    read_position: u32,
+    latest: Rc<RefCell<Option<JsValue>>>,
+    sent_state: String,
+    sent_columns: usize,
+    _onmessage: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl WorkerPipeline {
+    /// Load `worker_url` (a module worker such as `worker.js`), which waits
+    /// for `start`. Shared memory requires the page to be cross-origin
+    /// isolated.
+    pub fn new(worker_url: &str) -> Result<Self, String> {
+        let window = web_sys::window().ok_or("No window found")?;
+        let isolated = Reflect::get(&window, &"crossOriginIsolated".into())
+            .ok()
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        if !isolated {
+            return Err("SharedArrayBuffer needs a cross-origin isolated page \
+                        (Cross-Origin-Opener-Policy and Cross-Origin-Embedder-Policy headers)"
+                .into());
+        }
+
+        let ring = SampleRing::new();
+        let options = WorkerOptions::new();
+        options.set_type(WorkerType::Module);
+        let worker = Worker::new_with_options(worker_url, &options)
+            .map_err(|_| format!("Failed to start worker {worker_url}"))?;
+
+        // Frames that arrive between two renders replace each other; only
+        // the newest is worth drawing
+        let latest = Rc::new(RefCell::new(None));
+        let inbox = latest.clone();
+        let onmessage = Closure::new(move |event: MessageEvent| {
+            *inbox.borrow_mut() = Some(event.data());
+        });
+        worker.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            worker,
+            ring,
+            read_position: 0,
+            latest,
+            sent_state: String::new(),
+            sent_columns: 0,
+            _onmessage: onmessage,
+        })
+    }
+
+    pub fn ring(&self) -> &SampleRing {
+        &self.ring
+    }
+
+    /// Hand the ring to the worker once the capture has opened, with the
+    /// rate its samples run at
+    pub fn start(&self, sample_rate: u32) -> Result<(), String> {
+        let init = Object::new();
+        let _ = Reflect::set(&init, &"type".into(), &"init".into());
+        let _ = Reflect::set(&init, &"indices".into(), &self.ring.indices_buffer());
+        let _ = Reflect::set(&init, &"samples".into(), &self.ring.samples_buffer());
+        let _ = Reflect::set(&init, &"sampleRate".into(), &sample_rate.into());
+        self.worker
+            .post_message(&init)
+            .map_err(|_| "Failed to send the ring to the worker".to_string())
+    }
+
+    /// Samples captured since the last call, read alongside the worker
+    pub fn drain(&mut self) -> Vec<f32> {
+        self.ring.read(&mut self.read_position)
+    }
+
+    /// Send the settings and canvas width to the worker if they changed
+    /// since last time
+    pub fn configure(&mut self, state: String, columns: usize) {
+        if state == self.sent_state && columns == self.sent_columns {
+            return;
+        }
+        let message = Object::new();
+        let _ = Reflect::set(&message, &"type".into(), &"config".into());
+        let _ = Reflect::set(&message, &"state".into(), &state.as_str().into());
+        let _ = Reflect::set(&message, &"columns".into(), &(columns as u32).into());
+        if self.worker.post_message(&message).is_ok() {
+            self.sent_state = state;
+            self.sent_columns = columns;
+        }
+    }
+
+    /// The newest frame from the worker, if one arrived since the last call
+    pub fn take_frame(&self) -> Option<WorkerFrame> {
+        let data = self.latest.borrow_mut().take()?;
+        let points: Float32Array = Reflect::get(&data, &"points".into())
+            .ok()?
+            .dyn_into()
+            .ok()?;
+        let trigger_x = Reflect::get(&data, &"triggerX".into())
+            .ok()?
+            .as_f64()
+            .map(|x| x as f32);
+        let envelope: Option<Float32Array> = Reflect::get(&data, &"envelope".into())
+            .ok()
+            .and_then(|value| value.dyn_into().ok());
+        let measurements = Reflect::get(&data, &"measurements".into()).ok()?;
+        let pairs = |values: &Float32Array| -> Vec<(f32, f32)> {
+            values
+                .to_vec()
+                .chunks_exact(2)
+                .map(|pair| (pair[0], pair[1]))
+                .collect()
+        };
+
+        Some(WorkerFrame {
+            frame: Frame {
+                points: pairs(&points),
+                trigger_x,
+                envelope: envelope.as_ref().map(pairs).unwrap_or_default(),
+            },
+            measurements,
+        })
+    }
+}
+
+impl Drop for WorkerPipeline {
+    fn drop(&mut self) {
+        self.worker.terminate();
+    }
+}
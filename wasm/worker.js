@@ -0,0 +1,37 @@
+// Audio processing worker for OzeeCubed Web.
+//
+// The capture worklet writes samples into a SharedArrayBuffer ring; this
+// worker drains it, triggers and measures, and posts ready frames back so
+// the page only has to draw. Started by OzScopeWasm.init_worker_pipeline.
+import init, { ScopeWorker } from './pkg/ozeecubed_wasm.js';
+
+// How often to look for new samples, a little faster than the display
+const PROCESS_INTERVAL_MS = 8;
+
+const ready = init();
+let scope = null;
+
+function processLoop() {
+    try {
+        const frame = scope.process();
+        if (frame) {
+            // Hand the points over rather than copying them
+            postMessage(frame, [frame.points.buffer]);
+        }
+    } catch (e) {
+        console.error('Worker frame failed:', e);
+    }
+    setTimeout(processLoop, PROCESS_INTERVAL_MS);
+}
+
+// Installed straight away so messages sent while loading aren't lost
+onmessage = async (event) => {
+    await ready;
+    const message = event.data;
+    if (message.type === 'init') {
+        scope = new ScopeWorker(message.indices, message.samples, message.sampleRate);
+        processLoop();
+    } else if (message.type === 'config' && scope) {
+        scope.configure(message.state, message.columns);
+    }
+};